@@ -0,0 +1,103 @@
+//! Benchmarks of the maze core, run with `cargo bench -p cmaze`.
+//!
+//! To compare two versions, save a baseline for each of them with
+//! `cargo bench -p cmaze -- --save-baseline <name>` and diff them with
+//! `scripts/bench_compare.nu`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use cmaze::{
+    dims::Dims3D,
+    gameboard::{
+        algorithms::{DepthFirstSearch, MazeAlgorithm, RndKruskals},
+        Maze,
+    },
+};
+
+/// Square floors, from the smallest preset up to what's still playable
+const SIZES: [i32; 3] = [10, 50, 150];
+
+fn generate<A: MazeAlgorithm>(size: Dims3D, tower: bool) -> Maze {
+    A::generate(size, tower)
+        .expect("valid size")
+        .wait()
+        .expect("generation failed")
+}
+
+fn corners(maze: &Maze) -> (Dims3D, Dims3D) {
+    (Dims3D(0, 0, 0), maze.size() - Dims3D(1, 1, 1))
+}
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(20);
+
+    for size in SIZES {
+        let dims = Dims3D(size, size, 1);
+        group.bench_with_input(BenchmarkId::new("kruskal", size), &dims, |b, &dims| {
+            b.iter(|| generate::<RndKruskals>(dims, false))
+        });
+        group.bench_with_input(BenchmarkId::new("dfs", size), &dims, |b, &dims| {
+            b.iter(|| generate::<DepthFirstSearch>(dims, false))
+        });
+    }
+
+    group.finish();
+}
+
+/// Towers are split into floors generated in parallel and then connected by stairs
+fn floors(c: &mut Criterion) {
+    let mut group = c.benchmark_group("floors");
+    group.sample_size(20);
+
+    for depth in [2, 8] {
+        let dims = Dims3D(50, 50, depth);
+        group.bench_with_input(BenchmarkId::new("tower", depth), &dims, |b, &dims| {
+            b.iter(|| generate::<RndKruskals>(dims, true))
+        });
+        group.bench_with_input(BenchmarkId::new("connected", depth), &dims, |b, &dims| {
+            b.iter(|| generate::<RndKruskals>(dims, false))
+        });
+    }
+
+    group.finish();
+}
+
+fn solving(c: &mut Criterion) {
+    let mut group = c.benchmark_group("solve");
+
+    for size in SIZES {
+        let maze = generate::<DepthFirstSearch>(Dims3D(size, size, 1), false);
+        let (from, to) = corners(&maze);
+
+        group.bench_with_input(BenchmarkId::new("corners", size), &maze, |b, maze| {
+            b.iter(|| maze.solve(from, to))
+        });
+        group.bench_with_input(BenchmarkId::new("analyze", size), &maze, |b, maze| {
+            b.iter(|| maze.analyze(from, to))
+        });
+    }
+
+    group.finish();
+}
+
+/// Whole-maze transformations, used for hashing and for the minimap
+fn transforms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transform");
+
+    for size in SIZES {
+        let maze = generate::<RndKruskals>(Dims3D(size, size, 1), false);
+
+        group.bench_with_input(BenchmarkId::new("hash", size), &maze, |b, maze| {
+            b.iter(|| maze.canonical_hash())
+        });
+        group.bench_with_input(BenchmarkId::new("downsample", size), &maze, |b, maze| {
+            b.iter(|| maze.downsample(2))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, generation, floors, solving, transforms);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! Minimal frontend using only `cmaze`, no terminal UI involved.
+//!
+//! Generates a maze, solves it, serializes it and prints it as plain text.
+
+use cmaze::{
+    dims::Dims3D,
+    gameboard::{
+        algorithms::{DepthFirstSearch, MazeAlgorithm},
+        Maze,
+    },
+};
+
+fn main() {
+    let size = Dims3D(12, 6, 2);
+
+    let maze = DepthFirstSearch::generate(size, true)
+        .expect("invalid maze size")
+        .wait()
+        .expect("generation failed");
+
+    let (start, goal) = (Dims3D(0, 0, 0), size - Dims3D(1, 1, 1));
+    let path = maze
+        .solve(start, goal)
+        .expect("generated mazes are always solvable");
+
+    println!(
+        "Path from {:?} to {:?} has {} cells\n",
+        start,
+        goal,
+        path.len()
+    );
+    println!("{}", maze.to_text(&path));
+    println!("{}\n", maze.analyze(start, goal));
+
+    let serialized = ron::to_string(&maze).expect("maze can be serialized");
+    let loaded: Maze = ron::from_str(&serialized).expect("maze can be deserialized");
+    assert_eq!(loaded.to_text(&path), maze.to_text(&path));
+
+    println!("Serialized into {} bytes of RON", serialized.len());
+}
@@ -2,6 +2,8 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
 use serde::{Deserialize, Serialize};
 
+use crate::game::{GoalObjective, GoalPlacement, ItemPlacement};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Dims(pub i32, pub i32);
 
@@ -15,6 +17,19 @@ pub struct DimsU(pub usize, pub usize);
 pub struct GameMode {
     pub size: Dims3D,
     pub is_tower: bool,
+    #[serde(default)]
+    pub goals: GoalPlacement,
+    #[serde(default)]
+    pub objective: GoalObjective,
+    /// Percentage of dead ends removed after generation, see [`crate::gameboard::Maze::braid`]
+    #[serde(default)]
+    pub braid: u8,
+    /// Floors which are braided as a bit mask, all floors if `0`
+    #[serde(default)]
+    pub braid_floors: u64,
+    /// Coins, keys and doors placed in the maze
+    #[serde(default)]
+    pub items: ItemPlacement,
 }
 
 impl Add for Dims {
@@ -0,0 +1,562 @@
+//! Export of mazes to files, to share them outside of the terminal
+//!
+//! All floors are exported, with the path of the player and the solution. Text uses the
+//! same double line walls as the game, JSON lists open sides of every cell and PNG is a
+//! simple picture with a block of pixels for every cell and wall.
+
+use std::{path::Path, str::FromStr};
+
+use serde::Serialize;
+
+use crate::{
+    dims::*,
+    gameboard::{CellWall, Maze},
+};
+
+/// Pixels per cell or wall of the PNG picture
+const PNG_SCALE: usize = 4;
+/// Pixels between floors of the PNG picture
+const PNG_FLOOR_GAP: usize = 8;
+
+const COLOR_BACKGROUND: [u8; 3] = [255, 255, 255];
+const COLOR_WALL: [u8; 3] = [24, 24, 24];
+const COLOR_STAIRS: [u8; 3] = [150, 150, 150];
+const COLOR_PATH: [u8; 3] = [120, 170, 255];
+const COLOR_SOLUTION: [u8; 3] = [255, 150, 40];
+const COLOR_START: [u8; 3] = [40, 180, 60];
+const COLOR_GOAL: [u8; 3] = [220, 40, 40];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Text,
+    Json,
+    Png,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Text, ExportFormat::Json, ExportFormat::Png];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Png => "png",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Text",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Png => "PNG",
+        }
+    }
+
+    /// Format given by the extension of the path, `None` for unknown extensions
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "txt" | "text" => Ok(ExportFormat::Text),
+            "json" => Ok(ExportFormat::Json),
+            "png" => Ok(ExportFormat::Png),
+            _ => Err(format!(
+                "unknown export format `{}`, use txt, json or png",
+                s
+            )),
+        }
+    }
+}
+
+/// What's on a position of the drawing, walls between the cells included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tile {
+    Empty,
+    Wall,
+    Stairs(char),
+    Path,
+    Solution,
+    Start,
+    Goal,
+}
+
+/// Maze with the marks which are exported together with it
+#[derive(Clone, Copy)]
+pub struct MazeExport<'a> {
+    pub maze: &'a Maze,
+    pub start: Dims3D,
+    pub goals: &'a [Dims3D],
+    /// Cells visited by the player, in order
+    pub path: &'a [Dims3D],
+    /// Shortest path from the start to a goal
+    pub solution: &'a [Dims3D],
+}
+
+impl MazeExport<'_> {
+    pub fn to_bytes(&self, format: ExportFormat) -> Vec<u8> {
+        match format {
+            ExportFormat::Text => self.to_text().into_bytes(),
+            ExportFormat::Json => self.to_json().into_bytes(),
+            ExportFormat::Png => self.to_png(),
+        }
+    }
+
+    /// Unicode drawing of all floors, each has a header if there is more than one
+    ///
+    /// Path of the player is marked by `·`, the solution by `•`, start by `S` and goals by `G`.
+    pub fn to_text(&self) -> String {
+        let floors = self.tiles();
+        let depth = floors.len();
+
+        floors
+            .iter()
+            .enumerate()
+            .map(|(z, floor)| {
+                let rows: Vec<String> = (0..floor.len())
+                    .map(|y| {
+                        (0..floor[y].len())
+                            .map(|x| Self::tile_char(floor, x, y))
+                            .collect()
+                    })
+                    .collect();
+                match depth > 1 {
+                    true => format!("Floor {}\n{}\n", z + 1, rows.join("\n")),
+                    false => format!("{}\n", rows.join("\n")),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Size, open sides of every cell by floor and row, and the marked paths
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Json<'a> {
+            size: Dims3D,
+            start: Dims3D,
+            goals: &'a [Dims3D],
+            /// Open sides of the cells, indexed by floor, row and column
+            cells: Vec<Vec<Vec<Vec<CellWall>>>>,
+            path: &'a [Dims3D],
+            solution: &'a [Dims3D],
+        }
+
+        let cells = self
+            .maze
+            .get_cells()
+            .iter()
+            .map(|floor| {
+                floor
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| {
+                                CellWall::get_in_order()
+                                    .into_iter()
+                                    .filter(|wall| !cell.get_wall(*wall))
+                                    .collect()
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let json = Json {
+            size: self.maze.size(),
+            start: self.start,
+            goals: self.goals,
+            cells,
+            path: self.path,
+            solution: self.solution,
+        };
+        serde_json::to_string(&json).expect("maze should be serializable")
+    }
+
+    /// Picture of all floors under each other, as an uncompressed PNG image
+    pub fn to_png(&self) -> Vec<u8> {
+        let floors = self.tiles();
+        let (grid_w, grid_h) = (floors[0][0].len(), floors[0].len());
+
+        let width = grid_w * PNG_SCALE;
+        let height = (grid_h * PNG_SCALE + PNG_FLOOR_GAP) * floors.len() - PNG_FLOOR_GAP;
+
+        let mut pixels = vec![COLOR_BACKGROUND; width * height];
+        for (z, floor) in floors.iter().enumerate() {
+            let top = z * (grid_h * PNG_SCALE + PNG_FLOOR_GAP);
+            for (y, row) in floor.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    let color = match tile {
+                        Tile::Empty => continue,
+                        Tile::Wall => COLOR_WALL,
+                        Tile::Stairs(_) => COLOR_STAIRS,
+                        Tile::Path => COLOR_PATH,
+                        Tile::Solution => COLOR_SOLUTION,
+                        Tile::Start => COLOR_START,
+                        Tile::Goal => COLOR_GOAL,
+                    };
+
+                    for py in 0..PNG_SCALE {
+                        let row_start = (top + y * PNG_SCALE + py) * width + x * PNG_SCALE;
+                        pixels[row_start..row_start + PNG_SCALE].fill(color);
+                    }
+                }
+            }
+        }
+
+        png::encode_rgb(width, height, &pixels)
+    }
+
+    /// Tiles of all floors, indexed by floor, row and column
+    fn tiles(&self) -> Vec<Vec<Vec<Tile>>> {
+        let mut floors: Vec<Vec<Vec<Tile>>> = self
+            .maze
+            .char_grid()
+            .into_iter()
+            .map(|floor| {
+                floor
+                    .into_iter()
+                    .map(|row| {
+                        row.into_iter()
+                            .map(|ch| match ch {
+                                '#' => Tile::Wall,
+                                ' ' => Tile::Empty,
+                                '^' => Tile::Stairs('↑'),
+                                'v' => Tile::Stairs('↓'),
+                                _ => Tile::Stairs('↕'),
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // stairs stay visible, walls are never marked and the solution covers the path
+        for (path, tile) in [(self.path, Tile::Path), (self.solution, Tile::Solution)] {
+            for (z, x, y) in self.maze.grid_path(path) {
+                let current = &mut floors[z][y][x];
+                if matches!(current, Tile::Empty | Tile::Path) {
+                    *current = tile;
+                }
+            }
+        }
+
+        let ends = [(self.start, Tile::Start)]
+            .into_iter()
+            .chain(self.goals.iter().map(|goal| (*goal, Tile::Goal)));
+        for (pos, tile) in ends {
+            if self.maze.is_in_bounds(pos) {
+                floors[pos.2 as usize][pos.1 as usize * 2 + 1][pos.0 as usize * 2 + 1] = tile;
+            }
+        }
+
+        floors
+    }
+
+    fn tile_char(floor: &[Vec<Tile>], x: usize, y: usize) -> char {
+        match floor[y][x] {
+            Tile::Empty => ' ',
+            Tile::Stairs(ch) => ch,
+            Tile::Path => '·',
+            Tile::Solution => '•',
+            Tile::Start => 'S',
+            Tile::Goal => 'G',
+            Tile::Wall => {
+                let wall = |x: Option<usize>, y: Option<usize>| {
+                    let tile = floor.get(y?)?.get(x?)?;
+                    Some(*tile == Tile::Wall)
+                };
+                let left = wall(x.checked_sub(1), Some(y)).unwrap_or(false);
+                let top = wall(Some(x), y.checked_sub(1)).unwrap_or(false);
+                let right = wall(Some(x + 1), Some(y)).unwrap_or(false);
+                let bottom = wall(Some(x), Some(y + 1)).unwrap_or(false);
+
+                match (left, top, right, bottom) {
+                    (true, false, true, false) => '═',
+                    (false, true, false, true) => '║',
+                    (false, false, true, true) => '╔',
+                    (true, false, false, true) => '╗',
+                    (false, true, true, false) => '╚',
+                    (true, true, false, false) => '╝',
+                    (false, true, true, true) => '╠',
+                    (true, true, false, true) => '╣',
+                    (true, false, true, true) => '╦',
+                    (true, true, true, false) => '╩',
+                    (true, true, true, true) => '╬',
+                    // ends of walls and lone pillars
+                    _ => '▪',
+                }
+            }
+        }
+    }
+}
+
+/// Minimal PNG encoder, pixels are stored without compression
+mod png {
+    const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    /// Largest block of uncompressed deflate data
+    const MAX_BLOCK: usize = 0xffff;
+
+    /// 8-bit RGB image, `pixels` are in rows from the top left corner
+    pub fn encode_rgb(width: usize, height: usize, pixels: &[[u8; 3]]) -> Vec<u8> {
+        // every row starts with the filter type, 0 is no filter
+        let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+        for row in pixels.chunks(width) {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+
+        let mut header = vec![];
+        header.extend((width as u32).to_be_bytes());
+        header.extend((height as u32).to_be_bytes());
+        // bit depth, RGB color type, compression, filter and interlace methods
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+        png.extend((data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend(kind);
+        png.extend(data);
+        let crc = crc32(&png[start..]);
+        png.extend(crc.to_be_bytes());
+    }
+
+    /// Zlib stream of uncompressed deflate blocks
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x78, 0x01];
+
+        let blocks: Vec<_> = data.chunks(MAX_BLOCK).collect();
+        if blocks.is_empty() {
+            out.extend([1, 0, 0, 0xff, 0xff]);
+        }
+        for (i, block) in blocks.iter().enumerate() {
+            let last = i + 1 == blocks.len();
+            let len = block.len() as u16;
+            out.push(last as u8);
+            out.extend(len.to_le_bytes());
+            out.extend((!len).to_le_bytes());
+            out.extend(*block);
+        }
+
+        out.extend(adler32(data).to_be_bytes());
+        out
+    }
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+            }
+        }
+        !crc
+    }
+
+    fn adler32(data: &[u8]) -> u32 {
+        const MOD: u32 = 65521;
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + byte as u32) % MOD;
+            b = (b + a) % MOD;
+        }
+        (b << 16) | a
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn checksums() {
+            assert_eq!(crc32(b"IEND"), 0xae42_6082);
+            assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: Dims3D = Dims3D(0, 0, 0);
+    const GOALS: [Dims3D; 1] = [Dims3D(2, 1, 0)];
+    const PATH: [Dims3D; 2] = [Dims3D(0, 0, 0), Dims3D(1, 0, 0)];
+    const SOLUTION: [Dims3D; 4] = [
+        Dims3D(0, 0, 0),
+        Dims3D(1, 0, 0),
+        Dims3D(2, 0, 0),
+        Dims3D(2, 1, 0),
+    ];
+
+    /// Corridor along the top row, turning down at its end
+    fn maze() -> Maze {
+        let mut maze = Maze::new_walled(Dims3D(3, 2, 1), false);
+        maze.remove_wall(Dims3D(0, 0, 0), CellWall::Right);
+        maze.remove_wall(Dims3D(1, 0, 0), CellWall::Right);
+        maze.remove_wall(Dims3D(2, 0, 0), CellWall::Bottom);
+        maze
+    }
+
+    fn export(maze: &Maze) -> MazeExport<'_> {
+        MazeExport {
+            maze,
+            start: START,
+            goals: &GOALS,
+            path: &PATH,
+            solution: &SOLUTION,
+        }
+    }
+
+    #[test]
+    fn text() {
+        let maze = maze();
+        let expected = "\
+╔═════╗
+║S••••║
+╠═╦═╗•║
+║ ║ ║G║
+╚═╩═╩═╝
+";
+        assert_eq!(export(&maze).to_text(), expected);
+
+        // same grid as the plain drawing of the maze
+        let plain = "\
+#######
+#...  #
+##### #
+# # # #
+#######
+";
+        assert_eq!(maze.to_text(&PATH), plain);
+    }
+
+    #[test]
+    fn json() {
+        let maze = maze();
+        let json: serde_json::Value = serde_json::from_str(&export(&maze).to_json()).unwrap();
+
+        fn value(value: impl Serialize) -> serde_json::Value {
+            serde_json::to_value(value).unwrap()
+        }
+        assert_eq!(json["size"], value(Dims3D(3, 2, 1)));
+        assert_eq!(json["start"], value(START));
+        assert_eq!(json["goals"], value(GOALS));
+        assert_eq!(json["path"], value(PATH));
+        assert_eq!(json["solution"], value(SOLUTION));
+
+        for pos in SOLUTION
+            .into_iter()
+            .chain([Dims3D(0, 1, 0), Dims3D(1, 1, 0)])
+        {
+            let open: Vec<CellWall> =
+                serde_json::from_value(json["cells"][0][pos.1 as usize][pos.0 as usize].clone())
+                    .unwrap();
+            let expected: Vec<_> = CellWall::get_in_order()
+                .into_iter()
+                .filter(|wall| maze.get_wall(pos, *wall) == Some(false))
+                .collect();
+            assert_eq!(open, expected, "{:?}", pos);
+        }
+    }
+
+    /// Width, height and pixel rows of a PNG written by [`png::encode_rgb`]
+    fn decode_png(png: &[u8]) -> (usize, usize, Vec<Vec<[u8; 3]>>) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+        let mut chunks = vec![];
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            chunks.push((&rest[4..8], &rest[8..8 + len]));
+            rest = &rest[12 + len..];
+        }
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+
+        let header = chunks[0].1;
+        let width = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let height = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+        assert_eq!(&header[8..], [8, 2, 0, 0, 0]);
+
+        // stored deflate blocks after the zlib header
+        let (mut data, mut raw) = (&chunks[1].1[2..], vec![]);
+        loop {
+            let last = data[0] == 1;
+            let len = u16::from_le_bytes([data[1], data[2]]) as usize;
+            assert_eq!(u16::from_le_bytes([data[3], data[4]]), !(len as u16));
+            raw.extend(&data[5..5 + len]);
+            data = &data[5 + len..];
+            if last {
+                break;
+            }
+        }
+
+        let rows = raw
+            .chunks(width * 3 + 1)
+            .map(|row| {
+                assert_eq!(row[0], 0, "rows are not filtered");
+                row[1..].chunks(3).map(|px| [px[0], px[1], px[2]]).collect()
+            })
+            .collect();
+        (width, height, rows)
+    }
+
+    #[test]
+    fn png() {
+        let maze = maze();
+        let (width, height, rows) = decode_png(&export(&maze).to_png());
+
+        assert_eq!((width, height), (7 * PNG_SCALE, 5 * PNG_SCALE));
+        assert_eq!(rows.len(), height);
+
+        // every tile is a block of pixels in its color
+        let text = export(&maze).to_text();
+        for (y, line) in text.lines().enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                let color = match ch {
+                    ' ' => COLOR_BACKGROUND,
+                    '•' => COLOR_SOLUTION,
+                    'S' => COLOR_START,
+                    'G' => COLOR_GOAL,
+                    _ => COLOR_WALL,
+                };
+                for (px, py) in [(0, 0), (PNG_SCALE - 1, PNG_SCALE - 1)] {
+                    let pixel = rows[y * PNG_SCALE + py][x * PNG_SCALE + px];
+                    assert_eq!(pixel, color, "{:?} at {}, {}", ch, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn png_floors_are_stacked() {
+        let maze = Maze::new_walled(Dims3D(2, 2, 3), false);
+        let export = MazeExport {
+            maze: &maze,
+            start: START,
+            goals: &[],
+            path: &[],
+            solution: &[],
+        };
+        let (width, height, _) = decode_png(&export.to_png());
+
+        assert_eq!(width, 5 * PNG_SCALE);
+        assert_eq!(height, 3 * 5 * PNG_SCALE + 2 * PNG_FLOOR_GAP);
+    }
+}
@@ -1,19 +1,29 @@
 use crate::{
     dims::*,
     gameboard::{
-        algorithms::{GenErrorInstant, GenErrorThreaded, Progress, StopGenerationFlag},
-        CellWall, Maze,
+        algorithms::{
+            random_seed, GenErrorInstant, GenErrorThreaded, MazeRng, Progress, StopGenerationFlag,
+        },
+        encoding::{decode_moves, encode_moves},
+        CellContent, CellWall, Compression, DecodeError, Maze, WallShift,
     },
 };
 
 use pausable_clock::{PausableClock, PausableInstant};
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
     sync::{Arc, Mutex},
     thread::{self, JoinHandle},
 };
 
+/// Mixed into the seed of braiding, so it doesn't repeat the choices of the generator
+const BRAID_SEED_SALT: u64 = 0xB4A1_D5EE_D000_0001;
+/// Mixed into the seed of item placement, like [`BRAID_SEED_SALT`]
+const ITEMS_SEED_SALT: u64 = 0x17E3_5EED_0000_0002;
+
 #[derive(Debug)]
 pub struct GameAlreadyRunningError {}
 #[derive(Debug)]
@@ -30,13 +40,99 @@ pub enum RunningGameState {
     Quitted,
 }
 
+/// Generates maze from the seed, see [`crate::gameboard::algorithms::MazeAlgorithm::generate_seeded`]
 pub type GeneratorFn =
-    fn(Dims3D, bool) -> Result<ProgressComm<Result<Maze, GenErrorThreaded>>, GenErrorInstant>;
+    fn(Dims3D, bool, u64) -> Result<ProgressComm<Result<Maze, GenErrorThreaded>>, GenErrorInstant>;
 
 #[derive(Clone, Debug)]
 pub struct GameProperities {
     pub game_mode: GameMode,
     pub generator: GeneratorFn,
+    /// Seed of the maze, random if `None`
+    pub seed: Option<u64>,
+}
+
+/// Where goals are placed in the maze
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GoalPlacement {
+    /// Single goal in the corner opposite to the start
+    #[default]
+    Corner,
+    /// Goal in every corner of the last floor, except the start
+    Corners,
+    /// Goal in the middle of every side of the last floor
+    Exits,
+}
+
+impl GoalPlacement {
+    /// Positions of the goals in a maze of given size, never empty
+    pub fn positions(self, size: Dims3D) -> Vec<Dims3D> {
+        let Dims3D(w, h, d) = size;
+        let (z, start) = (d - 1, Dims3D(0, 0, 0));
+        let corner = Dims3D(w - 1, h - 1, z);
+
+        let candidates = match self {
+            GoalPlacement::Corner => vec![corner],
+            GoalPlacement::Corners => vec![
+                corner,
+                Dims3D(w - 1, 0, z),
+                Dims3D(0, h - 1, z),
+                Dims3D(0, 0, z),
+            ],
+            GoalPlacement::Exits => vec![
+                Dims3D(w - 1, h / 2, z),
+                Dims3D(w / 2, h - 1, z),
+                Dims3D(0, h / 2, z),
+                Dims3D(w / 2, 0, z),
+            ],
+        };
+
+        let mut goals: Vec<Dims3D> = vec![];
+        for goal in candidates {
+            if goal != start && !goals.contains(&goal) {
+                goals.push(goal);
+            }
+        }
+
+        if goals.is_empty() {
+            goals.push(corner);
+        }
+
+        goals
+    }
+}
+
+/// What player needs to do with goals to finish the game
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GoalObjective {
+    /// Game ends when any of the goals is reached
+    #[default]
+    ReachAny,
+    /// Game ends when all of the goals were visited
+    VisitAll,
+}
+
+/// Items placed in the maze, see [`Maze::place_items`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ItemPlacement {
+    /// Coins on every floor
+    #[serde(default)]
+    pub coins: u16,
+    /// Locked door on the way to the first goal, with a key somewhere before it
+    #[serde(default)]
+    pub door: bool,
+}
+
+impl ItemPlacement {
+    /// No items, usable in constants unlike [`Default::default`]
+    pub const NONE: ItemPlacement = ItemPlacement {
+        coins: 0,
+        door: false,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        self.coins == 0 && !self.door
+    }
 }
 
 pub enum MoveMode {
@@ -55,6 +151,45 @@ impl<R> ProgressComm<R> {
     pub fn progress(&self) -> Progress {
         *self.recv.lock().unwrap()
     }
+
+    /// Block until the work is done and return its result
+    pub fn wait(self) -> R {
+        self.handle.join().expect("worker thread panicked")
+    }
+}
+
+/// Recording of a run, which can be played back with [`RunningGame::from_replay`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    pub game_mode: GameMode,
+    pub seed: u64,
+    /// Maze encoded by [`Maze::encode`], so the replay doesn't depend on the generator
+    pub maze: Vec<u8>,
+    /// Directions of the steps encoded by [`encode_moves`]
+    pub moves: Vec<u8>,
+    /// Game time of every step, in milliseconds
+    pub times: Vec<u64>,
+}
+
+impl Replay {
+    /// Steps of the player with the game time they were made at
+    pub fn steps(&self) -> Result<Vec<(Duration, CellWall)>, DecodeError> {
+        let moves = decode_moves(&self.moves)?;
+        if moves.len() != self.times.len() {
+            return Err(DecodeError::Corrupted);
+        }
+
+        Ok(self
+            .times
+            .iter()
+            .map(|ms| Duration::from_millis(*ms))
+            .zip(moves)
+            .collect())
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.times.last().copied().unwrap_or_default())
+    }
 }
 
 pub struct RunningGame {
@@ -62,12 +197,32 @@ pub struct RunningGame {
     state: RunningGameState,
     game_mode: GameMode,
     gen_fn: GeneratorFn,
+    seed: u64,
     #[allow(dead_code)]
     clock: Option<PausableClock>,
     start: Option<PausableInstant>,
+    /// Real time of the start and the finish, pauses are not subtracted
+    wall_start: Option<Instant>,
+    wall_end: Option<Instant>,
+    /// Position of the player when the game starts
+    start_pos: Dims3D,
     player_pos: Dims3D,
-    goal_pos: Dims3D,
+    goals: Vec<Dims3D>,
+    reached_goals: Vec<bool>,
     moves: Vec<(Dims3D, CellWall)>,
+    /// Game time of every move, for replays
+    move_times: Vec<Duration>,
+    /// Time and moves added on top of the real ones, see [`RunningGame::add_penalty`]
+    penalty_time: Duration,
+    penalty_moves: usize,
+    /// Items in the maze when the game starts, they are put back on restart
+    placed_items: Vec<(Dims3D, CellContent)>,
+    /// Item picked up or used by every move, in the order of [`Self::get_moves`]
+    move_items: Vec<Option<CellContent>>,
+    coins: usize,
+    keys: usize,
+    /// Incremented on every change of the items in the maze, so it can be redrawn
+    item_changes: usize,
 }
 
 impl RunningGame {
@@ -77,35 +232,66 @@ impl RunningGame {
         let GameProperities {
             game_mode: maze_mode,
             generator: generation_func,
+            seed,
         } = props;
+        let seed = seed.unwrap_or_else(random_seed);
 
         let GameMode {
             size: msize,
             is_tower,
+            goals,
+            braid,
+            braid_floors,
+            items,
+            ..
         } = maze_mode;
 
         let player_pos = Dims3D(0, 0, 0);
-        let goal_pos = Dims3D(msize.0 - 1, msize.1 - 1, msize.2 - 1);
+        let goals = goals.positions(msize);
+        let reached_goals = vec![false; goals.len()];
 
         let ProgressComm {
             handle: maze_handle,
             stop_flag,
             recv: progress,
-        } = generation_func(msize, is_tower)?;
+        } = generation_func(msize, is_tower, seed)?;
 
         Ok(ProgressComm {
             handle: thread::spawn(move || {
-                let maze = maze_handle.join().unwrap()?;
+                let mut maze = maze_handle.join().unwrap()?;
+                if braid > 0 {
+                    let mut rng = MazeRng::seed_from_u64(seed ^ BRAID_SEED_SALT);
+                    maze.braid(braid.min(100) as f32 / 100.0, braid_floors, &mut rng);
+                }
+                if !items.is_empty() {
+                    let mut rng = MazeRng::seed_from_u64(seed ^ ITEMS_SEED_SALT);
+                    maze.place_items(player_pos, &goals, items, &mut rng);
+                }
+                let placed_items = maze.get_items();
+
                 Ok(RunningGame {
                     maze,
                     state: RunningGameState::NotStarted,
                     game_mode: maze_mode,
                     gen_fn: generation_func,
+                    seed,
                     clock: None,
                     start: None,
+                    wall_start: None,
+                    wall_end: None,
+                    start_pos: player_pos,
                     player_pos,
-                    goal_pos,
+                    goals,
+                    reached_goals,
                     moves: vec![],
+                    move_times: vec![],
+                    penalty_time: Duration::ZERO,
+                    penalty_moves: 0,
+                    placed_items,
+                    move_items: vec![],
+                    coins: 0,
+                    keys: 0,
+                    item_changes: 0,
                 })
             }),
             stop_flag,
@@ -113,6 +299,144 @@ impl RunningGame {
         })
     }
 
+    /// New game on the same maze, from the start and with no moves made
+    pub fn restarted(&self) -> RunningGame {
+        let mut game = RunningGame {
+            maze: self.maze.clone(),
+            state: RunningGameState::NotStarted,
+            game_mode: self.game_mode,
+            gen_fn: self.gen_fn,
+            seed: self.seed,
+            clock: None,
+            start: None,
+            wall_start: None,
+            wall_end: None,
+            start_pos: self.start_pos,
+            player_pos: self.start_pos,
+            goals: self.goals.clone(),
+            reached_goals: vec![false; self.goals.len()],
+            moves: vec![],
+            move_times: vec![],
+            penalty_time: Duration::ZERO,
+            penalty_moves: 0,
+            placed_items: self.placed_items.clone(),
+            move_items: vec![],
+            coins: 0,
+            keys: 0,
+            item_changes: 0,
+        };
+        game.restore_items();
+        game
+    }
+
+    /// Game on the maze of the replay, with no moves made, see [`RunningGame::replay_step`]
+    ///
+    /// Generator is only used for new mazes of the same mode, the maze itself is stored
+    /// in the replay.
+    pub fn from_replay(replay: &Replay, gen_fn: GeneratorFn) -> Result<RunningGame, DecodeError> {
+        let mut maze = Maze::decode(&replay.maze)?;
+        if maze.size() != replay.game_mode.size {
+            return Err(DecodeError::InvalidSize(maze.size()));
+        }
+
+        let goals = replay.game_mode.goals.positions(maze.size());
+        let items = replay.game_mode.items;
+        if !items.is_empty() {
+            let mut rng = MazeRng::seed_from_u64(replay.seed ^ ITEMS_SEED_SALT);
+            maze.place_items(Dims3D(0, 0, 0), &goals, items, &mut rng);
+        }
+        let placed_items = maze.get_items();
+        Ok(RunningGame {
+            maze,
+            state: RunningGameState::NotStarted,
+            game_mode: replay.game_mode,
+            gen_fn,
+            seed: replay.seed,
+            clock: None,
+            start: None,
+            wall_start: None,
+            wall_end: None,
+            start_pos: Dims3D(0, 0, 0),
+            player_pos: Dims3D(0, 0, 0),
+            reached_goals: vec![false; goals.len()],
+            goals,
+            moves: vec![],
+            move_times: vec![],
+            penalty_time: Duration::ZERO,
+            penalty_moves: 0,
+            placed_items,
+            move_items: vec![],
+            coins: 0,
+            keys: 0,
+            item_changes: 0,
+        })
+    }
+
+    /// Game on a maze which wasn't generated, like one loaded from a file
+    ///
+    /// Positions are not checked, `seed` is only used to tell the mazes apart, so it
+    /// should be different for different mazes.
+    pub fn from_maze(
+        maze: Maze,
+        start_pos: Dims3D,
+        goals: Vec<Dims3D>,
+        objective: GoalObjective,
+        seed: u64,
+        gen_fn: GeneratorFn,
+    ) -> RunningGame {
+        let game_mode = GameMode {
+            size: maze.size(),
+            is_tower: maze.is_tower(),
+            goals: GoalPlacement::default(),
+            objective,
+            braid: 0,
+            braid_floors: 0,
+            items: ItemPlacement::default(),
+        };
+        let placed_items = maze.get_items();
+
+        RunningGame {
+            maze,
+            state: RunningGameState::NotStarted,
+            game_mode,
+            gen_fn,
+            seed,
+            clock: None,
+            start: None,
+            wall_start: None,
+            wall_end: None,
+            start_pos,
+            player_pos: start_pos,
+            reached_goals: vec![false; goals.len()],
+            goals,
+            moves: vec![],
+            move_times: vec![],
+            penalty_time: Duration::ZERO,
+            penalty_moves: 0,
+            placed_items,
+            move_items: vec![],
+            coins: 0,
+            keys: 0,
+            item_changes: 0,
+        }
+    }
+
+    /// Records the moves made so far, with their times
+    pub fn replay(&self) -> Replay {
+        let dirs: Vec<_> = self.moves.iter().map(|(_, dir)| *dir).collect();
+        Replay {
+            game_mode: self.game_mode,
+            seed: self.seed,
+            maze: self.maze.encode(Compression::Rle),
+            moves: encode_moves(&dirs, Compression::Rle),
+            times: self
+                .move_times
+                .iter()
+                .map(|time| time.as_millis() as u64)
+                .collect(),
+        }
+    }
+
     pub fn get_state(&self) -> RunningGameState {
         self.state
     }
@@ -125,16 +449,54 @@ impl RunningGame {
         self.player_pos
     }
 
+    pub fn get_start_pos(&self) -> Dims3D {
+        self.start_pos
+    }
+
+    /// Position of the first goal
     pub fn get_goal_pos(&self) -> Dims3D {
-        self.goal_pos
+        self.goals[0]
+    }
+
+    pub fn get_goals(&self) -> &[Dims3D] {
+        &self.goals
+    }
+
+    pub fn is_goal_reached(&self, goal: Dims3D) -> bool {
+        self.goals
+            .iter()
+            .zip(&self.reached_goals)
+            .any(|(pos, reached)| *pos == goal && *reached)
+    }
+
+    /// Number of goals which still need to be visited to finish the game
+    pub fn get_remaining_goals(&self) -> usize {
+        match self.game_mode.objective {
+            GoalObjective::ReachAny if self.reached_goals.contains(&true) => 0,
+            GoalObjective::ReachAny => 1,
+            GoalObjective::VisitAll => self.reached_goals.iter().filter(|r| !**r).count(),
+        }
+    }
+
+    /// Applies the shifts to the maze, in order, see [`Maze::plan_shifts`]
+    pub fn shift_walls(&mut self, shifts: &[WallShift]) {
+        for shift in shifts {
+            self.maze.apply_shift(*shift);
+        }
+    }
+
+    /// Game time of every move, in the order of [`Self::get_moves`]
+    pub fn get_move_times(&self) -> &[Duration] {
+        &self.move_times
     }
 
     pub fn get_moves(&self) -> &Vec<(Dims3D, CellWall)> {
         &self.moves
     }
 
+    /// Number of moves made, with the penalty moves
     pub fn get_move_count(&self) -> usize {
-        self.moves.len()
+        self.moves.len() + self.penalty_moves
     }
 
     pub fn get_game_mode(&self) -> GameMode {
@@ -145,9 +507,49 @@ impl RunningGame {
         self.gen_fn
     }
 
+    /// Seed the maze was generated from
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn get_available_moves(&self) -> [bool; 6] {
-        let cell = &self.maze.get_cell(self.player_pos).unwrap();
-        CellWall::get_in_order().map(|wall| !cell.get_wall(wall))
+        CellWall::get_in_order().map(|wall| !self.is_blocked(wall))
+    }
+
+    /// Coins picked up so far
+    pub fn get_coins(&self) -> usize {
+        self.coins
+    }
+
+    /// Coins in the maze when the game started
+    pub fn get_total_coins(&self) -> usize {
+        self.placed_items
+            .iter()
+            .filter(|(_, item)| *item == CellContent::Coin)
+            .count()
+    }
+
+    /// Keys picked up and not used yet
+    pub fn get_keys(&self) -> usize {
+        self.keys
+    }
+
+    /// Number of changes of the items in the maze, it grows with every pickup, undo or restart
+    pub fn get_item_changes(&self) -> usize {
+        self.item_changes
+    }
+
+    /// Whether player can't go in the direction, because of a wall or a locked door
+    pub fn is_blocked(&self, dir: CellWall) -> bool {
+        let Some(cell) = self.maze.get_cell(self.player_pos) else {
+            return true;
+        };
+        if cell.get_wall(dir) {
+            return true;
+        }
+
+        let next = self.maze.get_cell(self.player_pos + dir.to_coord());
+        self.keys == 0 && next.is_some_and(|c| c.get_content() == Some(CellContent::Door))
     }
 
     pub fn start(&mut self) -> Result<(), GameAlreadyRunningError> {
@@ -155,6 +557,7 @@ impl RunningGame {
             self.state = RunningGameState::Running;
             self.clock = Some(PausableClock::default());
             self.start = Some(self.clock.as_mut().unwrap().now());
+            self.wall_start = Some(Instant::now());
 
             Ok(())
         } else {
@@ -166,6 +569,8 @@ impl RunningGame {
         self.state = RunningGameState::Quitted;
         self.clock = None;
         self.start = None;
+        self.wall_start = None;
+        self.wall_end = None;
     }
 
     pub fn move_player(
@@ -173,43 +578,53 @@ impl RunningGame {
         dir: CellWall,
         move_mode: MoveMode,
         tower_auto_up: bool,
+    ) -> Result<(Dims3D, usize), GameNotRunningError> {
+        self.move_player_at_most(dir, move_mode, tower_auto_up, usize::MAX)
+    }
+
+    /// Same as [`RunningGame::move_player`], but stops after `max_steps` cells
+    pub fn move_player_at_most(
+        &mut self,
+        dir: CellWall,
+        move_mode: MoveMode,
+        tower_auto_up: bool,
+        max_steps: usize,
     ) -> Result<(Dims3D, usize), GameNotRunningError> {
         self.check_running()?;
 
         let mut count = 0;
+        let is_slow = matches!(move_mode, MoveMode::Slow);
 
         match move_mode {
+            _ if max_steps == 0 => {}
+
             MoveMode::Slow => {
-                return if self.maze.get_cell(self.player_pos).unwrap().get_wall(dir) {
-                    Ok((self.player_pos, 0))
-                } else {
-                    self.moves.push((self.player_pos, dir));
-                    self.player_pos += dir.to_coord();
-                    Ok((self.player_pos, 1))
+                if !self.is_blocked(dir) {
+                    self.step(dir);
+                    count += 1;
                 }
             }
 
             MoveMode::Fast => {
-                while !self.maze.get_cell(self.player_pos).unwrap().get_wall(dir) {
-                    self.moves.push((self.player_pos, dir));
-                    self.player_pos += dir.to_coord();
+                while count < max_steps && !self.is_blocked(dir) {
+                    self.step(dir);
                     count += 1;
                 }
             }
 
             MoveMode::Normal => loop {
-                let mut cell = self.maze.get_cell(self.player_pos).unwrap();
-
-                if cell.get_wall(dir) {
+                if count == max_steps || self.is_blocked(dir) {
                     break;
                 }
 
                 count += 1;
 
-                self.moves.push((self.player_pos, dir));
-                self.player_pos += dir.to_coord();
+                // stops on the items, so they are noticed
+                if self.step(dir).is_some() {
+                    break;
+                }
 
-                cell = self.maze.get_cell(self.player_pos).unwrap();
+                let cell = self.maze.get_cell(self.player_pos).unwrap();
 
                 let perps = dir.perpendicular_walls();
                 if !cell.get_wall(perps.0)
@@ -222,27 +637,123 @@ impl RunningGame {
             },
         }
 
-        if tower_auto_up
-            && self.game_mode.is_tower
-            && !self
-                .maze
-                .get_cell(self.player_pos)
-                .unwrap()
-                .get_wall(CellWall::Up)
-        {
-            self.moves.push((self.player_pos, CellWall::Up));
-            self.player_pos += CellWall::Up.to_coord();
+        let can_climb = tower_auto_up && !is_slow && count < max_steps;
+        if can_climb && self.game_mode.is_tower && !self.is_blocked(CellWall::Up) {
+            self.step(CellWall::Up);
             count += 1;
         }
 
-        if self.player_pos == self.goal_pos {
+        if self.get_remaining_goals() == 0 {
             self.state = RunningGameState::Finished;
             self.clock.as_mut().unwrap().pause();
+            self.wall_end = Some(Instant::now());
         }
 
         Ok((self.player_pos, count))
     }
 
+    /// Applies a step of a replay, even if the game is not running
+    ///
+    /// Returns `false` if there is a wall in the way, so the replay doesn't match the maze.
+    pub fn replay_step(&mut self, dir: CellWall, time: Duration) -> bool {
+        if self.is_blocked(dir) {
+            return false;
+        }
+
+        self.step(dir);
+        *self.move_times.last_mut().unwrap() = time;
+        true
+    }
+
+    /// Takes back the last step, the player returns to the cell it was made from
+    ///
+    /// Goals reached only by the step are not reached anymore and the item picked up or used
+    /// by it is put back. Returns `false` if there is no step to take back.
+    pub fn undo_move(&mut self) -> Result<bool, GameNotRunningError> {
+        self.check_running()?;
+
+        let Some((from, dir)) = self.moves.pop() else {
+            return Ok(false);
+        };
+        self.move_times.pop();
+        self.player_pos = from;
+
+        if let Some(item) = self.move_items.pop().flatten() {
+            match item {
+                CellContent::Coin => self.coins -= 1,
+                CellContent::Key => self.keys -= 1,
+                CellContent::Door => self.keys += 1,
+            }
+            let cell = self.maze.get_cell_mut(from + dir.to_coord()).unwrap();
+            cell.set_content(Some(item));
+            self.item_changes += 1;
+        }
+
+        self.reached_goals.fill(false);
+        let visited = std::iter::once(self.start_pos)
+            .chain(self.moves.iter().map(|(pos, dir)| *pos + dir.to_coord()));
+        for pos in visited {
+            if let Some(i) = self.goals.iter().position(|g| *g == pos) {
+                self.reached_goals[i] = true;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Adds time to the game clock and moves to the move count, as a penalty
+    pub fn add_penalty(&mut self, time: Duration, moves: usize) {
+        self.penalty_time += time;
+        self.penalty_moves += moves;
+    }
+
+    /// Moves player by one cell without checking walls, marks reached goals and takes the item
+    ///
+    /// Returns the item picked up or the door unlocked by the step.
+    fn step(&mut self, dir: CellWall) -> Option<CellContent> {
+        self.moves.push((self.player_pos, dir));
+        self.move_times.push(self.get_elapsed().unwrap_or_default());
+        self.player_pos += dir.to_coord();
+
+        if let Some(i) = self.goals.iter().position(|g| *g == self.player_pos) {
+            self.reached_goals[i] = true;
+        }
+
+        let cell = self.maze.get_cell_mut(self.player_pos).unwrap();
+        let item = cell.get_content();
+        match item {
+            Some(CellContent::Coin) => self.coins += 1,
+            Some(CellContent::Key) => self.keys += 1,
+            Some(CellContent::Door) => self.keys = self.keys.saturating_sub(1),
+            None => {}
+        }
+        if item.is_some() {
+            cell.set_content(None);
+            self.item_changes += 1;
+        }
+
+        self.move_items.push(item);
+        item
+    }
+
+    /// Puts the items back to where they were when the game started
+    fn restore_items(&mut self) {
+        for (pos, _) in self.maze.get_items() {
+            self.maze.get_cell_mut(pos).unwrap().set_content(None);
+        }
+        for (pos, item) in &self.placed_items {
+            self.maze
+                .get_cell_mut(*pos)
+                .unwrap()
+                .set_content(Some(*item));
+        }
+
+        self.move_items.clear();
+        self.coins = 0;
+        self.keys = 0;
+        self.item_changes += 1;
+    }
+
     pub fn check_running(&self) -> Result<(), GameNotRunningError> {
         match self.state {
             RunningGameState::Running => Ok(()),
@@ -257,8 +768,17 @@ impl RunningGame {
         }
     }
 
+    /// Game time since the start, with the penalty time
     pub fn get_elapsed(&self) -> Option<Duration> {
-        self.clock.as_ref().map(|c| self.start.unwrap().elapsed(c))
+        self.clock
+            .as_ref()
+            .map(|c| self.start.unwrap().elapsed(c) + self.penalty_time)
+    }
+
+    /// Real time since the start, with the pauses, it stops when the game is finished
+    pub fn get_wall_elapsed(&self) -> Option<Duration> {
+        let start = self.wall_start?;
+        Some(self.wall_end.unwrap_or_else(Instant::now) - start)
     }
 
     pub fn pause(&mut self) -> Result<(), GameNotRunningError> {
@@ -282,9 +802,174 @@ impl RunningGame {
     pub fn reset(&mut self) {
         self.state = RunningGameState::NotStarted;
         self.moves.clear();
-        self.player_pos = Dims3D(0, 0, 0);
+        self.move_times.clear();
+        self.penalty_time = Duration::ZERO;
+        self.penalty_moves = 0;
+        self.restore_items();
+        self.player_pos = self.start_pos;
+        self.reached_goals.fill(false);
 
         self.clock = None;
         self.start = None;
+        self.wall_start = None;
+        self.wall_end = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboard::algorithms::{MazeAlgorithm, RndKruskals};
+
+    #[test]
+    fn replay_roundtrip() {
+        let props = GameProperities {
+            game_mode: GameMode {
+                size: Dims3D(8, 6, 2),
+                is_tower: false,
+                goals: GoalPlacement::Corner,
+                objective: GoalObjective::ReachAny,
+                braid: 0,
+                braid_floors: 0,
+                items: ItemPlacement::default(),
+            },
+            generator: RndKruskals::generate_seeded,
+            seed: Some(7),
+        };
+        let mut game = RunningGame::new_threaded(props).unwrap().wait().unwrap();
+        game.start().unwrap();
+        for dir in CellWall::get_in_order().into_iter().cycle().take(30) {
+            game.move_player(dir, MoveMode::Normal, false).unwrap();
+        }
+
+        let replay = game.replay();
+        let mut played = RunningGame::from_replay(&replay, RndKruskals::generate_seeded).unwrap();
+        for (time, dir) in replay.steps().unwrap() {
+            assert!(played.replay_step(dir, time));
+        }
+
+        assert_eq!(played.get_player_pos(), game.get_player_pos());
+        assert_eq!(played.get_moves(), game.get_moves());
+    }
+
+    #[test]
+    fn restarted_starts_over_from_the_start() {
+        let mut maze = Maze::new_walled(Dims3D(3, 1, 1), false);
+        maze.remove_wall(Dims3D(0, 0, 0), CellWall::Right);
+        maze.remove_wall(Dims3D(1, 0, 0), CellWall::Right);
+        let (start, goal) = (Dims3D(1, 0, 0), Dims3D(2, 0, 0));
+
+        let mut game = RunningGame::from_maze(
+            maze,
+            start,
+            vec![goal],
+            GoalObjective::ReachAny,
+            5,
+            RndKruskals::generate_seeded,
+        );
+        game.start().unwrap();
+        game.move_player(CellWall::Left, MoveMode::Slow, false)
+            .unwrap();
+        game.add_penalty(Duration::from_secs(5), 1);
+        assert_eq!(game.get_player_pos(), Dims3D(0, 0, 0));
+
+        let restarted = game.restarted();
+        assert_eq!(restarted.get_state(), RunningGameState::NotStarted);
+        assert_eq!(restarted.get_start_pos(), start);
+        assert_eq!(restarted.get_player_pos(), start);
+        assert_eq!(restarted.get_goals(), &[goal]);
+        assert_eq!(restarted.get_move_count(), 0);
+        assert!(restarted.get_moves().is_empty());
+        assert_eq!(restarted.get_elapsed(), None);
+        assert_eq!(restarted.get_maze().size(), game.get_maze().size());
+    }
+
+    #[test]
+    fn undo_returns_to_previous_cell() {
+        let props = GameProperities {
+            game_mode: GameMode {
+                size: Dims3D(6, 6, 1),
+                is_tower: false,
+                goals: GoalPlacement::Corner,
+                objective: GoalObjective::ReachAny,
+                braid: 0,
+                braid_floors: 0,
+                items: ItemPlacement::default(),
+            },
+            generator: RndKruskals::generate_seeded,
+            seed: Some(3),
+        };
+        let mut game = RunningGame::new_threaded(props).unwrap().wait().unwrap();
+        game.start().unwrap();
+        assert!(!game.undo_move().unwrap());
+
+        let dir = CellWall::get_in_order()
+            .into_iter()
+            .zip(game.get_available_moves())
+            .find_map(|(dir, available)| available.then_some(dir))
+            .unwrap();
+        game.move_player(dir, MoveMode::Slow, false).unwrap();
+        assert_eq!(game.get_move_count(), 1);
+
+        assert!(game.undo_move().unwrap());
+        assert_eq!(game.get_player_pos(), Dims3D(0, 0, 0));
+        assert_eq!(game.get_move_count(), 0);
+
+        game.add_penalty(Duration::from_secs(5), 2);
+        assert_eq!(game.get_move_count(), 2);
+        assert!(game.get_elapsed().unwrap() >= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn door_needs_key_and_undo_puts_items_back() {
+        let props = GameProperities {
+            game_mode: GameMode {
+                size: Dims3D(10, 10, 1),
+                is_tower: false,
+                goals: GoalPlacement::Corner,
+                objective: GoalObjective::ReachAny,
+                braid: 0,
+                braid_floors: 0,
+                items: ItemPlacement {
+                    coins: 3,
+                    door: true,
+                },
+            },
+            generator: RndKruskals::generate_seeded,
+            seed: Some(5),
+        };
+        let mut game = RunningGame::new_threaded(props).unwrap().wait().unwrap();
+        game.start().unwrap();
+        let items = game.get_maze().get_items();
+        assert_eq!(items.len(), 5);
+        assert_eq!(game.get_total_coins(), 3);
+
+        let find = |item| items.iter().find(|(_, i)| *i == item).unwrap().0;
+        let (door, key) = (find(CellContent::Door), find(CellContent::Key));
+        let goal = game.get_goal_pos();
+
+        let walk = |game: &mut RunningGame, to: Dims3D| {
+            let path = game.get_maze().solve(game.get_player_pos(), to).unwrap();
+            for next in &path[1..] {
+                let dir = Maze::which_wall_between(game.get_player_pos(), *next).unwrap();
+                if *next == door && game.get_keys() == 0 {
+                    assert!(game.is_blocked(dir));
+                    return;
+                }
+                game.move_player(dir, MoveMode::Slow, false).unwrap();
+            }
+        };
+
+        walk(&mut game, door);
+        walk(&mut game, key);
+        assert_eq!(game.get_keys(), 1);
+        walk(&mut game, goal);
+        assert_eq!(game.get_keys(), 0);
+        assert_eq!(game.get_state(), RunningGameState::Finished);
+
+        game.state = RunningGameState::Running;
+        while game.undo_move().unwrap() {}
+        assert_eq!((game.get_coins(), game.get_keys()), (0, 0));
+        assert_eq!(game.get_maze().get_items(), items);
     }
 }
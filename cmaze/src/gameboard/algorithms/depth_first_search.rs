@@ -3,8 +3,8 @@ use std::sync::{Arc, Mutex};
 use rand::seq::SliceRandom;
 
 use super::{
-    super::cell::Cell, GenErrorInstant, GenErrorThreaded, Maze, MazeAlgorithm, Progress,
-    StopGenerationFlag,
+    super::cell::Cell, Carve, GenErrorInstant, GenErrorThreaded, Maze, MazeAlgorithm, MazeRng,
+    Progress, StopGenerationFlag,
 };
 
 use crate::dims::*;
@@ -14,8 +14,10 @@ pub struct DepthFirstSearch {}
 impl MazeAlgorithm for DepthFirstSearch {
     fn generate_individual(
         size: Dims3D,
+        rng: &mut MazeRng,
         stopper: StopGenerationFlag,
         progress: Arc<Mutex<Progress>>,
+        carve: &mut Carve,
     ) -> Result<Maze, GenErrorThreaded> {
         if size.0 == 0 || size.1 == 0 || size.2 == 0 {
             return Err(GenErrorThreaded::GenerationError(
@@ -63,9 +65,10 @@ impl MazeAlgorithm for DepthFirstSearch {
 
             if !unvisited_neighbors.is_empty() {
                 stack.push(current);
-                let chosen = *unvisited_neighbors.choose(&mut rand::thread_rng()).unwrap();
+                let chosen = *unvisited_neighbors.choose(rng).unwrap();
                 let chosen_wall = Maze::which_wall_between(current, chosen).unwrap();
                 maze.remove_wall(current, chosen_wall);
+                carve(current, chosen_wall)?;
                 visited.push(chosen);
                 stack.push(chosen);
             }
@@ -1,12 +1,19 @@
 mod depth_first_search;
 mod rnd_kruskals;
 
-use rand::{thread_rng, Rng};
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use std::{
-    sync::{Arc, Mutex, RwLock},
-    thread,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use super::{Cell, CellWall, Maze};
@@ -15,18 +22,65 @@ use crate::{dims::*, game::ProgressComm};
 pub use depth_first_search::DepthFirstSearch;
 pub use rnd_kruskals::RndKruskals;
 
-#[derive(Debug)]
+/// Random generator used by the algorithms
+///
+/// Unlike `StdRng`, its output is stable across versions, so the same seed
+/// always generates the same maze.
+pub type MazeRng = ChaCha8Rng;
+
+/// Seed for a maze which doesn't need to be reproduced
+pub fn random_seed() -> u64 {
+    thread_rng().gen()
+}
+
+/// How the seed of a maze is chosen when none is given
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SeedSource {
+    /// Random seed, see [`random_seed`]
+    #[default]
+    Entropy,
+    /// Seconds since the Unix epoch, games started in the same second get the same maze
+    Time,
+    /// Number which goes up by one with every game, it's kept by the caller
+    Counter,
+}
+
+impl SeedSource {
+    /// Seed of a new maze, `counter` is used only by [`SeedSource::Counter`]
+    pub fn seed(self, counter: u64) -> u64 {
+        match self {
+            SeedSource::Entropy => random_seed(),
+            SeedSource::Time => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            SeedSource::Counter => counter,
+        }
+    }
+}
+
+/// Seed of a single floor, so the floors can be generated in parallel
+fn floor_seed(seed: u64, floor: usize) -> u64 {
+    seed ^ (floor as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// Error found before the generation starts
+#[derive(Debug, Error)]
 pub enum GenErrorInstant {
+    #[error("maze of size {}x{}x{} can't be generated", .0.0, .0.1, .0.2)]
     InvalidSize(Dims3D),
 }
 
-#[derive(Debug)]
+/// Error of the generation running on another thread
+#[derive(Debug, Error)]
 pub enum GenErrorThreaded {
+    #[error(transparent)]
     GenerationError(GenErrorInstant),
+    #[error("generation was cancelled")]
     AbortGeneration,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
+#[error("generation was stopped")]
 pub struct StopGenerationError;
 
 #[derive(Clone, Debug)]
@@ -57,6 +111,53 @@ impl Default for StopGenerationFlag {
     }
 }
 
+/// Called by the algorithms with every removed wall, the generation stops on error
+pub type Carve<'a> = dyn FnMut(Dims3D, CellWall) -> Result<(), GenErrorThreaded> + 'a;
+
+/// Wall removed during the generation, as a cell and its wall
+pub type WallRemoval = (Dims3D, CellWall);
+
+/// How many removals can wait in the stream, the generation pauses when it's full
+const STREAM_BUFFER: usize = 1024;
+
+/// Walls removed by the generation, in order, computed on another thread as they're read
+///
+/// Applying all of them to [`Maze::new_walled`] gives the same maze as
+/// [`MazeAlgorithm::generate_seeded`] with the same seed. Dropping the stream stops the
+/// generation.
+pub struct GenStream {
+    recv: Receiver<WallRemoval>,
+    handle: Option<JoinHandle<Result<(), GenErrorThreaded>>>,
+    stop_flag: StopGenerationFlag,
+}
+
+impl GenStream {
+    /// Waits for the end of the generation, after the stream was read whole
+    pub fn finish(mut self) -> Result<(), GenErrorThreaded> {
+        // removals which weren't read are not needed
+        while self.recv.recv().is_ok() {}
+        self.handle
+            .take()
+            .expect("stream is finished only once")
+            .join()
+            .expect("worker thread panicked")
+    }
+}
+
+impl Iterator for GenStream {
+    type Item = WallRemoval;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv.recv().ok()
+    }
+}
+
+impl Drop for GenStream {
+    fn drop(&mut self) {
+        self.stop_flag.stop();
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Progress {
     pub done: usize,
@@ -68,6 +169,15 @@ pub trait MazeAlgorithm {
     fn generate(
         size: Dims3D,
         floored: bool,
+    ) -> Result<ProgressComm<Result<Maze, GenErrorThreaded>>, GenErrorInstant> {
+        Self::generate_seeded(size, floored, random_seed())
+    }
+
+    /// Generates the maze deterministically, same seed gives the same maze
+    fn generate_seeded(
+        size: Dims3D,
+        floored: bool,
+        seed: u64,
     ) -> Result<ProgressComm<Result<Maze, GenErrorThreaded>>, GenErrorInstant> {
         if size.0 <= 0 || size.1 <= 0 || size.2 <= 0 {
             return Err(GenErrorInstant::InvalidSize(size));
@@ -87,19 +197,20 @@ pub trait MazeAlgorithm {
             handle: thread::spawn(move || {
                 let Dims3D(w, h, d) = size;
                 let (wu, hu, du) = (w as usize, h as usize, d as usize);
+                let mut rng = MazeRng::seed_from_u64(seed);
 
                 let cells = if floored && d > 1 {
-                    let mut cells = Self::generate_floors(size, progress, stop_flag)?;
+                    let mut cells = Self::generate_floors(size, seed, progress, stop_flag)?;
 
-                    for floor in 0..du - 1 {
-                        let (x, y) = (thread_rng().gen_range(0..wu), thread_rng().gen_range(0..hu));
+                    for (x, y, floor) in stairs(size, &mut rng) {
                         cells[floor][y][x].remove_wall(CellWall::Up);
                         cells[floor + 1][y][x].remove_wall(CellWall::Down);
                     }
 
                     cells
                 } else {
-                    Self::generate_individual(Dims3D(w, h, d), stop_flag, progress)?.cells
+                    let carve = &mut |_, _| Ok(());
+                    Self::generate_individual(size, &mut rng, stop_flag, progress, carve)?.cells
                 };
 
                 Ok(Maze {
@@ -115,17 +226,85 @@ pub trait MazeAlgorithm {
         })
     }
 
+    /// Generates the maze like [`MazeAlgorithm::generate_seeded`], but yields the removed walls
+    ///
+    /// Floors of towers are generated one after another, not in parallel.
+    fn stream_seeded(size: Dims3D, floored: bool, seed: u64) -> Result<GenStream, GenErrorInstant> {
+        if size.0 <= 0 || size.1 <= 0 || size.2 <= 0 {
+            return Err(GenErrorInstant::InvalidSize(size));
+        }
+
+        let (send, recv) = mpsc::sync_channel(STREAM_BUFFER);
+        let stop_flag = StopGenerationFlag::new();
+        let stopper = stop_flag.clone();
+
+        let handle = thread::spawn(move || {
+            let progress = || {
+                Arc::new(Mutex::new(Progress {
+                    done: 0,
+                    from: 1,
+                    is_finished: false,
+                }))
+            };
+            // fails when the stream was dropped
+            let carve_at = |floor: Option<i32>| {
+                let send = send.clone();
+                move |mut pos: Dims3D, wall| {
+                    pos.2 = floor.unwrap_or(pos.2);
+                    send.send((pos, wall))
+                        .map_err(|_| GenErrorThreaded::AbortGeneration)
+                }
+            };
+            let mut rng = MazeRng::seed_from_u64(seed);
+
+            if !(floored && size.2 > 1) {
+                let carve = &mut carve_at(None);
+                Self::generate_individual(size, &mut rng, stopper, progress(), carve)?;
+                return Ok(());
+            }
+
+            for floor in 0..size.2 {
+                let mut floor_rng = MazeRng::seed_from_u64(floor_seed(seed, floor as usize));
+                let floor_size = Dims3D(size.0, size.1, 1);
+                let carve = &mut carve_at(Some(floor));
+                Self::generate_individual(
+                    floor_size,
+                    &mut floor_rng,
+                    stopper.clone(),
+                    progress(),
+                    carve,
+                )?;
+            }
+
+            let carve = carve_at(None);
+            for (x, y, floor) in stairs(size, &mut rng) {
+                carve(Dims3D(x as i32, y as i32, floor as i32), CellWall::Up)?;
+            }
+            Ok(())
+        });
+
+        Ok(GenStream {
+            recv,
+            handle: Some(handle),
+            stop_flag,
+        })
+    }
+
     fn generate_floors(
         size: Dims3D,
+        seed: u64,
         progress: Arc<Mutex<Progress>>,
         stop_flag: StopGenerationFlag,
     ) -> Result<Vec<Vec<Vec<Cell>>>, GenErrorThreaded> {
         let Dims3D(w, h, d) = size;
         let (.., du) = (w as usize, h as usize, d as usize);
-        let generate_floor = |progress| {
+        let generate_floor = |(floor, progress)| {
             let stop_flag = stop_flag.clone();
+            let mut rng = MazeRng::seed_from_u64(floor_seed(seed, floor));
 
-            let generation_result = Self::generate_individual(Dims3D(w, h, 1), stop_flag, progress);
+            let carve = &mut |_, _| Ok(());
+            let generation_result =
+                Self::generate_individual(Dims3D(w, h, 1), &mut rng, stop_flag, progress, carve);
 
             generation_result.map(|mut res| res.cells.remove(0))
         };
@@ -166,7 +345,7 @@ pub trait MazeAlgorithm {
 
             (0..du)
                 .into_par_iter()
-                .map(|i| shared_progresses[i].clone())
+                .map(|i| (i, shared_progresses[i].clone()))
                 .map(generate_floor)
                 .collect::<Result<Vec<_>, GenErrorThreaded>>()
         })
@@ -174,7 +353,107 @@ pub trait MazeAlgorithm {
 
     fn generate_individual(
         size: Dims3D,
+        rng: &mut MazeRng,
         stopper: StopGenerationFlag,
         progress: Arc<Mutex<Progress>>,
+        carve: &mut Carve,
     ) -> Result<Maze, GenErrorThreaded>;
 }
+
+/// Cells with stairs up from every floor but the last one, as `(x, y, floor)`
+fn stairs(size: Dims3D, rng: &mut MazeRng) -> Vec<(usize, usize, usize)> {
+    let Dims3D(w, h, d) = size;
+    (0..d as usize - 1)
+        .map(|floor| {
+            (
+                rng.gen_range(0..w as usize),
+                rng.gen_range(0..h as usize),
+                floor,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text<A: MazeAlgorithm>(size: Dims3D, floored: bool, seed: u64) -> String {
+        let maze = A::generate_seeded(size, floored, seed).unwrap().wait();
+        maze.unwrap().to_text(&[])
+    }
+
+    #[test]
+    fn same_seed_same_maze() {
+        let (size, tower) = (Dims3D(15, 10, 3), Dims3D(8, 8, 4));
+
+        assert_eq!(
+            text::<RndKruskals>(size, false, 7),
+            text::<RndKruskals>(size, false, 7)
+        );
+        assert_eq!(
+            text::<DepthFirstSearch>(tower, true, 7),
+            text::<DepthFirstSearch>(tower, true, 7)
+        );
+        assert_ne!(
+            text::<RndKruskals>(size, false, 7),
+            text::<RndKruskals>(size, false, 8)
+        );
+    }
+
+    fn streamed<A: MazeAlgorithm>(size: Dims3D, floored: bool, seed: u64) -> String {
+        let mut maze = Maze::new_walled(size, floored);
+        let mut stream = A::stream_seeded(size, floored, seed).unwrap();
+        for (pos, wall) in &mut stream {
+            maze.remove_wall(pos, wall);
+        }
+        stream.finish().unwrap();
+        maze.to_text(&[])
+    }
+
+    #[test]
+    fn stream_builds_the_same_maze() {
+        let (size, tower) = (Dims3D(12, 7, 2), Dims3D(6, 5, 3));
+
+        assert_eq!(
+            streamed::<RndKruskals>(size, false, 4),
+            text::<RndKruskals>(size, false, 4)
+        );
+        assert_eq!(
+            streamed::<DepthFirstSearch>(tower, true, 4),
+            text::<DepthFirstSearch>(tower, true, 4)
+        );
+    }
+
+    #[test]
+    fn dropped_stream_stops() {
+        let mut stream = RndKruskals::stream_seeded(Dims3D(200, 200, 1), false, 1).unwrap();
+        assert!(stream.next().is_some());
+        drop(stream);
+    }
+
+    #[test]
+    fn braiding_removes_dead_ends() {
+        let size = Dims3D(20, 10, 2);
+        let maze = RndKruskals::generate_seeded(size, false, 3)
+            .unwrap()
+            .wait()
+            .unwrap();
+        let dead_ends = |maze: &Maze| maze.analyze(Dims3D(0, 0, 0), Dims3D(0, 0, 0)).dead_ends;
+
+        let mut braided = maze.clone();
+        braided.braid(1.0, 0, &mut MazeRng::seed_from_u64(1));
+        assert_eq!(dead_ends(&braided), 0);
+
+        // only the second floor
+        let mut braided = maze.clone();
+        braided.braid(0.5, 0b10, &mut MazeRng::seed_from_u64(1));
+        assert!(dead_ends(&braided) < dead_ends(&maze));
+        for cell in maze.cells[0].concat() {
+            let pos = cell.get_coord();
+            for wall in CellWall::get_in_order() {
+                assert_eq!(maze.get_wall(pos, wall), braided.get_wall(pos, wall));
+            }
+        }
+    }
+}
@@ -1,10 +1,11 @@
-use rand::{seq::SliceRandom, thread_rng};
+use rand::seq::SliceRandom;
 
 use std::sync::{Arc, Mutex};
 
 use super::{
     super::cell::{Cell, CellWall},
-    GenErrorInstant, GenErrorThreaded, Maze, MazeAlgorithm, Progress, StopGenerationFlag,
+    Carve, GenErrorInstant, GenErrorThreaded, Maze, MazeAlgorithm, MazeRng, Progress,
+    StopGenerationFlag,
 };
 use crate::dims::*;
 
@@ -19,8 +20,10 @@ pub struct RndKruskals {}
 impl MazeAlgorithm for RndKruskals {
     fn generate_individual(
         size: Dims3D,
+        rng: &mut MazeRng,
         stopper: StopGenerationFlag,
         progress: Arc<Mutex<Progress>>,
+        carve: &mut Carve,
     ) -> Result<Maze, GenErrorThreaded> {
         if size.0 == 0 || size.1 == 0 || size.2 == 0 {
             return Err(GenErrorThreaded::GenerationError(
@@ -85,7 +88,7 @@ impl MazeAlgorithm for RndKruskals {
             is_tower: false,
         };
 
-        walls.shuffle(&mut thread_rng());
+        walls.shuffle(rng);
         while let Some((pos0, wall)) = walls.pop() {
             let pos1 = pos0 + wall.to_coord();
 
@@ -101,6 +104,7 @@ impl MazeAlgorithm for RndKruskals {
             maze.get_cell_mut(pos1)
                 .unwrap()
                 .remove_wall(wall.reverse_wall());
+            carve(pos0, wall)?;
             let set0 = sets.swap_remove(set0_i);
 
             let set1_i = if set1_i == sets.len() - 1 {
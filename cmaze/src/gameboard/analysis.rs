@@ -0,0 +1,278 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{dims::*, gameboard::CellWall};
+
+use super::Maze;
+
+/// Seconds an average player needs for a point of [`MazeStats::score`]
+pub const SECONDS_PER_POINT: f32 = 0.5;
+
+/// How hard a maze is to solve, estimated from its [`MazeStats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Brutal,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Medium => write!(f, "Medium"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Brutal => write!(f, "Brutal"),
+        }
+    }
+}
+
+impl Difficulty {
+    /// Score of a typical maze of the difficulty, see [`MazeStats::score`]
+    pub fn typical_score(self) -> f32 {
+        match self {
+            Difficulty::Easy => 15.0,
+            Difficulty::Medium => 90.0,
+            Difficulty::Hard => 375.0,
+            Difficulty::Brutal => 900.0,
+        }
+    }
+}
+
+/// Structural statistics of a maze, see [`Maze::analyze`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MazeStats {
+    pub size: Dims3D,
+    pub tower: bool,
+    /// Cells with a single passage
+    pub dead_ends: usize,
+    /// Cells with three or more passages
+    pub junctions: usize,
+    /// Cells with stairs going up
+    pub stairs: usize,
+    /// Number of moves of the shortest solution, `None` if there is none
+    pub solution_len: Option<usize>,
+    /// Number of changes of direction along the shortest solution
+    pub turns: usize,
+}
+
+impl MazeStats {
+    pub fn cells(&self) -> usize {
+        (self.size.0 * self.size.1 * self.size.2) as usize
+    }
+
+    /// Ratio of turns to moves of the solution, from 0 (straight) to 1
+    pub fn twistiness(&self) -> f32 {
+        match self.solution_len {
+            Some(len) if len > 1 => self.turns as f32 / (len - 1) as f32,
+            _ => 0.0,
+        }
+    }
+
+    pub fn dead_end_ratio(&self) -> f32 {
+        self.dead_ends as f32 / self.cells().max(1) as f32
+    }
+
+    /// How hard the maze is, long and twisty solutions with many dead ends score the most
+    pub fn score(&self) -> f32 {
+        let len = self.solution_len.unwrap_or_default() as f32;
+        len * (0.5 + self.twistiness()) * (1.0 + self.dead_end_ratio())
+    }
+
+    /// Rough time an average player needs to solve the maze, from its [`MazeStats::score`]
+    pub fn expected_solve_time(&self) -> Duration {
+        Duration::from_secs_f32(self.score() * SECONDS_PER_POINT)
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        match self.score() {
+            s if s < 30.0 => Difficulty::Easy,
+            s if s < 150.0 => Difficulty::Medium,
+            s if s < 600.0 => Difficulty::Hard,
+            _ => Difficulty::Brutal,
+        }
+    }
+
+    /// Short human readable summary, like "30x30 twisty dungeon, 412-step solution"
+    pub fn describe(&self) -> String {
+        let Dims3D(w, h, d) = self.size;
+        let size = match d {
+            1 => format!("{}x{}", w, h),
+            _ => format!("{}x{}x{}", w, h, d),
+        };
+
+        let shape = match self.twistiness() {
+            t if t < 0.3 => "straight",
+            t if t < 0.5 => "winding",
+            _ => "twisty",
+        };
+
+        let kind = match self.difficulty() {
+            _ if self.tower => "tower",
+            Difficulty::Easy => "garden",
+            Difficulty::Medium => "maze",
+            Difficulty::Hard => "labyrinth",
+            Difficulty::Brutal => "dungeon",
+        };
+
+        let mut parts = vec![format!("{} {} {}", size, shape, kind)];
+
+        match self.solution_len {
+            Some(len) => parts.push(format!("{}-step solution", len)),
+            None => parts.push("no solution".to_string()),
+        }
+
+        if self.stairs > 0 {
+            let plural = if self.stairs == 1 { "" } else { "s" };
+            parts.push(format!("{} staircase{}", self.stairs, plural));
+        }
+
+        if self.dead_end_ratio() > 0.3 {
+            parts.push("many dead ends".to_string());
+        }
+
+        parts.join(", ")
+    }
+}
+
+impl fmt::Display for MazeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe())
+    }
+}
+
+impl Maze {
+    /// Computes statistics of the maze, solution is searched between `from` and `to`
+    pub fn analyze(&self, from: Dims3D, to: Dims3D) -> MazeStats {
+        let (mut dead_ends, mut junctions, mut stairs) = (0, 0, 0);
+
+        for floor in &self.cells {
+            for row in floor {
+                for cell in row {
+                    let passages = CellWall::get_in_order()
+                        .into_iter()
+                        .filter(|wall| !cell.get_wall(*wall))
+                        .count();
+
+                    match passages {
+                        1 => dead_ends += 1,
+                        3.. => junctions += 1,
+                        _ => {}
+                    }
+
+                    if !cell.get_wall(CellWall::Up) {
+                        stairs += 1;
+                    }
+                }
+            }
+        }
+
+        let solution = self.solve(from, to);
+        let turns = solution.as_ref().map_or(0, |path| {
+            path.windows(3)
+                .filter(|w| w[1] - w[0] != w[2] - w[1])
+                .count()
+        });
+
+        MazeStats {
+            size: self.size(),
+            tower: self.is_tower,
+            dead_ends,
+            junctions,
+            stairs,
+            solution_len: solution.map(|path| path.len() - 1),
+            turns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corridor() {
+        let maze = Maze::with_passages(
+            Dims3D(3, 1, 1),
+            &[
+                (Dims3D(0, 0, 0), CellWall::Right),
+                (Dims3D(1, 0, 0), CellWall::Right),
+            ],
+        );
+        let stats = maze.analyze(Dims3D(0, 0, 0), Dims3D(2, 0, 0));
+
+        assert_eq!(stats.dead_ends, 2);
+        assert_eq!(stats.junctions, 0);
+        assert_eq!(stats.stairs, 0);
+        assert_eq!(stats.solution_len, Some(2));
+        assert_eq!(stats.turns, 0);
+        assert_eq!(stats.twistiness(), 0.0);
+        assert_eq!(stats.difficulty(), Difficulty::Easy);
+        assert_eq!(
+            stats.describe(),
+            "3x1 straight garden, 2-step solution, many dead ends"
+        );
+    }
+
+    #[test]
+    fn turns_of_the_solution() {
+        // U-shape, down, right and up again
+        let maze = Maze::with_passages(
+            Dims3D(2, 2, 1),
+            &[
+                (Dims3D(0, 0, 0), CellWall::Bottom),
+                (Dims3D(0, 1, 0), CellWall::Right),
+                (Dims3D(1, 1, 0), CellWall::Top),
+            ],
+        );
+        let stats = maze.analyze(Dims3D(0, 0, 0), Dims3D(1, 0, 0));
+
+        assert_eq!(stats.solution_len, Some(3));
+        assert_eq!(stats.turns, 2);
+        assert_eq!(stats.twistiness(), 1.0);
+        assert!(stats
+            .describe()
+            .starts_with("2x2 twisty garden, 3-step solution"));
+    }
+
+    #[test]
+    fn junctions_and_dead_ends() {
+        // plus sign, the corners are closed off
+        let center = Dims3D(1, 1, 0);
+        let maze = Maze::with_passages(
+            Dims3D(3, 3, 1),
+            &[
+                (center, CellWall::Top),
+                (center, CellWall::Bottom),
+                (center, CellWall::Left),
+                (center, CellWall::Right),
+            ],
+        );
+        let stats = maze.analyze(Dims3D(1, 0, 0), Dims3D(1, 2, 0));
+
+        assert_eq!(stats.dead_ends, 4);
+        assert_eq!(stats.junctions, 1);
+        assert_eq!(stats.solution_len, Some(2));
+        assert_eq!(stats.turns, 0);
+    }
+
+    #[test]
+    fn stairs_and_no_solution() {
+        let maze = Maze::with_passages(Dims3D(2, 1, 2), &[(Dims3D(0, 0, 0), CellWall::Up)]);
+
+        let stats = maze.analyze(Dims3D(0, 0, 0), Dims3D(0, 0, 1));
+        assert_eq!(stats.stairs, 1);
+        assert_eq!(stats.solution_len, Some(1));
+        assert_eq!(
+            stats.describe(),
+            "2x1x2 straight garden, 1-step solution, 1 staircase, many dead ends"
+        );
+
+        let stats = maze.analyze(Dims3D(0, 0, 0), Dims3D(1, 0, 0));
+        assert_eq!(stats.solution_len, None);
+        assert_eq!(stats.score(), 0.0);
+        assert!(stats.describe().contains("no solution"));
+    }
+}
@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{dims::*, gameboard::cell::CellWall::*};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cell {
     left: bool,
     top: bool,
@@ -10,6 +12,9 @@ pub struct Cell {
     down: bool,
 
     coord: Dims3D,
+    /// Item lying in the cell, see [`crate::gameboard::Maze::place_items`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<CellContent>,
 }
 
 impl Cell {
@@ -22,6 +27,7 @@ impl Cell {
             up: true,
             down: true,
             coord: pos,
+            content: None,
         }
     }
 
@@ -36,6 +42,17 @@ impl Cell {
         }
     }
 
+    pub fn add_wall(&mut self, wall: CellWall) {
+        match wall {
+            Left => self.left = true,
+            Top => self.top = true,
+            Right => self.right = true,
+            Bottom => self.bottom = true,
+            Up => self.up = true,
+            Down => self.down = true,
+        }
+    }
+
     pub fn get_wall(&self, wall: CellWall) -> bool {
         match wall {
             Left => self.left,
@@ -50,6 +67,14 @@ impl Cell {
     pub fn get_coord(&self) -> Dims3D {
         self.coord
     }
+
+    pub fn get_content(&self) -> Option<CellContent> {
+        self.content
+    }
+
+    pub fn set_content(&mut self, content: Option<CellContent>) {
+        self.content = content;
+    }
 }
 
 impl PartialEq for Cell {
@@ -60,7 +85,18 @@ impl PartialEq for Cell {
 
 impl Eq for Cell {}
 
-#[derive(Copy, Clone)]
+/// Item in a cell, picked up or used when the player steps in
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CellContent {
+    /// Adds to the score
+    Coin,
+    /// Opens one door
+    Key,
+    /// Can't be entered without a key, the key is used up
+    Door,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CellWall {
     Left,
     Right,
@@ -93,6 +129,24 @@ impl CellWall {
         }
     }
 
+    /// Direction after turning left, when looking at the floor from above
+    ///
+    /// Floors are not turned to, [`Up`] and [`Down`] stay the same.
+    pub fn turn_left(&self) -> CellWall {
+        match self {
+            Top => Left,
+            Left => Bottom,
+            Bottom => Right,
+            Right => Top,
+            Up | Down => *self,
+        }
+    }
+
+    /// Direction after turning right, see [`CellWall::turn_left`]
+    pub fn turn_right(&self) -> CellWall {
+        self.turn_left().reverse_wall()
+    }
+
     pub fn perpendicular_walls(&self) -> (CellWall, CellWall, CellWall, CellWall) {
         match self {
             Left | Right => (Top, Bottom, Up, Down),
@@ -0,0 +1,386 @@
+//! Compact binary encoding of mazes and move lists
+//!
+//! Every cell stores only its right, bottom and up wall, the other ones are
+//! shared with the neighbors and the outer walls are always present, so a
+//! cell takes 3 bits. The packed bits can be further compressed, see
+//! [`Compression`].
+//!
+//! Encoded data start with a header: magic `TMZ`, format version, kind of
+//! the data and compression, so old data can still be read when the format
+//! changes.
+
+use thiserror::Error;
+
+use crate::dims::*;
+
+use super::{CellWall, Maze};
+
+const MAGIC: &[u8; 3] = b"TMZ";
+/// Current version of the format, increase on any incompatible change
+pub const FORMAT_VERSION: u8 = 1;
+
+const KIND_MAZE: u8 = 0;
+const KIND_MOVES: u8 = 1;
+
+const HEADER_LEN: usize = 6;
+
+/// Walls stored for every cell, in the order of the bits
+const STORED_WALLS: [CellWall; 3] = [CellWall::Right, CellWall::Bottom, CellWall::Up];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// Run-length encoding, good for mazes with long corridors, no dependencies
+    #[default]
+    Rle,
+    /// Zstandard, smallest output for large mazes
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Rle => 1,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, DecodeError> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Rle),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Compression::Zstd),
+            _ => Err(DecodeError::UnsupportedCompression(byte)),
+        }
+    }
+
+    fn compress(self, data: Vec<u8>) -> Vec<u8> {
+        match self {
+            Compression::None => data,
+            Compression::Rle => rle_encode(&data),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::encode_all(data.as_slice(), 19).expect("in-memory write"),
+        }
+    }
+
+    fn decompress(self, data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Rle => rle_decode(data),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::decode_all(data).map_err(|_| DecodeError::Corrupted),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("not an encoded maze")]
+    BadMagic,
+    #[error("unsupported format version {0}, newest supported is {FORMAT_VERSION}")]
+    UnsupportedVersion(u8),
+    #[error("unsupported compression {0}")]
+    UnsupportedCompression(u8),
+    #[error("expected encoded {expected}, found something else")]
+    WrongKind { expected: &'static str },
+    #[error("invalid maze size {0:?}")]
+    InvalidSize(Dims3D),
+    #[error("data are truncated or corrupted")]
+    Corrupted,
+}
+
+impl Maze {
+    /// Encodes the maze into the compact binary format
+    pub fn encode(&self, compression: Compression) -> Vec<u8> {
+        let mut payload = vec![self.is_tower as u8];
+        for side in [self.width, self.height, self.depth] {
+            payload.extend_from_slice(&(side as u32).to_le_bytes());
+        }
+
+        let mut bits = BitWriter::default();
+        for floor in &self.cells {
+            for row in floor {
+                for cell in row {
+                    for wall in STORED_WALLS {
+                        bits.push(cell.get_wall(wall));
+                    }
+                }
+            }
+        }
+        payload.extend(bits.finish());
+
+        with_header(KIND_MAZE, compression, payload)
+    }
+
+    /// Decodes a maze encoded by [`Maze::encode`]
+    pub fn decode(data: &[u8]) -> Result<Maze, DecodeError> {
+        let payload = read_header(data, KIND_MAZE, "maze")?;
+
+        let (&tower, rest) = payload.split_first().ok_or(DecodeError::Corrupted)?;
+        let mut sides = rest
+            .chunks_exact(4)
+            .take(3)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as i32);
+        let size = Dims3D(
+            sides.next().ok_or(DecodeError::Corrupted)?,
+            sides.next().ok_or(DecodeError::Corrupted)?,
+            sides.next().ok_or(DecodeError::Corrupted)?,
+        );
+
+        if size.0 <= 0 || size.1 <= 0 || size.2 <= 0 {
+            return Err(DecodeError::InvalidSize(size));
+        }
+
+        let Dims3D(w, h, d) = size;
+        let bit_count = (w as usize)
+            .checked_mul(h as usize)
+            .and_then(|n| n.checked_mul(d as usize))
+            .and_then(|n| n.checked_mul(STORED_WALLS.len()))
+            .ok_or(DecodeError::InvalidSize(size))?;
+        let bits = &rest[12..];
+        if bits.len() * 8 < bit_count {
+            return Err(DecodeError::Corrupted);
+        }
+
+        let mut maze = Maze::new_walled(size, tower != 0);
+
+        let mut reader = BitReader::new(bits);
+        for z in 0..d {
+            for y in 0..h {
+                for x in 0..w {
+                    for wall in STORED_WALLS {
+                        if !reader.read() {
+                            maze.remove_wall(Dims3D(x, y, z), wall);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(maze)
+    }
+}
+
+/// Encodes directions of moves, 3 bits per move, for replays
+pub fn encode_moves(moves: &[CellWall], compression: Compression) -> Vec<u8> {
+    let mut payload = (moves.len() as u32).to_le_bytes().to_vec();
+
+    let mut bits = BitWriter::default();
+    for wall in moves {
+        let index = CellWall::get_in_order()
+            .iter()
+            .position(|w| w == wall)
+            .unwrap() as u8;
+        for bit in 0..3 {
+            bits.push(index & (1 << bit) != 0);
+        }
+    }
+    payload.extend(bits.finish());
+
+    with_header(KIND_MOVES, compression, payload)
+}
+
+/// Decodes moves encoded by [`encode_moves`]
+pub fn decode_moves(data: &[u8]) -> Result<Vec<CellWall>, DecodeError> {
+    let payload = read_header(data, KIND_MOVES, "moves")?;
+    if payload.len() < 4 {
+        return Err(DecodeError::Corrupted);
+    }
+
+    let len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+    let bits = &payload[4..];
+    if bits.len() * 8 < len * 3 {
+        return Err(DecodeError::Corrupted);
+    }
+
+    let walls = CellWall::get_in_order();
+    let mut reader = BitReader::new(bits);
+    (0..len)
+        .map(|_| {
+            let index = (0..3).fold(0, |acc, bit| acc | ((reader.read() as usize) << bit));
+            walls.get(index).copied().ok_or(DecodeError::Corrupted)
+        })
+        .collect()
+}
+
+fn with_header(kind: u8, compression: Compression, payload: Vec<u8>) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN + payload.len());
+    data.extend_from_slice(MAGIC);
+    data.extend([FORMAT_VERSION, kind, compression.to_byte()]);
+    data.extend(compression.compress(payload));
+    data
+}
+
+fn read_header(data: &[u8], kind: u8, kind_name: &'static str) -> Result<Vec<u8>, DecodeError> {
+    if data.len() < HEADER_LEN || &data[..3] != MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+
+    let [version, found_kind, compression] = [data[3], data[4], data[5]];
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    if found_kind != kind {
+        return Err(DecodeError::WrongKind {
+            expected: kind_name,
+        });
+    }
+
+    Compression::from_byte(compression)?.decompress(&data[HEADER_LEN..])
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 1 << (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Next bit, length is checked by the callers
+    fn read(&mut self) -> bool {
+        let bit = self.bytes[self.pos / 8] & (1 << (self.pos % 8)) != 0;
+        self.pos += 1;
+        bit
+    }
+}
+
+/// Runs of the same byte are stored as `(count, byte)`, count is at most 255
+fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    let mut iter = data.iter().peekable();
+
+    while let Some(&byte) = iter.next() {
+        let mut count = 1u8;
+        while count < u8::MAX && iter.peek() == Some(&&byte) {
+            iter.next();
+            count += 1;
+        }
+        out.extend([count, byte]);
+    }
+
+    out
+}
+
+fn rle_decode(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if !data.len().is_multiple_of(2) {
+        return Err(DecodeError::Corrupted);
+    }
+
+    let mut out = vec![];
+    for pair in data.chunks_exact(2) {
+        if pair[0] == 0 {
+            return Err(DecodeError::Corrupted);
+        }
+        out.extend(std::iter::repeat_n(pair[1], pair[0] as usize));
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboard::algorithms::{DepthFirstSearch, MazeAlgorithm, RndKruskals};
+
+    fn compressions() -> Vec<Compression> {
+        vec![
+            Compression::None,
+            Compression::Rle,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+        ]
+    }
+
+    #[test]
+    fn maze_round_trip() {
+        let mazes = [
+            RndKruskals::generate(Dims3D(13, 7, 3), false),
+            DepthFirstSearch::generate(Dims3D(20, 20, 2), true),
+            RndKruskals::generate(Dims3D(1, 1, 1), false),
+        ];
+
+        for maze in mazes {
+            let maze = maze.unwrap().wait().unwrap();
+            for compression in compressions() {
+                let decoded = Maze::decode(&maze.encode(compression)).unwrap();
+                assert_eq!(decoded.size(), maze.size());
+                assert_eq!(decoded.is_tower(), maze.is_tower());
+                assert_eq!(decoded.to_text(&[]), maze.to_text(&[]));
+            }
+        }
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        let moves = [
+            CellWall::Left,
+            CellWall::Down,
+            CellWall::Up,
+            CellWall::Bottom,
+            CellWall::Bottom,
+            CellWall::Right,
+            CellWall::Top,
+        ];
+
+        for compression in compressions() {
+            let decoded = decode_moves(&encode_moves(&moves, compression)).unwrap();
+            assert_eq!(decoded, moves);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let maze = RndKruskals::generate(Dims3D(5, 5, 1), false)
+            .unwrap()
+            .wait()
+            .unwrap();
+        let data = maze.encode(Compression::None);
+
+        assert_eq!(Maze::decode(b"nope").err(), Some(DecodeError::BadMagic));
+
+        let mut newer = data.clone();
+        newer[3] = FORMAT_VERSION + 1;
+        assert_eq!(
+            Maze::decode(&newer).err(),
+            Some(DecodeError::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+
+        assert_eq!(
+            Maze::decode(&data[..data.len() - 2]).err(),
+            Some(DecodeError::Corrupted)
+        );
+        assert!(matches!(
+            decode_moves(&data),
+            Err(DecodeError::WrongKind { .. })
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use rand::{seq::SliceRandom, SeedableRng};
+
+use super::{algorithms::MazeRng, CellWall, Maze};
+use crate::dims::*;
+
+/// Cells of a maze which can be generated, the other ones stay walled off
+///
+/// Floors are connected only at the cells in [`CellMask::stairs`], so a mask made from
+/// a path keeps the stairs the path went through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellMask {
+    size: Dims3D,
+    cells: Vec<bool>,
+    /// Cells with stairs up
+    stairs: Vec<Dims3D>,
+}
+
+impl CellMask {
+    /// Mask with no cells
+    pub fn empty(size: Dims3D) -> Self {
+        let Dims3D(w, h, d) = size;
+        Self {
+            size,
+            cells: vec![false; (w * h * d).max(0) as usize],
+            stairs: vec![],
+        }
+    }
+
+    /// Cells of the path, widened by `radius` cells to every side on their floors
+    ///
+    /// Path should be made of neighboring cells, like the moves of a player. Positions
+    /// outside of `size` are ignored.
+    pub fn from_path(size: Dims3D, path: &[Dims3D], radius: i32) -> Self {
+        let mut mask = Self::empty(size);
+        for &pos in path {
+            mask.insert(pos);
+        }
+        for step in path.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            if from.2 != to.2 && mask.contains(from) && mask.contains(to) {
+                let lower = if from.2 < to.2 { from } else { to };
+                if !mask.stairs.contains(&lower) {
+                    mask.stairs.push(lower);
+                }
+            }
+        }
+
+        mask.inflate(radius);
+        mask
+    }
+
+    pub fn size(&self) -> Dims3D {
+        self.size
+    }
+
+    pub fn stairs(&self) -> &[Dims3D] {
+        &self.stairs
+    }
+
+    fn index(&self, pos: Dims3D) -> Option<usize> {
+        let Dims3D(w, h, d) = self.size;
+        let inside = (0..w).contains(&pos.0) && (0..h).contains(&pos.1) && (0..d).contains(&pos.2);
+        inside.then(|| (pos.0 + pos.1 * w + pos.2 * w * h) as usize)
+    }
+
+    pub fn contains(&self, pos: Dims3D) -> bool {
+        self.index(pos).is_some_and(|i| self.cells[i])
+    }
+
+    /// Adds the cell, returns `false` if it's outside of the mask
+    pub fn insert(&mut self, pos: Dims3D) -> bool {
+        match self.index(pos) {
+            Some(i) => {
+                self.cells[i] = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.iter().filter(|c| **c).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Dims3D> + '_ {
+        let Dims3D(w, h, d) = self.size;
+        (0..d)
+            .flat_map(move |z| (0..h).flat_map(move |y| (0..w).map(move |x| Dims3D(x, y, z))))
+            .filter(|pos| self.contains(*pos))
+    }
+
+    /// Widens the mask by `radius` cells to every side, including diagonals, floors stay as they are
+    pub fn inflate(&mut self, radius: i32) {
+        let original: Vec<_> = self.cells().collect();
+        for pos in original {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    self.insert(pos + Dims3D(x, y, 0));
+                }
+            }
+        }
+    }
+
+    /// Mask cut to the smallest size which holds all its cells, with the offset of its origin
+    ///
+    /// Positions in the cropped mask are the original ones minus the offset.
+    pub fn crop(&self) -> (CellMask, Dims3D) {
+        let Some(first) = self.cells().next() else {
+            return (Self::empty(Dims3D(0, 0, 0)), Dims3D(0, 0, 0));
+        };
+
+        let (min, max) = self.cells().fold((first, first), |(min, max), pos| {
+            (
+                Dims3D(min.0.min(pos.0), min.1.min(pos.1), min.2.min(pos.2)),
+                Dims3D(max.0.max(pos.0), max.1.max(pos.1), max.2.max(pos.2)),
+            )
+        });
+
+        let mut cropped = Self::empty(max - min + Dims3D(1, 1, 1));
+        for pos in self.cells() {
+            cropped.insert(pos - min);
+        }
+        cropped.stairs = self.stairs.iter().map(|&pos| pos - min).collect();
+        (cropped, min)
+    }
+
+    /// Generates a maze in the shape of the mask, same seed gives the same maze
+    ///
+    /// Every floor is carved by a randomized depth-first search, parts of a floor which
+    /// don't touch each other are connected only through the stairs.
+    pub fn generate(&self, seed: u64) -> Maze {
+        let mut rng = MazeRng::seed_from_u64(seed);
+        let mut maze = Maze::new_walled(self.size, false);
+        let mut visited = Self::empty(self.size);
+
+        for start in self.cells() {
+            if !visited.insert_new(start) {
+                continue;
+            }
+
+            let mut stack = vec![start];
+            while let Some(&current) = stack.last() {
+                let next: Vec<_> = [
+                    CellWall::Left,
+                    CellWall::Right,
+                    CellWall::Top,
+                    CellWall::Bottom,
+                ]
+                .into_iter()
+                .filter(|wall| {
+                    let pos = current + wall.to_coord();
+                    self.contains(pos) && !visited.contains(pos)
+                })
+                .collect();
+
+                match next.choose(&mut rng) {
+                    Some(&wall) => {
+                        maze.remove_wall(current, wall);
+                        let pos = current + wall.to_coord();
+                        visited.insert(pos);
+                        stack.push(pos);
+                    }
+                    None => {
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        for &pos in &self.stairs {
+            if self.contains(pos) && self.contains(pos + CellWall::Up.to_coord()) {
+                maze.remove_wall(pos, CellWall::Up);
+            }
+        }
+
+        maze
+    }
+
+    /// Adds the cell, returns `false` if it was already there
+    fn insert_new(&mut self, pos: Dims3D) -> bool {
+        let new = !self.contains(pos);
+        self.insert(pos);
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maze_follows_the_path() {
+        // along the bottom, up the stairs and back on the floor above
+        let path: Vec<_> = (0..6)
+            .map(|x| Dims3D(x, 6, 0))
+            .chain((0..6).rev().map(|x| Dims3D(x, 6, 1)))
+            .collect();
+        let mask = CellMask::from_path(Dims3D(10, 10, 3), &path, 1);
+        assert_eq!(mask.stairs(), [Dims3D(5, 6, 0)]);
+        assert!(mask.contains(Dims3D(6, 7, 0)) && !mask.contains(Dims3D(7, 7, 0)));
+
+        let (mask, offset) = mask.crop();
+        assert_eq!(offset, Dims3D(0, 5, 0));
+        assert_eq!(mask.size(), Dims3D(7, 3, 2));
+        assert_eq!(mask.len(), 7 * 3 * 2);
+
+        let maze = mask.generate(5);
+        let stats = maze.analyze(path[0] - offset, path[path.len() - 1] - offset);
+        assert_eq!(stats.stairs, 1);
+        assert!(stats.solution_len.is_some());
+        assert_eq!(maze.to_text(&[]), mask.generate(5).to_text(&[]));
+    }
+}
@@ -1,7 +1,23 @@
+use std::collections::VecDeque;
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
 use self::CellWall::*;
-use crate::{dims::*, gameboard::cell::{Cell, CellWall}};
+use crate::{
+    dims::*,
+    game::ItemPlacement,
+    gameboard::cell::{Cell, CellContent, CellWall},
+};
 
-#[derive(Clone)]
+/// Wall opened and another one closed on the loop it made, see [`Maze::plan_shifts`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WallShift {
+    pub open: (Dims3D, CellWall),
+    pub close: (Dims3D, CellWall),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Maze {
     pub(crate) cells: Vec<Vec<Vec<Cell>>>,
     pub(crate) width: usize,
@@ -11,6 +27,36 @@ pub struct Maze {
 }
 
 impl Maze {
+    /// Maze with all the walls, passages are made by [`Maze::remove_wall`]
+    pub fn new_walled(size: Dims3D, is_tower: bool) -> Maze {
+        let Dims3D(w, h, d) = size;
+        let cells = (0..d)
+            .map(|z| {
+                (0..h)
+                    .map(|y| (0..w).map(|x| Cell::new(Dims3D(x, y, z))).collect())
+                    .collect()
+            })
+            .collect();
+
+        Maze {
+            cells,
+            width: w as usize,
+            height: h as usize,
+            depth: d as usize,
+            is_tower,
+        }
+    }
+
+    /// Maze of the size with the passages between the cells, used by tests
+    #[cfg(test)]
+    pub(crate) fn with_passages(size: Dims3D, passages: &[(Dims3D, CellWall)]) -> Maze {
+        let mut maze = Maze::new_walled(size, false);
+        for (cell, wall) in passages {
+            maze.remove_wall(*cell, *wall);
+        }
+        maze
+    }
+
     pub fn size(&self) -> Dims3D {
         Dims3D(self.width as i32, self.height as i32, self.depth as i32)
     }
@@ -100,6 +146,16 @@ impl Maze {
         }
     }
 
+    pub fn add_wall(&mut self, cell: Dims3D, wall: CellWall) {
+        if !self.is_valid_wall(cell, wall) {
+            return;
+        }
+
+        self.cells[cell.2 as usize][cell.1 as usize][cell.0 as usize].add_wall(wall);
+        let next = cell + wall.to_coord();
+        self.cells[next.2 as usize][next.1 as usize][next.0 as usize].add_wall(wall.reverse_wall());
+    }
+
     pub fn get_cells(&self) -> &[Vec<Vec<Cell>>] {
         &self.cells
     }
@@ -123,4 +179,631 @@ impl Maze {
     pub fn is_tower(&self) -> bool {
         self.is_tower
     }
+
+    /// Creates a coarse version of the maze, used for lower level of detail
+    ///
+    /// Every block of `factor`x`factor` cells on a floor becomes a single cell,
+    /// interior walls of the block are skipped. Wall between two blocks is open
+    /// if any passage crosses their boundary, stairs are kept if any cell
+    /// in the block has them. Floors are never merged.
+    pub fn downsample(&self, factor: usize) -> Maze {
+        let factor = factor.max(1);
+        let width = self.width.div_ceil(factor);
+        let height = self.height.div_ceil(factor);
+
+        let mut cells: Vec<Vec<Vec<Cell>>> = (0..self.depth)
+            .map(|z| {
+                (0..height)
+                    .map(|y| {
+                        (0..width)
+                            .map(|x| Cell::new(Dims3D(x as i32, y as i32, z as i32)))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for (z, floor) in self.cells.iter().enumerate() {
+            for (y, row) in floor.iter().enumerate() {
+                for (x, cell) in row.iter().enumerate() {
+                    let (cx, cy) = (x / factor, y / factor);
+                    let coarse = &mut cells[z][cy][cx];
+
+                    for wall in [Up, Down] {
+                        if !cell.get_wall(wall) {
+                            coarse.remove_wall(wall);
+                        }
+                    }
+
+                    if !cell.get_wall(Right) && (x + 1) / factor != cx {
+                        coarse.remove_wall(Right);
+                        cells[z][cy][cx + 1].remove_wall(Left);
+                    }
+
+                    if !cell.get_wall(Bottom) && (y + 1) / factor != cy {
+                        cells[z][cy][cx].remove_wall(Bottom);
+                        cells[z][cy + 1][cx].remove_wall(Top);
+                    }
+                }
+            }
+        }
+
+        Maze {
+            cells,
+            width,
+            height,
+            depth: self.depth,
+            is_tower: self.is_tower,
+        }
+    }
+
+    /// Shortest path between two cells, both ends included
+    ///
+    /// Returns `None` if any of the cells is out of bounds or there is no path.
+    pub fn solve(&self, from: Dims3D, to: Dims3D) -> Option<Vec<Dims3D>> {
+        if !self.is_in_bounds(from) || !self.is_in_bounds(to) {
+            return None;
+        }
+
+        let index = |pos: Dims3D| {
+            (pos.2 as usize * self.height + pos.1 as usize) * self.width + pos.0 as usize
+        };
+
+        let mut prev = vec![None; self.width * self.height * self.depth];
+        let mut queue = VecDeque::from([from]);
+        prev[index(from)] = Some(from);
+
+        while let Some(pos) = queue.pop_front() {
+            if pos == to {
+                let mut path = vec![pos];
+                let mut pos = pos;
+                while pos != from {
+                    pos = prev[index(pos)].unwrap();
+                    path.push(pos);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let cell = self.get_cell(pos).unwrap();
+            for wall in CellWall::get_in_order() {
+                let next = pos + wall.to_coord();
+                if cell.get_wall(wall) || !self.is_in_bounds(next) || prev[index(next)].is_some() {
+                    continue;
+                }
+
+                prev[index(next)] = Some(pos);
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+
+    /// Removes about `ratio` of the dead ends on the `floors` by knocking out extra walls
+    ///
+    /// The maze isn't perfect anymore, it has loops. Only walls between cells of the same
+    /// floor are removed, neighbouring dead ends are joined first, so a single removed wall
+    /// gets rid of two of them. `floors` is a bit mask of the floors, `0` means all floors.
+    pub fn braid(&mut self, ratio: f32, floors: u64, rng: &mut impl Rng) {
+        let passages = |maze: &Maze, pos: Dims3D| {
+            let cell = maze.get_cell(pos).unwrap();
+            CellWall::get_in_order()
+                .into_iter()
+                .filter(|wall| !cell.get_wall(*wall))
+                .count()
+        };
+        let in_region = |pos: Dims3D| floors == 0 || floors & (1 << pos.2.min(63)) != 0;
+
+        let mut dead_ends: Vec<_> = self
+            .all_positions()
+            .filter(|pos| in_region(*pos) && passages(self, *pos) == 1)
+            .collect();
+        dead_ends.shuffle(rng);
+
+        for pos in dead_ends {
+            // could be joined with another dead end already
+            if passages(self, pos) != 1 || rng.gen::<f32>() >= ratio {
+                continue;
+            }
+
+            let cell = self.get_cell(pos).unwrap();
+            let mut walls: Vec<_> = [Left, Right, Top, Bottom]
+                .into_iter()
+                .filter(|wall| cell.get_wall(*wall))
+                .map(|wall| (wall, pos + wall.to_coord()))
+                .filter(|(_, next)| self.is_in_bounds(*next) && in_region(*next))
+                .collect();
+            walls.shuffle(rng);
+            walls.sort_by_key(|(_, next)| passages(self, *next) != 1);
+
+            if let Some((wall, _)) = walls.first() {
+                self.remove_wall(pos, *wall);
+            }
+        }
+    }
+
+    /// Places coins, a key and a locked door, previous items are removed
+    ///
+    /// The door is put on the shortest path from `start` to the first goal and the key in a cell
+    /// which can be reached without going through it. Items never lie on `start`, the goals,
+    /// the stairs or each other. Nothing is placed if the maze is too small for it.
+    pub fn place_items(
+        &mut self,
+        start: Dims3D,
+        goals: &[Dims3D],
+        items: ItemPlacement,
+        rng: &mut impl Rng,
+    ) {
+        for pos in self.all_positions().collect::<Vec<_>>() {
+            self.get_cell_mut(pos).unwrap().set_content(None);
+        }
+
+        let mut taken: Vec<Dims3D> = std::iter::once(start)
+            .chain(goals.iter().copied())
+            .collect();
+        let is_free = |maze: &Maze, taken: &[Dims3D], pos: &Dims3D| {
+            let cell = maze.get_cell(*pos).unwrap();
+            !taken.contains(pos) && cell.get_wall(Up) && cell.get_wall(Down)
+        };
+
+        if items.door {
+            let path = goals
+                .first()
+                .and_then(|goal| self.solve(start, *goal))
+                .unwrap_or_default();
+            // second half of the path, so the key has room before it
+            let doors: Vec<_> = match path.len() {
+                0..=2 => vec![],
+                len => path[(len / 2).max(1)..len - 1]
+                    .iter()
+                    .filter(|pos| is_free(self, &taken, pos))
+                    .copied()
+                    .collect(),
+            };
+
+            if let Some(&door) = doors.choose(rng) {
+                let mut keys = self.reachable(start, door);
+                keys.retain(|pos| is_free(self, &taken, pos));
+                if let Some(&key) = keys.choose(rng) {
+                    self.get_cell_mut(door)
+                        .unwrap()
+                        .set_content(Some(CellContent::Door));
+                    self.get_cell_mut(key)
+                        .unwrap()
+                        .set_content(Some(CellContent::Key));
+                    taken.extend([door, key]);
+                }
+            }
+        }
+
+        for floor in 0..self.depth as i32 {
+            let mut free: Vec<_> = self
+                .all_positions()
+                .filter(|pos| pos.2 == floor && is_free(self, &taken, pos))
+                .collect();
+            free.shuffle(rng);
+            for pos in free.into_iter().take(items.coins as usize) {
+                self.get_cell_mut(pos)
+                    .unwrap()
+                    .set_content(Some(CellContent::Coin));
+            }
+        }
+    }
+
+    /// Items lying in the maze, with their positions
+    pub fn get_items(&self) -> Vec<(Dims3D, CellContent)> {
+        self.cells
+            .iter()
+            .flatten()
+            .flatten()
+            .filter_map(|cell| Some((cell.get_coord(), cell.get_content()?)))
+            .collect()
+    }
+
+    /// Cells reachable from `from` without entering `blocked`
+    fn reachable(&self, from: Dims3D, blocked: Dims3D) -> Vec<Dims3D> {
+        let index = |pos: Dims3D| {
+            (pos.2 as usize * self.height + pos.1 as usize) * self.width + pos.0 as usize
+        };
+
+        let mut seen = vec![false; self.width * self.height * self.depth];
+        let mut cells = vec![from];
+        let mut queue = VecDeque::from([from]);
+        seen[index(from)] = true;
+
+        while let Some(pos) = queue.pop_front() {
+            let cell = self.get_cell(pos).unwrap();
+            for wall in CellWall::get_in_order() {
+                let next = pos + wall.to_coord();
+                if cell.get_wall(wall)
+                    || next == blocked
+                    || !self.is_in_bounds(next)
+                    || seen[index(next)]
+                {
+                    continue;
+                }
+
+                seen[index(next)] = true;
+                cells.push(next);
+                queue.push_back(next);
+            }
+        }
+
+        cells
+    }
+
+    /// Plans `count` wall shifts on the floor, they have to be applied in order
+    ///
+    /// Every shift opens a wall and closes another one on the loop it made, so no cell
+    /// is ever cut off. Cells which are already cut off, like outside of custom mazes,
+    /// are never touched. Fewer shifts are returned if there aren't enough walls to open.
+    pub fn plan_shifts(&self, floor: i32, count: usize, rng: &mut impl Rng) -> Vec<WallShift> {
+        const ATTEMPTS: usize = 20;
+
+        let mut maze = self.clone();
+        let mut shifts = vec![];
+        let (w, h) = (self.width as i32, self.height as i32);
+
+        for _ in 0..count * ATTEMPTS {
+            if shifts.len() == count {
+                break;
+            }
+
+            let from = Dims3D(rng.gen_range(0..w), rng.gen_range(0..h), floor);
+            let wall = *[Right, Bottom].choose(rng).unwrap();
+            if !maze.is_valid_wall(from, wall) || maze.get_wall(from, wall) != Some(true) {
+                continue;
+            }
+
+            // path between the cells makes a loop with the opened wall
+            let to = from + wall.to_coord();
+            let Some(path) = maze.solve(from, to) else {
+                continue;
+            };
+            let closable: Vec<_> = path
+                .windows(2)
+                .filter(|pair| pair[0].2 == pair[1].2)
+                .filter_map(|pair| Some((pair[0], Maze::which_wall_between(pair[0], pair[1])?)))
+                .collect();
+            let Some(&close) = closable.choose(rng) else {
+                continue;
+            };
+
+            let shift = WallShift {
+                open: (from, wall),
+                close,
+            };
+            maze.apply_shift(shift);
+
+            // closed wall is on a loop, so its cells have to stay connected
+            let (cell, dir) = close;
+            if maze.solve(cell, cell + dir.to_coord()).is_none() {
+                maze.undo_shift(shift);
+                continue;
+            }
+            shifts.push(shift);
+        }
+
+        shifts
+    }
+
+    pub fn apply_shift(&mut self, shift: WallShift) {
+        self.remove_wall(shift.open.0, shift.open.1);
+        self.add_wall(shift.close.0, shift.close.1);
+    }
+
+    fn undo_shift(&mut self, shift: WallShift) {
+        self.remove_wall(shift.close.0, shift.close.1);
+        self.add_wall(shift.open.0, shift.open.1);
+    }
+
+    fn all_positions(&self) -> impl Iterator<Item = Dims3D> {
+        let (w, h, d) = (self.width as i32, self.height as i32, self.depth as i32);
+        (0..d).flat_map(move |z| (0..h).flat_map(move |y| (0..w).map(move |x| Dims3D(x, y, z))))
+    }
+
+    /// Plain text drawing of all floors, cells of `path` are marked with dots
+    ///
+    /// Walls are `#`, stairs are `^` (up), `v` (down) and `x` (both ways).
+    /// Each floor has a header if there is more than one.
+    pub fn to_text(&self, path: &[Dims3D]) -> String {
+        let mut floors = self.char_grid();
+
+        for (z, x, y) in self.grid_path(path) {
+            let c = &mut floors[z][y][x];
+            if *c == ' ' {
+                *c = '.';
+            }
+        }
+
+        floors
+            .iter()
+            .enumerate()
+            .map(|(z, floor)| {
+                let rows: Vec<String> = floor.iter().map(|row| row.iter().collect()).collect();
+                if self.depth > 1 {
+                    format!("Floor {}\n{}\n", z + 1, rows.join("\n"))
+                } else {
+                    format!("{}\n", rows.join("\n"))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Hash of the maze layout, same for mazes which only differ by rotation or mirroring
+    ///
+    /// Floors in reverse order, with stairs swapped, are considered the same
+    /// too. Size is hashed as it is, so a maze and its transposition of a different size,
+    /// which are different presets, don't collide. Hash is stable, so it can be stored.
+    pub fn canonical_hash(&self) -> u64 {
+        let floors = self.char_grid();
+
+        let mut best = u64::MAX;
+        for reverse_floors in [false, true] {
+            for transform in 0..8 {
+                let mut hasher = Fnv1a::new();
+                hasher.write(self.width as u64);
+                hasher.write(self.height as u64);
+                hasher.write(self.depth as u64);
+                let mut floors: Vec<_> = floors
+                    .iter()
+                    .map(|f| transform_grid(f, transform))
+                    .collect();
+
+                if reverse_floors {
+                    floors.reverse();
+                    for c in floors.iter_mut().flatten().flatten() {
+                        *c = match *c {
+                            '^' => 'v',
+                            'v' => '^',
+                            c => c,
+                        };
+                    }
+                }
+
+                hasher.write(floors.len() as u64);
+                hasher.write(floors[0].len() as u64);
+                hasher.write(floors[0][0].len() as u64);
+                for c in floors.iter().flatten().flatten() {
+                    hasher.write(*c as u64);
+                }
+
+                best = best.min(hasher.finish());
+            }
+        }
+
+        best
+    }
+
+    /// Positions of the cells of `path` in [`Maze::char_grid`] as floor, column and row,
+    /// with the passages to the next cell on the same floor
+    pub(crate) fn grid_path(&self, path: &[Dims3D]) -> Vec<(usize, usize, usize)> {
+        let mut positions = vec![];
+        for (i, &pos) in path.iter().enumerate() {
+            if !self.is_in_bounds(pos) {
+                continue;
+            }
+
+            let (x, y) = (pos.0 * 2 + 1, pos.1 * 2 + 1);
+            positions.push((pos.2 as usize, x as usize, y as usize));
+
+            if let Some(&next) = path.get(i + 1) {
+                let diff = next - pos;
+                if diff.2 == 0 && diff.0.abs() + diff.1.abs() == 1 {
+                    let (x, y) = (x + diff.0, y + diff.1);
+                    positions.push((pos.2 as usize, x as usize, y as usize));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Floors as in [`Maze::to_text`], indexed by floor, row and column
+    pub(crate) fn char_grid(&self) -> Vec<Vec<Vec<char>>> {
+        let (w, h) = (self.width * 2 + 1, self.height * 2 + 1);
+        let mut floors = vec![vec![vec!['#'; w]; h]; self.depth];
+
+        // cell coords are not reliable for floors generated separately
+        for (cells, floor) in self.cells.iter().zip(&mut floors) {
+            for (y, row) in cells.iter().enumerate() {
+                for (x, cell) in row.iter().enumerate() {
+                    let (x, y) = (x * 2 + 1, y * 2 + 1);
+
+                    floor[y][x] = match (!cell.get_wall(Up), !cell.get_wall(Down)) {
+                        (true, true) => 'x',
+                        (true, false) => '^',
+                        (false, true) => 'v',
+                        (false, false) => ' ',
+                    };
+
+                    if !cell.get_wall(Right) {
+                        floor[y][x + 1] = ' ';
+                    }
+                    if !cell.get_wall(Bottom) {
+                        floor[y + 1][x] = ' ';
+                    }
+                }
+            }
+        }
+
+        floors
+    }
+}
+
+/// Rotates and mirrors a grid, all 8 combinations are given by `transform` in `0..8`
+fn transform_grid(grid: &[Vec<char>], transform: u8) -> Vec<Vec<char>> {
+    let (h, w) = (grid.len(), grid[0].len());
+    let transpose = transform & 4 != 0;
+    let (nw, nh) = if transpose { (h, w) } else { (w, h) };
+
+    (0..nh)
+        .map(|y| {
+            (0..nw)
+                .map(|x| {
+                    let x = if transform & 1 != 0 { nw - 1 - x } else { x };
+                    let y = if transform & 2 != 0 { nh - 1 - y } else { y };
+                    if transpose {
+                        grid[x][y]
+                    } else {
+                        grid[y][x]
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// FNV-1a, used because its output doesn't change between Rust versions
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::gameboard::algorithms::{MazeAlgorithm, RndKruskals};
+
+    #[test]
+    fn shifts_keep_cells_connected() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let size = Dims3D(12, 8, 2);
+        let mut maze = RndKruskals::generate_seeded(size, false, 7)
+            .unwrap()
+            .wait()
+            .unwrap();
+
+        for floor in [0, 1, 0, 1] {
+            let shifts = maze.plan_shifts(floor, 5, &mut rng);
+            assert_eq!(shifts.len(), 5);
+            for shift in shifts {
+                assert_eq!(maze.get_wall(shift.open.0, shift.open.1), Some(true));
+                maze.apply_shift(shift);
+            }
+
+            let start = Dims3D(0, 0, 0);
+            assert!(maze
+                .all_positions()
+                .all(|pos| maze.solve(start, pos).is_some()));
+        }
+    }
+
+    #[test]
+    fn downsample_merges_blocks() {
+        use CellWall::*;
+
+        // passages inside the left block, and one across to the right block
+        let maze = Maze::with_passages(
+            Dims3D(4, 2, 1),
+            &[
+                (Dims3D(0, 0, 0), Right),
+                (Dims3D(0, 0, 0), Bottom),
+                (Dims3D(1, 1, 0), Right),
+            ],
+        );
+        let coarse = maze.downsample(2);
+
+        assert_eq!(coarse.size(), Dims3D(2, 1, 1));
+        assert_eq!(coarse.get_wall(Dims3D(0, 0, 0), Right), Some(false));
+        assert_eq!(coarse.get_wall(Dims3D(1, 0, 0), Left), Some(false));
+        for wall in [Top, Bottom, Left, Up, Down] {
+            assert_eq!(coarse.get_wall(Dims3D(0, 0, 0), wall), Some(true));
+        }
+
+        let closed = Maze::with_passages(Dims3D(4, 2, 1), &[(Dims3D(0, 0, 0), Right)]);
+        assert_eq!(
+            closed.downsample(2).get_wall(Dims3D(0, 0, 0), Right),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn downsample_keeps_stairs_and_partial_blocks() {
+        use CellWall::*;
+
+        let maze = Maze::with_passages(
+            Dims3D(5, 3, 2),
+            &[(Dims3D(1, 1, 0), Up), (Dims3D(3, 2, 0), Bottom)],
+        );
+        let coarse = maze.downsample(2);
+
+        // blocks at the edges are smaller, floors are never merged
+        assert_eq!(coarse.size(), Dims3D(3, 2, 2));
+        assert_eq!(coarse.get_wall(Dims3D(0, 0, 0), Up), Some(false));
+        assert_eq!(coarse.get_wall(Dims3D(0, 0, 1), Down), Some(false));
+        assert_eq!(coarse.get_wall(Dims3D(1, 1, 0), Up), Some(true));
+    }
+
+    #[test]
+    fn downsample_by_one_is_identity() {
+        let maze = RndKruskals::generate_seeded(Dims3D(6, 5, 2), false, 3)
+            .unwrap()
+            .wait()
+            .unwrap();
+
+        for factor in [0, 1] {
+            let same = maze.downsample(factor);
+            assert_eq!(same.size(), maze.size());
+            for pos in maze.all_positions() {
+                for wall in CellWall::get_in_order() {
+                    assert_eq!(same.get_wall(pos, wall), maze.get_wall(pos, wall));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_hash_ignores_rotation_but_not_size() {
+        use CellWall::*;
+
+        // L shape in the top left corner of a 3x2 maze
+        let maze = Maze::with_passages(
+            Dims3D(3, 2, 1),
+            &[(Dims3D(0, 0, 0), Right), (Dims3D(0, 0, 0), Bottom)],
+        );
+        // mirrored left to right
+        let mirrored = Maze::with_passages(
+            Dims3D(3, 2, 1),
+            &[(Dims3D(2, 0, 0), Left), (Dims3D(2, 0, 0), Bottom)],
+        );
+        // rotated by 180 degrees
+        let rotated = Maze::with_passages(
+            Dims3D(3, 2, 1),
+            &[(Dims3D(2, 1, 0), Left), (Dims3D(2, 1, 0), Top)],
+        );
+        // transposed, it's a 2x3 maze
+        let transposed = Maze::with_passages(
+            Dims3D(2, 3, 1),
+            &[(Dims3D(0, 0, 0), Bottom), (Dims3D(0, 0, 0), Right)],
+        );
+        let other = Maze::with_passages(
+            Dims3D(3, 2, 1),
+            &[(Dims3D(1, 0, 0), Right), (Dims3D(1, 0, 0), Bottom)],
+        );
+
+        let hash = maze.canonical_hash();
+        assert_eq!(hash, maze.canonical_hash());
+        assert_eq!(hash, mirrored.canonical_hash());
+        assert_eq!(hash, rotated.canonical_hash());
+        assert_ne!(hash, transposed.canonical_hash());
+        assert_ne!(hash, other.canonical_hash());
+    }
 }
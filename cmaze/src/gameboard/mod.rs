@@ -1,5 +1,11 @@
 pub mod maze;
-pub use maze::Maze;
+pub use maze::{Maze, WallShift};
 pub mod cell;
-pub use cell::{Cell, CellWall};
+pub use cell::{Cell, CellContent, CellWall};
 pub mod algorithms;
+pub mod analysis;
+pub mod encoding;
+pub mod mask;
+pub use analysis::{Difficulty, MazeStats, SECONDS_PER_POINT};
+pub use encoding::{Compression, DecodeError};
+pub use mask::CellMask;
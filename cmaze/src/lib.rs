@@ -1,3 +1,7 @@
+pub mod export;
 pub mod game;
 pub mod gameboard;
 pub mod dims;
+#[cfg(feature = "print")]
+pub mod print;
+pub mod signature;
@@ -0,0 +1,456 @@
+//! Printable drawings of maze floors, as SVG or PDF
+//!
+//! Walls are drawn as lines, start and goals are marked with openings in the
+//! outer wall, or with crosses when they are inside the maze, and stairs with
+//! small arrows, so the result can be solved with a pencil.
+
+use std::{fmt::Write, str::FromStr};
+
+use crate::{
+    dims::*,
+    gameboard::{CellWall, Maze},
+};
+
+/// Millimeters to PDF points
+const MM_TO_PT: f32 = 72.0 / 25.4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Paper {
+    #[default]
+    A4,
+    A5,
+    Letter,
+}
+
+impl Paper {
+    /// Width and height in portrait orientation, in millimeters
+    pub fn size_mm(self) -> (f32, f32) {
+        match self {
+            Paper::A4 => (210.0, 297.0),
+            Paper::A5 => (148.0, 210.0),
+            Paper::Letter => (215.9, 279.4),
+        }
+    }
+}
+
+impl FromStr for Paper {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "a4" => Ok(Paper::A4),
+            "a5" => Ok(Paper::A5),
+            "letter" => Ok(Paper::Letter),
+            _ => Err(format!("unknown paper size `{}`, use A4, A5 or Letter", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintOptions {
+    pub paper: Paper,
+    /// Thickness of the walls, in millimeters
+    pub line_width: f32,
+    /// Space around the maze, in millimeters
+    pub margin: f32,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            paper: Paper::A4,
+            line_width: 0.8,
+            margin: 15.0,
+        }
+    }
+}
+
+/// Line between two points, in millimeters from the top left corner of the page
+type Line = ((f32, f32), (f32, f32));
+
+/// Page size and lines of a floor, ready to be written in any format
+struct Drawing {
+    page: (f32, f32),
+    line_width: f32,
+    lines: Vec<Line>,
+}
+
+impl Drawing {
+    fn new(
+        maze: &Maze,
+        floor: i32,
+        start: Dims3D,
+        goals: &[Dims3D],
+        options: &PrintOptions,
+    ) -> Self {
+        let Dims3D(w, h, _) = maze.size();
+        let marks: Vec<_> = std::iter::once(start)
+            .chain(goals.iter().copied())
+            .filter(|pos| pos.2 == floor && maze.is_in_bounds(*pos))
+            .collect();
+        let openings: Vec<_> = marks
+            .iter()
+            .filter_map(|pos| Some((*pos, Self::outer_wall(maze, *pos)?)))
+            .collect();
+        let has_wall = |pos, wall| Self::has_wall(maze, &openings, pos, wall);
+
+        // landscape for wide mazes
+        let (pw, ph) = options.paper.size_mm();
+        let page = if w > h { (ph, pw) } else { (pw, ph) };
+
+        let avail = (page.0 - options.margin * 2.0, page.1 - options.margin * 2.0);
+        let cell_size = (avail.0 / w as f32).min(avail.1 / h as f32);
+        let origin = (
+            (page.0 - cell_size * w as f32) / 2.0,
+            (page.1 - cell_size * h as f32) / 2.0,
+        );
+        let point = |x: f32, y: f32| (origin.0 + x * cell_size, origin.1 + y * cell_size);
+
+        let mut lines = vec![];
+
+        // horizontal walls, joined into runs
+        for y in 0..=h {
+            let mut run = None;
+            for x in 0..=w {
+                let wall = x < w && has_wall(Dims3D(x, y, floor), CellWall::Top);
+                match (wall, run) {
+                    (true, None) => run = Some(x),
+                    (false, Some(start)) => {
+                        lines.push((point(start as f32, y as f32), point(x as f32, y as f32)));
+                        run = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // vertical walls, joined into runs
+        for x in 0..=w {
+            let mut run = None;
+            for y in 0..=h {
+                let wall = y < h && has_wall(Dims3D(x, y, floor), CellWall::Left);
+                match (wall, run) {
+                    (true, None) => run = Some(y),
+                    (false, Some(start)) => {
+                        lines.push((point(x as f32, start as f32), point(x as f32, y as f32)));
+                        run = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // stairs, as arrows in the middle of the cell
+        for y in 0..h {
+            for x in 0..w {
+                let cell = maze.get_cell(Dims3D(x, y, floor)).unwrap();
+                let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+                for (wall, dir) in [(CellWall::Up, -1.0), (CellWall::Down, 1.0)] {
+                    if cell.get_wall(wall) {
+                        continue;
+                    }
+
+                    let tip = point(cx, cy + 0.25 * dir);
+                    lines.push((point(cx - 0.2, cy), tip));
+                    lines.push((point(cx + 0.2, cy), tip));
+                }
+            }
+        }
+
+        // start and goals inside the maze, as crosses
+        for pos in &marks {
+            if openings.iter().any(|(opened, _)| opened == pos) {
+                continue;
+            }
+
+            let (cx, cy) = (pos.0 as f32 + 0.5, pos.1 as f32 + 0.5);
+            lines.push((point(cx - 0.25, cy - 0.25), point(cx + 0.25, cy + 0.25)));
+            lines.push((point(cx - 0.25, cy + 0.25), point(cx + 0.25, cy - 0.25)));
+        }
+
+        Self {
+            page,
+            line_width: options.line_width,
+            lines,
+        }
+    }
+
+    /// Side of the outer wall next to the cell, `None` if it's not on the edge of the floor
+    ///
+    /// Left and right sides are preferred, so the corners open towards each other.
+    fn outer_wall(maze: &Maze, pos: Dims3D) -> Option<CellWall> {
+        [
+            CellWall::Left,
+            CellWall::Right,
+            CellWall::Top,
+            CellWall::Bottom,
+        ]
+        .into_iter()
+        .find(|wall| !maze.is_in_bounds(pos + wall.to_coord()))
+    }
+
+    /// Whether there is a wall on the `wall` side of `pos`, cells outside of the maze are allowed
+    ///
+    /// Outer wall is open at the `openings`, given by the cell inside of the maze.
+    fn has_wall(maze: &Maze, openings: &[(Dims3D, CellWall)], pos: Dims3D, wall: CellWall) -> bool {
+        // outer walls are looked up from the cell inside of the maze
+        let (pos, wall) = match maze.is_in_bounds(pos) {
+            true => (pos, wall),
+            false => (pos + wall.to_coord(), wall.reverse_wall()),
+        };
+
+        maze.is_in_bounds(pos)
+            && !openings.contains(&(pos, wall))
+            && maze.get_wall(pos, wall).unwrap()
+    }
+}
+
+/// Draws a floor of the maze as an SVG document, in real page size
+///
+/// `start` and `goals` are marked only when they are on the floor.
+pub fn to_svg(
+    maze: &Maze,
+    floor: i32,
+    start: Dims3D,
+    goals: &[Dims3D],
+    options: &PrintOptions,
+) -> String {
+    let drawing = Drawing::new(maze, floor, start, goals, options);
+    let (w, h) = drawing.page;
+
+    let mut path = String::new();
+    for ((x1, y1), (x2, y2)) in &drawing.lines {
+        write!(path, "M{:.2} {:.2}L{:.2} {:.2}", x1, y1, x2, y2).unwrap();
+    }
+
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}mm" height="{h}mm" viewBox="0 0 {w} {h}">"#,
+            r#"<rect width="100%" height="100%" fill="white"/>"#,
+            r#"<path d="{path}" stroke="black" stroke-width="{lw}" stroke-linecap="square" fill="none"/>"#,
+            "</svg>\n",
+        ),
+        w = w,
+        h = h,
+        path = path,
+        lw = drawing.line_width,
+    )
+}
+
+/// Draws a floor of the maze as a single page PDF document, see [`to_svg`]
+pub fn to_pdf(
+    maze: &Maze,
+    floor: i32,
+    start: Dims3D,
+    goals: &[Dims3D],
+    options: &PrintOptions,
+) -> Vec<u8> {
+    let drawing = Drawing::new(maze, floor, start, goals, options);
+    let (w, h) = (drawing.page.0 * MM_TO_PT, drawing.page.1 * MM_TO_PT);
+
+    // PDF has the origin in the bottom left corner
+    let mut content = format!("{:.2} w 2 J\n", drawing.line_width * MM_TO_PT);
+    for ((x1, y1), (x2, y2)) in &drawing.lines {
+        writeln!(
+            content,
+            "{:.2} {:.2} m {:.2} {:.2} l S",
+            x1 * MM_TO_PT,
+            h - y1 * MM_TO_PT,
+            x2 * MM_TO_PT,
+            h - y2 * MM_TO_PT,
+        )
+        .unwrap();
+    }
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Contents 4 0 R >>",
+            w, h
+        ),
+        format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        writeln!(pdf, "{} 0 obj\n{}\nendobj", i + 1, object).unwrap();
+    }
+
+    let xref = pdf.len();
+    writeln!(pdf, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1).unwrap();
+    for offset in offsets {
+        writeln!(pdf, "{:010} 00000 n ", offset).unwrap();
+    }
+    write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    )
+    .unwrap();
+
+    pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboard::algorithms::{MazeAlgorithm, RndKruskals};
+
+    const SIZE: Dims3D = Dims3D(5, 4, 2);
+
+    fn maze() -> Maze {
+        RndKruskals::generate_seeded(SIZE, false, 11)
+            .unwrap()
+            .wait()
+            .unwrap()
+    }
+
+    /// Walls of the first floor counted cell by cell, outer walls included
+    fn closed_walls(maze: &Maze) -> usize {
+        let Dims3D(w, h, _) = maze.size();
+        let inner = (0..h)
+            .flat_map(|y| (0..w).map(move |x| Dims3D(x, y, 0)))
+            .map(|pos| {
+                [
+                    (CellWall::Right, pos.0 + 1 < w),
+                    (CellWall::Bottom, pos.1 + 1 < h),
+                ]
+                .into_iter()
+                .filter(|(wall, inside)| *inside && maze.get_wall(pos, *wall).unwrap())
+                .count()
+            })
+            .sum::<usize>();
+        inner + 2 * (w + h) as usize
+    }
+
+    /// Total length of the walls in cells, the 5x4 maze is on a landscape A4 page
+    ///
+    /// Stairs and marks are drawn with diagonal lines, so they are skipped.
+    fn wall_units(lines: &[Line]) -> usize {
+        let cell = (267.0f32 / 5.0).min(180.0 / 4.0);
+        let length: f32 = lines
+            .iter()
+            .filter(|((x1, y1), (x2, y2))| x1 == x2 || y1 == y2)
+            .map(|((x1, y1), (x2, y2))| (x2 - x1).abs() + (y2 - y1).abs())
+            .sum();
+        (length / cell).round() as usize
+    }
+
+    /// Lines of the path of the SVG document
+    fn svg_lines(svg: &str) -> Vec<Line> {
+        let path = svg
+            .split(r#"d=""#)
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        path.split('M')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                let nums: Vec<f32> = segment
+                    .split([' ', 'L'])
+                    .map(|n| n.parse().unwrap())
+                    .collect();
+                ((nums[0], nums[1]), (nums[2], nums[3]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_line_per_closed_wall() {
+        let maze = maze();
+        let options = PrintOptions::default();
+        let (start, goal) = (Dims3D(0, 0, 0), Dims3D(4, 3, 1));
+
+        // goal is on the other floor, so only the start is open
+        let drawing = Drawing::new(&maze, 0, start, &[goal], &options);
+        assert_eq!(wall_units(&drawing.lines), closed_walls(&maze) - 1);
+
+        let drawing = Drawing::new(&maze, 0, start, &[Dims3D(4, 3, 0)], &options);
+        assert_eq!(wall_units(&drawing.lines), closed_walls(&maze) - 2);
+    }
+
+    #[test]
+    fn marks_inside_are_crossed() {
+        let maze = maze();
+        let options = PrintOptions::default();
+        let open = Drawing::new(&maze, 0, Dims3D(0, 0, 0), &[], &options);
+        let inside = Drawing::new(&maze, 0, Dims3D(0, 0, 0), &[Dims3D(2, 1, 0)], &options);
+
+        assert_eq!(inside.lines.len(), open.lines.len() + 2);
+        assert_eq!(Drawing::outer_wall(&maze, Dims3D(2, 1, 0)), None);
+        assert_eq!(
+            Drawing::outer_wall(&maze, Dims3D(4, 0, 0)),
+            Some(CellWall::Right)
+        );
+        assert_eq!(
+            Drawing::outer_wall(&maze, Dims3D(2, 3, 0)),
+            Some(CellWall::Bottom)
+        );
+    }
+
+    #[test]
+    fn svg_structure() {
+        let maze = maze();
+        let options = PrintOptions::default();
+        let goals = [Dims3D(4, 3, 0)];
+        let svg = to_svg(&maze, 0, Dims3D(0, 0, 0), &goals, &options);
+
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="297mm""#));
+        assert!(svg.ends_with("</svg>\n"));
+
+        let lines = svg_lines(&svg);
+        let drawing = Drawing::new(&maze, 0, Dims3D(0, 0, 0), &goals, &options);
+        assert_eq!(lines.len(), drawing.lines.len());
+        assert_eq!(wall_units(&lines), closed_walls(&maze) - 2);
+    }
+
+    #[test]
+    fn pdf_structure() {
+        let maze = maze();
+        let options = PrintOptions::default();
+        let goals = [Dims3D(4, 3, 0)];
+        let pdf = String::from_utf8(to_pdf(&maze, 0, Dims3D(0, 0, 0), &goals, &options)).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+
+        // startxref points to the table, which points to the objects
+        let trailer = pdf.rsplit("startxref\n").next().unwrap();
+        let xref: usize = trailer.lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n0 5\n"));
+        let offsets: Vec<usize> = pdf[xref..]
+            .lines()
+            .skip(3)
+            .take(4)
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        for (i, offset) in offsets.into_iter().enumerate() {
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+
+        let (header, stream) = pdf.split_once("stream\n").unwrap();
+        let length: usize = header.rsplit("/Length ").next().unwrap()[..]
+            .split(' ')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(stream[length..].starts_with("endstream"));
+
+        let drawing = Drawing::new(&maze, 0, Dims3D(0, 0, 0), &goals, &options);
+        assert_eq!(
+            stream[..length].matches(" l S").count(),
+            drawing.lines.len()
+        );
+    }
+}
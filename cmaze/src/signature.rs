@@ -0,0 +1,220 @@
+//! Integrity signatures of finished runs
+//!
+//! A signature is an HMAC-SHA256 of the replay, so a result can't be edited without
+//! signing it again. The key is built into the game and the game is open source, so anyone
+//! can make a build signing whatever they want. This only stops results which were
+//! obviously tampered with, the server should always check the replay itself too, which
+//! [`verify`] does.
+
+use std::time::Duration;
+
+use hmac_sha256::HMAC;
+use thiserror::Error;
+
+use crate::{
+    game::{Replay, RunningGame},
+    gameboard::{
+        algorithms::{MazeAlgorithm, RndKruskals},
+        DecodeError,
+    },
+};
+
+/// Version of the signed data, changed when the signed fields change
+pub const SIGNATURE_VERSION: u8 = 2;
+
+/// Key of the official builds is set by the `TMAZE_SIGNING_KEY` variable at build time
+pub const KEY: &[u8] = match option_env!("TMAZE_SIGNING_KEY") {
+    Some(key) => key.as_bytes(),
+    None => b"tmaze-unofficial-build",
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("signature doesn't match the replay")]
+    BadSignature,
+    #[error("invalid replay: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("step {0} was made before the previous one")]
+    TimeGoesBack(usize),
+    #[error("step {0} goes through a wall")]
+    ThroughWall(usize),
+    #[error("not all goals were reached")]
+    NotFinished,
+}
+
+/// Result of a run, as proven by its replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verified {
+    pub moves: usize,
+    pub time: Duration,
+}
+
+/// Hex encoded signature of the replay
+pub fn sign(replay: &Replay, key: &[u8]) -> String {
+    HMAC::mac(signed_data(replay), key)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Checks the signature and plays the replay back, to see it really finishes the game
+pub fn verify(replay: &Replay, signature: &str, key: &[u8]) -> Result<Verified, VerifyError> {
+    let expected = decode_hex(signature).ok_or(VerifyError::BadSignature)?;
+    if !HMAC::verify(signed_data(replay), key, &expected) {
+        return Err(VerifyError::BadSignature);
+    }
+
+    // generator isn't used, the maze is in the replay
+    let mut game = RunningGame::from_replay(replay, RndKruskals::generate_seeded)?;
+    let steps = replay.steps()?;
+
+    let mut last = Duration::ZERO;
+    for (i, (time, dir)) in steps.into_iter().enumerate() {
+        if time < last {
+            return Err(VerifyError::TimeGoesBack(i));
+        }
+        if !game.replay_step(dir, time) {
+            return Err(VerifyError::ThroughWall(i));
+        }
+        last = time;
+    }
+
+    if game.get_remaining_goals() > 0 {
+        return Err(VerifyError::NotFinished);
+    }
+
+    Ok(Verified {
+        moves: replay.times.len(),
+        time: replay.duration(),
+    })
+}
+
+/// Fields of the replay in a stable binary form, lengths are included so fields can't
+/// be shifted into each other
+fn signed_data(replay: &Replay) -> Vec<u8> {
+    let mode = replay.game_mode;
+    let mut data = vec![SIGNATURE_VERSION];
+
+    data.extend(replay.seed.to_le_bytes());
+    for dim in [mode.size.0, mode.size.1, mode.size.2] {
+        data.extend(dim.to_le_bytes());
+    }
+    data.extend([mode.is_tower as u8, mode.goals as u8, mode.objective as u8]);
+    data.extend([mode.braid]);
+    data.extend(mode.braid_floors.to_le_bytes());
+    // only with items, so the signatures made before them still verify
+    if !mode.items.is_empty() {
+        data.extend(mode.items.coins.to_le_bytes());
+        data.extend([mode.items.door as u8]);
+    }
+
+    for bytes in [&replay.maze, &replay.moves] {
+        data.extend((bytes.len() as u64).to_le_bytes());
+        data.extend(bytes);
+    }
+
+    data.extend((replay.times.len() as u64).to_le_bytes());
+    for time in &replay.times {
+        data.extend(time.to_le_bytes());
+    }
+
+    data
+}
+
+fn decode_hex(text: &str) -> Option<[u8; 32]> {
+    if text.len() != 64 || !text.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dims::*,
+        game::{GameProperities, GoalObjective, GoalPlacement, ItemPlacement, MoveMode},
+        gameboard::{
+            encoding::{decode_moves, encode_moves},
+            CellWall, Compression,
+        },
+    };
+
+    fn finished_replay() -> Replay {
+        let props = GameProperities {
+            game_mode: GameMode {
+                size: Dims3D(6, 4, 1),
+                is_tower: false,
+                goals: GoalPlacement::Corner,
+                objective: GoalObjective::ReachAny,
+                braid: 0,
+                braid_floors: 0,
+                items: ItemPlacement::default(),
+            },
+            generator: RndKruskals::generate_seeded,
+            seed: Some(3),
+        };
+        let mut game = RunningGame::new_threaded(props).unwrap().wait().unwrap();
+        game.start().unwrap();
+
+        let goal = game.get_goals()[0];
+        let path = game.get_maze().solve(Dims3D(0, 0, 0), goal).unwrap();
+        for pair in path.windows(2) {
+            let dir = CellWall::get_in_order()
+                .into_iter()
+                .find(|dir| pair[0] + dir.to_coord() == pair[1])
+                .unwrap();
+            game.move_player(dir, MoveMode::Slow, false).unwrap();
+        }
+
+        game.replay()
+    }
+
+    #[test]
+    fn signed_replay_verifies() {
+        let replay = finished_replay();
+        let signature = sign(&replay, KEY);
+
+        let verified = verify(&replay, &signature, KEY).unwrap();
+        assert_eq!(verified.moves, replay.times.len());
+        assert_eq!(
+            verify(&replay, &signature, b"other key"),
+            Err(VerifyError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let replay = finished_replay();
+        let signature = sign(&replay, KEY);
+
+        let mut edited = replay.clone();
+        edited.times[0] += 1;
+        assert_eq!(
+            verify(&edited, &signature, KEY),
+            Err(VerifyError::BadSignature)
+        );
+        edited.seed += 1;
+        edited.times[0] -= 1;
+        assert_eq!(
+            verify(&edited, &signature, KEY),
+            Err(VerifyError::BadSignature)
+        );
+
+        // signed again, but the run doesn't end in the goal
+        let mut unfinished = replay.clone();
+        unfinished.times.pop();
+        let dirs = decode_moves(&unfinished.moves).unwrap();
+        unfinished.moves = encode_moves(&dirs[..dirs.len() - 1], Compression::Rle);
+        let signature = sign(&unfinished, KEY);
+        assert_eq!(
+            verify(&unfinished, &signature, KEY),
+            Err(VerifyError::NotFinished)
+        );
+    }
+}
@@ -79,7 +79,7 @@ fn main() -> Result<()> {
 
     eprintln!("Creating destination directory at {}", dest.display());
 
-    fs::create_dir_all(&dest)?;
+    fs::create_dir_all(dest)?;
 
     for entry in WalkDir::new(&source) {
         let entry = entry?;
@@ -133,7 +133,7 @@ fn main() -> Result<()> {
             }),
 
             // binding syntax just for clarity
-            ext @ _ => match unsupported_files_action {
+            ext => match unsupported_files_action {
                 UnsupportedFilesAction::Ignore => {
                     eprintln!("- Unsupported file, ignoring");
                     continue;
@@ -196,13 +196,7 @@ mod audio {
             sample_rate: reader.spec().sample_rate,
         };
 
-        Ok((
-            header,
-            reader
-                .into_samples()
-                .map(|sample| sample)
-                .collect::<Result<_, _>>()?,
-        ))
+        Ok((header, reader.into_samples().collect::<Result<_, _>>()?))
     }
 
     pub(crate) fn save_mp3(path: &Path, header: Header, samples: Vec<i16>) -> Result<()> {
@@ -218,7 +212,7 @@ mod audio {
             .map_err(|e| anyhow::anyhow!("Unsupported number of channels: {:?}", e))?;
 
         encoder
-            .set_sample_rate(header.sample_rate as u32)
+            .set_sample_rate(header.sample_rate)
             .map_err(|e| anyhow::anyhow!("Unsupported sample rate: {:?}", e))?;
 
         encoder
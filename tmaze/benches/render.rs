@@ -0,0 +1,77 @@
+//! Benchmarks of the renderer, run with `cargo bench -p tmaze`.
+//!
+//! To compare two versions, save a baseline for each of them with
+//! `cargo bench -p tmaze -- --save-baseline <name>` and diff them with
+//! `scripts/bench_compare.nu`.
+
+use cmaze::dims::Dims;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use tmaze::{
+    renderer::{Cell, Frame},
+    settings::theme::{Color, Style},
+};
+
+/// Large terminal, where the blending is slow enough to notice
+const SIZE: Dims = Dims(240, 70);
+
+/// Frame like a drawn maze, with a few styles repeated over the whole screen
+fn maze_frame() -> Frame {
+    let styles = [
+        Style::fg(Color::RGB(200, 200, 200)),
+        Style::fg(Color::RGB(90, 90, 90)),
+        Style {
+            fg: Some(Color::RGB(255, 220, 0)),
+            bg: Some(Color::RGB(20, 20, 40)),
+            ..Style::default()
+        },
+    ];
+
+    let mut frame = Frame::new(SIZE);
+    for y in 0..SIZE.1 {
+        for x in 0..SIZE.0 {
+            let style = styles[((x / 3 + y) % 3) as usize];
+            frame.set(Dims(x, y), Cell::styled('─', style));
+        }
+    }
+    frame
+}
+
+/// Every cell with its own color, the worst case for memoizing
+fn gradient_frame() -> Frame {
+    let mut frame = Frame::new(SIZE);
+    for y in 0..SIZE.1 {
+        for x in 0..SIZE.0 {
+            let color = Color::RGB(x as u8, y as u8, (x * y) as u8);
+            frame.set(Dims(x, y), Cell::styled('#', Style::bg(color)));
+        }
+    }
+    frame
+}
+
+fn blend(c: &mut Criterion) {
+    let mut group = c.benchmark_group("blend");
+    let overlay = Style {
+        fg: Some(Color::RGB(0, 0, 0)),
+        bg: Some(Color::RGB(0, 0, 0)),
+        ..Style::default()
+    }
+    .to_cross();
+
+    let scenes = [
+        ("maze", maze_frame as fn() -> Frame),
+        ("gradient", gradient_frame),
+    ];
+    for (name, scene) in scenes {
+        group.bench_function(BenchmarkId::new("frame", name), |b| {
+            b.iter_batched_ref(
+                scene,
+                |frame| frame.blend(overlay, 0.6),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, blend);
+criterion_main!(benches);
@@ -60,7 +60,7 @@ fn main() {
     let menu = menu::Menu::new(menu_config).into_activity();
     app.activities_mut().push(menu);
 
-    app.data_mut().play_bgm(track::MusicTrack::Menu);
+    app.data_mut().play_bgm(track::MusicContext::Menu);
 
     app.run();
 }
@@ -30,7 +30,7 @@ impl ActivityHandler for MyActivity {
                     }
 
                     match code {
-                        crossterm::event::KeyCode::Char(ch) if ch as u32 % 2 == 0 => {
+                        crossterm::event::KeyCode::Char(ch) if (ch as u32).is_multiple_of(2) => {
                             log::warn!("Even key pressed: '{ch}'");
                         }
                         crossterm::event::KeyCode::Char(ch) if ch as u32 % 2 == 1 => {
@@ -0,0 +1,154 @@
+//! List of the achievements, unlocked ones with their date, locked ones with the progress
+
+use std::io;
+
+use cmaze::dims::*;
+use crossterm::event::{Event as TermEvent, KeyEvent};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    data::{
+        achievements::{Goal, ACHIEVEMENTS},
+        SaveData,
+    },
+    helpers::is_release,
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+    ui::{center_box_in_screen, draw_box, Hint, Screen},
+};
+
+use super::{app::AppData, Activity, ActivityHandler, Change, Event};
+
+struct Entry {
+    name: &'static str,
+    description: &'static str,
+    /// Date of unlocking or the progress
+    status: String,
+    unlocked: bool,
+}
+
+pub struct AchievementsScreen {
+    entries: Vec<Entry>,
+}
+
+impl AchievementsScreen {
+    pub fn new(save: &SaveData) -> Self {
+        let entries = ACHIEVEMENTS
+            .iter()
+            .map(|achievement| {
+                let unlocked = save.achievement_unlocked(achievement.id);
+                let status = match (unlocked, achievement.goal) {
+                    (Some(date), _) => date.format("%Y-%m-%d").to_string(),
+                    (None, Goal::Wins(count)) => format!("{}/{}", save.wins().min(count), count),
+                    (None, Goal::Run(_)) => "locked".to_string(),
+                };
+
+                Entry {
+                    name: achievement.name,
+                    description: achievement.description,
+                    status,
+                    unlocked: unlocked.is_some(),
+                }
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    pub fn new_activity(save: &SaveData) -> Activity {
+        Activity::new_base_boxed("achievements", Self::new(save))
+    }
+
+    fn size(&self) -> Dims {
+        let width = self
+            .entries
+            .iter()
+            .map(|e| {
+                e.description
+                    .width()
+                    .max(e.name.width() + e.status.width() + 4)
+            })
+            .max()
+            .unwrap_or(0);
+        Dims(width as i32 + 6, self.entries.len() as i32 * 2 + 3)
+    }
+}
+
+impl ActivityHandler for AchievementsScreen {
+    fn update(&mut self, events: Vec<Event>, _: &mut AppData) -> Option<Change> {
+        for event in events {
+            match event {
+                Event::Term(TermEvent::Key(KeyEvent { kind, .. })) if !is_release(kind) => {
+                    return Some(Change::pop_top());
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![Hint::new("Any key", "back")]
+    }
+}
+
+impl Screen for AchievementsScreen {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let [border, title, text, unlocked, locked] = theme.extract([
+            "ui.achievements.border",
+            "ui.achievements.title",
+            "ui.achievements.text",
+            "ui.achievements.unlocked",
+            "ui.achievements.locked",
+        ]);
+
+        let size = self.size();
+        let pos = center_box_in_screen(size, frame.size);
+
+        frame.fill_rect(pos, size, Cell::styled(' ', text));
+        draw_box(frame, pos, size, border);
+
+        let count = self.entries.iter().filter(|e| e.unlocked).count();
+        let header = format!(" Achievements - {}/{} ", count, self.entries.len());
+        frame.draw(pos + Dims(3, 0), header.as_str(), title);
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let line = pos + Dims(2, 2 + i as i32 * 2);
+            let (mark, style) = match entry.unlocked {
+                true => ('★', unlocked),
+                false => ('☆', locked),
+            };
+
+            frame.draw(line, mark, style);
+            frame.draw(line + Dims(2, 0), entry.name, style);
+            let status_x = size.0 - 4 - entry.status.width() as i32;
+            frame.draw(line + Dims(status_x, 0), entry.status.as_str(), style);
+            frame.draw(line + Dims(2, 1), entry.description, text);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn achievements_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("ui.achievements.border", "border")
+        .desc("Border of the achievements")
+        .link("ui.achievements.title", "text")
+        .desc("Title of the achievements")
+        .link("ui.achievements.text", "text")
+        .desc("Descriptions of achievements")
+        .link("ui.achievements.unlocked", "highlight")
+        .desc("Achievements which are unlocked")
+        .link("ui.achievements.locked", "dim")
+        .desc("Achievements which are still locked");
+
+    resolver
+}
@@ -3,7 +3,9 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use crate::ui::Screen;
+use cmaze::dims::Dims;
+
+use crate::ui::{Hint, Screen};
 
 use super::{app::AppData, event::Event};
 
@@ -141,6 +143,11 @@ impl Activities {
         self.activities.last_mut()
     }
 
+    /// Activities from the bottom of the stack
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Activity> + ExactSizeIterator {
+        self.activities.iter()
+    }
+
     pub fn len(&self) -> usize {
         self.activities.len()
     }
@@ -148,6 +155,14 @@ impl Activities {
     pub fn is_empty(&self) -> bool {
         self.activities.is_empty()
     }
+
+    /// Makes all activities lay themselves out again before their next update, like after the
+    /// terminal was resized, see [`ActivityHandler::relayout`]
+    pub fn invalidate_layout(&mut self) {
+        for activity in &mut self.activities {
+            activity.laid_out = None;
+        }
+    }
 }
 
 pub struct Activity {
@@ -155,6 +170,8 @@ pub struct Activity {
     name: String,
 
     handler: Box<dyn ActivityHandler>,
+    /// Screen size of the last layout, `None` when it's invalid
+    laid_out: Option<Dims>,
 }
 
 impl Activity {
@@ -167,6 +184,7 @@ impl Activity {
             source: source.into(),
             name: name.into(),
             handler,
+            laid_out: None,
         }
     }
 
@@ -196,6 +214,14 @@ impl Activity {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Lays the activity out for the screen, if it wasn't already
+    pub fn ensure_layout(&mut self, size: Dims) {
+        if self.laid_out != Some(size) {
+            self.handler.relayout(size);
+            self.laid_out = Some(size);
+        }
+    }
 }
 
 impl Deref for Activity {
@@ -213,8 +239,32 @@ impl DerefMut for Activity {
 }
 
 pub trait ActivityHandler {
+    /// Advances the simulation by one [`TICK`](super::app::TICK)
+    ///
+    /// Called only on the active activity, zero or more times before each
+    /// [`ActivityHandler::update`], so timers and animations run at the same
+    /// speed no matter how fast frames are drawn.
+    fn tick(&mut self, _data: &mut AppData) {}
+
     #[must_use]
     fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change>;
 
     fn screen(&self) -> &dyn Screen;
+
+    /// Recomputes the layout for the screen of the size
+    ///
+    /// Called before the first update and whenever the activity becomes active after its
+    /// layout was invalidated, see [`Activities::invalidate_layout`]. Activities which lay
+    /// themselves out on every update don't need it.
+    fn relayout(&mut self, _size: Dims) {}
+
+    /// Name of the activity shown to the player, see [`crate::ui::breadcrumbs`]
+    fn title(&self) -> Option<&str> {
+        None
+    }
+
+    /// Most useful keys of the activity, the most relevant first, see [`crate::ui::footer`]
+    fn hints(&self) -> Vec<Hint> {
+        Vec::new()
+    }
 }
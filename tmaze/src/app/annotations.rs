@@ -0,0 +1,195 @@
+//! Annotations of the maze board, like markers, highlights and text badges
+//!
+//! Overlays don't draw into the viewport themselves, they create [`Annotation`]s, which are
+//! then drawn together by [`draw_annotations`], lower layers first. Annotations derived from
+//! the game state, like the path, are created for every frame, others are kept in
+//! [`Annotations`] with their owner and removed once their lifetime runs out.
+
+use std::time::Duration;
+
+use cmaze::{
+    dims::*,
+    gameboard::{
+        CellWall::{Down, Up},
+        Maze,
+    },
+};
+
+use crate::{
+    helpers::{maze2screen_3d, Zoom},
+    renderer::Frame,
+    settings::theme::Theme,
+};
+
+/// Z-order of the annotations, higher layers are drawn over the lower ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// Visited places and the path of the player
+    Trail,
+    Goal,
+    Hint,
+    Marker,
+    Top,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mark {
+    /// Character drawn over the board, stairs are only recolored so they stay visible
+    Marker(char),
+    /// Only changes the color of what's already drawn
+    Highlight,
+    /// Text starting at the position
+    Badge(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Position on the board, walls between the cells included
+    pub pos: Dims3D,
+    pub mark: Mark,
+    /// Theme key of the style
+    pub style: &'static str,
+    pub layer: Layer,
+}
+
+impl Annotation {
+    /// Annotation of a maze cell
+    pub fn cell(cell: Dims3D, mark: Mark, style: &'static str, layer: Layer) -> Self {
+        Self::board(maze2screen_3d(cell), mark, style, layer)
+    }
+
+    pub fn board(pos: Dims3D, mark: Mark, style: &'static str, layer: Layer) -> Self {
+        Self {
+            pos,
+            mark,
+            style,
+            layer,
+        }
+    }
+
+    fn is_on_stairs(&self, maze: &Maze) -> bool {
+        let Dims3D(x, y, floor) = self.pos;
+        if x % 2 == 0 || y % 2 == 0 {
+            return false;
+        }
+
+        maze.get_cell(Dims3D((x - 1) / 2, (y - 1) / 2, floor))
+            .is_some_and(|cell| !cell.get_wall(Up) || !cell.get_wall(Down))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifetime {
+    /// Until removed by its owner
+    Forever,
+    /// Removed after the time runs out
+    For(Duration),
+}
+
+/// Annotations kept between frames, grouped by their owner
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    items: Vec<(&'static str, Annotation, Lifetime)>,
+}
+
+impl Annotations {
+    pub fn add(&mut self, owner: &'static str, annotation: Annotation, lifetime: Lifetime) {
+        self.items.push((owner, annotation, lifetime));
+    }
+
+    /// Replaces all the annotations of the owner
+    pub fn replace(
+        &mut self,
+        owner: &'static str,
+        annotations: impl IntoIterator<Item = Annotation>,
+        lifetime: Lifetime,
+    ) {
+        self.clear(owner);
+        for annotation in annotations {
+            self.add(owner, annotation, lifetime);
+        }
+    }
+
+    pub fn clear(&mut self, owner: &'static str) {
+        self.items.retain(|(o, _, _)| *o != owner);
+    }
+
+    pub fn has(&self, owner: &'static str) -> bool {
+        self.items.iter().any(|(o, _, _)| *o == owner)
+    }
+
+    /// Advances the time, removes annotations with expired lifetime
+    pub fn tick(&mut self, delta: Duration) {
+        self.items.retain_mut(|(_, _, lifetime)| match lifetime {
+            Lifetime::Forever => true,
+            Lifetime::For(left) => {
+                *left = left.saturating_sub(delta);
+                !left.is_zero()
+            }
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.items.iter().map(|(_, annotation, _)| annotation)
+    }
+}
+
+/// Draws annotations of the floor, ordered by their layers
+///
+/// Annotations of the same layer are drawn in the given order. Their positions are converted to
+/// the zoom of the board.
+pub fn draw_annotations<'a>(
+    frame: &mut Frame,
+    maze_pos: Dims,
+    zoom: Zoom,
+    floor: i32,
+    maze: &Maze,
+    theme: &Theme,
+    annotations: impl IntoIterator<Item = &'a Annotation>,
+) {
+    let mut annotations: Vec<_> = annotations
+        .into_iter()
+        .filter(|annotation| annotation.pos.2 == floor)
+        .collect();
+    annotations.sort_by_key(|annotation| annotation.layer);
+
+    for annotation in annotations {
+        let pos = maze_pos + zoom.board_pos(Dims(annotation.pos.0, annotation.pos.1));
+        let style = theme[annotation.style];
+
+        match &annotation.mark {
+            Mark::Marker(ch) if !annotation.is_on_stairs(maze) => frame.draw(pos, *ch, style),
+            Mark::Marker(_) | Mark::Highlight => {
+                if let Some(content) = frame.get_mut(pos).and_then(|cell| cell.content_mut()) {
+                    content.style.foreground_color = style.to_cross().foreground_color;
+                }
+            }
+            Mark::Badge(text) => frame.draw(pos, text.as_str(), style),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifetimes() {
+        let mark = |x| Annotation::cell(Dims3D(x, 0, 0), Mark::Highlight, "text", Layer::Top);
+
+        let mut annotations = Annotations::default();
+        annotations.add("hint", mark(0), Lifetime::For(Duration::from_secs(1)));
+        annotations.add("marker", mark(1), Lifetime::Forever);
+        annotations.tick(Duration::from_millis(500));
+        assert_eq!(annotations.iter().count(), 2);
+
+        annotations.tick(Duration::from_millis(500));
+        assert!(!annotations.has("hint"));
+        assert!(annotations.has("marker"));
+
+        annotations.replace("marker", [mark(2), mark(3)], Lifetime::Forever);
+        assert_eq!(annotations.iter().count(), 2);
+        annotations.clear("marker");
+        assert_eq!(annotations.iter().count(), 0);
+    }
+}
@@ -1,39 +1,81 @@
-use std::time::{Duration, Instant};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 
 use cmaze::dims::*;
 
-use crossterm::event::{read, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{read, Event as TermEvent, KeyCode, KeyEvent, KeyEventKind};
 
 use crate::{
     data::SaveData,
+    error::AppError,
     helpers::{constants::paths::settings_path, on_off},
     logging::{self, AppLogger, LoggerOptions, UiLogs},
-    renderer::{drawable::Drawable, Cell, Renderer},
+    renderer::{drawable::Drawable, Cell, Frame, Renderer},
     settings::{
-        theme::{Theme, ThemeResolver},
+        ambience::AmbienceVariant,
+        editor,
+        theme::{Theme, ThemeDefinition, ThemeResolver},
         Settings,
     },
     ui,
 };
 
 #[cfg(feature = "sound")]
-use crate::sound::{track::MusicTrack, SoundPlayer};
-
-#[cfg(feature = "sound")]
-use rodio::Source;
+use crate::sound::{
+    track::{MusicContext, Track},
+    SoundPlayer,
+};
+#[cfg(feature = "http")]
+use {
+    crate::http::{Command, HttpServer},
+    std::sync::mpsc::Sender,
+};
 
 use super::{
+    achievements,
     activity::{Activities, Activity, ActivityResult, Change},
+    cache::{self, Cache},
+    campaign, end_game,
     event::Event,
-    game,
+    floor_jump, floor_overview, game,
     jobs::Qer,
+    maze_diff,
+    modes::GameModeRegistry,
+    narration::{self, Narrator},
+    stats,
+    touch_prompt::TouchPrompt,
     Jobs,
 };
 
+/// How often are periodic tasks run, like saving changed settings
+const PERIODIC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often is the theme file checked for changes, so its edits show up right away
+const THEME_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Length of one simulation step, see [`ActivityHandler::tick`](super::ActivityHandler::tick)
+pub const TICK: Duration = Duration::from_millis(50);
+/// Most ticks run before a frame, the simulation slows down instead of
+/// freezing the app when it can't keep up
+const MAX_TICKS_PER_FRAME: u32 = 10;
+
 pub struct App {
     renderer: Renderer,
     activities: Activities,
     data: AppData,
+    last_periodic: Instant,
+    last_theme_check: Instant,
+    last_tick: Instant,
+    /// Time not yet simulated, always less than [`TICK`] after ticks are run
+    tick_lag: Duration,
+    /// Trail of the menus drawn at the top of the screen, see [`ui::breadcrumbs`]
+    breadcrumbs: ui::Breadcrumbs,
+    /// Screenshots asked for by the HTTP server, taken when the next frame is drawn
+    #[cfg(feature = "http")]
+    screenshots: Vec<Sender<Vec<String>>>,
 }
 
 pub struct AppData {
@@ -43,13 +85,37 @@ pub struct AppData {
     pub screen_size: Dims,
     pub theme: Theme,
     pub logs: UiLogs,
+    pub modes: GameModeRegistry,
+    /// How far is the current frame between the last and the next tick, from 0 to 1
+    ///
+    /// Used to interpolate positions, which are updated only in ticks.
+    pub tick_alpha: f32,
+    /// Expensive results shared between activities
+    pub cache: Cache,
+    /// Notifications drawn over all activities
+    pub toasts: ui::Toasts,
+    /// Lines for screen readers in the accessibility mode, see [`AppData::narrate`]
+    pub narrator: Narrator,
     jobs: Jobs,
     app_start: Instant,
+    ambience: Option<AmbienceVariant>,
+    /// Terminal bell rings after the next frame is shown
+    bell: bool,
+    /// Errors shown before the next update, see [`AppData::report`]
+    errors: Vec<AppError>,
+    /// [`Settings::overlays_revision`] of the last [`AppData::apply_settings`]
+    applied_overlays: u64,
+    /// File of the loaded theme with its modification time, see [`AppData::reload_theme_if_changed`]
+    theme_file: Option<(PathBuf, Option<SystemTime>)>,
+
+    /// Server with the state of the game, if it's enabled, see [`crate::http`]
+    #[cfg(feature = "http")]
+    pub http: Option<HttpServer>,
 
     #[cfg(feature = "sound")]
     pub sound_player: SoundPlayer,
     #[cfg(feature = "sound")]
-    bgm_track: Option<MusicTrack>,
+    bgm_context: Option<MusicContext>,
 }
 
 impl AppData {
@@ -57,24 +123,113 @@ impl AppData {
         self.app_start.elapsed()
     }
 
+    /// Says the text to screen readers, only in the accessibility mode
+    pub fn narrate(&mut self, text: impl Into<String>) {
+        if self.settings.get_accessible() {
+            self.narrator.say(text);
+        }
+    }
+
     #[cfg(feature = "sound")]
-    pub fn play_bgm(&mut self, track: MusicTrack) {
-        if let Some(prev_track) = self.bgm_track {
-            if prev_track == track {
-                return;
-            }
+    pub fn play_bgm(&mut self, context: MusicContext) {
+        if self.bgm_context == Some(context) {
+            return;
+        }
+
+        self.sound_player.set_volume(self.music_volume());
+
+        self.bgm_context = Some(context);
+        self.sound_player.play_playlist(context);
+    }
+
+    /// Rings the terminal bell after the frame is shown
+    pub fn ring_bell(&mut self) {
+        self.bell = true;
+    }
+
+    /// Shows the error in a popup over the active activity, for errors which happen outside of
+    /// activities, like in jobs or when settings are applied
+    pub fn report(&mut self, err: AppError) {
+        self.errors.push(err);
+    }
+
+    /// Plays a short sound over the music, if audio is enabled
+    #[cfg(feature = "sound")]
+    pub fn play_sound(&self, track: Track) {
+        if self.settings.get_enable_audio() {
+            self.sound_player.play_sound(track);
         }
+    }
 
-        let volume = if self.settings.get_enable_audio() && self.settings.get_enable_music() {
+    #[cfg(feature = "sound")]
+    fn music_volume(&self) -> f32 {
+        if self.settings.get_enable_audio() && self.settings.get_enable_music() {
             self.settings.get_audio_volume() * self.settings.get_music_volume()
         } else {
             0.0
+        }
+    }
+
+    /// Re-apply settings which are cached outside of [`Settings`]
+    ///
+    /// Should be called after many settings change at once, e.g. when
+    /// switching profiles.
+    pub fn apply_settings(&mut self) {
+        self.applied_overlays = self.settings.overlays_revision();
+        let theme = self.settings.load_theme().unwrap_or_else(|err| {
+            self.errors.push(err);
+            ThemeDefinition::builtin()
+        });
+        self.theme = init_theme_resolver().resolve(&theme);
+        self.theme_file = theme_file_state(&self.settings);
+        *self.logs.min_level.write().unwrap() = if self.use_data.show_debug {
+            self.settings.get_debug_logging_level()
+        } else {
+            self.settings.get_logging_level()
         };
-        self.sound_player.set_volume(volume);
 
-        self.bgm_track = Some(track);
-        let track = track.get_track().repeat_infinite();
-        self.sound_player.play_track(Box::new(track));
+        #[cfg(feature = "sound")]
+        self.sound_player.set_volume(self.music_volume());
+    }
+
+    /// Loads the theme again if its file changed since it was loaded
+    ///
+    /// Returns `true` if it was reloaded. Invalid file is reported and the current theme is kept
+    /// until the file changes again.
+    pub fn reload_theme_if_changed(&mut self) -> bool {
+        let state = theme_file_state(&self.settings);
+        if state == self.theme_file {
+            return false;
+        }
+        self.theme_file = state;
+
+        match self.settings.load_theme() {
+            Ok(theme) => {
+                self.theme = init_theme_resolver().resolve(&theme);
+                true
+            }
+            Err(err) => {
+                log::error!("Theme file changed, but could not be loaded: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Re-apply settings if the ambience variant changed since the last call
+    pub fn update_ambience(&mut self) {
+        let variant = self.settings.get_ambience_variant();
+        if variant == self.ambience {
+            return;
+        }
+
+        log::info!("Ambience changed");
+        self.ambience = variant;
+        self.apply_settings();
+
+        #[cfg(feature = "sound")]
+        if let Some(context) = self.bgm_context.take() {
+            self.play_bgm(context);
+        }
     }
 
     pub fn queuer(&self) -> Qer {
@@ -98,6 +253,45 @@ impl App {
         s
     }
 
+    /// Create a new headless app with a base activity
+    ///
+    /// Same as [`App::new`], but the app never touches the terminal,
+    /// see [`App::empty_headless`].
+    pub fn new_headless(base_activity: Activity, size: Dims, read_only: bool) -> Self {
+        let mut s = Self::empty_headless(size, read_only);
+        s.activities.push(base_activity);
+        s
+    }
+
+    /// Create a new headless app with the given settings and save data
+    ///
+    /// Same as [`App::new_headless`], but nothing is loaded from the files of the user.
+    pub fn new_headless_with(
+        base_activity: Activity,
+        size: Dims,
+        settings: Settings,
+        save: SaveData,
+        read_only: bool,
+    ) -> Self {
+        let renderer = Renderer::new_headless(size);
+        let mut s = Self::with_data(renderer, settings, save, vec![], read_only);
+        s.activities.push(base_activity);
+        s
+    }
+
+    /// Headless app with the default settings and empty save data, neither is ever written
+    #[cfg(test)]
+    pub(crate) fn new_headless_default(base_activity: Activity, size: Dims) -> Self {
+        let settings = Settings::fallback(PathBuf::new());
+        Self::new_headless_with(
+            base_activity,
+            size,
+            settings,
+            SaveData::new(PathBuf::new()),
+            true,
+        )
+    }
+
     /// Create a new app with no activities
     ///
     /// This method intializes all of the needed components of the app.
@@ -109,10 +303,39 @@ impl App {
     /// - initializes the job queue,
     pub fn empty(read_only: bool) -> Self {
         let renderer = Renderer::new().expect("failed to create renderer");
-        let activities = Activities::empty();
+        Self::with_renderer(renderer, read_only)
+    }
+
+    /// Create a new app with no activities, which renders into an off-screen buffer
+    ///
+    /// Terminal is left untouched and no sound is played. Use
+    /// [`App::run_headless`] to run it.
+    pub fn empty_headless(size: Dims, read_only: bool) -> Self {
+        Self::with_renderer(Renderer::new_headless(size), read_only)
+    }
+
+    fn with_renderer(renderer: Renderer, read_only: bool) -> Self {
+        let mut errors = vec![];
+        let settings = Settings::load(settings_path(), read_only).unwrap_or_else(|err| {
+            errors.push(err);
+            Settings::fallback(settings_path())
+        });
+        let save = SaveData::load().unwrap_or_else(|err| {
+            errors.push(AppError::SaveData(err));
+            SaveData::load_or()
+        });
+
+        Self::with_data(renderer, settings, save, errors, read_only)
+    }
 
-        let settings = Settings::load(settings_path(), read_only).expect("failed to load settings");
-        let save = SaveData::load().expect("failed to load save data");
+    fn with_data(
+        renderer: Renderer,
+        settings: Settings,
+        save: SaveData,
+        mut errors: Vec<AppError>,
+        read_only: bool,
+    ) -> Self {
+        let activities = Activities::empty();
         let use_data = AppStateData::default();
         let jobs = Jobs::new();
         let app_start = Instant::now();
@@ -120,7 +343,10 @@ impl App {
 
         log::info!("Loading theme");
         let resolver = init_theme_resolver();
-        let theme_def = settings.get_theme();
+        let theme_def = settings.load_theme().unwrap_or_else(|err| {
+            errors.push(err);
+            ThemeDefinition::builtin()
+        });
         let theme = resolver.resolve(&theme_def);
 
         let (logger, logs) = AppLogger::new_with_options(
@@ -132,13 +358,41 @@ impl App {
         logger.init();
 
         #[cfg(feature = "sound")]
-        let sound_player = SoundPlayer::new(settings.clone());
+        let sound_player = if renderer.is_headless() {
+            SoundPlayer::silent(settings.clone())
+        } else {
+            SoundPlayer::new(settings.clone())
+        };
+
+        #[cfg(feature = "http")]
+        let http = match renderer.is_headless() {
+            true => None,
+            false => start_http_server(&settings, &mut errors),
+        };
+
+        let mut renderer = renderer;
+        if !renderer.is_headless() {
+            renderer.set_compat(settings.get_render_compat());
+            renderer.set_report_modifiers(settings.get_sticky_modifiers());
+        }
 
         Self {
             renderer,
             activities,
+            last_periodic: app_start,
+            last_theme_check: app_start,
+            last_tick: app_start,
+            tick_lag: Duration::ZERO,
+            breadcrumbs: ui::Breadcrumbs::default(),
+            #[cfg(feature = "http")]
+            screenshots: vec![],
             data: AppData {
                 app_start,
+                ambience: settings.get_ambience_variant(),
+                bell: false,
+                errors,
+                applied_overlays: settings.overlays_revision(),
+                theme_file: theme_file_state(&settings),
                 settings,
                 save,
                 use_data,
@@ -146,11 +400,18 @@ impl App {
                 jobs,
                 theme,
                 logs,
+                modes: GameModeRegistry::builtin(),
+                tick_alpha: 0.0,
+                cache: Cache::default(),
+                toasts: ui::Toasts::default(),
+                narrator: Narrator::default(),
 
+                #[cfg(feature = "http")]
+                http,
                 #[cfg(feature = "sound")]
                 sound_player,
                 #[cfg(feature = "sound")]
-                bgm_track: None,
+                bgm_context: None,
             },
         }
     }
@@ -158,103 +419,413 @@ impl App {
     pub fn run(&mut self) -> Option<ActivityResult> {
         log::trace!("Starting main loop");
 
-        let rem_events = 'mainloop: loop {
-            while let Some(job) = self.data.jobs.pop() {
-                log::trace!("Running job: {:?}", job.name().unwrap_or("<unnamed>"));
-                job.call(&mut self.data);
-            }
+        let rem_events = loop {
+            self.run_jobs();
 
             let mut events = vec![];
+            self.run_periodic(false, &mut events);
+            self.check_theme(&mut events);
+            #[cfg(feature = "http")]
+            self.check_http(&mut events);
 
-            let mut delay = Duration::from_millis(45);
+            let mut delay = Duration::from_millis(self.data.settings.get_frame_delay());
             while let Ok(true) = crossterm::event::poll(delay) {
                 let event = read().unwrap();
-
-                self.renderer.on_event(&event);
-                self.data.screen_size = self.renderer.frame_size();
-
-                match event {
-                    crossterm::event::Event::Key(KeyEvent {
-                        code: KeyCode::F(3),
-                        kind: KeyEventKind::Press,
-                        ..
-                    }) => self.switch_debug(),
-                    event @ crossterm::event::Event::Mouse(_) => {
-                        if self.data.settings.get_enable_mouse() {
-                            events.push(Event::Term(event));
-                        }
-                    }
-                    event => events.push(Event::Term(event)),
-                }
+                self.on_term_event(event, &mut events);
 
                 // just so we read all events in the frame
                 delay = Duration::from_nanos(1)
             }
 
-            while let Some(change) = match self.activities.active_mut() {
-                Some(active) => {
-                    log::trace!("Updating activity: '{}'", active.name());
-                    active
-                }
-                None => break 'mainloop events,
+            let now = Instant::now();
+            self.run_ticks(now - self.last_tick);
+            self.last_tick = now;
+
+            if let Some(rem_events) = self.update_activities(events) {
+                break rem_events;
+            }
+
+            self.draw();
+            #[cfg(feature = "http")]
+            self.send_screenshots();
+            self.show();
+        };
+
+        log::trace!("Main loop ended");
+        self.run_periodic(true, &mut vec![]);
+
+        Self::activity_result(rem_events)
+    }
+
+    /// Run the app without a terminal
+    ///
+    /// Each frame gets at most one event from `events`, terminal events are
+    /// handled the same way as in [`App::run`] (e.g. resizing the buffer).
+    /// Frames are not delayed, so this runs as fast as possible, and each of
+    /// them runs exactly one tick, so the simulation is deterministic.
+    ///
+    /// Returns the result of the last activity if all activities were popped,
+    /// `None` if `max_frames` was reached first. Last drawn frame is available
+    /// through [`App::last_frame`].
+    pub fn run_headless(
+        &mut self,
+        events: impl IntoIterator<Item = Event>,
+        max_frames: usize,
+    ) -> Option<ActivityResult> {
+        log::trace!("Starting headless loop");
+
+        let mut input = events.into_iter();
+
+        for _ in 0..max_frames {
+            self.run_jobs();
+
+            let mut events = vec![];
+            match input.next() {
+                Some(Event::Term(event)) => self.on_term_event(event, &mut events),
+                Some(event) => events.push(event),
+                None => {}
+            }
+            #[cfg(feature = "http")]
+            self.check_http(&mut events);
+
+            self.run_ticks(TICK);
+
+            if let Some(rem_events) = self.update_activities(events) {
+                log::trace!("Headless loop ended");
+                return Self::activity_result(rem_events);
             }
-            .update(std::mem::take(&mut events), &mut self.data)
+
+            self.draw();
+            #[cfg(feature = "http")]
+            self.send_screenshots();
+            self.show();
+        }
+
+        log::trace!("Headless loop reached frame limit");
+
+        None
+    }
+
+    /// Reloads the theme if its file changed, at most once per [`THEME_CHECK_INTERVAL`]
+    fn check_theme(&mut self, events: &mut Vec<Event>) {
+        if self.last_theme_check.elapsed() < THEME_CHECK_INTERVAL {
+            return;
+        }
+
+        self.last_theme_check = Instant::now();
+        if self.data.reload_theme_if_changed() {
+            log::info!("Theme file changed, reloaded");
+            events.push(Event::ThemeReloaded);
+        }
+    }
+
+    /// Handles the requests of the HTTP server, pausing is left to the game and screenshots
+    /// are sent by [`App::send_screenshots`]
+    #[cfg(feature = "http")]
+    fn check_http(&mut self, events: &mut Vec<Event>) {
+        let Some(server) = &self.data.http else {
+            return;
+        };
+
+        while let Some(command) = server.try_recv() {
+            match command {
+                Command::Pause => events.push(Event::Pause),
+                Command::Screenshot(reply) => self.screenshots.push(reply),
+            }
+        }
+    }
+
+    /// Sends the drawn frame to everyone who asked for a screenshot
+    #[cfg(feature = "http")]
+    fn send_screenshots(&mut self) {
+        if self.screenshots.is_empty() {
+            return;
+        }
+
+        let text = self.renderer.frame().to_string();
+        let lines: Vec<_> = text.lines().map(String::from).collect();
+        for reply in self.screenshots.drain(..) {
+            let _ = reply.send(lines.clone());
+        }
+    }
+
+    /// Run periodic tasks, at most once per [`PERIODIC_INTERVAL`] unless `force`d
+    ///
+    /// - saves changed settings,
+    /// - reloads settings changed on disk, [`Event::SettingsReloaded`] is added to `events`,
+    /// - updates the ambience when the time of day changes,
+    /// - applies renderer settings.
+    fn run_periodic(&mut self, force: bool, events: &mut Vec<Event>) {
+        if !force && self.last_periodic.elapsed() < PERIODIC_INTERVAL {
+            return;
+        }
+
+        self.last_periodic = Instant::now();
+        if let Err(err) = self.data.settings.save_if_dirty() {
+            log::error!("Failed to save settings: {}", err);
+        }
+
+        if self.data.settings.reload_if_changed() {
+            log::info!("Settings file changed, reloaded");
+            self.data.apply_settings();
+
+            #[cfg(feature = "sound")]
+            if let Some(context) = self.data.bgm_context.take() {
+                self.data.play_bgm(context);
+            }
+
+            events.push(Event::SettingsReloaded);
+        }
+
+        self.data.update_ambience();
+        self.renderer
+            .set_compat(self.data.settings.get_render_compat());
+        self.renderer
+            .set_report_modifiers(self.data.settings.get_sticky_modifiers());
+    }
+
+    /// Runs ticks of the active activity for the `elapsed` time
+    ///
+    /// Time which doesn't add up to a whole tick is kept for the next call.
+    fn run_ticks(&mut self, elapsed: Duration) {
+        self.tick_lag += elapsed;
+
+        let ticks = (self.tick_lag.as_nanos() / TICK.as_nanos()) as u32;
+        self.tick_lag -= TICK * ticks;
+
+        if ticks > MAX_TICKS_PER_FRAME {
+            log::debug!(
+                "Simulation is {} ticks behind, skipping",
+                ticks - MAX_TICKS_PER_FRAME
+            );
+        }
+
+        if let Some(active) = self.activities.active_mut() {
+            for _ in 0..ticks.min(MAX_TICKS_PER_FRAME) {
+                active.tick(&mut self.data);
+            }
+        }
+        self.data.toasts.tick(TICK * ticks);
+
+        self.data.tick_alpha = self.tick_lag.as_secs_f32() / TICK.as_secs_f32();
+    }
+
+    fn run_jobs(&mut self) {
+        while let Some(job) = self.data.jobs.pop() {
+            log::trace!("Running job: {:?}", job.name().unwrap_or("<unnamed>"));
+            job.call(&mut self.data);
+        }
+    }
+
+    fn on_term_event(&mut self, event: TermEvent, events: &mut Vec<Event>) {
+        if let Some(size) = self.renderer.on_event(&event) {
+            log::debug!("Screen resized to {}x{}", size.0, size.1);
+            self.data.screen_size = size;
+            self.activities.invalidate_layout();
+        }
+
+        match event {
+            TermEvent::Key(KeyEvent {
+                code: KeyCode::F(3),
+                kind: KeyEventKind::Press,
+                ..
+            }) => self.switch_debug(),
+            TermEvent::Key(KeyEvent {
+                code: KeyCode::F(4),
+                kind: KeyEventKind::Press,
+                ..
+            }) if self.data.use_data.show_debug
+                && self.activities.active().map(|a| a.name()) != Some("maze diff") =>
             {
-                match change {
-                    Change::Push(activity) => {
-                        log::trace!(
-                            "Pushed new activity '{}/{}'",
-                            activity.source(),
-                            activity.name()
-                        );
-                        self.activities.push(activity);
-                    }
-                    Change::Pop { n, res } => {
-                        self.activities.pop_n(n);
-                        events.push(Event::ActiveAfterPop(res));
-                        log::trace!("Popped {} activities", n);
-                    }
-                    Change::PopUntil { name, res } => {
-                        self.activities.pop_until(&name);
-                        events.push(Event::ActiveAfterPop(res));
-                        log::trace!("Popped until '{}'", name);
-                    }
-                    Change::Replace(activity) => self.activities.replace(activity),
-                    Change::ReplaceAt { index, activity } => {
-                        self.activities.replace_at(index, activity);
-                    }
+                let diff = maze_diff::MazeDiffActivity::new_activity(self.data.screen_size);
+                self.activities.push(diff);
+            }
+            event @ TermEvent::Mouse(_) => {
+                if self.data.settings.get_enable_mouse() {
+                    events.push(Event::Term(event));
                 }
             }
+            event => events.push(Event::Term(event)),
+        }
+    }
+
+    /// Updates the active activity and applies all the changes
+    ///
+    /// Returns remaining events if there are no activities left.
+    fn update_activities(&mut self, mut events: Vec<Event>) -> Option<Vec<Event>> {
+        for err in std::mem::take(&mut self.data.errors) {
+            self.activities.push(ui::ErrorPopup::new_activity(&err));
+        }
+
+        let mut jump = None;
+        events.retain(|event| match event {
+            Event::Term(TermEvent::Mouse(mouse)) => match self.breadcrumbs.dispatch(mouse) {
+                Some(index) => {
+                    jump = Some(index);
+                    false
+                }
+                None => true,
+            },
+            _ => true,
+        });
+        if let Some(index) = jump {
+            let n = self.activities.len() - 1 - index;
+            self.apply_change(Change::pop(n), &mut events);
+        }
+
+        while let Some(change) = match self.activities.active_mut() {
+            Some(active) => {
+                log::trace!("Updating activity: '{}'", active.name());
+                active.ensure_layout(self.data.screen_size);
+                active
+            }
+            None => return Some(events),
+        }
+        .update(std::mem::take(&mut events), &mut self.data)
+        {
+            self.apply_change(change, &mut events);
+        }
+
+        None
+    }
+
+    /// Changes the activity stack, events for the new active activity are added to `events`
+    fn apply_change(&mut self, change: Change, events: &mut Vec<Event>) {
+        match change {
+            Change::Push(activity) => {
+                log::trace!(
+                    "Pushed new activity '{}/{}'",
+                    activity.source(),
+                    activity.name()
+                );
+                self.activities.push(activity);
+            }
+            Change::Pop { n, res } => {
+                self.activities.pop_n(n);
+                events.push(Event::ActiveAfterPop(res));
+                log::trace!("Popped {} activities", n);
+            }
+            Change::PopUntil { name, res } => {
+                self.activities.pop_until(&name);
+                events.push(Event::ActiveAfterPop(res));
+                log::trace!("Popped until '{}'", name);
+            }
+            Change::Replace(activity) => self.activities.replace(activity),
+            Change::ReplaceAt { index, activity } => {
+                self.activities.replace_at(index, activity);
+            }
+        }
+
+        // overlay of a dropped activity was removed
+        if self.data.settings.overlays_revision() != self.data.applied_overlays {
+            self.data.apply_settings();
+        }
+    }
+
+    fn show(&mut self) {
+        self.renderer.show().unwrap();
+        if std::mem::take(&mut self.data.bell) {
+            self.renderer.bell();
+        }
+    }
+
+    fn draw(&mut self) {
+        self.renderer
+            .frame()
+            .fill(Cell::styled(' ', self.data.theme.get("background")));
+
+        let active = self.activities.active().expect("No active active");
+        active
+            .screen()
+            .draw(self.renderer.frame(), &self.data.theme)
+            .unwrap();
 
-            self.renderer
-                .frame()
-                .fill(Cell::styled(' ', self.data.theme.get("background")));
+        if self.data.settings.get_show_breadcrumbs() {
+            self.breadcrumbs
+                .update(&self.activities, self.data.screen_size);
+            self.breadcrumbs
+                .draw(self.renderer.frame(), &self.data.theme);
+        } else {
+            self.breadcrumbs = ui::Breadcrumbs::default();
+        }
 
-            self.activities
-                .active()
-                .expect("No active active")
-                .screen()
-                .draw(self.renderer.frame(), &self.data.theme)
-                .unwrap();
+        let hints = self.data.settings.get_show_key_hints();
+        if hints {
+            ui::draw_footer(self.renderer.frame(), &active.hints(), &self.data.theme);
+        }
 
+        if self.data.settings.get_accessible() {
             self.data
-                .logs
-                .draw(Dims(0, 0), self.renderer.frame(), &self.data.theme);
+                .narrator
+                .draw(self.renderer.frame(), hints, &self.data.theme);
+        }
 
-            // TODO: let activities show debug info and about the app itself
-            // then we can draw it here
+        self.data
+            .logs
+            .draw(Dims(0, 0), self.renderer.frame(), &self.data.theme);
 
-            self.renderer.show().unwrap();
+        self.data
+            .toasts
+            .draw(self.renderer.frame(), &self.data.theme);
+
+        if self.data.use_data.show_debug {
+            self.draw_debug_info();
+        }
+
+        // TODO: let activities show debug info too
+    }
+
+    /// Info about the app itself, shown in the bottom right corner in debug mode
+    fn draw_debug_info(&mut self) {
+        let stats = self.data.cache.stats();
+        let hit_rate = match stats.hit_rate() {
+            Some(rate) => format!("{:.0}%", rate * 100.0),
+            None => "-".to_string(),
         };
+        let text = format!(
+            "cache: {}/{} hit rate {} ({} hits, {} misses, {} evicted)",
+            self.data.cache.len(),
+            cache::MAX_ENTRIES,
+            hit_rate,
+            stats.hits,
+            stats.misses,
+            stats.evictions,
+        );
 
-        log::trace!("Main loop ended");
+        let frames = self.renderer.frame_stats();
+        let frames = format!(
+            "frames: {} written, {} dropped, last took {:.1} ms",
+            frames.written,
+            frames.dropped,
+            frames.last_write.as_secs_f64() * 1000.0,
+        );
+
+        let frame = self.renderer.frame();
+        for (i, text) in [frames, text].iter().enumerate() {
+            let pos = Dims(
+                frame.size.0 - text.len() as i32,
+                frame.size.1 - 2 + i as i32,
+            );
+            frame.draw(pos, text.as_str(), self.data.theme["debug.text"]);
+        }
+    }
 
-        rem_events.into_iter().find_map(|e| match e {
+    fn activity_result(events: Vec<Event>) -> Option<ActivityResult> {
+        events.into_iter().find_map(|e| match e {
             Event::ActiveAfterPop(Some(res)) => Some(res),
             _ => None,
         })
     }
 
+    /// Last frame shown by the renderer
+    pub fn last_frame(&self) -> &Frame {
+        self.renderer.shown_frame()
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.renderer.is_headless()
+    }
+
     fn switch_debug(&mut self) {
         self.data.use_data.show_debug = !self.data.use_data.show_debug;
         self.data.logs.switch_debug(&self.data.settings);
@@ -272,6 +843,29 @@ impl App {
         &self.activities
     }
 
+    /// Ask once whether to enable the touch controls, if the terminal looks like it has no keyboard
+    ///
+    /// `forced` skips the detection, it's set by the `--touch` flag. Prompt is pushed on top,
+    /// so it should be called after the base activity is pushed. Headless apps are never asked,
+    /// there is nobody to answer.
+    pub fn suggest_touch_controls(&mut self, forced: bool) {
+        if !self.renderer.is_headless() && TouchPrompt::should_show(&self.data.settings, forced) {
+            log::info!("Touch terminal detected, asking to enable touch controls");
+            self.activities.push(TouchPrompt::new_activity());
+        }
+    }
+
+    /// Turns the accessibility mode on, it's set by the `--accessible` flag
+    ///
+    /// It's not saved, the mode is on only when the flag is given.
+    pub fn enable_accessible(&mut self) {
+        self.data.settings.change_unsaved(|settings| {
+            settings.set_accessible(true);
+        });
+        self.renderer
+            .set_compat(self.data.settings.get_render_compat());
+    }
+
     pub fn activities_mut(&mut self) -> &mut Activities {
         &mut self.activities
     }
@@ -295,18 +889,147 @@ pub struct AppStateData {
     pub show_debug: bool,
 }
 
-fn init_theme_resolver() -> ThemeResolver {
+/// File of the theme from the settings with its modification time, `None` if it's the built-in one
+fn theme_file_state(settings: &Settings) -> Option<(PathBuf, Option<SystemTime>)> {
+    let path = settings.theme_path()?;
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    Some((path, modified))
+}
+
+/// Starts the HTTP server if it's enabled, it needs a token so other programs can't use it
+#[cfg(feature = "http")]
+fn start_http_server(settings: &Settings, errors: &mut Vec<AppError>) -> Option<HttpServer> {
+    if !settings.get_http_server() {
+        return None;
+    }
+
+    let Some(token) = settings.get_http_token() else {
+        log::warn!("HTTP server is enabled, but it has no token, set `http_token` in the settings");
+        return None;
+    };
+
+    match HttpServer::start(settings.get_http_port() as u16, token) {
+        Ok(server) => Some(server),
+        Err(err) => {
+            errors.push(AppError::io("start the HTTP server", err));
+            None
+        }
+    }
+}
+
+pub fn init_theme_resolver() -> ThemeResolver {
     let mut resolver = ThemeResolver::new();
 
     resolver
         .link("default", "")
+        .desc("Base of all styles which aren't set")
         .link("background", "")
-        .link("empty", "");
+        .link("empty", "")
+        .desc("Empty space, not used by built-in screens");
 
     resolver
         .extend(ui::theme_resolver())
         .extend(game::game_theme_resolver())
-        .extend(logging::logging_theme_resolver());
+        .extend(end_game::end_game_theme_resolver())
+        .extend(campaign::campaign_theme_resolver())
+        .extend(achievements::achievements_theme_resolver())
+        .extend(stats::stats_theme_resolver())
+        .extend(floor_jump::floor_jump_theme_resolver())
+        .extend(floor_overview::floor_overview_theme_resolver())
+        .extend(narration::narration_theme_resolver())
+        .extend(maze_diff::maze_diff_theme_resolver())
+        .extend(logging::logging_theme_resolver())
+        .extend(editor::editor_theme_resolver());
+
+    #[cfg(feature = "first_person")]
+    resolver.extend(super::first_person::first_person_theme_resolver());
 
     resolver
 }
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use crate::ui::Popup;
+
+    use super::*;
+
+    fn key(code: KeyCode) -> Event {
+        Event::Term(TermEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    #[test]
+    fn headless_popup() {
+        let popup = Popup::new("Headless".to_string(), vec!["Hello".to_string()]);
+        let mut app =
+            App::new_headless_default(Activity::new_base_boxed("popup", popup), Dims(40, 12));
+
+        assert!(app.run_headless([], 2).is_none());
+        assert!(app.last_frame().to_string().contains("Hello"));
+
+        let res = app
+            .run_headless([key(KeyCode::Char('x'))], 5)
+            .expect("popup should return a result");
+        assert_eq!(*res.downcast::<KeyCode>().unwrap(), KeyCode::Char('x'));
+        assert_eq!(app.activity_count(), 0);
+    }
+
+    #[test]
+    fn headless_is_not_asked_for_touch() {
+        let popup = Popup::new("Headless".to_string(), vec![]);
+        let mut app =
+            App::new_headless_default(Activity::new_base_boxed("popup", popup), Dims(40, 80));
+
+        app.suggest_touch_controls(true);
+        assert_eq!(app.activity_count(), 1);
+        assert_eq!(app.data_mut().settings.read().mobile_mode, None);
+    }
+
+    #[test]
+    fn breadcrumb_goes_back() {
+        let menu = |title: &str| {
+            let config =
+                ui::MenuConfig::new_from_strings(title, ["One".to_string(), "Two".to_string()]);
+            Activity::new_base_boxed(title, ui::Menu::new(config))
+        };
+        let mut app = App::new_headless_default(menu("Main menu"), Dims(60, 20));
+        app.data_mut().settings.set_enable_mouse(true);
+        app.data_mut().settings.set_show_breadcrumbs(true);
+        app.activities_mut().push(menu("Settings"));
+        app.activities_mut().push(menu("Controls"));
+
+        assert!(app.run_headless([], 1).is_none());
+        let top = app.last_frame().to_string();
+        let top = top.lines().next().unwrap();
+        assert_eq!(top.trim(), "Main menu ▸ Settings ▸ Controls");
+
+        let click = || {
+            Event::Term(TermEvent::Mouse(crossterm::event::MouseEvent {
+                kind: crossterm::event::MouseEventKind::Up(crossterm::event::MouseButton::Left),
+                column: 3,
+                row: 0,
+                modifiers: KeyModifiers::NONE,
+            }))
+        };
+        assert!(app.run_headless([click()], 2).is_none());
+        assert_eq!(app.activity_count(), 1);
+        assert_eq!(app.active_name(), Some("Main menu"));
+
+        // the trail ends at the popup, the menus under it are not skipped
+        let popup = Popup::new("Untitled".to_string(), vec![]);
+        app.activities_mut()
+            .push(Activity::new_base_boxed("popup", popup));
+        app.activities_mut().push(menu("Paused"));
+        app.activities_mut().push(menu("Options"));
+
+        assert!(app.run_headless([], 1).is_none());
+        let top = app.last_frame().to_string();
+        let top = top.lines().next().unwrap();
+        assert_eq!(top.trim(), "Paused ▸ Options");
+
+        assert!(app.run_headless([click()], 2).is_none());
+        assert_eq!(app.activity_count(), 3);
+        assert_eq!(app.active_name(), Some("Paused"));
+    }
+}
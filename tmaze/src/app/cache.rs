@@ -0,0 +1,223 @@
+//! Cache of expensive results shared by all activities
+//!
+//! Every kind of cached value has its own key type implementing [`CacheKey`], the type of
+//! the key decides the type of the value. The cache holds at most [`MAX_ENTRIES`] values
+//! of all the kinds together, least recently used ones are evicted first.
+
+use std::{
+    any::{Any, TypeId},
+    hash::Hash,
+    sync::Arc,
+};
+
+use cmaze::{dims::GameMode, game::RunningGame};
+use hashbrown::HashMap;
+
+/// Most values kept in the cache
+pub const MAX_ENTRIES: usize = 64;
+
+/// Key of a cached value, the value type is decided by the key type
+pub trait CacheKey: Hash + Eq + Clone + 'static {
+    type Value: 'static;
+}
+
+/// Identity of a generated maze, same keys mean the same maze
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MazeKey {
+    seed: u64,
+    game_mode: GameMode,
+    /// Address of the generator function
+    generator: usize,
+}
+
+impl MazeKey {
+    pub fn of(game: &RunningGame) -> Self {
+        Self {
+            seed: game.get_seed(),
+            game_mode: game.get_game_mode(),
+            generator: game.get_gen_fn() as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> Option<f32> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f32 / total as f32)
+    }
+}
+
+/// Values of a single key type, with the time of their last use
+struct Store<K: CacheKey> {
+    values: HashMap<K, (Arc<K::Value>, u64)>,
+}
+
+/// Type-erased [`Store`], so stores of all the key types can be evicted together
+trait AnyStore {
+    fn len(&self) -> usize;
+    fn oldest(&self) -> Option<u64>;
+    fn evict_oldest(&mut self);
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<K: CacheKey> AnyStore for Store<K> {
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn oldest(&self) -> Option<u64> {
+        self.values.values().map(|(_, used)| *used).min()
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .values
+            .iter()
+            .min_by_key(|(_, (_, used))| *used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.values.remove(&key);
+        }
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Cache {
+    stores: HashMap<TypeId, Box<dyn AnyStore>>,
+    /// Incremented on every access, used to find the least recently used values
+    clock: u64,
+    stats: CacheStats,
+}
+
+impl Cache {
+    fn store<K: CacheKey>(&mut self) -> &mut Store<K> {
+        self.stores
+            .entry(TypeId::of::<K>())
+            .or_insert_with(|| {
+                Box::new(Store::<K> {
+                    values: HashMap::new(),
+                })
+            })
+            .as_any_mut()
+            .downcast_mut()
+            .expect("store should match the key type")
+    }
+
+    pub fn get<K: CacheKey>(&mut self, key: &K) -> Option<Arc<K::Value>> {
+        self.clock += 1;
+        let clock = self.clock;
+
+        let found = self.store::<K>().values.get_mut(key).map(|(value, used)| {
+            *used = clock;
+            value.clone()
+        });
+
+        match found {
+            Some(_) => self.stats.hits += 1,
+            None => self.stats.misses += 1,
+        }
+        found
+    }
+
+    pub fn insert<K: CacheKey>(&mut self, key: K, value: K::Value) -> Arc<K::Value> {
+        self.clock += 1;
+        let clock = self.clock;
+
+        let value = Arc::new(value);
+        self.store::<K>().values.insert(key, (value.clone(), clock));
+        self.evict();
+        value
+    }
+
+    /// Cached value of the key, it's computed by `f` if it isn't cached yet
+    pub fn get_or_insert_with<K: CacheKey>(
+        &mut self,
+        key: K,
+        f: impl FnOnce() -> K::Value,
+    ) -> Arc<K::Value> {
+        match self.get(&key) {
+            Some(value) => value,
+            None => self.insert(key, f()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stores.values().map(|store| store.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    fn evict(&mut self) {
+        while self.len() > MAX_ENTRIES {
+            let oldest = self
+                .stores
+                .values_mut()
+                .filter_map(|store| Some((store.oldest()?, store)))
+                .min_by_key(|(used, _)| *used);
+
+            match oldest {
+                Some((_, store)) => store.evict_oldest(),
+                None => break,
+            }
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Square(u64);
+
+    impl CacheKey for Square {
+        type Value = u64;
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Name(u64);
+
+    impl CacheKey for Name {
+        type Value = String;
+    }
+
+    #[test]
+    fn typed_keys_and_eviction() {
+        let mut cache = Cache::default();
+        assert_eq!(*cache.get_or_insert_with(Square(3), || 9), 9);
+        assert_eq!(*cache.get_or_insert_with(Square(3), || unreachable!()), 9);
+        assert_eq!(
+            *cache.get_or_insert_with(Name(3), || "three".into()),
+            "three"
+        );
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().misses, 2);
+
+        for i in 0..MAX_ENTRIES as u64 {
+            // keep the first value in use, so it isn't evicted
+            cache.get(&Square(3));
+            cache.insert(Square(i + 10), i);
+        }
+        assert_eq!(cache.len(), MAX_ENTRIES);
+        assert!(cache.get(&Square(3)).is_some());
+        assert!(cache.get(&Name(3)).is_none());
+    }
+}
@@ -0,0 +1,455 @@
+//! Campaign, a fixed sequence of mazes which introduce the mechanics one by one
+//!
+//! Every stage has a fixed seed, so all players get the same mazes. A stage is unlocked
+//! by finishing the previous one, and rated by up to three stars by the time it took.
+
+use std::{io, time::Duration};
+
+use cmaze::{
+    dims::*,
+    game::{GoalObjective, GoalPlacement, ItemPlacement},
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+
+use crate::{
+    data::SaveData,
+    helpers::is_release,
+    renderer::{Cell, Frame},
+    settings::{
+        theme::{Theme, ThemeResolver},
+        MazeGenAlgo,
+    },
+    ui::{center_box_in_screen, draw_box, format_duration, Hint, Screen},
+};
+
+use super::{
+    app::AppData, game::MazeGenerationActivity, game_state::GameRules, Activity, ActivityHandler,
+    Change, Event,
+};
+
+/// Stages on a single row of the map, the next row continues in the opposite direction
+const STAGES_PER_ROW: usize = 5;
+/// Width of a stage on the map, with the path to the next one
+const STAGE_WIDTH: i32 = 9;
+const ROW_HEIGHT: i32 = 4;
+const DETAIL_LINES: i32 = 5;
+
+/// Single maze of the campaign
+#[derive(Debug, Clone, Copy)]
+pub struct Stage {
+    /// Identifier of the stage in the save data, it must never change
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Mechanics of the stage, shown on the map
+    pub description: &'static str,
+    pub game_mode: GameMode,
+    pub seed: u64,
+    pub algo: MazeGenAlgo,
+    pub time_limit: Option<Duration>,
+    pub opponent_step: Option<Duration>,
+    /// Seconds to finish the stage for two and three stars, any finish gets one star
+    pub star_times: [f32; 2],
+}
+
+const fn mode(size: Dims3D, is_tower: bool, goals: GoalPlacement, braid: u8) -> GameMode {
+    let objective = match goals {
+        GoalPlacement::Corners => GoalObjective::VisitAll,
+        _ => GoalObjective::ReachAny,
+    };
+
+    GameMode {
+        size,
+        is_tower,
+        goals,
+        objective,
+        braid,
+        braid_floors: 0,
+        items: ItemPlacement::NONE,
+    }
+}
+
+const fn stage(id: &'static str, name: &'static str, description: &'static str) -> Stage {
+    Stage {
+        id,
+        name,
+        description,
+        game_mode: mode(Dims3D(10, 5, 1), false, GoalPlacement::Corner, 0),
+        seed: 0,
+        algo: MazeGenAlgo::RandomKruskals,
+        time_limit: None,
+        opponent_step: None,
+        star_times: [20.0, 10.0],
+    }
+}
+
+pub const STAGES: [Stage; 10] = [
+    Stage {
+        seed: 0x7a3e_0001,
+        ..stage("first-steps", "First steps", "Find the goal in the corner")
+    },
+    Stage {
+        game_mode: mode(Dims3D(30, 15, 1), false, GoalPlacement::Corner, 0),
+        seed: 0x7a3e_0002,
+        algo: MazeGenAlgo::DepthFirstSearch,
+        star_times: [60.0, 35.0],
+        ..stage("long-way", "Long way", "Bigger maze with long corridors")
+    },
+    Stage {
+        game_mode: mode(Dims3D(30, 15, 1), false, GoalPlacement::Corner, 50),
+        seed: 0x7a3e_0003,
+        star_times: [45.0, 25.0],
+        ..stage("loops", "Loops", "Paths cross, there's more than one way")
+    },
+    Stage {
+        game_mode: mode(Dims3D(20, 10, 1), false, GoalPlacement::Corners, 0),
+        seed: 0x7a3e_0004,
+        star_times: [120.0, 70.0],
+        ..stage("four-corners", "Four corners", "Visit all the corners")
+    },
+    Stage {
+        game_mode: mode(Dims3D(10, 5, 3), false, GoalPlacement::Corner, 0),
+        seed: 0x7a3e_0005,
+        star_times: [60.0, 35.0],
+        ..stage("upstairs", "Upstairs", "Stairs lead to the other floors")
+    },
+    Stage {
+        game_mode: mode(Dims3D(40, 20, 1), false, GoalPlacement::Corner, 0),
+        seed: 0x7a3e_0006,
+        time_limit: Some(Duration::from_secs(150)),
+        star_times: [100.0, 70.0],
+        ..stage(
+            "clock",
+            "Against the clock",
+            "Finish before the time runs out",
+        )
+    },
+    Stage {
+        game_mode: mode(Dims3D(10, 10, 5), true, GoalPlacement::Corner, 0),
+        seed: 0x7a3e_0007,
+        star_times: [90.0, 50.0],
+        ..stage("tower", "The tower", "Every floor has a single way up")
+    },
+    Stage {
+        game_mode: mode(Dims3D(30, 15, 1), false, GoalPlacement::Corner, 0),
+        seed: 0x7a3e_0008,
+        algo: MazeGenAlgo::DepthFirstSearch,
+        opponent_step: Some(Duration::from_millis(400)),
+        star_times: [60.0, 40.0],
+        ..stage("race", "Race", "Reach the goal before the opponent")
+    },
+    Stage {
+        game_mode: mode(Dims3D(40, 20, 3), false, GoalPlacement::Exits, 30),
+        seed: 0x7a3e_0009,
+        star_times: [150.0, 90.0],
+        ..stage("exits", "Exits", "Find any exit on the top floor")
+    },
+    Stage {
+        game_mode: mode(Dims3D(15, 15, 8), true, GoalPlacement::Corner, 0),
+        seed: 0x7a3e_000a,
+        time_limit: Some(Duration::from_secs(300)),
+        star_times: [240.0, 150.0],
+        ..stage("summit", "Summit", "Climb the highest tower in time")
+    },
+];
+
+impl Stage {
+    /// Stars for finishing the stage in this time, from 1 to 3
+    pub fn stars(&self, seconds: f32) -> u8 {
+        1 + self.star_times.iter().filter(|t| seconds <= **t).count() as u8
+    }
+
+    pub fn start(index: usize) -> Change {
+        Change::push(Self::generation(index))
+    }
+
+    /// Generation of the maze of the stage, which starts it
+    pub fn generation(index: usize) -> Activity {
+        let stage = &STAGES[index];
+        let rules = GameRules {
+            time_limit: stage.time_limit,
+            opponent_step: stage.opponent_step,
+            stage: Some(index),
+            ..Default::default()
+        };
+
+        Activity::new_base_boxed(
+            "maze_gen",
+            MazeGenerationActivity::new(stage.game_mode, stage.algo.to_fn())
+                .with_seed(stage.seed)
+                .with_rules(rules),
+        )
+    }
+
+    fn size_text(&self) -> String {
+        let Dims3D(w, h, d) = self.game_mode.size;
+        match d {
+            1 => format!("{}x{}", w, h),
+            _ => format!("{}x{}x{}", w, h, d),
+        }
+    }
+}
+
+/// Stars as text, filled ones first
+pub fn stars_text(stars: u8) -> String {
+    (0..3).map(|i| if i < stars { '★' } else { '☆' }).collect()
+}
+
+/// Map of the stages, with their stars and details of the selected one
+pub struct CampaignMap {
+    selected: usize,
+    /// Best results of the stages, as moves and seconds
+    results: Vec<Option<(i32, f32)>>,
+}
+
+impl CampaignMap {
+    pub fn new(save: &SaveData) -> Self {
+        let results = Self::results(save);
+        // first stage which is not finished yet
+        let selected = results
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(STAGES.len() - 1);
+
+        Self { selected, results }
+    }
+
+    pub fn new_activity(save: &SaveData) -> Activity {
+        Activity::new_base_boxed("campaign", Self::new(save))
+    }
+
+    fn results(save: &SaveData) -> Vec<Option<(i32, f32)>> {
+        STAGES
+            .iter()
+            .map(|stage| {
+                save.get_stage_result(stage.id)
+                    .map(|result| (result.moves, result.seconds))
+            })
+            .collect()
+    }
+
+    fn is_unlocked(&self, index: usize) -> bool {
+        index == 0 || self.results[index - 1].is_some()
+    }
+
+    fn stars(&self, index: usize) -> u8 {
+        self.results[index].map_or(0, |(_, secs)| STAGES[index].stars(secs))
+    }
+
+    /// Position of the stage on the map, relative to its top left corner
+    fn stage_pos(index: usize) -> Dims {
+        let row = index / STAGES_PER_ROW;
+        let col = match row % 2 {
+            0 => index % STAGES_PER_ROW,
+            _ => STAGES_PER_ROW - 1 - index % STAGES_PER_ROW,
+        };
+        // space for the turns of the path on both sides
+        Dims(2 + col as i32 * STAGE_WIDTH, row as i32 * ROW_HEIGHT)
+    }
+
+    fn size() -> Dims {
+        let rows = STAGES.len().div_ceil(STAGES_PER_ROW) as i32;
+        let map_width = STAGES_PER_ROW as i32 * STAGE_WIDTH;
+        // last row has the stages and their stars
+        let map_height = (rows - 1) * ROW_HEIGHT + 2;
+        Dims(map_width + 4, map_height + DETAIL_LINES + 3)
+    }
+
+    fn draw_path(&self, frame: &mut Frame, pos: Dims, index: usize, theme: &Theme) {
+        let style = match self.is_unlocked(index + 1) {
+            true => theme["ui.campaign.done"],
+            false => theme["ui.campaign.path"],
+        };
+        let from = pos + Self::stage_pos(index);
+        let to = pos + Self::stage_pos(index + 1);
+
+        if from.1 == to.1 {
+            let left = from.0.min(to.0) + 5;
+            frame.draw(
+                Dims(left, from.1),
+                "─".repeat(STAGE_WIDTH as usize - 5),
+                style,
+            );
+            return;
+        }
+
+        // turn to the next row, on the side of the map
+        let on_right = from.0 > pos.0 + 2;
+        let (edge, x, top, bottom) = match on_right {
+            true => (from.0 + 5, from.0 + 6, "─╮", "─╯"),
+            false => (from.0 - 2, from.0 - 2, "╭─", "╰─"),
+        };
+        frame.draw(Dims(edge, from.1), top, style);
+        for y in from.1 + 1..to.1 {
+            frame.draw(Dims(x, y), '│', style);
+        }
+        frame.draw(Dims(edge, to.1), bottom, style);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, pos: Dims, width: i32, theme: &Theme) {
+        let index = self.selected;
+        let stage = &STAGES[index];
+        let text = theme["ui.campaign.text"];
+
+        let title = format!("{}. {} - {}", index + 1, stage.name, stage.size_text());
+        frame.draw(pos, title.as_str(), theme["ui.campaign.title"]);
+        frame.draw(pos + Dims(0, 1), stage.description, text);
+
+        let result = match (self.results[index], self.is_unlocked(index)) {
+            (Some((moves, secs)), _) => format!(
+                "Best: {}, {} moves",
+                format_duration(Duration::from_secs_f32(secs)),
+                moves
+            ),
+            (None, true) => "Not finished yet".to_string(),
+            (None, false) => "Locked, finish the previous stage first".to_string(),
+        };
+        frame.draw(pos + Dims(0, 2), result.as_str(), text);
+
+        let [two, three] = stage.star_times;
+        let times = format!(
+            "{} under {}, {} under {}",
+            stars_text(2),
+            format_duration(Duration::from_secs_f32(two)),
+            stars_text(3),
+            format_duration(Duration::from_secs_f32(three)),
+        );
+        frame.draw(pos + Dims(0, 3), times.as_str(), theme["ui.campaign.star"]);
+
+        let help: String = "Arrows: select, Enter: play, Esc: back"
+            .chars()
+            .take(width as usize)
+            .collect();
+        frame.draw(pos + Dims(0, 4), help, theme["ui.campaign.path"]);
+    }
+}
+
+impl ActivityHandler for CampaignMap {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        // results change after a stage is played
+        self.results = Self::results(&data.save);
+
+        for event in events {
+            let Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Right | KeyCode::Down | KeyCode::Char('d' | 's') => {
+                    self.selected = (self.selected + 1).min(STAGES.len() - 1);
+                }
+                KeyCode::Left | KeyCode::Up | KeyCode::Char('a' | 'w') => {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+                KeyCode::Home => self.selected = 0,
+                KeyCode::End => self.selected = STAGES.len() - 1,
+                KeyCode::Enter | KeyCode::Char(' ') if self.is_unlocked(self.selected) => {
+                    return Some(Stage::start(self.selected));
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Some(Change::pop_top()),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![
+            Hint::new("←→", "select stage"),
+            Hint::new("Enter", "play"),
+            Hint::new("Esc", "back"),
+        ]
+    }
+}
+
+impl Screen for CampaignMap {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let size = Self::size();
+        let pos = center_box_in_screen(size, frame.size);
+        let map = pos + Dims(2, 1);
+
+        frame.fill_rect(pos, size, Cell::styled(' ', theme["ui.campaign.text"]));
+        draw_box(frame, pos, size, theme["ui.campaign.border"]);
+        let stars: u8 = (0..STAGES.len()).map(|i| self.stars(i)).sum();
+        let title = format!(" Campaign - {}/{} ★ ", stars, STAGES.len() * 3);
+        frame.draw(pos + Dims(3, 0), title.as_str(), theme["ui.campaign.title"]);
+
+        for index in 0..STAGES.len() - 1 {
+            self.draw_path(frame, map, index, theme);
+        }
+
+        for index in 0..STAGES.len() {
+            let stage_pos = map + Self::stage_pos(index);
+            let key = match (self.results[index], self.is_unlocked(index)) {
+                (Some(_), _) => "ui.campaign.done",
+                (None, true) => "ui.campaign.text",
+                (None, false) => "ui.campaign.locked",
+            };
+            let style = match index == self.selected {
+                true => theme[key].invert(),
+                false => theme[key],
+            };
+
+            frame.draw(stage_pos, format!("[{:^3}]", index + 1), style);
+            if self.results[index].is_some() {
+                let stars = stars_text(self.stars(index));
+                frame.draw(stage_pos + Dims(1, 1), stars, theme["ui.campaign.star"]);
+            }
+        }
+
+        let details = Dims(pos.0 + 2, pos.1 + size.1 - DETAIL_LINES - 1);
+        frame.draw(
+            details - Dims(1, 1),
+            "─".repeat(size.0 as usize - 2),
+            theme["ui.campaign.border"],
+        );
+        self.draw_details(frame, details, size.0 - 4, theme);
+
+        Ok(())
+    }
+}
+
+pub fn campaign_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+    resolver
+        .link("ui.campaign.border", "ui.popup.border")
+        .desc("Border of the campaign map")
+        .link("ui.campaign.title", "ui.popup.title")
+        .desc("Title of the campaign map")
+        .link("ui.campaign.text", "ui.popup.text")
+        .desc("Names of the campaign stages")
+        .link("ui.campaign.done", "highlight")
+        .desc("Stages of the campaign which are done")
+        .link("ui.campaign.locked", "dim")
+        .desc("Stages of the campaign which are locked")
+        .link("ui.campaign.path", "dim")
+        .desc("Paths between stages and the help of the campaign")
+        .link("ui.campaign.star", "highlight")
+        .desc("Stars and times of the campaign stages");
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_are_unique() {
+        for (i, stage) in STAGES.iter().enumerate() {
+            assert!(STAGES[i + 1..].iter().all(|other| other.id != stage.id));
+            assert!(stage.star_times[0] > stage.star_times[1]);
+        }
+
+        let stage = &STAGES[0];
+        assert_eq!(stage.stars(stage.star_times[1]), 3);
+        assert_eq!(stage.stars(stage.star_times[0]), 2);
+        assert_eq!(stage.stars(stage.star_times[0] + 1.0), 1);
+    }
+}
@@ -0,0 +1,259 @@
+//! Daily challenge, the same maze for everyone on the same day
+//!
+//! Days are counted in UTC, so all players get the same maze at the same
+//! moment, no matter their timezone. Local time is used only for display.
+
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Utc};
+use cmaze::{
+    dims::*,
+    game::{GeneratorFn, GoalObjective, GoalPlacement},
+};
+
+use crate::{
+    data::SaveData,
+    settings::MazeGenAlgo,
+    ui::{self, format_duration, Hint, Menu, MenuConfig},
+};
+
+use super::{
+    app::AppData, game::MazeGenerationActivity, game_state::GameRules, Activity, ActivityHandler,
+    Change, Event,
+};
+
+/// How many previous dailies can be played again
+pub const RETRO_DAYS: u64 = 7;
+
+/// Mazes the daily is chosen from, as size and whether it's a tower
+const DAILY_MAZES: [(Dims3D, bool); 6] = [
+    (Dims3D(30, 15, 1), false),
+    (Dims3D(40, 20, 1), false),
+    (Dims3D(60, 25, 1), false),
+    (Dims3D(20, 10, 3), false),
+    (Dims3D(25, 12, 2), false),
+    (Dims3D(15, 15, 4), true),
+];
+
+/// Date of the current daily
+pub fn today(now: DateTime<Utc>) -> NaiveDate {
+    now.date_naive()
+}
+
+/// Start of the next daily, at UTC midnight
+pub fn next_daily(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = today(now) + Days::new(1);
+    tomorrow.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+pub fn time_until_next(now: DateTime<Utc>) -> std::time::Duration {
+    (next_daily(now) - now).to_std().unwrap_or_default()
+}
+
+/// Seed of the daily, it's different for every date
+///
+/// Number of the day is mixed by a bijection (SplitMix64 finalizer), so no
+/// two dates can share a seed.
+pub fn seed(date: NaiveDate) -> u64 {
+    let mut x = date.num_days_from_ce() as u32 as u64;
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Daily challenge of a single date
+#[derive(Debug, Clone, Copy)]
+pub struct Daily {
+    pub date: NaiveDate,
+    pub seed: u64,
+    pub game_mode: GameMode,
+    pub generator: GeneratorFn,
+}
+
+impl Daily {
+    pub fn new(date: NaiveDate) -> Self {
+        let seed = seed(date);
+
+        // maze and algorithm are picked by different bits of the seed
+        let (size, is_tower) = DAILY_MAZES[(seed >> 48) as usize % DAILY_MAZES.len()];
+        let algo = match (seed >> 47) & 1 {
+            0 => MazeGenAlgo::RandomKruskals,
+            _ => MazeGenAlgo::DepthFirstSearch,
+        };
+
+        Self {
+            date,
+            seed,
+            game_mode: GameMode {
+                size,
+                is_tower,
+                goals: GoalPlacement::Corner,
+                objective: GoalObjective::ReachAny,
+                braid: 0,
+                braid_floors: 0,
+                items: Default::default(),
+            },
+            generator: algo.to_fn(),
+        }
+    }
+
+    pub fn size_text(&self) -> String {
+        let Dims3D(w, h, d) = self.game_mode.size;
+        match (d, self.game_mode.is_tower) {
+            (1, _) => format!("{}x{}", w, h),
+            (_, false) => format!("{}x{}x{}", w, h, d),
+            (_, true) => format!("{}x{}x{} tower", w, h, d),
+        }
+    }
+
+    pub fn start(&self) -> Change {
+        let rules = GameRules {
+            daily: Some(self.date),
+            ..Default::default()
+        };
+
+        Change::push(Activity::new_base_boxed(
+            "maze_gen",
+            MazeGenerationActivity::new(self.game_mode, self.generator)
+                .with_seed(self.seed)
+                .with_rules(rules),
+        ))
+    }
+}
+
+/// Today's daily and the previous ones, with a countdown to the next one
+pub struct DailyMenu {
+    menu: Menu,
+    dates: Vec<NaiveDate>,
+    countdown: String,
+}
+
+impl DailyMenu {
+    /// `real_time` shows the real time of the results instead of the game time
+    pub fn new(save: &SaveData, real_time: bool) -> Self {
+        Self::build(save, real_time, Utc::now(), 0)
+    }
+
+    pub fn new_activity(save: &SaveData, real_time: bool) -> Activity {
+        Activity::new_base_boxed("daily", Self::new(save, real_time))
+    }
+
+    fn build(save: &SaveData, real_time: bool, now: DateTime<Utc>, selected: usize) -> Self {
+        let today = today(now);
+        let dates: Vec<_> = (0..=RETRO_DAYS)
+            .map(|days| today - Days::new(days))
+            .collect();
+
+        let options: Vec<_> = dates
+            .iter()
+            .enumerate()
+            .map(|(i, &date)| {
+                let label = match i {
+                    0 => "Today".to_string(),
+                    1 => "Yesterday".to_string(),
+                    _ => date.format("%a %d %b").to_string(),
+                };
+                let result = match save.get_daily_result(date) {
+                    Some(result) => format!(
+                        "{}, {} moves",
+                        format_duration(std::time::Duration::from_secs_f32(result.time(real_time))),
+                        result.moves
+                    ),
+                    None => "not played".to_string(),
+                };
+                format!("{} - {} - {}", label, Daily::new(date).size_text(), result)
+            })
+            .collect();
+
+        let countdown = Self::countdown(now);
+        let menu_config = MenuConfig::new_from_strings("Daily challenge", options)
+            .subtitle(countdown.clone())
+            .default(selected);
+
+        Self {
+            menu: Menu::new(menu_config),
+            dates,
+            countdown,
+        }
+    }
+
+    fn countdown(now: DateTime<Utc>) -> String {
+        let secs = time_until_next(now).as_secs();
+        let local = next_daily(now).with_timezone(&Local);
+        format!(
+            "Next daily in {:02}:{:02}:{:02}, at {} local time",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            local.format("%H:%M"),
+        )
+    }
+}
+
+impl ActivityHandler for DailyMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        // rebuilt when the countdown changes, or a new day starts
+        let now = Utc::now();
+        if Self::countdown(now) != self.countdown || today(now) != self.dates[0] {
+            let selected = match today(now) == self.dates[0] {
+                true => self.menu.selected(),
+                false => 0,
+            };
+            let real_time = data.settings.get_real_time_results();
+            *self = Self::build(&data.save, real_time, now, selected);
+        }
+
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                Some(Daily::new(self.dates[index]).start())
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn ui::Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn seeds_are_unique() {
+        let start = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        let seeds: HashSet<_> = (0..365 * 100)
+            .map(|days| seed(start + Days::new(days)))
+            .collect();
+        assert_eq!(seeds.len(), 365 * 100);
+    }
+
+    #[test]
+    fn days_change_at_utc_midnight() {
+        let now = "2024-03-10T23:59:30Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(today(now), NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+        assert_eq!(time_until_next(now).as_secs(), 30);
+
+        // already the next day in the local time, but still the same daily
+        let local = now.with_timezone(&chrono::FixedOffset::east_opt(5 * 3600).unwrap());
+        assert_ne!(local.date_naive(), today(now));
+        assert_eq!(today(local.to_utc()), today(now));
+
+        let after = now + chrono::Duration::seconds(30);
+        assert_eq!(after, next_daily(now));
+        assert_eq!(today(after), NaiveDate::from_ymd_opt(2024, 3, 11).unwrap());
+        assert_ne!(seed(today(after)), seed(today(now)));
+    }
+}
@@ -0,0 +1,141 @@
+//! Floors drawn with braille dots or half blocks, so big floors fit on the screen
+//!
+//! Walls of the floor in the normal zoom are taken as pixels, a wall, a junction or nothing on
+//! every position. Pixels are then packed into characters, two of them into a half block or
+//! 2x4 of them into a braille pattern, see [`Zoom::Braille`] and [`Zoom::HalfBlock`].
+
+use cmaze::{
+    dims::*,
+    gameboard::{CellWall, Maze},
+};
+
+use crate::{
+    helpers::{maze2screen, maze_render_size, LineDir, Zoom},
+    renderer::{Cell, Frame},
+    settings::theme::Theme,
+};
+
+use super::texture;
+
+/// Bits of the braille dots by their position in the character, from U+2800
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Walls of the floor as pixels, positions are the same as in the normal zoom
+struct Pixels {
+    size: Dims,
+    walls: Vec<bool>,
+}
+
+impl Pixels {
+    fn of_floor(maze: &Maze, floor: i32) -> Self {
+        let size = maze_render_size(maze, Zoom::Normal);
+        let mut pixels = Self {
+            size,
+            walls: vec![false; (size.0 * size.1) as usize],
+        };
+
+        for y in -1..maze.size().1 {
+            for x in -1..maze.size().0 {
+                let cell_pos = Dims3D(x, y, floor);
+                let Dims(rx, ry) = maze2screen(cell_pos);
+
+                if maze.get_wall(cell_pos, CellWall::Right).unwrap() {
+                    pixels.set(Dims(rx + 1, ry));
+                }
+                if maze.get_wall(cell_pos, CellWall::Bottom).unwrap() {
+                    pixels.set(Dims(rx, ry + 1));
+                }
+                if !matches!(texture::junction_dir(maze, cell_pos), LineDir::Empty) {
+                    pixels.set(Dims(rx + 1, ry + 1));
+                }
+            }
+        }
+
+        pixels
+    }
+
+    fn set(&mut self, pos: Dims) {
+        if pos.0 >= 0 && pos.1 >= 0 && pos.0 < self.size.0 && pos.1 < self.size.1 {
+            self.walls[(pos.1 * self.size.0 + pos.0) as usize] = true;
+        }
+    }
+
+    fn get(&self, pos: Dims) -> bool {
+        pos.0 < self.size.0
+            && pos.1 < self.size.1
+            && self.walls[(pos.1 * self.size.0 + pos.0) as usize]
+    }
+
+    /// Character of the pixels starting at `pos`
+    fn char_at(&self, zoom: Zoom, pos: Dims) -> char {
+        match zoom {
+            Zoom::HalfBlock => match (self.get(pos), self.get(pos + Dims(0, 1))) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            },
+            _ => {
+                let mut bits = 0;
+                for (dy, row) in BRAILLE_DOTS.iter().enumerate() {
+                    for (dx, bit) in row.iter().enumerate() {
+                        if self.get(pos + Dims(dx as i32, dy as i32)) {
+                            bits |= bit;
+                        }
+                    }
+                }
+                char::from_u32(0x2800 + bits).expect("braille patterns are valid characters")
+            }
+        }
+    }
+}
+
+/// Walls of the floor in a dense zoom, see [`Zoom::is_dense`]
+pub fn render_floor(maze: &Maze, floor: i32, zoom: Zoom, theme: &Theme) -> Frame {
+    debug_assert!(zoom.is_dense(), "{:?} is not a dense zoom", zoom);
+
+    let pixels = Pixels::of_floor(maze, floor);
+    let style = theme["game.walls.dense"];
+    let background = theme["game.background"];
+
+    let size = maze_render_size(maze, zoom);
+    let (step_x, step_y) = match zoom {
+        Zoom::HalfBlock => (1, 2),
+        _ => (2, 4),
+    };
+
+    let mut frame = Frame::new(size);
+    frame.fill(Cell::styled(' ', background));
+    for y in 0..size.1 {
+        for x in 0..size.0 {
+            let ch = pixels.char_at(zoom, Dims(x * step_x, y * step_y));
+            if ch != ' ' {
+                frame.draw(Dims(x, y), ch, style);
+            }
+        }
+    }
+
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels_are_packed() {
+        let mut pixels = Pixels {
+            size: Dims(3, 5),
+            walls: vec![false; 15],
+        };
+        pixels.set(Dims(0, 0));
+        pixels.set(Dims(1, 3));
+        pixels.set(Dims(2, 1));
+
+        assert_eq!(pixels.char_at(Zoom::Braille, Dims(0, 0)), '⢁');
+        assert_eq!(pixels.char_at(Zoom::Braille, Dims(2, 0)), '⠂');
+        assert_eq!(pixels.char_at(Zoom::HalfBlock, Dims(0, 0)), '▀');
+        assert_eq!(pixels.char_at(Zoom::HalfBlock, Dims(1, 2)), '▄');
+        assert_eq!(pixels.char_at(Zoom::HalfBlock, Dims(2, 4)), ' ');
+    }
+}
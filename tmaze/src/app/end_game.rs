@@ -0,0 +1,1039 @@
+use std::{fs, io, time::Duration};
+
+use chrono::{DateTime, Local};
+use cmaze::{
+    dims::*,
+    game::{GeneratorFn, GoalObjective, RunningGame},
+    gameboard::{algorithms::random_seed, CellMask, MazeStats},
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    data::model::SolveResult,
+    helpers::{constants::paths::export_path, is_release},
+    renderer::Frame,
+    settings::{
+        theme::{Theme, ThemeResolver},
+        Settings,
+    },
+    ui::{format_duration, Button, FocusManager, Focusable, Hint, Hit, HitMap, Popup, Screen},
+};
+
+use super::{
+    app::AppData,
+    app::TICK,
+    cache::{Cache, CacheKey, MazeKey},
+    campaign::{stars_text, Stage, STAGES},
+    event_log::{RunEvent, RunLog},
+    game::{GameActivity, MazeGenerationActivity},
+    game_state::{GameData, GameRules},
+    stamina::StaminaMeter,
+    tags::{TagDialog, TagsSet},
+    Activity, ActivityHandler, Change, Event,
+};
+
+/// Analysis of a maze, which is shown again when the same maze is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct AnalysisKey {
+    maze: MazeKey,
+    from: Dims3D,
+    to: Dims3D,
+}
+
+impl CacheKey for AnalysisKey {
+    type Value = MazeStats;
+}
+
+/// Statistics of the maze of the game, from the start to the goal
+pub fn maze_stats(game: &RunningGame, cache: &mut Cache) -> MazeStats {
+    let key = AnalysisKey {
+        maze: MazeKey::of(game),
+        from: game.get_start_pos(),
+        to: game.get_goal_pos(),
+    };
+    cache
+        .get_or_insert_with(key, || game.get_maze().analyze(key.from, key.to))
+        .as_ref()
+        .clone()
+}
+
+/// How the run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Won,
+    /// Time limit ran out
+    TimeUp,
+    /// Opponent reached the goal first
+    Beaten,
+}
+
+impl Outcome {
+    pub fn title(self) -> &'static str {
+        match self {
+            Outcome::Won => "You won",
+            Outcome::TimeUp => "Time's up",
+            Outcome::Beaten => "Opponent won",
+        }
+    }
+}
+
+/// Animation drawn behind the end screen, like confetti after a win
+pub trait EndGameEffect {
+    /// Advances the animation, called every [`super::app::TICK`]
+    fn tick(&mut self, screen_size: Dims);
+
+    fn draw(&self, frame: &mut Frame, theme: &Theme);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EndAction {
+    Retry,
+    NewMaze,
+    Analyze,
+    Share,
+    Remix,
+    Menu,
+}
+
+/// How many cells around the path are part of the remixed maze
+const REMIX_RADIUS: i32 = 1;
+
+const ACTIONS: [(EndAction, &str); 6] = [
+    (EndAction::Retry, "Retry"),
+    (EndAction::NewMaze, "New maze"),
+    (EndAction::Remix, "Remix"),
+    (EndAction::Analyze, "Analyze"),
+    (EndAction::Share, "Share"),
+    (EndAction::Menu, "Menu"),
+];
+
+/// Results of the run, shown after it ends
+pub struct EndGameActivity {
+    outcome: Outcome,
+    time: Option<Duration>,
+    /// Real time of the run, with the pauses
+    wall_time: Option<Duration>,
+    /// Whether the real time is shown as the main result instead of the game time
+    real_time: bool,
+    moves: usize,
+    /// Coins picked up and all coins of the maze, `None` if there were none
+    coins: Option<(usize, usize)>,
+    /// How many times the stamina ran out, `None` if the run had no stamina
+    exhausted: Option<usize>,
+    /// Best result of the mode before this run, without the recorded run
+    best: Option<SolveResult>,
+    new_best: bool,
+    assisted: bool,
+    stats: MazeStats,
+    log: RunLog,
+    /// Date of the run in the history, `None` if it wasn't saved, so it can't be tagged
+    run_date: Option<DateTime<Local>>,
+    tags: Vec<String>,
+
+    retry: RunningGame,
+    /// Cells the player went through, from the start, remixed into a new maze
+    path: Vec<Dims3D>,
+    game_mode: GameMode,
+    gen_fn: GeneratorFn,
+    rules: GameRules,
+
+    buttons: Vec<Button>,
+    /// Buttons wrap to more rows on narrow screens
+    button_rows: i32,
+    /// Buttons by their index in [`ACTIONS`]
+    hits: HitMap<usize>,
+    focus: FocusManager,
+    effect: Option<Box<dyn EndGameEffect>>,
+    /// Time without any event before the screen is left on its own, zero disables it
+    auto_return: Duration,
+    idle_ticks: u32,
+}
+
+impl EndGameActivity {
+    /// Creates the screen, `best` is the best result before this run
+    pub fn new(
+        data: &GameData,
+        outcome: Outcome,
+        best: Option<SolveResult>,
+        cache: &mut Cache,
+    ) -> Self {
+        let game = &data.game;
+        let time = game.get_elapsed().filter(|_| !data.rules.relaxed);
+        let wall_time = game.get_wall_elapsed().filter(|_| !data.rules.relaxed);
+        let moves = game.get_move_count();
+        let coins =
+            Some((game.get_coins(), game.get_total_coins())).filter(|(_, total)| *total > 0);
+
+        // same condition as in `SaveData::set_best_result`
+        let eligible = outcome == Outcome::Won && !data.assisted && !data.rules.custom;
+        let new_best = eligible
+            && time.is_some_and(|time| {
+                best.as_ref().is_none_or(|best| {
+                    time.as_secs_f32() < best.seconds && moves as i32 <= best.moves
+                })
+            });
+
+        let stats = maze_stats(game, cache);
+
+        let mut buttons: Vec<_> = ACTIONS
+            .iter()
+            .map(|(_, text)| {
+                Button::new(
+                    text.to_string(),
+                    Dims(0, 0),
+                    Dims(text.width() as i32 + 4, 3),
+                )
+            })
+            .collect();
+
+        // first button is focused by default
+        let focus = FocusManager::new(buttons.len());
+        buttons[0].set = true;
+
+        let effect: Option<Box<dyn EndGameEffect>> = match outcome {
+            Outcome::Won => Some(Box::new(Confetti::new())),
+            _ => None,
+        };
+
+        Self {
+            outcome,
+            time,
+            wall_time,
+            real_time: false,
+            moves,
+            coins,
+            exhausted: data.stamina.as_ref().map(StaminaMeter::exhausted),
+            best,
+            new_best,
+            assisted: data.assisted,
+            stats,
+            log: data.log.clone(),
+            run_date: None,
+            tags: vec![],
+            retry: game.restarted(),
+            path: [game.get_start_pos()]
+                .into_iter()
+                .chain(
+                    game.get_moves()
+                        .iter()
+                        .map(|(from, dir)| *from + dir.to_coord()),
+                )
+                .collect(),
+            game_mode: game.get_game_mode(),
+            gen_fn: game.get_gen_fn(),
+            rules: data.rules,
+            buttons,
+            button_rows: 1,
+            hits: HitMap::new(),
+            focus,
+            effect,
+            auto_return: Duration::ZERO,
+            idle_ticks: 0,
+        }
+    }
+
+    /// Replaces the animation, `None` disables it
+    pub fn with_effect(mut self, effect: Option<Box<dyn EndGameEffect>>) -> Self {
+        self.effect = effect;
+        self
+    }
+
+    /// Shows the real time of the run and the best result instead of the game time
+    pub fn with_real_time(mut self, real_time: bool) -> Self {
+        self.real_time = real_time;
+        self
+    }
+
+    /// Leaves the screen after this long without any event, see [`Self::auto_return_change`]
+    pub fn with_auto_return(mut self, auto_return: Duration) -> Self {
+        self.auto_return = auto_return;
+        self
+    }
+
+    /// Run in the history, so it can be tagged
+    pub fn with_run_date(mut self, date: Option<DateTime<Local>>) -> Self {
+        self.run_date = date;
+        self
+    }
+
+    /// Stage started after a won stage when the screen is left on its own
+    fn next_stage(&self) -> Option<usize> {
+        let next = self.rules.stage? + 1;
+        (self.outcome == Outcome::Won && next < STAGES.len()).then_some(next)
+    }
+
+    fn auto_return_left(&self) -> Option<Duration> {
+        (!self.auto_return.is_zero())
+            .then(|| self.auto_return.saturating_sub(TICK * self.idle_ticks))
+    }
+
+    /// Next stage of the campaign after a won stage, otherwise the main menu
+    fn auto_return_change(&self) -> Change {
+        match self.next_stage() {
+            Some(next) => {
+                log::info!("End screen idle, starting stage {}", next + 1);
+                Change::replace(Stage::generation(next))
+            }
+            None => {
+                log::info!("End screen idle, going back to the main menu");
+                Change::pop_until("main menu")
+            }
+        }
+    }
+
+    fn tag(&self, data: &AppData) -> Option<Change> {
+        let Some(date) = self.run_date else {
+            log::warn!("Only saved runs can be tagged");
+            return None;
+        };
+
+        Some(Change::push(Activity::new_base_boxed(
+            "tags",
+            TagDialog::new(date, &self.tags, data.save.history()),
+        )))
+    }
+
+    /// Time shown as the main result
+    fn shown_time(&self) -> Option<Duration> {
+        match self.real_time {
+            true => self.wall_time.or(self.time),
+            false => self.time,
+        }
+    }
+
+    fn focusable(buttons: &mut [Button]) -> Vec<&mut dyn Focusable> {
+        buttons
+            .iter_mut()
+            .map(|b| b as &mut dyn Focusable)
+            .collect()
+    }
+
+    fn move_focus(&mut self, forward: bool) {
+        let change = match forward {
+            true => self.focus.focus_next(),
+            false => self.focus.focus_prev(),
+        };
+
+        if let Some(change) = change {
+            change.dispatch(&mut Self::focusable(&mut self.buttons));
+        }
+    }
+
+    fn focus_button(&mut self, index: usize) {
+        if let Some(change) = self.focus.focus(index) {
+            change.dispatch(&mut Self::focusable(&mut self.buttons));
+        }
+    }
+
+    /// Ratio of the optimal solution to the moves made, only for finished runs
+    fn efficiency(&self) -> Option<f32> {
+        let optimal = self.stats.solution_len?;
+        (self.outcome == Outcome::Won && self.moves > 0)
+            .then(|| (optimal as f32 / self.moves as f32).min(1.0))
+    }
+
+    fn best_text(&self) -> Option<(String, bool)> {
+        let best = self.best.as_ref()?;
+        let (best_moves, best_secs) = (best.moves, best.time(self.real_time));
+        let best_time = Duration::from_secs_f32(best_secs);
+
+        let Some(time) = self.shown_time() else {
+            return Some((
+                format!(
+                    "Best: {} in {} moves",
+                    format_duration(best_time),
+                    best_moves
+                ),
+                false,
+            ));
+        };
+
+        let delta = time.as_secs_f32() - best_secs;
+        let sign = if delta < 0.0 { "-" } else { "+" };
+        let text = format!(
+            "Best: {} ({}{:.1}s), {} moves ({:+})",
+            format_duration(best_time),
+            sign,
+            delta.abs(),
+            best_moves,
+            self.moves as i32 - best_moves,
+        );
+
+        Some((text, delta < 0.0))
+    }
+
+    fn lines(&self) -> Vec<(String, &'static str)> {
+        let mut lines = vec![(format!("{} moves", self.moves), "ui.end.text")];
+        if let Some((coins, total)) = self.coins {
+            lines.push((format!("Coins: {}/{}", coins, total), "ui.end.text"));
+        }
+        if let Some(exhausted) = self.exhausted {
+            let text = format!("Out of stamina: {}x", exhausted);
+            lines.push((text, "ui.end.text"));
+        }
+        if !self.rules.custom {
+            lines.push((format!("Seed: {}", self.retry.get_seed()), "ui.end.dim"));
+        }
+
+        // the other clock, under the main result
+        let other = match self.real_time {
+            true => self.time.map(|time| ("Game time", time)),
+            false => self.wall_time.map(|time| ("Real time", time)),
+        };
+        if let Some((label, time)) = other.filter(|_| self.time.is_some()) {
+            let text = format!("{}: {}", label, format_duration(time));
+            lines.push((text, "ui.end.dim"));
+        }
+
+        if self.new_best {
+            lines.push(("New personal best!".to_string(), "ui.end.better"));
+        } else if let Some((text, better)) = self.best_text() {
+            let style = if better {
+                "ui.end.better"
+            } else {
+                "ui.end.worse"
+            };
+            lines.push((text, style));
+        }
+
+        if let (Some(index), Some(time)) = (self.rules.stage, self.time) {
+            let stage = &STAGES[index];
+            let stars = stars_text(stage.stars(time.as_secs_f32()));
+            let text = format!("Stage {}: {} {}", index + 1, stage.name, stars);
+            lines.push((text, "ui.end.title"));
+        }
+
+        lines.push((String::new(), "ui.end.text"));
+
+        lines.push((format!("Level: {}", self.stats.difficulty()), "ui.end.text"));
+        if let (Some(efficiency), Some(optimal)) = (self.efficiency(), self.stats.solution_len) {
+            let text = format!(
+                "Efficiency: {:.0}% ({} optimal moves)",
+                efficiency * 100.0,
+                optimal
+            );
+            lines.push((text, "ui.end.text"));
+        }
+        lines.push((self.stats.describe(), "ui.end.dim"));
+
+        if self.assisted {
+            lines.push(("Assisted run, result not saved".to_string(), "ui.end.dim"));
+        }
+        if let Some(left) = self.auto_return_left() {
+            let target = match self.next_stage() {
+                Some(_) => "Next stage",
+                None => "Main menu",
+            };
+            let text = format!("{} in {}s", target, left.as_secs_f32().ceil() as u64);
+            lines.push((text, "ui.end.dim"));
+        }
+        if !self.tags.is_empty() {
+            lines.push((format!("Tags: {}", self.tags.join(", ")), "ui.end.dim"));
+        }
+
+        lines
+    }
+
+    /// Places the buttons centered under the content, wrapping them on narrow screens
+    fn layout_buttons(&mut self, screen: Dims) {
+        let mut rows: Vec<Vec<usize>> = vec![vec![]];
+        let mut row_width = 0;
+
+        for (i, button) in self.buttons.iter().enumerate() {
+            let width = button.size().0 + 1;
+            if row_width + width > screen.0 && !rows.last().unwrap().is_empty() {
+                rows.push(vec![]);
+                row_width = 0;
+            }
+            rows.last_mut().unwrap().push(i);
+            row_width += width;
+        }
+
+        self.button_rows = rows.len() as i32;
+        let top =
+            (screen.1 - self.content_height()) / 2 + self.content_height() - rows.len() as i32 * 3;
+
+        for (row, indices) in rows.iter().enumerate() {
+            let width: i32 = indices.iter().map(|&i| self.buttons[i].size().0 + 1).sum();
+            let mut x = (screen.0 - width + 1) / 2;
+
+            for &i in indices {
+                self.buttons[i].pos = Dims(x, top + row as i32 * 3);
+                x += self.buttons[i].size().0 + 1;
+            }
+        }
+
+        self.hits.clear();
+        for (i, button) in self.buttons.iter().enumerate() {
+            self.hits.register(button.rect(), i);
+        }
+    }
+
+    fn content_height(&self) -> i32 {
+        // title, gap, big number, gap, lines, gap, buttons
+        1 + 1 + BIG_HEIGHT + 1 + self.lines().len() as i32 + 1 + self.button_rows * 3
+    }
+
+    fn act(&self, action: EndAction, data: &mut AppData) -> Option<Change> {
+        match action {
+            EndAction::Retry => {
+                let game = GameData::new(self.retry.restarted(), self.rules, 0, &data.settings);
+                Some(Change::replace(Activity::new_base_boxed(
+                    "game",
+                    GameActivity::new(game, data),
+                )))
+            }
+            EndAction::NewMaze => {
+                // random maze of the same size, which is not the daily challenge
+                // or the campaign stage anymore
+                let rules = GameRules {
+                    daily: None,
+                    stage: None,
+                    ..self.rules
+                };
+                Some(Change::replace(Activity::new_base_boxed(
+                    "game",
+                    MazeGenerationActivity::new(self.game_mode, self.gen_fn).with_rules(rules),
+                )))
+            }
+            EndAction::Remix if self.path.len() < 2 => {
+                log::warn!("Nothing to remix, the player didn't move");
+                None
+            }
+            EndAction::Remix => Some(Change::replace(Activity::new_base_boxed(
+                "game",
+                GameActivity::new(self.remix(&data.settings), data),
+            ))),
+            EndAction::Analyze => Some(Change::push(Activity::new_base_boxed(
+                "analysis",
+                self.analysis_popup(),
+            ))),
+            EndAction::Share => Some(Change::push(Activity::new_base_boxed(
+                "share",
+                self.share_popup(),
+            ))),
+            EndAction::Menu => Some(Change::pop_top()),
+        }
+    }
+
+    /// New maze in the shape of the path of this run, from its start to where it ended
+    ///
+    /// It's a maze of its own, so the result is not saved, like with mazes from files.
+    fn remix(&self, settings: &Settings) -> GameData {
+        let mask = CellMask::from_path(self.game_mode.size, &self.path, REMIX_RADIUS);
+        let (mask, offset) = mask.crop();
+        let seed = random_seed();
+        let start = self.path[0] - offset;
+        let goal = self.path[self.path.len() - 1] - offset;
+
+        let game = RunningGame::from_maze(
+            mask.generate(seed),
+            start,
+            vec![goal],
+            GoalObjective::default(),
+            seed,
+            self.gen_fn,
+        );
+        let rules = GameRules {
+            endless: false,
+            daily: None,
+            stage: None,
+            custom: true,
+            shifting: None,
+            race: false,
+            online: false,
+            ..self.rules
+        };
+        GameData::new(game, rules, 0, settings)
+    }
+
+    fn analysis_popup(&self) -> Popup {
+        let stats = &self.stats;
+        let Dims3D(w, h, d) = stats.size;
+
+        let mut texts = vec![
+            format!("Size:        {}x{}x{}", w, h, d),
+            format!("Difficulty:  {}", stats.difficulty()),
+            format!(
+                "Dead ends:   {} ({:.0}%)",
+                stats.dead_ends,
+                stats.dead_end_ratio() * 100.0
+            ),
+            format!("Junctions:   {}", stats.junctions),
+            format!("Staircases:  {}", stats.stairs),
+            format!("Twistiness:  {:.0}%", stats.twistiness() * 100.0),
+        ];
+
+        if let Some(len) = stats.solution_len {
+            texts.push(format!("Solution:    {} moves, {} turns", len, stats.turns));
+        }
+
+        texts.push(format!("Your moves:  {}", self.moves));
+        if let Some(efficiency) = self.efficiency() {
+            texts.push(format!("Efficiency:  {:.0}%", efficiency * 100.0));
+        }
+
+        let pauses = self.log.count(|e| *e == RunEvent::Pause);
+        texts.push(format!("Pauses:      {}", pauses));
+        if let Some(exhausted) = self.exhausted {
+            texts.push(format!("Exhausted:   {}", exhausted));
+        }
+        if d > 1 {
+            let floors = self.log.count(|e| matches!(e, RunEvent::Floor { .. }));
+            texts.push(format!("Floor moves: {}", floors));
+        }
+        if let (Some(time), Some(wall_time)) = (self.time, self.wall_time) {
+            texts.push(format!("Game time:   {}", format_duration(time)));
+            texts.push(format!("Real time:   {}", format_duration(wall_time)));
+        }
+        if self.time.is_some() {
+            let idle = format_duration(self.log.longest_idle());
+            texts.push(format!("Longest gap: {}", idle));
+        }
+
+        Popup::new("Analysis".to_string(), texts)
+    }
+
+    /// Short text of the result, for sharing with friends
+    fn share_text(&self) -> String {
+        let mut text = format!("TMaze: {}", self.stats.describe());
+        match self.time {
+            Some(time) => {
+                text += &format!(
+                    " - {} in {}, {} moves",
+                    self.outcome.title().to_lowercase(),
+                    format_duration(time),
+                    self.moves
+                )
+            }
+            None => {
+                text += &format!(
+                    " - {} in {} moves",
+                    self.outcome.title().to_lowercase(),
+                    self.moves
+                )
+            }
+        }
+        text
+    }
+
+    fn share_popup(&self) -> Popup {
+        let text = self.share_text();
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let path = export_path().join(format!("result-{}.txt", time));
+
+        let res = fs::create_dir_all(export_path()).and_then(|_| fs::write(&path, &text));
+        match res {
+            Ok(()) => {
+                log::info!("Result saved to {}", path.display());
+                Popup::new(
+                    "Share".to_string(),
+                    vec![text, String::new(), format!("Saved to {}", path.display())],
+                )
+            }
+            Err(err) => {
+                log::error!("Failed to save the result: {}", err);
+                Popup::new(
+                    "Share".to_string(),
+                    vec![text, String::new(), err.to_string()],
+                )
+            }
+        }
+    }
+}
+
+impl ActivityHandler for EndGameActivity {
+    fn tick(&mut self, data: &mut AppData) {
+        if let Some(effect) = &mut self.effect {
+            effect.tick(data.screen_size);
+        }
+        self.idle_ticks = self.idle_ticks.saturating_add(1);
+    }
+
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        self.layout_buttons(data.screen_size);
+
+        if !events.is_empty() {
+            self.idle_ticks = 0;
+        } else if self.auto_return_left().is_some_and(|left| left.is_zero()) {
+            return Some(self.auto_return_change());
+        }
+
+        let (events, changes) = self.focus.handle_events(events);
+        for change in changes {
+            change.dispatch(&mut Self::focusable(&mut self.buttons));
+        }
+
+        for event in events {
+            match event {
+                Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) if !is_release(kind) => {
+                    let action = match code {
+                        KeyCode::Left => {
+                            self.move_focus(false);
+                            continue;
+                        }
+                        KeyCode::Right => {
+                            self.move_focus(true);
+                            continue;
+                        }
+                        KeyCode::Enter | KeyCode::Char(' ') => {
+                            ACTIONS[self.focus.focused().unwrap_or(0)].0
+                        }
+                        KeyCode::Char('r') => EndAction::Retry,
+                        KeyCode::Char('n') => EndAction::NewMaze,
+                        KeyCode::Char('a') => EndAction::Analyze,
+                        KeyCode::Char('s') => EndAction::Share,
+                        KeyCode::Char('x') => EndAction::Remix,
+                        KeyCode::Char('m') | KeyCode::Esc => EndAction::Menu,
+                        KeyCode::Char('q') => return Some(Change::pop_all()),
+                        KeyCode::Char('t') => return self.tag(data),
+                        _ => continue,
+                    };
+
+                    return self.act(action, data);
+                }
+                Event::ActiveAfterPop(Some(res)) => {
+                    if let Ok(tags) = res.downcast::<TagsSet>() {
+                        self.tags = tags.0;
+                    }
+                }
+                Event::Term(TermEvent::Mouse(event)) => match self.hits.dispatch(&event) {
+                    Some(Hit::Hover(index)) => self.focus_button(index),
+                    Some(Hit::Click(index)) => {
+                        self.focus_button(index);
+                        return self.act(ACTIONS[index].0, data);
+                    }
+                    None => {}
+                },
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![
+            Hint::new("R", "retry"),
+            Hint::new("N", "new maze"),
+            Hint::new("X", "remix"),
+            Hint::new("M", "menu"),
+            Hint::new("A", "analyze"),
+            Hint::new("S", "share"),
+            Hint::new("T", "tag"),
+        ]
+    }
+}
+
+impl Screen for EndGameActivity {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        if let Some(effect) = &self.effect {
+            effect.draw(frame, theme);
+        }
+
+        let width = frame.size.0;
+        let centered = |text: &str| (width - text.width() as i32) / 2;
+        let mut y = (frame.size.1 - self.content_height()) / 2;
+
+        let title = self.outcome.title();
+        frame.draw(Dims(centered(title), y), title, theme["ui.end.title"]);
+        y += 2;
+
+        // time is the main result, moves are shown instead in relaxed runs
+        let big = match self.shown_time() {
+            Some(time) => format_duration(time),
+            None => self.moves.to_string(),
+        };
+        let big_lines = big_text(&big);
+        for (i, line) in big_lines.iter().enumerate() {
+            frame.draw(
+                Dims(centered(line), y + i as i32),
+                line.as_str(),
+                theme["ui.end.big"],
+            );
+        }
+        y += BIG_HEIGHT + 1;
+
+        for (text, style) in self.lines() {
+            frame.draw(Dims(centered(&text), y), text.as_str(), theme[style]);
+            y += 1;
+        }
+
+        for button in &self.buttons {
+            button.draw_colored(frame, theme);
+        }
+
+        Ok(())
+    }
+}
+
+const BIG_HEIGHT: i32 = 3;
+
+/// Renders digits and time separators three characters tall, other characters are skipped
+fn big_text(text: &str) -> [String; BIG_HEIGHT as usize] {
+    let mut lines: [String; 3] = Default::default();
+
+    for ch in text.chars() {
+        let glyph = match ch {
+            '0' => ["┌─┐", "│ │", "└─┘"],
+            '1' => [" ┐ ", " │ ", " ┴ "],
+            '2' => ["╶─┐", "┌─┘", "└─╴"],
+            '3' => ["╶─┐", " ─┤", "╶─┘"],
+            '4' => ["╷ ╷", "└─┤", "  ╵"],
+            '5' => ["┌─╴", "└─┐", "╶─┘"],
+            '6' => ["┌─╴", "├─┐", "└─┘"],
+            '7' => ["╶─┐", "  │", "  ╵"],
+            '8' => ["┌─┐", "├─┤", "└─┘"],
+            '9' => ["┌─┐", "└─┤", "╶─┘"],
+            '.' => [" ", " ", "."],
+            'm' => [" ", "·", "·"],
+            _ => continue,
+        };
+
+        for (line, part) in lines.iter_mut().zip(glyph) {
+            line.push_str(part);
+            line.push(' ');
+        }
+    }
+
+    lines.map(|line| line.trim_end().to_string())
+}
+
+/// Confetti falling from the top of the screen for a few seconds
+pub struct Confetti {
+    particles: Vec<Particle>,
+    /// Ticks left to spawn new particles
+    spawning: u32,
+}
+
+struct Particle {
+    pos: (f32, f32),
+    vel: (f32, f32),
+    ch: char,
+    style: usize,
+}
+
+impl Confetti {
+    const CHARS: [char; 5] = ['*', '•', '+', '°', '~'];
+    const STYLES: [&'static str; 4] = [
+        "ui.end.confetti.1",
+        "ui.end.confetti.2",
+        "ui.end.confetti.3",
+        "ui.end.confetti.4",
+    ];
+    /// Two seconds of new particles
+    const SPAWN_TICKS: u32 = 40;
+    const GRAVITY: f32 = 0.02;
+
+    pub fn new() -> Self {
+        Self {
+            particles: vec![],
+            spawning: Self::SPAWN_TICKS,
+        }
+    }
+}
+
+impl Default for Confetti {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EndGameEffect for Confetti {
+    fn tick(&mut self, screen_size: Dims) {
+        let mut rng = thread_rng();
+
+        if self.spawning > 0 {
+            self.spawning -= 1;
+
+            let count = (screen_size.0 / 20).max(1);
+            for _ in 0..count {
+                self.particles.push(Particle {
+                    pos: (rng.gen_range(0.0..screen_size.0 as f32), 0.0),
+                    vel: (rng.gen_range(-0.3..0.3), rng.gen_range(0.1..0.4)),
+                    ch: *Self::CHARS.choose(&mut rng).unwrap(),
+                    style: rng.gen_range(0..Self::STYLES.len()),
+                });
+            }
+        }
+
+        for particle in &mut self.particles {
+            particle.vel.0 *= 0.95;
+            particle.vel.1 += Self::GRAVITY;
+            particle.pos.0 += particle.vel.0;
+            particle.pos.1 += particle.vel.1;
+        }
+
+        self.particles
+            .retain(|p| p.pos.1 < screen_size.1 as f32 && p.pos.0 >= 0.0);
+    }
+
+    fn draw(&self, frame: &mut Frame, theme: &Theme) {
+        for particle in &self.particles {
+            let pos = Dims(particle.pos.0 as i32, particle.pos.1 as i32);
+            frame.draw(pos, particle.ch, theme[Self::STYLES[particle.style]]);
+        }
+    }
+}
+
+pub fn end_game_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("ui.end.text", "text")
+        .desc("Text of the end screen")
+        .link("ui.end.title", "highlight")
+        .desc("Title of the end screen")
+        .link("ui.end.big", "ui.end.text")
+        .desc("Big time or moves on the end screen")
+        .link("ui.end.dim", "dim")
+        .desc("Less important text of the end screen")
+        .link("ui.end.better", "ui.end.text")
+        .desc("Results better than before")
+        .link("ui.end.worse", "ui.end.text")
+        .desc("Results worse than before")
+        .link("ui.end.confetti.1", "highlight")
+        .desc("Confetti on the end screen, first color")
+        .link("ui.end.confetti.2", "highlight")
+        .desc("Confetti on the end screen, second color")
+        .link("ui.end.confetti.3", "highlight")
+        .desc("Confetti on the end screen, third color")
+        .link("ui.end.confetti.4", "highlight")
+        .desc("Confetti on the end screen, fourth color");
+
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use cmaze::{
+        game::{MoveMode, RunningGameState},
+        gameboard::{
+            algorithms::{MazeAlgorithm, RndKruskals},
+            CellWall, Maze,
+        },
+    };
+
+    use super::*;
+
+    const START: Dims3D = Dims3D(1, 0, 0);
+
+    /// Run on a corridor, the player walked from the middle to the goal at the end
+    fn finished(rules: GameRules) -> GameData {
+        let mut maze = Maze::new_walled(Dims3D(3, 1, 1), false);
+        maze.remove_wall(Dims3D(0, 0, 0), CellWall::Right);
+        maze.remove_wall(Dims3D(1, 0, 0), CellWall::Right);
+
+        let mut game = RunningGame::from_maze(
+            maze,
+            START,
+            vec![Dims3D(2, 0, 0)],
+            GoalObjective::ReachAny,
+            1,
+            RndKruskals::generate_seeded,
+        );
+        game.start().unwrap();
+        game.move_player(CellWall::Right, MoveMode::Slow, false)
+            .unwrap();
+        GameData::new(game, rules, 0, &Settings::default())
+    }
+
+    fn end(data: &GameData, outcome: Outcome, best: Option<SolveResult>) -> EndGameActivity {
+        EndGameActivity::new(data, outcome, best, &mut Cache::default())
+    }
+
+    fn best(moves: i32, seconds: f32) -> SolveResult {
+        SolveResult {
+            moves,
+            seconds,
+            wall_seconds: None,
+            run: vec![],
+            env: None,
+        }
+    }
+
+    #[test]
+    fn retry_restarts_from_the_start() {
+        let data = finished(GameRules::default());
+        let end = end(&data, Outcome::Won, None);
+
+        assert_eq!(end.retry.get_state(), RunningGameState::NotStarted);
+        assert_eq!(end.retry.get_player_pos(), START);
+        assert_eq!(end.retry.get_move_count(), 0);
+        assert_eq!(end.path, vec![START, Dims3D(2, 0, 0)]);
+        assert_eq!(end.efficiency(), Some(1.0));
+    }
+
+    #[test]
+    fn new_best_only_for_eligible_wins() {
+        let data = finished(GameRules::default());
+        let won = end(&data, Outcome::Won, None);
+        assert!(won.new_best);
+        assert!(won.effect.is_some());
+
+        assert!(end(&data, Outcome::Won, Some(best(10, 1000.0))).new_best);
+        assert!(!end(&data, Outcome::Won, Some(best(0, 0.0))).new_best);
+
+        let lost = end(&data, Outcome::TimeUp, None);
+        assert!(!lost.new_best);
+        assert!(lost.effect.is_none());
+        assert_eq!(lost.efficiency(), None);
+        assert!(!end(&data, Outcome::Beaten, None).new_best);
+
+        let mut assisted = finished(GameRules::default());
+        assisted.assisted = true;
+        assert!(!end(&assisted, Outcome::Won, None).new_best);
+
+        let custom = finished(GameRules {
+            custom: true,
+            ..Default::default()
+        });
+        assert!(!end(&custom, Outcome::Won, None).new_best);
+    }
+
+    #[test]
+    fn relaxed_runs_have_no_time() {
+        let data = finished(GameRules {
+            relaxed: true,
+            ..Default::default()
+        });
+        let end = end(&data, Outcome::Won, None);
+
+        assert_eq!(end.shown_time(), None);
+        assert!(!end.new_best);
+    }
+
+    #[test]
+    fn next_stage_after_a_won_stage() {
+        let stage = |stage| {
+            finished(GameRules {
+                stage: Some(stage),
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(end(&stage(0), Outcome::Won, None).next_stage(), Some(1));
+        assert_eq!(end(&stage(0), Outcome::TimeUp, None).next_stage(), None);
+        let last = stage(STAGES.len() - 1);
+        assert_eq!(end(&last, Outcome::Won, None).next_stage(), None);
+        let free = finished(GameRules::default());
+        assert_eq!(end(&free, Outcome::Won, None).next_stage(), None);
+
+        assert!(matches!(
+            end(&stage(0), Outcome::Won, None).auto_return_change(),
+            Change::Replace(_)
+        ));
+        assert!(matches!(
+            end(&stage(0), Outcome::Beaten, None).auto_return_change(),
+            Change::PopUntil { .. }
+        ));
+    }
+}
@@ -5,4 +5,10 @@ use super::activity::ActivityResult;
 pub enum Event {
     Term(TermEvent),
     ActiveAfterPop(Option<ActivityResult>),
+    /// Settings file was changed on disk and reloaded, see [`crate::settings::Settings::reload_if_changed`]
+    SettingsReloaded,
+    /// Theme file was changed on disk and reloaded, see [`super::app::AppData::reload_theme_if_changed`]
+    ThemeReloaded,
+    /// Running game should pause, asked for from outside of the terminal, like by the HTTP server
+    Pause,
 }
@@ -0,0 +1,187 @@
+//! Structured log of the events of a run, written as JSON lines
+//!
+//! Every line is a single [`LogEntry`], with the game time in milliseconds and
+//! the event itself, e.g. `{"t":1520,"event":"move","dir":"Left",...}`.
+
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use cmaze::{dims::Dims3D, game::RunningGame, gameboard::CellWall};
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::constants::paths::export_path;
+
+use super::end_game::Outcome;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    Start {
+        seed: u64,
+        size: Dims3D,
+        goals: Vec<Dims3D>,
+    },
+    /// Single move of the player, which can go over more cells
+    Move {
+        dir: CellWall,
+        fast: bool,
+        from: Dims3D,
+        to: Dims3D,
+    },
+    /// Last move was taken back, the player returned from `from` to `to`
+    Undo {
+        from: Dims3D,
+        to: Dims3D,
+    },
+    /// Player tried to move into a wall, or to a floor which isn't there
+    Bump {
+        dir: CellWall,
+        at: Dims3D,
+    },
+    /// Player went to other floor, logged right after the move
+    Floor {
+        from: i32,
+        to: i32,
+    },
+    /// Player ran out of stamina, see [`super::stamina`]
+    Exhausted,
+    Pause,
+    Resume,
+    Hint,
+    End {
+        outcome: Outcome,
+        moves: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Game time of the event, in milliseconds
+    pub t: u64,
+    #[serde(flatten)]
+    pub event: RunEvent,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunLog {
+    entries: Vec<LogEntry>,
+}
+
+impl RunLog {
+    pub fn new(game: &RunningGame) -> Self {
+        let mut log = Self::default();
+        log.push(
+            Duration::ZERO,
+            RunEvent::Start {
+                seed: game.get_seed(),
+                size: game.get_maze().size(),
+                goals: game.get_goals().to_vec(),
+            },
+        );
+        log
+    }
+
+    pub fn push(&mut self, time: Duration, event: RunEvent) {
+        self.entries.push(LogEntry {
+            t: time.as_millis() as u64,
+            event,
+        });
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// Number of the logged events
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Events logged after the first `from` ones, so they can be handled only once
+    pub fn since(&self, from: usize) -> impl Iterator<Item = &RunEvent> {
+        self.entries.iter().skip(from).map(|entry| &entry.event)
+    }
+
+    /// Number of events matching the predicate
+    pub fn count(&self, pred: impl Fn(&RunEvent) -> bool) -> usize {
+        self.entries.iter().filter(|e| pred(&e.event)).count()
+    }
+
+    /// Longest time without a move while the game was running
+    pub fn longest_idle(&self) -> Duration {
+        let mut longest = 0;
+        let mut last = None;
+        for entry in &self.entries {
+            match entry.event {
+                RunEvent::Move { .. } | RunEvent::Undo { .. } | RunEvent::End { .. } => {
+                    if let Some(last) = last {
+                        longest = longest.max(entry.t - last);
+                    }
+                    last = Some(entry.t);
+                }
+                RunEvent::Pause => last = None,
+                RunEvent::Start { .. } | RunEvent::Resume => last = Some(entry.t),
+                _ => {}
+            }
+        }
+        Duration::from_millis(longest)
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).expect("log entry should serialize") + "\n")
+            .collect()
+    }
+
+    pub fn from_jsonl(text: &str) -> serde_json::Result<Self> {
+        let entries = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Writes the log to the exports directory, returns its path
+    pub fn write(&self) -> io::Result<PathBuf> {
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let path = export_path().join(format!("run-{}.jsonl", time));
+
+        fs::create_dir_all(export_path())?;
+        fs::write(&path, self.to_jsonl())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jsonl_roundtrip() {
+        let mut log = RunLog::default();
+        log.push(
+            Duration::from_millis(1520),
+            RunEvent::Move {
+                dir: CellWall::Up,
+                fast: false,
+                from: Dims3D(1, 2, 0),
+                to: Dims3D(1, 2, 1),
+            },
+        );
+        log.push(
+            Duration::from_millis(1520),
+            RunEvent::Floor { from: 0, to: 1 },
+        );
+        log.push(Duration::from_secs(2), RunEvent::Pause);
+
+        let text = log.to_jsonl();
+        assert!(text.starts_with(r#"{"t":1520,"event":"move","dir":"Up","#));
+        assert_eq!(text.lines().count(), 3);
+        assert_eq!(RunLog::from_jsonl(&text).unwrap().entries(), log.entries());
+    }
+}
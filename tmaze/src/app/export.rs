@@ -0,0 +1,186 @@
+//! Dialog exporting the maze of the paused game to a file, see [`cmaze::export`]
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use cmaze::{
+    dims::*,
+    export::{ExportFormat, MazeExport},
+    game::RunningGame,
+    gameboard::Maze,
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+
+use crate::{
+    error::AppError,
+    helpers::{is_release, new_export_file},
+    renderer::Frame,
+    settings::theme::Theme,
+    ui::{center_box_in_screen, draw_box, ErrorPopup, Popup, Screen, TextInput},
+};
+
+use super::{app::AppData, Activity, ActivityHandler, Change, Event};
+
+/// Asks for the path and format of the exported maze, format follows the extension
+#[derive(Clone)]
+pub struct ExportDialog {
+    maze: Maze,
+    start: Dims3D,
+    goals: Vec<Dims3D>,
+    path: Vec<Dims3D>,
+    solution: Vec<Dims3D>,
+    format: ExportFormat,
+    input: TextInput,
+}
+
+impl ExportDialog {
+    pub fn new(game: &RunningGame) -> Self {
+        let maze = game.get_maze().clone();
+        let player = game.get_player_pos();
+        let start = game.get_moves().first().map_or(player, |(pos, _)| *pos);
+
+        let path = game
+            .get_moves()
+            .iter()
+            .map(|(pos, _)| *pos)
+            .chain([player])
+            .collect();
+        let solution = game
+            .get_goals()
+            .iter()
+            .filter_map(|goal| maze.solve(start, *goal))
+            .min_by_key(Vec::len)
+            .unwrap_or_default();
+
+        let format = ExportFormat::default();
+        let mut input = TextInput::new();
+        input.set_value(new_export_file(format.extension()).display().to_string());
+
+        Self {
+            maze,
+            start,
+            goals: game.get_goals().to_vec(),
+            path,
+            solution,
+            format,
+            input,
+        }
+    }
+
+    /// Switches to the next format and changes the extension of the path to match it
+    fn cycle_format(&mut self) {
+        let index = ExportFormat::ALL
+            .iter()
+            .position(|f| *f == self.format)
+            .unwrap_or(0);
+        self.format = ExportFormat::ALL[(index + 1) % ExportFormat::ALL.len()];
+
+        let path = PathBuf::from(self.input.value()).with_extension(self.format.extension());
+        self.input.set_value(path.display().to_string());
+    }
+
+    /// Format given by the extension of the path, unknown extensions use the selected one
+    fn format_of(&self, path: &Path) -> ExportFormat {
+        ExportFormat::from_path(path).unwrap_or(self.format)
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let export = MazeExport {
+            maze: &self.maze,
+            start: self.start,
+            goals: &self.goals,
+            path: &self.path,
+            solution: &self.solution,
+        };
+        let format = self.format_of(path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, export.to_bytes(format))
+    }
+
+    fn export(&self) -> Change {
+        let path = PathBuf::from(self.input.value().trim());
+        match self.write(&path) {
+            Ok(()) => {
+                log::info!("Maze exported to {}", path.display());
+                let popup = Popup::new(
+                    "Maze exported".to_string(),
+                    vec!["Saved to:".to_string(), path.display().to_string()],
+                );
+                Change::replace(Activity::new_base_boxed("export", popup))
+            }
+            Err(err) => Change::replace(ErrorPopup::new_activity(&AppError::io(
+                "export the maze",
+                err,
+            ))),
+        }
+    }
+
+    fn size(screen: Dims) -> Dims {
+        Dims((screen.0 - 4).clamp(30, 70), 6)
+    }
+}
+
+impl ActivityHandler for ExportDialog {
+    fn update(&mut self, events: Vec<Event>, _data: &mut AppData) -> Option<Change> {
+        for event in events {
+            let Event::Term(TermEvent::Key(key @ KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Esc => return Some(Change::pop_top()),
+                KeyCode::Enter if !self.input.value().trim().is_empty() => {
+                    return Some(self.export())
+                }
+                KeyCode::Tab => self.cycle_format(),
+                _ => {
+                    self.input.handle_key(key);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+}
+
+impl Screen for ExportDialog {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let size = Self::size(frame.size);
+        let pos = center_box_in_screen(size, frame.size);
+        let inner = size.0 - 4;
+
+        let border = theme["ui_menu_border"];
+        let text = theme["ui_menu_text"];
+        let dim = theme["dim"];
+
+        draw_box(frame, pos, size, border);
+        frame.draw(pos + Dims(3, 0), " Export maze ", theme["ui_menu_title"]);
+
+        self.input.draw(frame, theme, pos + Dims(2, 1), inner);
+        frame.draw(pos + Dims(1, 2), "─".repeat(size.0 as usize - 2), border);
+
+        let format = self.format_of(Path::new(self.input.value().trim()));
+        let format = format!("Format: {}", format.name());
+        frame.draw(pos + Dims(2, 3), format.as_str(), text);
+        let help: String = "Tab: format, Enter: save, Esc: cancel"
+            .chars()
+            .take(inner as usize)
+            .collect();
+        frame.draw(pos + Dims(2, 4), help, dim);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! Experimental first-person view of the corridor in front of the player
+//!
+//! Maze is drawn like in the old dungeon crawlers: cells in the facing direction are layers of
+//! a tunnel, every one smaller than the one before, and walls are shaded by their distance.
+//! It's shown next to the map, see [`crate::settings::Settings::get_first_person_view`].
+
+use cmaze::{
+    dims::*,
+    game::RunningGame,
+    gameboard::{CellContent, CellWall},
+};
+
+use crate::{
+    renderer::{Cell, Frame},
+    settings::theme::{Style, Theme, ThemeResolver},
+};
+
+/// Farthest cell which is drawn
+const MAX_DEPTH: i32 = 6;
+/// Size of every layer of the tunnel relative to the previous one
+const SHRINK: f32 = 0.6;
+/// Shades of the walls, from the nearest one
+const SHADES: [char; 4] = ['█', '▓', '▒', '░'];
+
+fn shade(depth: i32) -> char {
+    SHADES[(depth as usize).min(SHADES.len() - 1)]
+}
+
+/// Distance of the layer at the depth from the sides of the view
+fn inset(size: Dims, depth: i32) -> Dims {
+    let scale = 1.0 - SHRINK.powi(depth);
+    Dims(
+        (size.0 as f32 / 2.0 * scale).round() as i32,
+        (size.1 as f32 / 2.0 * scale).round() as i32,
+    )
+}
+
+/// Fills the column between the rows, both included
+fn column(frame: &mut Frame, x: i32, top: i32, bottom: i32, ch: char, style: Style) {
+    for y in top..=bottom {
+        frame.try_set(Dims(x, y), Cell::styled(ch, style));
+    }
+}
+
+/// View from the player in the `facing` direction
+pub fn render(game: &RunningGame, facing: CellWall, size: Dims, theme: &Theme) -> Frame {
+    let [wall, door, goal, coin, key, stairs] = theme.extract([
+        "game.fp.wall",
+        "game.door",
+        "game.goal",
+        "game.coin",
+        "game.key",
+        "game.stairs",
+    ]);
+
+    let mut frame = Frame::new(size);
+    frame.fill(Cell::styled(' ', theme["game.background"]));
+
+    let maze = game.get_maze();
+    let mut pos = game.get_player_pos();
+
+    for depth in 0..MAX_DEPTH {
+        let (near, far) = (inset(size, depth), inset(size, depth + 1));
+        let Some(cell) = maze.get_cell(pos) else {
+            break;
+        };
+
+        // side walls, or the far wall of the side corridor
+        for x in near.0..far.0 {
+            let t = (x - near.0) as f32 / (far.0 - near.0) as f32;
+            let top = near.1 + ((far.1 - near.1) as f32 * t).round() as i32;
+
+            for (side, x) in [
+                (facing.turn_left(), x),
+                (facing.turn_right(), size.0 - 1 - x),
+            ] {
+                match cell.get_wall(side) {
+                    true => column(&mut frame, x, top, size.1 - 1 - top, shade(depth), wall),
+                    false => {
+                        let ch = shade(depth + 1);
+                        column(&mut frame, x, far.1, size.1 - 1 - far.1, ch, wall)
+                    }
+                }
+            }
+        }
+
+        // things on the floor, the player's own cell is not drawn
+        let mark = match cell.get_content() {
+            _ if depth == 0 => None,
+            _ if game.get_goals().contains(&pos) => Some(('$', goal)),
+            Some(CellContent::Coin) => Some(('¢', coin)),
+            Some(CellContent::Key) => Some(('⚷', key)),
+            _ if !cell.get_wall(CellWall::Up) => Some(('↑', stairs)),
+            _ if !cell.get_wall(CellWall::Down) => Some(('↓', stairs)),
+            _ => None,
+        };
+        if let Some((ch, style)) = mark {
+            let y = size.1 - 1 - (near.1 + far.1) / 2;
+            frame.try_set(Dims(size.0 / 2, y), Cell::styled(ch, style));
+        }
+
+        let next = pos + facing.to_coord();
+        let locked = game.get_keys() == 0
+            && maze
+                .get_cell(next)
+                .is_some_and(|c| c.get_content() == Some(CellContent::Door));
+
+        // wall or a locked door at the end of the corridor
+        if cell.get_wall(facing) || locked || far.0 * 2 >= size.0 - 1 {
+            let (ch, style) = match locked {
+                true => ('▓', door),
+                false => (shade(depth + 1), wall),
+            };
+            for x in far.0..size.0 - far.0 {
+                column(&mut frame, x, far.1, size.1 - 1 - far.1, ch, style);
+            }
+            break;
+        }
+
+        pos = next;
+    }
+
+    frame
+}
+
+pub fn first_person_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("game.fp.wall", "game.walls")
+        .desc("Walls in the first person view")
+        .link("game.fp.border", "game.viewport.border")
+        .desc("Border of the first person view");
+
+    resolver
+}
@@ -0,0 +1,209 @@
+//! Overlay for jumping straight to a floor in spectator mode
+
+use cmaze::{dims::*, game::RunningGame};
+use crossterm::event::{KeyCode, MouseButton, MouseEvent, MouseEventKind};
+use hashbrown::HashSet;
+
+use crate::{
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+    ui::{center_box_in_screen, draw_box},
+};
+
+/// Width of the completion bar, in characters
+const BAR_WIDTH: usize = 10;
+
+/// State of a single floor, shown as one row of the overlay
+#[derive(Debug, Clone, Copy)]
+pub(super) struct FloorInfo {
+    pub visited: usize,
+    pub cells: usize,
+    pub player: bool,
+    pub goals: usize,
+    pub reached_goals: usize,
+}
+
+impl FloorInfo {
+    /// Every floor of the maze of the game, from the lowest one
+    pub fn of_game(game: &RunningGame) -> Vec<Self> {
+        let Dims3D(w, h, d) = game.get_maze().size();
+
+        let visited: HashSet<Dims3D> = game
+            .get_moves()
+            .iter()
+            .map(|(pos, _)| *pos)
+            .chain([game.get_start_pos(), game.get_player_pos()])
+            .collect();
+
+        (0..d)
+            .map(|floor| {
+                let goals = game.get_goals().iter().filter(|g| g.2 == floor);
+                FloorInfo {
+                    visited: visited.iter().filter(|pos| pos.2 == floor).count(),
+                    cells: (w * h) as usize,
+                    player: game.get_player_pos().2 == floor,
+                    goals: goals.clone().count(),
+                    reached_goals: goals.filter(|g| game.is_goal_reached(**g)).count(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn completion(&self) -> f32 {
+        self.visited as f32 / self.cells.max(1) as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloorJumpAction {
+    None,
+    Jump(i32),
+    Close,
+}
+
+/// List of the floors with visited cells and goals, opened with `G` in spectator mode
+#[derive(Debug, Clone)]
+pub struct FloorJump {
+    floors: Vec<FloorInfo>,
+    selected: usize,
+}
+
+impl FloorJump {
+    pub fn new(game: &RunningGame, current: i32) -> Self {
+        Self {
+            floors: FloorInfo::of_game(game),
+            selected: current.max(0) as usize,
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> FloorJumpAction {
+        let count = self.floors.len();
+        match code {
+            KeyCode::Up | KeyCode::Char('w') => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Char('s') => {
+                self.selected = (self.selected + 1) % count;
+            }
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = count - 1,
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                return FloorJumpAction::Jump(self.selected as i32);
+            }
+            // 1-9 are the first floors, 0 is the tenth
+            KeyCode::Char(ch @ '0'..='9') => {
+                let floor = (ch as usize + 9 - '0' as usize) % 10;
+                if floor < count {
+                    return FloorJumpAction::Jump(floor as i32);
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('g' | 'G') => return FloorJumpAction::Close,
+            _ => {}
+        }
+
+        FloorJumpAction::None
+    }
+
+    pub fn handle_mouse(&mut self, event: MouseEvent, screen: Dims) -> FloorJumpAction {
+        let (pos, size, first) = self.layout(screen);
+        let row = event.row as i32 - pos.1 - 1;
+        let inside = (event.column as i32) > pos.0
+            && (event.column as i32) < pos.0 + size.0 - 1
+            && (0..size.1 - 2).contains(&row);
+
+        match event.kind {
+            MouseEventKind::Moved if inside => self.selected = first + row as usize,
+            MouseEventKind::Down(MouseButton::Left) if inside => {
+                return FloorJumpAction::Jump((first + row as usize) as i32);
+            }
+            MouseEventKind::Down(MouseButton::Left) => return FloorJumpAction::Close,
+            MouseEventKind::ScrollDown => {
+                self.selected = (self.selected + 1).min(self.floors.len() - 1);
+            }
+            MouseEventKind::ScrollUp => self.selected = self.selected.saturating_sub(1),
+            _ => {}
+        }
+
+        FloorJumpAction::None
+    }
+
+    /// Position and size of the box, and the first visible floor
+    fn layout(&self, screen: Dims) -> (Dims, Dims, usize) {
+        let rows = (self.floors.len() as i32).min((screen.1 - 4).max(1));
+        let size = Dims(BAR_WIDTH as i32 + 24, rows + 2);
+        let first = (self.selected + 1).saturating_sub(rows as usize);
+        (center_box_in_screen(size, screen), size, first)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, theme: &Theme) {
+        let (pos, size, first) = self.layout(frame.size);
+
+        frame.fill_rect(pos, size, Cell::styled(' ', theme["ui.floors.text"]));
+        draw_box(frame, pos, size, theme["ui.floors.border"]);
+        frame.draw(pos + Dims(2, 0), " Floors ", theme["ui.floors.title"]);
+
+        let rows = (size.1 - 2) as usize;
+        for (row, (index, floor)) in self
+            .floors
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .enumerate()
+        {
+            let line = pos + Dims(2, row as i32 + 1);
+            let selected = index == self.selected;
+            let style = |key: &str| match selected {
+                true => theme[key].invert(),
+                false => theme[key],
+            };
+
+            let filled = (floor.completion() * BAR_WIDTH as f32).round() as usize;
+            let number = format!("{:>2} ", index + 1);
+            let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+            let percent = format!(" {:>3}% ", (floor.completion() * 100.0).round() as i32);
+
+            frame.draw(line, number.as_str(), style("ui.floors.text"));
+            frame.draw(line + Dims(3, 0), bar.as_str(), style("ui.floors.bar"));
+            frame.draw(
+                line + Dims(3 + BAR_WIDTH as i32, 0),
+                percent.as_str(),
+                style("ui.floors.text"),
+            );
+
+            // player and goals of the floor
+            let mut x = line.0 + 3 + BAR_WIDTH as i32 + percent.len() as i32;
+            if floor.player {
+                frame.draw(Dims(x, line.1), "you ", style("ui.floors.player"));
+                x += 4;
+            }
+            if floor.goals > 0 {
+                let (text, key) = match floor.reached_goals == floor.goals {
+                    true => ("✓", "ui.floors.reached"),
+                    false => ("$", "ui.floors.goal"),
+                };
+                frame.draw(Dims(x, line.1), text, style(key));
+            }
+        }
+    }
+}
+
+pub fn floor_jump_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+    resolver
+        .link("ui.floors.border", "ui.popup.border")
+        .desc("Border of the floor jump popup")
+        .link("ui.floors.title", "ui.popup.title")
+        .desc("Title of the floor jump popup")
+        .link("ui.floors.text", "ui.popup.text")
+        .desc("Floors in the floor jump popup")
+        .link("ui.floors.bar", "game.visited")
+        .desc("Bars of visited cells in the floor jump popup")
+        .link("ui.floors.player", "game.player")
+        .desc("Floor of the player in the floor jump popup")
+        .link("ui.floors.goal", "game.goal")
+        .desc("Floors with goals in the floor jump popup")
+        .link("ui.floors.reached", "game.goal.reached")
+        .desc("Floors with reached goals in the floor jump popup");
+    resolver
+}
@@ -0,0 +1,287 @@
+//! Overview of all floors of a maze, opened with `Tab` in mazes with more floors
+//!
+//! Floors are drawn side by side as thumbnails, with the share of visited cells and their goals.
+//! Selecting one jumps to it in spectator mode, see [`GameViewMode::Overview`]. Thumbnails are
+//! the floors of the maze board at the zoom or level of detail which fits the tiles.
+
+use cmaze::{dims::*, game::RunningGame, gameboard::CellWall};
+use crossterm::event::{KeyCode, MouseEvent, MouseEventKind};
+
+use crate::{
+    helpers::{maze2screen_3d, Zoom},
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+    ui::{draw_box, Hit, HitMap, Rect},
+};
+
+use super::{
+    floor_jump::{FloorInfo, FloorJumpAction},
+    game_state::GameViewMode,
+};
+
+/// Smallest tile of a floor, floors which don't fit are scrolled to
+const MIN_TILE: Dims = Dims(16, 6);
+/// Space of a tile taken by its border and the line with the stats
+const TILE_CHROME: Dims = Dims(2, 3);
+
+/// Placement of the tiles of the floors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Grid {
+    area: Rect,
+    columns: usize,
+    tile: Dims,
+    /// First visible row, when not all floors fit
+    first_row: usize,
+}
+
+impl Grid {
+    /// Grid with the biggest thumbnails of the floors of size `floor`
+    fn fit(count: usize, area: Rect, floor: Dims) -> Self {
+        let size = area.size();
+        let max_columns = ((size.0 / MIN_TILE.0).max(1) as usize).min(count.max(1));
+
+        let (columns, tile) = (1..=max_columns)
+            .map(|columns| {
+                let rows = count.div_ceil(columns) as i32;
+                let tile = Dims(
+                    (size.0 / columns as i32).max(MIN_TILE.0),
+                    (size.1 / rows.max(1)).max(MIN_TILE.1),
+                );
+                (columns, tile)
+            })
+            .max_by(|(_, a), (_, b)| scale(*a, floor).total_cmp(&scale(*b, floor)))
+            .expect("there is at least one column");
+
+        Self {
+            area,
+            columns,
+            tile,
+            first_row: 0,
+        }
+    }
+
+    fn visible_rows(&self) -> usize {
+        (self.area.size().1 / self.tile.1).max(1) as usize
+    }
+
+    /// Scrolls so the floor is visible
+    fn scroll_to(&mut self, floor: usize) {
+        let row = floor / self.columns;
+        let rows = self.visible_rows();
+        if row < self.first_row {
+            self.first_row = row;
+        } else if row >= self.first_row + rows {
+            self.first_row = row + 1 - rows;
+        }
+    }
+
+    /// Position of the tile of the floor, `None` if it's scrolled out
+    fn tile_pos(&self, floor: usize) -> Option<Dims> {
+        let (row, column) = (floor / self.columns, floor % self.columns);
+        let row = row.checked_sub(self.first_row)?;
+        (row < self.visible_rows())
+            .then(|| self.area.start + Dims(column as i32 * self.tile.0, row as i32 * self.tile.1))
+    }
+}
+
+/// How much a floor of size `floor` has to be shrunk to fit the tile, bigger is better
+fn scale(tile: Dims, floor: Dims) -> f32 {
+    let inner = tile - TILE_CHROME;
+    (inner.0 as f32 / floor.0.max(1) as f32).min(inner.1 as f32 / floor.1.max(1) as f32)
+}
+
+/// Thumbnails of all floors with their stats, the selected one can be jumped to
+pub struct FloorOverview {
+    floors: Vec<FloorInfo>,
+    selected: usize,
+    /// Mode the overview was opened from, it's restored when the overview is closed
+    pub previous: GameViewMode,
+    grid: Option<Grid>,
+    hits: HitMap<usize>,
+    /// Level of detail of the thumbnails, see [`FloorOverview::thumbnail_size`]
+    pub lod: u32,
+    /// Zoom of the thumbnails in full detail
+    pub zoom: Zoom,
+}
+
+impl FloorOverview {
+    pub fn new(game: &RunningGame, current: i32, previous: GameViewMode) -> Self {
+        Self {
+            floors: FloorInfo::of_game(game),
+            selected: current.max(0) as usize,
+            previous,
+            grid: None,
+            hits: HitMap::new(),
+            lod: 0,
+            zoom: Zoom::Normal,
+        }
+    }
+
+    /// Places the tiles in the area, `floor` is the size of a rendered floor in full detail
+    pub fn update_layout(&mut self, area: Rect, floor: Dims) {
+        let mut grid = match self.grid {
+            Some(grid) if grid.area == area => grid,
+            _ => Grid::fit(self.floors.len(), area, floor),
+        };
+        grid.scroll_to(self.selected);
+        self.grid = Some(grid);
+
+        self.hits.clear();
+        for floor in 0..self.floors.len() {
+            if let Some(pos) = grid.tile_pos(floor) {
+                self.hits.register(Rect::sized_at(pos, grid.tile), floor);
+            }
+        }
+    }
+
+    /// Space for a thumbnail, the level of detail is picked so the floor fits it
+    pub fn thumbnail_size(&self) -> Option<Dims> {
+        self.grid.map(|grid| grid.tile - TILE_CHROME)
+    }
+
+    /// Moves the selection to the neighbouring tile, floors up and down go by one
+    pub fn select(&mut self, dir: CellWall) {
+        let count = self.floors.len();
+        let columns = self.grid.map_or(1, |grid| grid.columns);
+        self.selected = match dir {
+            CellWall::Left | CellWall::Down => self.selected.saturating_sub(1),
+            CellWall::Right | CellWall::Up => (self.selected + 1).min(count - 1),
+            CellWall::Top => self.selected.checked_sub(columns).unwrap_or(self.selected),
+            CellWall::Bottom if self.selected + columns < count => self.selected + columns,
+            CellWall::Bottom => self.selected,
+        };
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> FloorJumpAction {
+        match code {
+            KeyCode::Up | KeyCode::Char('w') => self.select(CellWall::Top),
+            KeyCode::Down | KeyCode::Char('s') => self.select(CellWall::Bottom),
+            KeyCode::Left | KeyCode::Char('a') => self.select(CellWall::Left),
+            KeyCode::Right | KeyCode::Char('d') => self.select(CellWall::Right),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = self.floors.len() - 1,
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                return FloorJumpAction::Jump(self.selected as i32);
+            }
+            // 1-9 are the first floors, 0 is the tenth
+            KeyCode::Char(ch @ '0'..='9') => {
+                let floor = (ch as usize + 9 - '0' as usize) % 10;
+                if floor < self.floors.len() {
+                    return FloorJumpAction::Jump(floor as i32);
+                }
+            }
+            KeyCode::Esc | KeyCode::Tab => return FloorJumpAction::Close,
+            _ => {}
+        }
+
+        FloorJumpAction::None
+    }
+
+    pub fn handle_mouse(&mut self, event: MouseEvent) -> FloorJumpAction {
+        match self.hits.dispatch(&event) {
+            Some(Hit::Hover(floor)) => self.selected = floor,
+            Some(Hit::Click(floor)) => return FloorJumpAction::Jump(floor as i32),
+            None => match event.kind {
+                MouseEventKind::ScrollDown => self.select(CellWall::Bottom),
+                MouseEventKind::ScrollUp => self.select(CellWall::Top),
+                _ => {}
+            },
+        }
+
+        FloorJumpAction::None
+    }
+
+    /// Draws the tiles, `thumbnails` are the floors at [`FloorOverview::lod`] and
+    /// [`FloorOverview::zoom`]
+    pub fn draw(
+        &self,
+        frame: &mut Frame,
+        theme: &Theme,
+        thumbnails: &[Frame],
+        player: (Dims3D, char),
+    ) {
+        let Some(grid) = self.grid else {
+            return;
+        };
+
+        for (index, floor) in self.floors.iter().enumerate() {
+            let Some(pos) = grid.tile_pos(index) else {
+                continue;
+            };
+
+            let border = match index == self.selected {
+                true => theme["ui.overview.selected"],
+                false => theme["ui.overview.border"],
+            };
+            draw_box(frame, pos, grid.tile, border);
+            frame.draw(pos + Dims(2, 0), format!(" {} ", index + 1), border);
+
+            // stats, the most important first, so they can be cut
+            let inner_width = (grid.tile.0 - TILE_CHROME.0) as usize;
+            let mut stats = format!("{}%", (floor.completion() * 100.0).round() as i32);
+            if floor.goals > 0 {
+                stats += &format!(" goals {}/{}", floor.reached_goals, floor.goals);
+            }
+            if floor.player {
+                stats += " you";
+            }
+            let stats: String = stats.chars().take(inner_width).collect();
+            frame.draw(pos + Dims(1, 1), stats.as_str(), theme["ui.overview.text"]);
+
+            let inner = Rect::sized_at(pos + Dims(1, 2), grid.tile - TILE_CHROME);
+            let Some(thumbnail) = thumbnails.get(index) else {
+                continue;
+            };
+            let mut view = Frame::new(inner.size());
+            view.fill(Cell::styled(' ', theme["game.background"]));
+            let offset = (inner.size() - thumbnail.size) / 2;
+            view.draw(offset, thumbnail, ());
+
+            let (player_pos, player_char) = player;
+            if player_pos.2 == index as i32 {
+                let Dims3D(x, y, _) = maze2screen_3d(player_pos);
+                let pos = match self.lod {
+                    0 => self.zoom.board_pos(Dims(x, y)),
+                    lod => Dims((x - 1) / (1 << lod) + 1, (y - 1) / (1 << lod) + 1),
+                };
+                view.draw(offset + pos, player_char, theme["game.player"]);
+            }
+            frame.draw(inner.start, &view, ());
+        }
+    }
+}
+
+pub fn floor_overview_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+    resolver
+        .link("ui.overview.border", "game.viewport.border")
+        .desc("Tiles of the floors in the overview")
+        .link("ui.overview.selected", "highlight")
+        .desc("Selected tile in the overview")
+        .link("ui.overview.text", "text")
+        .desc("Stats of the floors in the overview");
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_fits_the_floors() {
+        let area = Rect::sized(Dims(80, 24));
+
+        // wide floors are stacked, square ones are side by side
+        let wide = Grid::fit(2, area, Dims(61, 11));
+        assert_eq!(wide.columns, 1);
+        let square = Grid::fit(4, area, Dims(21, 21));
+        assert_eq!(square.columns, 4);
+
+        let mut many = Grid::fit(40, area, Dims(21, 21));
+        assert!(many.tile.0 >= MIN_TILE.0 && many.tile.1 >= MIN_TILE.1);
+        assert!(many.tile_pos(39).is_none());
+        many.scroll_to(39);
+        assert!(many.tile_pos(39).is_some());
+        assert!(many.tile_pos(0).is_none());
+    }
+}
@@ -0,0 +1,450 @@
+//! Gallery of interesting generated mazes, like the one with the longest solution
+//!
+//! Every generated maze is compared with the gallery, and archived if it beats all the
+//! mazes of some [`Find`] category. Archived mazes can be played again from the main menu.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use cmaze::{
+    game::{Replay, RunningGame},
+    gameboard::MazeStats,
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{history::RunRecord, SaveData},
+    helpers::{constants::paths::gallery_path, is_release, Zoom},
+    settings::{theme::Theme, MazeGenAlgo, Settings},
+    ui::{Hint, Menu, MenuConfig, Popup, Screen},
+};
+
+use super::{
+    app::AppData,
+    game::{GameActivity, MazeBoard},
+    game_state::{GameData, GameRules},
+    tags::TagFilter,
+    Activity, ActivityHandler, Change, Event,
+};
+
+/// Smaller mazes are never archived
+const MIN_CELLS: usize = 64;
+
+/// Shortest solution of mazes compared by twistiness
+const MIN_TWISTY_SOLUTION: usize = 20;
+
+/// Size of the thumbnail in characters, bigger mazes are cropped
+const THUMBNAIL_SIZE: (usize, usize) = (41, 13);
+
+/// Category of interesting mazes, a maze is archived if it's the best of one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Find {
+    LongestSolution,
+    MostDeadEnds,
+    Twistiest,
+    /// Maze without junctions, so it's a single corridor
+    Corridor,
+}
+
+impl Find {
+    const ALL: [Find; 4] = [
+        Find::LongestSolution,
+        Find::MostDeadEnds,
+        Find::Twistiest,
+        Find::Corridor,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Find::LongestSolution => "Longest solution",
+            Find::MostDeadEnds => "Most dead ends",
+            Find::Twistiest => "Twistiest",
+            Find::Corridor => "Single corridor",
+        }
+    }
+
+    /// Score of the maze in this category, higher is better, `None` if it doesn't qualify
+    fn score(self, stats: &MazeStats) -> Option<f32> {
+        if stats.cells() < MIN_CELLS {
+            return None;
+        }
+
+        match self {
+            Find::LongestSolution => stats.solution_len.map(|len| len as f32),
+            Find::MostDeadEnds => Some(stats.dead_end_ratio()),
+            Find::Twistiest => stats
+                .solution_len
+                .filter(|len| *len >= MIN_TWISTY_SOLUTION)
+                .map(|_| stats.twistiness()),
+            Find::Corridor => (stats.junctions == 0).then_some(stats.cells() as f32),
+        }
+    }
+
+    fn describe_score(self, score: f32) -> String {
+        match self {
+            Find::LongestSolution => format!("{} steps", score),
+            Find::MostDeadEnds => format!("{:.0}% dead ends", score * 100.0),
+            Find::Twistiest => format!("{:.0}% turns", score * 100.0),
+            Find::Corridor => format!("{} cells", score),
+        }
+    }
+}
+
+/// Archived maze, as it's saved in the gallery directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GalleryEntry {
+    pub date: DateTime<Local>,
+    pub find: Find,
+    pub score: f32,
+    /// Summary of the maze, see [`MazeStats::describe`]
+    pub description: String,
+    /// Maze of the entry, without any moves
+    pub maze: Replay,
+    /// First floor drawn as text, cropped to [`THUMBNAIL_SIZE`]
+    pub thumbnail: Vec<String>,
+}
+
+impl GalleryEntry {
+    fn label(&self) -> String {
+        format!(
+            "{} - {} - {}",
+            self.find.title(),
+            self.find.describe_score(self.score),
+            self.date.format("%d %b %H:%M"),
+        )
+    }
+}
+
+/// Archives the maze if it's the best one of some category
+///
+/// Only the first category the maze is the best of is used, so every maze is archived
+/// at most once. Returns the category it was archived in.
+pub fn consider(
+    game: &RunningGame,
+    stats: &MazeStats,
+    settings: &Settings,
+    theme: &Theme,
+) -> io::Result<Option<Find>> {
+    if settings.is_ro() {
+        return Ok(None);
+    }
+
+    let entries = load_entries();
+    let best = |find: Find| {
+        entries
+            .iter()
+            .filter(|(_, e)| e.find == find)
+            .map(|(_, e)| e.score)
+            .reduce(f32::max)
+    };
+
+    let record = Find::ALL.into_iter().find_map(|find| {
+        let score = find.score(stats)?;
+        best(find)
+            .is_none_or(|best| score > best)
+            .then_some((find, score))
+    });
+    let Some((find, score)) = record else {
+        return Ok(None);
+    };
+
+    let date = Local::now();
+    let entry = GalleryEntry {
+        date,
+        find,
+        score,
+        description: stats.describe(),
+        maze: game.restarted().replay(),
+        thumbnail: thumbnail(game, theme),
+    };
+
+    let dir = gallery_path();
+    let path = dir.join(format!("find-{}.ron", date.format("%Y%m%d-%H%M%S%.3f")));
+    let text = ron::to_string(&entry).map_err(io::Error::other)?;
+
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, text)?;
+    prune(settings.get_gallery_size() as usize)?;
+
+    Ok(Some(find))
+}
+
+fn thumbnail(game: &RunningGame, theme: &Theme) -> Vec<String> {
+    let (width, height) = THUMBNAIL_SIZE;
+    MazeBoard::render_floor(game.get_maze(), 0, Zoom::Normal, theme)
+        .to_string()
+        .lines()
+        .take(height)
+        .map(|line| line.chars().take(width).collect())
+        .collect()
+}
+
+/// Removes the oldest entries over the limit
+fn prune(limit: usize) -> io::Result<()> {
+    for (old, _) in load_entries().into_iter().skip(limit) {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Archived mazes, newest first, invalid ones are skipped
+pub fn load_entries() -> Vec<(PathBuf, GalleryEntry)> {
+    let Ok(entries) = fs::read_dir(gallery_path()) else {
+        return vec![];
+    };
+
+    let mut entries: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .filter_map(|path| Some((path.clone(), read_entry(&path)?)))
+        .collect();
+    entries.sort_by_key(|(_, e)| std::cmp::Reverse(e.date));
+    entries
+}
+
+fn read_entry(path: &Path) -> Option<GalleryEntry> {
+    let content = fs::read_to_string(path).ok()?;
+    ron::from_str(&content)
+        .inspect_err(|err| log::warn!("Invalid gallery entry {:?}: {}", path, err))
+        .ok()
+}
+
+/// Result of [`GalleryEntryView`] when the entry was deleted
+struct EntryDeleted;
+
+/// List of the archived mazes, with an option to clear the gallery
+///
+/// Mazes can be filtered by the tags of the runs played on them, see [`TagFilter`].
+pub struct GalleryMenu {
+    menu: Menu,
+    /// Entries which pass the filter
+    entries: Vec<(PathBuf, GalleryEntry)>,
+    history: Vec<RunRecord>,
+    filter: TagFilter,
+}
+
+impl GalleryMenu {
+    pub fn new_activity(save: &SaveData) -> Activity {
+        let entries = load_entries();
+        if entries.is_empty() {
+            return Activity::new_base_boxed(
+                "gallery",
+                Popup::new(
+                    "Gallery".to_string(),
+                    vec![
+                        "No interesting mazes found yet".to_string(),
+                        "Mazes with the longest solution, most dead ends".to_string(),
+                        "and other records are archived here".to_string(),
+                    ],
+                ),
+            );
+        }
+
+        let history = save.history().to_vec();
+        let filter = TagFilter::new(&history);
+        Activity::new_base_boxed("gallery", Self::build(entries, history, filter, 0))
+    }
+
+    fn build(
+        entries: Vec<(PathBuf, GalleryEntry)>,
+        history: Vec<RunRecord>,
+        filter: TagFilter,
+        selected: usize,
+    ) -> Self {
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|(_, entry)| Self::passes(&filter, &history, entry))
+            .collect();
+
+        let mut options: Vec<_> = entries.iter().map(|(_, e)| e.label()).collect();
+        match filter.tag() {
+            Some(_) if entries.is_empty() => {
+                options.push("No mazes played with the tag".to_string())
+            }
+            Some(_) => {}
+            None => options.push("Clear gallery".to_string()),
+        }
+
+        let subtitle = match filter.has_tags() {
+            true => format!("{} - Tab: filter by tag", filter.label()),
+            false => "Interesting mazes found so far".to_string(),
+        };
+        let menu_config = MenuConfig::new_from_strings("Gallery", options)
+            .subtitle(subtitle)
+            .default(selected.min(entries.len()));
+
+        Self {
+            menu: Menu::new(menu_config),
+            entries,
+            history,
+            filter,
+        }
+    }
+
+    /// Whether the maze was played in a run with the tag of the filter
+    fn passes(filter: &TagFilter, history: &[RunRecord], entry: &GalleryEntry) -> bool {
+        filter.tag().is_none()
+            || history
+                .iter()
+                .any(|run| run.seed == entry.maze.seed && filter.matches(run))
+    }
+
+    fn rebuild(&mut self, entries: Vec<(PathBuf, GalleryEntry)>) {
+        let history = std::mem::take(&mut self.history);
+        let filter = std::mem::take(&mut self.filter);
+        *self = Self::build(entries, history, filter, self.menu.selected());
+    }
+
+    fn clear(&mut self) -> Change {
+        for (path, _) in &self.entries {
+            if let Err(err) = fs::remove_file(path) {
+                log::error!("Could not remove {:?}: {}", path, err);
+            }
+        }
+        log::warn!("Gallery cleared");
+        Change::pop_top()
+    }
+}
+
+impl ActivityHandler for GalleryMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        let deleted = events.iter().any(
+            |event| matches!(event, Event::ActiveAfterPop(Some(res)) if res.is::<EntryDeleted>()),
+        );
+        if deleted {
+            let entries = load_entries();
+            if entries.is_empty() {
+                return Some(Change::pop_top());
+            }
+            self.rebuild(entries);
+        }
+
+        let tab = |event: &Event| {
+            matches!(
+                event,
+                Event::Term(TermEvent::Key(KeyEvent { code: KeyCode::Tab, kind, .. }))
+                    if !is_release(*kind)
+            )
+        };
+        let (tabs, events): (Vec<_>, Vec<_>) = events.into_iter().partition(tab);
+        if !tabs.is_empty() && self.filter.has_tags() {
+            for _ in &tabs {
+                self.filter.cycle(true);
+            }
+            self.rebuild(load_entries());
+        }
+
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                match self.entries.get(index) {
+                    Some((path, entry)) => Some(Change::push(Activity::new_base_boxed(
+                        "gallery_entry",
+                        GalleryEntryView::new(path.clone(), entry.clone()),
+                    ))),
+                    None if self.filter.tag().is_some() => None,
+                    None => Some(self.clear()),
+                }
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        let mut hints = self.menu.hints();
+        if self.filter.has_tags() {
+            hints.push(Hint::new("Tab", "filter by tag"));
+        }
+        hints
+    }
+}
+
+/// Thumbnail of an archived maze, it can be played or deleted from here
+pub struct GalleryEntryView {
+    path: PathBuf,
+    entry: GalleryEntry,
+    popup: Popup,
+}
+
+impl GalleryEntryView {
+    pub fn new(path: PathBuf, entry: GalleryEntry) -> Self {
+        let mut texts = entry.thumbnail.clone();
+        texts.extend([
+            String::new(),
+            entry.description.clone(),
+            String::new(),
+            "Enter: play, D: delete, Esc: back".to_string(),
+        ]);
+        let popup = Popup::new(entry.find.title().to_string(), texts);
+
+        Self { path, entry, popup }
+    }
+
+    fn play(&self, data: &mut AppData) -> Option<Change> {
+        let game = match RunningGame::from_replay(&self.entry.maze, MazeGenAlgo::default().to_fn())
+        {
+            Ok(game) => game,
+            Err(err) => {
+                log::error!("Could not load the maze: {}", err);
+                return None;
+            }
+        };
+
+        let game = GameData::new(game, GameRules::default(), 0, &data.settings);
+        Some(Change::push(Activity::new_base_boxed(
+            "game",
+            GameActivity::new(game, data),
+        )))
+    }
+
+    fn delete(&self) -> Option<Change> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Some(Change::pop_top_with(EntryDeleted)),
+            Err(err) => {
+                log::error!("Could not delete the maze: {}", err);
+                None
+            }
+        }
+    }
+}
+
+impl ActivityHandler for GalleryEntryView {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        for event in events {
+            let Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Enter | KeyCode::Char(' ' | 'p') => return self.play(data),
+                KeyCode::Delete | KeyCode::Char('d' | 'D') => return self.delete(),
+                KeyCode::Esc | KeyCode::Char('q') => return Some(Change::pop_top()),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.popup
+    }
+}
@@ -1,48 +1,103 @@
+use chrono::{DateTime, Local};
 use cmaze::{
     dims::*,
-    game::{GameProperities, GeneratorFn, ProgressComm, RunningGame, RunningGameState},
+    game::{
+        GameProperities, GeneratorFn, GoalObjective, ProgressComm, RunningGame, RunningGameState,
+    },
     gameboard::{
         algorithms::{
-            DepthFirstSearch, GenErrorInstant, GenErrorThreaded, MazeAlgorithm, Progress,
-            RndKruskals,
+            DepthFirstSearch, GenErrorThreaded, MazeAlgorithm, Progress, RndKruskals, SeedSource,
         },
-        Cell, CellWall,
+        Cell, CellContent, CellWall, Maze,
     },
 };
 
 use crate::{
-    app::{game_state::GameData, GameViewMode},
+    app::{
+        achievements::AchievementsScreen,
+        annotations::{draw_annotations, Annotation, Layer, Mark},
+        campaign::STAGES,
+        dense,
+        end_game::{maze_stats, EndGameActivity, Outcome},
+        event_log::RunEvent,
+        export::ExportDialog,
+        gallery::{self, GalleryMenu},
+        game_state::{GameData, GameRules, MacroState},
+        modes::GameModeMenu,
+        race::RaceActivity,
+        replay::{save_replay, ReplaysMenu},
+        screensaver::ScreensaverActivity,
+        shifting::{ShiftEvent, Shifter},
+        stamina::StaminaMeter,
+        stats::StatsScreen,
+        target::{SolveTarget, TargetSearchActivity},
+        texture, GameViewMode,
+    },
+    data::{
+        achievements::Win,
+        environment::Environment,
+        history::RunRecord,
+        maze_spec::{load_specs, MazeSpec},
+        model::{RunStep, SolveResult},
+        progression::{Level, PRESET_UNLOCKS},
+        SaveData,
+    },
     helpers::{
-        constants, is_release, maze2screen, maze2screen_3d, maze_render_size, strings, LineDir,
+        constants, is_release, maze2screen, maze2screen_3d, maze_render_size, path_lines, strings,
+        LineDir, Zoom,
     },
     lerp, menu_actions,
     renderer::{self, Frame},
     settings::{
         self,
         theme::{Theme, ThemeResolver},
-        CameraMode, Settings, SettingsActivity,
+        BumpFeedback, CameraMode, MazePreset, OverlayGuard, ProfileSwitchActivity, Settings,
+        SettingsActivity,
     },
     ui::{
         self,
         helpers::format_duration,
         multisize_duration_format, split_menu_actions,
         usecase::dpad::{DPad, DPadType},
-        Menu, MenuAction, MenuConfig, Popup, ProgressBar, Rect, Screen,
+        ErrorPopup, Hint, Menu, MenuAction, MenuConfig, Popup, ProgressBar, Rect, Screen, Tween,
     },
 };
 
 #[cfg(feature = "sound")]
 #[allow(unused_imports)]
-use crate::sound::{track::MusicTrack, SoundPlayer};
+use crate::sound::{
+    track::{tone, MusicContext},
+    SoundPlayer,
+};
 
-use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+#[cfg(feature = "net")]
+use crate::app::online::Peer;
+#[cfg(feature = "http")]
+use crate::http::{HttpServer, Publisher};
+#[cfg(feature = "lua")]
+use crate::scripts::Scripts;
+
+use crossterm::event::{
+    Event as TermEvent, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
 
 #[cfg(feature = "sound")]
 #[allow(unused_imports)]
 use rodio::Source;
+use unicode_width::UnicodeWidthStr;
 
 use super::{
-    app::{AppData, AppStateData},
+    app::{AppData, AppStateData, TICK},
+    cache::{CacheKey, MazeKey},
+    memory::{available_memory, format_bytes, MemoryCheck},
+    narration,
+    overlay::OverlayFiles,
+    preset_diff,
+    surprise::{self, SurprisesMenu},
     Activity, ActivityHandler, Change, Event,
 };
 
@@ -52,16 +107,27 @@ pub fn create_controls_popup() -> Activity {
         [
             "~ In game",
             " WASD and arrows: move",
+            " With relative controls: W forward, A/D turn",
             " Space: switch adventure/spectaror mode",
             " Q, F or L: move down",
             " E, R or P: move up",
             " With SHIFT move at the end in single dir",
+            " - and +: zoom out and in",
+            " Click a cell: walk there, run won't be saved",
+            " G: jump to a floor (spectator)",
+            " V: show the next floor side by side",
+            " H: show a hint, run won't be saved",
+            " U or Backspace: undo the last move",
+            " M and 1-9: record macro, M: stop",
+            " 1-9: replay macro, run won't be saved",
             " Escape: pause menu",
+            " Keys can be changed in Settings, Key bindings",
             "",
-            "~ In end game popup",
-            " Enter or space: main menu",
-            " Q: quit TMaze",
-            " R: restart game",
+            "~ In end game screen",
+            " Arrows or Tab: select button, Enter: press",
+            " R: retry the maze, N: new maze",
+            " A: analyze, S: share, M: main menu",
+            " T: tag the run, Q: quit TMaze",
         ]
         .into_iter()
         .map(String::from)
@@ -74,14 +140,22 @@ pub fn create_controls_popup() -> Activity {
 pub struct MainMenu {
     menu: Menu,
     actions: Vec<MenuAction<Change>>,
+    /// Ticks without any event, the demo starts after [`Settings::get_attract_delay`]
+    idle_ticks: u32,
+    /// Level shown under the title
+    level: Option<Level>,
 }
 
 #[allow(clippy::new_without_default)]
 impl MainMenu {
     pub fn new() -> Self {
         let options = menu_actions!(
-            "New Game" -> data => Self::start_new_game(&data.settings, &data.use_data),
-            "Settings" -> _ => Self::show_settings_screen(),
+            "New Game" -> data => Change::push(GameModeMenu::new_activity(&data.modes)),
+            "Replays" -> _ => Change::push(ReplaysMenu::new_activity()),
+            "Gallery" -> data => Change::push(GalleryMenu::new_activity(&data.save)),
+            "Achievements" -> data => Change::push(AchievementsScreen::new_activity(&data.save)),
+            "Statistics" -> data => Change::push(StatsScreen::new_activity(data)),
+            "Settings" -> data => Self::show_settings_screen(&data.settings),
             "Controls" -> _ => Self::show_controls_popup(),
             "About" -> _ => Self::show_about_popup(),
             "Quit" -> _ => Change::pop_top(),
@@ -92,14 +166,18 @@ impl MainMenu {
         Self {
             menu: Menu::new(MenuConfig::new("TMaze", options).counted()),
             actions,
+            idle_ticks: 0,
+            level: None,
         }
     }
 
-    fn show_settings_screen() -> Change {
-        Change::push(Activity::new_base_boxed(
-            "settings".to_string(),
-            settings::SettingsActivity::new(),
-        ))
+    fn should_start_attract(&self, settings: &Settings) -> bool {
+        let delay = Duration::from_secs(settings.get_attract_delay());
+        !delay.is_zero() && TICK * self.idle_ticks >= delay
+    }
+
+    fn show_settings_screen(settings: &Settings) -> Change {
+        Change::push(settings::SettingsActivity::new_activity(settings))
     }
 
     fn show_controls_popup() -> Change {
@@ -107,9 +185,11 @@ impl MainMenu {
     }
 
     fn show_about_popup() -> Change {
-        const FEATURE_LIST: [(&str, bool); 2] = [
+        const FEATURE_LIST: [(&str, bool); 4] = [
             ("updates", cfg!(feature = "updates")),
             ("sound", cfg!(feature = "sound")),
+            ("net", cfg!(feature = "net")),
+            ("lua", cfg!(feature = "lua")),
         ];
 
         let mut lines = vec![
@@ -159,24 +239,37 @@ impl MainMenu {
         Change::push(Activity::new_base_boxed("about".to_string(), popup))
     }
 
-    fn start_new_game(settings: &Settings, use_data: &AppStateData) -> Change {
-        Change::push(Activity::new_base_boxed(
-            "maze size",
-            MazeSizeMenu::new(settings, use_data),
-        ))
-    }
-
     #[cfg(feature = "sound")]
     fn play_menu_bgm(data: &mut AppData) {
-        data.play_bgm(MusicTrack::Menu);
+        data.play_bgm(MusicContext::Menu);
     }
 }
 
 impl ActivityHandler for MainMenu {
+    fn tick(&mut self, _data: &mut AppData) {
+        self.idle_ticks = self.idle_ticks.saturating_add(1);
+    }
+
     fn update(&mut self, events: Vec<super::Event>, data: &mut AppData) -> Option<Change> {
         #[cfg(feature = "sound")]
         Self::play_menu_bgm(data);
 
+        let level = data.save.level();
+        if self.level != Some(level) {
+            self.level = Some(level);
+            let text = format!("Level {} - {}/{} XP", level.level, level.xp, level.needed);
+            self.menu.set_subtitles(vec![text]);
+        }
+
+        if !events.is_empty() {
+            self.idle_ticks = 0;
+        } else if self.should_start_attract(&data.settings) {
+            self.idle_ticks = 0;
+            return Some(Change::push(ScreensaverActivity::new_attract_activity(
+                data,
+            )));
+        }
+
         match self.menu.update(events, data)? {
             Change::Pop {
                 res: Some(sub_activity),
@@ -194,21 +287,52 @@ impl ActivityHandler for MainMenu {
     fn screen(&self) -> &dyn ui::Screen {
         &self.menu
     }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
 }
 
 pub struct MazeSizeMenu {
     menu: Menu,
+    /// Presets from the settings, then the ones unlocked by the levels
     presets: Vec<GameMode>,
+    /// Level needed for the preset, if it's still locked
+    locks: Vec<Option<u32>>,
+    /// Whether the presets were compared with the ones seen last time
+    presets_checked: bool,
+    /// Mazes from the mazes directory, listed after the presets
+    specs: Vec<MazeSpec>,
+    rules: GameRules,
 }
 
 impl MazeSizeMenu {
-    pub fn new(settings: &Settings, app_state_data: &AppStateData) -> Self {
+    pub fn new(settings: &Settings, app_state_data: &AppStateData, save: &SaveData) -> Self {
+        let specs: Vec<_> = load_specs().into_iter().map(|(_, spec)| spec).collect();
+        let level = save.level().level;
+        let unlock_titles = PRESET_UNLOCKS
+            .iter()
+            .map(|unlock| match unlock.level <= level {
+                true => unlock.title.to_string(),
+                false => format!("🔒 {} (level {})", unlock.title, unlock.level),
+            });
         let mut menu_config = MenuConfig::new_from_strings(
             "Maze size".to_string(),
             settings
                 .get_mazes()
                 .iter()
-                .map(|maze| maze.title.clone())
+                .map(|maze| match maze.target {
+                    Some(target) => format!("{} ({})", maze.title, target.describe()),
+                    None => maze.title.clone(),
+                })
+                .chain(unlock_titles)
+                .chain(specs.iter().map(|spec| format!("{} (custom)", spec.title)))
+                .chain(["Surprise me".to_string()])
+                .chain((!save.surprises().is_empty()).then(|| "Past surprises".to_string()))
                 .collect::<Vec<_>>(),
         );
 
@@ -225,13 +349,67 @@ impl MazeSizeMenu {
         let presets = settings
             .get_mazes()
             .iter()
-            .map(|maze| GameMode {
-                size: Dims3D(maze.width as i32, maze.height as i32, maze.depth as i32),
-                is_tower: maze.tower,
-            })
+            .map(MazePreset::game_mode)
+            .chain(PRESET_UNLOCKS.iter().map(|unlock| unlock.game_mode))
             .collect::<Vec<_>>();
 
-        Self { menu, presets }
+        let locks = settings
+            .get_mazes()
+            .iter()
+            .map(|_| None)
+            .chain(
+                PRESET_UNLOCKS
+                    .iter()
+                    .map(|unlock| Some(unlock.level).filter(|needed| *needed > level)),
+            )
+            .collect();
+
+        Self {
+            menu,
+            presets,
+            locks,
+            presets_checked: false,
+            specs,
+            rules: GameRules::default(),
+        }
+    }
+
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    fn play_spec(&self, spec: &MazeSpec, data: &mut AppData) -> Option<Change> {
+        let gen_fn = data.settings.get_default_maze_gen_algo().to_fn();
+        let game = match spec.to_game(gen_fn) {
+            Ok(game) => game,
+            Err(err) => {
+                log::error!("Could not load the maze {:?}: {}", spec.title, err);
+                return None;
+            }
+        };
+
+        let rules = GameRules {
+            custom: true,
+            ..self.rules
+        };
+        let game = GameData::new(game, rules, 0, &data.settings);
+        Some(Change::push(play_activity(game, data)))
+    }
+
+    /// Shows the presets which changed since they were seen last time, see [`preset_diff`]
+    fn check_presets(&self, data: &mut AppData) -> Option<Change> {
+        let current = preset_diff::preset_modes(&data.settings.get_mazes());
+        if data.save.preset_modes() == &current || data.settings.is_ro() {
+            return None;
+        }
+
+        let changes = preset_diff::diff(data.save.preset_modes(), &current);
+        if let Err(err) = data.save.set_preset_modes(current) {
+            log::error!("Failed to save the presets: {}", err);
+        }
+
+        (!changes.is_empty()).then(|| Change::push(preset_diff::popup(&changes, &data.save)))
     }
 
     // TODO: custom maze size config
@@ -240,19 +418,57 @@ impl MazeSizeMenu {
 
 impl ActivityHandler for MazeSizeMenu {
     fn update(&mut self, events: Vec<super::Event>, data: &mut AppData) -> Option<Change> {
+        if !self.presets_checked {
+            self.presets_checked = true;
+            if let Some(change) = self.check_presets(data) {
+                return Some(change);
+            }
+        }
+
         match self.menu.update(events, data) {
             Some(change) => match change {
                 Change::Pop {
                     res: Some(size), ..
                 } => {
                     let index = *size.downcast::<usize>().expect("menu should return index");
+                    if let Some(i) = index.checked_sub(self.presets.len()) {
+                        return match self.specs.get(i) {
+                            Some(spec) => self.play_spec(&spec.clone(), data),
+                            None if i == self.specs.len() => {
+                                Some(surprise::surprise_me(self.rules, data))
+                            }
+                            None => Some(Change::push(Activity::new_base_boxed(
+                                "surprises",
+                                SurprisesMenu::new(data.save.surprises(), self.rules),
+                            ))),
+                        };
+                    }
+                    if let Some(level) = self.locks[index] {
+                        log::warn!("Reach level {} to unlock this maze", level);
+                        return None;
+                    }
                     data.use_data.last_selected_preset = Some(index);
 
                     let preset = self.presets[index];
+                    let mazes = data.settings.get_mazes();
+                    let settings_preset = mazes.get(index);
+                    let rules = GameRules {
+                        hardcore: settings_preset.is_some_and(|preset| preset.hardcore),
+                        preset: settings_preset.map(|_| index),
+                        time_limit: settings_preset
+                            .and_then(MazePreset::time_limit)
+                            .or(self.rules.time_limit),
+                        stamina: settings_preset
+                            .and_then(|preset| preset.stamina)
+                            .or(self.rules.stamina),
+                        ..self.rules
+                    };
 
                     Some(Change::push(Activity::new_base_boxed(
                         "maze_gen".to_string(),
-                        MazeAlgorithmMenu::new(preset, &data.settings),
+                        MazeAlgorithmMenu::new(preset, &data.settings)
+                            .with_rules(rules)
+                            .with_target(settings_preset.and_then(|preset| preset.target)),
                     )))
                 }
                 res => Some(res),
@@ -264,10 +480,21 @@ impl ActivityHandler for MazeSizeMenu {
     fn screen(&self) -> &dyn ui::Screen {
         &self.menu
     }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
 }
 
 pub struct MazeAlgorithmMenu {
     preset: GameMode,
+    rules: GameRules,
+    /// Size of the maze is searched for the target, see [`TargetSearchActivity`]
+    target: Option<SolveTarget>,
     menu: Menu,
     functions: Vec<MenuAction<GeneratorFn>>,
 }
@@ -275,8 +502,8 @@ pub struct MazeAlgorithmMenu {
 impl MazeAlgorithmMenu {
     pub fn new(preset: GameMode, settings: &Settings) -> Self {
         let options = menu_actions!(
-            "Randomized Kruskal's" -> _ => RndKruskals::generate as GeneratorFn,
-            "Depth-first search" -> _ => DepthFirstSearch::generate,
+            "Randomized Kruskal's" -> _ => RndKruskals::generate_seeded as GeneratorFn,
+            "Depth-first search" -> _ => DepthFirstSearch::generate_seeded,
         );
 
         let (options, functions) = split_menu_actions(options);
@@ -290,21 +517,42 @@ impl MazeAlgorithmMenu {
         Self {
             menu,
             preset,
+            rules: GameRules::default(),
+            target: None,
             functions,
         }
     }
+
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_target(mut self, target: Option<SolveTarget>) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Generates the maze with the algorithm, or searches for it first if there is a target
+    fn generate(&self, gen: GeneratorFn) -> Change {
+        let activity = match self.target {
+            Some(target) => Activity::new_base_boxed(
+                "maze_search".to_string(),
+                TargetSearchActivity::new(self.preset, gen, target).with_rules(self.rules),
+            ),
+            None => Activity::new_base_boxed(
+                "maze_gen".to_string(),
+                MazeGenerationActivity::new(self.preset, gen).with_rules(self.rules),
+            ),
+        };
+        Change::push(activity)
+    }
 }
 
 impl ActivityHandler for MazeAlgorithmMenu {
     fn update(&mut self, events: Vec<super::Event>, data: &mut AppData) -> Option<Change> {
         if data.settings.get_dont_ask_for_maze_algo() {
-            return Some(Change::push(Activity::new_base_boxed(
-                "maze_gen".to_string(),
-                MazeGenerationActivity::new(
-                    self.preset,
-                    data.settings.get_default_maze_gen_algo().to_fn(),
-                ),
-            )));
+            return Some(self.generate(data.settings.get_default_maze_gen_algo().to_fn()));
         }
 
         match self.menu.update(events, data) {
@@ -315,11 +563,7 @@ impl ActivityHandler for MazeAlgorithmMenu {
                     let index = *algo.downcast::<usize>().expect("menu should return index");
 
                     let gen = self.functions[index](data);
-
-                    Some(Change::push(Activity::new_base_boxed(
-                        "maze_gen".to_string(),
-                        MazeGenerationActivity::new(self.preset, gen),
-                    )))
+                    Some(self.generate(gen))
                 }
                 res => Some(res),
             },
@@ -330,12 +574,42 @@ impl ActivityHandler for MazeAlgorithmMenu {
     fn screen(&self) -> &dyn ui::Screen {
         &self.menu
     }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
 }
 
+/// Mazes with at most this many cells are regenerated if they were played recently
+const SMALL_MAZE_CELLS: i32 = 64;
+/// How many times is a recently played maze regenerated, before it's accepted anyway
+const MAX_DUPLICATE_RETRIES: usize = 5;
+
+/// Smallest viewport area in which the split view is shown, it's turned off in smaller ones
+const SPLIT_VIEW_MIN_SIZE: Dims = Dims(60, 12);
+
+/// Width and height of the smaller maze offered after a generation timeout, relative to the old one
+const TIMEOUT_SHRINK: f32 = 0.7;
+/// Remaining time is estimated only after this part of the maze is generated
+const ETA_MIN_PROGRESS: f64 = 0.01;
+
 pub struct MazeGenerationActivity {
     comm: Option<ProgressComm<Result<RunningGame, GenErrorThreaded>>>,
     game_props: GameProperities,
     progress_bar: ProgressBar,
+    retries: usize,
+    rules: GameRules,
+    cleared: usize,
+    /// When the generation thread was started
+    started: Instant,
+    /// Generation is cancelled after [`Settings::get_generation_timeout`]
+    timeout: bool,
+    /// Memory the maze takes was checked already, see [`MemoryCheck`]
+    memory_checked: bool,
 }
 
 impl MazeGenerationActivity {
@@ -343,6 +617,7 @@ impl MazeGenerationActivity {
         let game_props = GameProperities {
             game_mode,
             generator: maze_gen,
+            seed: None,
         };
 
         let progress_bar = ProgressBar::new(format!("Generating maze: {:?}", game_mode.size));
@@ -351,8 +626,116 @@ impl MazeGenerationActivity {
             comm: None,
             game_props,
             progress_bar,
+            retries: 0,
+            rules: GameRules::default(),
+            cleared: 0,
+            started: Instant::now(),
+            timeout: true,
+            memory_checked: false,
+        }
+    }
+
+    /// Waits for the maze as long as it takes
+    pub fn without_timeout(mut self) -> Self {
+        self.timeout = false;
+        self
+    }
+
+    /// Generates the maze even when it's over the memory budget
+    fn without_memory_check(mut self) -> Self {
+        self.memory_checked = true;
+        self
+    }
+
+    /// Same generation with another game mode, rules, seed and the run progress are kept
+    fn with_game_mode(&self, game_mode: GameMode) -> Self {
+        let mut activity = Self::new(game_mode, self.game_props.generator)
+            .with_rules(self.rules)
+            .with_cleared(self.cleared);
+        activity.game_props.seed = self.game_props.seed;
+        activity
+    }
+
+    /// Title of the progress bar, with the estimated remaining time
+    fn progress_title(&self, Progress { done, from, .. }: Progress) -> String {
+        let ratio = done as f64 / from as f64;
+        let title = format!(
+            "Generating maze: {}/{} - {:.2} %",
+            done,
+            from,
+            ratio * 100.0
+        );
+        let title = match self.game_props.seed {
+            Some(seed) => format!("{} - seed {}", title, seed),
+            None => title,
+        };
+
+        match ratio >= ETA_MIN_PROGRESS {
+            true => {
+                let remaining = self.started.elapsed().mul_f64((1.0 - ratio) / ratio);
+                format!("{} - {} left", title, format_duration(remaining))
+            }
+            false => title,
+        }
+    }
+
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Generate the maze from the seed instead of a random one
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.game_props.seed = Some(seed);
+        self
+    }
+
+    /// Continue an endless run, after `cleared` finished mazes
+    pub fn with_cleared(mut self, cleared: usize) -> Self {
+        self.cleared = cleared;
+        self
+    }
+
+    /// Whether the maze should be regenerated, because it was played recently
+    ///
+    /// Only small mazes are checked, collisions are very unlikely for bigger ones.
+    /// Mazes from a given seed are never regenerated.
+    fn is_duplicate(&mut self, game: &RunningGame, data: &mut AppData) -> bool {
+        let Dims3D(w, h, d) = self.game_props.game_mode.size;
+        if w * h * d > SMALL_MAZE_CELLS || data.settings.is_ro() || self.game_props.seed.is_some() {
+            return false;
+        }
+
+        let hash = game.get_maze().canonical_hash();
+        if data.save.is_recent_maze(hash) && self.retries < MAX_DUPLICATE_RETRIES {
+            log::debug!("Maze was played recently, generating another one");
+            self.retries += 1;
+            return true;
+        }
+
+        if let Err(err) = data.save.add_recent_maze(hash) {
+            log::error!("Failed to save recent mazes: {}", err);
+        }
+        false
+    }
+}
+
+/// Seed from [`Settings::get_seed_source`], `None` for random seeds, so duplicates can be
+/// generated again
+fn source_seed(data: &mut AppData) -> Option<u64> {
+    let source = data.settings.get_seed_source();
+    if source == SeedSource::Entropy {
+        return None;
+    }
+
+    let seed = source.seed(data.save.seed_counter());
+    if source == SeedSource::Counter && !data.settings.is_ro() {
+        if let Err(err) = data.save.advance_seed_counter() {
+            log::error!("Failed to save the seed counter: {}", err);
         }
     }
+    log::info!("Maze seed: {}", seed);
+    Some(seed)
 }
 
 impl ActivityHandler for MazeGenerationActivity {
@@ -376,27 +759,31 @@ impl ActivityHandler for MazeGenerationActivity {
             }
         }
 
+        if self.comm.is_none() && !self.memory_checked {
+            self.memory_checked = true;
+            let size = self.game_props.game_mode.size;
+            let check =
+                MemoryCheck::new(size, data.settings.get_memory_budget(), available_memory());
+            if check != MemoryCheck::Fits {
+                log::warn!("Maze of size {:?} needs too much memory: {:?}", size, check);
+                return Some(Change::replace(MemoryWarning::new_activity(self, check)));
+            }
+        }
+
+        if self.comm.is_none() && self.game_props.seed.is_none() {
+            self.game_props.seed = source_seed(data);
+        }
+
         match self.comm {
             None => match RunningGame::new_threaded(self.game_props.clone()) {
                 Ok(comm) => {
                     log::info!("Maze generation thread started");
                     self.comm = Some(comm);
+                    self.started = Instant::now();
 
                     None
                 }
-                Err(err) => match err {
-                    GenErrorInstant::InvalidSize(size) => {
-                        let popup = Popup::new(
-                            "Invalid maze size".to_string(),
-                            vec![format!("Size: {:?}", size)],
-                        );
-
-                        Some(Change::replace(Activity::new_base_boxed(
-                            "invalid size".to_string(),
-                            popup,
-                        )))
-                    }
-                },
+                Err(err) => Some(Change::replace(ErrorPopup::new_activity(&err.into()))),
             },
 
             Some(ref comm) if comm.handle.is_finished() => {
@@ -409,36 +796,45 @@ impl ActivityHandler for MazeGenerationActivity {
                     .expect("Could not join maze generation thread");
 
                 match res {
+                    Ok(game) if self.is_duplicate(&game, data) => None,
                     Ok(game) => {
-                        let game_data = GameData {
-                            camera_pos: maze2screen_3d(game.get_player_pos()),
-                            game,
-                            view_mode: GameViewMode::Adventure,
-                            player_char: constants::get_random_player_char(),
-                        };
-                        Some(Change::replace(Activity::new_base_boxed(
-                            "game".to_string(),
-                            GameActivity::new(game_data, data),
-                        )))
-                    }
-                    Err(err) => match err {
-                        GenErrorThreaded::AbortGeneration => Some(Change::pop_top()),
-                        GenErrorThreaded::GenerationError(_) => {
-                            panic!("Instant generation error should be handled before");
+                        let stats = maze_stats(&game, &mut data.cache);
+                        log::info!("Generated {}", stats);
+                        // the summary outlives the generation screen, which is replaced right away
+                        data.toasts.push("Maze generated", stats.describe());
+
+                        match gallery::consider(&game, &stats, &data.settings, &data.theme) {
+                            Ok(Some(find)) => {
+                                log::warn!("New find for the gallery: {}", find.title())
+                            }
+                            Ok(None) => {}
+                            Err(err) => log::error!("Could not archive the maze: {}", err),
                         }
-                    },
+
+                        let game_data =
+                            GameData::new(game, self.rules, self.cleared, &data.settings);
+                        Some(Change::replace(play_activity(game_data, data)))
+                    }
+                    Err(GenErrorThreaded::AbortGeneration) => Some(Change::pop_top()),
+                    Err(err) => Some(Change::replace(ErrorPopup::new_activity(&err.into()))),
                 }
             }
 
             Some(ref comm) => {
-                let Progress { done, from, .. } = comm.progress();
-                self.progress_bar.update_progress(done as f64 / from as f64);
-                self.progress_bar.update_title(format!(
-                    "Generating maze: {}/{} - {:.2} %",
-                    done,
-                    from,
-                    done as f64 / from as f64 * 100.0
-                ));
+                let progress = comm.progress();
+                let timeout = data.settings.get_generation_timeout();
+
+                if self.timeout && timeout > 0 && self.started.elapsed().as_secs() >= timeout {
+                    log::warn!("Maze generation timed out after {} s", timeout);
+                    // thread is detached, it finishes on its own once it sees the flag
+                    self.comm.take().unwrap().stop_flag.stop();
+                    return Some(Change::replace(GenerationTimeout::new_activity(self)));
+                }
+
+                self.progress_bar
+                    .update_progress(progress.done as f64 / progress.from as f64);
+                self.progress_bar
+                    .update_title(self.progress_title(progress));
                 None
             }
         }
@@ -447,189 +843,1044 @@ impl ActivityHandler for MazeGenerationActivity {
     fn screen(&self) -> &dyn ui::Screen {
         &self.progress_bar
     }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![Hint::new("Esc", "cancel")]
+    }
 }
 
-pub struct PauseMenu {
+/// Offered when a maze is not generated in time, a smaller or a simpler one can be generated
+pub struct GenerationTimeout {
     menu: Menu,
-    actions: Vec<MenuAction<Change>>,
+    /// Generation started by every item of the menu, `None` goes back
+    retries: Vec<Option<MazeGenerationActivity>>,
 }
 
-#[allow(clippy::new_without_default)]
-impl PauseMenu {
-    pub fn new() -> Self {
-        let options = menu_actions!(
-            "Resume" -> _ => Change::pop_top(),
-            "Main Menu" -> _ => Change::pop_until("main menu"),
-            "Controls" -> _ => Change::push(create_controls_popup()),
-            "Settings" -> _ => Change::push(SettingsActivity::new_activity()),
-            "Quit" -> _ => Change::pop_all(),
+impl GenerationTimeout {
+    pub fn new(generation: &MazeGenerationActivity) -> Self {
+        let mode = generation.game_props.game_mode;
+        let mut items = vec![];
+
+        let Dims3D(w, h, d) = mode.size;
+        let smaller = Dims3D(
+            ((w as f32 * TIMEOUT_SHRINK) as i32).max(2),
+            ((h as f32 * TIMEOUT_SHRINK) as i32).max(2),
+            d,
         );
+        if smaller != mode.size {
+            let size = match d {
+                1 => format!("{}x{}", smaller.0, smaller.1),
+                _ => format!("{}x{}x{}", smaller.0, smaller.1, d),
+            };
+            let mode = GameMode {
+                size: smaller,
+                ..mode
+            };
+            items.push((format!("Smaller maze, {}", size), Some(mode)));
+        }
 
-        let (options, actions) = split_menu_actions(options);
+        // braiding and items are extra passes over the whole maze
+        let simpler = GameMode {
+            braid: 0,
+            braid_floors: 0,
+            items: Default::default(),
+            ..mode
+        };
+        if simpler != mode {
+            items.push(("Without braids and items".to_string(), Some(simpler)));
+        }
 
-        let menu = Menu::new(MenuConfig::new("Paused", options));
+        let mut retries: Vec<_> = items
+            .iter()
+            .map(|(_, mode)| mode.map(|mode| generation.with_game_mode(mode)))
+            .collect();
+        retries.push(Some(generation.with_game_mode(mode).without_timeout()));
+        retries.push(None);
+
+        let options: Vec<_> = items
+            .into_iter()
+            .map(|(text, _)| text)
+            .chain(["Keep waiting".to_string(), "Back".to_string()])
+            .collect();
+
+        let timeout = generation.started.elapsed().as_secs();
+        let menu_config = MenuConfig::new_from_strings("Maze generation timed out", options)
+            .subtitle(format!("Not done after {} s", timeout))
+            .subtitle("Timeout can be changed in Settings");
+
+        Self {
+            menu: Menu::new(menu_config),
+            retries,
+        }
+    }
 
-        Self { menu, actions }
+    pub fn new_activity(generation: &MazeGenerationActivity) -> Activity {
+        Activity::new_base_boxed("generation timeout", Self::new(generation))
     }
 }
 
-impl ActivityHandler for PauseMenu {
+impl ActivityHandler for GenerationTimeout {
     fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
-        match self.menu.update(events, data) {
-            Some(change) => match change {
-                Change::Pop { res: Some(res), .. } => {
-                    let index = *res.downcast::<usize>().expect("menu should return index");
-
-                    Some((self.actions[index])(data))
+        match self.menu.update(events, data)? {
+            Change::Pop { res: Some(res), .. } => {
+                let index = *res.downcast::<usize>().expect("menu should return index");
+                match self.retries[index].take() {
+                    Some(generation) => Some(Change::replace(Activity::new_base_boxed(
+                        "maze_gen", generation,
+                    ))),
+                    // same as cancelling the generation
+                    None => Some(Change::pop(2)),
                 }
-                res => Some(res),
-            },
-            None => None,
+            }
+            res => Some(res),
         }
     }
 
     fn screen(&self) -> &dyn Screen {
         &self.menu
     }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
 }
 
-pub struct EndGamePopup {
-    popup: Popup,
-    game_mode: GameMode,
-    gen_fn: GeneratorFn,
+/// Offered before a maze which doesn't fit the memory is generated, a smaller one can be
+/// generated, or the same one when it's only over the budget
+pub struct MemoryWarning {
+    menu: Menu,
+    /// Generation started by every item of the menu, `None` goes back
+    retries: Vec<Option<MazeGenerationActivity>>,
 }
 
-impl EndGamePopup {
-    pub fn new(game: &RunningGame) -> Self {
-        let maze_size = game.get_maze().size();
-        let texts = vec![
-            format!("Time:  {}", format_duration(game.get_elapsed().unwrap())),
-            format!("Moves: {}", game.get_move_count()),
-            format!("Size:  {}x{}x{}", maze_size.0, maze_size.1, maze_size.2,),
-        ];
+impl MemoryWarning {
+    pub fn new(generation: &MazeGenerationActivity, check: MemoryCheck) -> Self {
+        let mode = generation.game_props.game_mode;
+        let mut options = vec![];
+        let mut retries = vec![];
+
+        if let Some(smaller) = check.smaller_size(mode.size) {
+            let size = match smaller.2 {
+                1 => format!("{}x{}", smaller.0, smaller.1),
+                _ => format!("{}x{}x{}", smaller.0, smaller.1, smaller.2),
+            };
+            options.push(format!("Smaller maze, {}", size));
+            retries.push(Some(generation.with_game_mode(GameMode {
+                size: smaller,
+                ..mode
+            })));
+        }
 
-        let popup = Popup::new("You won".to_string(), texts);
+        let (title, subtitle, hint) = match check {
+            MemoryCheck::OverBudget { needed, budget } => {
+                options.push("Generate anyway".to_string());
+                retries.push(Some(generation.with_game_mode(mode).without_memory_check()));
+                (
+                    "Maze is over the memory budget",
+                    format!(
+                        "Needs {}, budget is {}",
+                        format_bytes(needed),
+                        format_bytes(budget)
+                    ),
+                    "Budget can be changed in Settings",
+                )
+            }
+            MemoryCheck::OverAvailable {
+                needed, available, ..
+            } => (
+                "Maze doesn't fit the memory",
+                format!(
+                    "Needs {}, {} is free",
+                    format_bytes(needed),
+                    format_bytes(available)
+                ),
+                "Close other programs to free some",
+            ),
+            MemoryCheck::Fits => unreachable!("fitting mazes are generated right away"),
+        };
+        options.push("Back".to_string());
+        retries.push(None);
 
-        let game_mode = game.get_game_mode();
-        let gen_fn = game.get_gen_fn();
+        let menu_config = MenuConfig::new_from_strings(title, options)
+            .subtitle(subtitle)
+            .subtitle(hint);
 
         Self {
-            popup,
-            game_mode,
-            gen_fn,
+            menu: Menu::new(menu_config),
+            retries,
         }
     }
+
+    pub fn new_activity(generation: &MazeGenerationActivity, check: MemoryCheck) -> Activity {
+        Activity::new_base_boxed("memory warning", Self::new(generation, check))
+    }
 }
 
-impl ActivityHandler for EndGamePopup {
+impl ActivityHandler for MemoryWarning {
     fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
-        match self.popup.update(events, data) {
-            Some(Change::Pop {
-                n: 1,
-                res: Some(code),
-            }) => match code.downcast::<KeyCode>() {
-                Ok(b) => match *b {
-                    KeyCode::Char('r') => Some(Change::replace(Activity::new_base_boxed(
-                        "game",
-                        MazeGenerationActivity::new(self.game_mode, self.gen_fn),
+        match self.menu.update(events, data)? {
+            Change::Pop { res: Some(res), .. } => {
+                let index = *res.downcast::<usize>().expect("menu should return index");
+                match self.retries[index].take() {
+                    Some(generation) => Some(Change::replace(Activity::new_base_boxed(
+                        "maze_gen", generation,
                     ))),
-                    KeyCode::Char('q') => Some(Change::pop_all()),
-                    KeyCode::Enter | KeyCode::Char(' ') => Some(Change::pop_top()),
-                    _ => None,
-                },
-                _ => panic!("expected `KeyCode` from `Popup`"),
-            },
-            res => res,
+                    // same as cancelling the generation
+                    None => Some(Change::pop(2)),
+                }
+            }
+            res => Some(res),
         }
     }
 
     fn screen(&self) -> &dyn Screen {
-        &self.popup
+        &self.menu
     }
-}
-
-pub struct GameActivity {
-    camera_mode: CameraMode,
-    game: GameData,
-    maze_board: MazeBoard,
-    show_debug: bool,
-
-    // spacing
-    margins: Dims,
-    viewport_rect: Rect,
-    dpad_rect: Option<Rect>,
 
-    // smooth
-    sm_camera_pos: Dims3D,
-    sm_player_pos: Dims3D,
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
 
-    // touch
-    touch_controls: Option<Box<DPad>>,
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
 }
 
-impl GameActivity {
-    pub fn new(game: GameData, app_data: &mut AppData) -> Self {
-        let settings = &app_data.settings;
-
-        let camera_mode = settings.get_camera_mode();
-        let maze_board = MazeBoard::new(&game.game, &app_data.theme);
-        let margins = settings.get_viewport_margin();
-
-        #[cfg(feature = "sound")]
-        app_data.play_bgm(MusicTrack::choose_for_maze(game.game.get_maze()));
+/// Activity playing the game, split-screen race or the usual single player game
+pub fn play_activity(game: GameData, data: &mut AppData) -> Activity {
+    match game.rules.race {
+        true => Activity::new_base_boxed("game", RaceActivity::new(game.game, data)),
+        false => Activity::new_base_boxed("game", GameActivity::new(game, data)),
+    }
+}
 
-        let sm_camera_pos = game.camera_pos;
-        let sm_player_pos = maze2screen_3d(game.game.get_player_pos());
+/// Short summary of the run in progress
+#[derive(Debug, Clone, Copy)]
+pub struct RunSummary {
+    pub elapsed: Duration,
+    pub moves: usize,
+}
 
+impl RunSummary {
+    pub fn new(game: &RunningGame) -> Self {
         Self {
-            camera_mode,
-            game,
-            maze_board,
-            show_debug: false,
+            elapsed: game.get_elapsed().unwrap_or_default(),
+            moves: game.get_move_count(),
+        }
+    }
+}
 
-            margins,
-            viewport_rect: Rect::sized(app_data.screen_size),
-            dpad_rect: None,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuitTarget {
+    MainMenu,
+    Exit,
+}
 
-            sm_camera_pos,
-            sm_player_pos,
+impl QuitTarget {
+    fn change(self) -> Change {
+        match self {
+            QuitTarget::MainMenu => Change::pop_until("main menu"),
+            QuitTarget::Exit => Change::pop_all(),
+        }
+    }
 
-            touch_controls: None,
+    /// Quits right away or asks for confirmation, based on settings
+    fn quit(self, summary: RunSummary, settings: &Settings) -> Change {
+        if settings.get_confirm_quit() {
+            Change::push(QuitConfirmActivity::new_activity(summary, self))
+        } else {
+            self.change()
         }
     }
+}
 
-    /// Returns the size of the viewport and whether the floor fits in the viewport
-    pub fn viewport_size(&self, screen_size: Dims) -> (Dims, bool) {
-        let vp_size = screen_size - self.margins * 2;
+/// Asks whether to really leave the run in progress
+pub struct QuitConfirmActivity {
+    menu: Menu,
+    target: QuitTarget,
+}
 
-        let maze_frame = &self.maze_board.frames[self.game.game.get_player_pos().2 as usize];
-        let floor_size = maze_frame.size;
+impl QuitConfirmActivity {
+    pub fn new(summary: RunSummary, target: QuitTarget) -> Self {
+        let quit = match target {
+            QuitTarget::MainMenu => "Quit to main menu",
+            QuitTarget::Exit => "Quit TMaze",
+        };
 
-        let does_fit = floor_size.0 <= vp_size.0 && floor_size.1 <= vp_size.1;
+        let menu_config = MenuConfig::new_from_strings(
+            "Quit the run?",
+            ["Keep playing".to_string(), quit.to_string()],
+        )
+        .subtitle(format!("Time:  {}", format_duration(summary.elapsed)))
+        .subtitle(format!("Moves: {}", summary.moves))
+        .subtitle("Progress will be lost");
 
-        (if does_fit { floor_size } else { vp_size }, does_fit)
+        Self {
+            menu: Menu::new(menu_config),
+            target,
+        }
     }
 
-    fn current_floor_frame(&self) -> &Frame {
-        &self.maze_board.frames[self.game.camera_pos.2 as usize]
+    pub fn new_activity(summary: RunSummary, target: QuitTarget) -> Activity {
+        Activity::new_base_boxed("quit confirm", Self::new(summary, target))
     }
+}
 
-    fn render_meta_texts(&self, frame: &mut Frame, theme: &Theme, vp: Rect) {
-        let max_width = (vp.size().0 / 2 + 1) as usize;
-
-        let pl_pos = self.game.game.get_player_pos() + Dims3D(1, 1, 1);
-
-        // texts
-        let from_start =
-            multisize_duration_format(self.game.game.get_elapsed().unwrap(), max_width);
-        let move_count = strings::multisize_string(
-            [
-                format!("{} moves", self.game.game.get_move_count()),
-                format!("{}m", self.game.game.get_move_count()),
-            ],
-            max_width,
+impl ActivityHandler for QuitConfirmActivity {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data)? {
+            Change::Pop { res: Some(res), .. } => {
+                match *res.downcast::<usize>().expect("menu should return index") {
+                    1 => Some(self.target.change()),
+                    _ => Some(Change::pop_top()),
+                }
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+/// How much the game frame behind the pause menu is dimmed
+const PAUSE_DIM_ALPHA: f32 = 0.6;
+
+pub struct PauseMenu {
+    menu: Menu,
+    actions: Vec<MenuAction<Change>>,
+    /// Dimmed game frame from the moment of pausing, drawn behind the menu
+    snapshot: Option<Frame>,
+}
+
+impl PauseMenu {
+    pub fn new(summary: RunSummary, game: &GameData) -> Self {
+        #[cfg(feature = "print")]
+        let (maze, floor, start, goals) = (
+            game.game.get_maze().clone(),
+            game.game.get_player_pos().2,
+            game.game.get_start_pos(),
+            game.game.get_goals().to_vec(),
+        );
+        let export = ExportDialog::new(&game.game);
+
+        let options = menu_actions!(
+            "Resume" -> _ => Change::pop_top(),
+            "Main Menu" -> data => QuitTarget::MainMenu.quit(summary, &data.settings),
+            "Print maze" on "print" -> data => Self::print_maze(&maze, floor, start, &goals, &data.settings),
+            "Export maze" -> _ => Change::push(Activity::new_base_boxed("export", export.clone())),
+            "Controls" -> _ => Change::push(create_controls_popup()),
+            "Settings" -> data => Change::push(SettingsActivity::new_activity(&data.settings)),
+            "Switch profile" -> data => Change::push(ProfileSwitchActivity::new_activity(&data.settings)),
+            "Quit" -> data => QuitTarget::Exit.quit(summary, &data.settings),
         );
 
+        let (options, actions) = split_menu_actions(options);
+
+        let menu = Menu::new(MenuConfig::new("Paused", options));
+
+        Self {
+            menu,
+            actions,
+            snapshot: None,
+        }
+    }
+
+    /// Shows the frame behind the menu, dimmed by the `game.paused` style
+    pub fn with_snapshot(mut self, mut snapshot: Frame, theme: &Theme) -> Self {
+        snapshot.blend(theme["game.paused"].to_cross(), PAUSE_DIM_ALPHA);
+        self.snapshot = Some(snapshot);
+        self
+    }
+}
+
+#[cfg(feature = "print")]
+impl PauseMenu {
+    /// Prints current floor of the maze to a new SVG file in the exports directory
+    fn print_maze(
+        maze: &Maze,
+        floor: i32,
+        start: Dims3D,
+        goals: &[Dims3D],
+        settings: &Settings,
+    ) -> Change {
+        use crate::{
+            error::AppError,
+            helpers::{new_export_file, print::print_to_file},
+        };
+
+        let path = new_export_file("svg");
+        let options = settings.get_print_options();
+        let popup = match print_to_file(maze, floor, start, goals, &options, &path) {
+            Ok(()) => {
+                log::info!("Maze printed to {}", path.display());
+                Popup::new(
+                    "Maze printed".to_string(),
+                    vec!["Saved to:".to_string(), path.display().to_string()],
+                )
+            }
+            Err(err) => {
+                return Change::push(ErrorPopup::new_activity(&AppError::io(
+                    "print the maze",
+                    err,
+                )))
+            }
+        };
+
+        Change::push(Activity::new_base_boxed("print", popup))
+    }
+}
+
+impl ActivityHandler for PauseMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data) {
+            Some(change) => match change {
+                Change::Pop { res: Some(res), .. } => {
+                    let index = *res.downcast::<usize>().expect("menu should return index");
+
+                    Some((self.actions[index])(data))
+                }
+                res => Some(res),
+            },
+            None => None,
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+impl Screen for PauseMenu {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> std::io::Result<()> {
+        if let Some(snapshot) = &self.snapshot {
+            frame.draw(Dims(0, 0), snapshot, ());
+        }
+        self.menu.draw(frame, theme)
+    }
+}
+
+/// Remaining time when the timer turns to `game.timer.warning`, a quarter of shorter limits
+const TIMER_WARNING: Duration = Duration::from_secs(10);
+
+/// Widest stamina bar in the HUD, with its title
+const STAMINA_BAR_WIDTH: i32 = 24;
+
+/// How long the viewport flashes after a bump, see [`BumpFeedback::Flash`]
+const FLASH_DURATION: Duration = Duration::from_millis(150);
+const FLASH_ALPHA: f32 = 0.5;
+
+pub struct GameActivity {
+    camera_mode: CameraMode,
+    game: GameData,
+    maze_board: MazeBoard,
+    show_debug: bool,
+    show_path: bool,
+    /// Seed is shown in the HUD, when the seed source makes it worth sharing
+    show_seed: bool,
+
+    // spacing
+    margins: Dims,
+    viewport_rect: Rect,
+    /// Area of the neighbouring floor when split view is on
+    split_rect: Option<Rect>,
+    /// Area of the first-person view, see [`super::first_person`]
+    #[cfg(feature = "first_person")]
+    first_person_rect: Option<Rect>,
+    dpad_rect: Option<Rect>,
+
+    // animated, advanced in ticks and sampled between them when drawn
+    camera_tween: Tween,
+    player_tween: Tween,
+    tick_alpha: f32,
+
+    // touch
+    touch_controls: Option<Box<DPad>>,
+
+    /// Path of the opponent in versus mode, empty otherwise
+    opponent: Vec<Dims3D>,
+    /// How long the opponent has been running, advanced in ticks
+    opponent_time: Duration,
+
+    /// Best run of the same mode, replayed along with the player, empty if disabled
+    ghost: Vec<RunStep>,
+
+    /// Moves the walls in the shifting maze
+    shifter: Option<Shifter>,
+
+    /// Other player of the online race
+    #[cfg(feature = "net")]
+    peer: Option<Peer>,
+
+    /// Events of the run log which were already handled, see [`Self::feedback`]
+    seen_events: usize,
+    /// Remaining time of the viewport flash
+    flash: Duration,
+    /// Cells left on the way to the clicked cell, the player walks to the next one every step
+    walk: VecDeque<Dims3D>,
+    /// Time since the last step of the walk
+    walk_time: Duration,
+    /// Items of the maze board, it's redrawn when they change
+    item_changes: usize,
+
+    /// Game is presented in words instead of the maze, see [`super::narration`]
+    accessible: bool,
+    /// Position of the player when the surroundings were last described
+    narrated_pos: Option<Dims3D>,
+
+    /// User scripts hooked to the events of the game
+    #[cfg(feature = "lua")]
+    scripts: Scripts,
+    /// Settings of the maze preset, reverted when the game is left
+    _preset_settings: Option<OverlayGuard>,
+    /// Stats of the run written for streaming, see [`super::overlay`]
+    overlay: Option<OverlayFiles>,
+    /// Status of the game for the HTTP server, cleared when the game is left
+    #[cfg(feature = "http")]
+    http: Option<Publisher>,
+}
+
+impl GameActivity {
+    pub fn new(mut game: GameData, app_data: &mut AppData) -> Self {
+        let unlocked_chars = app_data.save.level().unlocked_chars();
+        game.player_char = constants::get_random_player_char_with(&unlocked_chars);
+
+        let preset_settings = game
+            .rules
+            .preset
+            .and_then(|index| app_data.settings.get_mazes().get(index)?.settings.clone())
+            .map(|overlay| app_data.settings.with_overlay(overlay));
+        if preset_settings.is_some() {
+            app_data.apply_settings();
+        }
+
+        let settings = &app_data.settings;
+
+        let camera_mode = settings.get_camera_mode();
+        let maze_board = MazeBoard::new(&game.game, &app_data.theme);
+        let margins = settings.get_viewport_margin(app_data.screen_size);
+        let show_path = settings.get_show_path();
+        let show_seed = settings.get_seed_source() != SeedSource::Entropy;
+
+        #[cfg(feature = "sound")]
+        app_data.play_bgm(MusicContext::Game);
+
+        let camera_tween = Tween::new(game.camera_pos);
+        let player_tween = Tween::new(maze2screen_3d(game.game.get_player_pos()));
+
+        let opponent = match game.rules.opponent_step {
+            Some(_) => {
+                let key = OpponentPathKey(MazeKey::of(&game.game), game.game.get_player_pos());
+                let path = app_data
+                    .cache
+                    .get_or_insert_with(key, || opponent_path(&game.game));
+                path.to_vec()
+            }
+            None => vec![],
+        };
+
+        let ghost = match game.rules {
+            _ if !app_data.settings.get_show_ghost() => None,
+            GameRules { relaxed: true, .. }
+            | GameRules { custom: true, .. }
+            | GameRules { online: true, .. } => None,
+            GameRules {
+                stage: Some(stage), ..
+            } => app_data.save.get_stage_run(STAGES[stage].id),
+            GameRules {
+                daily: Some(date), ..
+            } => app_data.save.get_daily_run(date),
+            _ => app_data.save.get_best_run(game.game.get_game_mode()),
+        };
+        let ghost = ghost.map(<[_]>::to_vec).unwrap_or_default();
+        let shifter = game.rules.shifting.map(Shifter::new);
+        let item_changes = game.game.get_item_changes();
+
+        let overlay = app_data.settings.get_overlay_files().then(|| {
+            let preset = game
+                .rules
+                .preset
+                .and_then(|index| Some(app_data.settings.get_mazes().get(index)?.title.clone()))
+                .unwrap_or_else(|| {
+                    let Dims3D(w, h, d) = game.game.get_game_mode().size;
+                    format!("{}x{}x{}", w, h, d)
+                });
+            let best = best_result(&game, &app_data.save)
+                .map(|best| Duration::from_secs_f32(best.seconds.max(0.0)));
+            OverlayFiles::new(
+                app_data.settings.get_overlay_dir(),
+                app_data.queuer(),
+                preset,
+                best,
+            )
+        });
+
+        Self {
+            camera_mode,
+            game,
+            maze_board,
+            show_debug: false,
+            show_path,
+            show_seed,
+
+            margins,
+            viewport_rect: Rect::sized(app_data.screen_size),
+            split_rect: None,
+            #[cfg(feature = "first_person")]
+            first_person_rect: None,
+            dpad_rect: None,
+
+            camera_tween,
+            player_tween,
+            tick_alpha: 0.0,
+
+            touch_controls: None,
+
+            opponent,
+            opponent_time: Duration::ZERO,
+
+            ghost,
+
+            shifter,
+
+            seen_events: 0,
+            flash: Duration::ZERO,
+            walk: VecDeque::new(),
+            walk_time: Duration::ZERO,
+            item_changes,
+
+            accessible: app_data.settings.get_accessible(),
+            narrated_pos: None,
+
+            #[cfg(feature = "net")]
+            peer: None,
+
+            #[cfg(feature = "lua")]
+            scripts: Scripts::load(),
+            _preset_settings: preset_settings,
+            overlay,
+            #[cfg(feature = "http")]
+            http: app_data.http.as_ref().map(HttpServer::publisher),
+        }
+    }
+
+    /// Describes the surroundings of the player when they moved, in the accessibility mode
+    fn narrate(&mut self, data: &mut AppData) {
+        self.accessible = data.settings.get_accessible();
+        let pos = self.game.game.get_player_pos();
+        if !self.accessible || self.narrated_pos == Some(pos) {
+            return;
+        }
+
+        let text = narration::describe_surroundings(&self.game.game);
+        match self.narrated_pos {
+            Some(prev) if prev.2 == pos.2 => data.narrate(text),
+            _ => data.narrate(format!("floor {}; {}", pos.2 + 1, text)),
+        }
+        self.narrated_pos = Some(pos);
+    }
+
+    /// Plain text status of the game drawn instead of the maze, in the accessibility mode
+    ///
+    /// Time isn't shown, screen readers would read it again every second.
+    fn draw_presenter(&self, frame: &mut Frame, theme: &Theme) {
+        let game = &self.game.game;
+        let Dims3D(x, y, floor) = game.get_player_pos() + Dims3D(1, 1, 1);
+        let [items, _] = self.items_text();
+        let lines = [
+            format!(
+                "Floor {} of {}, row {}, column {}",
+                floor,
+                game.get_maze().size().2,
+                y,
+                x
+            ),
+            format!(
+                "{} moves, {} goals left{}",
+                game.get_move_count(),
+                game.get_remaining_goals(),
+                items
+            ),
+        ];
+
+        for (i, line) in lines.iter().enumerate() {
+            frame.draw(Dims(1, i as i32 + 1), line.as_str(), theme["text"]);
+        }
+    }
+
+    /// Reacts to the events logged since the last update, like the bumps into walls
+    fn feedback(&mut self, data: &mut AppData) {
+        let bumped = self
+            .game
+            .log
+            .since(self.seen_events)
+            .any(|event| matches!(event, RunEvent::Bump { .. }));
+        self.seen_events = self.game.log.len();
+        if !bumped {
+            return;
+        }
+
+        let settings = &data.settings;
+        match settings.get_bump_feedback() {
+            BumpFeedback::None => {}
+            BumpFeedback::Bell => data.ring_bell(),
+            BumpFeedback::Flash if settings.get_no_flash() || settings.get_reduce_motion() => {}
+            BumpFeedback::Flash => self.flash = FLASH_DURATION,
+        }
+    }
+
+    /// Races the other player, see [`super::online`]
+    #[cfg(feature = "net")]
+    pub fn with_peer(mut self, peer: Peer) -> Self {
+        self.peer = Some(peer);
+        self
+    }
+
+    /// Position of the camera, between the last tick and the next one
+    fn smooth_camera_pos(&self) -> Dims3D {
+        self.camera_tween.pos(TICK.mul_f32(self.tick_alpha))
+    }
+
+    /// Position of the player, between the last tick and the next one
+    fn smooth_player_pos(&self) -> Dims3D {
+        self.player_tween.pos(TICK.mul_f32(self.tick_alpha))
+    }
+
+    /// Moves the animations to the current positions of the camera and the player
+    fn animate(&mut self, settings: &Settings) {
+        let easing = settings.get_animation_easing();
+        let per_cell = |millis| match settings.get_reduce_motion() {
+            true => Duration::ZERO,
+            false => Duration::from_millis(millis),
+        };
+
+        self.camera_tween.retarget(
+            self.game.camera_pos,
+            per_cell(settings.get_camera_animation()),
+            easing,
+        );
+        self.player_tween.retarget(
+            maze2screen_3d(self.game.game.get_player_pos()),
+            per_cell(settings.get_player_animation()),
+            easing,
+        );
+        self.camera_tween.advance(TICK);
+        self.player_tween.advance(TICK);
+    }
+
+    /// Index of the opponent on its path and progress to the next cell, it moves by one cell every step
+    fn opponent_progress(&self, time: Duration) -> Option<(usize, f32)> {
+        let step = self.game.rules.opponent_step?;
+        let last = self.opponent.len().checked_sub(1)?;
+
+        let steps = time.as_secs_f64() / step.as_secs_f64().max(0.001);
+        match steps as usize {
+            index if index >= last => Some((last, 0.0)),
+            index => Some((index, steps.fract() as f32)),
+        }
+    }
+
+    fn opponent_index(&self) -> Option<usize> {
+        self.opponent_progress(self.opponent_time)
+            .map(|(index, _)| index)
+    }
+
+    /// Position of the opponent on the screen, interpolated between cells
+    fn opponent_screen_pos(&self) -> Option<Dims3D> {
+        let time = self.opponent_time + TICK.mul_f32(self.tick_alpha);
+        let (index, progress) = self.opponent_progress(time)?;
+
+        let from = maze2screen_3d(self.opponent[index]);
+        match self.opponent.get(index + 1) {
+            Some(next) if next.2 == from.2 => {
+                Some(interpolate(from, maze2screen_3d(*next), progress))
+            }
+            _ => Some(from),
+        }
+    }
+
+    /// Position of the ghost at the current game time, it stays in the goal once it's there
+    ///
+    /// The best run was most likely in a different maze, so the ghost only shows the pace
+    /// and may go through the walls of this one.
+    fn ghost_pos(&self) -> Option<Dims3D> {
+        let millis = self.game.game.get_elapsed()?.as_millis() as u64;
+        let index = self.ghost.partition_point(|(time, _)| *time <= millis);
+        let (_, pos) = self.ghost.get(index.checked_sub(1)?)?;
+        Some(*pos)
+    }
+
+    /// Checks whether the run was lost by the rules of the game mode
+    fn check_lost(&self) -> Option<Outcome> {
+        let rules = &self.game.rules;
+        // the limit is checked against the game clock, not the ticks like the opponent, it
+        // stops while paused, includes the penalties and is what the countdown in the HUD and
+        // the saved result show, so the run ends exactly when the countdown hits zero
+        let elapsed = self.game.game.get_elapsed()?;
+
+        if rules.time_limit.is_some_and(|limit| elapsed >= limit) {
+            return Some(Outcome::TimeUp);
+        }
+
+        if self.opponent_index()? + 1 == self.opponent.len() {
+            return Some(Outcome::Beaten);
+        }
+
+        None
+    }
+
+    /// Returns the size of the viewport and whether the floor fits in the viewport
+    pub fn viewport_size(&self, screen_size: Dims) -> (Dims, bool) {
+        self.fit_viewport(screen_size, self.current_floor_frame().size)
+    }
+
+    fn fit_viewport(&self, area_size: Dims, floor_size: Dims) -> (Dims, bool) {
+        let vp_size = area_size - self.margins * 2;
+
+        let does_fit = floor_size.0 <= vp_size.0 && floor_size.1 <= vp_size.1;
+
+        (if does_fit { floor_size } else { vp_size }, does_fit)
+    }
+
+    fn current_floor(&self) -> i32 {
+        match self.game.view_mode {
+            GameViewMode::Adventure => self.game.game.get_player_pos().2,
+            GameViewMode::Spectator | GameViewMode::Overview => self.game.camera_pos.2,
+        }
+    }
+
+    /// Area of the overview of the floors, with a line for the texts above and under it
+    fn overview_rect(&self) -> Rect {
+        self.viewport_rect.margin(Dims(0, 1))
+    }
+
+    /// Lays out the overview of the floors and renders its thumbnails in the detail which fits
+    fn update_overview(&mut self, theme: &Theme) {
+        let max_lod = self.game.max_lod();
+        let area = self.overview_rect();
+        let Some(overview) = &mut self.game.overview else {
+            return;
+        };
+
+        overview.update_layout(area, self.maze_board.floors(0)[0].size);
+        let Some(space) = overview.thumbnail_size() else {
+            return;
+        };
+
+        // all walls are kept in the dense zooms, detail is dropped only after them
+        let zooms = [Zoom::Normal, Zoom::HalfBlock, Zoom::Braille].map(|zoom| (0, zoom));
+        let lods = (1..=max_lod).map(|lod| (lod, Zoom::Normal));
+        let views: Vec<_> = zooms.into_iter().chain(lods).collect();
+        for (i, &(lod, zoom)) in views.iter().enumerate() {
+            self.maze_board.ensure_zoom(&self.game.game, zoom, theme);
+            self.maze_board.ensure_lod(&self.game.game, lod, theme);
+            let size = self.maze_board.view(lod, zoom)[0].size;
+            if i + 1 == views.len() || (size.0 <= space.0 && size.1 <= space.1) {
+                (overview.lod, overview.zoom) = (lod, zoom);
+                break;
+            }
+        }
+    }
+
+    fn current_floor_frame(&self) -> &Frame {
+        &self.floors()[self.current_floor() as usize]
+    }
+
+    /// Floor shown next to the current one in split view, the one above if there is any
+    fn split_floor(&self) -> i32 {
+        let floor = self.current_floor();
+        match floor + 1 < self.game.game.get_maze().size().2 {
+            true => floor + 1,
+            false => floor - 1,
+        }
+    }
+
+    /// Converts position on the board in full detail and normal zoom to the current view, which
+    /// is either zoomed or has lower level of detail
+    fn to_view(&self, pos: Dims3D) -> Dims3D {
+        if self.game.lod == 0 {
+            let Dims(x, y) = self.game.zoom.board_pos(Dims(pos.0, pos.1));
+            return Dims3D(x, y, pos.2);
+        }
+
+        let f = self.game.lod_factor();
+        Dims3D((pos.0 - 1) / f + 1, (pos.1 - 1) / f + 1, pos.2)
+    }
+
+    /// Position of the floor in its viewport, it's centered on the camera unless it fits
+    fn maze_pos(&self, vp_size: Dims, does_fit: bool) -> Dims {
+        let camera_pos = self.to_view(self.smooth_camera_pos());
+        match does_fit {
+            true => match self.game.view_mode {
+                GameViewMode::Adventure => Dims(0, 0),
+                GameViewMode::Spectator | GameViewMode::Overview => {
+                    Dims::from(self.to_view(maze2screen_3d(Dims(0, 0)))) - camera_pos.into()
+                }
+            },
+            false => vp_size / 2 - camera_pos.into(),
+        }
+    }
+
+    /// Cell of the current floor drawn at the position on the screen, [`None`] on a wall
+    ///
+    /// In the dense zooms walls share the characters with the cells, so the cell is picked.
+    fn cell_at(&self, pos: Dims) -> Option<Dims3D> {
+        let floor = self.current_floor();
+        let area = self.viewport_rect;
+        let (vp_size, does_fit) = self.fit_viewport(area.size(), self.current_floor_frame().size);
+        let vp_pos = (area.size() - vp_size) / 2 + area.start;
+        if !Rect::sized_at(vp_pos, vp_size).contains(pos) {
+            return None;
+        }
+        let pos = pos - vp_pos - self.maze_pos(vp_size, does_fit);
+
+        // cells are compared where they are drawn, so it works in every zoom
+        let half_width = match self.game.lod {
+            0 => self.game.zoom.cell_width() / 2,
+            _ => 0,
+        };
+        let Dims3D(w, h, _) = self.game.game.get_maze().size();
+        (0..h)
+            .flat_map(|y| (0..w).map(move |x| Dims3D(x, y, floor)))
+            .map(|cell| (cell, self.to_view(maze2screen_3d(cell))))
+            .filter(|(_, Dims3D(x, y, _))| (x - pos.0).abs() <= half_width && *y == pos.1)
+            .min_by_key(|(_, Dims3D(x, _, _))| (x - pos.0).abs())
+            .map(|(cell, _)| cell)
+    }
+
+    /// Cell clicked in the maze, only in adventure mode with the maze shown
+    fn clicked_cell(&self, event: &MouseEvent, settings: &Settings) -> Option<Dims3D> {
+        let shown = settings.get_enable_mouse()
+            && !self.accessible
+            && self.game.view_mode == GameViewMode::Adventure;
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) if shown => {
+                self.cell_at(Dims(event.column as i32, event.row as i32))
+            }
+            _ => None,
+        }
+    }
+
+    /// Walks the player to the cell by the shortest path, a neighbouring cell is a single move
+    ///
+    /// Longer walks make the run assisted, the clicked cell could be the goal.
+    fn walk_to(&mut self, settings: &Settings, target: Dims3D) {
+        let from = self.game.game.get_player_pos();
+        let Some(path) = self.game.game.get_maze().solve(from, target) else {
+            log::info!("Cell can't be reached from here");
+            self.walk.clear();
+            return;
+        };
+
+        self.walk = path.into_iter().skip(1).collect();
+        self.walk_time = Duration::ZERO;
+        if self.walk.len() == 1 {
+            self.walk_step(settings);
+        } else if self.walk.len() > 1 && !self.game.assisted {
+            // the way is found by the game, like with a hint
+            log::warn!("Walked to a cell, this run will not be saved as best result");
+            self.game.assisted = true;
+        }
+    }
+
+    /// Moves the player to the next cell of the walk
+    fn walk_step(&mut self, settings: &Settings) {
+        let Some(next) = self.walk.pop_front() else {
+            return;
+        };
+
+        let from = self.game.game.get_player_pos();
+        let wall = CellWall::get_in_order()
+            .into_iter()
+            .find(|wall| from + wall.to_coord() == next);
+        if let Some(wall) = wall {
+            self.game.step_player(settings, wall);
+        }
+
+        let pos = self.game.game.get_player_pos();
+        if pos == next {
+            return;
+        }
+
+        // stairs of a tower took the player up, the rest of the way is found again,
+        // otherwise the way is blocked, like by a locked door or the lack of stamina
+        let target = self.walk.back().copied();
+        self.walk.clear();
+        if let Some(target) = target.filter(|_| pos != from) {
+            if let Some(path) = self.game.game.get_maze().solve(pos, target) {
+                self.walk = path.into_iter().skip(1).collect();
+            }
+        }
+    }
+
+    /// Rendered floors of the current view, see [`GameActivity::to_view`]
+    fn floors(&self) -> &[Frame] {
+        self.maze_board.view(self.game.lod, self.game.zoom)
+    }
+
+    fn render_meta_texts(&self, frame: &mut Frame, theme: &Theme, vp: Rect) {
+        let max_width = (vp.size().0 / 2 + 1) as usize;
+
+        let pl_pos = self.game.game.get_player_pos() + Dims3D(1, 1, 1);
+
+        // texts
+        let elapsed = self.game.game.get_elapsed().unwrap();
+        let warning = self.game.rules.time_limit.is_some_and(|limit| {
+            let remaining = limit.saturating_sub(elapsed);
+            !self.game.rules.relaxed && remaining <= TIMER_WARNING.min(limit / 4)
+        });
+        let from_start = match self.game.rules {
+            GameRules { relaxed: true, .. } => String::new(),
+            GameRules {
+                time_limit: Some(limit),
+                ..
+            } => multisize_duration_format(limit.saturating_sub(elapsed), max_width),
+            _ => multisize_duration_format(elapsed, max_width),
+        };
+        let moves = self.game.game.get_move_count();
+        let [items, items_short] = self.items_text();
+        let move_count = match self.game.game.get_game_mode().objective {
+            GoalObjective::VisitAll => {
+                let goals = self.game.game.get_remaining_goals();
+                strings::multisize_string(
+                    [
+                        format!("{} moves, {} goals left{}", moves, goals, items),
+                        format!("{}m, {} goals{}", moves, goals, items_short),
+                        format!("{}m {}g{}", moves, goals, items_short),
+                    ],
+                    max_width,
+                )
+            }
+            GoalObjective::ReachAny => strings::multisize_string(
+                [
+                    format!("{} moves{}", moves, items),
+                    format!("{}m{}", moves, items_short),
+                ],
+                max_width,
+            ),
+        };
+
         let pos_text = if self.game.game.get_maze().size().2 > 1 {
             strings::multisize_string(
                 [
@@ -650,8 +1901,38 @@ impl GameActivity {
             )
         };
 
-        let view_mode = self.game.view_mode;
-        let view_mode = strings::multisize_string(view_mode.to_multisize_strings(), max_width);
+        let view_mode = match self.game.macro_state {
+            MacroState::Recording { slot, .. } => strings::multisize_string(
+                [
+                    format!("Recording macro {}", slot),
+                    format!("Rec {}", slot),
+                    format!("R{}", slot),
+                ],
+                max_width,
+            ),
+            _ if self.game.view_mode == GameViewMode::Adventure
+                && self.game.relative_controls() =>
+            {
+                let arrow = match self.game.facing {
+                    CellWall::Top => '↑',
+                    CellWall::Left => '←',
+                    CellWall::Right => '→',
+                    CellWall::Bottom => '↓',
+                    CellWall::Up | CellWall::Down => '·',
+                };
+                strings::multisize_string(
+                    [
+                        format!("Adventure, facing {}", arrow),
+                        format!("Adv {}", arrow),
+                        format!("A{}", arrow),
+                    ],
+                    max_width,
+                )
+            }
+            _ => strings::multisize_string(self.game.view_mode.to_multisize_strings(), max_width)
+                .to_string(),
+        };
+        let view_mode = view_mode.as_str();
 
         let tl = vp.start - Dims(0, 1);
         let br = vp.start + vp.size();
@@ -660,50 +1941,287 @@ impl GameActivity {
         let mut draw = |text: &str, pos| frame.draw(pos, text, style);
 
         draw(&pos_text, tl);
-        draw(view_mode, Dims(br.0 - view_mode.len() as i32, tl.1));
+        draw(view_mode, Dims(br.0 - view_mode.width() as i32, tl.1));
+
+        if self.show_seed {
+            // above the other texts, centered over the maze
+            let seed = format!("Seed {}", self.game.game.get_seed());
+            let x = vp.start.0 + (vp.size().0 - seed.width() as i32) / 2;
+            draw(&seed, Dims(x.max(0), tl.1 - 1));
+        }
         draw(&move_count, Dims(tl.0, br.1));
-        draw(&from_start, Dims(br.0 - from_start.len() as i32, br.1));
+
+        let timer_style = match warning {
+            true => theme["game.timer.warning"],
+            false => style,
+        };
+        let timer_pos = Dims(br.0 - from_start.len() as i32, br.1);
+        frame.draw(timer_pos, from_start.as_str(), timer_style);
+
+        if let Some(meter) = &self.game.stamina {
+            // between the move count and the timer, if there is room
+            let left = tl.0 + move_count.width() as i32 + 2;
+            let width = (timer_pos.0 - 2 - left).min(STAMINA_BAR_WIDTH);
+            if width >= 4 {
+                let title = if width >= 12 { "Stamina" } else { "" };
+                let mut bar = ProgressBar::new(title.to_string());
+                bar.update_progress(meter.fraction());
+                let pos = Dims(left + (timer_pos.0 - 2 - left - width) / 2, br.1);
+                bar.draw_line(frame, pos, width, theme);
+            }
+        }
+    }
+
+    /// Coins and keys of the player, appended to the move count, long and short version
+    ///
+    /// Both are empty if there are no items in the maze.
+    fn items_text(&self) -> [String; 2] {
+        let game = &self.game.game;
+        let (coins, total, keys) = (game.get_coins(), game.get_total_coins(), game.get_keys());
+
+        let mut texts = [String::new(), String::new()];
+        if total > 0 {
+            texts[0] += &format!(", {}/{} coins", coins, total);
+            texts[1] += &format!(" {}/{}¢", coins, total);
+        }
+        if keys > 0 {
+            texts[0] += &format!(", {} {}", keys, if keys == 1 { "key" } else { "keys" });
+            texts[1] += &format!(" {}⚷", keys);
+        }
+        texts
     }
 
-    pub fn render_visited_places(&self, frame: &mut Frame, maze_pos: Dims, theme: &Theme) {
+    /// Visited places on the floor, when the path isn't shown
+    fn visited_annotations(&self, floor: i32) -> Vec<Annotation> {
         use CellWall::{Down, Up};
 
         let game = &self.game.game;
-        for (move_pos, _) in game.get_moves() {
-            let cell = game.get_maze().get_cell(*move_pos).unwrap();
-            if move_pos.2 == game.get_player_pos().2 && cell.get_wall(Up) && cell.get_wall(Down) {
-                let real_pos = maze2screen(*move_pos) + maze_pos;
-                frame.draw(real_pos, '.', theme["game.visited"]); // FIXME: move out of the
-                                                                  // loop
+        game.get_moves()
+            .iter()
+            .map(|(pos, _)| *pos)
+            .filter(|pos| {
+                let cell = game.get_maze().get_cell(*pos).unwrap();
+                pos.2 == floor && cell.get_wall(Up) && cell.get_wall(Down)
+            })
+            .map(|pos| Annotation::cell(pos, Mark::Marker('.'), "game.visited", Layer::Trail))
+            .collect()
+    }
+
+    /// Path of the player on the floor as lines, colored by age
+    fn path_annotations(&self, floor: i32) -> Vec<Annotation> {
+        use CellWall::*;
+
+        const AGE_STYLES: [&str; 4] = [
+            "game.path.new",
+            "game.path.recent",
+            "game.path.old",
+            "game.path.oldest",
+        ];
+
+        let game = &self.game.game;
+        let maze = game.get_maze();
+        let moves = game.get_moves();
+
+        let mut annotations = vec![];
+        for (pos, (line, index)) in path_lines(moves.iter().copied(), floor) {
+            // keep stairs visible
+            let cell_pos = Dims3D((pos.0 - 1) / 2, (pos.1 - 1) / 2, floor);
+            let is_cell = pos.0 % 2 == 1 && pos.1 % 2 == 1;
+            if is_cell {
+                let cell = maze.get_cell(cell_pos).unwrap();
+                if !cell.get_wall(Up) || !cell.get_wall(Down) {
+                    continue;
+                }
+            }
+
+            let age = (moves.len() - 1 - index) * AGE_STYLES.len() / moves.len();
+            annotations.push(Annotation::board(
+                Dims3D(pos.0, pos.1, floor),
+                Mark::Marker(line.round()),
+                AGE_STYLES[age],
+                Layer::Trail,
+            ));
+        }
+        annotations
+    }
+
+    fn reached_goal_annotations(&self, floor: i32) -> Vec<Annotation> {
+        let game = &self.game.game;
+        game.get_goals()
+            .iter()
+            .filter(|goal| goal.2 == floor && game.is_goal_reached(**goal))
+            .map(|goal| {
+                Annotation::cell(*goal, Mark::Marker('$'), "game.goal.reached", Layer::Goal)
+            })
+            .collect()
+    }
+
+    fn render_player(
+        &self,
+        maze_pos: Dims,
+        game: &RunningGame,
+        viewport: &mut Frame,
+        theme: &Theme,
+    ) {
+        let player = self.to_view(self.smooth_player_pos());
+        let player_draw_pos = maze_pos + player.into();
+        let cell = game
+            .get_maze()
+            .get_cell(self.game.game.get_player_pos())
+            .unwrap();
+        if !cell.get_wall(CellWall::Up) || !cell.get_wall(CellWall::Down) {
+            viewport[player_draw_pos]
+                .content_mut()
+                .unwrap()
+                .style
+                .foreground_color = theme["game.player"].to_cross().foreground_color;
+        } else {
+            viewport.draw(player_draw_pos, self.game.player_char, theme["game.player"]);
+        }
+    }
+
+    /// Environment of the run, stored with its results
+    fn environment(&self, data: &AppData) -> Environment {
+        Environment {
+            zoom: self.game.zoom,
+            ..Environment::new(&data.settings, data.screen_size, self.game.modifiers())
+        }
+    }
+
+    /// Saves the result as best result, if the run is eligible
+    ///
+    /// Daily challenges have their own records, see [`super::daily`].
+    fn save_result(&self, data: &mut AppData) {
+        if self.game.assisted
+            || self.game.rules.relaxed
+            || self.game.rules.custom
+            || self.game.rules.shifting.is_some()
+            || self.game.rules.online
+            || data.settings.is_ro()
+        {
+            return;
+        }
+
+        let game = &self.game.game;
+        let result = SolveResult {
+            moves: game.get_move_count() as i32,
+            seconds: game.get_elapsed().unwrap().as_secs_f32(),
+            wall_seconds: game.get_wall_elapsed().map(|t| t.as_secs_f32()),
+            run: recorded_run(game),
+            env: Some(self.environment(data)),
+        };
+        let res = match self.game.rules {
+            GameRules {
+                stage: Some(stage), ..
+            } => data.save.set_stage_result(STAGES[stage].id, result),
+            GameRules {
+                daily: Some(date), ..
+            } => data.save.set_daily_result(date, result),
+            _ => data.save.set_best_result(game.get_game_mode(), result),
+        };
+
+        if let Err(err) = res {
+            log::error!("Failed to save the result: {}", err);
+        }
+    }
+
+    /// Counts the win, shows the achievements and levels it brought
+    fn record_win(&self, data: &mut AppData) {
+        if self.game.assisted || data.settings.is_ro() {
+            return;
+        }
+
+        let game = &self.game.game;
+        let mut visited = HashSet::new();
+        let win = Win {
+            size: game.get_game_mode().size,
+            seconds: game.get_elapsed().unwrap_or_default().as_secs_f32(),
+            moves: game.get_move_count(),
+            backtracked: self.game.undos > 0
+                || !recorded_run(game)
+                    .into_iter()
+                    .all(|(_, pos)| visited.insert(pos)),
+            daily: self.game.rules.daily.is_some(),
+            coins: game.get_coins(),
+        };
+
+        match data.save.record_win(&win) {
+            Ok(reward) => {
+                log::info!("Gained {} XP", reward.xp);
+                if let Some(level) = reward.level_up {
+                    log::info!("Reached level {}", level);
+                    data.toasts.push("Level up", format!("Level {}", level));
+                }
+                for achievement in reward.achievements {
+                    log::info!("Achievement unlocked: {}", achievement.name);
+                    data.toasts.push("Achievement unlocked", achievement.name);
+                }
             }
+            Err(err) => log::error!("Failed to save the progress: {}", err),
         }
     }
 
-    fn render_player(
-        &self,
-        maze_pos: Dims,
-        game: &RunningGame,
-        viewport: &mut Frame,
-        theme: &Theme,
-    ) {
-        let player = self.sm_player_pos;
-        let player_draw_pos = maze_pos + player.into();
-        let cell = game
-            .get_maze()
-            .get_cell(self.game.game.get_player_pos())
-            .unwrap();
-        if !cell.get_wall(CellWall::Up) || !cell.get_wall(CellWall::Down) {
-            viewport[player_draw_pos]
-                .content_mut()
-                .unwrap()
-                .style
-                .foreground_color = theme["game.player"].to_cross().foreground_color;
-        } else {
-            viewport.draw(player_draw_pos, self.game.player_char, theme["game.player"]);
+    /// Adds the won run to the history, returns its date, so it can be tagged
+    fn add_to_history(&self, data: &mut AppData) -> Option<DateTime<Local>> {
+        if data.settings.is_ro() {
+            return None;
+        }
+
+        let game = &self.game.game;
+        let date = Local::now();
+        let run = RunRecord {
+            date,
+            game_mode: game.get_game_mode(),
+            seed: game.get_seed(),
+            seconds: game.get_elapsed().unwrap_or_default().as_secs_f32(),
+            moves: game.get_move_count(),
+            coins: game.get_coins(),
+            exhausted: self.game.stamina.as_ref().map(StaminaMeter::exhausted),
+            tags: vec![],
+            env: Some(self.environment(data)),
+        };
+
+        match data.save.add_run(run) {
+            Ok(()) => Some(date),
+            Err(err) => {
+                log::error!("Failed to save the run: {}", err);
+                None
+            }
+        }
+    }
+
+    fn save_replay(&self, data: &AppData) {
+        // replays can't store custom start positions or moving walls
+        let replayable = !self.game.rules.custom && self.game.rules.shifting.is_none();
+        let replayable = replayable && !self.game.rules.online;
+        if !data.settings.get_save_replays() || !replayable || data.settings.is_ro() {
+            return;
+        }
+
+        match save_replay(&self.game.game) {
+            Ok(path) => log::info!("Replay saved to {}", path.display()),
+            Err(err) => log::error!("Failed to save the replay: {}", err),
+        }
+    }
+
+    /// Ends the run log and writes it, if it's enabled
+    fn write_log(&mut self, data: &AppData, outcome: Outcome) {
+        let moves = self.game.game.get_move_count();
+        self.game.log_event(RunEvent::End { outcome, moves });
+
+        if !data.settings.get_write_event_log() || data.settings.is_ro() {
+            return;
+        }
+
+        match self.game.log.write() {
+            Ok(path) => log::info!("Event log saved to {}", path.display()),
+            Err(err) => log::error!("Failed to save the event log: {}", err),
         }
     }
 
     fn update_viewport(&mut self, data: &AppData) {
+        self.margins = data.settings.get_viewport_margin(data.screen_size);
+
         if self.is_dpad_enabled() {
             let (viewport_rect, dpad_rect) = DPad::split_screen(data);
             let mut dpad_rect = dpad_rect;
@@ -716,6 +2234,29 @@ impl GameActivity {
         } else {
             self.viewport_rect = Rect::sized(data.screen_size);
         }
+
+        self.split_rect = None;
+        let size = self.viewport_rect.size();
+        let fits = size.0 >= SPLIT_VIEW_MIN_SIZE.0 && size.1 >= SPLIT_VIEW_MIN_SIZE.1;
+
+        // takes the place of the split view
+        #[cfg(feature = "first_person")]
+        {
+            self.first_person_rect = None;
+            if data.settings.get_first_person_view() && fits {
+                let (viewport_rect, first_person_rect) =
+                    self.viewport_rect.split_x(Offset::Rel(0.5));
+                self.viewport_rect = viewport_rect;
+                self.first_person_rect = Some(first_person_rect);
+                return;
+            }
+        }
+
+        if self.game.split_view && fits {
+            let (viewport_rect, split_rect) = self.viewport_rect.split_x(Offset::Rel(0.5));
+            self.viewport_rect = viewport_rect;
+            self.split_rect = Some(split_rect);
+        }
     }
 }
 impl GameActivity {
@@ -727,11 +2268,21 @@ impl GameActivity {
         let dpad_type = DPadType::from_maze(self.game.game.get_maze());
         let swap_up_down = data.settings.get_dpad_swap_up_down();
 
-        let touch_controls = DPad::new(None, swap_up_down, dpad_type);
+        let actions = data.settings.get_dpad_actions();
+
+        let touch_controls = DPad::new(None, swap_up_down, dpad_type, actions);
         self.touch_controls = Some(Box::new(touch_controls));
     }
 
     fn update_dpad(&mut self, data: &AppData) {
+        let actions_changed = self
+            .touch_controls
+            .as_ref()
+            .is_some_and(|dpad| dpad.actions() != data.settings.get_dpad_actions());
+        if actions_changed {
+            self.init_dpad(data);
+        }
+
         if (data.settings.get_enable_dpad() && data.settings.get_enable_mouse())
             != self.is_dpad_enabled()
         {
@@ -752,6 +2303,67 @@ impl GameActivity {
         }
     }
 
+    /// Pauses or quits the game on the result of [`GameData::handle_event`]
+    ///
+    /// Does nothing unless the game is running, a walk may finish it before the events are handled
+    fn handle_input_result(&mut self, res: Result<(), bool>, data: &AppData) -> Option<Change> {
+        if self.game.game.get_state() != RunningGameState::Running {
+            return None;
+        }
+
+        match res {
+            Err(false) => self.pause(data),
+            Err(true) if data.settings.get_confirm_quit() => {
+                self.game.game.pause().unwrap();
+                self.game.log_event(RunEvent::Pause);
+
+                Some(Change::push(QuitConfirmActivity::new_activity(
+                    RunSummary::new(&self.game.game),
+                    QuitTarget::MainMenu,
+                )))
+            }
+            Err(true) => Some(Change::pop_until("main menu")),
+            Ok(_) => None,
+        }
+    }
+
+    /// Pause the game and open the pause menu, with the game frozen behind it
+    ///
+    /// Returns [`None`] if the game isn't running
+    fn pause(&mut self, data: &AppData) -> Option<Change> {
+        if self.game.game.get_state() != RunningGameState::Running {
+            return None;
+        }
+
+        self.game.game.pause().unwrap();
+        self.game.log_event(RunEvent::Pause);
+        #[cfg(feature = "http")]
+        self.publish_status();
+
+        let mut menu = PauseMenu::new(RunSummary::new(&self.game.game), &self.game);
+        if !data.settings.get_hide_maze_when_paused() {
+            let mut snapshot = Frame::new(data.screen_size);
+            snapshot.fill(renderer::Cell::styled(' ', data.theme["background"]));
+            if let Err(err) = self.draw(&mut snapshot, &data.theme) {
+                log::warn!("Could not draw the paused game: {}", err);
+            }
+            menu = menu.with_snapshot(snapshot, &data.theme);
+        }
+
+        Some(Change::push(Activity::new_base_boxed(
+            "pause".to_string(),
+            menu,
+        )))
+    }
+
+    /// Shows the game to the clients of the HTTP server
+    #[cfg(feature = "http")]
+    fn publish_status(&self) {
+        if let Some(http) = &self.http {
+            http.publish(&self.game.game);
+        }
+    }
+
     fn deinit_dpad(&mut self, data: &AppData) {
         self.touch_controls = None;
 
@@ -760,60 +2372,155 @@ impl GameActivity {
     }
 }
 
+/// Best result of the same maze, by the stage or the day of the run if it has one
+fn best_result<'a>(game: &GameData, save: &'a SaveData) -> Option<&'a SolveResult> {
+    match game.rules {
+        GameRules {
+            stage: Some(stage), ..
+        } => save.get_stage_result(STAGES[stage].id),
+        GameRules {
+            daily: Some(date), ..
+        } => save.get_daily_result(date),
+        _ => save.get_best_result(game.game.get_game_mode()),
+    }
+}
+
 impl ActivityHandler for GameActivity {
+    fn tick(&mut self, data: &mut AppData) {
+        self.animate(&data.settings);
+
+        if self.game.game.get_state() == RunningGameState::Running {
+            self.opponent_time += TICK;
+            if let Some(meter) = &mut self.game.stamina {
+                meter.rest(TICK);
+            }
+
+            // one cell of the walk every animation of the player
+            self.walk_time += TICK;
+            let step = Duration::from_millis(data.settings.get_player_animation()).max(TICK);
+            if !self.walk.is_empty() && self.walk_time >= step {
+                self.walk_time = Duration::ZERO;
+                self.walk_step(&data.settings);
+            }
+        }
+
+        self.game.annotations.tick(TICK);
+        self.flash = self.flash.saturating_sub(TICK);
+
+        #[cfg(feature = "http")]
+        self.publish_status();
+    }
+
     fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
         match self.game.game.get_state() {
             RunningGameState::NotStarted => self.game.game.start().unwrap(),
-            RunningGameState::Paused => self.game.game.resume().unwrap(),
+            RunningGameState::Paused => {
+                self.game.game.resume().unwrap();
+                self.game.log_event(RunEvent::Resume);
+            }
             _ => {}
         }
 
         self.update_dpad(data);
         self.update_viewport(data);
+        self.narrate(data);
 
         if let Some(ref mut tc) = self.touch_controls {
             tc.update_space(self.dpad_rect.expect("dpad rect not set"));
         }
 
         for event in events {
-            #[allow(clippy::single_match)]
             match event {
                 Event::Term(event) => match event {
                     TermEvent::Key(key_event) => {
-                        match self.game.handle_event(&data.settings, key_event) {
-                            Err(false) => {
-                                self.game.game.pause().unwrap();
-
-                                return Some(Change::push(Activity::new_base_boxed(
-                                    "pause".to_string(),
-                                    PauseMenu::new(),
-                                )));
-                            }
-                            Err(true) => return Some(Change::pop_until("main menu")),
-                            Ok(_) => {}
+                        self.walk.clear();
+                        let res = self.game.handle_event(&mut data.settings, key_event);
+                        if let Some(change) = self.handle_input_result(res, data) {
+                            return Some(change);
                         }
                     }
+                    TermEvent::FocusLost if data.settings.get_pause_on_focus_loss() => {
+                        if let Some(change) = self.pause(data) {
+                            return Some(change);
+                        }
+                    }
+                    TermEvent::Mouse(event) if self.game.overview.is_some() => {
+                        let overview = self.game.overview.as_mut().unwrap();
+                        let action = overview.handle_mouse(event);
+                        self.game.overview_action(action);
+                    }
+                    TermEvent::Mouse(event) if self.game.floor_jump.is_some() => {
+                        let floor_jump = self.game.floor_jump.as_mut().unwrap();
+                        let action = floor_jump.handle_mouse(event, data.screen_size);
+                        self.game.floor_jump_action(action);
+                    }
                     TermEvent::Mouse(event) => {
-                        if let Some(ref mut touch_controls) = self.touch_controls {
-                            if let Some(dir) = touch_controls.apply_mouse_event(event) {
-                                self.game.apply_move(&data.settings, dir, false);
+                        let action = self
+                            .touch_controls
+                            .as_mut()
+                            .and_then(|touch_controls| touch_controls.apply_mouse_event(event));
+                        if let Some(action) = action {
+                            self.walk.clear();
+                            let res = self.game.handle_action(&mut data.settings, action, false);
+                            if let Some(change) = self.handle_input_result(res, data) {
+                                return Some(change);
                             }
+                        } else if let Some(cell) = self.clicked_cell(&event, &data.settings) {
+                            self.walk_to(&data.settings, cell);
                         }
                     }
                     _ => {}
                 },
+                Event::Pause => {
+                    if let Some(change) = self.pause(data) {
+                        return Some(change);
+                    }
+                }
+                Event::SettingsReloaded => {
+                    // the rest is read from the settings every frame
+                    self.camera_mode = data.settings.get_camera_mode();
+                    self.maze_board = MazeBoard::new(&self.game.game, &data.theme);
+                }
                 _ => (),
             }
         }
 
         if let Some(ref mut tc) = self.touch_controls {
-            tc.update_available_moves(if self.game.view_mode == GameViewMode::Adventure {
-                self.game.game.get_available_moves()
-            } else {
-                [true; 6] // enable all
-            });
+            let relative = data.settings.get_relative_controls();
+            tc.update_available_moves(
+                if self.game.view_mode == GameViewMode::Adventure && !relative {
+                    self.game.game.get_available_moves()
+                } else {
+                    [true; 6] // enable all, relative moves don't go in the direction of the button
+                },
+            );
+        }
+
+        if let Some(shifter) = &mut self.shifter {
+            match shifter.update(&mut self.game.game) {
+                ShiftEvent::Warning => {
+                    #[cfg(feature = "sound")]
+                    data.play_sound(tone(660.0, Duration::from_millis(120)));
+                }
+                ShiftEvent::Shifted => {
+                    self.maze_board = MazeBoard::new(&self.game.game, &data.theme);
+                }
+                ShiftEvent::None => {}
+            }
+        }
+
+        if self.game.game.get_item_changes() != self.item_changes {
+            self.item_changes = self.game.game.get_item_changes();
+            self.maze_board = MazeBoard::new(&self.game.game, &data.theme);
         }
 
+        // after the board is rendered again, the camera needs its size
+        self.maze_board
+            .ensure_lod(&self.game.game, self.game.lod, &data.theme);
+        self.maze_board
+            .ensure_zoom(&self.game.game, self.game.zoom, &data.theme);
+        self.update_overview(&data.theme);
+
         if self.game.view_mode == GameViewMode::Adventure {
             match self.camera_mode {
                 CameraMode::CloseFollow => {
@@ -822,7 +2529,7 @@ impl ActivityHandler for GameActivity {
                 CameraMode::EdgeFollow(xoff, yoff) => 'b: {
                     self.game.camera_pos.2 = self.game.game.get_player_pos().2;
 
-                    let (vp_size, does_fit) = self.viewport_size(data.screen_size);
+                    let (vp_size, does_fit) = self.viewport_size(self.viewport_rect.size());
 
                     if does_fit {
                         break 'b;
@@ -831,9 +2538,11 @@ impl ActivityHandler for GameActivity {
                     let xoff = xoff.to_abs(vp_size.0);
                     let yoff = yoff.to_abs(vp_size.1);
 
-                    let player_pos = maze2screen(self.game.game.get_player_pos());
-                    let player_pos_in_vp =
-                        player_pos - self.game.camera_pos.into() + vp_size / 2 + Dims(1, 1);
+                    let player_pos = maze2screen_3d(self.game.game.get_player_pos());
+                    let camera_pos = self.to_view(self.game.camera_pos);
+                    let player_pos_in_vp = Dims::from(self.to_view(player_pos)) - camera_pos.into()
+                        + vp_size / 2
+                        + Dims(1, 1);
 
                     if player_pos_in_vp.0 < xoff || player_pos_in_vp.0 > vp_size.0 - xoff {
                         self.game.camera_pos.0 = player_pos.0;
@@ -846,69 +2555,264 @@ impl ActivityHandler for GameActivity {
             }
         }
 
-        self.sm_player_pos = lerp!((self.sm_player_pos) -> (maze2screen_3d(self.game.game.get_player_pos())) at data.settings.get_player_smoothing());
-        self.sm_camera_pos = lerp!((self.sm_camera_pos) -> (self.game.camera_pos) at data.settings.get_camera_smoothing());
+        self.feedback(data);
+        self.tick_alpha = data.tick_alpha;
 
         self.show_debug = data.use_data.show_debug;
+        self.show_path = data.settings.get_show_path();
+
+        #[cfg(feature = "lua")]
+        self.scripts.update(&self.game.game);
+
+        #[cfg(feature = "net")]
+        if let Some(peer) = &mut self.peer {
+            if let Some(outcome) = peer.update(&self.game.game) {
+                let popup = peer.result_popup(outcome, &self.game.game);
+                self.write_log(data, outcome);
+                let _ = self.game.game.pause();
+
+                return Some(Change::replace_at(
+                    1,
+                    Activity::new_base_boxed("race result", popup),
+                ));
+            }
+        }
+
+        if let Some(overlay) = &mut self.overlay {
+            overlay.update(&self.game.game);
+        }
+
+        // best result before this run, so the end screen can compare them
+        let best = best_result(&self.game, &data.save).map(SolveResult::without_run);
 
         if self.game.game.get_state() == RunningGameState::Finished {
+            self.save_result(data);
+            self.record_win(data);
+            let run_date = self.add_to_history(data);
+            self.write_log(data, Outcome::Won);
+            self.save_replay(data);
+
+            if self.game.rules.endless {
+                let cleared = self.game.cleared + 1;
+                log::info!("Maze {} cleared", cleared);
+
+                let game = &self.game.game;
+                return Some(Change::replace(Activity::new_base_boxed(
+                    "maze_gen",
+                    MazeGenerationActivity::new(game.get_game_mode(), game.get_gen_fn())
+                        .with_rules(self.game.rules)
+                        .with_cleared(cleared),
+                )));
+            }
+
             return Some(Change::replace_at(
                 1,
-                Activity::new_base_boxed("won".to_string(), EndGamePopup::new(&self.game.game)),
+                Activity::new_base_boxed(
+                    "won".to_string(),
+                    EndGameActivity::new(&self.game, Outcome::Won, best, &mut data.cache)
+                        .with_real_time(data.settings.get_real_time_results())
+                        .with_auto_return(Duration::from_secs(
+                            data.settings.get_end_screen_timeout(),
+                        ))
+                        .with_run_date(run_date),
+                ),
             ));
         };
 
+        if let Some(outcome) = self.check_lost() {
+            self.write_log(data, outcome);
+            self.game.game.pause().unwrap();
+
+            return Some(Change::replace_at(
+                1,
+                Activity::new_base_boxed(
+                    "lost",
+                    EndGameActivity::new(&self.game, outcome, best, &mut data.cache)
+                        .with_real_time(data.settings.get_real_time_results())
+                        .with_auto_return(Duration::from_secs(
+                            data.settings.get_end_screen_timeout(),
+                        )),
+                ),
+            ));
+        }
+
         None
     }
 
     fn screen(&self) -> &dyn ui::Screen {
         self
     }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.game.hints()
+    }
 }
 
-impl Screen for GameActivity {
-    fn draw(&self, frame: &mut Frame, theme: &Theme) -> std::io::Result<()> {
-        let maze_frame = self.current_floor_frame();
+impl GameActivity {
+    /// Draws the floor centered in the area with its border, returns the border and whether the
+    /// whole floor fits
+    fn draw_floor(&self, frame: &mut Frame, theme: &Theme, area: Rect, floor: i32) -> (Rect, bool) {
+        let maze_frame = &self.floors()[floor as usize];
         let game = &self.game.game;
 
-        let game_view_rect = self.viewport_rect;
-        let game_view_size = game_view_rect.size();
-
-        let (vp_size, does_fit) = self.viewport_size(game_view_size);
-        let maze_pos = match does_fit {
-            true => match self.game.view_mode {
-                GameViewMode::Adventure => Dims(0, 0),
-                GameViewMode::Spectator => maze2screen(Dims(0, 0)) - self.sm_camera_pos.into(),
-            },
-            false => vp_size / 2 - self.sm_camera_pos.into(),
-        };
+        let area_size = area.size();
+        let (vp_size, does_fit) = self.fit_viewport(area_size, maze_frame.size);
+        let maze_pos = self.maze_pos(vp_size, does_fit);
 
         // TODO: reuse the viewport between frames and resize it when needed
         let mut viewport = Frame::new(vp_size);
 
         // maze
         viewport.draw(maze_pos, maze_frame, ());
-        self.render_visited_places(&mut viewport, maze_pos, theme);
+        // marks would cover the dots of several cells
+        let detailed = self.game.lod == 0 && !self.game.zoom.is_dense();
+        if detailed {
+            let trail = match self.show_path {
+                true => self.path_annotations(floor),
+                false => self.visited_annotations(floor),
+            };
+            let goals = self.reached_goal_annotations(floor);
+            let annotations = trail
+                .iter()
+                .chain(&goals)
+                .chain(self.game.annotations.iter());
+            draw_annotations(
+                &mut viewport,
+                maze_pos,
+                self.game.zoom,
+                floor,
+                game.get_maze(),
+                theme,
+                annotations,
+            );
+
+            if let (Some(shifter), Some(elapsed)) = (&self.shifter, game.get_elapsed()) {
+                shifter.draw(
+                    &mut viewport,
+                    maze_pos,
+                    self.game.zoom,
+                    floor,
+                    elapsed,
+                    theme,
+                );
+            }
+        }
 
         // player
-        if (self.game.game.get_player_pos().2) == self.sm_camera_pos.2 {
-            self.render_player(maze_pos, game, &mut viewport, theme);
+        if game.get_player_pos().2 == floor {
+            if detailed {
+                self.render_player(maze_pos, game, &mut viewport, theme);
+            } else {
+                let pos = self.to_view(maze2screen_3d(game.get_player_pos()));
+                viewport.draw(
+                    maze_pos + pos.into(),
+                    self.game.player_char,
+                    theme["game.player"],
+                );
+            }
+        }
+
+        // ghost, under the opponent
+        if let Some(pos) = self.ghost_pos().map(maze2screen_3d) {
+            if pos.2 == floor {
+                let pos = self.to_view(pos);
+                viewport.draw(
+                    maze_pos + pos.into(),
+                    self.game.player_char,
+                    theme["game.ghost"],
+                );
+            }
+        }
+
+        // opponent
+        if let Some(pos) = self.opponent_screen_pos() {
+            if pos.2 == floor {
+                let pos = self.to_view(pos);
+                viewport.draw(
+                    maze_pos + pos.into(),
+                    constants::OPPONENT_CHAR,
+                    theme["game.opponent"],
+                );
+            }
+        }
+
+        // other player of the online race
+        #[cfg(feature = "net")]
+        if let Some(pos) = self.peer.as_ref().and_then(Peer::pos) {
+            if pos.2 == floor {
+                let pos = self.to_view(maze2screen_3d(pos));
+                viewport.draw(
+                    maze_pos + pos.into(),
+                    constants::OPPONENT_CHAR,
+                    theme["game.opponent"],
+                );
+            }
+        }
+
+        if !self.flash.is_zero() {
+            viewport.blend(theme["game.flash"].to_cross(), FLASH_ALPHA);
         }
 
         // show viewport box
-        let vp_pos = (game_view_size - vp_size) / 2 + self.viewport_rect.start;
+        let vp_pos = (area_size - vp_size) / 2 + area.start;
         let vp_rect = Rect::sized_at(vp_pos, vp_size).margin(Dims(-1, -1));
         vp_rect.render(frame, theme["game.viewport.border"]);
 
-        if let CameraMode::EdgeFollow(xoff, yoff) = self.camera_mode {
-            if !does_fit && self.show_debug {
-                render_edge_follow_rulers((xoff, yoff), frame, vp_rect, theme);
+        frame.draw(vp_pos, &viewport, ());
+
+        (vp_rect, does_fit)
+    }
+}
+
+impl Screen for GameActivity {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> std::io::Result<()> {
+        if self.accessible {
+            self.draw_presenter(frame, theme);
+        } else if let Some(overview) = &self.game.overview {
+            let player = (self.game.game.get_player_pos(), self.game.player_char);
+            let thumbnails = self.maze_board.view(overview.lod, overview.zoom);
+            overview.draw(frame, theme, thumbnails, player);
+            self.render_meta_texts(frame, theme, self.overview_rect());
+        } else {
+            let (vp_rect, does_fit) =
+                self.draw_floor(frame, theme, self.viewport_rect, self.current_floor());
+
+            if let CameraMode::EdgeFollow(xoff, yoff) = self.camera_mode {
+                if !does_fit && self.show_debug {
+                    render_edge_follow_rulers((xoff, yoff), frame, vp_rect, theme);
+                }
             }
+
+            self.render_meta_texts(frame, theme, vp_rect);
         }
 
-        self.render_meta_texts(frame, theme, vp_rect);
+        // neighbouring floor
+        let maze_shown = !self.accessible && self.game.overview.is_none();
+        if let Some(split_rect) = self.split_rect.filter(|_| maze_shown) {
+            let floor = self.split_floor();
+            let (split_vp_rect, _) = self.draw_floor(frame, theme, split_rect, floor);
+
+            let dir = match floor > self.current_floor() {
+                true => "above",
+                false => "below",
+            };
+            let label = format!("floor:{} {}", floor + 1, dir);
+            frame.draw(split_vp_rect.start - Dims(0, 1), label, theme["text"]);
+        }
 
-        frame.draw(vp_pos, &viewport, ());
+        #[cfg(feature = "first_person")]
+        if let Some(area) = self.first_person_rect.filter(|_| !self.accessible) {
+            let border = area.margin(Dims(1, 1));
+            let view = super::first_person::render(
+                &self.game.game,
+                self.game.facing,
+                border.margin(Dims(1, 1)).size(),
+                theme,
+            );
+            frame.draw(border.start + Dims(1, 1), &view, ());
+            border.render(frame, theme["game.fp.border"]);
+            frame.draw(border.start - Dims(0, 1), "First person", theme["text"]);
+        }
 
         // touch controls
         if let Some(ref touch_controls) = self.touch_controls {
@@ -926,10 +2830,67 @@ impl Screen for GameActivity {
             self.viewport_rect.render(frame, theme["debug.border"]);
         }
 
+        #[cfg(feature = "lua")]
+        self.scripts.draw(frame, theme["text"]);
+
+        if let Some(ref floor_jump) = self.game.floor_jump {
+            floor_jump.draw(frame, theme);
+        }
+
         Ok(())
     }
 }
 
+/// Position between `prev` and `cur`, floor is always the current one
+fn interpolate(prev: Dims3D, cur: Dims3D, alpha: f32) -> Dims3D {
+    let mut pos = lerp!((prev) -> (cur) at alpha);
+    pos.2 = cur.2;
+    pos
+}
+
+/// Positions of the player after every move, starting with the start position
+fn recorded_run(game: &RunningGame) -> Vec<RunStep> {
+    let start = (0, game.get_start_pos());
+    let moves = game.get_moves().iter().zip(game.get_move_times());
+    [start]
+        .into_iter()
+        .chain(moves.map(|((from, dir), time)| (time.as_millis() as u64, *from + dir.to_coord())))
+        .collect()
+}
+
+/// Path of the versus mode opponent from the start position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct OpponentPathKey(MazeKey, Dims3D);
+
+impl CacheKey for OpponentPathKey {
+    type Value = Vec<Dims3D>;
+}
+
+/// Path of the versus mode opponent, through all the goals if all have to be visited
+fn opponent_path(game: &RunningGame) -> Vec<Dims3D> {
+    let maze = game.get_maze();
+    let start = game.get_player_pos();
+
+    match game.get_game_mode().objective {
+        GoalObjective::ReachAny => game
+            .get_goals()
+            .iter()
+            .filter_map(|goal| maze.solve(start, *goal))
+            .min_by_key(|path| path.len())
+            .unwrap_or_default(),
+        GoalObjective::VisitAll => {
+            let mut path = vec![start];
+            for goal in game.get_goals() {
+                let from = *path.last().unwrap();
+                if let Some(part) = maze.solve(from, *goal) {
+                    path.extend(part.into_iter().skip(1));
+                }
+            }
+            path
+        }
+    }
+}
+
 #[inline]
 fn render_edge_follow_rulers(rulers: (Offset, Offset), frame: &mut Frame, vp: Rect, theme: &Theme) {
     let [s_start, s_end] = theme.extract(["debug.rulers.start", "debug.rulers.end"]);
@@ -969,66 +2930,186 @@ fn render_edge_follow_rulers(rulers: (Offset, Offset), frame: &mut Frame, vp: Re
 
 pub struct MazeBoard {
     frames: Vec<Frame>,
+    /// Floors with lower level of detail, index `i` is downsampled by `2^(i + 1)`
+    lod_frames: Vec<Option<Vec<Frame>>>,
+    /// Floors in full detail with other than the normal zoom, see [`MazeBoard::ensure_zoom`]
+    zoom_frames: HashMap<Zoom, Vec<Frame>>,
 }
 
 impl MazeBoard {
     pub fn new(game: &RunningGame, theme: &Theme) -> Self {
-        let maze = game.get_maze();
+        let frames = Self::render_floors(game.get_maze(), game, 1, Zoom::Normal, theme);
+
+        Self {
+            frames,
+            lod_frames: vec![],
+            zoom_frames: HashMap::new(),
+        }
+    }
+
+    /// Rendered floors in full detail with given zoom, see [`MazeBoard::ensure_zoom`]
+    pub fn zoomed(&self, zoom: Zoom) -> &[Frame] {
+        match zoom {
+            Zoom::Normal => &self.frames,
+            // not rendered yet, full detail is better than nothing
+            zoom => self.zoom_frames.get(&zoom).unwrap_or(&self.frames),
+        }
+    }
+
+    /// Floors in given level of detail, zoom is used only in full detail
+    pub fn view(&self, lod: u32, zoom: Zoom) -> &[Frame] {
+        match lod {
+            0 => self.zoomed(zoom),
+            lod => self.floors(lod),
+        }
+    }
 
+    /// Renders and caches floors in full detail with given zoom, if not already
+    pub fn ensure_zoom(&mut self, game: &RunningGame, zoom: Zoom, theme: &Theme) {
+        if zoom == Zoom::Normal || self.zoom_frames.contains_key(&zoom) {
+            return;
+        }
+
+        log::debug!("Rendering maze with zoom {:?}", zoom);
+        let frames = Self::render_floors(game.get_maze(), game, 1, zoom, theme);
+        self.zoom_frames.insert(zoom, frames);
+    }
+
+    /// Rendered floors for given level of detail, see [`MazeBoard::ensure_lod`]
+    ///
+    /// Falls back to the nearest rendered level, the finer one on a tie, full detail is
+    /// always rendered.
+    pub fn floors(&self, lod: u32) -> &[Frame] {
+        let rendered = |level: u32| match level {
+            0 => Some(&self.frames),
+            level => self.lod_frames.get(level as usize - 1)?.as_ref(),
+        };
+
+        (0..=self.lod_frames.len() as u32)
+            .filter_map(|level| Some((level, rendered(level)?)))
+            .min_by_key(|(level, _)| level.abs_diff(lod))
+            .map_or(&self.frames, |(_, frames)| frames)
+    }
+
+    /// Renders and caches floors for given level of detail, if not already
+    pub fn ensure_lod(&mut self, game: &RunningGame, lod: u32, theme: &Theme) {
+        if lod == 0 {
+            return;
+        }
+
+        let index = lod as usize - 1;
+        if self.lod_frames.len() <= index {
+            self.lod_frames.resize_with(index + 1, || None);
+        }
+
+        if self.lod_frames[index].is_none() {
+            let factor = 1 << lod;
+            let maze = game.get_maze().downsample(factor);
+            log::debug!("Rendering maze at detail 1:{}", factor);
+            let frames = Self::render_floors(&maze, game, factor as i32, Zoom::Normal, theme);
+            self.lod_frames[index] = Some(frames);
+        }
+    }
+
+    fn render_floors(
+        maze: &Maze,
+        game: &RunningGame,
+        factor: i32,
+        zoom: Zoom,
+        theme: &Theme,
+    ) -> Vec<Frame> {
         let mut frames: Vec<_> = (0..maze.size().2)
-            .map(|floor| Self::render_floor(game, floor, theme))
+            .map(|floor| Self::render_floor(maze, floor, zoom, theme))
             .collect();
 
-        Self::render_special(&mut frames, game, theme);
+        // too noisy when the walls are downsampled, textures are made for the normal zoom
+        if factor == 1 && zoom == Zoom::Normal {
+            for (floor, frame) in frames.iter_mut().enumerate() {
+                texture::apply(frame, maze, floor as i32, game.get_seed(), theme);
+            }
+        }
+
+        Self::render_special(&mut frames, game, factor, zoom, theme);
 
-        Self { frames }
+        frames
     }
 
-    fn render_floor(game: &RunningGame, floor: i32, theme: &Theme) -> Frame {
-        let maze = game.get_maze();
+    pub(crate) fn render_floor(maze: &Maze, floor: i32, zoom: Zoom, theme: &Theme) -> Frame {
+        // stairs and items don't fit between the dots
+        if zoom.is_dense() {
+            return dense::render_floor(maze, floor, zoom, theme);
+        }
+
         let normals = theme["game.walls"];
 
-        let size = maze_render_size(maze);
+        let size = maze_render_size(maze, zoom);
 
         let mut frame = Frame::new(size);
         frame.fill(renderer::Cell::styled(' ', theme["game.background"]));
 
-        let mut draw = |pos, l: LineDir| frame.draw(Dims::from(pos), l.double(), normals);
-
-        for y in -1..maze.size().1 {
-            for x in -1..maze.size().0 {
-                let cell_pos = Dims3D(x, y, floor);
-                let Dims(rx, ry) = maze2screen(cell_pos);
-
-                if maze.get_wall(cell_pos, CellWall::Right).unwrap() {
-                    draw((rx + 1, ry), LineDir::Vertical);
+        match zoom {
+            // passages instead of walls, there's no room for them
+            Zoom::Compact => {
+                for y in 0..maze.size().1 {
+                    for x in 0..maze.size().0 {
+                        let cell_pos = Dims3D(x, y, floor);
+                        let open = |wall| !maze.get_wall(cell_pos, wall).unwrap();
+                        let dir = LineDir::from_bools(
+                            open(CellWall::Left),
+                            open(CellWall::Top),
+                            open(CellWall::Right),
+                            open(CellWall::Bottom),
+                        );
+                        frame.draw(Dims(x, y), dir.round(), normals);
+                    }
                 }
+            }
+            Zoom::Braille | Zoom::HalfBlock => unreachable!("dense zooms are rendered above"),
+            Zoom::Normal | Zoom::Large => {
+                let half = zoom.cell_width() / 2;
+                let mut draw = |pos, l: LineDir, dx| {
+                    frame.draw(
+                        zoom.board_pos(Dims::from(pos)) + Dims(dx, 0),
+                        l.double(),
+                        normals,
+                    )
+                };
 
-                if maze.get_wall(cell_pos, CellWall::Bottom).unwrap() {
-                    draw((rx, ry + 1), LineDir::Horizontal);
-                }
+                for y in -1..maze.size().1 {
+                    for x in -1..maze.size().0 {
+                        let cell_pos = Dims3D(x, y, floor);
+                        let Dims(rx, ry) = maze2screen(cell_pos);
 
-                let cp1 = cell_pos;
-                let cp2 = cell_pos + Dims3D(1, 1, 0);
+                        if maze.get_wall(cell_pos, CellWall::Right).unwrap() {
+                            draw((rx + 1, ry), LineDir::Vertical, 0);
+                        }
 
-                let dir = LineDir::from_bools(
-                    maze.get_wall(cp1, CellWall::Bottom).unwrap(),
-                    maze.get_wall(cp1, CellWall::Right).unwrap(),
-                    maze.get_wall(cp2, CellWall::Top).unwrap(),
-                    maze.get_wall(cp2, CellWall::Left).unwrap(),
-                );
+                        if maze.get_wall(cell_pos, CellWall::Bottom).unwrap() {
+                            for dx in -half..=half {
+                                draw((rx, ry + 1), LineDir::Horizontal, dx);
+                            }
+                        }
 
-                draw((rx + 1, ry + 1), dir);
+                        draw((rx + 1, ry + 1), texture::junction_dir(maze, cell_pos), 0);
+                    }
+                }
             }
         }
 
         let cells = &maze.get_cells()[floor as usize];
-        Self::render_stairs(&mut frame, cells, maze.is_tower(), theme);
+        Self::render_stairs(&mut frame, cells, maze.is_tower(), zoom, theme);
+        Self::render_items(&mut frame, cells, zoom, theme);
 
         frame
     }
 
-    fn render_stairs(frame: &mut Frame, floors: &[Vec<Cell>], tower: bool, theme: &Theme) {
+    fn render_stairs(
+        frame: &mut Frame,
+        floors: &[Vec<Cell>],
+        tower: bool,
+        zoom: Zoom,
+        theme: &Theme,
+    ) {
         let s_stairs_up = theme["game.stairs.up"];
         let s_stairs_down = theme["game.stairs.down"];
         let s_stairs_both = theme["game.stairs.both"];
@@ -1046,16 +3127,40 @@ impl MazeBoard {
 
                 let style = if tower && up { s_stairs_up_tower } else { st };
                 let pos = maze2screen(Dims(x as i32, y as i32));
-                frame.draw(pos, ch, style);
+                frame.draw(zoom.board_pos(pos), ch, style);
             }
         }
     }
 
-    fn render_special(frames: &mut [Frame], game: &RunningGame, theme: &Theme) {
+    fn render_items(frame: &mut Frame, floors: &[Vec<Cell>], zoom: Zoom, theme: &Theme) {
+        let [coin, key, door] = theme.extract(["game.coin", "game.key", "game.door"]);
+
+        for cell in floors.iter().flatten() {
+            let (ch, style) = match cell.get_content() {
+                Some(CellContent::Coin) => ('¢', coin),
+                Some(CellContent::Key) => ('⚷', key),
+                Some(CellContent::Door) => ('▓', door),
+                None => continue,
+            };
+
+            let Dims3D(x, y, _) = cell.get_coord();
+            frame.draw(zoom.board_pos(maze2screen(Dims(x, y))), ch, style);
+        }
+    }
+
+    fn render_special(
+        frames: &mut [Frame],
+        game: &RunningGame,
+        factor: i32,
+        zoom: Zoom,
+        theme: &Theme,
+    ) {
         let goal_style = theme["game.goal"];
-        let goal_pos = game.get_goal_pos();
 
-        frames[goal_pos.2 as usize].draw(maze2screen(goal_pos), '$', goal_style);
+        for goal in game.get_goals() {
+            let pos = Dims3D(goal.0 / factor, goal.1 / factor, goal.2);
+            frames[goal.2 as usize].draw(zoom.board_pos(maze2screen(pos)), '$', goal_style);
+        }
     }
 }
 
@@ -1064,24 +3169,232 @@ pub fn game_theme_resolver() -> ThemeResolver {
 
     resolver
         .link("game.walls", "border")
+        .desc("Walls of the maze")
+        .link("game.walls.moss", "game.walls")
+        .desc("Moss on the walls, with textures on")
+        .link("game.walls.crack", "game.walls")
+        .desc("Cracks in the walls, with textures on")
+        .link("game.walls.dense", "game.walls")
+        .desc("Walls drawn with braille dots or half blocks, in the densest zooms")
         // stairs
         .link("game.stairs", "game.walls")
+        .desc("Stairs between floors")
         .link("game.stairs.up", "game.stairs")
+        .desc("Stairs to the floor above")
         .link("game.stairs.down", "game.stairs")
+        .desc("Stairs to the floor below")
         .link("game.stairs.both", "game.stairs")
+        .desc("Stairs to both floors")
         .link("game.stairs.up.tower", "game.goal")
+        .desc("Stairs up in towers, which lead to the goal")
         // game
         .link("game.goal", "")
+        .desc("Goal of the maze")
+        .link("game.goal.reached", "game.visited")
+        .desc("Goals which were already reached")
+        .link("game.coin", "game.goal")
+        .desc("Coins to collect")
+        .link("game.key", "highlight")
+        .desc("Keys which open doors")
+        .link("game.door", "game.walls")
+        .desc("Doors opened by keys")
         .link("game.player", "highlight")
+        .desc("The player")
+        .link("game.opponent", "game.player")
+        .desc("Opponent of a race")
+        .link("game.ghost", "dim")
+        .desc("Ghost of the best run")
+        .link("game.shift", "highlight")
+        .desc("Walls which are about to shift")
+        .link("game.hint", "highlight")
+        .desc("Hints of the way to the goal")
         .link("game.player.on.stairs", "game.stairs")
+        .desc("The player standing on stairs")
         .link("game.visited", "dim")
+        .desc("Cells the player visited")
+        .link("game.path", "highlight")
+        .desc("Path the player walked")
+        .link("game.path.new", "game.path")
+        .desc("Newest part of the walked path")
+        .link("game.path.recent", "game.path")
+        .desc("Recent part of the walked path")
+        .link("game.path.old", "game.visited")
+        .desc("Old part of the walked path")
+        .link("game.path.oldest", "game.path.old")
+        .desc("Oldest part of the walked path")
         .link("game.background", "background")
+        .desc("Background of the maze")
+        .link("game.paused", "dim")
+        .desc("Dims the maze behind the pause menu")
+        .link("game.flash", "highlight")
+        .desc("Flash of the maze when bumping into a wall")
+        .link("game.timer.warning", "highlight")
+        .desc("Timer when the time is running out")
         // special
         .link("game.viewport.border", "border")
+        .desc("Border around the maze")
         .link("debug.border", "border")
+        .desc("Border of debug info")
+        .link("debug.text", "text")
+        .desc("Text of debug info")
         .link("debug.rulers", "debug.border")
+        .desc("Rulers of the camera in debug mode")
         .link("debug.rulers.start", "debug.rulers")
-        .link("debug.rulers.end", "debug.rulers");
+        .desc("Start of the camera rulers in debug mode")
+        .link("debug.rulers.end", "debug.rulers")
+        .desc("End of the camera rulers in debug mode");
 
     resolver
 }
+
+#[cfg(test)]
+mod tests {
+    use cmaze::game::GoalPlacement;
+
+    use crossterm::event::KeyModifiers;
+
+    use crate::{
+        app::{app, App},
+        settings::{theme::ThemeDefinition, MazeGenAlgo},
+        ui::Popup,
+    };
+
+    use super::*;
+
+    fn game(goals: GoalPlacement, objective: GoalObjective) -> RunningGame {
+        let game_mode = GameMode {
+            size: Dims3D(7, 5, 2),
+            is_tower: false,
+            goals,
+            objective,
+            braid: 0,
+            braid_floors: 0,
+            items: Default::default(),
+        };
+        let props = GameProperities {
+            game_mode,
+            generator: MazeGenAlgo::RandomKruskals.to_fn(),
+            seed: Some(7),
+        };
+        RunningGame::new_threaded(props)
+            .unwrap()
+            .handle
+            .join()
+            .unwrap()
+            .unwrap()
+    }
+
+    fn assert_walkable(game: &RunningGame, path: &[Dims3D]) {
+        let maze = game.get_maze();
+        assert_eq!(path.first(), Some(&game.get_player_pos()));
+        for step in path.windows(2) {
+            let wall =
+                Maze::which_wall_between(step[0], step[1]).expect("steps should be adjacent");
+            assert_eq!(maze.get_wall(step[0], wall), Some(false), "{:?}", step);
+        }
+    }
+
+    #[test]
+    fn board_falls_back_to_rendered_detail() {
+        let theme = app::init_theme_resolver().resolve(&ThemeDefinition::builtin());
+        let game = game(GoalPlacement::Corner, GoalObjective::ReachAny);
+        let mut board = MazeBoard::new(&game, &theme);
+
+        let full = board.floors(0).as_ptr();
+        assert_eq!(board.floors(2).as_ptr(), full);
+        assert_eq!(board.zoomed(Zoom::Braille).as_ptr(), full);
+
+        board.ensure_lod(&game, 2, &theme);
+        let coarse = board.floors(2).as_ptr();
+        assert_ne!(coarse, full);
+        // level 1 is as near to full detail as to level 2, the finer one wins
+        assert_eq!(board.floors(1).as_ptr(), full);
+        assert_eq!(board.floors(5).as_ptr(), coarse);
+    }
+
+    #[test]
+    fn opponent_reaches_goal() {
+        let game = game(GoalPlacement::Exits, GoalObjective::ReachAny);
+        let path = opponent_path(&game);
+
+        assert_walkable(&game, &path);
+        assert!(game.get_goals().contains(path.last().unwrap()));
+
+        let shortest = game
+            .get_goals()
+            .iter()
+            .filter_map(|goal| game.get_maze().solve(game.get_player_pos(), *goal))
+            .map(|path| path.len())
+            .min();
+        assert_eq!(Some(path.len()), shortest);
+    }
+
+    #[test]
+    fn opponent_visits_all_goals() {
+        let game = game(GoalPlacement::Corners, GoalObjective::VisitAll);
+        let path = opponent_path(&game);
+
+        assert_walkable(&game, &path);
+        assert!(game.get_goals().len() > 1);
+        for goal in game.get_goals() {
+            assert!(path.contains(goal), "{:?} not visited", goal);
+        }
+    }
+
+    #[test]
+    fn click_walks_to_cell() {
+        let screen = Dims(60, 30);
+        let popup = Popup::new("Base".to_string(), vec![]);
+        let mut app = App::new_headless_default(Activity::new_base_boxed("base", popup), screen);
+        let data = app.data_mut();
+        data.settings.set_enable_mouse(true);
+
+        let click = |pos: Dims| {
+            Event::Term(TermEvent::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column: pos.0 as u16,
+                row: pos.1 as u16,
+                modifiers: KeyModifiers::NONE,
+            }))
+        };
+
+        for zoom in [Zoom::Normal, Zoom::Large] {
+            let game = game(GoalPlacement::Corner, GoalObjective::ReachAny);
+            let start = game.get_player_pos();
+            // a few cells away, but not past the goal, which would end the game
+            let (target, _) = (0..7)
+                .flat_map(|x| (0..5).map(move |y| Dims3D(x, y, 0)))
+                .filter_map(|cell| Some((cell, game.get_maze().solve(start, cell)?)))
+                .filter(|(_, path)| path.iter().all(|pos| !game.get_goals().contains(pos)))
+                .max_by_key(|(_, path)| path.len())
+                .unwrap();
+
+            let game = GameData::new(game, GameRules::default(), 0, &data.settings);
+            let mut activity = GameActivity::new(game, data);
+            activity.game.zoom = zoom;
+            assert!(activity.update(vec![], data).is_none());
+
+            // the maze fits the screen, it's in the middle of it
+            let maze_pos = (screen - activity.current_floor_frame().size) / 2;
+            let cell_pos = |cell| maze_pos + zoom.board_pos(maze2screen(cell));
+
+            let wall = cell_pos(start) + Dims(zoom.cell_width() / 2 + 1, 0);
+            for pos in [wall, Dims(0, 0)] {
+                assert!(activity.update(vec![click(pos)], data).is_none());
+                assert!(activity.walk.is_empty(), "{:?} {:?}", zoom, pos);
+            }
+            assert_eq!(activity.game.game.get_player_pos(), start);
+
+            assert!(activity
+                .update(vec![click(cell_pos(target))], data)
+                .is_none());
+            for _ in 0..1000 {
+                if activity.walk.is_empty() {
+                    break;
+                }
+                activity.tick(data);
+            }
+            assert_eq!(activity.game.game.get_player_pos(), target, "{:?}", zoom);
+        }
+    }
+}
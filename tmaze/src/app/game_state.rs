@@ -1,5 +1,6 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
+use chrono::NaiveDate;
 use cmaze::{
     dims::*,
     game::{MoveMode, RunningGame},
@@ -7,15 +8,29 @@ use cmaze::{
 };
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
+use super::{
+    annotations::{Annotation, Annotations, Layer, Lifetime, Mark},
+    event_log::{RunEvent, RunLog},
+    floor_jump::{FloorJump, FloorJumpAction},
+    floor_overview::FloorOverview,
+    input::InputFilter,
+    stamina::{Stamina, StaminaMeter},
+};
 use crate::{
-    helpers::{is_release, maze2screen_3d},
-    settings::Settings,
+    helpers::{constants, is_release, maze2screen_3d, Zoom},
+    settings::{
+        keymap::{Action, KeyMap},
+        MacroStep, Settings,
+    },
+    ui::Hint,
 };
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum GameViewMode {
     Adventure,
     Spectator,
+    /// Thumbnails of all floors, see [`GameData::overview`]
+    Overview,
 }
 
 impl GameViewMode {
@@ -23,6 +38,7 @@ impl GameViewMode {
         match self {
             GameViewMode::Adventure => ["Adventure", "Adv", "A"],
             GameViewMode::Spectator => ["Spectator", "Spec", "S"],
+            GameViewMode::Overview => ["Overview", "Over", "O"],
         }
     }
 }
@@ -32,69 +48,387 @@ impl Display for GameViewMode {
         match self {
             GameViewMode::Adventure => write!(f, "Adventure"),
             GameViewMode::Spectator => write!(f, "Spectator"),
+            GameViewMode::Overview => write!(f, "Overview"),
         }
     }
 }
 
+/// Maximum number of moves in a single macro
+pub const MAX_MACRO_LEN: usize = 64;
+
+/// Number of cells of the path shown by a hint
+pub const HINT_CELLS: usize = 8;
+/// How long a hint is shown
+pub const HINT_DURATION: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Default)]
+pub enum MacroState {
+    #[default]
+    Idle,
+    /// Waiting for the slot number to start recording
+    SelectingSlot,
+    Recording {
+        slot: u8,
+        steps: Vec<MacroStep>,
+    },
+}
+
+/// Rules of the run, set by the game mode, see [`super::modes`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GameRules {
+    /// Run is lost when the time runs out, measured by the game clock, so pauses don't count
+    /// and penalties do
+    pub time_limit: Option<Duration>,
+    /// Timer is hidden and the result is not saved
+    pub relaxed: bool,
+    /// Next maze is generated right after the goal is reached
+    pub endless: bool,
+    /// Opponent races to the goal, moving by one cell in this time
+    pub opponent_step: Option<Duration>,
+    /// Run is the daily challenge of this UTC date, see [`super::daily`]
+    pub daily: Option<NaiveDate>,
+    /// Run is this stage of the campaign, see [`super::campaign`]
+    pub stage: Option<usize>,
+    /// Maze was loaded from a file, so the result and replay are not saved,
+    /// see [`crate::data::maze_spec`]
+    pub custom: bool,
+    /// Walls move while the game runs, so the result and replay are not saved,
+    /// see [`super::shifting`]
+    pub shifting: Option<Shifting>,
+    /// Two players race on the same maze in split screen, see [`super::race`]
+    pub race: bool,
+    /// Race against a player on another computer, so the result and replay are not saved,
+    /// see [`super::online`]
+    pub online: bool,
+    /// Moves can't be taken back, set by the preset
+    pub hardcore: bool,
+    /// Index of the maze preset of the game, its settings are used while it's played,
+    /// see [`crate::settings::MazePreset::settings`]
+    pub preset: Option<usize>,
+    /// Moves cost stamina, which comes back while the player stands still, see [`super::stamina`]
+    pub stamina: Option<Stamina>,
+}
+
+/// Rules of the shifting maze
+#[derive(Debug, Clone, Copy)]
+pub struct Shifting {
+    /// Game time between the shifts
+    pub interval: Duration,
+    /// How many walls are opened, and closed, by every shift
+    pub walls: usize,
+}
+
 pub struct GameData {
     pub game: RunningGame,
     pub camera_pos: Dims3D,
     pub view_mode: GameViewMode,
     pub player_char: char,
+    /// Level of detail in spectator mode, maze is downsampled by `2^lod`
+    pub lod: u32,
+    /// Scale of the maze, used only in full detail
+    pub zoom: Zoom,
+    pub macro_state: MacroState,
+    /// Run used some kind of assistance (e.g. macros), so it's not eligible for best results
+    pub assisted: bool,
+    pub rules: GameRules,
+    /// Number of mazes finished before this one in endless mode
+    pub cleared: usize,
+    pub input: InputFilter,
+    /// Floor selection overlay, open in spectator mode
+    pub floor_jump: Option<FloorJump>,
+    /// Overview of the floors, open in [`GameViewMode::Overview`]
+    pub overview: Option<FloorOverview>,
+    /// Show the neighbouring floor next to the current one
+    pub split_view: bool,
+    pub log: RunLog,
+    /// Annotations of the maze kept between frames, like the hint
+    pub annotations: Annotations,
+    /// Steps made by every move of the player, so [`GameData::undo`] takes back the whole move
+    move_steps: Vec<usize>,
+    /// Number of moves taken back
+    pub undos: usize,
+    /// Where the player faces, movement keys are relative to it with relative controls
+    pub facing: CellWall,
+    /// Stamina of the player, if the rules have it
+    pub stamina: Option<StaminaMeter>,
+    /// Copy of the setting, so the hints match it
+    relative_controls: bool,
+    keymap: KeyMap,
 }
 
 impl GameData {
-    pub fn handle_event(&mut self, settings: &Settings, event: KeyEvent) -> Result<(), bool> {
+    pub fn new(game: RunningGame, rules: GameRules, cleared: usize, settings: &Settings) -> Self {
+        let facing = initial_facing(&game);
+        Self {
+            camera_pos: maze2screen_3d(game.get_player_pos()),
+            log: RunLog::new(&game),
+            game,
+            view_mode: GameViewMode::Adventure,
+            player_char: constants::get_random_player_char(),
+            lod: 0,
+            zoom: settings.get_maze_zoom(),
+            macro_state: MacroState::Idle,
+            assisted: false,
+            rules,
+            cleared,
+            input: InputFilter::from_settings(settings),
+            floor_jump: None,
+            overview: None,
+            split_view: false,
+            annotations: Annotations::default(),
+            move_steps: vec![],
+            undos: 0,
+            facing,
+            stamina: rules.stamina.map(StaminaMeter::new),
+            relative_controls: settings.get_relative_controls(),
+            keymap: settings.get_keymap(),
+        }
+    }
+
+    /// Rules which change the run, stored with its result, see [`crate::data::environment`]
+    pub fn modifiers(&self) -> Vec<String> {
+        let rules = &self.rules;
+        let flags = [
+            (self.assisted, "assisted"),
+            (rules.relaxed, "relaxed"),
+            (rules.endless, "endless"),
+            (rules.opponent_step.is_some(), "opponent"),
+            (rules.custom, "custom maze"),
+            (rules.shifting.is_some(), "shifting"),
+            (rules.race, "race"),
+            (rules.online, "online"),
+            (rules.hardcore, "hardcore"),
+            (rules.stamina.is_some(), "stamina"),
+        ];
+
+        let mut list: Vec<_> = flags
+            .into_iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, name)| name.to_string())
+            .collect();
+        if let Some(limit) = rules.time_limit {
+            list.push(format!("time limit {} s", limit.as_secs()));
+        }
+
+        list
+    }
+
+    /// Whether the movement keys are relative to [`Self::facing`], see [`Settings::get_relative_controls`]
+    pub fn relative_controls(&self) -> bool {
+        self.relative_controls
+    }
+
+    /// Factor by which the maze is downsampled for current level of detail
+    pub fn lod_factor(&self) -> i32 {
+        1 << self.lod
+    }
+
+    /// Lowest level of detail, where the whole floor is few cells wide
+    pub fn max_lod(&self) -> u32 {
+        let size = self.game.get_maze().size();
+        let max_side = size.0.max(size.1).max(1) as u32;
+        max_side.ilog2().saturating_sub(1)
+    }
+
+    /// Keys which can be used right now, see [`crate::ui::footer`]
+    pub fn hints(&self) -> Vec<Hint> {
+        if self.overview.is_some() {
+            return vec![
+                Hint::new("Arrows", "select floor"),
+                Hint::new("Enter", "jump"),
+                Hint::new("Tab", "close"),
+            ];
+        }
+
+        if self.floor_jump.is_some() {
+            return vec![
+                Hint::new("↑↓", "select floor"),
+                Hint::new("Enter", "jump"),
+                Hint::new("Esc", "close"),
+            ];
+        }
+
+        match self.macro_state {
+            MacroState::SelectingSlot => {
+                return vec![
+                    Hint::new("1-9", "macro slot"),
+                    Hint::new("Any key", "cancel"),
+                ];
+            }
+            MacroState::Recording { .. } => return vec![Hint::new("M", "stop recording")],
+            _ => {}
+        }
+
+        let floors = self.game.get_maze().size().2 > 1;
+        let keys = |actions: &[Action]| self.keymap.hint(actions);
+        let moves = keys(&[
+            Action::MoveUp,
+            Action::MoveLeft,
+            Action::MoveDown,
+            Action::MoveRight,
+        ]);
+        let hints = match self.view_mode {
+            GameViewMode::Adventure if self.relative_controls => [
+                Some(Hint::owned(keys(&[Action::MoveUp]), "forward")),
+                Some(Hint::owned(
+                    keys(&[Action::MoveLeft, Action::MoveRight]),
+                    "turn",
+                )),
+                floors.then_some(Hint::owned(
+                    keys(&[Action::FloorDown, Action::FloorUp]),
+                    "floor down/up",
+                )),
+                Some(Hint::owned(keys(&[Action::Pause]), "pause")),
+                Some(Hint::owned(keys(&[Action::MoveDown]), "turn around")),
+                (!self.rules.hardcore).then_some(Hint::owned(keys(&[Action::Undo]), "undo")),
+            ],
+            GameViewMode::Adventure => [
+                Some(Hint::owned(moves.clone(), "move")),
+                floors.then_some(Hint::owned(
+                    keys(&[Action::FloorDown, Action::FloorUp]),
+                    "floor down/up",
+                )),
+                Some(Hint::owned(keys(&[Action::Pause]), "pause")),
+                Some(Hint::owned(keys(&[Action::SwitchMode]), "spectator")),
+                Some(Hint::owned(keys(&[Action::Hint]), "hint")),
+                (!self.rules.hardcore).then_some(Hint::owned(keys(&[Action::Undo]), "undo")),
+            ],
+            GameViewMode::Spectator | GameViewMode::Overview => [
+                Some(Hint::owned(moves, "look around")),
+                Some(Hint::new("-/+", "zoom")),
+                floors.then_some(Hint::new("G/Tab", "jump to floor")),
+                Some(Hint::owned(keys(&[Action::SwitchMode]), "back to player")),
+                Some(Hint::owned(keys(&[Action::Pause]), "pause")),
+                None,
+            ],
+        };
+        hints.into_iter().flatten().collect()
+    }
+
+    pub fn handle_event(&mut self, settings: &mut Settings, event: KeyEvent) -> Result<(), bool> {
+        if is_release(event.kind) {
+            return Ok(());
+        }
+
+        self.input.update(settings);
+        self.relative_controls = settings.get_relative_controls();
+        self.keymap = settings.get_keymap();
+        let Some(event) = self.input.filter(event) else {
+            return Ok(());
+        };
         let KeyEvent {
-            code,
-            modifiers,
-            kind,
-            ..
+            code, modifiers, ..
         } = event;
-        if is_release(kind) {
+
+        let is_fast = modifiers.contains(KeyModifiers::SHIFT);
+
+        if let Some(floor_jump) = &mut self.floor_jump {
+            let action = floor_jump.handle_key(code);
+            self.floor_jump_action(action);
             return Ok(());
         }
 
-        let is_fast = modifiers.contains(KeyModifiers::SHIFT);
+        if let Some(overview) = &mut self.overview {
+            let action = overview.handle_key(code);
+            self.overview_action(action);
+            return Ok(());
+        }
 
-        match code {
-            KeyCode::Up | KeyCode::Char('w' | 'W') => {
-                self.apply_move(settings, CellWall::Top, is_fast);
-            }
-            KeyCode::Down | KeyCode::Char('s' | 'S') => {
-                self.apply_move(settings, CellWall::Bottom, is_fast);
-            }
-            KeyCode::Left | KeyCode::Char('a' | 'A') => {
-                self.apply_move(settings, CellWall::Left, is_fast);
-            }
-            KeyCode::Right | KeyCode::Char('d' | 'D') => {
-                self.apply_move(settings, CellWall::Right, is_fast);
-            }
-            KeyCode::Char('Q') => return Err(true),
-            KeyCode::Char('f' | 'q' | 'l') => {
-                self.apply_move(settings, CellWall::Down, is_fast);
+        if let MacroState::SelectingSlot = self.macro_state {
+            self.macro_state = match code {
+                KeyCode::Char(ch @ '1'..='9') => {
+                    let slot = ch as u8 - b'0';
+                    log::info!("Recording macro {}, press M to stop", slot);
+                    MacroState::Recording {
+                        slot,
+                        steps: vec![],
+                    }
+                }
+                _ => {
+                    log::info!("Macro recording cancelled");
+                    MacroState::Idle
+                }
+            };
+            return Ok(());
+        }
+
+        if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+            return Err(true);
+        }
+
+        if code == KeyCode::Char('Q') {
+            return Err(true);
+        }
+
+        let adventure = self.view_mode == GameViewMode::Adventure;
+        match self.keymap.action(&event) {
+            Some(Action::Hint | Action::Undo) if !adventure => {
+                self.handle_fixed_key(settings, code)
             }
-            KeyCode::Char('r' | 'e' | 'p') => {
-                self.apply_move(settings, CellWall::Up, is_fast);
+            Some(action) => self.handle_action(settings, action, is_fast),
+            None => self.handle_fixed_key(settings, code),
+        }
+    }
+
+    /// Does the action of a key or a dpad button, errors are the same as of [`Self::handle_event`]
+    pub fn handle_action(
+        &mut self,
+        settings: &mut Settings,
+        action: Action,
+        is_fast: bool,
+    ) -> Result<(), bool> {
+        let adventure = self.view_mode == GameViewMode::Adventure;
+
+        if self.relative_controls && adventure {
+            let turned = match action {
+                Action::MoveUp => {
+                    self.player_move(settings, self.facing, is_fast);
+                    return Ok(());
+                }
+                Action::MoveLeft => Some(self.facing.turn_left()),
+                Action::MoveRight => Some(self.facing.turn_right()),
+                Action::MoveDown => Some(self.facing.reverse_wall()),
+                _ => None,
+            };
+            if let Some(facing) = turned {
+                self.facing = facing;
+                return Ok(());
             }
-            KeyCode::Char(' ') => {
-                match self.view_mode {
-                    GameViewMode::Spectator => {
-                        self.camera_pos = maze2screen_3d(self.game.get_player_pos());
-                        self.view_mode = GameViewMode::Adventure;
-                    }
-                    GameViewMode::Adventure => {
-                        self.view_mode = GameViewMode::Spectator;
-                    }
+        }
+
+        match action {
+            Action::Pause => return Err(false),
+            Action::SwitchMode => self.switch_view_mode(),
+            Action::Hint if adventure => self.show_hint(),
+            Action::Undo if adventure => self.undo(settings),
+            _ => {
+                if let Some(dir) = action.direction() {
+                    self.player_move(settings, dir, is_fast);
                 }
-                log::info!("Switched to {}", self.view_mode);
             }
+        }
+
+        Ok(())
+    }
+
+    /// Keys which can't be rebound, see [`KeyMap`]
+    fn handle_fixed_key(&mut self, settings: &mut Settings, code: KeyCode) -> Result<(), bool> {
+        match code {
+            KeyCode::Char('m' | 'M') => self.toggle_macro_recording(settings),
+            KeyCode::Char(ch @ '1'..='9') => self.replay_macro(settings, ch as u8 - b'0'),
             KeyCode::Char('.') => {
                 self.view_mode = GameViewMode::Spectator;
                 self.camera_pos = self.game.get_player_pos() - self.game.get_goal_pos();
                 self.camera_pos.2 *= -1;
                 log::info!("Switched to {} and reseted view pos", self.view_mode);
             }
+            KeyCode::Char('g') if self.view_mode == GameViewMode::Spectator => {
+                self.open_floor_jump();
+            }
+            KeyCode::Char('v' | 'V') => self.toggle_split_view(),
+            KeyCode::Tab => self.open_overview(),
+            KeyCode::Char('-') => self.zoom(true),
+            KeyCode::Char('+' | '=') => self.zoom(false),
             KeyCode::Esc => return Err(false),
             _ => {}
         }
@@ -102,8 +436,247 @@ impl GameData {
         Ok(())
     }
 
+    fn switch_view_mode(&mut self) {
+        match self.view_mode {
+            GameViewMode::Spectator => {
+                self.camera_pos = maze2screen_3d(self.game.get_player_pos());
+                self.view_mode = GameViewMode::Adventure;
+                self.lod = 0;
+            }
+            GameViewMode::Adventure => {
+                self.view_mode = GameViewMode::Spectator;
+            }
+            GameViewMode::Overview => return self.overview_action(FloorJumpAction::Close),
+        }
+        log::info!("Switched to {}", self.view_mode);
+    }
+
+    fn toggle_macro_recording(&mut self, settings: &mut Settings) {
+        match std::mem::take(&mut self.macro_state) {
+            MacroState::Recording { slot, steps } => {
+                log::info!("Saved macro {} with {} moves", slot, steps.len());
+                settings.set_macro(slot, steps);
+            }
+            _ if self.view_mode == GameViewMode::Adventure => {
+                log::info!("Select macro slot 1-9");
+                self.macro_state = MacroState::SelectingSlot;
+            }
+            _ => {}
+        }
+    }
+
+    fn replay_macro(&mut self, settings: &Settings, slot: u8) {
+        if self.view_mode != GameViewMode::Adventure {
+            return;
+        }
+
+        if let MacroState::Recording { .. } = self.macro_state {
+            log::warn!("Cannot replay macro while recording");
+            return;
+        }
+
+        let Some(steps) = settings.get_macro(slot) else {
+            log::warn!("Macro {} is empty", slot);
+            return;
+        };
+
+        if !self.assisted {
+            log::warn!("Macro used, this run will not be saved as best result");
+            self.assisted = true;
+        }
+
+        for step in steps {
+            self.apply_move(settings, step.dir, step.fast);
+        }
+    }
+
+    fn open_floor_jump(&mut self) {
+        if self.game.get_maze().size().2 <= 1 {
+            log::info!("Maze has only one floor");
+            return;
+        }
+
+        self.floor_jump = Some(FloorJump::new(&self.game, self.camera_pos.2));
+    }
+
+    fn open_overview(&mut self) {
+        if self.game.get_maze().size().2 <= 1 {
+            log::info!("Maze has only one floor");
+            return;
+        }
+
+        let floor = match self.view_mode {
+            GameViewMode::Adventure => self.game.get_player_pos().2,
+            _ => self.camera_pos.2,
+        };
+        self.overview = Some(FloorOverview::new(&self.game, floor, self.view_mode));
+        self.view_mode = GameViewMode::Overview;
+    }
+
+    /// Jumps to the floor selected in the overview in spectator mode, or closes it
+    pub fn overview_action(&mut self, action: FloorJumpAction) {
+        match action {
+            FloorJumpAction::None => {}
+            FloorJumpAction::Jump(floor) => {
+                self.overview = None;
+                self.view_mode = GameViewMode::Spectator;
+                self.camera_pos.2 = floor;
+                log::info!("Floor {}", floor + 1);
+            }
+            FloorJumpAction::Close => {
+                if let Some(overview) = self.overview.take() {
+                    self.view_mode = overview.previous;
+                }
+            }
+        }
+    }
+
+    /// Shows the start of the shortest path to the nearest goal, the run is then assisted
+    fn show_hint(&mut self) {
+        let maze = self.game.get_maze();
+        let from = self.game.get_player_pos();
+        let path = self
+            .game
+            .get_goals()
+            .iter()
+            .filter(|goal| !self.game.is_goal_reached(**goal))
+            .filter_map(|goal| maze.solve(from, *goal))
+            .min_by_key(|path| path.len());
+
+        let Some(path) = path else {
+            log::warn!("There is no path to a goal");
+            return;
+        };
+
+        if !self.assisted {
+            log::warn!("Hint used, this run will not be saved as best result");
+            self.assisted = true;
+        }
+
+        let cells = path.into_iter().skip(1).take(HINT_CELLS);
+        self.annotations.replace(
+            "hint",
+            cells.map(|cell| Annotation::cell(cell, Mark::Marker('*'), "game.hint", Layer::Hint)),
+            Lifetime::For(HINT_DURATION),
+        );
+        self.log_event(RunEvent::Hint);
+    }
+
+    /// Takes back the last move of the player, at the cost of the penalty from the settings
+    pub fn undo(&mut self, settings: &Settings) {
+        if self.rules.hardcore {
+            log::warn!("Moves can't be taken back in this maze");
+            return;
+        }
+
+        if let MacroState::Recording { .. } = self.macro_state {
+            log::warn!("Cannot undo while recording a macro");
+            return;
+        }
+
+        let Some(steps) = self.move_steps.pop() else {
+            log::info!("Nothing to undo");
+            return;
+        };
+
+        let from = self.game.get_player_pos();
+        for _ in 0..steps {
+            if !matches!(self.game.undo_move(), Ok(true)) {
+                break;
+            }
+        }
+
+        let time = Duration::from_secs(settings.get_undo_time_penalty());
+        let moves = settings.get_undo_move_penalty() as usize;
+        self.game.add_penalty(time, moves);
+        self.undos += 1;
+
+        let to = self.game.get_player_pos();
+        self.log_event(RunEvent::Undo { from, to });
+        if from.2 != to.2 {
+            self.log_event(RunEvent::Floor {
+                from: from.2,
+                to: to.2,
+            });
+        }
+
+        match (time.is_zero(), moves) {
+            (true, 0) => log::info!("Move taken back"),
+            (false, 0) => log::info!("Move taken back, +{}s", time.as_secs()),
+            (true, _) => log::info!("Move taken back, +{} moves", moves),
+            (false, _) => log::info!("Move taken back, +{}s, +{} moves", time.as_secs(), moves),
+        }
+    }
+
+    fn toggle_split_view(&mut self) {
+        if self.game.get_maze().size().2 <= 1 {
+            log::info!("Maze has only one floor");
+            return;
+        }
+
+        self.split_view = !self.split_view;
+        log::info!("Split view {}", if self.split_view { "on" } else { "off" });
+    }
+
+    /// Applies the result of the floor jump overlay
+    pub fn floor_jump_action(&mut self, action: FloorJumpAction) {
+        match action {
+            FloorJumpAction::None => {}
+            FloorJumpAction::Jump(floor) => {
+                self.camera_pos.2 = floor;
+                self.floor_jump = None;
+                log::info!("Floor {}", floor + 1);
+            }
+            FloorJumpAction::Close => self.floor_jump = None,
+        }
+    }
+
+    /// Changes the zoom, spectator mode zooms out past the braille zoom by lowering the level
+    /// of detail
+    pub fn zoom(&mut self, out: bool) {
+        let spectator = self.view_mode == GameViewMode::Spectator;
+        let zoom = match out {
+            true => self.zoom.zoom_out(),
+            false => self.zoom.zoom_in(),
+        };
+
+        match zoom {
+            Some(zoom) if self.lod == 0 => {
+                self.zoom = zoom;
+                log::info!("Zoom {:?}", zoom);
+                return;
+            }
+            _ if !spectator => return,
+            _ => {}
+        }
+
+        let lod = match out {
+            true => (self.lod + 1).min(self.max_lod()),
+            false => self.lod.saturating_sub(1),
+        };
+
+        if lod != self.lod {
+            self.lod = lod;
+            log::info!("Detail 1:{}", self.lod_factor());
+        }
+    }
+
+    /// Move requested by the player, ignored if it comes too soon after the previous one
+    pub fn player_move(&mut self, settings: &Settings, wall: CellWall, fast: bool) {
+        if self.view_mode == GameViewMode::Adventure && !self.input.accept_move() {
+            return;
+        }
+
+        self.apply_move(settings, wall, fast);
+    }
+
     pub fn apply_move(&mut self, settings: &Settings, wall: CellWall, fast: bool) {
         match self.view_mode {
+            GameViewMode::Overview => {
+                if let Some(overview) = &mut self.overview {
+                    overview.select(wall);
+                }
+            }
             GameViewMode::Spectator => {
                 let mut off = wall.reverse_wall().to_coord();
                 off.0 *= 2;
@@ -112,26 +685,119 @@ impl GameData {
                     off.1 *= 5;
                 }
 
+                // keep panning speed the same on screen
+                let factor = match self.zoom {
+                    Zoom::Compact | Zoom::HalfBlock if self.lod == 0 => 2,
+                    Zoom::Braille if self.lod == 0 => 4,
+                    _ => self.lod_factor(),
+                };
+                off.0 *= factor;
+                off.1 *= factor;
+
                 let mut pos = self.camera_pos - off;
                 pos.2 = pos.2.clamp(0, self.game.get_maze().size().2 - 1);
 
                 self.camera_pos = pos;
             }
             GameViewMode::Adventure => {
-                self.game
-                    .move_player(
-                        wall,
-                        if settings.get_slow() {
-                            MoveMode::Slow
-                        } else if fast {
-                            MoveMode::Fast
-                        } else {
-                            MoveMode::Normal
-                        },
-                        !settings.get_disable_tower_auto_up(),
-                    )
-                    .unwrap();
+                // stamina bar shows why the player doesn't move
+                if self.stamina.as_ref().is_some_and(|meter| !meter.can_move()) {
+                    return;
+                }
+
+                if let MacroState::Recording { steps, .. } = &mut self.macro_state {
+                    if steps.len() < MAX_MACRO_LEN {
+                        steps.push(MacroStep { dir: wall, fast });
+                    } else {
+                        log::warn!("Macro is full, move was not recorded");
+                    }
+                }
+
+                let mode = if settings.get_slow() {
+                    MoveMode::Slow
+                } else if fast {
+                    MoveMode::Fast
+                } else {
+                    MoveMode::Normal
+                };
+                self.move_player(settings, wall, mode, fast);
             }
         }
     }
+
+    /// Moves the player by a single cell, used when walking to a clicked cell
+    ///
+    /// Steps are not recorded in macros, they are replayed as whole moves.
+    pub fn step_player(&mut self, settings: &Settings, wall: CellWall) {
+        if self.view_mode != GameViewMode::Adventure
+            || self.stamina.as_ref().is_some_and(|meter| !meter.can_move())
+        {
+            return;
+        }
+
+        self.move_player(settings, wall, MoveMode::Slow, false);
+    }
+
+    fn move_player(&mut self, settings: &Settings, wall: CellWall, mode: MoveMode, fast: bool) {
+        let from = self.game.get_player_pos();
+        let max_steps = self
+            .stamina
+            .as_ref()
+            .map_or(usize::MAX, StaminaMeter::steps_left);
+        let (_, steps) = self
+            .game
+            .move_player_at_most(wall, mode, !settings.get_disable_tower_auto_up(), max_steps)
+            .unwrap();
+        match steps {
+            0 => self.log_event(RunEvent::Bump {
+                dir: wall,
+                at: from,
+            }),
+            _ => self.move_steps.push(steps),
+        }
+        if steps > 0
+            && self
+                .stamina
+                .as_mut()
+                .is_some_and(|meter| meter.spend(steps))
+        {
+            log::warn!("Out of stamina, stand still to catch your breath");
+            self.log_event(RunEvent::Exhausted);
+        }
+
+        let to = self.game.get_player_pos();
+        // player turns to where it went, also with the absolute controls
+        if from != to && !matches!(wall, CellWall::Up | CellWall::Down) {
+            self.facing = wall;
+        }
+        if from != to {
+            self.log_event(RunEvent::Move {
+                dir: wall,
+                fast,
+                from,
+                to,
+            });
+        }
+        if from.2 != to.2 {
+            self.log_event(RunEvent::Floor {
+                from: from.2,
+                to: to.2,
+            });
+        }
+    }
+
+    /// Adds the event to the run log, at the current game time
+    pub fn log_event(&mut self, event: RunEvent) {
+        let time = self.game.get_elapsed().unwrap_or_default();
+        self.log.push(time, event);
+    }
+}
+
+/// First open direction from the start on its floor, so the player doesn't face a wall
+fn initial_facing(game: &RunningGame) -> CellWall {
+    CellWall::get_in_order()
+        .into_iter()
+        .zip(game.get_available_moves())
+        .find(|(dir, open)| *open && !matches!(dir, CellWall::Up | CellWall::Down))
+        .map_or(CellWall::Right, |(dir, _)| dir)
 }
@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, ModifierKeyCode};
+
+use crate::settings::Settings;
+
+/// Accessibility processing of key events, before they are handled by the game
+#[derive(Debug, Clone)]
+pub struct InputFilter {
+    /// Minimum time between two accepted moves
+    debounce: Duration,
+    /// Modifier pressed alone is applied to the next key
+    sticky: bool,
+    last_move: Option<Instant>,
+    latched: KeyModifiers,
+}
+
+impl InputFilter {
+    pub fn from_settings(settings: &Settings) -> Self {
+        let mut filter = Self {
+            debounce: Duration::ZERO,
+            sticky: false,
+            last_move: None,
+            latched: KeyModifiers::NONE,
+        };
+        filter.update(settings);
+        filter
+    }
+
+    /// Applies changed settings, keeps the state
+    pub fn update(&mut self, settings: &Settings) {
+        self.debounce = Duration::from_millis(settings.get_input_debounce());
+        self.sticky = settings.get_sticky_modifiers();
+
+        if !self.sticky {
+            self.latched = KeyModifiers::NONE;
+        }
+    }
+
+    /// Returns the event which should be handled, `None` if it was consumed
+    ///
+    /// With sticky modifiers, modifier keys pressed alone are latched and
+    /// added to the next key, so chords can be typed one key at a time.
+    /// Terminals report lone modifiers only with keyboard enhancements, see
+    /// [`crate::renderer::Renderer::set_report_modifiers`].
+    pub fn filter(&mut self, mut event: KeyEvent) -> Option<KeyEvent> {
+        if !self.sticky {
+            return Some(event);
+        }
+
+        if let KeyCode::Modifier(key) = event.code {
+            self.latched |= modifier_of(key);
+            return None;
+        }
+
+        event.modifiers |= std::mem::replace(&mut self.latched, KeyModifiers::NONE);
+        Some(event)
+    }
+
+    /// Whether a move requested now should be accepted, based on the debounce interval
+    pub fn accept_move(&mut self) -> bool {
+        self.accept_move_at(Instant::now())
+    }
+
+    fn accept_move_at(&mut self, now: Instant) -> bool {
+        match self.last_move {
+            Some(last) if now.duration_since(last) < self.debounce => false,
+            _ => {
+                self.last_move = Some(now);
+                true
+            }
+        }
+    }
+}
+
+fn modifier_of(key: ModifierKeyCode) -> KeyModifiers {
+    use ModifierKeyCode::*;
+
+    match key {
+        LeftShift | RightShift => KeyModifiers::SHIFT,
+        LeftControl | RightControl => KeyModifiers::CONTROL,
+        LeftAlt | RightAlt => KeyModifiers::ALT,
+        LeftSuper | RightSuper => KeyModifiers::SUPER,
+        LeftHyper | RightHyper => KeyModifiers::HYPER,
+        LeftMeta | RightMeta => KeyModifiers::META,
+        IsoLevel3Shift | IsoLevel5Shift => KeyModifiers::NONE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(debounce: u64, sticky: bool) -> InputFilter {
+        let mut settings = Settings::default();
+        settings
+            .set_input_debounce(debounce)
+            .set_sticky_modifiers(sticky);
+        InputFilter::from_settings(&settings)
+    }
+
+    #[test]
+    fn moves_are_debounced() {
+        let mut filter = filter(100, false);
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert!(filter.accept_move_at(at(0)));
+        // repeats inside the window are dropped and don't extend it
+        assert!(!filter.accept_move_at(at(50)));
+        assert!(!filter.accept_move_at(at(99)));
+        assert!(filter.accept_move_at(at(100)));
+        assert!(!filter.accept_move_at(at(150)));
+        assert!(filter.accept_move_at(at(300)));
+    }
+
+    #[test]
+    fn no_debounce_accepts_every_move() {
+        let mut filter = filter(0, false);
+        let now = Instant::now();
+        assert!(filter.accept_move_at(now));
+        assert!(filter.accept_move_at(now));
+    }
+
+    #[test]
+    fn sticky_modifier_applies_to_one_key() {
+        let mut filter = filter(0, true);
+        let shift = KeyEvent::from(KeyCode::Modifier(ModifierKeyCode::LeftShift));
+        let up = KeyEvent::from(KeyCode::Up);
+
+        assert_eq!(filter.filter(shift), None);
+        assert_eq!(filter.filter(up).unwrap().modifiers, KeyModifiers::SHIFT);
+        assert_eq!(filter.filter(up).unwrap().modifiers, KeyModifiers::NONE);
+    }
+
+    #[test]
+    fn modifiers_pass_through_without_sticky() {
+        let mut filter = filter(0, false);
+        let shift = KeyEvent::from(KeyCode::Modifier(ModifierKeyCode::LeftShift));
+        let up = KeyEvent::from(KeyCode::Up);
+
+        assert_eq!(filter.filter(shift), Some(shift));
+        assert_eq!(filter.filter(up).unwrap().modifiers, KeyModifiers::NONE);
+    }
+}
@@ -0,0 +1,398 @@
+//! Diff of two mazes generated from the same seed, a tool for the authors of the generators
+//!
+//! Opened with F4 in the debug mode. Both mazes have the same size and seed and differ in the
+//! algorithm or the braiding, walls only in the first maze are shown as removed and walls only in
+//! the second one as added. A change of a generator shows up when both sides use the same
+//! algorithm before and after it.
+
+use std::io;
+
+use cmaze::{
+    dims::*,
+    game::{GameProperities, RunningGame},
+    gameboard::{algorithms::random_seed, CellWall, Maze},
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+
+use crate::{
+    helpers::{bench::algorithm_name, is_release, maze2screen, LineDir},
+    renderer::Frame,
+    settings::{
+        theme::{Style, Theme, ThemeResolver},
+        MazeGenAlgo,
+    },
+    ui::{center_box_in_screen, Hint, Screen},
+};
+
+use super::{app::AppData, Activity, ActivityHandler, Change, Event};
+
+const ALGORITHMS: [MazeGenAlgo; 2] = [MazeGenAlgo::RandomKruskals, MazeGenAlgo::DepthFirstSearch];
+/// Percentages of the dead ends removed, cycled through
+const BRAIDS: [u8; 4] = [0, 25, 50, 100];
+/// Lines above the mazes, with the title and the counts
+const HEADER_HEIGHT: i32 = 3;
+
+/// Generator and its parameters of one side of the diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Variant {
+    pub algorithm: MazeGenAlgo,
+    pub braid: u8,
+}
+
+impl Variant {
+    fn name(&self) -> String {
+        match self.braid {
+            0 => algorithm_name(self.algorithm).to_string(),
+            braid => format!("{} {}% loops", algorithm_name(self.algorithm), braid),
+        }
+    }
+
+    fn generate(&self, size: Dims3D, seed: u64) -> Result<Maze, String> {
+        let game_mode = GameMode {
+            size,
+            is_tower: false,
+            goals: Default::default(),
+            objective: Default::default(),
+            braid: self.braid,
+            braid_floors: 0,
+            items: Default::default(),
+        };
+        let props = GameProperities {
+            game_mode,
+            generator: self.algorithm.to_fn(),
+            seed: Some(seed),
+        };
+
+        let game = RunningGame::new_threaded(props)
+            .map_err(|err| format!("Invalid maze: {:?}", err))?
+            .handle
+            .join()
+            .map_err(|_| "Generation panicked".to_string())?
+            .map_err(|err| format!("Generation failed: {:?}", err))?;
+        Ok(game.get_maze().clone())
+    }
+}
+
+/// Where a wall is in the two mazes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WallDiff {
+    Both,
+    Neither,
+    Removed,
+    Added,
+}
+
+impl WallDiff {
+    fn of(old: &Maze, new: &Maze, cell: Dims3D, wall: CellWall) -> Self {
+        let has = |maze: &Maze| maze.get_wall(cell, wall).unwrap_or(true);
+        match (has(old), has(new)) {
+            (true, true) => WallDiff::Both,
+            (false, false) => WallDiff::Neither,
+            (true, false) => WallDiff::Removed,
+            (false, true) => WallDiff::Added,
+        }
+    }
+
+    fn exists(self) -> bool {
+        self != WallDiff::Neither
+    }
+}
+
+pub struct MazeDiffActivity {
+    seed: u64,
+    size: Dims3D,
+    variants: [Variant; 2],
+    /// Both mazes, or why they couldn't be generated
+    mazes: Result<[Maze; 2], String>,
+    /// Walls only in the first maze and only in the second one
+    removed: usize,
+    added: usize,
+}
+
+impl MazeDiffActivity {
+    /// Single floor as big as fits the screen, the first maze is compared with the other algorithm
+    pub fn new(screen_size: Dims) -> Self {
+        let width = ((screen_size.0 - 2) / 2 - 1).clamp(2, 60);
+        let height = ((screen_size.1 - HEADER_HEIGHT - 2) / 2 - 1).clamp(2, 30);
+        let variant = |algorithm| Variant {
+            algorithm,
+            braid: 0,
+        };
+
+        let mut diff = Self {
+            seed: random_seed(),
+            size: Dims3D(width, height, 1),
+            variants: ALGORITHMS.map(variant),
+            mazes: Err(String::new()),
+            removed: 0,
+            added: 0,
+        };
+        diff.generate();
+        diff
+    }
+
+    pub fn new_activity(screen_size: Dims) -> Activity {
+        Activity::new_base_boxed("maze diff", Self::new(screen_size))
+    }
+
+    fn generate(&mut self) {
+        let [old, new] = self.variants;
+        self.mazes = old
+            .generate(self.size, self.seed)
+            .and_then(|old| Ok([old, new.generate(self.size, self.seed)?]));
+
+        (self.removed, self.added) = (0, 0);
+        let Ok([old, new]) = &self.mazes else {
+            return;
+        };
+        let Dims3D(w, h, d) = self.size;
+        for z in 0..d {
+            for y in -1..h {
+                for x in -1..w {
+                    for wall in [CellWall::Right, CellWall::Bottom] {
+                        match WallDiff::of(old, new, Dims3D(x, y, z), wall) {
+                            WallDiff::Removed => self.removed += 1,
+                            WallDiff::Added => self.added += 1,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        log::info!(
+            "Diff of {} and {}, seed {}: {} walls removed, {} added",
+            self.variants[0].name(),
+            self.variants[1].name(),
+            self.seed,
+            self.removed,
+            self.added
+        );
+    }
+
+    fn cycle_algorithm(&mut self, side: usize) {
+        let variant = &mut self.variants[side];
+        let index = ALGORITHMS.iter().position(|a| *a == variant.algorithm);
+        variant.algorithm = ALGORITHMS[index.map_or(0, |i| (i + 1) % ALGORITHMS.len())];
+    }
+
+    fn cycle_braid(&mut self, side: usize) {
+        let variant = &mut self.variants[side];
+        let index = BRAIDS.iter().position(|b| *b == variant.braid);
+        variant.braid = BRAIDS[index.map_or(0, |i| (i + 1) % BRAIDS.len())];
+    }
+
+    /// Walls of both mazes on one board, the changed ones in their own styles
+    fn render(&self, old: &Maze, new: &Maze, theme: &Theme) -> Frame {
+        let [both, removed, added] =
+            theme.extract(["debug.diff.same", "debug.diff.removed", "debug.diff.added"]);
+        let style = |diff| match diff {
+            WallDiff::Removed => removed,
+            WallDiff::Added => added,
+            WallDiff::Both | WallDiff::Neither => both,
+        };
+
+        let Dims3D(w, h, _) = self.size;
+        let mut frame = Frame::new(Dims(w * 2 + 1, h * 2 + 1));
+        for y in -1..h {
+            for x in -1..w {
+                let cell = Dims3D(x, y, 0);
+                let corner = cell + Dims3D(1, 1, 0);
+                let Dims(rx, ry) = maze2screen(cell);
+                let diff = |cell, wall| WallDiff::of(old, new, cell, wall);
+
+                let right = diff(cell, CellWall::Right);
+                if right.exists() {
+                    frame.draw(Dims(rx + 1, ry), LineDir::Vertical.double(), style(right));
+                }
+                let bottom = diff(cell, CellWall::Bottom);
+                if bottom.exists() {
+                    frame.draw(
+                        Dims(rx, ry + 1),
+                        LineDir::Horizontal.double(),
+                        style(bottom),
+                    );
+                }
+
+                // same as `texture::junction_dir`, of the walls of any of the mazes
+                let junction = LineDir::from_bools(
+                    bottom.exists(),
+                    right.exists(),
+                    diff(corner, CellWall::Top).exists(),
+                    diff(corner, CellWall::Left).exists(),
+                );
+                frame.draw(Dims(rx + 1, ry + 1), junction.double(), both);
+            }
+        }
+
+        frame
+    }
+}
+
+impl ActivityHandler for MazeDiffActivity {
+    fn update(&mut self, events: Vec<Event>, _: &mut AppData) -> Option<Change> {
+        for event in events {
+            let Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Char('a') => self.cycle_algorithm(0),
+                KeyCode::Char('b') => self.cycle_algorithm(1),
+                KeyCode::Char('A') => self.cycle_braid(0),
+                KeyCode::Char('B') => self.cycle_braid(1),
+                KeyCode::Char('r') => self.seed = random_seed(),
+                KeyCode::Esc | KeyCode::Char('q') => return Some(Change::pop_top()),
+                _ => continue,
+            }
+            self.generate();
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![
+            Hint::new("A/B", "algorithm"),
+            Hint::new("Shift+A/B", "loops"),
+            Hint::new("R", "new seed"),
+            Hint::new("Esc", "back"),
+        ]
+    }
+}
+
+impl Screen for MazeDiffActivity {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let [text, dim, removed, added]: [Style; 4] = theme.extract([
+            "debug.text",
+            "debug.diff.dim",
+            "debug.diff.removed",
+            "debug.diff.added",
+        ]);
+
+        let Dims3D(w, h, _) = self.size;
+        let title = format!(
+            "{} vs {}, {}x{}, seed {}",
+            self.variants[0].name(),
+            self.variants[1].name(),
+            w,
+            h,
+            self.seed
+        );
+        frame.draw(Dims(1, 0), title, text);
+
+        let [old, new] = match &self.mazes {
+            Ok(mazes) => mazes,
+            Err(err) => {
+                frame.draw(Dims(1, 1), err.as_str(), removed);
+                return Ok(());
+            }
+        };
+
+        let counts = [
+            (format!("-{} removed", self.removed), removed),
+            (format!("+{} added", self.added), added),
+            ("walls in the second maze".to_string(), dim),
+        ];
+        let mut x = 1;
+        for (count, style) in counts {
+            let width = count.chars().count() as i32 + 1;
+            frame.draw(Dims(x, 1), count, style);
+            x += width;
+        }
+
+        let board = self.render(old, new, theme);
+        let area = frame.size - Dims(0, HEADER_HEIGHT);
+        let pos = center_box_in_screen(board.size, area) + Dims(0, HEADER_HEIGHT);
+        frame.draw(pos, &board, ());
+
+        Ok(())
+    }
+}
+
+pub fn maze_diff_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("debug.diff.same", "game.walls")
+        .desc("Walls of both mazes in the maze diff")
+        .link("debug.diff.removed", "debug.text")
+        .desc("Walls only in the first maze of the maze diff")
+        .link("debug.diff.added", "debug.text")
+        .desc("Walls only in the second maze of the maze diff")
+        .link("debug.diff.dim", "dim")
+        .desc("Labels of the maze diff");
+
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(variants: [Variant; 2]) -> MazeDiffActivity {
+        let mut diff = MazeDiffActivity {
+            seed: 5,
+            size: Dims3D(12, 8, 1),
+            variants,
+            mazes: Err(String::new()),
+            removed: 0,
+            added: 0,
+        };
+        diff.generate();
+        diff
+    }
+
+    #[test]
+    fn same_variants_have_no_diff() {
+        let variant = Variant {
+            algorithm: MazeGenAlgo::RandomKruskals,
+            braid: 0,
+        };
+        let diff = generated([variant; 2]);
+
+        assert!(diff.mazes.is_ok());
+        assert_eq!((diff.removed, diff.added), (0, 0));
+    }
+
+    #[test]
+    fn braiding_only_removes_walls() {
+        let variant = |braid| Variant {
+            algorithm: MazeGenAlgo::DepthFirstSearch,
+            braid,
+        };
+        let diff = generated([variant(0), variant(100)]);
+
+        assert!(diff.removed > 0);
+        assert_eq!(diff.added, 0);
+
+        // the other way around the same walls are added
+        let reversed = generated([variant(100), variant(0)]);
+        assert_eq!((reversed.removed, reversed.added), (0, diff.removed));
+    }
+
+    #[test]
+    fn variants_cycle_around() {
+        let mut diff = generated(ALGORITHMS.map(|algorithm| Variant {
+            algorithm,
+            braid: 0,
+        }));
+
+        diff.cycle_algorithm(0);
+        assert_eq!(diff.variants[0].algorithm, ALGORITHMS[1]);
+        diff.cycle_algorithm(0);
+        assert_eq!(diff.variants[0].algorithm, ALGORITHMS[0]);
+
+        for braid in BRAIDS.iter().cycle().skip(1).take(BRAIDS.len()) {
+            diff.cycle_braid(1);
+            assert_eq!(diff.variants[1].braid, *braid);
+        }
+        assert_eq!(diff.variants[0].braid, 0);
+    }
+}
@@ -0,0 +1,151 @@
+//! Estimate of the memory a maze takes, checked before it's generated
+//!
+//! Very big mazes take gigabytes and the system could kill the whole app while it's generating
+//! one. The estimate counts the cells of the maze, the working memory of the generator and the
+//! rendered floors in all levels of detail, see [`estimate`].
+
+use std::mem::size_of;
+
+use cmaze::{dims::*, gameboard::Cell};
+
+use crate::renderer;
+
+const MIB: u64 = 1024 * 1024;
+const GIB: u64 = 1024 * MIB;
+
+/// Working memory of the generator per cell, a set of the connected cells and the walls
+const GENERATION_BYTES_PER_CELL: u64 = 192;
+/// Floors are rendered in all levels of detail, each is a quarter of the previous one
+const LOD_FACTOR: f64 = 4.0 / 3.0;
+/// Suggested smaller mazes shrink by this until they fit
+const SHRINK_STEP: f64 = 0.9;
+
+/// Bytes needed to generate and play a maze of the size
+pub fn estimate(size: Dims3D) -> u64 {
+    let Dims3D(w, h, d) = size;
+    let (w, h, d) = (w.max(0) as u64, h.max(0) as u64, d.max(0) as u64);
+
+    let cells = w * h * d;
+    let chars = (2 * w + 1) * (2 * h + 1) * d;
+    let frames = (chars * size_of::<renderer::Cell>() as u64) as f64 * LOD_FACTOR;
+
+    cells * (size_of::<Cell>() as u64 + GENERATION_BYTES_PER_CELL) + frames as u64
+}
+
+/// Memory the system can still give to the app, only known on Linux
+pub fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    match bytes >= GIB {
+        true => format!("{:.1} GiB", bytes as f64 / GIB as f64),
+        false => format!("{} MiB", bytes.div_ceil(MIB)),
+    }
+}
+
+/// Whether a maze fits the memory, checked before it's generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryCheck {
+    Fits,
+    /// More than the budget from the settings, it can still be generated
+    OverBudget {
+        needed: u64,
+        budget: u64,
+    },
+    /// More than the system has available, the generation would likely get the app killed
+    OverAvailable {
+        needed: u64,
+        available: u64,
+        /// Budget from the settings, 0 is no budget
+        budget: u64,
+    },
+}
+
+impl MemoryCheck {
+    /// Checks the size against the budget in MiB, 0 is no budget, and the available memory
+    pub fn new(size: Dims3D, budget: u64, available: Option<u64>) -> Self {
+        let needed = estimate(size);
+        let budget = budget * MIB;
+
+        match available {
+            Some(available) if needed > available => Self::OverAvailable {
+                needed,
+                available,
+                budget,
+            },
+            _ if budget > 0 && needed > budget => Self::OverBudget { needed, budget },
+            _ => Self::Fits,
+        }
+    }
+
+    /// Biggest maze with the same floors and about the same shape, which fits the memory and
+    /// the budget
+    ///
+    /// Returns `None` when the maze already fits or it can't get any smaller.
+    pub fn smaller_size(&self, size: Dims3D) -> Option<Dims3D> {
+        let (needed, limit) = match *self {
+            Self::Fits => return None,
+            Self::OverBudget { needed, budget } => (needed, budget),
+            Self::OverAvailable {
+                needed,
+                available,
+                budget: 0,
+            } => (needed, available),
+            Self::OverAvailable {
+                needed,
+                available,
+                budget,
+            } => (needed, available.min(budget)),
+        };
+
+        // memory grows with the area of the floors
+        let mut scale = (limit as f64 / needed as f64).sqrt();
+        loop {
+            let smaller = Dims3D(
+                ((size.0 as f64 * scale) as i32).max(2),
+                ((size.1 as f64 * scale) as i32).max(2),
+                size.2,
+            );
+            if estimate(smaller) <= limit {
+                return (smaller != size).then_some(smaller);
+            }
+            if smaller.0 == 2 && smaller.1 == 2 {
+                return None;
+            }
+            scale *= SHRINK_STEP;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn big_mazes_are_shrunk_to_fit() {
+        let size = Dims3D(4000, 3000, 4);
+        assert_eq!(
+            MemoryCheck::new(Dims3D(20, 10, 1), 1024, None),
+            MemoryCheck::Fits
+        );
+
+        let check = MemoryCheck::new(size, 1024, None);
+        assert!(matches!(check, MemoryCheck::OverBudget { .. }));
+        let smaller = check.smaller_size(size).unwrap();
+        assert_eq!(smaller.2, 4);
+        assert!(estimate(smaller) <= 1024 * MIB);
+
+        // available memory wins over the budget
+        let check = MemoryCheck::new(size, 0, Some(GIB));
+        assert!(matches!(check, MemoryCheck::OverAvailable { .. }));
+        let check = MemoryCheck::new(size, 512, Some(GIB));
+        assert!(estimate(check.smaller_size(size).unwrap()) <= 512 * MIB);
+        assert_eq!(MemoryCheck::new(size, 0, None), MemoryCheck::Fits);
+    }
+}
@@ -1,10 +1,44 @@
+pub mod achievements;
 pub mod activity;
+pub mod annotations;
 #[allow(clippy::module_inception)]
 pub mod app;
+pub mod cache;
+pub mod campaign;
+pub mod daily;
+pub mod dense;
+pub mod end_game;
 pub mod event;
+pub mod event_log;
+pub mod export;
+#[cfg(feature = "first_person")]
+pub mod first_person;
+pub mod floor_jump;
+pub mod floor_overview;
+pub mod gallery;
 pub mod game;
 pub mod game_state;
+pub mod input;
 pub mod jobs;
+pub mod maze_diff;
+pub mod memory;
+pub mod modes;
+pub mod narration;
+#[cfg(feature = "net")]
+pub mod online;
+pub mod overlay;
+pub mod preset_diff;
+pub mod race;
+pub mod replay;
+pub mod screensaver;
+pub mod shifting;
+pub mod stamina;
+pub mod stats;
+pub mod surprise;
+pub mod tags;
+pub mod target;
+pub mod texture;
+pub mod touch_prompt;
 
 use std::io;
 
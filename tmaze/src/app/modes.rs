@@ -0,0 +1,496 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{
+    settings::{ModeValue, Settings},
+    ui::{self, Hint, Menu, MenuConfig},
+};
+
+#[cfg(feature = "net")]
+use super::online::OnlineMenu;
+use super::{
+    app::AppData,
+    campaign::CampaignMap,
+    daily::DailyMenu,
+    game::MazeSizeMenu,
+    game_state::{GameRules, Shifting},
+    stamina::Stamina,
+    Activity, ActivityHandler, Change, Event,
+};
+
+/// Creates the first activity of the mode, usually a maze selection
+pub type ModeFactory = fn(&ModeOptions, &mut AppData) -> Change;
+
+/// Option of a game mode, users can override its value in settings
+#[derive(Debug, Clone)]
+pub struct ModeSetting {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub default: ModeValue,
+}
+
+/// Game mode, as shown in the "New Game" menu
+pub struct GameModeDef {
+    /// Unique identifier, used as a key in settings
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// Options of the mode with their default values
+    pub settings: Vec<ModeSetting>,
+    pub factory: ModeFactory,
+}
+
+/// Option values of a game mode, with the user overrides applied
+#[derive(Debug, Clone, Default)]
+pub struct ModeOptions(BTreeMap<String, ModeValue>);
+
+impl ModeOptions {
+    pub fn resolve(mode: &GameModeDef, settings: &Settings) -> Self {
+        let overrides = settings.get_mode_options(&mode.id);
+
+        let values = mode
+            .settings
+            .iter()
+            .map(|setting| {
+                let value = match overrides.get(setting.key) {
+                    Some(value) if value.same_kind(&setting.default) => *value,
+                    Some(_) => {
+                        log::warn!(
+                            "Invalid value of option `{}` of mode `{}`, using default",
+                            setting.key,
+                            mode.id
+                        );
+                        setting.default
+                    }
+                    None => setting.default,
+                };
+                (setting.key.to_string(), value)
+            })
+            .collect();
+
+        Self(values)
+    }
+
+    pub fn bool(&self, key: &str) -> bool {
+        match self.0.get(key) {
+            Some(ModeValue::Bool(value)) => *value,
+            _ => panic!("mode option `{}` is not a bool", key),
+        }
+    }
+
+    pub fn number(&self, key: &str) -> f64 {
+        match self.0.get(key) {
+            Some(ModeValue::Number(value)) => *value,
+            _ => panic!("mode option `{}` is not a number", key),
+        }
+    }
+
+    /// Number option as a duration in seconds, negative values are clamped to zero
+    pub fn seconds(&self, key: &str) -> Duration {
+        Duration::from_secs_f64(self.number(key).max(0.0))
+    }
+}
+
+/// All game modes, in the order they are shown in the menu
+///
+/// Modes registered later with the same id replace the earlier ones, so
+/// plugins can override even the built-in modes.
+#[derive(Default)]
+pub struct GameModeRegistry {
+    modes: Vec<GameModeDef>,
+}
+
+impl GameModeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with all modes shipped with TMaze
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+
+        registry
+            .register(GameModeDef {
+                id: "classic".to_string(),
+                name: "Classic".to_string(),
+                description: "Find the goal".to_string(),
+                settings: vec![],
+                factory: classic,
+            })
+            .register(GameModeDef {
+                id: "campaign".to_string(),
+                name: "Campaign".to_string(),
+                description: "Stages with new mechanics".to_string(),
+                settings: vec![],
+                factory: campaign,
+            })
+            .register(GameModeDef {
+                id: "rush".to_string(),
+                name: "Rush".to_string(),
+                description: "Beat the clock".to_string(),
+                settings: vec![ModeSetting {
+                    key: "time_limit",
+                    description: "Seconds to finish the maze",
+                    default: ModeValue::Number(120.0),
+                }],
+                factory: rush,
+            })
+            .register(GameModeDef {
+                id: "zen".to_string(),
+                name: "Zen".to_string(),
+                description: "No timer, no records".to_string(),
+                settings: vec![],
+                factory: zen,
+            })
+            .register(GameModeDef {
+                id: "versus".to_string(),
+                name: "Versus AI".to_string(),
+                description: "Race an opponent".to_string(),
+                settings: vec![ModeSetting {
+                    key: "step_time",
+                    description: "Seconds the opponent takes to move by one cell",
+                    default: ModeValue::Number(0.6),
+                }],
+                factory: versus,
+            })
+            .register(GameModeDef {
+                id: "race".to_string(),
+                name: "Race".to_string(),
+                description: "Two players, WASD against arrows".to_string(),
+                settings: vec![],
+                factory: race,
+            })
+            .register(GameModeDef {
+                id: "shifting".to_string(),
+                name: "Shifting".to_string(),
+                description: "Walls move while you play".to_string(),
+                settings: vec![
+                    ModeSetting {
+                        key: "interval",
+                        description: "Seconds between the shifts",
+                        default: ModeValue::Number(15.0),
+                    },
+                    ModeSetting {
+                        key: "walls",
+                        description: "Walls moved by every shift",
+                        default: ModeValue::Number(4.0),
+                    },
+                ],
+                factory: shifting,
+            })
+            .register(GameModeDef {
+                id: "stamina".to_string(),
+                name: "Stamina".to_string(),
+                description: "Moves tire you, stop to plan".to_string(),
+                settings: vec![
+                    ModeSetting {
+                        key: "max",
+                        description: "Most stamina, the run starts with it",
+                        default: ModeValue::Number(Stamina::default().max as f64),
+                    },
+                    ModeSetting {
+                        key: "move_cost",
+                        description: "Stamina used by a move by one cell",
+                        default: ModeValue::Number(Stamina::default().move_cost as f64),
+                    },
+                    ModeSetting {
+                        key: "regen",
+                        description: "Stamina regained per second of standing still",
+                        default: ModeValue::Number(Stamina::default().regen as f64),
+                    },
+                ],
+                factory: stamina,
+            })
+            .register(GameModeDef {
+                id: "endless".to_string(),
+                name: "Endless".to_string(),
+                description: "One maze after another".to_string(),
+                settings: vec![],
+                factory: endless,
+            })
+            .register(GameModeDef {
+                id: "daily".to_string(),
+                name: "Daily".to_string(),
+                description: "Same maze for everyone today".to_string(),
+                settings: vec![],
+                factory: daily,
+            });
+
+        #[cfg(feature = "net")]
+        registry.register(GameModeDef {
+            id: "online".to_string(),
+            name: "Online".to_string(),
+            description: "Race a player on another computer".to_string(),
+            settings: vec![ModeSetting {
+                key: "port",
+                description: "Port the hosted races listen on",
+                default: ModeValue::Number(crate::net::DEFAULT_PORT as f64),
+            }],
+            factory: online,
+        });
+
+        registry
+    }
+
+    pub fn register(&mut self, mode: GameModeDef) -> &mut Self {
+        match self.modes.iter_mut().find(|m| m.id == mode.id) {
+            Some(existing) => {
+                log::info!("Game mode `{}` replaced", mode.id);
+                *existing = mode;
+            }
+            None => self.modes.push(mode),
+        }
+        self
+    }
+
+    pub fn modes(&self) -> &[GameModeDef] {
+        &self.modes
+    }
+
+    pub fn get(&self, id: &str) -> Option<&GameModeDef> {
+        self.modes.iter().find(|m| m.id == id)
+    }
+
+    /// Starts the mode, with options resolved from the settings
+    pub fn start(&self, id: &str, data: &mut AppData) -> Option<Change> {
+        let mode = self.get(id)?;
+        let options = ModeOptions::resolve(mode, &data.settings);
+        Some((mode.factory)(&options, data))
+    }
+}
+
+fn new_game(rules: GameRules, data: &mut AppData) -> Change {
+    Change::push(Activity::new_base_boxed(
+        "maze size",
+        MazeSizeMenu::new(&data.settings, &data.use_data, &data.save).with_rules(rules),
+    ))
+}
+
+fn classic(_: &ModeOptions, data: &mut AppData) -> Change {
+    new_game(GameRules::default(), data)
+}
+
+fn rush(options: &ModeOptions, data: &mut AppData) -> Change {
+    new_game(rush_rules(options), data)
+}
+
+fn rush_rules(options: &ModeOptions) -> GameRules {
+    GameRules {
+        time_limit: Some(options.seconds("time_limit")),
+        ..Default::default()
+    }
+}
+
+fn zen(_: &ModeOptions, data: &mut AppData) -> Change {
+    let rules = GameRules {
+        relaxed: true,
+        ..Default::default()
+    };
+    new_game(rules, data)
+}
+
+fn versus(options: &ModeOptions, data: &mut AppData) -> Change {
+    new_game(versus_rules(options), data)
+}
+
+fn versus_rules(options: &ModeOptions) -> GameRules {
+    GameRules {
+        opponent_step: Some(options.seconds("step_time")),
+        ..Default::default()
+    }
+}
+
+fn race(_: &ModeOptions, data: &mut AppData) -> Change {
+    let rules = GameRules {
+        race: true,
+        ..Default::default()
+    };
+    new_game(rules, data)
+}
+
+fn shifting(options: &ModeOptions, data: &mut AppData) -> Change {
+    new_game(shifting_rules(options), data)
+}
+
+fn shifting_rules(options: &ModeOptions) -> GameRules {
+    GameRules {
+        shifting: Some(Shifting {
+            interval: options.seconds("interval"),
+            walls: options.number("walls").max(1.0) as usize,
+        }),
+        ..Default::default()
+    }
+}
+
+fn stamina(options: &ModeOptions, data: &mut AppData) -> Change {
+    new_game(stamina_rules(options), data)
+}
+
+fn stamina_rules(options: &ModeOptions) -> GameRules {
+    GameRules {
+        stamina: Some(Stamina {
+            max: options.number("max") as f32,
+            move_cost: options.number("move_cost") as f32,
+            regen: options.number("regen") as f32,
+        }),
+        ..Default::default()
+    }
+}
+
+fn endless(_: &ModeOptions, data: &mut AppData) -> Change {
+    let rules = GameRules {
+        endless: true,
+        ..Default::default()
+    };
+    new_game(rules, data)
+}
+
+fn campaign(_: &ModeOptions, data: &mut AppData) -> Change {
+    Change::push(CampaignMap::new_activity(&data.save))
+}
+
+#[cfg(feature = "net")]
+fn online(options: &ModeOptions, _: &mut AppData) -> Change {
+    let port = options.number("port").clamp(0.0, u16::MAX as f64) as u16;
+    Change::push(OnlineMenu::new_activity(port))
+}
+
+fn daily(_: &ModeOptions, data: &mut AppData) -> Change {
+    Change::push(DailyMenu::new_activity(
+        &data.save,
+        data.settings.get_real_time_results(),
+    ))
+}
+
+/// "New Game" menu, built from the registered game modes
+pub struct GameModeMenu {
+    menu: Menu,
+    ids: Vec<String>,
+}
+
+impl GameModeMenu {
+    pub fn new(registry: &GameModeRegistry) -> Self {
+        let (ids, options): (Vec<_>, Vec<_>) = registry
+            .modes()
+            .iter()
+            .map(|mode| {
+                let text = format!("{} - {}", mode.name, mode.description);
+                (mode.id.clone(), text)
+            })
+            .unzip();
+
+        let menu = Menu::new(MenuConfig::new_from_strings("New Game", options).counted());
+
+        Self { menu, ids }
+    }
+
+    pub fn new_activity(registry: &GameModeRegistry) -> Activity {
+        Activity::new_base_boxed("game mode", Self::new(registry))
+    }
+}
+
+impl ActivityHandler for GameModeMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data)? {
+            Change::Pop { res: Some(res), .. } => {
+                let index = *res.downcast::<usize>().expect("menu should return index");
+                let id = &self.ids[index];
+
+                // registry is borrowed from the app data, so it's taken out for the call
+                let modes = std::mem::take(&mut data.modes);
+                let change = modes.start(id, data);
+                data.modes = modes;
+
+                change.or_else(|| {
+                    log::error!("Game mode `{}` is not registered", id);
+                    Some(Change::pop_top())
+                })
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn ui::Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use cmaze::dims::Dims;
+
+    use crate::{app::App, ui::Popup};
+
+    use super::*;
+
+    fn default_options(id: &str) -> ModeOptions {
+        let registry = GameModeRegistry::builtin();
+        let mode = registry.get(id).expect("mode should be built-in");
+        ModeOptions::resolve(mode, &Settings::default())
+    }
+
+    #[test]
+    fn builtin_modes_are_unique() {
+        let registry = GameModeRegistry::builtin();
+        let ids: HashSet<_> = registry.modes().iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids.len(), registry.modes().len());
+
+        for mode in registry.modes() {
+            let keys: HashSet<_> = mode.settings.iter().map(|s| s.key).collect();
+            assert_eq!(keys.len(), mode.settings.len(), "{}", mode.id);
+        }
+    }
+
+    #[test]
+    fn builtin_options_resolve_to_defaults() {
+        let registry = GameModeRegistry::builtin();
+        for mode in registry.modes() {
+            let options = ModeOptions::resolve(mode, &Settings::default());
+            for setting in &mode.settings {
+                assert_eq!(options.0.get(setting.key), Some(&setting.default));
+            }
+        }
+    }
+
+    #[test]
+    fn builtin_rules_are_valid() {
+        let rush = rush_rules(&default_options("rush"));
+        assert!(rush.time_limit.is_some_and(|limit| !limit.is_zero()));
+
+        let versus = versus_rules(&default_options("versus"));
+        assert!(versus.opponent_step.is_some_and(|step| !step.is_zero()));
+
+        let shifting = shifting_rules(&default_options("shifting"))
+            .shifting
+            .unwrap();
+        assert!(!shifting.interval.is_zero());
+        assert!(shifting.walls >= 1);
+
+        let stamina = stamina_rules(&default_options("stamina")).stamina.unwrap();
+        assert_eq!(stamina, Stamina::default());
+        assert!(stamina.max >= stamina.move_cost && stamina.move_cost > 0.0);
+    }
+
+    #[test]
+    fn builtin_modes_start() {
+        let popup = Popup::new("Base".to_string(), vec![]);
+        let mut app =
+            App::new_headless_default(Activity::new_base_boxed("base", popup), Dims(80, 24));
+
+        let registry = GameModeRegistry::builtin();
+        for mode in registry.modes() {
+            let change = registry.start(&mode.id, app.data_mut());
+            assert!(matches!(change, Some(Change::Push(_))), "{}", mode.id);
+        }
+        assert!(registry.start("missing", app.data_mut()).is_none());
+    }
+}
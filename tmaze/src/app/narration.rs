@@ -0,0 +1,154 @@
+//! Text descriptions for screen readers, used in the accessibility mode
+//!
+//! Screen readers read the text which changed on the screen, so in the accessibility mode the
+//! game describes the surroundings of the player in words after every move, see
+//! [`describe_surroundings`], instead of drawing the maze. Menus and popups say what's selected
+//! or shown, see [`super::app::AppData::narrate`]. The newest line is drawn at the bottom of the
+//! screen over every activity.
+
+use std::collections::VecDeque;
+
+use cmaze::{dims::*, game::RunningGame, gameboard::CellWall};
+
+use crate::{
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+    ui::footer::FOOTER_MIN_SIZE,
+};
+
+/// How many of the last lines are kept
+const HISTORY_LEN: usize = 16;
+
+/// Last lines said, the newest is shown
+#[derive(Debug, Default)]
+pub struct Narrator {
+    lines: VecDeque<String>,
+}
+
+impl Narrator {
+    /// Adds the line, the same line said again right after is left out
+    pub fn say(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        if self.lines.back() == Some(&text) {
+            return;
+        }
+
+        log::debug!("Narrating: {}", text);
+        if self.lines.len() == HISTORY_LEN {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(text);
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.lines.back().map(String::as_str)
+    }
+
+    /// Lines from the oldest one
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// Draws the newest line at the bottom of the screen, above the key hints if they're shown
+    pub fn draw(&self, frame: &mut Frame, hints: bool, theme: &Theme) {
+        let Some(line) = self.last() else {
+            return;
+        };
+
+        let size = frame.size;
+        let footer = hints && size.0 >= FOOTER_MIN_SIZE.0 && size.1 >= FOOTER_MIN_SIZE.1;
+        let y = size.1 - 1 - footer as i32;
+        let style = theme["ui.narration"];
+        frame.fill_rect(Dims(0, y), Dims(size.0, 1), Cell::styled(' ', style));
+        frame.draw(Dims(0, y), line, style);
+    }
+}
+
+/// Name of the direction, as the player hears it
+pub fn direction_name(dir: CellWall) -> &'static str {
+    match dir {
+        CellWall::Top => "north",
+        CellWall::Bottom => "south",
+        CellWall::Left => "west",
+        CellWall::Right => "east",
+        CellWall::Up => "up",
+        CellWall::Down => "down",
+    }
+}
+
+/// Where the player can go from their cell, what's around and how far the nearest goal is
+///
+/// For example "open: north, east, up; locked door: west; goal 12 cells away".
+pub fn describe_surroundings(game: &RunningGame) -> String {
+    let maze = game.get_maze();
+    let pos = game.get_player_pos();
+
+    let mut open = vec![];
+    let mut doors = vec![];
+    for dir in CellWall::get_in_order() {
+        if !game.is_blocked(dir) {
+            open.push(direction_name(dir));
+        } else if !maze.get_wall(pos, dir).unwrap_or(true) {
+            // passage is there, only the door is locked
+            doors.push(direction_name(dir));
+        }
+    }
+
+    let mut parts = vec![match open.is_empty() {
+        true => "no way out".to_string(),
+        false => format!("open: {}", open.join(", ")),
+    }];
+    if !doors.is_empty() {
+        parts.push(format!("locked door: {}", doors.join(", ")));
+    }
+
+    let nearest = game
+        .get_goals()
+        .iter()
+        .filter(|goal| !game.is_goal_reached(**goal))
+        .filter_map(|goal| maze.solve(pos, *goal))
+        .map(|path| path.len() - 1)
+        .min();
+    match nearest {
+        Some(1) => parts.push("goal 1 cell away".to_string()),
+        Some(cells) => parts.push(format!("goal {} cells away", cells)),
+        None => {}
+    }
+
+    parts.join("; ")
+}
+
+pub fn narration_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+    resolver
+        .link("ui.narration", "text")
+        .desc("Line describing the game or the selected item, in the accessibility mode");
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_lines_are_said_once() {
+        let mut narrator = Narrator::default();
+        narrator.say("open: north");
+        narrator.say("open: north");
+        narrator.say("open: south");
+        narrator.say("open: north");
+        assert_eq!(
+            narrator.lines().collect::<Vec<_>>(),
+            ["open: north", "open: south", "open: north"]
+        );
+
+        for i in 0..HISTORY_LEN * 2 {
+            narrator.say(i.to_string());
+        }
+        assert_eq!(narrator.lines().count(), HISTORY_LEN);
+        assert_eq!(
+            narrator.last(),
+            Some((HISTORY_LEN * 2 - 1).to_string().as_str())
+        );
+    }
+}
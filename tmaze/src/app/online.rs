@@ -0,0 +1,622 @@
+//! Online race, two players on the same seeded maze, see [`crate::net`]
+//!
+//! One player hosts and picks the maze, the other joins by address. Both play their own
+//! game, only the positions are exchanged, so the opponent is drawn as a second marker.
+
+use std::{
+    io,
+    net::TcpListener,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use cmaze::{
+    dims::*,
+    game::{GameProperities, ProgressComm, RunningGame, RunningGameState},
+    gameboard::algorithms::{random_seed, GenErrorThreaded},
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+
+use crate::{
+    helpers::is_release,
+    net::{self, Connection, Message, NetError, PROTOCOL_VERSION},
+    renderer::Frame,
+    settings::{theme::Theme, MazeGenAlgo, Settings},
+    ui::{self, center_box_in_screen, draw_box, helpers::format_duration, Menu, MenuConfig},
+    ui::{ErrorPopup, Hint, Popup, Screen, TextInput},
+};
+
+use super::{
+    app::AppData,
+    end_game::Outcome,
+    game::GameActivity,
+    game_state::{GameData, GameRules},
+    memory::{available_memory, MemoryCheck},
+    Activity, ActivityHandler, Change, Event,
+};
+
+/// Other player during the race
+pub struct Peer {
+    conn: Option<Connection>,
+    /// Last position the other player reported
+    pos: Dims3D,
+    /// Last position sent to the other player
+    sent: Dims3D,
+    /// Time and moves of the other player, once they finish
+    finished: Option<(Duration, usize)>,
+    /// Whether this player already told the other one about the finish
+    announced: bool,
+}
+
+impl Peer {
+    pub fn new(conn: Connection, start: Dims3D) -> Self {
+        Self {
+            conn: Some(conn),
+            pos: start,
+            sent: start,
+            finished: None,
+            announced: false,
+        }
+    }
+
+    /// Position of the other player, `None` after they left
+    pub fn pos(&self) -> Option<Dims3D> {
+        self.conn.as_ref().map(|_| self.pos)
+    }
+
+    /// Exchanges the positions and finishes, returns the outcome once the race is decided
+    ///
+    /// Whoever reports the finish first wins, so with a slow connection both players can
+    /// win when they finish at almost the same time.
+    pub fn update(&mut self, game: &RunningGame) -> Option<Outcome> {
+        while let Some(msg) = self.conn.as_ref().and_then(Connection::try_recv) {
+            match msg {
+                Ok(Message::Position { pos }) if game.get_maze().is_in_bounds(pos) => {
+                    self.pos = pos
+                }
+                Ok(Message::Position { pos }) => {
+                    log::warn!(
+                        "Opponent reported a position outside of the maze: {:?}",
+                        pos
+                    );
+                }
+                Ok(Message::Finished { elapsed, moves }) => {
+                    self.finished = Some((Duration::from_millis(elapsed), moves));
+                }
+                Ok(Message::Bye) => self.disconnect(None),
+                Ok(msg) => self.disconnect(Some(NetError::Unexpected(msg))),
+                Err(err) => self.disconnect(Some(err)),
+            }
+        }
+
+        let pos = game.get_player_pos();
+        if pos != self.sent {
+            self.sent = pos;
+            self.send(Message::Position { pos });
+        }
+
+        if game.get_state() == RunningGameState::Finished && !self.announced {
+            self.announced = true;
+            self.send(Message::Finished {
+                elapsed: game.get_elapsed().unwrap_or_default().as_millis() as u64,
+                moves: game.get_move_count(),
+            });
+
+            return match self.finished {
+                Some(_) => Some(Outcome::Beaten),
+                None => Some(Outcome::Won),
+            };
+        }
+
+        self.finished.map(|_| Outcome::Beaten)
+    }
+
+    /// Popup announcing the winner
+    pub fn result_popup(&self, outcome: Outcome, game: &RunningGame) -> Popup {
+        let mut lines = vec![];
+        if outcome == Outcome::Won {
+            lines.push(format!(
+                "You: {}, {} moves",
+                format_duration(game.get_elapsed().unwrap_or_default()),
+                game.get_move_count()
+            ));
+        }
+        match (self.finished, &self.conn) {
+            (Some((elapsed, moves)), _) => lines.push(format!(
+                "Opponent: {}, {} moves",
+                format_duration(elapsed),
+                moves
+            )),
+            (None, None) => lines.push("Opponent left the race".to_string()),
+            (None, Some(_)) => lines.push("Opponent didn't finish".to_string()),
+        }
+
+        Popup::new(outcome.title().to_string(), lines)
+    }
+
+    fn send(&mut self, msg: Message) {
+        if let Some(Err(err)) = self.conn.as_mut().map(|conn| conn.send(&msg)) {
+            self.disconnect(Some(err));
+        }
+    }
+
+    fn disconnect(&mut self, err: Option<NetError>) {
+        match err {
+            Some(err) => log::error!("Online race failed: {}", err),
+            None => log::warn!("Opponent left the race"),
+        }
+        self.conn = None;
+    }
+}
+
+/// Host or join an online race
+pub struct OnlineMenu {
+    menu: Menu,
+    port: u16,
+}
+
+impl OnlineMenu {
+    pub fn new_activity(port: u16) -> Activity {
+        let menu = Menu::new(MenuConfig::new_from_strings(
+            "Online race",
+            ["Host a race".to_string(), "Join a race".to_string()],
+        ));
+        Activity::new_base_boxed("online", Self { menu, port })
+    }
+}
+
+impl ActivityHandler for OnlineMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => match *index.downcast::<usize>().expect("menu should return index") {
+                0 => Some(Change::push(HostMenu::new_activity(data, self.port))),
+                _ => Some(Change::push(JoinDialog::new_activity())),
+            },
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn ui::Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+/// Maze of the hosted race, from the presets
+struct HostMenu {
+    menu: Menu,
+    modes: Vec<GameMode>,
+    port: u16,
+}
+
+impl HostMenu {
+    fn new_activity(data: &AppData, port: u16) -> Activity {
+        let presets = data.settings.get_mazes();
+        let menu = Menu::new(
+            MenuConfig::new_from_strings(
+                "Maze of the race",
+                presets.iter().map(|p| p.title.clone()).collect::<Vec<_>>(),
+            )
+            .subtitle(format!(
+                "Listens on port {} of all network interfaces",
+                port
+            )),
+        );
+        let modes = presets.iter().map(|p| p.game_mode()).collect();
+
+        Activity::new_base_boxed("online host", Self { menu, modes, port })
+    }
+}
+
+impl ActivityHandler for HostMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                let algo = data.settings.get_default_maze_gen_algo();
+                let lobby = match net::listen(self.port) {
+                    Ok(listener) => Lobby::host(listener, self.modes[index], algo),
+                    Err(err) => return Some(failed(err.into())),
+                };
+                Some(Change::replace(Activity::new_base_boxed("lobby", lobby)))
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn ui::Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+/// Asks for the address of the host
+struct JoinDialog {
+    input: TextInput,
+}
+
+impl JoinDialog {
+    fn new_activity() -> Activity {
+        let input = TextInput::new().with_placeholder(format!("host:{}", net::DEFAULT_PORT));
+        Activity::new_base_boxed("online join", Self { input })
+    }
+}
+
+impl ActivityHandler for JoinDialog {
+    fn update(&mut self, events: Vec<Event>, _data: &mut AppData) -> Option<Change> {
+        for event in events {
+            let Event::Term(TermEvent::Key(key @ KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Esc => return Some(Change::pop_top()),
+                KeyCode::Enter if !self.input.value().trim().is_empty() => {
+                    let lobby = Lobby::join(self.input.value().trim().to_string());
+                    return Some(Change::replace(Activity::new_base_boxed("lobby", lobby)));
+                }
+                _ => {
+                    self.input.handle_key(key);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+}
+
+impl Screen for JoinDialog {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let size = Dims((frame.size.0 - 4).clamp(30, 50), 4);
+        let pos = center_box_in_screen(size, frame.size);
+
+        draw_box(frame, pos, size, theme["ui_menu_border"]);
+        frame.draw(pos + Dims(3, 0), " Join a race ", theme["ui_menu_title"]);
+        self.input.draw(frame, theme, pos + Dims(2, 1), size.0 - 4);
+        frame.draw(pos + Dims(2, 2), "Enter: join, Esc: cancel", theme["dim"]);
+
+        Ok(())
+    }
+}
+
+enum LobbyState {
+    /// Host waits for the other player
+    Listening(TcpListener),
+    /// Joining player connects to the host
+    Connecting(Receiver<Result<Connection, NetError>>),
+    /// Players greet each other and agree on the maze
+    Handshake,
+    Generating(ProgressComm<Result<RunningGame, GenErrorThreaded>>),
+    /// Maze is ready, waiting for the other player
+    Ready(Box<RunningGame>),
+}
+
+/// Connects the players and generates the maze of the race
+pub struct Lobby {
+    state: Option<LobbyState>,
+    conn: Option<Connection>,
+    /// Maze of the race, set by the host
+    maze: Option<(GameMode, MazeGenAlgo)>,
+    peer_ready: bool,
+    status: String,
+}
+
+impl Lobby {
+    fn host(listener: TcpListener, game_mode: GameMode, algo: MazeGenAlgo) -> Self {
+        let port = listener.local_addr().map_or(0, |addr| addr.port());
+        Self {
+            state: Some(LobbyState::Listening(listener)),
+            conn: None,
+            maze: Some((game_mode, algo)),
+            peer_ready: false,
+            status: format!("Waiting for the other player on port {}", port),
+        }
+    }
+
+    fn join(address: String) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let status = format!("Connecting to {}", address);
+        thread::spawn(move || {
+            let _ = tx.send(Connection::connect(&address));
+        });
+
+        Self {
+            state: Some(LobbyState::Connecting(rx)),
+            conn: None,
+            maze: None,
+            peer_ready: false,
+            status,
+        }
+    }
+
+    fn is_host(&self) -> bool {
+        self.maze.is_some()
+    }
+
+    fn send(&mut self, msg: Message) -> Result<(), NetError> {
+        self.conn.as_mut().ok_or(NetError::Closed)?.send(&msg)
+    }
+
+    fn handle(&mut self, msg: Message, settings: &Settings) -> Result<(), NetError> {
+        match msg {
+            Message::Hello { version } if version != PROTOCOL_VERSION => {
+                Err(NetError::Version(version))
+            }
+            Message::Hello { .. } if self.is_host() => {
+                let (game_mode, algo) = self.maze.unwrap();
+                let seed = random_seed();
+                self.send(Message::Hello {
+                    version: PROTOCOL_VERSION,
+                })?;
+                self.send(Message::Maze {
+                    game_mode,
+                    algo,
+                    seed,
+                })?;
+                self.generate(game_mode, algo, seed);
+                Ok(())
+            }
+            Message::Hello { .. } => Ok(()),
+            Message::Maze {
+                game_mode,
+                algo,
+                seed,
+            } if !self.is_host() => {
+                check_size(game_mode.size, settings)?;
+                self.generate(game_mode, algo, seed);
+                Ok(())
+            }
+            Message::Ready => {
+                self.peer_ready = true;
+                Ok(())
+            }
+            Message::Bye => Err(NetError::Closed),
+            msg => Err(NetError::Unexpected(msg)),
+        }
+    }
+
+    fn generate(&mut self, game_mode: GameMode, algo: MazeGenAlgo, seed: u64) {
+        let props = GameProperities {
+            game_mode,
+            generator: algo.to_fn(),
+            seed: Some(seed),
+        };
+        self.status = format!("Generating maze: {:?}", game_mode.size);
+        self.state = match RunningGame::new_threaded(props) {
+            Ok(comm) => Some(LobbyState::Generating(comm)),
+            Err(err) => {
+                log::error!("Could not generate the maze of the race: {:?}", err);
+                None
+            }
+        };
+    }
+
+    fn advance(&mut self, data: &mut AppData) -> Result<Option<Change>, NetError> {
+        // messages after `Ready` are part of the race, they are left for the peer
+        while !self.peer_ready {
+            let Some(msg) = self.conn.as_ref().and_then(Connection::try_recv) else {
+                break;
+            };
+            self.handle(msg?, &data.settings)?;
+        }
+
+        self.state = match self.state.take() {
+            Some(LobbyState::Listening(listener)) => match net::accept(&listener)? {
+                Some(conn) => {
+                    self.conn = Some(conn);
+                    self.status = "Player connected".to_string();
+                    Some(LobbyState::Handshake)
+                }
+                None => Some(LobbyState::Listening(listener)),
+            },
+            Some(LobbyState::Connecting(rx)) => match rx.try_recv() {
+                Ok(conn) => {
+                    self.conn = Some(conn?);
+                    self.send(Message::Hello {
+                        version: PROTOCOL_VERSION,
+                    })?;
+                    self.status = "Waiting for the maze".to_string();
+                    Some(LobbyState::Handshake)
+                }
+                Err(_) => Some(LobbyState::Connecting(rx)),
+            },
+            Some(LobbyState::Generating(comm)) if comm.handle.is_finished() => match comm.wait() {
+                Ok(game) => {
+                    self.send(Message::Ready)?;
+                    self.status = "Waiting for the other player".to_string();
+                    Some(LobbyState::Ready(Box::new(game)))
+                }
+                Err(err) => {
+                    log::error!("Could not generate the maze of the race: {:?}", err);
+                    None
+                }
+            },
+            Some(LobbyState::Ready(game)) if self.peer_ready => {
+                let rules = GameRules {
+                    online: true,
+                    ..Default::default()
+                };
+                let peer = Peer::new(self.conn.take().unwrap(), game.get_player_pos());
+                let game = GameData::new(*game, rules, 0, &data.settings);
+                let activity = GameActivity::new(game, data).with_peer(peer);
+                return Ok(Some(Change::replace(Activity::new_base_boxed(
+                    "game", activity,
+                ))));
+            }
+            state => state,
+        };
+
+        match self.state {
+            Some(_) => Ok(None),
+            None => Ok(Some(Change::replace(Activity::new_base_boxed(
+                "online failed",
+                Popup::new(
+                    "Online race failed".to_string(),
+                    vec!["Maze could not be generated".to_string()],
+                ),
+            )))),
+        }
+    }
+}
+
+impl ActivityHandler for Lobby {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        for event in events {
+            if let Event::Term(TermEvent::Key(KeyEvent {
+                code: KeyCode::Esc,
+                kind,
+                ..
+            })) = event
+            {
+                if !is_release(kind) {
+                    return Some(Change::pop_top());
+                }
+            }
+        }
+
+        self.advance(data).unwrap_or_else(|err| Some(failed(err)))
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+}
+
+impl Screen for Lobby {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let size = Dims(self.status.len().max(20) as i32 + 4, 4);
+        let pos = center_box_in_screen(size, frame.size);
+
+        draw_box(frame, pos, size, theme["ui_menu_border"]);
+        frame.draw(pos + Dims(3, 0), " Online race ", theme["ui_menu_title"]);
+        frame.draw(
+            pos + Dims(2, 1),
+            self.status.as_str(),
+            theme["ui_menu_text"],
+        );
+        frame.draw(pos + Dims(2, 2), "Esc: cancel", theme["dim"]);
+
+        Ok(())
+    }
+}
+
+/// Maze sent by the host has to fit the sides of a preset and the memory
+fn check_size(size: Dims3D, settings: &Settings) -> Result<(), NetError> {
+    let side = 1..=u16::MAX as i32;
+    let in_range = [size.0, size.1, size.2].iter().all(|s| side.contains(s));
+    if !in_range {
+        return Err(NetError::MazeTooBig(size));
+    }
+
+    let check = MemoryCheck::new(size, settings.get_memory_budget(), available_memory());
+    if check != MemoryCheck::Fits {
+        log::warn!("Maze of the race needs too much memory: {:?}", check);
+        return Err(NetError::MazeTooBig(size));
+    }
+    Ok(())
+}
+
+fn failed(err: NetError) -> Change {
+    Change::replace(ErrorPopup::new_activity(&err.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use cmaze::{
+        game::GoalObjective,
+        gameboard::{CellWall, Maze},
+    };
+
+    use super::*;
+
+    fn connected() -> (Connection, Connection) {
+        let listener = net::listen(0).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = Connection::connect(&format!("127.0.0.1:{}", port)).unwrap();
+        loop {
+            if let Some(host) = net::accept(&listener).unwrap() {
+                return (client, host);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn positions_outside_of_the_maze_are_ignored() {
+        let mut maze = Maze::new_walled(Dims3D(3, 3, 1), false);
+        maze.remove_wall(Dims3D(0, 0, 0), CellWall::Right);
+        let game = RunningGame::from_maze(
+            maze,
+            Dims3D(0, 0, 0),
+            vec![Dims3D(2, 2, 0)],
+            GoalObjective::ReachAny,
+            0,
+            MazeGenAlgo::RandomKruskals.to_fn(),
+        );
+
+        let (client, mut host) = connected();
+        let mut peer = Peer::new(client, Dims3D(0, 0, 0));
+
+        let far = Dims3D(i32::MAX, i32::MIN, 0);
+        host.send(&Message::Position { pos: far }).unwrap();
+        host.send(&Message::Position {
+            pos: Dims3D(1, 0, 0),
+        })
+        .unwrap();
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while peer.pos() != Some(Dims3D(1, 0, 0)) {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "position not received"
+            );
+            assert_ne!(peer.pos(), Some(far));
+            peer.update(&game);
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn too_big_mazes_are_rejected() {
+        let settings = Settings::default();
+        assert!(check_size(Dims3D(10, 10, 1), &settings).is_ok());
+        for size in [
+            Dims3D(0, 10, 1),
+            Dims3D(-5, 10, 1),
+            Dims3D(100_000, 10, 1),
+            Dims3D(u16::MAX as i32, u16::MAX as i32, u16::MAX as i32),
+        ] {
+            assert!(matches!(
+                check_size(size, &settings),
+                Err(NetError::MazeTooBig(_))
+            ));
+        }
+    }
+}
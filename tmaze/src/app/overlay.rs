@@ -0,0 +1,138 @@
+//! Stats of the current run written to text files, so streaming software like OBS can show them
+//!
+//! Every stat has its own file in the overlay directory, see
+//! [`crate::settings::Settings::get_overlay_dir`]. Files are written by jobs, at most once per
+//! [`WRITE_INTERVAL`] and only when a stat changed, the last stats are written when the game
+//! is left.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use cmaze::game::RunningGame;
+
+use crate::ui::helpers::smart_format_duration;
+
+use super::jobs::{Job, Qer};
+
+/// Shortest time between two writes of the files
+pub const WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stats of the run as they are written, one file each
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlayStats {
+    pub time: String,
+    pub moves: String,
+    pub preset: String,
+    /// Difference from the personal best, negative while the run is faster
+    pub pb_delta: String,
+}
+
+impl OverlayStats {
+    pub fn new(game: &RunningGame, preset: &str, best: Option<Duration>) -> Self {
+        let elapsed = game.get_elapsed().unwrap_or_default();
+        let pb_delta = match best {
+            Some(best) if elapsed >= best => {
+                format!("+{}", smart_format_duration(elapsed - best, true))
+            }
+            Some(best) => format!("-{}", smart_format_duration(best - elapsed, true)),
+            None => "no PB".to_string(),
+        };
+
+        Self {
+            time: smart_format_duration(elapsed, true),
+            moves: game.get_move_count().to_string(),
+            preset: preset.to_string(),
+            pb_delta,
+        }
+    }
+
+    fn files(&self) -> [(&'static str, &str); 4] {
+        [
+            ("time.txt", &self.time),
+            ("moves.txt", &self.moves),
+            ("preset.txt", &self.preset),
+            ("pb_delta.txt", &self.pb_delta),
+        ]
+    }
+}
+
+/// Writes the stats of one game, the pending ones are written when it's dropped
+pub struct OverlayFiles {
+    dir: PathBuf,
+    qer: Qer,
+    /// Name of the preset or the size of the maze
+    preset: String,
+    /// Game time of the personal best
+    best: Option<Duration>,
+    written: Option<OverlayStats>,
+    pending: Option<OverlayStats>,
+    last_write: Option<Instant>,
+}
+
+impl OverlayFiles {
+    pub fn new(dir: PathBuf, qer: Qer, preset: String, best: Option<Duration>) -> Self {
+        Self {
+            dir,
+            qer,
+            preset,
+            best,
+            written: None,
+            pending: None,
+            last_write: None,
+        }
+    }
+
+    /// Queues a write of the stats, if they changed and the last write isn't too recent
+    pub fn update(&mut self, game: &RunningGame) {
+        let stats = OverlayStats::new(game, &self.preset, self.best);
+        if self.written.as_ref() == Some(&stats) {
+            self.pending = None;
+            return;
+        }
+        self.pending = Some(stats);
+
+        if self
+            .last_write
+            .is_some_and(|last| last.elapsed() < WRITE_INTERVAL)
+        {
+            return;
+        }
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        let Some(stats) = self.pending.take() else {
+            return;
+        };
+
+        self.last_write = Some(Instant::now());
+        self.written = Some(stats.clone());
+
+        let dir = self.dir.clone();
+        self.qer.queue(
+            Job::new(move |_| {
+                if let Err(err) = write(&dir, &stats) {
+                    log::warn!("Could not write the overlay files: {}", err);
+                }
+            })
+            .named("overlay files".to_string()),
+        );
+    }
+}
+
+impl Drop for OverlayFiles {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+fn write(dir: &Path, stats: &OverlayStats) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, text) in stats.files() {
+        fs::write(dir.join(name), text)?;
+    }
+    Ok(())
+}
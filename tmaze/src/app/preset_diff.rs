@@ -0,0 +1,214 @@
+//! Changes of the maze presets since they were last seen
+//!
+//! Best results are stored by the game mode of the preset, so when a preset is edited in the
+//! settings, its records stay with the old game mode. The size menu lists the changed presets
+//! once, with what changed and whether the records still apply.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use cmaze::dims::*;
+
+use crate::{
+    data::SaveData,
+    settings::MazePreset,
+    ui::{format_duration, Popup},
+};
+
+use super::Activity;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetChange {
+    Changed {
+        title: String,
+        old: GameMode,
+        new: GameMode,
+    },
+    /// Same game mode under another title, records still apply
+    Renamed {
+        old: String,
+        new: String,
+    },
+    Removed {
+        title: String,
+        old: GameMode,
+    },
+}
+
+/// Game modes of the presets by their title
+pub fn preset_modes(presets: &[MazePreset]) -> BTreeMap<String, GameMode> {
+    presets
+        .iter()
+        .map(|preset| (preset.title.clone(), preset.game_mode()))
+        .collect()
+}
+
+/// Changes from the `seen` presets to the `current` ones, new presets are not listed
+pub fn diff(
+    seen: &BTreeMap<String, GameMode>,
+    current: &BTreeMap<String, GameMode>,
+) -> Vec<PresetChange> {
+    seen.iter()
+        .filter_map(|(title, &old)| match current.get(title) {
+            Some(&new) if new == old => None,
+            Some(&new) => Some(PresetChange::Changed {
+                title: title.clone(),
+                old,
+                new,
+            }),
+            None => {
+                let renamed = current
+                    .iter()
+                    .find(|(new_title, &mode)| mode == old && !seen.contains_key(*new_title));
+                Some(match renamed {
+                    Some((new_title, _)) => PresetChange::Renamed {
+                        old: title.clone(),
+                        new: new_title.clone(),
+                    },
+                    None => PresetChange::Removed {
+                        title: title.clone(),
+                        old,
+                    },
+                })
+            }
+        })
+        .collect()
+}
+
+fn size_text(mode: &GameMode) -> String {
+    let Dims3D(w, h, d) = mode.size;
+    match (d, mode.is_tower) {
+        (1, _) => format!("{}x{}", w, h),
+        (_, false) => format!("{}x{}x{}", w, h, d),
+        (_, true) => format!("{}x{}x{} tower", w, h, d),
+    }
+}
+
+/// Lines with the changed parameters of the game mode
+pub fn mode_diff(old: &GameMode, new: &GameMode) -> Vec<String> {
+    let mut lines = vec![];
+    if old.size != new.size || old.is_tower != new.is_tower {
+        lines.push(format!("size: {} -> {}", size_text(old), size_text(new)));
+    }
+    if old.goals != new.goals {
+        lines.push(format!("goals: {:?} -> {:?}", old.goals, new.goals));
+    }
+    if old.objective != new.objective {
+        lines.push(format!(
+            "objective: {:?} -> {:?}",
+            old.objective, new.objective
+        ));
+    }
+    if old.braid != new.braid {
+        lines.push(format!("braid: {}% -> {}%", old.braid, new.braid));
+    }
+    if old.braid_floors != new.braid_floors {
+        lines.push("braided floors changed".to_string());
+    }
+    if old.items != new.items {
+        lines.push(format!(
+            "items: {} coins{} -> {} coins{}",
+            old.items.coins,
+            if old.items.door { " and door" } else { "" },
+            new.items.coins,
+            if new.items.door { " and door" } else { "" },
+        ));
+    }
+    lines
+}
+
+fn best_text(save: &SaveData, mode: GameMode) -> Option<String> {
+    let best = save.get_best_result(mode)?;
+    Some(format!(
+        "{}, {} moves",
+        format_duration(Duration::from_secs_f32(best.seconds)),
+        best.moves
+    ))
+}
+
+/// Popup with the changes and what happens to the best results
+pub fn popup(changes: &[PresetChange], save: &SaveData) -> Activity {
+    let mut lines = vec![];
+    for change in changes {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        match change {
+            PresetChange::Changed { title, old, new } => {
+                lines.push(format!("~ {}", title));
+                lines.extend(mode_diff(old, new).into_iter().map(|l| format!(" {}", l)));
+                match best_text(save, *old) {
+                    Some(best) => lines.push(format!(" Best {} is kept for the old one", best)),
+                    None => lines.push(" No best result is affected".to_string()),
+                }
+                if let Some(best) = best_text(save, *new) {
+                    lines.push(format!(" New one already has best {}", best));
+                }
+            }
+            PresetChange::Renamed { old, new } => {
+                lines.push(format!("~ {} -> {}", old, new));
+                lines.push(" Same maze, best result still applies".to_string());
+            }
+            PresetChange::Removed { title, old } => {
+                lines.push(format!("~ {} (removed)", title));
+                if let Some(best) = best_text(save, *old) {
+                    lines.push(format!(" Best {} is kept, if it comes back", best));
+                }
+            }
+        }
+    }
+
+    Activity::new_base_boxed(
+        "preset changes",
+        Popup::new("Presets changed".to_string(), lines),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(w: i32) -> GameMode {
+        GameMode {
+            size: Dims3D(w, 10, 1),
+            is_tower: false,
+            goals: Default::default(),
+            objective: Default::default(),
+            braid: 0,
+            braid_floors: 0,
+            items: Default::default(),
+        }
+    }
+
+    fn modes(list: &[(&str, i32)]) -> BTreeMap<String, GameMode> {
+        list.iter()
+            .map(|(t, w)| (t.to_string(), mode(*w)))
+            .collect()
+    }
+
+    #[test]
+    fn changed_renamed_and_removed_presets() {
+        let seen = modes(&[("a", 10), ("b", 20), ("c", 30), ("d", 40)]);
+        let current = modes(&[("a", 10), ("b", 25), ("e", 30), ("f", 50)]);
+
+        assert_eq!(
+            diff(&seen, &current),
+            [
+                PresetChange::Changed {
+                    title: "b".to_string(),
+                    old: mode(20),
+                    new: mode(25),
+                },
+                PresetChange::Renamed {
+                    old: "c".to_string(),
+                    new: "e".to_string(),
+                },
+                PresetChange::Removed {
+                    title: "d".to_string(),
+                    old: mode(40),
+                },
+            ]
+        );
+        assert_eq!(mode_diff(&mode(20), &mode(25)), ["size: 20x10 -> 25x10"]);
+    }
+}
@@ -0,0 +1,225 @@
+//! Local race of two players on the same maze, in split screen
+//!
+//! Every player has their own copy of the game, so the maze, timers and moves are
+//! independent. First player to finish wins, the result is not saved.
+
+use std::io;
+
+use cmaze::{
+    dims::*,
+    game::{MoveMode, RunningGame, RunningGameState},
+    gameboard::CellWall,
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+
+use crate::{
+    helpers::{is_release, maze2screen},
+    renderer::Frame,
+    settings::{theme::Theme, Settings},
+    ui::{helpers::format_duration, Popup, Rect, Screen},
+};
+
+use super::{
+    app::AppData,
+    game::{MazeBoard, QuitConfirmActivity, QuitTarget, RunSummary},
+    Activity, ActivityHandler, Change, Event,
+};
+
+/// Characters of the players, their numbers
+const PLAYER_CHARS: [char; 2] = ['1', '2'];
+const CONTROLS: [&str; 2] = ["WASD, Q/E stairs", "arrows, PgDn/PgUp stairs"];
+
+/// Player and direction of the move bound to the key
+fn key_move(code: KeyCode) -> Option<(usize, CellWall)> {
+    use CellWall::*;
+
+    let bound = match code {
+        KeyCode::Char('w' | 'W') => (0, Top),
+        KeyCode::Char('a' | 'A') => (0, Left),
+        KeyCode::Char('s' | 'S') => (0, Bottom),
+        KeyCode::Char('d' | 'D') => (0, Right),
+        KeyCode::Char('q' | 'Q') => (0, Down),
+        KeyCode::Char('e' | 'E') => (0, Up),
+        KeyCode::Up => (1, Top),
+        KeyCode::Left => (1, Left),
+        KeyCode::Down => (1, Bottom),
+        KeyCode::Right => (1, Right),
+        KeyCode::PageDown => (1, Down),
+        KeyCode::PageUp => (1, Up),
+        _ => return None,
+    };
+    Some(bound)
+}
+
+pub struct RaceActivity {
+    games: [RunningGame; 2],
+    maze_board: MazeBoard,
+}
+
+impl RaceActivity {
+    pub fn new(game: RunningGame, data: &AppData) -> Self {
+        let maze_board = MazeBoard::new(&game, &data.theme);
+        let second = game.restarted();
+
+        Self {
+            games: [game, second],
+            maze_board,
+        }
+    }
+
+    fn move_player(&mut self, player: usize, dir: CellWall, settings: &Settings) {
+        let mode = match settings.get_slow() {
+            true => MoveMode::Slow,
+            false => MoveMode::Normal,
+        };
+        let auto_up = !settings.get_disable_tower_auto_up();
+
+        // game can already be finished by an earlier event
+        let _ = self.games[player].move_player(dir, mode, auto_up);
+    }
+
+    fn pause(&mut self) {
+        for game in &mut self.games {
+            let _ = game.pause();
+        }
+    }
+
+    /// Results of both players, after the winner finished
+    fn finish(&mut self, winner: usize) -> Change {
+        self.pause();
+
+        let lines: Vec<_> = self
+            .games
+            .iter()
+            .enumerate()
+            .map(|(i, game)| {
+                let status = match i == winner {
+                    true => format!(
+                        "finished in {}",
+                        format_duration(game.get_elapsed().unwrap_or_default())
+                    ),
+                    false => "didn't finish".to_string(),
+                };
+                format!(
+                    "Player {}: {}, {} moves",
+                    i + 1,
+                    status,
+                    game.get_move_count()
+                )
+            })
+            .collect();
+
+        let popup = Popup::new(format!("Player {} won", winner + 1), lines);
+        Change::replace_at(1, Activity::new_base_boxed("race result", popup))
+    }
+
+    fn draw_player(&self, frame: &mut Frame, theme: &Theme, area: Rect, player: usize) {
+        let game = &self.games[player];
+        let pos = game.get_player_pos();
+        let floor = &self.maze_board.floors(0)[pos.2 as usize];
+
+        let title = format!("Player {} - {}", player + 1, CONTROLS[player]);
+        frame.draw(area.start + Dims(1, 0), title, theme["text"]);
+        let status = format!(
+            "{} moves  {}  floor {}",
+            game.get_move_count(),
+            format_duration(game.get_elapsed().unwrap_or_default()),
+            pos.2 + 1
+        );
+        frame.draw(Dims(area.start.0 + 1, area.end.1), status, theme["text"]);
+
+        // viewport follows the player on the axes where the floor doesn't fit
+        let inner = area.margin(Dims(1, 2));
+        let vp_size = Dims(
+            floor.size.0.min(inner.size().0),
+            floor.size.1.min(inner.size().1),
+        );
+        let player_pos = maze2screen(pos);
+        let follow = |fits: bool, vp: i32, player: i32| match fits {
+            true => 0,
+            false => vp / 2 - player,
+        };
+        let maze_pos = Dims(
+            follow(floor.size.0 <= vp_size.0, vp_size.0, player_pos.0),
+            follow(floor.size.1 <= vp_size.1, vp_size.1, player_pos.1),
+        );
+
+        let mut viewport = Frame::new(vp_size);
+        viewport.draw(maze_pos, floor, ());
+
+        // other player first, so this one is on top
+        for (i, other) in self.games.iter().enumerate().rev() {
+            let other_pos = other.get_player_pos();
+            if other_pos.2 != pos.2 {
+                continue;
+            }
+            let style = match i == player {
+                true => theme["game.player"],
+                false => theme["game.opponent"],
+            };
+            viewport.draw(maze_pos + maze2screen(other_pos), PLAYER_CHARS[i], style);
+        }
+
+        let vp_pos = inner.start + (inner.size() - vp_size) / 2;
+        Rect::sized_at(vp_pos, vp_size)
+            .margin(Dims(-1, -1))
+            .render(frame, theme["game.viewport.border"]);
+        frame.draw(vp_pos, &viewport, ());
+    }
+}
+
+impl ActivityHandler for RaceActivity {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        for game in &mut self.games {
+            match game.get_state() {
+                RunningGameState::NotStarted => game.start().unwrap(),
+                RunningGameState::Paused => game.resume().unwrap(),
+                _ => {}
+            }
+        }
+
+        for event in events {
+            let Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+            if is_release(kind) {
+                continue;
+            }
+
+            if code == KeyCode::Esc {
+                if !data.settings.get_confirm_quit() {
+                    return Some(Change::pop_until("main menu"));
+                }
+
+                self.pause();
+                return Some(Change::push(QuitConfirmActivity::new_activity(
+                    RunSummary::new(&self.games[0]),
+                    QuitTarget::MainMenu,
+                )));
+            }
+
+            if let Some((player, dir)) = key_move(code) {
+                self.move_player(player, dir, &data.settings);
+            }
+        }
+
+        let winner = self
+            .games
+            .iter()
+            .position(|game| game.get_state() == RunningGameState::Finished)?;
+        Some(self.finish(winner))
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+}
+
+impl Screen for RaceActivity {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let (left, right) = Rect::sized(frame.size).split_x(Offset::Rel(0.5));
+        self.draw_player(frame, theme, left, 0);
+        self.draw_player(frame, theme, right, 1);
+        Ok(())
+    }
+}
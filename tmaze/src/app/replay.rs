@@ -0,0 +1,389 @@
+//! Recorded runs, saved after a win and played back from the main menu
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use cmaze::{
+    dims::*,
+    game::{Replay, RunningGame},
+    gameboard::CellWall,
+    signature::{self, Verified, VerifyError},
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::{constants, constants::paths::replay_path, is_release, maze2screen, Zoom},
+    renderer::Frame,
+    settings::{theme::Theme, MazeGenAlgo},
+    ui::{format_duration, Hint, Menu, MenuConfig, Popup, Rect, Screen},
+};
+
+use super::{
+    annotations::{draw_annotations, Annotation, Layer, Mark},
+    app::{AppData, TICK},
+    game::MazeBoard,
+    Activity, ActivityHandler, Change, Event,
+};
+
+/// Oldest replays are removed when there are more of them
+const MAX_REPLAYS: usize = 50;
+
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+const SEEK_STEP: Duration = Duration::from_secs(5);
+
+/// Replay as it's saved in the replays directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFile {
+    pub date: DateTime<Local>,
+    pub replay: Replay,
+    /// Integrity signature, see [`cmaze::signature`], older replays don't have it
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl ReplayFile {
+    /// Checks the signature and the replay itself, replays without a signature aren't valid
+    pub fn verify(&self) -> Result<Verified, VerifyError> {
+        let signature = self.signature.as_ref().ok_or(VerifyError::BadSignature)?;
+        signature::verify(&self.replay, signature, signature::KEY)
+    }
+
+    fn describe(&self) -> String {
+        let Dims3D(w, h, d) = self.replay.game_mode.size;
+        let size = match d {
+            1 => format!("{}x{}", w, h),
+            _ => format!("{}x{}x{}", w, h, d),
+        };
+        let mut text = format!(
+            "{} - {} - {}, {} moves",
+            self.date.format("%d %b %H:%M"),
+            size,
+            format_duration(self.replay.duration()),
+            self.replay.times.len(),
+        );
+        if self.signature.is_some() && self.verify().is_err() {
+            text += " (modified)";
+        }
+        text
+    }
+}
+
+/// Saves replay of the game, removes the oldest ones over the limit
+pub fn save_replay(game: &RunningGame) -> io::Result<PathBuf> {
+    let date = Local::now();
+    let replay = game.replay();
+    let file = ReplayFile {
+        date,
+        signature: Some(signature::sign(&replay, signature::KEY)),
+        replay,
+    };
+
+    let dir = replay_path();
+    let path = dir.join(format!("replay-{}.ron", date.format("%Y%m%d-%H%M%S")));
+    let text = ron::to_string(&file).map_err(io::Error::other)?;
+
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, text)?;
+
+    for (old, _) in load_replays().into_iter().skip(MAX_REPLAYS) {
+        fs::remove_file(old)?;
+    }
+
+    Ok(path)
+}
+
+/// Saved replays, newest first, invalid ones are skipped
+pub fn load_replays() -> Vec<(PathBuf, ReplayFile)> {
+    let Ok(entries) = fs::read_dir(replay_path()) else {
+        return vec![];
+    };
+
+    let mut replays: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .filter_map(|path| Some((path.clone(), read_replay(&path)?)))
+        .collect();
+    replays.sort_by_key(|(_, r)| std::cmp::Reverse(r.date));
+    replays
+}
+
+fn read_replay(path: &Path) -> Option<ReplayFile> {
+    let content = fs::read_to_string(path).ok()?;
+    ron::from_str(&content)
+        .inspect_err(|err| log::warn!("Invalid replay {:?}: {}", path, err))
+        .ok()
+}
+
+/// List of the saved replays
+pub struct ReplaysMenu {
+    menu: Menu,
+    replays: Vec<ReplayFile>,
+}
+
+impl ReplaysMenu {
+    pub fn new_activity() -> Activity {
+        let replays: Vec<_> = load_replays().into_iter().map(|(_, r)| r).collect();
+
+        if replays.is_empty() {
+            return Activity::new_base_boxed(
+                "replays",
+                Popup::new(
+                    "Replays".to_string(),
+                    vec!["No replays yet, win a game to record one".to_string()],
+                ),
+            );
+        }
+
+        let options: Vec<_> = replays.iter().map(ReplayFile::describe).collect();
+        let menu_config = MenuConfig::new_from_strings("Replays", options);
+
+        Activity::new_base_boxed(
+            "replays",
+            Self {
+                menu: Menu::new(menu_config),
+                replays,
+            },
+        )
+    }
+}
+
+impl ActivityHandler for ReplaysMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                match ReplayActivity::new(&self.replays[index].replay, data) {
+                    Ok(replay) => Some(Change::push(Activity::new_base_boxed("replay", replay))),
+                    Err(err) => {
+                        log::error!("Could not play the replay: {}", err);
+                        None
+                    }
+                }
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+/// Plays back a replay, it can be paused, sped up and seeked
+pub struct ReplayActivity {
+    replay: Replay,
+    steps: Vec<(Duration, CellWall)>,
+    game: RunningGame,
+    board: MazeBoard,
+    player_char: char,
+
+    /// Number of steps already applied
+    applied: usize,
+    time: Duration,
+    speed: usize,
+    paused: bool,
+}
+
+impl ReplayActivity {
+    pub fn new(replay: &Replay, data: &AppData) -> Result<Self, cmaze::gameboard::DecodeError> {
+        let steps = replay.steps()?;
+        let game = Self::fresh_game(replay)?;
+        let board = MazeBoard::new(&game, &data.theme);
+
+        Ok(Self {
+            replay: replay.clone(),
+            steps,
+            game,
+            board,
+            player_char: constants::get_random_player_char(),
+            applied: 0,
+            time: Duration::ZERO,
+            speed: 2,
+            paused: false,
+        })
+    }
+
+    fn fresh_game(replay: &Replay) -> Result<RunningGame, cmaze::gameboard::DecodeError> {
+        RunningGame::from_replay(replay, MazeGenAlgo::default().to_fn())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.applied == self.steps.len()
+    }
+
+    /// Applies all the steps made until the current time
+    fn catch_up(&mut self) {
+        while let Some(&(time, dir)) = self.steps.get(self.applied) {
+            if time > self.time {
+                break;
+            }
+
+            if !self.game.replay_step(dir, time) {
+                log::warn!("Replay doesn't match the maze, stopping");
+                self.steps.truncate(self.applied);
+                break;
+            }
+            self.applied += 1;
+        }
+
+        if self.is_finished() {
+            self.paused = true;
+        }
+    }
+
+    fn seek(&mut self, time: Duration) {
+        if time < self.time {
+            self.game = Self::fresh_game(&self.replay).expect("replay was already decoded");
+            self.applied = 0;
+        }
+
+        self.time = time.min(self.replay.duration());
+        self.catch_up();
+    }
+
+    fn toggle_pause(&mut self) {
+        if self.is_finished() {
+            self.seek(Duration::ZERO);
+            self.paused = false;
+        } else {
+            self.paused = !self.paused;
+        }
+    }
+
+    fn status(&self) -> String {
+        format!(
+            "{} {}x  {} / {}  step {}/{}",
+            if self.paused { "||" } else { ">" },
+            SPEEDS[self.speed],
+            format_duration(self.time),
+            format_duration(self.replay.duration()),
+            self.applied,
+            self.steps.len(),
+        )
+    }
+}
+
+impl ActivityHandler for ReplayActivity {
+    fn tick(&mut self, _data: &mut AppData) {
+        if !self.paused {
+            self.time += TICK.mul_f32(SPEEDS[self.speed]);
+            self.catch_up();
+        }
+    }
+
+    fn update(&mut self, events: Vec<Event>, _data: &mut AppData) -> Option<Change> {
+        for event in events {
+            let Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Char(' ') | KeyCode::Enter => self.toggle_pause(),
+                KeyCode::Right | KeyCode::Char('d') => self.seek(self.time + SEEK_STEP),
+                KeyCode::Left | KeyCode::Char('a') => {
+                    self.seek(self.time.saturating_sub(SEEK_STEP))
+                }
+                KeyCode::Up | KeyCode::Char('+' | '=' | 'w') => {
+                    self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+                }
+                KeyCode::Down | KeyCode::Char('-' | 's') => {
+                    self.speed = self.speed.saturating_sub(1);
+                }
+                KeyCode::Home => self.seek(Duration::ZERO),
+                KeyCode::End => self.seek(self.replay.duration()),
+                KeyCode::Esc | KeyCode::Char('q') => return Some(Change::pop_top()),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+}
+
+impl Screen for ReplayActivity {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let player = self.game.get_player_pos();
+        let maze_frame = &self.board.floors(0)[player.2 as usize];
+
+        // last two lines are for the status and controls
+        let area = Rect::sized(frame.size - Dims(0, 2)).margin(Dims(2, 1));
+        let area_size = area.size();
+        let fits = maze_frame.size.0 <= area_size.0 && maze_frame.size.1 <= area_size.1;
+        let (vp_size, maze_pos) = match fits {
+            true => (maze_frame.size, Dims(0, 0)),
+            false => (area_size, area_size / 2 - maze2screen(player)),
+        };
+
+        let mut viewport = Frame::new(vp_size);
+        viewport.draw(maze_pos, maze_frame, ());
+
+        let maze = self.game.get_maze();
+        let visited: Vec<_> = self
+            .game
+            .get_moves()
+            .iter()
+            .filter(|(pos, _)| {
+                let cell = maze.get_cell(*pos).unwrap();
+                cell.get_wall(CellWall::Up) && cell.get_wall(CellWall::Down)
+            })
+            .map(|(pos, _)| Annotation::cell(*pos, Mark::Marker('.'), "game.visited", Layer::Trail))
+            .collect();
+        draw_annotations(
+            &mut viewport,
+            maze_pos,
+            Zoom::Normal,
+            player.2,
+            maze,
+            theme,
+            &visited,
+        );
+        viewport.draw(
+            maze2screen(player) + maze_pos,
+            self.player_char,
+            theme["game.player"],
+        );
+
+        let vp_pos = (area_size - vp_size) / 2 + area.start;
+        Rect::sized_at(vp_pos, vp_size)
+            .margin(Dims(-1, -1))
+            .render(frame, theme["game.viewport.border"]);
+        frame.draw(vp_pos, &viewport, ());
+
+        let status = self.status();
+        let controls = "Space: pause, arrows: seek and speed, Esc: back";
+        let bottom = frame.size.1;
+        for (text, line, style) in [
+            (status.as_str(), bottom - 2, theme["text"]),
+            (controls, bottom - 1, theme["dim"]),
+        ] {
+            let x = (frame.size.0 - text.chars().count() as i32).max(0) / 2;
+            frame.draw(Dims(x, line), text, style);
+        }
+
+        Ok(())
+    }
+}
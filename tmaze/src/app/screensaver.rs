@@ -0,0 +1,338 @@
+//! Endlessly generated and solved mazes, shown as a screensaver or as a demo of the game
+//!
+//! The demo, called the attract mode, starts from the main menu when it's idle for
+//! [`attract_delay`](crate::settings::Settings::get_attract_delay) seconds. It uses the
+//! installed themes, a different one for every maze, and any key returns back to the menu.
+
+use std::io;
+
+use cmaze::{
+    dims::*,
+    game::ProgressComm,
+    gameboard::{
+        algorithms::{random_seed, GenErrorThreaded},
+        CellWall, Maze,
+    },
+};
+use crossterm::event::{Event as TermEvent, KeyEventKind};
+use rand::{seq::SliceRandom, thread_rng};
+
+use crate::{
+    content::{self, ContentKind},
+    helpers::{maze2screen, maze_render_size, path_lines, Zoom},
+    renderer::Frame,
+    settings::{
+        theme::{Color, NamedColor, Style, Theme, ThemeDefinition},
+        MazeGenAlgo, Settings,
+    },
+    ui::{center_box_in_screen, Screen},
+};
+
+use super::{
+    app::{init_theme_resolver, AppData},
+    game::MazeBoard,
+    Activity, ActivityHandler, Change, Event,
+};
+
+/// Biggest maze generated by the screensaver, in cells
+const MAX_SIZE: Dims = Dims(30, 15);
+/// Ticks the solved maze stays on the screen before the next one
+const HOLD_TICKS: usize = 40;
+/// Ticks the solved maze stays on the screen with reduced motion, it's shown all at once
+const CALM_HOLD_TICKS: usize = 200;
+
+/// Wall and path colors, one scheme per maze
+const SCHEMES: [(NamedColor, NamedColor); 5] = [
+    (NamedColor::Blue, NamedColor::Yellow),
+    (NamedColor::Green, NamedColor::Magenta),
+    (NamedColor::Cyan, NamedColor::Red),
+    (NamedColor::DarkGrey, NamedColor::Green),
+    (NamedColor::Magenta, NamedColor::Cyan),
+];
+
+/// Colors of the mazes, the next one is used for every new maze
+enum Palette {
+    /// Wall and path colors of [`SCHEMES`]
+    Schemes,
+    /// Installed themes with their names
+    Themes(Vec<(String, Theme)>),
+}
+
+impl Palette {
+    /// Enabled installed themes, the current one if there are none
+    fn installed_themes(settings: &Settings, current: &Theme) -> Self {
+        let resolver = init_theme_resolver();
+        let mut themes: Vec<_> = content::discover(ContentKind::Theme)
+            .into_iter()
+            .filter(|item| item.builtin || settings.is_content_enabled(&item.id()))
+            .filter_map(|item| {
+                let definition = ThemeDefinition::load_by_path(item.path)
+                    .inspect_err(|err| log::warn!("Demo skips theme {}: {}", item.name, err))
+                    .ok()?;
+                Some((item.name, resolver.resolve(&definition)))
+            })
+            .collect();
+
+        if themes.is_empty() {
+            themes.push(("Current theme".to_string(), current.clone()));
+        }
+        Palette::Themes(themes)
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Palette::Schemes => SCHEMES.len(),
+            Palette::Themes(themes) => themes.len(),
+        }
+    }
+}
+
+/// How the screensaver looks for the goal
+#[derive(Debug, Clone, Copy)]
+enum Solver {
+    /// Goes straight to the goal
+    Shortest,
+    /// Keeps the right hand on the wall
+    WallFollower,
+}
+
+impl Solver {
+    fn solve(self, maze: &Maze, from: Dims3D, to: Dims3D) -> Vec<Dims3D> {
+        match self {
+            Solver::Shortest => maze.solve(from, to).unwrap_or_default(),
+            Solver::WallFollower => Self::follow_wall(maze, from, to),
+        }
+    }
+
+    fn follow_wall(maze: &Maze, from: Dims3D, to: Dims3D) -> Vec<Dims3D> {
+        use CellWall::*;
+
+        // clockwise, so the next one is on the right
+        const DIRS: [CellWall; 4] = [Top, Right, Bottom, Left];
+
+        let limit = (maze.size().0 * maze.size().1 * 4) as usize;
+        let (mut pos, mut dir) = (from, 1);
+        let mut path = vec![pos];
+
+        while pos != to && path.len() < limit {
+            // try right, straight, left and back, in this order
+            for turn in [1, 0, 3, 2] {
+                let next_dir = (dir + turn) % 4;
+                if !maze.get_wall(pos, DIRS[next_dir]).unwrap() {
+                    dir = next_dir;
+                    pos += DIRS[dir].to_coord();
+                    path.push(pos);
+                    break;
+                }
+            }
+        }
+
+        path
+    }
+}
+
+enum Phase {
+    Generating(ProgressComm<Result<Maze, GenErrorThreaded>>),
+    Solving {
+        maze: Maze,
+        walls: Frame,
+        path: Vec<Dims3D>,
+        shown: usize,
+    },
+}
+
+/// Endlessly generates and solves small mazes, any key exits
+pub struct ScreensaverActivity {
+    phase: Option<Phase>,
+    palette: Palette,
+    scheme: usize,
+    hold: usize,
+    /// Started from the main menu, so it only pops itself
+    attract: bool,
+}
+
+impl ScreensaverActivity {
+    pub fn new() -> Self {
+        Self {
+            phase: None,
+            palette: Palette::Schemes,
+            scheme: 0,
+            hold: 0,
+            attract: false,
+        }
+    }
+
+    pub fn new_activity() -> Activity {
+        Activity::new_base_boxed("screensaver", Self::new())
+    }
+
+    /// Demo of the game using the installed themes, any key returns to the previous activity
+    pub fn new_attract_activity(data: &AppData) -> Activity {
+        let attract = Self {
+            palette: Palette::installed_themes(&data.settings, &data.theme),
+            attract: true,
+            ..Self::new()
+        };
+        Activity::new_base_boxed("attract", attract)
+    }
+
+    fn theme<'a>(&'a self, current: &'a Theme) -> &'a Theme {
+        match &self.palette {
+            Palette::Schemes => current,
+            Palette::Themes(themes) => &themes[self.scheme].1,
+        }
+    }
+
+    fn path_style(&self) -> Style {
+        match &self.palette {
+            Palette::Schemes => Style::fg(Color::Named(SCHEMES[self.scheme].1)),
+            Palette::Themes(themes) => themes[self.scheme].1["game.path"],
+        }
+    }
+
+    fn generate(screen_size: Dims) -> Phase {
+        let Dims(w, h) = screen_size;
+        let size = Dims3D(
+            ((w - 1) / 2).clamp(2, MAX_SIZE.0),
+            ((h - 1) / 2).clamp(2, MAX_SIZE.1),
+            1,
+        );
+
+        let algo = [MazeGenAlgo::RandomKruskals, MazeGenAlgo::DepthFirstSearch]
+            .choose(&mut thread_rng())
+            .unwrap();
+
+        let comm = (algo.to_fn())(size, false, random_seed()).expect("size is always valid");
+        Phase::Generating(comm)
+    }
+
+    fn start_solving(&self, maze: Maze, theme: &Theme) -> Phase {
+        let goal = maze.size() - Dims3D(1, 1, 1);
+        let solver = [Solver::Shortest, Solver::WallFollower]
+            .choose(&mut thread_rng())
+            .unwrap();
+
+        let mut walls = MazeBoard::render_floor(&maze, 0, Zoom::Normal, self.theme(theme));
+        if let Palette::Schemes = self.palette {
+            let wall_style = Style::fg(Color::Named(SCHEMES[self.scheme].0)).into();
+            for y in 0..walls.size.1 {
+                for x in 0..walls.size.0 {
+                    if let Some(content) = walls[Dims(x, y)].content_mut() {
+                        if content.character != ' ' {
+                            content.style = wall_style;
+                        }
+                    }
+                }
+            }
+        }
+
+        Phase::Solving {
+            path: solver.solve(&maze, Dims3D(0, 0, 0), goal),
+            walls,
+            maze,
+            shown: 0,
+        }
+    }
+
+    fn draw_path(frame: &mut Frame, pos: Dims, path: &[Dims3D], style: Style) {
+        let steps = path
+            .windows(2)
+            .filter_map(|pair| Some((pair[0], Maze::which_wall_between(pair[0], pair[1])?)));
+        for (at, (line, _)) in path_lines(steps, 0) {
+            frame.draw(pos + at, line.round(), style);
+        }
+
+        if let Some(&head) = path.last() {
+            frame.draw(pos + maze2screen(head), '●', style);
+        }
+    }
+}
+
+impl Default for ScreensaverActivity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityHandler for ScreensaverActivity {
+    fn tick(&mut self, data: &mut AppData) {
+        if let Some(Phase::Solving { path, shown, .. }) = &mut self.phase {
+            if data.settings.get_reduce_motion() {
+                *shown = path.len();
+            }
+
+            if *shown < path.len() {
+                *shown += 1;
+            } else {
+                self.hold += 1;
+            }
+        }
+    }
+
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        for event in events {
+            if let Event::Term(TermEvent::Key(key)) = event {
+                if key.kind != KeyEventKind::Release {
+                    return Some(match self.attract {
+                        true => Change::pop_top(),
+                        false => Change::pop_all(),
+                    });
+                }
+            }
+        }
+
+        let hold = match data.settings.get_reduce_motion() {
+            true => CALM_HOLD_TICKS,
+            false => HOLD_TICKS,
+        };
+
+        self.phase = match self.phase.take() {
+            None => Some(Self::generate(data.screen_size)),
+            Some(Phase::Generating(comm)) if comm.handle.is_finished() => match comm.wait() {
+                Ok(maze) => Some(self.start_solving(maze, &data.theme)),
+                Err(err) => {
+                    log::error!("Screensaver failed to generate a maze: {:?}", err);
+                    None
+                }
+            },
+            Some(Phase::Solving { .. }) if self.hold >= hold => {
+                self.hold = 0;
+                self.scheme = (self.scheme + 1) % self.palette.len();
+                None
+            }
+            phase => phase,
+        };
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+}
+
+impl Screen for ScreensaverActivity {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let Some(Phase::Solving {
+            maze,
+            walls,
+            path,
+            shown,
+        }) = &self.phase
+        else {
+            return Ok(());
+        };
+
+        let pos = center_box_in_screen(maze_render_size(maze, Zoom::Normal), frame.size);
+        frame.draw(pos, walls, ());
+        Self::draw_path(frame, pos, &path[..*shown], self.path_style());
+
+        if let (true, Palette::Themes(themes)) = (self.attract, &self.palette) {
+            let caption = format!("Demo - {} - press any key", themes[self.scheme].0);
+            let x = (frame.size.0 - caption.chars().count() as i32).max(0) / 2;
+            frame.draw(Dims(x, 0), caption.as_str(), self.theme(theme)["text"]);
+        }
+
+        Ok(())
+    }
+}
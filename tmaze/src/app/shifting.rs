@@ -0,0 +1,107 @@
+//! Shifting maze, walls of the player's floor move while the game runs
+//!
+//! Walls which are about to move blink for [`WARNING`] before the shift. Every shift opens
+//! a wall and closes another one on the loop it made, so the goals can always be reached,
+//! see [`cmaze::gameboard::Maze::plan_shifts`].
+
+use std::time::Duration;
+
+use cmaze::{dims::*, game::RunningGame, gameboard::WallShift};
+use rand::thread_rng;
+
+use crate::{
+    helpers::{maze2screen, Zoom},
+    renderer::Frame,
+    settings::theme::Theme,
+};
+
+use super::game_state::Shifting;
+
+/// How long before the shift the walls start blinking
+pub const WARNING: Duration = Duration::from_secs(2);
+
+const BLINK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftEvent {
+    None,
+    /// Shifts were planned and will happen after [`WARNING`]
+    Warning,
+    /// Walls were moved, so the maze has to be drawn again
+    Shifted,
+}
+
+/// Plans and applies the shifts, by the game time
+#[derive(Debug, Clone)]
+pub struct Shifter {
+    rules: Shifting,
+    /// Game time of the next shift
+    next: Duration,
+    planned: Vec<WallShift>,
+}
+
+impl Shifter {
+    pub fn new(rules: Shifting) -> Self {
+        Self {
+            rules,
+            next: rules.interval.max(WARNING),
+            planned: vec![],
+        }
+    }
+
+    pub fn update(&mut self, game: &mut RunningGame) -> ShiftEvent {
+        let Some(elapsed) = game.get_elapsed() else {
+            return ShiftEvent::None;
+        };
+
+        if elapsed >= self.next {
+            self.next += self.rules.interval.max(WARNING);
+            if self.planned.is_empty() {
+                return ShiftEvent::None;
+            }
+
+            game.shift_walls(&std::mem::take(&mut self.planned));
+            return ShiftEvent::Shifted;
+        }
+
+        if self.planned.is_empty() && elapsed + WARNING >= self.next {
+            let floor = game.get_player_pos().2;
+            self.planned = game
+                .get_maze()
+                .plan_shifts(floor, self.rules.walls, &mut thread_rng());
+            if !self.planned.is_empty() {
+                return ShiftEvent::Warning;
+            }
+        }
+
+        ShiftEvent::None
+    }
+
+    /// Blinking walls which are about to move, `maze_pos` is the position of the floor
+    pub fn draw(
+        &self,
+        frame: &mut Frame,
+        maze_pos: Dims,
+        zoom: Zoom,
+        floor: i32,
+        elapsed: Duration,
+        theme: &Theme,
+    ) {
+        if (elapsed.as_millis() / BLINK.as_millis()) % 2 == 1 {
+            return;
+        }
+
+        let style = theme["game.shift"];
+        for shift in &self.planned {
+            for ((pos, wall), ch) in [(shift.open, '░'), (shift.close, '▒')] {
+                if pos.2 != floor {
+                    continue;
+                }
+
+                let off = wall.to_coord();
+                let pos = zoom.board_pos(maze2screen(pos) + Dims(off.0, off.1));
+                frame.draw(maze_pos + pos, ch, style);
+            }
+        }
+    }
+}
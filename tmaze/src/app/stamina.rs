@@ -0,0 +1,144 @@
+//! Stamina, every move costs some and it comes back while the player stands still
+//!
+//! Running through the maze quickly empties the stamina, so the player has to stop and plan
+//! the route. Stamina comes back only after the player stood still for [`REST_DELAY`].
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How long the player has to stand still before the stamina starts coming back
+pub const REST_DELAY: Duration = Duration::from_millis(500);
+
+/// Rules of the stamina, set by the game mode or the maze preset
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stamina {
+    /// Most stamina the player can have, the run starts with it
+    pub max: f32,
+    /// Stamina used by a move by one cell
+    pub move_cost: f32,
+    /// Stamina regained per second of standing still
+    pub regen: f32,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            max: 10.0,
+            move_cost: 1.0,
+            regen: 2.0,
+        }
+    }
+}
+
+impl Stamina {
+    /// Rules with values which can't stop the player forever, e.g. a move costing more than
+    /// the maximum or no regeneration
+    pub fn sanitized(self) -> Self {
+        let max = self.max.max(1.0);
+        Self {
+            max,
+            move_cost: self.move_cost.clamp(0.0, max),
+            regen: self.regen.max(0.1),
+        }
+    }
+}
+
+/// Stamina of the player during the run
+#[derive(Debug, Clone)]
+pub struct StaminaMeter {
+    rules: Stamina,
+    value: f32,
+    /// Game time since the last move
+    resting: Duration,
+    /// How many times the stamina ran out
+    exhausted: usize,
+}
+
+impl StaminaMeter {
+    pub fn new(rules: Stamina) -> Self {
+        let rules = rules.sanitized();
+        Self {
+            rules,
+            value: rules.max,
+            resting: Duration::ZERO,
+            exhausted: 0,
+        }
+    }
+
+    /// Whether there is enough stamina for a move
+    pub fn can_move(&self) -> bool {
+        self.steps_left() > 0
+    }
+
+    /// How many cells the player can move by before the stamina runs out
+    pub fn steps_left(&self) -> usize {
+        match self.rules.move_cost > 0.0 {
+            true => (self.value / self.rules.move_cost) as usize,
+            false => usize::MAX,
+        }
+    }
+
+    /// Uses the stamina for a move over `steps` cells, returns `true` if it ran out
+    pub fn spend(&mut self, steps: usize) -> bool {
+        self.resting = Duration::ZERO;
+        self.value = (self.value - self.rules.move_cost * steps as f32).max(0.0);
+
+        let out = !self.can_move();
+        if out {
+            self.exhausted += 1;
+        }
+        out
+    }
+
+    /// Advances the game time by `dt` in which the player didn't move
+    pub fn rest(&mut self, dt: Duration) {
+        let before = self.resting;
+        self.resting += dt;
+
+        let rested = self.resting.saturating_sub(before.max(REST_DELAY));
+        self.value = (self.value + self.rules.regen * rested.as_secs_f32()).min(self.rules.max);
+    }
+
+    /// Stamina left, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        (self.value / self.rules.max) as f64
+    }
+
+    /// How many times the stamina ran out
+    pub fn exhausted(&self) -> usize {
+        self.exhausted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regenerates_only_when_standing_still() {
+        let mut meter = StaminaMeter::new(Stamina {
+            max: 3.0,
+            move_cost: 1.0,
+            regen: 2.0,
+        });
+
+        assert!(!meter.spend(1));
+        assert_eq!(meter.steps_left(), 2);
+        assert!(meter.spend(2));
+        assert!(!meter.can_move());
+        assert_eq!(meter.exhausted(), 1);
+
+        // nothing comes back before the delay
+        meter.rest(REST_DELAY);
+        assert!(!meter.can_move());
+
+        meter.rest(Duration::from_millis(500));
+        assert!(meter.can_move());
+        assert!((meter.fraction() - 1.0 / 3.0).abs() < 1e-6);
+
+        meter.rest(Duration::from_secs(10));
+        assert_eq!(meter.fraction(), 1.0);
+    }
+}
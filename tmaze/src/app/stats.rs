@@ -0,0 +1,216 @@
+//! Statistics of the finished runs, filtered by their tags, see [`super::tags`]
+//!
+//! Runs can also be limited to the ones played by the same rules and settings as the current
+//! ones, see [`crate::data::environment`].
+
+use std::{collections::HashMap, io, time::Duration};
+
+use cmaze::dims::*;
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    data::{
+        environment::Environment,
+        history::{RunRecord, RunStats},
+    },
+    helpers::is_release,
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+    ui::{center_box_in_screen, draw_box, format_duration, Hint, Screen},
+};
+
+use super::{app::AppData, tags::TagFilter, Activity, ActivityHandler, Change, Event};
+
+/// Most sizes of mazes listed, the most played ones
+const TOP_SIZES: usize = 5;
+
+pub struct StatsScreen {
+    history: Vec<RunRecord>,
+    filter: TagFilter,
+    /// Environment of a plain run with the current settings
+    current: Environment,
+    /// Only runs played the same way as [`StatsScreen::current`] are counted
+    same_gameplay: bool,
+    /// Label and value of every line, empty label is a gap
+    lines: Vec<(String, String)>,
+}
+
+impl StatsScreen {
+    pub fn new(data: &AppData) -> Self {
+        let history = data.save.history();
+        let mut screen = Self {
+            history: history.to_vec(),
+            filter: TagFilter::new(history),
+            current: Environment::new(&data.settings, data.screen_size, vec![]),
+            same_gameplay: false,
+            lines: vec![],
+        };
+        screen.update_lines();
+        screen
+    }
+
+    pub fn new_activity(data: &AppData) -> Activity {
+        Activity::new_base_boxed("stats", Self::new(data))
+    }
+
+    /// Runs saved before the environment was stored are left out with the same gameplay
+    fn counts(&self, run: &RunRecord) -> bool {
+        let same = |env: &Environment| env.same_gameplay(&self.current);
+        self.filter.matches(run) && (!self.same_gameplay || run.env.as_ref().is_some_and(same))
+    }
+
+    fn update_lines(&mut self) {
+        let runs: Vec<_> = self.history.iter().filter(|run| self.counts(run)).collect();
+        let stats = RunStats::of(runs.iter().copied());
+        let time = |secs: f32| format_duration(Duration::from_secs_f32(secs));
+
+        let mut lines = vec![
+            ("Runs".to_string(), stats.runs.to_string()),
+            ("Total time".to_string(), time(stats.total_seconds)),
+        ];
+        if let (Some(best), Some(avg), Some(moves)) =
+            (stats.best_seconds, stats.avg_seconds(), stats.avg_moves())
+        {
+            lines.extend([
+                ("Best time".to_string(), time(best)),
+                ("Average time".to_string(), time(avg)),
+                ("Average moves".to_string(), format!("{:.0}", moves)),
+            ]);
+        }
+        if stats.total_coins > 0 {
+            lines.push(("Coins".to_string(), stats.total_coins.to_string()));
+        }
+
+        let mut sizes = HashMap::<Dims3D, Vec<&RunRecord>>::new();
+        for run in &runs {
+            sizes.entry(run.game_mode.size).or_default().push(run);
+        }
+        let mut sizes: Vec<_> = sizes.into_iter().collect();
+        sizes.sort_by_key(|(Dims3D(w, h, d), runs)| (std::cmp::Reverse(runs.len()), *w, *h, *d));
+
+        if !sizes.is_empty() {
+            lines.push((String::new(), String::new()));
+        }
+        for (Dims3D(w, h, d), runs) in sizes.into_iter().take(TOP_SIZES) {
+            let stats = RunStats::of(runs);
+            let size = match d {
+                1 => format!("{}x{}", w, h),
+                _ => format!("{}x{}x{}", w, h, d),
+            };
+            let best = stats.best_seconds.map(time).unwrap_or_default();
+            lines.push((size, format!("{} runs, best {}", stats.runs, best)));
+        }
+
+        self.lines = lines;
+    }
+
+    fn size(&self) -> Dims {
+        let width = self
+            .lines
+            .iter()
+            .map(|(label, value)| label.width() + value.width() + 4)
+            .chain([self.header().width() + 4, self.footer().width()])
+            .max()
+            .unwrap_or(0);
+        Dims(width as i32 + 6, self.lines.len() as i32 + 5)
+    }
+
+    fn header(&self) -> String {
+        match self.same_gameplay {
+            true => format!(" Statistics - {} - same settings ", self.filter.label()),
+            false => format!(" Statistics - {} ", self.filter.label()),
+        }
+    }
+
+    fn footer(&self) -> &'static str {
+        match self.filter.has_tags() {
+            true => "←→: filter by tag, S: same settings, Esc: back",
+            false => "S: same settings, tag runs on the end screen with T",
+        }
+    }
+}
+
+impl ActivityHandler for StatsScreen {
+    fn update(&mut self, events: Vec<Event>, _: &mut AppData) -> Option<Change> {
+        for event in events {
+            match event {
+                Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) if !is_release(kind) => {
+                    match code {
+                        KeyCode::Left => self.filter.cycle(false),
+                        KeyCode::Right | KeyCode::Tab => self.filter.cycle(true),
+                        KeyCode::Char('s') => self.same_gameplay = !self.same_gameplay,
+                        _ => return Some(Change::pop_top()),
+                    }
+                    self.update_lines();
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        let same = Hint::new("S", "same settings");
+        match self.filter.has_tags() {
+            true => vec![
+                Hint::new("←→", "filter by tag"),
+                same,
+                Hint::new("Esc", "back"),
+            ],
+            false => vec![same, Hint::new("Any key", "back")],
+        }
+    }
+}
+
+impl Screen for StatsScreen {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let [border, title, text, dim] = theme.extract([
+            "ui.stats.border",
+            "ui.stats.title",
+            "ui.stats.text",
+            "ui.stats.dim",
+        ]);
+
+        let size = self.size();
+        let pos = center_box_in_screen(size, frame.size);
+
+        frame.fill_rect(pos, size, Cell::styled(' ', text));
+        draw_box(frame, pos, size, border);
+        frame.draw(pos + Dims(3, 0), self.header().as_str(), title);
+
+        for (i, (label, value)) in self.lines.iter().enumerate() {
+            let line = pos + Dims(3, 2 + i as i32);
+            frame.draw(line, label.as_str(), text);
+            let value_x = size.0 - 6 - value.width() as i32;
+            frame.draw(line + Dims(value_x, 0), value.as_str(), text);
+        }
+
+        let footer = self.footer();
+        let footer_x = (size.0 - footer.width() as i32) / 2;
+        frame.draw(pos + Dims(footer_x, size.1 - 2), footer, dim);
+
+        Ok(())
+    }
+}
+
+pub fn stats_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("ui.stats.border", "border")
+        .desc("Border of the statistics")
+        .link("ui.stats.title", "text")
+        .desc("Title of the statistics")
+        .link("ui.stats.text", "text")
+        .desc("Values of the statistics")
+        .link("ui.stats.dim", "dim")
+        .desc("Labels and empty parts of the statistics");
+
+    resolver
+}
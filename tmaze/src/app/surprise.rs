@@ -0,0 +1,155 @@
+//! "Surprise me" and the past surprises in the maze size menu, see [`crate::data::surprise`]
+//!
+//! A surprise goes straight to the generation, without asking for the algorithm. Past surprises
+//! can be played again with the same maze or saved as a preset.
+
+use cmaze::gameboard::algorithms::random_seed;
+
+use crate::{
+    data::surprise::{self, Surprise},
+    ui::{Hint, Menu, MenuConfig, Screen},
+};
+
+use super::{
+    app::AppData, game::MazeGenerationActivity, game_state::GameRules, Activity, ActivityHandler,
+    Change, Event,
+};
+
+/// Generates the maze of the surprise, it's the same every time
+pub fn play(surprise: &Surprise, rules: GameRules) -> Change {
+    let preset = &surprise.preset;
+    let rules = GameRules {
+        hardcore: preset.hardcore,
+        time_limit: preset.time_limit().or(rules.time_limit),
+        stamina: preset.stamina.or(rules.stamina),
+        preset: None,
+        ..rules
+    };
+
+    Change::push(Activity::new_base_boxed(
+        "maze_gen",
+        MazeGenerationActivity::new(preset.game_mode(), surprise.algorithm.to_fn())
+            .with_rules(rules)
+            .with_seed(surprise.seed),
+    ))
+}
+
+/// Composes a new surprise, saves it and generates its maze
+pub fn surprise_me(rules: GameRules, data: &mut AppData) -> Change {
+    let surprise = surprise::compose(random_seed(), &data.settings, data.save.history());
+    log::info!(
+        "Surprise {}: {}",
+        surprise.seed,
+        surprise::describe(&surprise.preset)
+    );
+    data.toasts
+        .push("Surprise", surprise::describe(&surprise.preset));
+
+    let change = play(&surprise, rules);
+    if !data.settings.is_ro() {
+        if let Err(err) = data.save.add_surprise(surprise) {
+            log::error!("Failed to save the surprise: {}", err);
+        }
+    }
+    change
+}
+
+/// Past surprises, newest first, a chosen one can be played or saved as a preset
+pub struct SurprisesMenu {
+    menu: Menu,
+    surprises: Vec<Surprise>,
+    rules: GameRules,
+    /// Surprise the actions are shown for
+    chosen: Option<usize>,
+}
+
+impl SurprisesMenu {
+    pub fn new(surprises: &[Surprise], rules: GameRules) -> Self {
+        let surprises: Vec<_> = surprises.iter().rev().cloned().collect();
+        let options: Vec<_> = surprises
+            .iter()
+            .map(|surprise| {
+                format!(
+                    "{} - {}",
+                    surprise.date.format("%d %b %H:%M"),
+                    surprise::describe(&surprise.preset)
+                )
+            })
+            .collect();
+        let menu = Menu::new(MenuConfig::new_from_strings("Past surprises", options));
+
+        Self {
+            menu,
+            surprises,
+            rules,
+            chosen: None,
+        }
+    }
+
+    fn actions(&mut self, index: usize) -> Change {
+        self.chosen = Some(index);
+        let title = surprise::describe(&self.surprises[index].preset);
+        let options = ["Play again".to_string(), "Save as preset".to_string()];
+        Change::push(Activity::new_base_boxed(
+            "surprise actions",
+            Menu::new(MenuConfig::new_from_strings(title, options)),
+        ))
+    }
+
+    /// Adds the surprise to the presets in the settings
+    fn save_as_preset(&self, index: usize, data: &mut AppData) {
+        let preset = self.surprises[index].preset.clone();
+        let mut mazes = data.settings.get_mazes();
+        if mazes.iter().any(|maze| maze.title == preset.title) {
+            data.toasts.push("Already a preset", preset.title);
+            return;
+        }
+
+        data.toasts.push("Saved as a preset", preset.title.clone());
+        mazes.push(preset);
+        data.settings.set_mazes(mazes);
+    }
+}
+
+impl ActivityHandler for SurprisesMenu {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        for event in &events {
+            let Event::ActiveAfterPop(res) = event else {
+                continue;
+            };
+            let Some(index) = self.chosen.take() else {
+                continue;
+            };
+
+            match res.as_ref().and_then(|res| res.downcast_ref::<usize>()) {
+                Some(0) => return Some(play(&self.surprises[index], self.rules)),
+                Some(_) => self.save_as_preset(index, data),
+                // the only surprise would be chosen again right away
+                None if self.surprises.len() == 1 => return Some(Change::pop_top()),
+                None => {}
+            }
+        }
+
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                Some(self.actions(index))
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
@@ -0,0 +1,215 @@
+//! Tagging of the finished runs and filtering by the tags, see [`crate::data::history`]
+
+use std::io;
+
+use chrono::{DateTime, Local};
+use cmaze::dims::*;
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+
+use crate::{
+    data::history::{all_tags, parse_tags, RunRecord},
+    helpers::is_release,
+    renderer::Frame,
+    settings::theme::Theme,
+    ui::{center_box_in_screen, draw_box, Hint, Screen, TextInput},
+};
+
+use super::{app::AppData, ActivityHandler, Change, Event};
+
+/// Result of [`TagDialog`], new tags of the run
+pub struct TagsSet(pub Vec<String>);
+
+/// Asks for the tags of the run, separated by commas
+pub struct TagDialog {
+    date: DateTime<Local>,
+    input: TextInput,
+    /// Tags of the last tagged run before this one, offered for recurring events
+    last_tags: Vec<String>,
+}
+
+impl TagDialog {
+    pub fn new(date: DateTime<Local>, tags: &[String], history: &[RunRecord]) -> Self {
+        let mut input = TextInput::new().with_placeholder("practice, race night");
+        input.set_value(tags.join(", "));
+
+        let last_tags = history
+            .iter()
+            .rev()
+            .filter(|run| run.date != date)
+            .find(|run| !run.tags.is_empty())
+            .map(|run| run.tags.clone())
+            .unwrap_or_default();
+
+        Self {
+            date,
+            input,
+            last_tags,
+        }
+    }
+
+    fn save(&self, data: &mut AppData) -> Change {
+        let tags = parse_tags(self.input.value());
+        if let Err(err) = data.save.set_run_tags(self.date, tags.clone()) {
+            log::error!("Failed to save the tags: {}", err);
+            return Change::pop_top();
+        }
+
+        match tags.is_empty() {
+            true => log::info!("Tags removed"),
+            false => log::info!("Tagged as {}", tags.join(", ")),
+        }
+        Change::pop_top_with(TagsSet(tags))
+    }
+
+    fn size(screen: Dims) -> Dims {
+        Dims((screen.0 - 4).clamp(30, 50), 6)
+    }
+}
+
+impl ActivityHandler for TagDialog {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        for event in events {
+            let Event::Term(TermEvent::Key(key @ KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Esc => return Some(Change::pop_top()),
+                KeyCode::Enter => return Some(self.save(data)),
+                KeyCode::Tab if !self.last_tags.is_empty() => {
+                    self.input.set_value(self.last_tags.join(", "));
+                }
+                _ => {
+                    self.input.handle_key(key);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        let mut hints = vec![Hint::new("Enter", "save"), Hint::new("Esc", "cancel")];
+        if !self.last_tags.is_empty() {
+            hints.push(Hint::new("Tab", "last tags"));
+        }
+        hints
+    }
+}
+
+impl Screen for TagDialog {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let size = Self::size(frame.size);
+        let pos = center_box_in_screen(size, frame.size);
+        let inner = size.0 - 4;
+
+        let border = theme["ui_menu_border"];
+        let text = theme["ui_menu_text"];
+        let dim = theme["dim"];
+
+        draw_box(frame, pos, size, border);
+        frame.draw(pos + Dims(3, 0), " Tag the run ", theme["ui_menu_title"]);
+
+        self.input.draw(frame, theme, pos + Dims(2, 1), inner);
+        frame.draw(pos + Dims(1, 2), "─".repeat(size.0 as usize - 2), border);
+
+        let last: String = match self.last_tags.is_empty() {
+            true => "Separate the tags by commas".to_string(),
+            false => format!("Tab: {}", self.last_tags.join(", ")),
+        }
+        .chars()
+        .take(inner as usize)
+        .collect();
+        frame.draw(pos + Dims(2, 3), last, text);
+        let help: String = "Enter: save, Esc: cancel"
+            .chars()
+            .take(inner as usize)
+            .collect();
+        frame.draw(pos + Dims(2, 4), help, dim);
+
+        Ok(())
+    }
+}
+
+/// Filter of the runs by a single tag, cycled through the tags used in the history
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    tags: Vec<String>,
+    /// Index of the selected tag, all runs pass if `None`
+    selected: Option<usize>,
+}
+
+impl TagFilter {
+    pub fn new(history: &[RunRecord]) -> Self {
+        Self {
+            tags: all_tags(history),
+            selected: None,
+        }
+    }
+
+    /// Whether there are any tags to filter by
+    pub fn has_tags(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.selected.map(|i| self.tags[i].as_str())
+    }
+
+    /// Selects the next tag, after the last one all runs are shown again
+    pub fn cycle(&mut self, forward: bool) {
+        let len = self.tags.len();
+        if len == 0 {
+            return;
+        }
+
+        // `None` is at the position `len`
+        let current = self.selected.unwrap_or(len);
+        let next = match forward {
+            true => (current + 1) % (len + 1),
+            false => (current + len) % (len + 1),
+        };
+        self.selected = (next < len).then_some(next);
+    }
+
+    pub fn matches(&self, run: &RunRecord) -> bool {
+        self.tag().is_none_or(|tag| run.has_tag(tag))
+    }
+
+    pub fn label(&self) -> String {
+        match self.tag() {
+            Some(tag) => format!("Tag: {}", tag),
+            None => "All runs".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_cycles_through_tags_and_back() {
+        let mut filter = TagFilter {
+            tags: vec!["a".to_string(), "b".to_string()],
+            selected: None,
+        };
+
+        filter.cycle(true);
+        assert_eq!(filter.tag(), Some("a"));
+        filter.cycle(true);
+        assert_eq!(filter.tag(), Some("b"));
+        filter.cycle(true);
+        assert_eq!(filter.tag(), None);
+        filter.cycle(false);
+        assert_eq!(filter.tag(), Some("b"));
+    }
+}
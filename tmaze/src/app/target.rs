@@ -0,0 +1,316 @@
+//! Generation of a maze which takes about the given time to solve, or has the given difficulty
+//!
+//! Size of the maze is found by a binary search, every size is scored by the analysis of a few
+//! mazes generated with it, see [`cmaze::gameboard::MazeStats::score`]. Mazes of the found size
+//! are generated once more and the one closest to the target is played, from its seed.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use cmaze::{
+    dims::*,
+    game::{GameProperities, GeneratorFn, RunningGame},
+    gameboard::{algorithms::random_seed, Difficulty, SECONDS_PER_POINT},
+};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::is_release,
+    ui::{self, helpers::format_duration, Hint, ProgressBar},
+};
+
+use super::{
+    app::AppData, game::MazeGenerationActivity, game_state::GameRules, Activity, ActivityHandler,
+    Change, Event,
+};
+
+/// Mazes generated to score one size
+const SAMPLES: usize = 3;
+/// Mazes of the found size, the closest one to the target is played
+const CANDIDATES: usize = 8;
+/// Longer side of the searched mazes
+const MIN_SIDE: i32 = 2;
+const MAX_SIDE: i32 = 150;
+
+/// What the maze of a preset should be like, instead of its size
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SolveTarget {
+    /// Expected solve time in seconds
+    Time(f32),
+    Difficulty(Difficulty),
+}
+
+impl SolveTarget {
+    /// Score of the analysis the maze should have
+    pub fn score(self) -> f32 {
+        match self {
+            SolveTarget::Time(seconds) => seconds.max(1.0) / SECONDS_PER_POINT,
+            SolveTarget::Difficulty(difficulty) => difficulty.typical_score(),
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            SolveTarget::Time(seconds) => {
+                format!(
+                    "~{}",
+                    format_duration(Duration::from_secs_f32(seconds.max(1.0)))
+                )
+            }
+            SolveTarget::Difficulty(difficulty) => difficulty.to_string(),
+        }
+    }
+}
+
+/// Size and seed of the maze closest to the target
+#[derive(Debug, Clone, Copy)]
+struct Found {
+    mode: GameMode,
+    seed: u64,
+    score: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SearchProgress {
+    step: usize,
+    size: Dims3D,
+}
+
+/// Steps of the binary search and the final pick of the candidates
+fn total_steps() -> usize {
+    (MAX_SIDE - MIN_SIDE + 1).ilog2() as usize + 2
+}
+
+/// Mode with the longer side `side`, the ratio of the sides is kept
+fn scaled(mode: GameMode, side: i32) -> GameMode {
+    let Dims3D(w, h, d) = mode.size;
+    let longer = w.max(h).max(1) as f32;
+    let scale = |len: i32| ((len as f32 * side as f32 / longer).round() as i32).max(MIN_SIDE);
+    GameMode {
+        size: Dims3D(scale(w), scale(h), d),
+        ..mode
+    }
+}
+
+/// Score of the maze of the mode generated from the seed, `None` if it couldn't be generated
+fn score(mode: GameMode, generator: GeneratorFn, seed: u64) -> Option<f32> {
+    let props = GameProperities {
+        game_mode: mode,
+        generator,
+        seed: Some(seed),
+    };
+    let game = RunningGame::new_threaded(props).ok()?.wait().ok()?;
+    let stats = game
+        .get_maze()
+        .analyze(game.get_start_pos(), game.get_goal_pos());
+    Some(stats.score())
+}
+
+fn search(
+    mode: GameMode,
+    generator: GeneratorFn,
+    target: f32,
+    progress: &Mutex<SearchProgress>,
+    stop: &AtomicBool,
+) -> Option<Found> {
+    let (mut lo, mut hi) = (MIN_SIDE, MAX_SIDE);
+    let mut step = 0;
+
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let candidate = scaled(mode, mid);
+        *progress.lock().unwrap() = SearchProgress {
+            step,
+            size: candidate.size,
+        };
+
+        let scores: Vec<_> = (0..SAMPLES)
+            .map_while(|_| match stop.load(Ordering::Relaxed) {
+                true => None,
+                false => score(candidate, generator, random_seed()),
+            })
+            .collect();
+        if scores.len() < SAMPLES {
+            return None;
+        }
+
+        let mean = scores.iter().sum::<f32>() / SAMPLES as f32;
+        match mean < target {
+            true => lo = mid + 1,
+            false => hi = mid,
+        }
+        step += 1;
+    }
+
+    let mode = scaled(mode, lo);
+    *progress.lock().unwrap() = SearchProgress {
+        step,
+        size: mode.size,
+    };
+
+    let mut best: Option<Found> = None;
+    for _ in 0..CANDIDATES {
+        if stop.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let seed = random_seed();
+        let Some(score) = score(mode, generator, seed) else {
+            continue;
+        };
+        if best.is_none_or(|best| (score - target).abs() < (best.score - target).abs()) {
+            best = Some(Found { mode, seed, score });
+        }
+    }
+    best
+}
+
+/// Searches for the maze in the background, then generates and plays it
+pub struct TargetSearchActivity {
+    mode: GameMode,
+    generator: GeneratorFn,
+    target: SolveTarget,
+    rules: GameRules,
+    progress_bar: ProgressBar,
+    progress: Arc<Mutex<SearchProgress>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Option<Found>>>,
+}
+
+impl TargetSearchActivity {
+    /// Ratio of the sides and the other properties of `mode` are kept, only its size is searched
+    pub fn new(mode: GameMode, generator: GeneratorFn, target: SolveTarget) -> Self {
+        Self {
+            mode,
+            generator,
+            target,
+            rules: GameRules::default(),
+            progress_bar: ProgressBar::new(format!("Finding a {} maze", target.describe())),
+            progress: Arc::new(Mutex::new(SearchProgress {
+                step: 0,
+                size: mode.size,
+            })),
+            stop: Arc::default(),
+            handle: None,
+        }
+    }
+
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    fn start(&mut self) {
+        let (mode, generator) = (self.mode, self.generator);
+        let target = self.target.score();
+        let progress = self.progress.clone();
+        let stop = self.stop.clone();
+
+        self.handle = Some(thread::spawn(move || {
+            search(mode, generator, target, &progress, &stop)
+        }));
+    }
+
+    fn cancel(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl ActivityHandler for TargetSearchActivity {
+    fn update(&mut self, events: Vec<Event>, _: &mut AppData) -> Option<Change> {
+        for event in events {
+            if let Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) = event {
+                if !is_release(kind) && matches!(code, KeyCode::Esc | KeyCode::Char('q')) {
+                    self.cancel();
+                    return Some(Change::pop(2));
+                }
+            }
+        }
+
+        let Some(handle) = &self.handle else {
+            self.start();
+            return None;
+        };
+
+        if !handle.is_finished() {
+            let SearchProgress { step, size } = *self.progress.lock().unwrap();
+            self.progress_bar
+                .update_progress(step as f64 / total_steps() as f64);
+            self.progress_bar.update_title(format!(
+                "Finding a {} maze: trying {}x{}x{}",
+                self.target.describe(),
+                size.0,
+                size.1,
+                size.2
+            ));
+            return None;
+        }
+
+        let found = self
+            .handle
+            .take()
+            .unwrap()
+            .join()
+            .expect("Could not join the maze search thread");
+        let Some(found) = found else {
+            log::error!("No maze was found for the target");
+            return Some(Change::pop_top());
+        };
+
+        let Dims3D(w, h, d) = found.mode.size;
+        log::info!(
+            "Found a {}x{}x{} maze, expected solve time {}",
+            w,
+            h,
+            d,
+            format_duration(Duration::from_secs_f32(found.score * SECONDS_PER_POINT))
+        );
+
+        Some(Change::replace(Activity::new_base_boxed(
+            "maze_gen".to_string(),
+            MazeGenerationActivity::new(found.mode, self.generator)
+                .with_rules(self.rules)
+                .with_seed(found.seed),
+        )))
+    }
+
+    fn screen(&self) -> &dyn ui::Screen {
+        &self.progress_bar
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![Hint::new("Esc", "cancel")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sides_keep_their_ratio() {
+        let mode = GameMode {
+            size: Dims3D(20, 10, 2),
+            is_tower: false,
+            goals: Default::default(),
+            objective: Default::default(),
+            braid: 0,
+            braid_floors: 0,
+            items: Default::default(),
+        };
+
+        assert_eq!(scaled(mode, 40).size, Dims3D(40, 20, 2));
+        assert_eq!(scaled(mode, 3).size, Dims3D(3, 2, 2));
+        assert!(SolveTarget::Time(300.0).score() > SolveTarget::Time(60.0).score());
+    }
+}
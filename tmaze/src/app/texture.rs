@@ -0,0 +1,152 @@
+//! Cosmetic texture of the maze, moss and cracks on the walls
+//!
+//! Nothing is stored, every junction and dead end is looked up by its position and the seed of
+//! the maze when the floor is rendered, so the same maze always looks the same. How much of it
+//! is decorated is set by the theme, see [`Theme::texture_density`].
+
+use cmaze::{
+    dims::*,
+    gameboard::{CellWall, Maze},
+};
+
+use crate::{
+    helpers::{maze2screen, LineDir},
+    renderer::Frame,
+    settings::theme::Theme,
+};
+
+/// Mixed into the seed, so the texture doesn't follow the choices of the generator
+const TEXTURE_SALT: u64 = 0x7E47_0BE5_11C0_FFEE;
+
+const MOSS_CHARS: [char; 3] = [',', '\'', '"'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Texture {
+    Moss,
+    Crack,
+}
+
+/// Texture at the position on the rendered floor, if there is any
+///
+/// `density` is the percentage of positions which get one, the rest of the hash picks the
+/// kind of the texture.
+pub fn texture_at(seed: u64, pos: Dims3D, density: u8) -> Option<(Texture, u64)> {
+    let mut x = seed ^ TEXTURE_SALT;
+    for coord in [pos.0, pos.1, pos.2] {
+        x = mix(x ^ coord as u32 as u64);
+    }
+
+    if x % 100 >= density as u64 {
+        return None;
+    }
+
+    let rest = x / 100;
+    let texture = match rest & 1 {
+        0 => Texture::Moss,
+        _ => Texture::Crack,
+    };
+    Some((texture, rest >> 1))
+}
+
+/// SplitMix64 finalizer, see [`super::daily::seed`]
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Decorates the rendered floor, walls keep their shape so the maze reads the same
+///
+/// Moss only changes the color of the junction, cracks draw it with thin lines. Dead ends
+/// without stairs can have moss on the floor.
+pub fn apply(frame: &mut Frame, maze: &Maze, floor: i32, seed: u64, theme: &Theme) {
+    let density = theme.texture_density();
+    if density == 0 {
+        return;
+    }
+
+    let [moss, crack] = theme.extract(["game.walls.moss", "game.walls.crack"]);
+
+    for y in -1..maze.size().1 {
+        for x in -1..maze.size().0 {
+            let cell_pos = Dims3D(x, y, floor);
+            let junction = maze2screen(cell_pos) + Dims(1, 1);
+            let dir = junction_dir(maze, cell_pos);
+            if matches!(dir, LineDir::Empty) {
+                continue;
+            }
+
+            match texture_at(seed, Dims3D(junction.0, junction.1, floor), density) {
+                Some((Texture::Moss, _)) => frame.draw(junction, dir.double(), moss),
+                Some((Texture::Crack, _)) => frame.draw(junction, dir.round(), crack),
+                None => {}
+            }
+        }
+    }
+
+    for (y, row) in maze.get_cells()[floor as usize].iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let closed = [
+                CellWall::Left,
+                CellWall::Top,
+                CellWall::Right,
+                CellWall::Bottom,
+            ]
+            .into_iter()
+            .filter(|&wall| cell.get_wall(wall))
+            .count();
+            let stairs = !cell.get_wall(CellWall::Up) || !cell.get_wall(CellWall::Down);
+            if closed != 3 || stairs {
+                continue;
+            }
+
+            let pos = maze2screen(Dims(x as i32, y as i32));
+            if let Some((Texture::Moss, variant)) =
+                texture_at(seed, Dims3D(pos.0, pos.1, floor), density)
+            {
+                let ch = MOSS_CHARS[variant as usize % MOSS_CHARS.len()];
+                frame.draw(pos, ch, moss);
+            }
+        }
+    }
+}
+
+/// Shape of the walls meeting at the bottom right corner of the cell
+pub fn junction_dir(maze: &Maze, cell_pos: Dims3D) -> LineDir {
+    let corner = cell_pos + Dims3D(1, 1, 0);
+    LineDir::from_bools(
+        maze.get_wall(cell_pos, CellWall::Bottom).unwrap(),
+        maze.get_wall(cell_pos, CellWall::Right).unwrap(),
+        maze.get_wall(corner, CellWall::Top).unwrap(),
+        maze.get_wall(corner, CellWall::Left).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_follows_seed_and_density() {
+        let positions = (0..40).flat_map(|y| (0..40).map(move |x| Dims3D(x, y, 0)));
+        let count = |seed, density| {
+            positions
+                .clone()
+                .filter(|&pos| texture_at(seed, pos, density).is_some())
+                .count()
+        };
+
+        assert_eq!(count(1, 0), 0);
+        assert_eq!(count(1, 100), 1600);
+        assert!((100..220).contains(&count(1, 10)));
+
+        let textures = |seed| {
+            positions
+                .clone()
+                .map(|pos| texture_at(seed, pos, 50))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(textures(7), textures(7));
+        assert_ne!(textures(7), textures(8));
+    }
+}
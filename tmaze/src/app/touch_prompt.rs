@@ -0,0 +1,76 @@
+//! One-time question whether to enable the touch controls, shown on terminals without a keyboard
+//!
+//! Answer is stored as [`crate::settings::SettingsInner::mobile_mode`], so the player is never
+//! asked again, even if the prompt was just dismissed.
+
+use crate::{
+    settings::Settings,
+    ui::{Hint, Menu, MenuConfig, Screen},
+};
+
+use super::{app::AppData, Activity, ActivityHandler, Change, Event};
+
+pub struct TouchPrompt {
+    menu: Menu,
+}
+
+impl TouchPrompt {
+    pub fn new() -> Self {
+        let menu_config = MenuConfig::new_from_strings(
+            "Enable touch controls?",
+            ["Enable".to_string(), "Keep keyboard".to_string()],
+        )
+        .subtitle("No keyboard detected")
+        .subtitle("Adds an on-screen dpad")
+        .subtitle("Can be changed in the settings");
+
+        Self {
+            menu: Menu::new(menu_config),
+        }
+    }
+
+    pub fn new_activity() -> Activity {
+        Activity::new_base_boxed("touch prompt", Self::new())
+    }
+
+    /// Whether the prompt should be shown, `forced` is set by the `--touch` flag
+    pub fn should_show(settings: &Settings, forced: bool) -> bool {
+        settings.read().mobile_mode.is_none() && (forced || Settings::detect_touch_terminal())
+    }
+}
+
+impl Default for TouchPrompt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityHandler for TouchPrompt {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        let enable = match self.menu.update(events, data)? {
+            Change::Pop { res: Some(res), .. } => {
+                *res.downcast::<usize>().expect("menu should return index") == 0
+            }
+            // dismissed, counts as no, so it's not asked on every start
+            Change::Pop { res: None, .. } => false,
+            res => return Some(res),
+        };
+
+        data.settings.set_mobile_mode(enable);
+        data.apply_settings();
+        log::info!(
+            "Touch controls {}",
+            if enable { "enabled" } else { "not enabled" }
+        );
+
+        Some(Change::pop_top())
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
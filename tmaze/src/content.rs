@@ -0,0 +1,259 @@
+//! Discovery of user content installed in the data directories
+//!
+//! Every kind of content has its own directory in the game config directory,
+//! see [`ContentKind::dir`]. Themes take their name and version from the `meta`
+//! section, presets from their title.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use crate::{
+    helpers::constants::paths::{preset_path, theme_path},
+    settings::{theme::ThemeDefinition, MazePreset},
+};
+
+/// Theme which is generated by the game, it can't be disabled
+const DEFAULT_THEME_FILE: &str = "default_theme.json5";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Theme,
+    Preset,
+}
+
+impl ContentKind {
+    pub const ALL: [ContentKind; 2] = [ContentKind::Theme, ContentKind::Preset];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentKind::Theme => "Themes",
+            ContentKind::Preset => "Presets",
+        }
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            ContentKind::Theme => "theme",
+            ContentKind::Preset => "preset",
+        }
+    }
+
+    pub fn dir(self) -> PathBuf {
+        match self {
+            ContentKind::Theme => theme_path(),
+            ContentKind::Preset => preset_path(),
+        }
+    }
+
+    /// Identifier used to remember disabled content, e.g. `theme/dark.json5`
+    pub fn id(self, file_name: &str) -> String {
+        format!("{}/{}", self.key(), file_name)
+    }
+
+    fn accepts(self, path: &Path) -> bool {
+        let ext = path.extension().and_then(|e| e.to_str());
+        match self {
+            ContentKind::Theme => matches!(ext, Some("json" | "json5" | "toml")),
+            ContentKind::Preset => ext == Some("ron"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentItem {
+    pub kind: ContentKind,
+    pub file_name: String,
+    /// Name from the manifest, file name if there is none
+    pub name: String,
+    pub version: Option<String>,
+    pub path: PathBuf,
+    /// Content of the game itself, it can't be disabled
+    pub builtin: bool,
+}
+
+impl ContentItem {
+    pub fn id(&self) -> String {
+        self.kind.id(&self.file_name)
+    }
+
+    fn load(kind: ContentKind, path: PathBuf) -> Self {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let (name, version) = match kind {
+            ContentKind::Theme => match ThemeDefinition::load_by_path(path.clone()) {
+                Ok(theme) => (
+                    theme.meta("name").map(str::to_string),
+                    theme.meta("version").map(str::to_string),
+                ),
+                Err(err) => {
+                    log::warn!("Invalid theme {:?}: {}", path, err);
+                    (None, None)
+                }
+            },
+            ContentKind::Preset => (read_preset(&path).map(|preset| preset.title), None),
+        };
+
+        Self {
+            kind,
+            name: name.unwrap_or_else(|| file_name.clone()),
+            builtin: kind == ContentKind::Theme && file_name == DEFAULT_THEME_FILE,
+            file_name,
+            version,
+            path,
+        }
+    }
+}
+
+fn read_preset(path: &Path) -> Option<MazePreset> {
+    let content = fs::read_to_string(path).ok()?;
+    ron::from_str(&content)
+        .inspect_err(|err| log::warn!("Invalid preset {:?}: {}", path, err))
+        .ok()
+}
+
+/// Paths of the content sorted by file name, missing directory means no content
+fn content_paths(kind: ContentKind, dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| kind.accepts(path))
+        .collect();
+    paths.sort();
+    paths
+}
+
+pub fn discover(kind: ContentKind) -> Vec<ContentItem> {
+    discover_in(kind, &kind.dir())
+}
+
+fn discover_in(kind: ContentKind, dir: &Path) -> Vec<ContentItem> {
+    content_paths(kind, dir)
+        .into_iter()
+        .map(|path| ContentItem::load(kind, path))
+        .collect()
+}
+
+pub fn discover_all() -> Vec<ContentItem> {
+    ContentKind::ALL.into_iter().flat_map(discover).collect()
+}
+
+/// All presets from the presets directory with their content ids
+///
+/// Disabled ones are included too, they are filtered out by [`Settings::get_mazes`].
+///
+/// [`Settings::get_mazes`]: crate::settings::Settings::get_mazes
+pub fn load_presets() -> Vec<(String, MazePreset)> {
+    load_presets_in(&ContentKind::Preset.dir())
+}
+
+fn load_presets_in(dir: &Path) -> Vec<(String, MazePreset)> {
+    content_paths(ContentKind::Preset, dir)
+        .into_iter()
+        .filter_map(|path| {
+            let file_name = path.file_name()?.to_string_lossy().into_owned();
+            let preset = read_preset(&path)?;
+            Some((ContentKind::Preset.id(&file_name), preset))
+        })
+        .map(|(id, preset)| {
+            let preset = MazePreset {
+                // only presets from the settings can be the default one
+                default: false,
+                ..preset
+            };
+            (id, preset)
+        })
+        .collect()
+}
+
+/// Opens the directory in the file manager of the system, creates it if it's missing
+pub fn open_folder(path: &Path) -> io::Result<()> {
+    fs::create_dir_all(path)?;
+
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+
+    process::Command::new(program)
+        .arg(path)
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESET: &str = r#"(title: "Huge", width: 80, height: 40, default: true)"#;
+
+    fn content_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tmaze-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn discover_themes() {
+        let dir = content_dir("themes");
+        let theme = r#"{ meta: { name: "Dark", version: "1.2" }, styles: {} }"#;
+        fs::write(dir.join("dark.json5"), theme).unwrap();
+        fs::write(dir.join(DEFAULT_THEME_FILE), "{ styles: {} }").unwrap();
+        fs::write(dir.join("broken.toml"), "styles = [").unwrap();
+        fs::write(dir.join("notes.txt"), "not a theme").unwrap();
+
+        let items = discover_in(ContentKind::Theme, &dir);
+        let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["broken.toml", "Dark", DEFAULT_THEME_FILE]);
+
+        assert_eq!(items[1].version.as_deref(), Some("1.2"));
+        assert_eq!(items[1].id(), "theme/dark.json5");
+        assert!(!items[1].builtin);
+        assert!(items[2].builtin);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn discover_presets() {
+        let dir = content_dir("presets");
+        fs::write(dir.join("huge.ron"), PRESET).unwrap();
+        fs::write(dir.join("broken.ron"), "(title: ").unwrap();
+        fs::write(dir.join("huge.toml"), "title = 'Huge'").unwrap();
+
+        let items = discover_in(ContentKind::Preset, &dir);
+        let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["broken.ron", "Huge"]);
+
+        let presets = load_presets_in(&dir);
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].0, "preset/huge.ron");
+        assert_eq!(presets[0].1.title, "Huge");
+        assert!(!presets[0].1.default);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_dir_has_no_content() {
+        let dir = std::env::temp_dir().join("tmaze-missing-content");
+        assert!(discover_in(ContentKind::Theme, &dir).is_empty());
+        assert!(load_presets_in(&dir).is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! Achievements, unlocked by finishing the mazes
+//!
+//! Every finished run is described by [`Win`] and checked against the achievements which
+//! are still locked, see [`super::SaveData::record_win`].
+
+use cmaze::dims::*;
+
+/// Finished run, as seen by the achievements
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Win {
+    pub size: Dims3D,
+    pub seconds: f32,
+    pub moves: usize,
+    /// Whether the player visited some cell more than once
+    pub backtracked: bool,
+    pub daily: bool,
+    /// Coins picked up in the maze
+    pub coins: usize,
+}
+
+impl Win {
+    fn cells(&self) -> i32 {
+        self.size.0 * self.size.1 * self.size.2
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Goal {
+    /// Unlocked by a single run
+    Run(fn(&Win) -> bool),
+    /// Unlocked after this many finished runs
+    Wins(u32),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Achievement {
+    /// Identifier in the save data, it must never change
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub goal: Goal,
+}
+
+impl Achievement {
+    /// Whether the run unlocks it, `wins` already counts the run
+    pub fn is_unlocked_by(&self, win: &Win, wins: u32) -> bool {
+        match self.goal {
+            Goal::Run(check) => check(win),
+            Goal::Wins(count) => wins >= count,
+        }
+    }
+}
+
+pub const ACHIEVEMENTS: [Achievement; 8] = [
+    Achievement {
+        id: "first-win",
+        name: "First win",
+        description: "Finish any maze",
+        goal: Goal::Wins(1),
+    },
+    Achievement {
+        id: "third-dimension",
+        name: "Third dimension",
+        description: "Finish a maze with more floors",
+        goal: Goal::Run(|win| win.size.2 > 1),
+    },
+    Achievement {
+        id: "no-backtracking",
+        name: "Straight to the goal",
+        description: "Finish a 10x10 or bigger maze without visiting a cell twice",
+        goal: Goal::Run(|win| win.cells() >= 100 && !win.backtracked),
+    },
+    Achievement {
+        id: "speedrun",
+        name: "Speedrunner",
+        description: "Finish a 20x10 or bigger maze in under 30 seconds",
+        goal: Goal::Run(|win| win.cells() >= 200 && win.seconds < 30.0),
+    },
+    Achievement {
+        id: "giant",
+        name: "Giant",
+        description: "Finish a maze with at least 2000 cells",
+        goal: Goal::Run(|win| win.cells() >= 2000),
+    },
+    Achievement {
+        id: "daily",
+        name: "Daily routine",
+        description: "Finish a daily challenge",
+        goal: Goal::Run(|win| win.daily),
+    },
+    Achievement {
+        id: "wins-10",
+        name: "Regular",
+        description: "Finish 10 mazes",
+        goal: Goal::Wins(10),
+    },
+    Achievement {
+        id: "wins-100",
+        name: "Veteran",
+        description: "Finish 100 mazes",
+        goal: Goal::Wins(100),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn win(size: Dims3D, seconds: f32, backtracked: bool) -> Win {
+        Win {
+            size,
+            seconds,
+            moves: 0,
+            backtracked,
+            daily: false,
+            coins: 0,
+        }
+    }
+
+    fn unlocked(win: &Win, wins: u32) -> Vec<&'static str> {
+        ACHIEVEMENTS
+            .iter()
+            .filter(|a| a.is_unlocked_by(win, wins))
+            .map(|a| a.id)
+            .collect()
+    }
+
+    #[test]
+    fn runs_unlock_achievements() {
+        assert_eq!(
+            unlocked(&win(Dims3D(5, 5, 1), 60.0, true), 1),
+            ["first-win"]
+        );
+        assert_eq!(
+            unlocked(&win(Dims3D(10, 10, 2), 60.0, false), 10),
+            ["first-win", "third-dimension", "no-backtracking", "wins-10"]
+        );
+        assert_eq!(
+            unlocked(&win(Dims3D(50, 40, 1), 20.0, true), 2),
+            ["first-win", "speedrun", "giant"]
+        );
+    }
+
+    #[test]
+    fn ids_are_unique() {
+        for (i, a) in ACHIEVEMENTS.iter().enumerate() {
+            assert!(ACHIEVEMENTS[..i].iter().all(|b| b.id != a.id));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Environment a run was played in, stored with its results
+//!
+//! Times depend on more than the maze, a slow mode or a move delay make them longer and rules
+//! like stamina change the run completely. Statistics can be limited to the runs played the same
+//! way, see [`Environment::same_gameplay`], and reports of impossible times can be checked
+//! against the version and the settings of the run.
+
+use cmaze::dims::Dims;
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::Zoom, settings::Settings};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    /// Version of the app the run was played in
+    pub version: String,
+    /// Size of the terminal at the end of the run
+    pub screen: Dims,
+    /// Zoom of the maze at the end of the run
+    #[serde(default)]
+    pub zoom: Zoom,
+    /// Non-ASCII characters were replaced, see [`crate::renderer::RenderCompat`]
+    #[serde(default)]
+    pub ascii: bool,
+    #[serde(default)]
+    pub first_person: bool,
+    /// Rules of the run, like hardcore or stamina, see [`crate::app::game_state::GameData::modifiers`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<String>,
+    /// Settings which change how the game plays and aren't at their defaults
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub settings: Vec<String>,
+}
+
+impl Environment {
+    /// Environment of a run with the modifiers, played with the current settings
+    pub fn new(settings: &Settings, screen: Dims, modifiers: Vec<String>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            screen,
+            zoom: settings.get_maze_zoom(),
+            ascii: settings.get_render_compat().ascii,
+            first_person: settings.get_first_person_view(),
+            modifiers,
+            settings: gameplay_settings(settings),
+        }
+    }
+
+    /// Runs were played by the same rules and settings, so their times can be compared
+    ///
+    /// How the maze was drawn and the version of the app are not compared.
+    pub fn same_gameplay(&self, other: &Self) -> bool {
+        self.modifiers == other.modifiers && self.settings == other.settings
+    }
+}
+
+/// Settings which change how the game plays, when they aren't at their defaults
+pub fn gameplay_settings(settings: &Settings) -> Vec<String> {
+    let flags = [
+        (settings.get_slow(), "slow"),
+        (settings.get_disable_tower_auto_up(), "no auto climbing"),
+        (settings.get_relative_controls(), "relative controls"),
+    ];
+
+    let mut list: Vec<_> = flags
+        .into_iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    if settings.get_input_debounce() > 0 {
+        list.push(format!("move delay {} ms", settings.get_input_debounce()));
+    }
+
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gameplay_is_compared_without_rendering() {
+        let env = Environment {
+            version: "1.0.0".to_string(),
+            screen: Dims(80, 24),
+            zoom: Zoom::Normal,
+            ascii: false,
+            first_person: false,
+            modifiers: vec![],
+            settings: vec!["slow".to_string()],
+        };
+        let drawn_else = Environment {
+            version: "2.0.0".to_string(),
+            screen: Dims(200, 60),
+            zoom: Zoom::Braille,
+            ascii: true,
+            ..env.clone()
+        };
+        assert!(env.same_gameplay(&drawn_else));
+
+        let fast = Environment {
+            settings: vec![],
+            ..env.clone()
+        };
+        assert!(!env.same_gameplay(&fast));
+        let hardcore = Environment {
+            modifiers: vec!["hardcore".to_string()],
+            ..env.clone()
+        };
+        assert!(!env.same_gameplay(&hardcore));
+    }
+}
@@ -0,0 +1,120 @@
+//! History of the finished runs, which can be tagged, e.g. "practice" or "race night"
+//!
+//! Tags are set on the end game screen and used to filter the statistics and the gallery,
+//! so runs of recurring events can be kept apart from the others.
+
+use chrono::{DateTime, Local};
+use cmaze::dims::*;
+use serde::{Deserialize, Serialize};
+
+use super::environment::Environment;
+
+/// How many runs are kept, the oldest ones are removed first
+pub const HISTORY_LEN: usize = 1000;
+
+/// Longest tag, longer ones are cut
+const MAX_TAG_LEN: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// When the run was finished, it's also the id of the run
+    pub date: DateTime<Local>,
+    pub game_mode: GameMode,
+    /// Seed of the maze, the same maze can be found in the gallery
+    pub seed: u64,
+    /// Game time, without the pauses
+    pub seconds: f32,
+    pub moves: usize,
+    /// Coins picked up in the maze
+    #[serde(default)]
+    pub coins: usize,
+    /// How many times the stamina ran out, `None` if the run had no stamina
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exhausted: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Missing in runs saved before it was stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<Environment>,
+}
+
+impl RunRecord {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// Tags from the text entered by the player, separated by commas, lowercase and without duplicates
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in text.split(',') {
+        let tag: String = tag
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+            .chars()
+            .take(MAX_TAG_LEN)
+            .collect();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// All tags used in the history, sorted
+pub fn all_tags(history: &[RunRecord]) -> Vec<String> {
+    let mut tags: Vec<_> = history.iter().flat_map(|run| run.tags.clone()).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Summary of the runs, shown in the statistics
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RunStats {
+    pub runs: usize,
+    pub total_seconds: f32,
+    pub best_seconds: Option<f32>,
+    pub total_moves: usize,
+    pub total_coins: usize,
+}
+
+impl RunStats {
+    pub fn of<'a>(runs: impl IntoIterator<Item = &'a RunRecord>) -> Self {
+        runs.into_iter().fold(Self::default(), |stats, run| Self {
+            runs: stats.runs + 1,
+            total_seconds: stats.total_seconds + run.seconds,
+            best_seconds: Some(
+                stats
+                    .best_seconds
+                    .map_or(run.seconds, |b| b.min(run.seconds)),
+            ),
+            total_moves: stats.total_moves + run.moves,
+            total_coins: stats.total_coins + run.coins,
+        })
+    }
+
+    pub fn avg_seconds(&self) -> Option<f32> {
+        (self.runs > 0).then(|| self.total_seconds / self.runs as f32)
+    }
+
+    pub fn avg_moves(&self) -> Option<f32> {
+        (self.runs > 0).then(|| self.total_moves as f32 / self.runs as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_normalized() {
+        assert_eq!(
+            parse_tags(" Practice,race   Night, ,practice"),
+            ["practice", "race night"]
+        );
+        assert!(parse_tags("").is_empty());
+    }
+}
@@ -0,0 +1,314 @@
+//! Mazes drawn by hand, loaded from `.maze.json` and `.maze.ron` files in the mazes directory
+//!
+//! Floors are drawn as text, the same way as the text export: cell `x, y` is at column
+//! `2x + 1` of line `2y + 1`, with walls or passages between the cells. Spaces are passages,
+//! `S` is the start, `G` a goal, `^`, `v` and `x` are stairs up, down and both ways, other
+//! characters are walls. Cells drawn as walls are not part of the maze, so the maze can
+//! have any shape.
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use cmaze::{
+    dims::*,
+    game::{GeneratorFn, GoalObjective, RunningGame},
+    gameboard::{CellWall, Maze},
+};
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::helpers::constants::paths::maze_spec_path;
+
+const EXTENSIONS: [&str; 2] = [".maze.json", ".maze.ron"];
+
+#[derive(Debug, Error)]
+pub enum SpecError {
+    #[error("could not read the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid file: {0}")]
+    Parse(String),
+    #[error("maze has no floors")]
+    NoFloors,
+    #[error("floors must have the same odd number of lines and characters, at least 3")]
+    InvalidSize,
+    #[error("start is missing, mark it with `S`")]
+    NoStart,
+    #[error("there can only be one start")]
+    MultipleStarts,
+    #[error("goal is missing, mark it with `G`")]
+    NoGoal,
+    #[error("{0:?} is not a cell of the maze")]
+    OutsideMask(Dims3D),
+    #[error("stairs at {0:?} don't lead to stairs on the next floor")]
+    Stairs(Dims3D),
+    #[error("{0:?} can't be reached from the start")]
+    Unreachable(Dims3D),
+}
+
+/// Maze drawn in a file, see the [module](self) docs for the format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MazeSpec {
+    pub title: String,
+    /// Lines of every floor, from the bottom one
+    pub floors: Vec<Vec<String>>,
+    /// Start position, instead of the `S` mark
+    #[serde(default)]
+    pub start: Option<Dims3D>,
+    /// Goal positions, in addition to the `G` marks
+    #[serde(default)]
+    pub goals: Vec<Dims3D>,
+    #[serde(default)]
+    pub objective: GoalObjective,
+    #[serde(default)]
+    pub tower: bool,
+}
+
+/// Validated maze of a [`MazeSpec`], with the start and goals
+pub struct SpecMaze {
+    pub maze: Maze,
+    pub start: Dims3D,
+    pub goals: Vec<Dims3D>,
+}
+
+impl MazeSpec {
+    pub fn load(path: &Path) -> Result<Self, SpecError> {
+        let content = fs::read_to_string(path)?;
+        let name = path.to_string_lossy();
+        if name.ends_with(".json") {
+            serde_json::from_str(&content).map_err(|err| SpecError::Parse(err.to_string()))
+        } else {
+            ron::from_str(&content).map_err(|err| SpecError::Parse(err.to_string()))
+        }
+    }
+
+    /// Builds the maze and checks the start and goals are inside and all cells are reachable
+    pub fn build(&self) -> Result<SpecMaze, SpecError> {
+        let grid: Vec<Vec<Vec<char>>> = self
+            .floors
+            .iter()
+            .map(|floor| floor.iter().map(|line| line.chars().collect()).collect())
+            .collect();
+
+        let first = grid.first().ok_or(SpecError::NoFloors)?;
+        let (grid_w, grid_h) = (first.first().map_or(0, Vec::len), first.len());
+        let valid = |len: usize| len >= 3 && len % 2 == 1;
+        let same_size = grid
+            .iter()
+            .all(|floor| floor.len() == grid_h && floor.iter().all(|line| line.len() == grid_w));
+        if !valid(grid_w) || !valid(grid_h) || !same_size {
+            return Err(SpecError::InvalidSize);
+        }
+
+        let size = Dims3D((grid_w / 2) as i32, (grid_h / 2) as i32, grid.len() as i32);
+        let at = |pos: Dims3D, off: Dims| {
+            let (x, y) = (pos.0 * 2 + 1 + off.0, pos.1 * 2 + 1 + off.1);
+            grid[pos.2 as usize][y as usize][x as usize]
+        };
+
+        let mut maze = Maze::new_walled(size, self.tower);
+        let mut mask = HashSet::new();
+        let mut start = self.start.into_iter().collect::<Vec<_>>();
+        let mut goals = vec![];
+
+        for pos in cells(size) {
+            match at(pos, Dims(0, 0)) {
+                'S' => start.push(pos),
+                'G' => goals.push(pos),
+                ch if is_wall(ch) => continue,
+                _ => {}
+            }
+            mask.insert(pos);
+        }
+
+        for &pos in &mask {
+            for (wall, off) in [
+                (CellWall::Right, Dims(1, 0)),
+                (CellWall::Bottom, Dims(0, 1)),
+            ] {
+                let next = pos + wall.to_coord();
+                if mask.contains(&next) && !is_wall(at(pos, off)) {
+                    maze.remove_wall(pos, wall);
+                }
+            }
+
+            let (up, down) = stairs(at(pos, Dims(0, 0)));
+            for (has, wall, other) in [(up, CellWall::Up, false), (down, CellWall::Down, true)] {
+                if !has {
+                    continue;
+                }
+
+                // stairs have to continue on the other floor
+                let next = pos + wall.to_coord();
+                let leads = mask.contains(&next) && {
+                    let (up, down) = stairs(at(next, Dims(0, 0)));
+                    if other {
+                        up
+                    } else {
+                        down
+                    }
+                };
+                if !leads {
+                    return Err(SpecError::Stairs(pos));
+                }
+                maze.remove_wall(pos, wall);
+            }
+        }
+
+        let start = match start[..] {
+            [] => return Err(SpecError::NoStart),
+            [start] => start,
+            _ => return Err(SpecError::MultipleStarts),
+        };
+        goals.extend(&self.goals);
+        if goals.is_empty() {
+            return Err(SpecError::NoGoal);
+        }
+        if let Some(outside) = [start]
+            .iter()
+            .chain(&goals)
+            .find(|pos| !mask.contains(*pos))
+        {
+            return Err(SpecError::OutsideMask(*outside));
+        }
+
+        let reached = reachable(&maze, start);
+        let mut unreachable: Vec<_> = mask.difference(&reached).copied().collect();
+        unreachable.sort_by_key(|pos| (pos.2, pos.1, pos.0));
+        if let Some(pos) = unreachable.first() {
+            return Err(SpecError::Unreachable(*pos));
+        }
+
+        Ok(SpecMaze { maze, start, goals })
+    }
+
+    /// New game on the maze, `gen_fn` is used by modes which continue with generated mazes
+    pub fn to_game(&self, gen_fn: GeneratorFn) -> Result<RunningGame, SpecError> {
+        let SpecMaze { maze, start, goals } = self.build()?;
+        let seed = maze.canonical_hash();
+        Ok(RunningGame::from_maze(
+            maze,
+            start,
+            goals,
+            self.objective,
+            seed,
+            gen_fn,
+        ))
+    }
+}
+
+fn is_wall(ch: char) -> bool {
+    !matches!(
+        ch,
+        ' ' | '·' | '•' | 'S' | 'G' | '^' | '↑' | 'v' | '↓' | 'x' | '↕'
+    )
+}
+
+/// Whether the character is stairs up and down
+fn stairs(ch: char) -> (bool, bool) {
+    match ch {
+        '^' | '↑' => (true, false),
+        'v' | '↓' => (false, true),
+        'x' | '↕' => (true, true),
+        _ => (false, false),
+    }
+}
+
+fn cells(Dims3D(w, h, d): Dims3D) -> impl Iterator<Item = Dims3D> {
+    (0..d).flat_map(move |z| (0..h).flat_map(move |y| (0..w).map(move |x| Dims3D(x, y, z))))
+}
+
+fn reachable(maze: &Maze, start: Dims3D) -> HashSet<Dims3D> {
+    let mut reached = HashSet::from_iter([start]);
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(pos) = queue.pop_front() {
+        for wall in CellWall::get_in_order() {
+            let next = pos + wall.to_coord();
+            if maze.get_wall(pos, wall) == Some(false) && reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    reached
+}
+
+/// Specs in the mazes directory, sorted by file name, invalid ones are skipped
+pub fn load_specs() -> Vec<(PathBuf, MazeSpec)> {
+    let Ok(entries) = fs::read_dir(maze_spec_path()) else {
+        return vec![];
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| {
+            let name = path.to_string_lossy().to_lowercase();
+            EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let spec = MazeSpec::load(&path)
+                .and_then(|spec| spec.build().map(|_| spec))
+                .inspect_err(|err| log::warn!("Invalid maze {:?}: {}", path, err))
+                .ok()?;
+            Some((path, spec))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(floors: &[&[&str]]) -> MazeSpec {
+        MazeSpec {
+            title: "test".to_string(),
+            floors: floors
+                .iter()
+                .map(|floor| floor.iter().map(|line| line.to_string()).collect())
+                .collect(),
+            start: None,
+            goals: vec![],
+            objective: GoalObjective::default(),
+            tower: false,
+        }
+    }
+
+    #[test]
+    fn shaped_maze() {
+        let built = spec(&[&["#######", "#S    #", "### ###", "#  G ^#", "#######"]]).build();
+        assert!(matches!(built, Err(SpecError::Stairs(Dims3D(2, 1, 0)))));
+
+        // the top right cell and the top of the second floor are not part of the maze
+        let built = spec(&[
+            &["#######", "#S  ###", "### ###", "#  G ^#", "#######"],
+            &["#######", "#######", "#######", "#    v#", "#######"],
+        ])
+        .build()
+        .unwrap();
+        assert_eq!(built.start, Dims3D(0, 0, 0));
+        assert_eq!(built.goals, vec![Dims3D(1, 1, 0)]);
+        assert_eq!(
+            built
+                .maze
+                .solve(built.start, Dims3D(0, 1, 1))
+                .map(|p| p.len()),
+            Some(7)
+        );
+
+        let unreachable = spec(&[&["#####", "#S#G#", "#####"]]).build();
+        assert!(matches!(
+            unreachable,
+            Err(SpecError::Unreachable(Dims3D(1, 0, 0)))
+        ));
+    }
+}
@@ -1,29 +1,80 @@
+use achievements::{Win, ACHIEVEMENTS};
 use chrono::{DateTime, Datelike, Local, NaiveDate};
 use cmaze::dims::*;
-use model::SolveResult;
+use environment::Environment;
+use history::{RunRecord, HISTORY_LEN};
+use model::{RunStep, SolveResult};
+use progression::{xp_for, Level, WinReward};
 use ron::{de::from_reader, ser::to_writer};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::File,
     path::{Path, PathBuf},
 };
+use surprise::Surprise;
 
 use crate::{
     helpers::constants::paths::save_data_path,
     settings::{Settings, UpdateCheckInterval},
 };
 
+pub mod achievements;
+pub mod environment;
+pub mod history;
+pub mod maze_spec;
+pub mod progression;
+pub mod surprise;
+
 pub mod model {
     use super::*;
 
-    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
     pub struct SolveResult {
         pub moves: i32,
+        /// Game time, without the pauses
         pub seconds: f32,
+        /// Real time, with the pauses, missing in results saved before it was tracked
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub wall_seconds: Option<f32>,
+        /// Positions of the player during the run, replayed by the ghost
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub run: Vec<RunStep>,
+        /// Missing in results saved before it was stored
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub env: Option<Environment>,
     }
+
+    impl SolveResult {
+        /// Game or real time in seconds, real time falls back to the game time
+        pub fn time(&self, real_time: bool) -> f32 {
+            match real_time {
+                true => self.wall_seconds.unwrap_or(self.seconds),
+                false => self.seconds,
+            }
+        }
+
+        /// Copy without the recorded run
+        pub fn without_run(&self) -> Self {
+            Self {
+                moves: self.moves,
+                seconds: self.seconds,
+                wall_seconds: self.wall_seconds,
+                run: vec![],
+                env: self.env.clone(),
+            }
+        }
+    }
+
+    /// Game time in milliseconds and the position of the player from that time
+    pub type RunStep = (u64, Dims3D);
 }
 
+/// How many recently played mazes are remembered
+const RECENT_MAZES_LEN: usize = 16;
+/// How many surprises are kept, see [`surprise`]
+const SURPRISES_LEN: usize = 10;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SaveData {
     pub last_update_check: Option<DateTime<Local>>,
@@ -31,29 +82,86 @@ pub struct SaveData {
     #[serde(default)]
     best_results: HashMap<GameMode, SolveResult>,
 
+    /// Canonical hashes of recently played mazes, newest last
+    #[serde(default)]
+    recent_mazes: Vec<u64>,
+
+    /// Best results of daily challenges, by their UTC date
+    #[serde(default)]
+    daily_results: BTreeMap<NaiveDate, SolveResult>,
+
+    /// Best results of finished campaign stages, by their id
+    #[serde(default)]
+    campaign_results: BTreeMap<String, SolveResult>,
+
+    /// Unlocked achievements by their id, with the time of unlocking
+    #[serde(default)]
+    achievements: BTreeMap<String, DateTime<Local>>,
+
+    /// Number of finished mazes, counted for the achievements
+    #[serde(default)]
+    wins: u32,
+
+    /// Experience from the finished mazes, see [`progression`]
+    #[serde(default)]
+    xp: u32,
+
+    /// Coins picked up in all the finished mazes
+    #[serde(default)]
+    coins: u32,
+
+    /// Game modes of the presets by their title, when they were last seen, see
+    /// [`crate::app::preset_diff`]
+    #[serde(default)]
+    preset_modes: BTreeMap<String, GameMode>,
+
+    /// Finished runs, oldest first, see [`history`]
+    #[serde(default)]
+    history: Vec<RunRecord>,
+
+    /// Seed of the next maze with the counter seed source, see [`Settings::get_seed_source`]
+    #[serde(default)]
+    seed_counter: u64,
+
+    /// Played surprises, oldest first, see [`surprise`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    surprises: Vec<Surprise>,
+
     #[serde(skip_serializing, skip_deserializing)]
     path: PathBuf,
 }
 
 impl SaveData {
+    /// Save data with nothing saved yet, written to the path
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            last_update_check: None,
+            best_results: HashMap::new(),
+            recent_mazes: vec![],
+            daily_results: BTreeMap::new(),
+            campaign_results: BTreeMap::new(),
+            achievements: BTreeMap::new(),
+            wins: 0,
+            xp: 0,
+            coins: 0,
+            preset_modes: BTreeMap::new(),
+            history: vec![],
+            seed_counter: 0,
+            surprises: vec![],
+            path,
+        }
+    }
+
     pub fn load() -> Result<Self, ron::Error> {
         match Self::load_from(&save_data_path()) {
             Ok(data) => Ok(data),
-            Err(ron::Error::Io(_)) => Ok(SaveData {
-                last_update_check: None,
-                best_results: HashMap::new(),
-                path: save_data_path(),
-            }),
+            Err(ron::Error::Io(_)) => Ok(Self::new(save_data_path())),
             Err(err) => Err(err),
         }
     }
 
     pub fn load_or() -> Self {
-        Self::load().unwrap_or_else(|_| Self {
-            last_update_check: None,
-            best_results: HashMap::new(),
-            path: save_data_path(),
-        })
+        Self::load().unwrap_or_else(|_| Self::new(save_data_path()))
     }
 
     fn load_from(path: &Path) -> Result<Self, ron::Error> {
@@ -101,22 +209,187 @@ impl SaveData {
             .unwrap_or(false)
     }
 
-    pub fn get_best_result(&self, mode: GameMode) -> Option<(i32, f32)> {
-        let result = self.best_results.get(&mode).copied()?;
-        Some((result.moves, result.seconds))
+    pub fn get_best_result(&self, mode: GameMode) -> Option<&SolveResult> {
+        self.best_results.get(&mode)
+    }
+
+    /// Run of the best result, empty for results saved before runs were recorded
+    pub fn get_best_run(&self, mode: GameMode) -> Option<&[RunStep]> {
+        Some(&self.best_results.get(&mode)?.run)
     }
 
     pub fn set_best_result(
         &mut self,
         mode: GameMode,
-        moves: i32,
-        seconds: f32,
+        result: SolveResult,
     ) -> Result<(), ron::Error> {
-        let old = self.best_results.get(&mode).copied();
-        if old.map_or(true, |old| old.seconds > seconds && old.moves >= moves) {
-            self.best_results
-                .insert(mode, SolveResult { moves, seconds });
+        let old = self.best_results.get(&mode);
+        if old.is_none_or(|old| old.seconds > result.seconds && old.moves >= result.moves) {
+            self.best_results.insert(mode, result);
         }
         self.write()
     }
+
+    pub fn get_daily_result(&self, date: NaiveDate) -> Option<&SolveResult> {
+        self.daily_results.get(&date)
+    }
+
+    pub fn get_daily_run(&self, date: NaiveDate) -> Option<&[RunStep]> {
+        Some(&self.daily_results.get(&date)?.run)
+    }
+
+    /// Saves the result of the daily challenge of given date, keeps the better one
+    ///
+    /// Every day has its own record, so replaying older dailies doesn't affect the others.
+    pub fn set_daily_result(
+        &mut self,
+        date: NaiveDate,
+        result: SolveResult,
+    ) -> Result<(), ron::Error> {
+        let old = self.daily_results.get(&date);
+        if old.is_none_or(|old| old.seconds > result.seconds && old.moves >= result.moves) {
+            self.daily_results.insert(date, result);
+        }
+        self.write()
+    }
+
+    pub fn get_stage_result(&self, id: &str) -> Option<&SolveResult> {
+        self.campaign_results.get(id)
+    }
+
+    pub fn get_stage_run(&self, id: &str) -> Option<&[RunStep]> {
+        Some(&self.campaign_results.get(id)?.run)
+    }
+
+    /// Saves the result of the campaign stage, keeps the better one
+    pub fn set_stage_result(&mut self, id: &str, result: SolveResult) -> Result<(), ron::Error> {
+        let old = self.campaign_results.get(id);
+        if old.is_none_or(|old| old.seconds > result.seconds && old.moves >= result.moves) {
+            self.campaign_results.insert(id.to_string(), result);
+        }
+        self.write()
+    }
+
+    /// Whether a maze with this hash was played recently, see [`cmaze::gameboard::Maze::canonical_hash`]
+    pub fn is_recent_maze(&self, hash: u64) -> bool {
+        self.recent_mazes.contains(&hash)
+    }
+
+    /// Seed of the next maze with the counter seed source
+    pub fn seed_counter(&self) -> u64 {
+        self.seed_counter
+    }
+
+    /// Moves the seed counter to the next seed
+    pub fn advance_seed_counter(&mut self) -> Result<(), ron::Error> {
+        self.seed_counter = self.seed_counter.wrapping_add(1);
+        self.write()
+    }
+
+    pub fn add_recent_maze(&mut self, hash: u64) -> Result<(), ron::Error> {
+        self.recent_mazes.retain(|h| *h != hash);
+        self.recent_mazes.push(hash);
+        if self.recent_mazes.len() > RECENT_MAZES_LEN {
+            self.recent_mazes.remove(0);
+        }
+        self.write()
+    }
+
+    /// When the achievement was unlocked, `None` if it's still locked
+    pub fn achievement_unlocked(&self, id: &str) -> Option<DateTime<Local>> {
+        self.achievements.get(id).copied()
+    }
+
+    pub fn wins(&self) -> u32 {
+        self.wins
+    }
+
+    /// Coins picked up in all the finished mazes
+    pub fn coins(&self) -> u32 {
+        self.coins
+    }
+
+    /// Game modes of the presets when they were last seen
+    pub fn preset_modes(&self) -> &BTreeMap<String, GameMode> {
+        &self.preset_modes
+    }
+
+    pub fn set_preset_modes(
+        &mut self,
+        modes: BTreeMap<String, GameMode>,
+    ) -> Result<(), ron::Error> {
+        self.preset_modes = modes;
+        self.write()
+    }
+
+    /// Played surprises, oldest first
+    pub fn surprises(&self) -> &[Surprise] {
+        &self.surprises
+    }
+
+    /// Adds the surprise, the oldest ones over [`SURPRISES_LEN`] are removed
+    pub fn add_surprise(&mut self, surprise: Surprise) -> Result<(), ron::Error> {
+        self.surprises.push(surprise);
+        let over = self.surprises.len().saturating_sub(SURPRISES_LEN);
+        self.surprises.drain(..over);
+        self.write()
+    }
+
+    /// Finished runs, oldest first
+    pub fn history(&self) -> &[RunRecord] {
+        &self.history
+    }
+
+    /// Adds the run to the history, the oldest runs over [`HISTORY_LEN`] are removed
+    pub fn add_run(&mut self, run: RunRecord) -> Result<(), ron::Error> {
+        self.history.push(run);
+        let over = self.history.len().saturating_sub(HISTORY_LEN);
+        self.history.drain(..over);
+        self.write()
+    }
+
+    /// Replaces the tags of the run finished at the date
+    pub fn set_run_tags(
+        &mut self,
+        date: DateTime<Local>,
+        tags: Vec<String>,
+    ) -> Result<(), ron::Error> {
+        if let Some(run) = self.history.iter_mut().rev().find(|run| run.date == date) {
+            run.tags = tags;
+        }
+        self.write()
+    }
+
+    pub fn level(&self) -> Level {
+        Level::of(self.xp)
+    }
+
+    /// Counts the finished run, returns the experience and achievements it brought
+    pub fn record_win(&mut self, win: &Win) -> Result<WinReward, ron::Error> {
+        self.wins += 1;
+        self.coins = self.coins.saturating_add(win.coins as u32);
+
+        let level = self.level().level;
+        let xp = xp_for(win);
+        self.xp = self.xp.saturating_add(xp);
+        let level_up = Some(self.level().level).filter(|new| *new > level);
+
+        let unlocked: Vec<_> = ACHIEVEMENTS
+            .iter()
+            .filter(|a| !self.achievements.contains_key(a.id))
+            .filter(|a| a.is_unlocked_by(win, self.wins))
+            .collect();
+
+        let now = Local::now();
+        for achievement in &unlocked {
+            self.achievements.insert(achievement.id.to_string(), now);
+        }
+
+        self.write()?;
+        Ok(WinReward {
+            achievements: unlocked,
+            xp,
+            level_up,
+        })
+    }
 }
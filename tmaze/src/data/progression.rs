@@ -0,0 +1,179 @@
+//! Experience and levels of the player, gained by finishing the mazes
+//!
+//! Experience of a run grows with the size of the maze, its floors, how fast it was
+//! finished and the coins picked up, see [`xp_for`]. Levels unlock the presets in [`PRESET_UNLOCKS`] and the player
+//! characters in [`CHAR_UNLOCKS`].
+
+use cmaze::{
+    dims::*,
+    game::{GoalObjective, GoalPlacement, ItemPlacement},
+};
+
+use super::achievements::{Achievement, Win};
+
+/// Experience needed for the second level, every next one needs this much more
+const LEVEL_STEP: u32 = 50;
+
+/// Seconds per cell, runs faster than this get more experience
+const PAR_SECONDS_PER_CELL: f32 = 0.25;
+
+/// Experience for every coin picked up
+const XP_PER_COIN: u32 = 2;
+
+/// Built-in preset, listed locked in the size menu until the level is reached
+#[derive(Debug, Clone, Copy)]
+pub struct PresetUnlock {
+    pub level: u32,
+    pub title: &'static str,
+    pub game_mode: GameMode,
+}
+
+const fn mode(size: Dims3D, is_tower: bool) -> GameMode {
+    GameMode {
+        size,
+        is_tower,
+        goals: GoalPlacement::Corner,
+        objective: GoalObjective::ReachAny,
+        braid: 0,
+        braid_floors: 0,
+        items: ItemPlacement::NONE,
+    }
+}
+
+pub const PRESET_UNLOCKS: [PresetUnlock; 4] = [
+    PresetUnlock {
+        level: 3,
+        title: "80x40 Labyrinth",
+        game_mode: mode(Dims3D(80, 40, 1), false),
+    },
+    PresetUnlock {
+        level: 5,
+        title: "12x12x6 Tower",
+        game_mode: mode(Dims3D(12, 12, 6), true),
+    },
+    PresetUnlock {
+        level: 8,
+        title: "40x20x5 Catacombs",
+        game_mode: mode(Dims3D(40, 20, 5), false),
+    },
+    PresetUnlock {
+        level: 12,
+        title: "150x60x3 Colossus",
+        game_mode: mode(Dims3D(150, 60, 3), false),
+    },
+];
+
+/// Player characters unlocked by the levels, next to the default ones
+pub const CHAR_UNLOCKS: [(u32, char); 4] = [(2, '◆'), (4, '♦'), (6, '☻'), (10, '♛')];
+
+/// Level of the player and the progress to the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Level {
+    pub level: u32,
+    /// Experience gained since the start of the level
+    pub xp: u32,
+    /// Experience needed for the next level
+    pub needed: u32,
+}
+
+impl Level {
+    pub fn of(total_xp: u32) -> Self {
+        let mut level = 1;
+        let mut xp = total_xp;
+        while xp >= level * LEVEL_STEP {
+            xp -= level * LEVEL_STEP;
+            level += 1;
+        }
+
+        Self {
+            level,
+            xp,
+            needed: level * LEVEL_STEP,
+        }
+    }
+
+    pub fn unlocked_chars(&self) -> Vec<char> {
+        CHAR_UNLOCKS
+            .iter()
+            .filter(|(level, _)| *level <= self.level)
+            .map(|(_, ch)| *ch)
+            .collect()
+    }
+}
+
+/// Experience of the finished run
+pub fn xp_for(win: &Win) -> u32 {
+    let Dims3D(w, h, d) = win.size;
+    let cells = (w * h * d).max(1) as f32;
+
+    let base = cells.sqrt() * (1.0 + 0.5 * (d - 1) as f32);
+    let par = cells * PAR_SECONDS_PER_CELL;
+    let speed = (par / win.seconds.max(1.0)).clamp(0.5, 2.0);
+
+    let coins = (win.coins as u32).saturating_mul(XP_PER_COIN);
+    ((base * speed).round() as u32).max(1).saturating_add(coins)
+}
+
+/// What the finished run brought, see [`super::SaveData::record_win`]
+#[derive(Debug, Clone)]
+pub struct WinReward {
+    pub achievements: Vec<&'static Achievement>,
+    pub xp: u32,
+    /// New level, if the run reached one
+    pub level_up: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn win(size: Dims3D, seconds: f32) -> Win {
+        Win {
+            size,
+            seconds,
+            moves: 0,
+            backtracked: false,
+            daily: false,
+            coins: 0,
+        }
+    }
+
+    #[test]
+    fn levels_need_more_xp() {
+        assert_eq!(
+            Level::of(0),
+            Level {
+                level: 1,
+                xp: 0,
+                needed: 50
+            }
+        );
+        assert_eq!(Level::of(49).level, 1);
+        assert_eq!(
+            Level::of(50),
+            Level {
+                level: 2,
+                xp: 0,
+                needed: 100
+            }
+        );
+        assert_eq!(
+            Level::of(175),
+            Level {
+                level: 3,
+                xp: 25,
+                needed: 150
+            }
+        );
+    }
+
+    #[test]
+    fn bigger_and_faster_runs_give_more_xp() {
+        let small = xp_for(&win(Dims3D(10, 10, 1), 25.0));
+        assert_eq!(small, 10);
+        assert!(xp_for(&win(Dims3D(10, 10, 1), 10.0)) > small);
+        assert!(xp_for(&win(Dims3D(10, 10, 1), 100.0)) < small);
+        assert!(xp_for(&win(Dims3D(10, 10, 2), 50.0)) > small);
+        assert_eq!(xp_for(&win(Dims3D(1, 1, 1), 1000.0)), 1);
+    }
+}
@@ -0,0 +1,215 @@
+//! Random presets of "Surprise me" in the maze size menu
+//!
+//! Everything is picked from one seed, the size within the bounds from the settings, the
+//! algorithm and the modifiers. Modifiers the player finished more mazes with are picked more
+//! often, see [`modifier_chances`]. Surprises are saved with the seed, so the same maze can be
+//! played again or kept as a preset.
+
+use chrono::{DateTime, Local};
+use cmaze::gameboard::algorithms::MazeRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::stamina::Stamina,
+    settings::{MazeGenAlgo, MazePreset, PresetMode, Settings},
+};
+
+use super::history::RunRecord;
+
+/// Smallest width and height of a surprise
+pub const MIN_SIDE: u16 = 5;
+
+/// Chance of a modifier nobody played with yet
+const BASE_CHANCE: f64 = 0.15;
+/// Chance added to a modifier all the finished runs had
+const LIKED_CHANCE: f64 = 0.5;
+/// Seconds of the time limit per cell, with [`TIME_LIMIT_BASE`]
+const TIME_LIMIT_PER_CELL: f32 = 0.3;
+const TIME_LIMIT_BASE: f32 = 30.0;
+
+/// Surprise which was played, the same maze is generated from the seed again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Surprise {
+    pub date: DateTime<Local>,
+    /// Seed the preset was composed from and the maze generated with
+    pub seed: u64,
+    pub algorithm: MazeGenAlgo,
+    pub preset: MazePreset,
+}
+
+/// Modifiers a surprise can have, picked independently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Floors,
+    Tower,
+    Braid,
+    Coins,
+    Door,
+    Hardcore,
+    Stamina,
+    Timed,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 8] = [
+        Modifier::Floors,
+        Modifier::Tower,
+        Modifier::Braid,
+        Modifier::Coins,
+        Modifier::Door,
+        Modifier::Hardcore,
+        Modifier::Stamina,
+        Modifier::Timed,
+    ];
+
+    /// Whether the finished run had the modifier
+    fn in_run(self, run: &RunRecord) -> bool {
+        let mode = &run.game_mode;
+        let rules = run.env.as_ref().map_or(&[][..], |env| &env.modifiers[..]);
+        match self {
+            Modifier::Floors => mode.size.2 > 1,
+            Modifier::Tower => mode.is_tower,
+            Modifier::Braid => mode.braid > 0,
+            Modifier::Coins => mode.items.coins > 0,
+            Modifier::Door => mode.items.door,
+            Modifier::Hardcore => rules.iter().any(|rule| rule == "hardcore"),
+            Modifier::Stamina => run.exhausted.is_some(),
+            Modifier::Timed => rules.iter().any(|rule| rule.starts_with("time limit")),
+        }
+    }
+}
+
+/// Chance of every modifier in [`Modifier::ALL`], higher for the ones in more finished runs
+pub fn modifier_chances(history: &[RunRecord]) -> [f64; Modifier::ALL.len()] {
+    Modifier::ALL.map(|modifier| {
+        let runs = history.iter().filter(|run| modifier.in_run(run)).count();
+        let share = runs as f64 / history.len().max(1) as f64;
+        BASE_CHANCE + LIKED_CHANCE * share
+    })
+}
+
+/// Composes the surprise of the seed, it's the same for the same seed, bounds and history
+pub fn compose(seed: u64, settings: &Settings, history: &[RunRecord]) -> Surprise {
+    let mut rng = MazeRng::seed_from_u64(seed);
+    let side = |rng: &mut MazeRng, max: u64| {
+        let max = (max.min(u16::MAX as u64) as u16).max(MIN_SIDE);
+        rng.gen_range(MIN_SIDE..=max)
+    };
+
+    let width = side(&mut rng, settings.get_surprise_max_width());
+    let height = side(&mut rng, settings.get_surprise_max_height());
+    let algorithm = match rng.gen_bool(0.5) {
+        true => MazeGenAlgo::RandomKruskals,
+        false => MazeGenAlgo::DepthFirstSearch,
+    };
+
+    let chances = modifier_chances(history);
+    let picked: Vec<_> = Modifier::ALL
+        .into_iter()
+        .zip(chances)
+        .filter(|(_, chance)| rng.gen_bool(chance.min(1.0)))
+        .map(|(modifier, _)| modifier)
+        .collect();
+    let has = |modifier| picked.contains(&modifier);
+
+    let max_floors = settings.get_surprise_max_floors().clamp(1, 16) as u16;
+    let floors = has(Modifier::Floors) && max_floors > 1;
+
+    let depth = match floors {
+        true => rng.gen_range(2..=max_floors),
+        false => 1,
+    };
+    let cells = width as f32 * height as f32 * depth as f32;
+
+    let preset = MazePreset {
+        title: format!("Surprise {}", seed % 10_000),
+        width,
+        height,
+        depth,
+        tower: has(Modifier::Tower) && floors,
+        default: false,
+        goals: Default::default(),
+        objective: Default::default(),
+        braid: match has(Modifier::Braid) {
+            true => rng.gen_range(0.2..=0.6),
+            false => 0.0,
+        },
+        braid_floors: vec![],
+        hardcore: has(Modifier::Hardcore),
+        mode: match has(Modifier::Timed) {
+            true => PresetMode::Timed((cells * TIME_LIMIT_PER_CELL + TIME_LIMIT_BASE).round()),
+            false => PresetMode::Normal,
+        },
+        coins: match has(Modifier::Coins) {
+            true => rng.gen_range(3..=10),
+            false => 0,
+        },
+        door: has(Modifier::Door),
+        stamina: has(Modifier::Stamina).then(Stamina::default),
+        target: None,
+        settings: None,
+    };
+
+    Surprise {
+        date: Local::now(),
+        seed,
+        algorithm,
+        preset,
+    }
+}
+
+/// Short description of the surprise, like "34x21x2 tower, coins, hardcore"
+pub fn describe(preset: &MazePreset) -> String {
+    let size = match preset.depth {
+        1 => format!("{}x{}", preset.width, preset.height),
+        depth => format!("{}x{}x{}", preset.width, preset.height, depth),
+    };
+
+    let flags = [
+        (preset.tower, "tower"),
+        (preset.braid > 0.0, "loops"),
+        (preset.coins > 0, "coins"),
+        (preset.door, "door"),
+        (preset.hardcore, "hardcore"),
+        (preset.stamina.is_some(), "stamina"),
+        (preset.time_limit().is_some(), "timed"),
+    ];
+    let flags: Vec<_> = flags
+        .into_iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, name)| name)
+        .collect();
+
+    match flags.is_empty() {
+        true => size,
+        false => format!("{} {}", size, flags.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surprise_is_composed_from_the_seed() {
+        let mut settings = Settings::default();
+        settings
+            .set_surprise_max_width(12)
+            .set_surprise_max_height(8)
+            .set_surprise_max_floors(3);
+
+        for seed in 0..50 {
+            let surprise = compose(seed, &settings, &[]);
+            let again = compose(seed, &settings, &[]);
+            assert_eq!(surprise.algorithm, again.algorithm);
+            assert_eq!(describe(&surprise.preset), describe(&again.preset));
+
+            let preset = &surprise.preset;
+            assert!((MIN_SIDE..=12).contains(&preset.width));
+            assert!((MIN_SIDE..=8).contains(&preset.height));
+            assert!((1..=3).contains(&preset.depth));
+            assert!(!preset.tower || preset.depth > 1);
+        }
+    }
+}
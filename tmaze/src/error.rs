@@ -0,0 +1,95 @@
+//! Errors which are shown to the player, see [`crate::ui::ErrorPopup`]
+//!
+//! Every error has a short title, a message from its source and a hint how to recover,
+//! so a failure doesn't end the game and the player knows what to do about it.
+
+use std::{io, path::PathBuf};
+
+use cmaze::gameboard::algorithms::{GenErrorInstant, GenErrorThreaded};
+use thiserror::Error;
+
+#[cfg(feature = "net")]
+use crate::net::NetError;
+use crate::settings::{format::FormatError, theme::LoadError};
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("Generation failed, {0}")]
+    Generation(#[from] GenErrorThreaded),
+    #[error("Settings file {} is invalid, {source}", path.display())]
+    Settings {
+        path: PathBuf,
+        #[source]
+        source: FormatError,
+    },
+    #[error("Save data could not be loaded, {0}")]
+    SaveData(#[source] ron::Error),
+    #[error("Theme `{name}` could not be loaded, {source}")]
+    Theme {
+        name: String,
+        #[source]
+        source: LoadError,
+    },
+    /// File operation, `action` says what was being done, like "export the maze"
+    #[error("Could not {action}, {source}")]
+    Io {
+        action: &'static str,
+        #[source]
+        source: io::Error,
+    },
+    #[cfg(feature = "net")]
+    #[error("Online race failed, {0}")]
+    Net(#[from] NetError),
+}
+
+impl AppError {
+    pub fn io(action: &'static str, source: io::Error) -> Self {
+        Self::Io { action, source }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            AppError::Generation(_) => "Maze not generated",
+            AppError::Settings { .. } => "Invalid settings",
+            AppError::SaveData(_) => "Progress not loaded",
+            AppError::Theme { .. } => "Theme not loaded",
+            AppError::Io { .. } => "Something went wrong",
+            #[cfg(feature = "net")]
+            AppError::Net(_) => "Online race failed",
+        }
+    }
+
+    /// What the player can do about the error
+    pub fn hint(&self) -> &'static str {
+        match self {
+            AppError::Generation(GenErrorThreaded::GenerationError(
+                GenErrorInstant::InvalidSize(_),
+            )) => "Every side of the maze has to be at least 1",
+            AppError::Generation(_) => "Try again, or try a smaller maze",
+            AppError::Settings { .. } => {
+                "Defaults are used and changes are not saved until the file is fixed or deleted"
+            }
+            AppError::SaveData(_) => {
+                "Back up the save file now, it's overwritten when the next result is saved"
+            }
+            AppError::Theme { .. } => {
+                "Default theme is used, check the theme file or pick another one in the settings"
+            }
+            AppError::Io { source, .. } => match source.kind() {
+                io::ErrorKind::NotFound => "Check that the directory exists",
+                io::ErrorKind::PermissionDenied => "Check the permissions of the directory",
+                _ => "Try again, details are in the log file",
+            },
+            #[cfg(feature = "net")]
+            AppError::Net(NetError::Version(_)) => "Both players need the same version of TMaze",
+            #[cfg(feature = "net")]
+            AppError::Net(_) => "Check the address and that the other player is waiting",
+        }
+    }
+}
+
+impl From<GenErrorInstant> for AppError {
+    fn from(err: GenErrorInstant) -> Self {
+        Self::Generation(GenErrorThreaded::GenerationError(err))
+    }
+}
@@ -0,0 +1,258 @@
+//! Benchmark of the maze generators, run with `tmaze bench`
+//!
+//! Unlike the criterion benchmarks of `cmaze`, it runs on the installed binary and measures the
+//! mazes too, so a change of an algorithm shows up in the shape of the mazes as well as in the
+//! times. Seeds are fixed, so the same version generates the same mazes and the JSON output can
+//! be compared between versions in CI.
+
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use cmaze::{dims::*, gameboard::Maze};
+use serde::Serialize;
+
+use crate::settings::MazeGenAlgo;
+
+/// Square floors, the same as in the benchmarks of `cmaze`
+pub const DEFAULT_SIZES: [Dims3D; 3] = [Dims3D(10, 10, 1), Dims3D(50, 50, 1), Dims3D(150, 150, 1)];
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub algorithms: Vec<MazeGenAlgo>,
+    pub sizes: Vec<Dims3D>,
+    /// Mazes generated of every algorithm and size, at least one
+    pub runs: usize,
+    pub tower: bool,
+    /// Seed of the first maze, the next ones count up from it and wrap around
+    pub seed: u64,
+}
+
+/// Times in milliseconds, by the nearest rank
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    pub fn new(mut times: Vec<Duration>) -> Self {
+        times.sort();
+        let at = |p: f64| {
+            let rank = ((p * times.len() as f64).ceil() as usize).clamp(1, times.len().max(1));
+            times
+                .get(rank - 1)
+                .map_or(0.0, |time| time.as_secs_f64() * 1000.0)
+        };
+
+        Self {
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: at(1.0),
+        }
+    }
+}
+
+/// Results of one algorithm and size
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub algorithm: &'static str,
+    pub size: Dims3D,
+    pub runs: usize,
+    pub generation_ms: Percentiles,
+    /// Shortest path between the opposite corners
+    pub solve_ms: Percentiles,
+    /// Average ratio of the cells with a single passage
+    pub dead_ends: f64,
+    /// Average ratio of the cells with three or more passages
+    pub junctions: f64,
+    /// Average length of the solution between the opposite corners
+    pub solution_len: f64,
+}
+
+/// Name of the algorithm on the command line and in the results
+pub fn algorithm_name(algo: MazeGenAlgo) -> &'static str {
+    match algo {
+        MazeGenAlgo::RandomKruskals => "kruskal",
+        MazeGenAlgo::DepthFirstSearch => "dfs",
+    }
+}
+
+pub fn parse_algorithm(name: &str) -> Result<MazeGenAlgo, String> {
+    [MazeGenAlgo::RandomKruskals, MazeGenAlgo::DepthFirstSearch]
+        .into_iter()
+        .find(|algo| algorithm_name(*algo) == name)
+        .ok_or_else(|| format!("Unknown algorithm `{}`, use kruskal or dfs", name))
+}
+
+/// Parses sizes like `50x50` or `20x20x4`, sides fit in `u16` like the ones of `tmaze generate`
+pub fn parse_size(text: &str) -> Result<Dims3D, String> {
+    let parts = text
+        .split('x')
+        .map(|part| part.trim().parse::<u16>().map(i32::from))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| {
+            format!(
+                "Invalid size `{}`, use WIDTHxHEIGHT or WIDTHxHEIGHTxDEPTH",
+                text
+            )
+        })?;
+
+    match parts[..] {
+        [w, h] if w > 0 && h > 0 => Ok(Dims3D(w, h, 1)),
+        [w, h, d] if w > 0 && h > 0 && d > 0 => Ok(Dims3D(w, h, d)),
+        _ => Err(format!(
+            "Invalid size `{}`, all sides must be positive",
+            text
+        )),
+    }
+}
+
+fn generate(algo: MazeGenAlgo, size: Dims3D, tower: bool, seed: u64) -> Result<Maze, String> {
+    (algo.to_fn())(size, tower, seed)
+        .map_err(|err| format!("Invalid maze: {:?}", err))?
+        .wait()
+        .map_err(|err| format!("Generation failed: {:?}", err))
+}
+
+/// Generates the mazes and measures them, `progress` is called before every algorithm and size
+pub fn run(
+    options: &BenchOptions,
+    mut progress: impl FnMut(MazeGenAlgo, Dims3D),
+) -> Result<Vec<BenchResult>, String> {
+    let runs = options.runs;
+    let mut results = vec![];
+
+    for &algo in &options.algorithms {
+        for &size in &options.sizes {
+            progress(algo, size);
+
+            let mut generation = Vec::with_capacity(runs);
+            let mut solve = Vec::with_capacity(runs);
+            let (mut dead_ends, mut junctions, mut solution_len) = (0.0, 0.0, 0.0);
+
+            for i in 0..runs {
+                let start = Instant::now();
+                let maze = generate(
+                    algo,
+                    size,
+                    options.tower,
+                    options.seed.wrapping_add(i as u64),
+                )?;
+                generation.push(start.elapsed());
+
+                let (from, to) = (Dims3D(0, 0, 0), size - Dims3D(1, 1, 1));
+                let start = Instant::now();
+                let _ = maze.solve(from, to);
+                solve.push(start.elapsed());
+
+                let stats = maze.analyze(from, to);
+                let cells = stats.cells().max(1) as f64;
+                dead_ends += stats.dead_ends as f64 / cells;
+                junctions += stats.junctions as f64 / cells;
+                solution_len += stats.solution_len.unwrap_or_default() as f64;
+            }
+
+            results.push(BenchResult {
+                algorithm: algorithm_name(algo),
+                size,
+                runs,
+                generation_ms: Percentiles::new(generation),
+                solve_ms: Percentiles::new(solve),
+                dead_ends: dead_ends / runs as f64,
+                junctions: junctions / runs as f64,
+                solution_len: solution_len / runs as f64,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+/// Results as a table with a row for every algorithm and size
+pub fn table(results: &[BenchResult]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{:<9} {:>11} {:>5} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "algorithm",
+        "size",
+        "runs",
+        "gen p50",
+        "gen p90",
+        "gen p99",
+        "gen max",
+        "solve p50",
+        "dead ends",
+        "junctions",
+        "solution"
+    )
+    .unwrap();
+
+    for result in results {
+        let Dims3D(w, h, d) = result.size;
+        let ms = |time: f64| format!("{:.2}ms", time);
+        writeln!(
+            out,
+            "{:<9} {:>11} {:>5} {:>9} {:>9} {:>9} {:>9} {:>9} {:>8.1}% {:>8.1}% {:>9.0}",
+            result.algorithm,
+            format!("{}x{}x{}", w, h, d),
+            result.runs,
+            ms(result.generation_ms.p50),
+            ms(result.generation_ms.p90),
+            ms(result.generation_ms.p99),
+            ms(result.generation_ms.max),
+            ms(result.solve_ms.p50),
+            result.dead_ends * 100.0,
+            result.junctions * 100.0,
+            result.solution_len,
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_by_nearest_rank() {
+        let times = (1..=10).rev().map(Duration::from_millis).collect();
+        let percentiles = Percentiles::new(times);
+        assert_eq!(percentiles.p50, 5.0);
+        assert_eq!(percentiles.p90, 9.0);
+        assert_eq!(percentiles.p99, 10.0);
+        assert_eq!(percentiles.max, 10.0);
+
+        assert_eq!(Percentiles::new(vec![]).max, 0.0);
+    }
+
+    #[test]
+    fn sizes_are_parsed() {
+        assert_eq!(parse_size("50x20"), Ok(Dims3D(50, 20, 1)));
+        assert_eq!(parse_size("5x5x3"), Ok(Dims3D(5, 5, 3)));
+        assert!(parse_size("5x0").is_err());
+        assert!(parse_size("big").is_err());
+        assert_eq!(parse_size("65535x1"), Ok(Dims3D(65535, 1, 1)));
+        assert!(parse_size("65536x1").is_err());
+    }
+
+    #[test]
+    fn seeds_wrap_around() {
+        let options = BenchOptions {
+            algorithms: vec![MazeGenAlgo::RandomKruskals],
+            sizes: vec![Dims3D(4, 4, 1)],
+            runs: 2,
+            tower: false,
+            seed: u64::MAX,
+        };
+        let results = run(&options, |_, _| {}).unwrap();
+        assert_eq!(results[0].runs, 2);
+    }
+}
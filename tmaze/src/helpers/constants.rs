@@ -3,9 +3,16 @@ use rand::{self, seq::SliceRandom, thread_rng};
 const AVAILABLE_PLAYER_CHARS: [char; 8] = ['O', '□', '◇', '☆', '○', '■', '●', '¤'];
 
 pub const GOAL_CHAR: char = '$';
+pub const OPPONENT_CHAR: char = '@';
 
 pub fn get_random_player_char() -> char {
-    *AVAILABLE_PLAYER_CHARS.choose(&mut thread_rng()).unwrap()
+    get_random_player_char_with(&[])
+}
+
+/// Random character from the default ones and the `extra` ones, like the unlocked ones
+pub fn get_random_player_char_with(extra: &[char]) -> char {
+    let chars: Vec<_> = AVAILABLE_PLAYER_CHARS.iter().chain(extra).collect();
+    **chars.choose(&mut thread_rng()).unwrap()
 }
 
 pub mod colors {
@@ -45,14 +52,47 @@ pub mod paths {
         theme_path().join(theme)
     }
 
+    pub fn scripts_path() -> PathBuf {
+        base_path().join("scripts/")
+    }
+
+    pub fn preset_path() -> PathBuf {
+        base_path().join("presets/")
+    }
+
+    /// TOML settings are used if they exist, RON ones otherwise
     pub fn settings_path() -> PathBuf {
-        base_path().join("settings.ron")
+        let toml = base_path().join("settings.toml");
+        match toml.exists() {
+            true => toml,
+            false => base_path().join("settings.ron"),
+        }
     }
 
     pub fn save_data_path() -> PathBuf {
         base_path().join("data.ron")
     }
 
+    pub fn replay_path() -> PathBuf {
+        base_path().join("replays/")
+    }
+
+    pub fn gallery_path() -> PathBuf {
+        base_path().join("gallery/")
+    }
+
+    pub fn maze_spec_path() -> PathBuf {
+        base_path().join("mazes/")
+    }
+
+    pub fn export_path() -> PathBuf {
+        base_path().join("exports/")
+    }
+
+    pub fn overlay_path() -> PathBuf {
+        base_path().join("overlay/")
+    }
+
     pub fn log_file_path() -> PathBuf {
         base_path().join("log.txt")
     }
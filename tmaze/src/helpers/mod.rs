@@ -1,11 +1,19 @@
+pub mod bench;
 pub mod constants;
+#[cfg(feature = "print")]
+pub mod print;
 pub mod strings;
 
 use core::fmt;
+use std::{collections::HashMap, path::PathBuf};
 
 use crossterm::event::KeyEventKind;
+use serde::{Deserialize, Serialize};
 
-use cmaze::{dims::*, gameboard::Maze};
+use cmaze::{
+    dims::*,
+    gameboard::{CellWall, Maze},
+};
 
 #[inline]
 pub const fn line_center(container_start: i32, container_end: i32, item_width: i32) -> i32 {
@@ -20,10 +28,90 @@ pub const fn box_center(container_start: Dims, container_end: Dims, box_dims: Di
     )
 }
 
+/// Unique path of a new printed or exported maze in the exports directory
+pub fn new_export_file(extension: &str) -> PathBuf {
+    let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    constants::paths::export_path().join(format!("maze-{}.{}", time, extension))
+}
+
 #[inline]
-pub fn maze_render_size(maze: &Maze) -> Dims {
+pub fn maze_render_size(maze: &Maze, zoom: Zoom) -> Dims {
     let msize = maze.size();
-    Dims(msize.0, msize.1) * 2 + Dims(1, 1)
+    let normal = Dims(msize.0, msize.1) * 2 + Dims(1, 1);
+    match zoom {
+        Zoom::Braille => Dims((normal.0 + 1) / 2, (normal.1 + 3) / 4),
+        Zoom::HalfBlock => Dims(normal.0, (normal.1 + 1) / 2),
+        Zoom::Compact => Dims(msize.0, msize.1),
+        Zoom::Normal => normal,
+        Zoom::Large => Dims(msize.0 * 4, msize.1 * 2) + Dims(1, 1),
+    }
+}
+
+/// Scale of the rendered maze
+///
+/// Positions on the board are given in the normal zoom, like from [`maze2screen`], and
+/// converted by [`Zoom::board_pos`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Zoom {
+    /// Walls drawn with braille dots, a character has 2x4 of them
+    Braille,
+    /// Cell is one character showing the passages out of it, without walls
+    Compact,
+    /// Walls drawn with half blocks, a character has two of them
+    HalfBlock,
+    /// Cell is one character with walls around it
+    #[default]
+    Normal,
+    /// Cell is three characters wide with walls around it
+    Large,
+}
+
+impl Zoom {
+    pub fn zoom_in(self) -> Option<Self> {
+        match self {
+            Zoom::Braille => Some(Zoom::Compact),
+            Zoom::Compact => Some(Zoom::HalfBlock),
+            Zoom::HalfBlock => Some(Zoom::Normal),
+            Zoom::Normal => Some(Zoom::Large),
+            Zoom::Large => None,
+        }
+    }
+
+    pub fn zoom_out(self) -> Option<Self> {
+        match self {
+            Zoom::Braille => None,
+            Zoom::Compact => Some(Zoom::Braille),
+            Zoom::HalfBlock => Some(Zoom::Compact),
+            Zoom::Normal => Some(Zoom::HalfBlock),
+            Zoom::Large => Some(Zoom::Normal),
+        }
+    }
+
+    /// Characters of a cell between its walls
+    pub fn cell_width(self) -> i32 {
+        match self {
+            Zoom::Large => 3,
+            _ => 1,
+        }
+    }
+
+    /// Walls are drawn as pixels, several cells share a character, so only the walls, goals and
+    /// players are drawn
+    pub fn is_dense(self) -> bool {
+        matches!(self, Zoom::Braille | Zoom::HalfBlock)
+    }
+
+    /// Converts a position on the board in normal zoom to this zoom, walls of the compact zoom
+    /// fall on the next cell
+    pub fn board_pos(self, pos: Dims) -> Dims {
+        match self {
+            Zoom::Braille => Dims(pos.0.div_euclid(2), pos.1.div_euclid(4)),
+            Zoom::HalfBlock => Dims(pos.0, pos.1.div_euclid(2)),
+            Zoom::Compact => Dims(pos.0.div_euclid(2), pos.1.div_euclid(2)),
+            Zoom::Normal => pos,
+            Zoom::Large => Dims(pos.0 * 2, pos.1),
+        }
+    }
 }
 
 #[inline]
@@ -135,6 +223,52 @@ pub fn maze2screen(pos_on_maze: impl Into<Dims3D>) -> Dims {
     Dims(pos_on_maze.0 * 2 + 1, pos_on_maze.1 * 2 + 1)
 }
 
+/// Lines of a path on the `floor`, by their screen position relative to the maze
+///
+/// Every step is the cell it starts in and its direction, steps between floors have no line.
+/// Each line has the index of the latest step which goes through it.
+pub fn path_lines(
+    steps: impl IntoIterator<Item = (Dims3D, CellWall)>,
+    floor: i32,
+) -> HashMap<Dims, (LineDir, usize)> {
+    use CellWall::*;
+
+    // screen position -> (left, top, right, bottom, index of the latest step)
+    let mut segments: HashMap<Dims, ([bool; 4], usize)> = HashMap::new();
+    let mut connect = |pos: Dims, wall: CellWall, index: usize| {
+        let (dirs, latest) = segments.entry(pos).or_default();
+        match wall {
+            Left => dirs[0] = true,
+            Top => dirs[1] = true,
+            Right => dirs[2] = true,
+            Bottom => dirs[3] = true,
+            Up | Down => {}
+        }
+        *latest = index;
+    };
+
+    for (i, (pos, dir)) in steps.into_iter().enumerate() {
+        if pos.2 != floor || matches!(dir, Up | Down) {
+            continue;
+        }
+
+        let off = Dims::from(dir.to_coord());
+        let start = maze2screen(pos);
+
+        connect(start, dir, i);
+        connect(start + off, dir, i);
+        connect(start + off, dir.reverse_wall(), i);
+        connect(start + off * 2, dir.reverse_wall(), i);
+    }
+
+    segments
+        .into_iter()
+        .map(|(pos, ([left, top, right, bottom], index))| {
+            (pos, (LineDir::from_bools(left, top, right, bottom), index))
+        })
+        .collect()
+}
+
 pub fn is_release(k: KeyEventKind) -> bool {
     k == KeyEventKind::Release
 }
@@ -200,3 +334,33 @@ macro_rules! make_even {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_lines_connect_steps() {
+        use CellWall::*;
+
+        let steps = [
+            (Dims3D(0, 0, 0), Right),
+            (Dims3D(1, 0, 0), Bottom),
+            (Dims3D(1, 1, 0), Up),
+            (Dims3D(1, 1, 1), Left),
+        ];
+        let lines = path_lines(steps, 0);
+
+        let line = |x, y| {
+            let (line, index) = &lines[&Dims(x, y)];
+            (line.round(), *index)
+        };
+        assert_eq!(line(1, 1), (LineDir::OpenRight.round(), 0));
+        assert_eq!(line(2, 1), (LineDir::Horizontal.round(), 0));
+        assert_eq!(line(3, 1), (LineDir::BottomLeft.round(), 1));
+        assert_eq!(line(3, 2), (LineDir::Vertical.round(), 1));
+        assert_eq!(line(3, 3), (LineDir::OpenTop.round(), 1));
+        // the step between floors and the one on the other floor have no lines
+        assert_eq!(lines.len(), 5);
+    }
+}
@@ -0,0 +1,31 @@
+use std::{fs, io, path::Path};
+
+use cmaze::{
+    dims::Dims3D,
+    gameboard::Maze,
+    print::{to_pdf, to_svg, PrintOptions},
+};
+
+/// Writes a floor of the maze to `path`, as PDF if it ends with `.pdf`, SVG otherwise
+pub fn print_to_file(
+    maze: &Maze,
+    floor: i32,
+    start: Dims3D,
+    goals: &[Dims3D],
+    options: &PrintOptions,
+    path: &Path,
+) -> io::Result<()> {
+    let is_pdf = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+
+    let data = match is_pdf {
+        true => to_pdf(maze, floor, start, goals, options),
+        false => to_svg(maze, floor, start, goals, options).into_bytes(),
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)
+}
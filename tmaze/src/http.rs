@@ -0,0 +1,348 @@
+//! Local HTTP server with the state of the game, for stream overlays and dashboards
+//!
+//! Server listens only on localhost and every request has to carry the token from the settings,
+//! either as `Authorization: Bearer <token>` or as the `token` query parameter. Responses are
+//! JSON objects:
+//! - `GET /state` - current game, `null` when no game is played,
+//! - `GET /screenshot` - text of the last drawn frame, one string per line,
+//! - `POST /pause` - pauses the running game.
+//!
+//! Requests are handled on a separate thread, which only reads the [`GameStatus`] published by
+//! the game and sends [`Command`]s to the app, see [`HttpServer::try_recv`].
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use cmaze::{
+    dims::*,
+    game::{RunningGame, RunningGameState},
+};
+use serde::Serialize;
+
+/// Port used when the settings don't have one
+pub const DEFAULT_PORT: u16 = 7879;
+
+/// How long the app has to take a screenshot
+const SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest accepted request line and headers, the API has no use for anything longer
+const MAX_REQUEST: u64 = 8 * 1024;
+
+/// Game as it's seen from the outside, published by the game activity
+#[derive(Debug, Clone, Serialize)]
+pub struct GameStatus {
+    pub maze: MazeStatus,
+    pub pos: Dims3D,
+    /// Game time in milliseconds
+    pub elapsed: u64,
+    pub moves: usize,
+    pub state: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MazeStatus {
+    pub size: Dims3D,
+    pub is_tower: bool,
+    pub seed: u64,
+    pub goals: Vec<Dims3D>,
+    pub remaining_goals: usize,
+}
+
+impl GameStatus {
+    pub fn new(game: &RunningGame) -> Self {
+        let mode = game.get_game_mode();
+        Self {
+            maze: MazeStatus {
+                size: mode.size,
+                is_tower: mode.is_tower,
+                seed: game.get_seed(),
+                goals: game.get_goals().to_vec(),
+                remaining_goals: game.get_remaining_goals(),
+            },
+            pos: game.get_player_pos(),
+            elapsed: game.get_elapsed().unwrap_or_default().as_millis() as u64,
+            moves: game.get_move_count(),
+            state: match game.get_state() {
+                RunningGameState::NotStarted => "not_started",
+                RunningGameState::Running => "running",
+                RunningGameState::Paused => "paused",
+                RunningGameState::Finished => "finished",
+                RunningGameState::Quitted => "quitted",
+            },
+        }
+    }
+}
+
+/// Request of a client, handled by the app
+pub enum Command {
+    /// Pause the running game
+    Pause,
+    /// Send the last drawn frame, one string per line
+    Screenshot(Sender<Vec<String>>),
+}
+
+/// Status of the game, shared with the server thread
+#[derive(Debug, Clone, Default)]
+struct StatusHandle(Arc<Mutex<Option<GameStatus>>>);
+
+impl StatusHandle {
+    fn set(&self, status: Option<GameStatus>) {
+        *self.0.lock().unwrap() = status;
+    }
+
+    fn get(&self) -> Option<GameStatus> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Publishes the status of a game, it's cleared when the publisher is dropped with the game
+pub struct Publisher(StatusHandle);
+
+impl Publisher {
+    pub fn publish(&self, game: &RunningGame) {
+        self.0.set(Some(GameStatus::new(game)));
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        self.0.set(None);
+    }
+}
+
+/// Running server, it stops with the app
+pub struct HttpServer {
+    status: StatusHandle,
+    commands: Receiver<Command>,
+    port: u16,
+}
+
+impl HttpServer {
+    /// Starts the server on localhost, port 0 picks any free one
+    pub fn start(port: u16, token: String) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let port = listener.local_addr()?.port();
+        let status = StatusHandle::default();
+        let (tx, commands) = mpsc::channel();
+
+        let shared = status.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::warn!("HTTP connection failed: {}", err);
+                        continue;
+                    }
+                };
+                if let Err(err) = handle(stream, &token, &shared, &tx) {
+                    log::debug!("HTTP request failed: {}", err);
+                }
+            }
+        });
+
+        log::info!("HTTP server listening on http://127.0.0.1:{}", port);
+        Ok(Self {
+            status,
+            commands,
+            port,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Publisher for a new game, there is only one game at a time
+    pub fn publisher(&self) -> Publisher {
+        Publisher(self.status.clone())
+    }
+
+    /// Next command from a client, if there is any
+    pub fn try_recv(&self) -> Option<Command> {
+        self.commands.try_recv().ok()
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    /// Token from the header or the query
+    token: Option<String>,
+}
+
+/// Reads the request line and the headers, [`None`] if they're longer than [`MAX_REQUEST`]
+fn read_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream).take(MAX_REQUEST);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if reader.limit() == 0 {
+        return Ok(None);
+    }
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut token = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("token="))
+        .map(String::from);
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if reader.limit() == 0 {
+            return Ok(None);
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                if let Some(bearer) = value.trim().strip_prefix("Bearer ") {
+                    token = Some(bearer.trim().to_string());
+                }
+            }
+        }
+    }
+
+    Ok(Some(Request {
+        method,
+        path: path.to_string(),
+        token,
+    }))
+}
+
+fn handle(
+    stream: TcpStream,
+    token: &str,
+    status: &StatusHandle,
+    commands: &Sender<Command>,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let Some(request) = read_request(&stream)? else {
+        return respond(stream, 431, error("request too large"));
+    };
+
+    let (code, body) = if request.token.as_deref() != Some(token) {
+        (401, error("invalid token"))
+    } else {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/state") => (200, json(&status.get())),
+            ("POST", "/pause") => match commands.send(Command::Pause) {
+                Ok(()) => (202, json(&serde_json::json!({ "ok": true }))),
+                Err(_) => (503, error("app is closing")),
+            },
+            ("GET", "/screenshot") => {
+                let (tx, rx) = mpsc::channel();
+                let lines = commands
+                    .send(Command::Screenshot(tx))
+                    .ok()
+                    .and_then(|_| rx.recv_timeout(SCREENSHOT_TIMEOUT).ok());
+                match lines {
+                    Some(lines) => (200, json(&serde_json::json!({ "lines": lines }))),
+                    None => (503, error("screenshot not taken")),
+                }
+            }
+            (_, "/state" | "/pause" | "/screenshot") => (405, error("method not allowed")),
+            _ => (404, error("not found")),
+        }
+    };
+
+    respond(stream, code, body)
+}
+
+fn respond(mut stream: TcpStream, code: u16, body: String) -> io::Result<()> {
+    let reason = match code {
+        200 => "OK",
+        202 => "Accepted",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Service Unavailable",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "null".to_string())
+}
+
+fn error(message: &str) -> String {
+    json(&serde_json::json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(port: u16, head: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(stream, "{}\r\n\r\n", head).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn requests_need_the_token() {
+        let server = HttpServer::start(0, "secret".to_string()).unwrap();
+        let port = server.port();
+
+        assert!(request(port, "GET /state HTTP/1.1").starts_with("HTTP/1.1 401"));
+        assert!(request(port, "GET /state?token=nope HTTP/1.1").starts_with("HTTP/1.1 401"));
+
+        let response = request(port, "GET /state?token=secret HTTP/1.1");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("null"));
+
+        let response = request(port, "POST /pause HTTP/1.1\r\nAuthorization: Bearer secret");
+        assert!(response.starts_with("HTTP/1.1 202"));
+        assert!(matches!(server.try_recv(), Some(Command::Pause)));
+
+        assert!(request(port, "GET /pause?token=secret HTTP/1.1").starts_with("HTTP/1.1 405"));
+    }
+
+    #[test]
+    fn long_requests_are_rejected() {
+        let server = HttpServer::start(0, "secret".to_string()).unwrap();
+        let port = server.port();
+
+        let header = format!("X-Long: {}", "a".repeat(MAX_REQUEST as usize));
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "GET /state?token=secret HTTP/1.1\r\n{}\r\n\r\n",
+            header
+        )
+        .unwrap();
+        // the rest of the request is never read, so the connection may be reset after the answer
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        assert!(response.starts_with("HTTP/1.1 431"), "{:?}", response);
+
+        // the server still answers the next client
+        let response = request(port, "GET /state?token=secret HTTP/1.1");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(!response.contains("Access-Control-Allow-Origin"));
+    }
+}
@@ -1,8 +1,16 @@
 pub mod app;
+pub mod content;
 pub mod data;
+pub mod error;
 pub mod helpers;
+#[cfg(feature = "http")]
+pub mod http;
 pub mod logging;
+#[cfg(feature = "net")]
+pub mod net;
 pub mod renderer;
+#[cfg(feature = "lua")]
+pub mod scripts;
 pub mod settings;
 #[cfg(feature = "sound")]
 pub mod sound;
@@ -119,7 +119,7 @@ impl UiLogs {
         }
     }
 
-    fn borrow_mut_logs(&self) -> MutexGuard<Logs> {
+    fn borrow_mut_logs(&self) -> MutexGuard<'_, Logs> {
         self.logs.lock().expect("a thread holding log panicked")
     }
 }
@@ -232,11 +232,19 @@ impl AppLogger {
         (logger, ui_logs)
     }
 
+    /// Set this logger as the global logger
+    ///
+    /// Only the first logger in the process is used, later calls are ignored,
+    /// so multiple apps (e.g. in tests) can be created.
     pub fn init(self) {
-        self.register_panic_hook();
-        let log_ref = Box::<_>::leak(Box::new(self));
-        log::set_logger(log_ref).unwrap();
-        log::set_max_level(log::LevelFilter::Trace);
+        static INITIALIZED: std::sync::Once = std::sync::Once::new();
+
+        INITIALIZED.call_once(|| {
+            self.register_panic_hook();
+            let log_ref = Box::<_>::leak(Box::new(self));
+            log::set_logger(log_ref).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
     }
 
     fn register_panic_hook(&self) {
@@ -265,7 +273,7 @@ impl AppLogger {
         }));
     }
 
-    fn borrow_mut_logs(&self) -> MutexGuard<Logs> {
+    fn borrow_mut_logs(&self) -> MutexGuard<'_, Logs> {
         self.logs.lock().expect("a thread holding log panicked")
     }
 }
@@ -314,8 +322,11 @@ pub fn logging_theme_resolver() -> crate::settings::theme::ThemeResolver {
 
     resolver
         .link("log.message", "text")
+        .desc("Messages of the log in the corner")
         .link("log.source", "text")
-        .link("log.extra", "border");
+        .desc("Where the messages of the log come from")
+        .link("log.extra", "border")
+        .desc("Arrows between the sources and the messages of the log");
 
     resolver
 }
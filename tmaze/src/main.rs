@@ -1,13 +1,31 @@
+use std::path::PathBuf;
+
+use cmaze::{
+    dims::{Dims, Dims3D},
+    gameboard::algorithms::random_seed,
+};
 use tmaze::{
-    app::{game::MainMenu, Activity, App, GameError},
-    helpers::constants::paths::{save_data_path, settings_path},
-    settings::Settings,
+    app::{
+        app::init_theme_resolver, game::MainMenu, screensaver::ScreensaverActivity, Activity, App,
+        GameError,
+    },
+    helpers::{
+        bench::{self, BenchOptions},
+        constants::paths::{save_data_path, settings_path},
+    },
+    renderer::helpers::term_size,
+    settings::{MazeGenAlgo, Settings},
 };
 
+#[cfg(feature = "print")]
+use cmaze::game::GoalPlacement;
+#[cfg(feature = "print")]
+use tmaze::helpers::print::print_to_file;
+
 #[cfg(feature = "updates")]
 use tmaze::updates;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[clap(version, author, about, name = "tmaze")]
@@ -16,8 +34,23 @@ struct Args {
     reset_config: bool,
     #[clap(short, long, action, help = "Show config path and quit")]
     show_config_path: bool,
+    #[clap(long, action, help = "Convert RON config to TOML and quit")]
+    migrate_config: bool,
     #[clap(long, help = "Show config in debug format and quit")]
     debug_config: bool,
+    #[clap(
+        long,
+        action,
+        help = "Show theme keys and what they are based on and quit"
+    )]
+    print_styles: bool,
+    #[clap(
+        long,
+        action,
+        requires = "print_styles",
+        help = "Show what the theme keys are used for too"
+    )]
+    style_desc: bool,
     #[clap(short, long, action, help = "Delete all saved data and quit")]
     delete_data: bool,
     #[clap(
@@ -27,6 +60,218 @@ struct Args {
         help = "Run in read-only mode, no data will be saved"
     )]
     read_only: bool,
+    #[clap(
+        long,
+        value_name = "FRAMES",
+        help = "Run without a terminal for given number of frames, then print the last frame"
+    )]
+    headless: Option<usize>,
+    #[clap(
+        long,
+        action,
+        help = "Offer touch controls on start, as if no keyboard was detected"
+    )]
+    touch: bool,
+    #[clap(
+        long,
+        action,
+        help = "Describe the game in words for screen readers, for this run only"
+    )]
+    accessible: bool,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[clap(about = "Endlessly generate and solve mazes, any key exits")]
+    Screensaver,
+    #[clap(about = "Generate a maze and print it as text, or save it as SVG or PDF")]
+    Generate(GenerateArgs),
+    #[clap(about = "Generate many mazes and show how long it took and what they look like")]
+    Bench(BenchArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    #[clap(default_value_t = 20)]
+    width: u16,
+    #[clap(default_value_t = 10)]
+    height: u16,
+    #[clap(default_value_t = 1)]
+    depth: u16,
+    #[clap(long, help = "Generate a tower, floors are connected only once")]
+    tower: bool,
+    #[clap(long, help = "Use depth-first search instead of Kruskal's algorithm")]
+    dfs: bool,
+    #[clap(long, help = "Seed of the maze, same seed generates the same maze")]
+    seed: Option<u64>,
+    #[clap(
+        short,
+        long,
+        help = "Output file, `.svg` or `.pdf` are printable, anything else is text"
+    )]
+    output: Option<PathBuf>,
+    #[clap(long, default_value_t = 1, help = "Floor to print, from 1")]
+    floor: i32,
+    #[clap(long, help = "Paper size of printed mazes: A4, A5 or Letter")]
+    paper: Option<String>,
+    #[clap(long, help = "Line width of printed mazes, in millimeters")]
+    line_width: Option<f32>,
+}
+
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    #[clap(
+        short = 'n',
+        long,
+        default_value_t = 20,
+        help = "Mazes of every algorithm and size"
+    )]
+    runs: usize,
+    #[clap(
+        long = "size",
+        value_name = "WxH[xD]",
+        value_parser = bench::parse_size,
+        help = "Size of the mazes, can be repeated [default: 10x10, 50x50, 150x150]"
+    )]
+    sizes: Vec<Dims3D>,
+    #[clap(
+        long = "algorithm",
+        value_name = "NAME",
+        value_parser = bench::parse_algorithm,
+        help = "Algorithm, kruskal or dfs, can be repeated [default: all]"
+    )]
+    algorithms: Vec<MazeGenAlgo>,
+    #[clap(long, help = "Generate towers, floors are connected only once")]
+    tower: bool,
+    #[clap(
+        long,
+        default_value_t = 0,
+        help = "Seed of the first maze, the next ones count up"
+    )]
+    seed: u64,
+    #[clap(
+        long,
+        help = "Print the results as JSON, for comparing them between versions"
+    )]
+    json: bool,
+}
+
+fn run_bench(args: BenchArgs) -> Result<(), String> {
+    let options = BenchOptions {
+        algorithms: match args.algorithms.is_empty() {
+            true => vec![MazeGenAlgo::RandomKruskals, MazeGenAlgo::DepthFirstSearch],
+            false => args.algorithms,
+        },
+        sizes: match args.sizes.is_empty() {
+            true => bench::DEFAULT_SIZES.to_vec(),
+            false => args.sizes,
+        },
+        runs: args.runs.max(1),
+        tower: args.tower,
+        seed: args.seed,
+    };
+
+    let results = bench::run(&options, |algo, Dims3D(w, h, d)| {
+        let name = bench::algorithm_name(algo);
+        eprintln!(
+            "Generating {} {}x{}x{} mazes with {}",
+            options.runs, w, h, d, name
+        );
+    })?;
+
+    match args.json {
+        true => {
+            let json = serde_json::to_string_pretty(&results).map_err(|err| err.to_string())?;
+            println!("{}", json);
+        }
+        false => print!("{}", bench::table(&results)),
+    }
+    Ok(())
+}
+
+fn generate(args: GenerateArgs) -> Result<(), String> {
+    let size = Dims3D(args.width as i32, args.height as i32, args.depth as i32);
+    let algo = match args.dfs {
+        true => MazeGenAlgo::DepthFirstSearch,
+        false => MazeGenAlgo::RandomKruskals,
+    };
+
+    // checked first, so a big maze isn't generated for nothing
+    let floor = args.floor - 1;
+    if !(0..size.2).contains(&floor) {
+        return Err(format!("Floor must be between 1 and {}", size.2));
+    }
+
+    let seed = args.seed.unwrap_or_else(random_seed);
+    let maze = (algo.to_fn())(size, args.tower, seed)
+        .map_err(|err| format!("Invalid maze: {:?}", err))?
+        .wait()
+        .map_err(|err| format!("Generation failed: {:?}", err))?;
+
+    let Some(path) = args.output else {
+        print!("{}", maze.to_text(&[]));
+        return Ok(());
+    };
+
+    let printable = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["svg", "pdf"].contains(&ext.to_lowercase().as_str()));
+
+    if !printable {
+        return std::fs::write(&path, maze.to_text(&[])).map_err(|err| err.to_string());
+    }
+
+    #[cfg(feature = "print")]
+    {
+        let mut options = Settings::load(settings_path(), true)
+            .map_err(|err| format!("Failed to load settings: {}", err))?
+            .get_print_options();
+        if let Some(paper) = args.paper {
+            options.paper = paper.parse()?;
+        }
+        if let Some(width) = args.line_width {
+            options.line_width = width;
+        }
+
+        // same start and goal as a game of the default mode
+        let goals = GoalPlacement::default().positions(size);
+        print_to_file(&maze, floor, Dims3D(0, 0, 0), &goals, &options, &path)
+            .map_err(|err| err.to_string())
+    }
+
+    #[cfg(not(feature = "print"))]
+    {
+        let _ = (args.paper, args.line_width);
+        Err("TMaze was built without the `print` feature".to_string())
+    }
+}
+
+/// Prints the theme keys with the keys they inherit their style from, in columns
+fn print_styles(descriptions: bool) {
+    let resolver = init_theme_resolver();
+    let keys = resolver.keys();
+    let width = keys.iter().map(|key| key.len()).max().unwrap_or(0);
+    let based_width = keys
+        .iter()
+        .map(|key| resolver.get(key).len().max("default".len()))
+        .max()
+        .unwrap_or(0);
+
+    for key in keys {
+        let based_on = match resolver.get(key) {
+            "" => "default",
+            based_on => based_on,
+        };
+        match (descriptions, resolver.description(key)) {
+            (true, Some(desc)) => {
+                println!("{:width$}  {:based_width$}  {}", key, based_on, desc)
+            }
+            _ => println!("{:width$}  {}", key, based_on),
+        }
+    }
 }
 
 fn main() -> Result<(), GameError> {
@@ -37,6 +282,14 @@ fn main() -> Result<(), GameError> {
         return Ok(());
     }
 
+    if _args.migrate_config {
+        match Settings::migrate_to_toml(&settings_path()) {
+            Ok(path) => println!("Settings are in {}", path.display()),
+            Err(err) => eprintln!("{}\n{}", err, err.hint()),
+        }
+        return Ok(());
+    }
+
     if _args.show_config_path {
         let settings_path = settings_path();
         if let Some(s) = settings_path.to_str() {
@@ -52,17 +305,60 @@ fn main() -> Result<(), GameError> {
         return Ok(());
     }
 
+    if _args.print_styles {
+        print_styles(_args.style_desc);
+        return Ok(());
+    }
+
     if _args.delete_data {
         let _ = std::fs::remove_file(save_data_path());
         return Ok(());
     }
 
+    if let Some(frames) = _args.headless {
+        let (w, h) = term_size();
+        let menu = Activity::new_base_boxed("main menu", MainMenu::new());
+        let mut app = App::new_headless(menu, Dims(w as i32, h as i32), _args.read_only);
+        if _args.accessible {
+            app.enable_accessible();
+        }
+        app.run_headless([], frames);
+        print!("{}", app.last_frame());
+        return Ok(());
+    }
+
     better_panic::install();
 
+    match _args.command {
+        Some(Command::Screensaver) => {
+            App::new(ScreensaverActivity::new_activity(), _args.read_only).run();
+            return Ok(());
+        }
+        Some(Command::Generate(args)) => {
+            if let Err(err) = generate(args) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Command::Bench(args)) => {
+            if let Err(err) = run_bench(args) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
     let mut app = App::empty(_args.read_only);
     let menu = MainMenu::new();
     app.activities_mut()
         .push(Activity::new_base_boxed("main menu", menu));
+    app.suggest_touch_controls(_args.touch);
+    if _args.accessible {
+        app.enable_accessible();
+    }
 
     #[cfg(feature = "updates")]
     updates::check(app.data_mut());
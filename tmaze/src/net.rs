@@ -0,0 +1,289 @@
+//! Protocol of the online race, two TMaze instances connected over TCP
+//!
+//! Messages are JSON objects, one per line. The joining side sends [`Message::Hello`] first,
+//! the host answers with its own hello and the [`Message::Maze`] to race on, both sides then
+//! generate the same maze from the seed and send [`Message::Ready`]. During the race only the
+//! positions and the finish are sent.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use cmaze::dims::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::settings::MazeGenAlgo;
+
+/// Bumped on every incompatible change of [`Message`]
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Port used when the address doesn't have one
+pub const DEFAULT_PORT: u16 = 7878;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sending fails after this long, so a peer which stops reading doesn't freeze the game
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest accepted message, messages are small, anything longer is from a broken peer
+const MAX_LINE: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    Hello {
+        version: u32,
+    },
+    /// Maze of the race, sent by the host
+    Maze {
+        game_mode: GameMode,
+        algo: MazeGenAlgo,
+        seed: u64,
+    },
+    /// Maze was generated, race starts when both sides are ready
+    Ready,
+    Position {
+        pos: Dims3D,
+    },
+    /// Player reached the goal, time is in milliseconds
+    Finished {
+        elapsed: u64,
+        moves: usize,
+    },
+    /// Player left the race
+    Bye,
+}
+
+#[derive(Debug, Error)]
+pub enum NetError {
+    #[error("connection failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid message: {0}")]
+    Protocol(#[from] serde_json::Error),
+    #[error("other side uses version {0} of the protocol, this one {PROTOCOL_VERSION}")]
+    Version(u32),
+    #[error("unexpected message: {0:?}")]
+    Unexpected(Message),
+    #[error("other side closed the connection")]
+    Closed,
+    #[error("maze of size {0:?} asked by the other side is too big")]
+    MazeTooBig(Dims3D),
+    #[error("message longer than {MAX_LINE} bytes")]
+    TooLong,
+}
+
+/// Connection to the other player, messages are read on a separate thread
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Receiver<Result<Message, NetError>>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (tx, incoming) = mpsc::channel();
+
+        thread::spawn(move || loop {
+            let msg = match read_line(&mut reader) {
+                Ok(Some(line)) => serde_json::from_slice(&line).map_err(NetError::from),
+                Ok(None) => Err(NetError::Closed),
+                Err(err) => Err(err),
+            };
+            let failed = msg.is_err();
+            if tx.send(msg).is_err() || failed {
+                return;
+            }
+        });
+
+        Ok(Self { stream, incoming })
+    }
+
+    /// Connects to the address, [`DEFAULT_PORT`] is used if it doesn't have a port
+    pub fn connect(address: &str) -> Result<Self, NetError> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "address not found");
+        for addr in socket_addrs(address)? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => return Ok(Self::new(stream)?),
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err.into())
+    }
+
+    pub fn send(&mut self, msg: &Message) -> Result<(), NetError> {
+        let mut line = serde_json::to_string(msg)?;
+        line.push('\n');
+        self.stream.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Next received message, if there is any
+    pub fn try_recv(&self) -> Option<Result<Message, NetError>> {
+        match self.incoming.try_recv() {
+            Ok(msg) => Some(msg),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(NetError::Closed)),
+        }
+    }
+}
+
+impl Drop for Connection {
+    /// Says goodbye and closes the connection
+    fn drop(&mut self) {
+        let _ = self.send(&Message::Bye);
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Reads one line, without the newline, [`None`] if the connection was closed
+///
+/// Fails with [`NetError::TooLong`] instead of buffering a line longer than [`MAX_LINE`].
+fn read_line(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>, NetError> {
+    let mut line = vec![];
+    reader.take(MAX_LINE + 1).read_until(b'\n', &mut line)?;
+    match line.pop() {
+        Some(b'\n') => Ok(Some(line)),
+        None => Ok(None),
+        Some(_) if line.len() as u64 >= MAX_LINE => Err(NetError::TooLong),
+        // closed in the middle of a message
+        Some(_) => Err(NetError::Closed),
+    }
+}
+
+/// Resolves the address of the host, like `example.com`, `10.0.0.2:7000`, `::1` or `[::1]:7000`
+///
+/// IPv6 addresses need brackets when they have a port, they are full of colons.
+fn socket_addrs(address: &str) -> io::Result<Vec<SocketAddr>> {
+    if let Ok(addr) = address.parse::<SocketAddr>() {
+        return Ok(vec![addr]);
+    }
+
+    let ip = address.strip_prefix('[').and_then(|a| a.strip_suffix(']'));
+    if let Ok(ip) = ip.unwrap_or(address).parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, DEFAULT_PORT)]);
+    }
+
+    let host_port = address
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)));
+    let addrs = host_port
+        .unwrap_or((address, DEFAULT_PORT))
+        .to_socket_addrs()?;
+    Ok(addrs.collect())
+}
+
+/// Listens for the other player, without blocking
+///
+/// Binds all network interfaces, not just localhost, so the other player can join
+/// from another computer. Anyone who can reach the port can join the race.
+pub fn listen(port: u16) -> io::Result<TcpListener> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Accepts the other player, if they already connected
+pub fn accept(listener: &TcpListener) -> io::Result<Option<Connection>> {
+    match listener.accept() {
+        Ok((stream, addr)) => {
+            log::info!("Player connected from {}", addr);
+            stream.set_nonblocking(false)?;
+            Connection::new(stream).map(Some)
+        }
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recv(conn: &Connection) -> Message {
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while std::time::Instant::now() < deadline {
+            if let Some(msg) = conn.try_recv() {
+                return msg.unwrap();
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("no message received");
+    }
+
+    #[test]
+    fn addresses_get_the_default_port() {
+        let addr = |address: &str| socket_addrs(address).unwrap();
+        let v4 = |port| SocketAddr::from(([127, 0, 0, 1], port));
+        let v6 = |port| SocketAddr::from((std::net::Ipv6Addr::LOCALHOST, port));
+
+        assert_eq!(addr("127.0.0.1"), [v4(DEFAULT_PORT)]);
+        assert_eq!(addr("127.0.0.1:9000"), [v4(9000)]);
+        assert_eq!(addr("::1"), [v6(DEFAULT_PORT)]);
+        assert_eq!(addr("[::1]"), [v6(DEFAULT_PORT)]);
+        assert_eq!(addr("[::1]:9000"), [v6(9000)]);
+        assert_eq!(addr("fe80::1")[0].port(), DEFAULT_PORT);
+        assert!(addr("localhost:9000").iter().all(|a| a.port() == 9000));
+    }
+
+    #[test]
+    fn messages_are_exchanged() {
+        let listener = listen(0).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let mut client = Connection::connect(&format!("127.0.0.1:{}", port)).unwrap();
+        let mut host = loop {
+            if let Some(conn) = accept(&listener).unwrap() {
+                break conn;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+
+        client
+            .send(&Message::Position {
+                pos: Dims3D(1, 2, 0),
+            })
+            .unwrap();
+        assert!(matches!(
+            recv(&host),
+            Message::Position {
+                pos: Dims3D(1, 2, 0)
+            }
+        ));
+
+        host.send(&Message::Finished {
+            elapsed: 1500,
+            moves: 7,
+        })
+        .unwrap();
+        assert!(matches!(
+            recv(&client),
+            Message::Finished {
+                elapsed: 1500,
+                moves: 7
+            }
+        ));
+
+        drop(client);
+        assert!(matches!(recv(&host), Message::Bye));
+    }
+
+    #[test]
+    fn long_lines_are_rejected() {
+        let mut reader = io::Cursor::new(vec![b' '; MAX_LINE as usize * 2]);
+        assert!(matches!(read_line(&mut reader), Err(NetError::TooLong)));
+
+        let mut reader = io::Cursor::new(b"{\"type\":\"ready\"}\n".to_vec());
+        assert_eq!(
+            read_line(&mut reader).unwrap().unwrap(),
+            b"{\"type\":\"ready\"}"
+        );
+        assert!(read_line(&mut reader).unwrap().is_none());
+    }
+}
@@ -0,0 +1,286 @@
+use std::sync::OnceLock;
+
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+
+/// Colors the terminal can show, colors of the theme are reduced to them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ColorSupport {
+    /// 16 basic colors
+    Basic,
+    /// 256 color palette
+    Ansi256,
+    /// 24-bit colors
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Colors of the current terminal, guessed from its environment variables, result is cached
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<ColorSupport> = OnceLock::new();
+
+        *DETECTED.get_or_init(|| {
+            let detected = match is_legacy_console() {
+                true => ColorSupport::Basic,
+                false => Self::from_env(|name| std::env::var(name).ok()),
+            };
+            log::debug!("Detected colors of the terminal: {:?}", detected);
+            detected
+        })
+    }
+
+    /// Colors of the terminal described by the variables, terminfo isn't read
+    ///
+    /// `COLORTERM` is the most reliable, then known terminals and `TERM`. Unknown terminals
+    /// get the 256 colors, which almost every terminal emulator has.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        if let Some("truecolor" | "24bit") = var("COLORTERM").as_deref() {
+            return ColorSupport::TrueColor;
+        }
+
+        if var("WT_SESSION").is_some() {
+            return ColorSupport::TrueColor;
+        }
+
+        match var("TERM_PROGRAM").as_deref() {
+            Some("iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty") => {
+                return ColorSupport::TrueColor
+            }
+            Some("Apple_Terminal") => return ColorSupport::Ansi256,
+            _ => {}
+        }
+
+        match var("TERM").as_deref() {
+            Some(term) if term.contains("truecolor") || term.contains("direct") => {
+                ColorSupport::TrueColor
+            }
+            Some(term) if term.contains("256") => ColorSupport::Ansi256,
+            Some("dumb" | "linux" | "vt100" | "vt220" | "ansi" | "cons25" | "xterm-color") => {
+                ColorSupport::Basic
+            }
+            Some(_) => ColorSupport::Ansi256,
+            // modern Windows consoles don't set `TERM`, but have all colors
+            None if cfg!(windows) => ColorSupport::TrueColor,
+            None => ColorSupport::Basic,
+        }
+    }
+}
+
+/// Workarounds for terminals with limited capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderCompat {
+    /// Replace non-ASCII characters, like box drawing, with ASCII ones
+    pub ascii: bool,
+    /// Colors are reduced to the ones the terminal has
+    pub colors: ColorSupport,
+    /// Wrap every frame in a synchronized update, so it's not torn
+    pub sync_updates: bool,
+}
+
+impl Default for RenderCompat {
+    fn default() -> Self {
+        Self {
+            ascii: false,
+            colors: ColorSupport::TrueColor,
+            sync_updates: true,
+        }
+    }
+}
+
+impl RenderCompat {
+    /// Fallbacks for legacy consoles and colors of the terminal, see [`ColorSupport::detect`]
+    pub fn detect() -> Self {
+        let legacy = is_legacy_console();
+        Self {
+            ascii: legacy,
+            colors: ColorSupport::detect(),
+            sync_updates: !legacy,
+        }
+    }
+
+    pub fn char(&self, ch: char) -> char {
+        if self.ascii {
+            to_ascii(ch)
+        } else {
+            ch
+        }
+    }
+
+    pub fn color(&self, color: Color) -> Color {
+        match self.colors {
+            ColorSupport::Basic => to_16_colors(color),
+            ColorSupport::Ansi256 => to_256_colors(color),
+            ColorSupport::TrueColor => color,
+        }
+    }
+}
+
+/// Whether we run in a console host without ANSI and Unicode support
+///
+/// That's the old conhost on Windows, unless it's hosted in Windows Terminal
+/// or another terminal which sets its own variables. Result is cached.
+pub fn is_legacy_console() -> bool {
+    static LEGACY: OnceLock<bool> = OnceLock::new();
+
+    *LEGACY.get_or_init(|| {
+        let var = |name| std::env::var_os(name).is_some();
+        cfg!(windows)
+            && !var("WT_SESSION")
+            && !var("TERM_PROGRAM")
+            && !var("TERM")
+            && std::env::var("ConEmuANSI").as_deref() != Ok("ON")
+    })
+}
+
+/// Closest ASCII character, box drawing is mapped to `-`, `|` and `+`
+pub fn to_ascii(ch: char) -> char {
+    match ch {
+        _ if ch.is_ascii() => ch,
+        '─' | '━' | '═' | '╴' | '╶' | '╸' | '╺' => '-',
+        '│' | '┃' | '║' | '╵' | '╷' | '╹' | '╻' => '|',
+        '\u{2500}'..='\u{257f}' => '+',
+        '▪' | '■' | '█' | '▓' => '#',
+        '▀' => '"',
+        '▄' => '_',
+        '\u{2800}' => ' ',
+        '\u{2801}'..='\u{28ff}' => ':',
+        '↑' => '^',
+        '↓' => 'v',
+        '←' => '<',
+        '→' => '>',
+        '⥮' => 'x',
+        '●' | '•' => '@',
+        '·' => '.',
+        '¢' => 'c',
+        '⚷' => 'k',
+        _ => '?',
+    }
+}
+
+/// Closest of the 16 basic colors
+pub fn to_16_colors(color: Color) -> Color {
+    const PALETTE: [(Color, (u8, u8, u8)); 16] = [
+        (Color::Black, (0, 0, 0)),
+        (Color::DarkRed, (128, 0, 0)),
+        (Color::DarkGreen, (0, 128, 0)),
+        (Color::DarkYellow, (128, 128, 0)),
+        (Color::DarkBlue, (0, 0, 128)),
+        (Color::DarkMagenta, (128, 0, 128)),
+        (Color::DarkCyan, (0, 128, 128)),
+        (Color::Grey, (192, 192, 192)),
+        (Color::DarkGrey, (128, 128, 128)),
+        (Color::Red, (255, 0, 0)),
+        (Color::Green, (0, 255, 0)),
+        (Color::Yellow, (255, 255, 0)),
+        (Color::Blue, (0, 0, 255)),
+        (Color::Magenta, (255, 0, 255)),
+        (Color::Cyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+
+    let (r, g, b) = match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(value) => ansi_to_rgb(value),
+        color => return color,
+    };
+
+    let dist = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    PALETTE
+        .into_iter()
+        .min_by_key(|(_, rgb)| dist(*rgb))
+        .map(|(color, _)| color)
+        .unwrap()
+}
+
+/// Closest color of the 256 color palette, from the color cube or the greys
+pub fn to_256_colors(color: Color) -> Color {
+    let Color::Rgb { r, g, b } = color else {
+        return color;
+    };
+
+    // levels of the cube are 0, 95, 135, 175, 215 and 255
+    let level = |c: u8| match c {
+        0..=47 => 0,
+        48..=114 => 1,
+        c => (c - 35) / 40,
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+
+    let avg = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let grey = 232 + (avg.saturating_sub(3) / 10).min(23);
+
+    let dist = |value: u8| {
+        let (r2, g2, b2) = ansi_to_rgb(value);
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    match dist(grey) < dist(cube) {
+        true => Color::AnsiValue(grey),
+        false => Color::AnsiValue(cube),
+    }
+}
+
+/// RGB value of a color from the 256 color palette
+fn ansi_to_rgb(value: u8) -> (u8, u8, u8) {
+    match value {
+        0..=15 => {
+            let base = if value & 8 != 0 { 255 } else { 128 };
+            let on = |bit: u8| if value & bit != 0 { base } else { 0 };
+            (on(1), on(2), on(4))
+        }
+        16..=231 => {
+            let i = value - 16;
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        232..=255 => {
+            let grey = 8 + (value - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> ColorSupport {
+        ColorSupport::from_env(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn colors_are_detected_from_env() {
+        use ColorSupport::*;
+
+        assert_eq!(
+            detect(&[("COLORTERM", "truecolor"), ("TERM", "xterm")]),
+            TrueColor
+        );
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), Ansi256);
+        assert_eq!(detect(&[("TERM", "linux")]), Basic);
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "Apple_Terminal"), ("TERM", "xterm")]),
+            Ansi256
+        );
+    }
+
+    #[test]
+    fn colors_are_reduced_to_the_palette() {
+        let rgb = |r, g, b| Color::Rgb { r, g, b };
+
+        assert_eq!(to_256_colors(rgb(255, 0, 0)), Color::AnsiValue(196));
+        assert_eq!(to_256_colors(rgb(95, 135, 175)), Color::AnsiValue(67));
+        assert_eq!(to_256_colors(rgb(128, 128, 128)), Color::AnsiValue(244));
+        assert_eq!(to_256_colors(Color::Red), Color::Red);
+        assert_eq!(to_16_colors(rgb(250, 10, 10)), Color::Red);
+    }
+}
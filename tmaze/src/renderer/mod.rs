@@ -1,5 +1,7 @@
+pub mod compat;
 pub mod drawable;
 pub mod helpers;
+pub mod output;
 
 use std::{
     io::{self, stdout, Write},
@@ -7,18 +9,32 @@ use std::{
 };
 
 use cmaze::dims::Dims;
-use crossterm::{event::Event, execute, style::ContentStyle, terminal, QueueableCommand};
+use crossterm::{
+    event::Event,
+    execute,
+    style::{Color, ContentStyle},
+    terminal,
+};
 use unicode_width::UnicodeWidthChar;
 
 use crate::settings::theme::Style;
 
-use self::{drawable::Drawable, helpers::term_size};
+use self::{
+    compat::RenderCompat,
+    drawable::Drawable,
+    helpers::term_size,
+    output::{FrameStats, Output},
+};
 
 pub struct Renderer {
     size: Dims,
+    /// Last shown frame, only kept when headless, the output thread has its own
     shown: Frame,
     hidden: Frame,
-    full_redraw: bool,
+    /// Thread writing the frames to the terminal, `None` when headless
+    output: Option<Output>,
+    compat: RenderCompat,
+    report_modifiers: bool,
 }
 
 impl Renderer {
@@ -28,18 +44,42 @@ impl Renderer {
         let hidden = Frame::new(size);
         let shown = Frame::new(size);
 
+        let compat = RenderCompat::detect();
+
         let mut ren = Renderer {
             size,
             shown,
             hidden,
-            full_redraw: true,
+            output: None,
+            compat,
+            report_modifiers: false,
         };
 
         ren.turn_on()?;
+        ren.output = Some(Output::spawn(ren.size, compat)?);
 
         Ok(ren)
     }
 
+    /// Create a renderer which never touches the terminal
+    ///
+    /// Frames are only swapped on [`Renderer::show`], the last shown frame
+    /// can be read with [`Renderer::shown_frame`].
+    pub fn new_headless(size: Dims) -> Self {
+        Renderer {
+            size,
+            shown: Frame::new(size),
+            hidden: Frame::new(size),
+            output: None,
+            compat: RenderCompat::default(),
+            report_modifiers: false,
+        }
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.output.is_none()
+    }
+
     fn turn_on(&mut self) -> io::Result<()> {
         self.register_panic_hook();
 
@@ -49,6 +89,7 @@ impl Renderer {
             crossterm::cursor::Hide,
             crossterm::terminal::EnterAlternateScreen,
             crossterm::event::EnableMouseCapture,
+            crossterm::event::EnableFocusChange,
         )?;
 
         self.on_resize(None);
@@ -58,12 +99,14 @@ impl Renderer {
 
     fn turn_off(&mut self) -> io::Result<()> {
         self.unregiser_panic_hook();
+        self.set_report_modifiers(false);
 
         crossterm::execute!(
             stdout(),
             crossterm::cursor::Show,
             crossterm::terminal::LeaveAlternateScreen,
             crossterm::event::DisableMouseCapture,
+            crossterm::event::DisableFocusChange,
         )?;
         crossterm::terminal::disable_raw_mode()?;
         Ok(())
@@ -79,6 +122,7 @@ impl Renderer {
                 crossterm::terminal::LeaveAlternateScreen,
                 crossterm::cursor::Show,
                 crossterm::event::DisableMouseCapture,
+                crossterm::event::DisableFocusChange,
             )
             .unwrap();
 
@@ -98,13 +142,19 @@ impl Renderer {
         self.size = size.unwrap_or_else(|| terminal::size().unwrap().into());
         self.shown.resize(self.size);
         self.hidden.resize(self.size);
-        self.full_redraw = true;
+        if let Some(output) = &self.output {
+            output.redraw();
+        }
     }
 
-    pub fn on_event(&mut self, event: &Event) {
-        if let Event::Resize(x, y) = event {
-            self.on_resize(Some((*x, *y).into()))
-        }
+    /// Returns the new size of the frame, if the terminal was resized
+    pub fn on_event(&mut self, event: &Event) -> Option<Dims> {
+        let Event::Resize(x, y) = event else {
+            return None;
+        };
+
+        self.on_resize(Some((*x, *y).into()));
+        Some(self.size)
     }
 
     pub fn frame(&mut self) -> &mut Frame {
@@ -115,70 +165,88 @@ impl Renderer {
         self.size
     }
 
-    pub fn show(&mut self) -> io::Result<()> {
-        let mut tty = stdout();
+    /// Last frame that was shown, it's only kept when headless
+    pub fn shown_frame(&self) -> &Frame {
+        &self.shown
+    }
 
-        let mut style = ContentStyle::default();
-        tty.queue(crossterm::style::ResetColor)?;
+    /// Written and dropped frames of the output thread, all zero when headless
+    pub fn frame_stats(&self) -> FrameStats {
+        self.output.as_ref().map(Output::stats).unwrap_or_default()
+    }
 
-        for y in 0..self.size.1 {
-            if self.hidden[y] == self.shown[y] && !self.full_redraw {
-                continue;
-            }
+    pub fn compat(&self) -> RenderCompat {
+        self.compat
+    }
 
-            tty.queue(crossterm::cursor::MoveTo(0, y as u16))?;
-
-            for x in 0..self.size.0 {
-                if let Cell::Content(c) = &self.hidden[y][x as usize] {
-                    if style != c.style {
-                        if style.background_color != c.style.background_color {
-                            match c.style.background_color {
-                                Some(x) => {
-                                    tty.queue(crossterm::style::SetBackgroundColor(x))?;
-                                }
-                                None => {
-                                    tty.queue(crossterm::style::SetBackgroundColor(
-                                        crossterm::style::Color::Reset,
-                                    ))?;
-                                }
-                            }
-                        }
-                        if style.foreground_color != c.style.foreground_color {
-                            match c.style.foreground_color {
-                                Some(x) => {
-                                    tty.queue(crossterm::style::SetForegroundColor(x))?;
-                                }
-                                None => {
-                                    tty.queue(crossterm::style::SetForegroundColor(
-                                        crossterm::style::Color::Reset,
-                                    ))?;
-                                }
-                            }
-                        }
-                        if style.attributes != c.style.attributes {
-                            tty.queue(crossterm::style::SetAttribute(
-                                crossterm::style::Attribute::Reset,
-                            ))?;
-                            if let Some(x) = c.style.foreground_color {
-                                tty.queue(crossterm::style::SetForegroundColor(x))?;
-                            }
-                            if let Some(x) = c.style.background_color {
-                                tty.queue(crossterm::style::SetBackgroundColor(x))?;
-                            }
-                            tty.queue(crossterm::style::SetAttributes(c.style.attributes))?;
-                        }
-                        style = c.style;
-                    }
-                    tty.queue(crossterm::style::Print(c.character))?;
-                }
+    /// Change terminal workarounds, whole screen is redrawn if they changed
+    pub fn set_compat(&mut self, compat: RenderCompat) {
+        self.compat = compat;
+        if let Some(output) = &self.output {
+            output.set_compat(compat);
+        }
+    }
+
+    /// Ask the terminal to report modifier keys pressed alone
+    ///
+    /// Needs keyboard enhancements, which are not supported by all terminals,
+    /// then nothing happens. Used by sticky modifiers, see [`crate::app::input::InputFilter`].
+    pub fn set_report_modifiers(&mut self, enabled: bool) {
+        use crossterm::event::{
+            KeyboardEnhancementFlags as Flags, PopKeyboardEnhancementFlags,
+            PushKeyboardEnhancementFlags,
+        };
+
+        if self.is_headless() || self.report_modifiers == enabled {
+            return;
+        }
+
+        let res = if enabled {
+            if !crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false) {
+                log::warn!("Terminal doesn't report modifier keys, sticky modifiers won't work");
+                return;
             }
+
+            execute!(
+                stdout(),
+                PushKeyboardEnhancementFlags(
+                    Flags::DISAMBIGUATE_ESCAPE_CODES
+                        | Flags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                        // so shifted keys are still reported as uppercase letters
+                        | Flags::REPORT_ALTERNATE_KEYS
+                )
+            )
+        } else {
+            execute!(stdout(), PopKeyboardEnhancementFlags)
+        };
+
+        match res {
+            Ok(()) => self.report_modifiers = enabled,
+            Err(err) => log::error!("Failed to change keyboard enhancements: {}", err),
         }
+    }
 
-        tty.flush()?;
-        self.full_redraw = false;
+    /// Rings the terminal bell, nothing happens when headless
+    pub fn bell(&self) {
+        if self.is_headless() {
+            return;
+        }
 
-        std::mem::swap(&mut self.shown, &mut self.hidden);
+        // frames are written with stdout locked, so the bell can't get in the middle of one
+        let mut tty = stdout().lock();
+        if let Err(err) = tty.write_all(b"\x07").and_then(|_| tty.flush()) {
+            log::error!("Failed to ring the bell: {}", err);
+        }
+    }
 
+    /// Shows the drawn frame, the terminal is written on the output thread
+    pub fn show(&mut self) -> io::Result<()> {
+        match &self.output {
+            Some(output) => output.submit(&mut self.hidden),
+            None => std::mem::swap(&mut self.shown, &mut self.hidden),
+        }
+
+        self.hidden.resize(self.size);
         self.hidden.clear();
 
         Ok(())
@@ -187,7 +255,10 @@ impl Renderer {
 
 impl Drop for Renderer {
     fn drop(&mut self) {
-        let _ = self.turn_off();
+        // the last frame is written before the terminal is restored
+        if self.output.take().is_some() {
+            let _ = self.turn_off();
+        }
     }
 }
 
@@ -293,6 +364,15 @@ impl Frame {
         true
     }
 
+    /// Cell at the position, `None` if it's outside of the frame
+    pub fn get_mut(&mut self, pos: Dims) -> Option<&mut Cell> {
+        if (pos.0 < 0 || pos.0 >= self.size.0) || (pos.1 < 0 || pos.1 >= self.size.1) {
+            return None;
+        }
+
+        Some(&mut self.buffer[pos.1 as usize][pos.0 as usize])
+    }
+
     pub fn set(&mut self, pos: Dims, cell: Cell) {
         self.buffer[pos.1 as usize][pos.0 as usize] = cell;
     }
@@ -329,6 +409,49 @@ impl Frame {
         }
     }
 
+    /// Blends colors of all cells with colors of the `overlay`, like a translucent layer
+    ///
+    /// `alpha` of 1 replaces the colors completely. Only RGB colors can be mixed, others
+    /// are replaced by the overlay if `alpha` is at least a half.
+    ///
+    /// Alpha is quantized to 1/255, so the channels are mixed with integers, see
+    /// `benches/render.rs`.
+    pub fn blend(&mut self, overlay: ContentStyle, alpha: f32) {
+        fn mix(under: Option<Color>, over: Option<Color>, alpha: u32) -> Option<Color> {
+            let channel =
+                |a: u8, b: u8| ((a as u32 * (255 - alpha) + b as u32 * alpha + 127) / 255) as u8;
+            match (under, over) {
+                (_, None) => under,
+                (
+                    Some(Color::Rgb { r, g, b }),
+                    Some(Color::Rgb {
+                        r: r2,
+                        g: g2,
+                        b: b2,
+                    }),
+                ) => Some(Color::Rgb {
+                    r: channel(r, r2),
+                    g: channel(g, g2),
+                    b: channel(b, b2),
+                }),
+                (_, over) if alpha >= 128 => over,
+                _ => under,
+            }
+        }
+
+        let alpha = (alpha.clamp(0.0, 1.0) * 255.0).round() as u32;
+        for cell in self.buffer.iter_mut().flatten() {
+            if let Some(content) = cell.content_mut() {
+                let style = &mut content.style;
+                style.foreground_color =
+                    mix(style.foreground_color, overlay.foreground_color, alpha);
+                style.background_color =
+                    mix(style.background_color, overlay.background_color, alpha);
+                style.attributes.extend(overlay.attributes);
+            }
+        }
+    }
+
     pub fn fill_rect(&mut self, pos: Dims, size: Dims, cell: Cell) {
         for y in pos.1..pos.1 + size.1 {
             for x in pos.0..pos.0 + size.0 {
@@ -365,10 +488,29 @@ impl std::ops::Index<i32> for Frame {
 
 impl Drawable for &Frame {
     fn draw(&self, pos: Dims, frame: &mut Frame, _: ()) {
-        for y in 0..self.size.1 {
-            for x in 0..self.size.0 {
-                frame.try_set(Dims(pos.0 + x, pos.1 + y), self[Dims(x, y)]);
+        // only the visible part, so big frames are cheap to draw
+        let (x_start, y_start) = ((-pos.0).max(0), (-pos.1).max(0));
+        let x_end = self.size.0.min(frame.size.0 - pos.0);
+        let y_end = self.size.1.min(frame.size.1 - pos.1);
+
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                frame.set(Dims(pos.0 + x, pos.1 + y), self[Dims(x, y)]);
             }
         }
     }
 }
+
+/// Plain text representation of the frame, styles are ignored
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in &self.buffer {
+            let line: String = row
+                .iter()
+                .filter_map(|cell| cell.content().map(|c| c.character))
+                .collect();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+//! Writing frames to the terminal on a separate thread
+//!
+//! The logic thread draws the next frame while the previous one is written, so slow terminals,
+//! like over SSH, don't delay the input. When the terminal can't keep up, the frame waiting to
+//! be written is replaced by the newer one and counted as dropped.
+
+use std::{
+    io::{self, stdout, Write},
+    mem,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use cmaze::dims::Dims;
+use crossterm::{style::ContentStyle, terminal, QueueableCommand};
+
+use super::{compat::RenderCompat, Cell, Frame};
+
+/// Counters of the output thread, shown in the debug overlay
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub written: u64,
+    /// Frames replaced by newer ones before they were written
+    pub dropped: u64,
+    /// How long the last frame took to write
+    pub last_write: Duration,
+}
+
+/// State shared by both threads, frames are moved between them, never copied
+struct Exchange {
+    /// Frame waiting to be written
+    pending: Option<Frame>,
+    /// Written frame, reused by the logic thread for drawing
+    spare: Option<Frame>,
+    compat: RenderCompat,
+    full_redraw: bool,
+    stats: FrameStats,
+    quit: bool,
+}
+
+struct Shared {
+    exchange: Mutex<Exchange>,
+    wake: Condvar,
+}
+
+pub struct Output {
+    shared: Arc<Shared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Output {
+    pub fn spawn(size: Dims, compat: RenderCompat) -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            exchange: Mutex::new(Exchange {
+                pending: None,
+                spare: None,
+                compat,
+                full_redraw: true,
+                stats: FrameStats::default(),
+                quit: false,
+            }),
+            wake: Condvar::new(),
+        });
+
+        let handle = thread::Builder::new().name("output".to_string()).spawn({
+            let shared = shared.clone();
+            move || run(&shared, size)
+        })?;
+
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    fn exchange(&self) -> std::sync::MutexGuard<'_, Exchange> {
+        self.shared.exchange.lock().unwrap()
+    }
+
+    /// Hands the frame to the output thread, it's replaced by a buffer for the next frame
+    ///
+    /// Buffer is not cleared and can have a different size.
+    pub fn submit(&self, frame: &mut Frame) {
+        let mut ex = self.exchange();
+        match ex.pending.as_mut() {
+            Some(pending) => {
+                mem::swap(pending, frame);
+                ex.stats.dropped += 1;
+            }
+            None => {
+                let next = ex.spare.take().unwrap_or_else(|| Frame::new(frame.size));
+                ex.pending = Some(mem::replace(frame, next));
+            }
+        }
+        self.shared.wake.notify_one();
+    }
+
+    pub fn set_compat(&self, compat: RenderCompat) {
+        let mut ex = self.exchange();
+        if ex.compat != compat {
+            ex.compat = compat;
+            ex.full_redraw = true;
+        }
+    }
+
+    /// Next frame is written whole, not only the changed lines
+    pub fn redraw(&self) {
+        self.exchange().full_redraw = true;
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        self.exchange().stats
+    }
+}
+
+impl Drop for Output {
+    /// Writes the pending frame and stops the thread
+    fn drop(&mut self) {
+        self.exchange().quit = true;
+        self.shared.wake.notify_one();
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(shared: &Shared, size: Dims) {
+    let mut shown = Frame::new(size);
+
+    loop {
+        let (frame, compat, full_redraw) = {
+            let mut ex = shared
+                .wake
+                .wait_while(shared.exchange.lock().unwrap(), |ex| {
+                    ex.pending.is_none() && !ex.quit
+                })
+                .unwrap();
+
+            let Some(frame) = ex.pending.take() else {
+                return;
+            };
+            (frame, ex.compat, mem::take(&mut ex.full_redraw))
+        };
+
+        let resized = frame.size != shown.size;
+        if resized {
+            shown.resize(frame.size);
+        }
+
+        let start = Instant::now();
+        if let Err(err) = write_frame(&frame, &shown, compat, full_redraw || resized) {
+            log::error!("Failed to write the frame: {}", err);
+            shared.exchange.lock().unwrap().full_redraw = true;
+        }
+        let last_write = start.elapsed();
+
+        let written = mem::replace(&mut shown, frame);
+        let mut ex = shared.exchange.lock().unwrap();
+        ex.spare = Some(written);
+        ex.stats.written += 1;
+        ex.stats.last_write = last_write;
+    }
+}
+
+/// Writes lines of the `frame` which changed since the `shown` one
+fn write_frame(
+    frame: &Frame,
+    shown: &Frame,
+    compat: RenderCompat,
+    full_redraw: bool,
+) -> io::Result<()> {
+    // locked for the whole frame, so other writes can't get in the middle of it
+    let mut tty = stdout().lock();
+
+    let mut style = ContentStyle::default();
+    if compat.sync_updates {
+        tty.queue(terminal::BeginSynchronizedUpdate)?;
+    }
+    tty.queue(crossterm::style::ResetColor)?;
+
+    for y in 0..frame.size.1 {
+        if frame[y] == shown[y] && !full_redraw {
+            continue;
+        }
+
+        tty.queue(crossterm::cursor::MoveTo(0, y as u16))?;
+
+        for x in 0..frame.size.0 {
+            if let Cell::Content(c) = &frame[y][x as usize] {
+                if style != c.style {
+                    if style.background_color != c.style.background_color {
+                        match c.style.background_color {
+                            Some(x) => {
+                                tty.queue(crossterm::style::SetBackgroundColor(compat.color(x)))?;
+                            }
+                            None => {
+                                tty.queue(crossterm::style::SetBackgroundColor(
+                                    crossterm::style::Color::Reset,
+                                ))?;
+                            }
+                        }
+                    }
+                    if style.foreground_color != c.style.foreground_color {
+                        match c.style.foreground_color {
+                            Some(x) => {
+                                tty.queue(crossterm::style::SetForegroundColor(compat.color(x)))?;
+                            }
+                            None => {
+                                tty.queue(crossterm::style::SetForegroundColor(
+                                    crossterm::style::Color::Reset,
+                                ))?;
+                            }
+                        }
+                    }
+                    if style.attributes != c.style.attributes {
+                        tty.queue(crossterm::style::SetAttribute(
+                            crossterm::style::Attribute::Reset,
+                        ))?;
+                        if let Some(x) = c.style.foreground_color {
+                            tty.queue(crossterm::style::SetForegroundColor(compat.color(x)))?;
+                        }
+                        if let Some(x) = c.style.background_color {
+                            tty.queue(crossterm::style::SetBackgroundColor(compat.color(x)))?;
+                        }
+                        tty.queue(crossterm::style::SetAttributes(c.style.attributes))?;
+                    }
+                    style = c.style;
+                }
+                tty.queue(crossterm::style::Print(compat.char(c.character)))?;
+            }
+        }
+    }
+
+    if compat.sync_updates {
+        tty.queue(terminal::EndSynchronizedUpdate)?;
+    }
+    tty.flush()
+}
@@ -0,0 +1,384 @@
+//! Lua scripts hooked to the events of the game
+//!
+//! Every `*.lua` file in the scripts directory runs in its own Lua state with only the
+//! `string`, `table` and `math` libraries. Scripts register callbacks in the `tmaze` table:
+//!
+//! ```lua
+//! tmaze.on_game_start(function(maze) end) -- maze.width, maze.height, maze.floors
+//! tmaze.on_move(function(x, y, floor) end)  -- once per move, the cell moved to
+//! tmaze.on_update(function(elapsed) end)  -- every frame, time in seconds
+//! tmaze.on_finish(function(elapsed, moves) end)
+//! ```
+//!
+//! and draw over the game with the `frame` table, `frame.text(x, y, text)` puts the text at
+//! the position, negative ones count from the right and bottom edge, `frame.clear()` removes
+//! all of them. Script which fails is stopped, the error is logged. So is one which runs
+//! for more than a million instructions in a single callback.
+
+use std::{fs, path::Path};
+
+use cmaze::{
+    dims::*,
+    game::{RunningGame, RunningGameState},
+};
+use mlua::{Function, HookTriggers, Lua, LuaOptions, StdLib, Table};
+
+use crate::{helpers::constants::paths::scripts_path, renderer::Frame, settings::theme::Style};
+
+const HOOKS: [&str; 4] = ["on_game_start", "on_move", "on_update", "on_finish"];
+
+/// Instructions a script can run when it's loaded or in a single callback
+const INSTRUCTION_BUDGET: u32 = 1_000_000;
+
+/// Instructions between checks of the budget
+const BUDGET_STEP: u32 = 1000;
+
+/// Instructions the script can still run, see [`INSTRUCTION_BUDGET`]
+#[derive(Debug, Clone, Copy)]
+struct Budget(u32);
+
+/// Text drawn by the script, see [`Scripts::draw`]
+#[derive(Debug, Clone, Default)]
+struct Overlay(Vec<(Dims, String)>);
+
+struct Script {
+    name: String,
+    lua: Lua,
+}
+
+impl Script {
+    fn load(path: &Path) -> mlua::Result<Self> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let source = fs::read_to_string(path)?;
+
+        let lua = Lua::new_with(
+            StdLib::STRING | StdLib::TABLE | StdLib::MATH,
+            LuaOptions::default(),
+        )?;
+        lua.set_app_data(Overlay::default());
+        lua.set_app_data(Budget(INSTRUCTION_BUDGET));
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(BUDGET_STEP),
+            |lua, _| {
+                let mut budget = lua
+                    .app_data_mut::<Budget>()
+                    .expect("budget is set when the script is loaded");
+                budget.0 = budget.0.saturating_sub(BUDGET_STEP);
+                match budget.0 {
+                    0 => Err(mlua::Error::runtime("script ran out of instructions")),
+                    _ => Ok(()),
+                }
+            },
+        );
+
+        let tmaze = lua.create_table()?;
+        for hook in HOOKS {
+            lua.set_named_registry_value(hook, lua.create_table()?)?;
+            let register = lua.create_function(move |lua, callback: Function| {
+                lua.named_registry_value::<Table>(hook)?.raw_push(callback)
+            })?;
+            tmaze.set(hook, register)?;
+        }
+        lua.globals().set("tmaze", tmaze)?;
+
+        let frame = lua.create_table()?;
+        frame.set(
+            "text",
+            lua.create_function(|lua, (x, y, text): (i32, i32, String)| {
+                if let Some(mut overlay) = lua.app_data_mut::<Overlay>() {
+                    overlay.0.push((Dims(x, y), text));
+                }
+                Ok(())
+            })?,
+        )?;
+        frame.set(
+            "clear",
+            lua.create_function(|lua, ()| {
+                if let Some(mut overlay) = lua.app_data_mut::<Overlay>() {
+                    overlay.0.clear();
+                }
+                Ok(())
+            })?,
+        )?;
+        lua.globals().set("frame", frame)?;
+
+        let log_name = name.clone();
+        lua.globals().set(
+            "print",
+            lua.create_function(move |_, text: String| {
+                log::info!("[{}] {}", log_name, text);
+                Ok(())
+            })?,
+        )?;
+
+        lua.load(&source).set_name(name.as_str()).exec()?;
+
+        Ok(Self { name, lua })
+    }
+
+    fn call<'lua>(
+        &'lua self,
+        hook: &str,
+        args: impl mlua::IntoLuaMulti<'lua> + Clone,
+    ) -> mlua::Result<()> {
+        let callbacks: Table = self.lua.named_registry_value(hook)?;
+        for callback in callbacks.sequence_values::<Function>() {
+            self.lua.set_app_data(Budget(INSTRUCTION_BUDGET));
+            callback?.call::<_, ()>(args.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// Loaded scripts and the state of the game they have seen
+#[derive(Default)]
+pub struct Scripts {
+    scripts: Vec<Script>,
+    started: bool,
+    finished: bool,
+    /// Moves already passed to `on_move`
+    moves: usize,
+}
+
+impl Scripts {
+    /// Loads all scripts from the scripts directory, the invalid ones are skipped
+    pub fn load() -> Self {
+        let Ok(entries) = fs::read_dir(scripts_path()) else {
+            return Self::default();
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "lua"))
+            .collect();
+        paths.sort();
+
+        let scripts = paths
+            .iter()
+            .filter_map(|path| {
+                Script::load(path)
+                    .inspect_err(|err| log::error!("Failed to load script {:?}: {}", path, err))
+                    .ok()
+            })
+            .collect();
+
+        Self {
+            scripts,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    /// Calls the hooks of everything which happened since the last update
+    pub fn update(&mut self, game: &RunningGame) {
+        if self.is_empty() || game.get_state() == RunningGameState::NotStarted {
+            return;
+        }
+
+        if !self.started {
+            self.started = true;
+            let size = game.get_maze().size();
+            self.game_start(|lua| {
+                let maze = lua.create_table()?;
+                maze.set("width", size.0)?;
+                maze.set("height", size.1)?;
+                maze.set("floors", size.2)?;
+                Ok(maze)
+            });
+        }
+
+        // moves taken back are not reported, the next ones are reported again
+        let moves = game.get_moves();
+        for &(from, dir) in moves.iter().skip(self.moves) {
+            let pos = from + dir.to_coord();
+            self.call_hook("on_move", (pos.0, pos.1, pos.2 + 1));
+        }
+        self.moves = moves.len();
+
+        let elapsed = game.get_elapsed().unwrap_or_default().as_secs_f64();
+        self.call_hook("on_update", elapsed);
+
+        if game.get_state() == RunningGameState::Finished && !self.finished {
+            self.finished = true;
+            self.call_hook("on_finish", (elapsed, game.get_move_count()));
+        }
+    }
+
+    /// `on_game_start` with the argument made in the state of each script
+    fn game_start(&mut self, arg: impl for<'lua> Fn(&'lua Lua) -> mlua::Result<Table<'lua>>) {
+        self.scripts.retain(|script| {
+            let res = arg(&script.lua).and_then(|arg| script.call("on_game_start", arg));
+            Self::check(script, res)
+        });
+    }
+
+    fn call_hook<A>(&mut self, hook: &str, args: A)
+    where
+        A: for<'lua> mlua::IntoLuaMulti<'lua> + Clone,
+    {
+        self.scripts
+            .retain(|script| Self::check(script, script.call(hook, args.clone())));
+    }
+
+    /// Logs the error of the script, returns whether it can keep running
+    fn check(script: &Script, res: mlua::Result<()>) -> bool {
+        match res {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("Script {} failed and was stopped: {}", script.name, err);
+                false
+            }
+        }
+    }
+
+    /// Draws the texts of all scripts over the frame
+    pub fn draw(&self, frame: &mut Frame, style: Style) {
+        for script in &self.scripts {
+            let Some(overlay) = script.lua.app_data_ref::<Overlay>() else {
+                continue;
+            };
+            for (pos, text) in &overlay.0 {
+                let from_end = |pos: i32, size: i32| match pos < 0 {
+                    true => size + pos,
+                    false => pos,
+                };
+                let pos = Dims(from_end(pos.0, frame.size.0), from_end(pos.1, frame.size.1));
+                frame.draw(pos, text.as_str(), style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cmaze::{
+        game::{GameProperities, MoveMode},
+        gameboard::CellWall,
+    };
+
+    use crate::settings::MazeGenAlgo;
+
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        local moves = 0
+        tmaze.on_game_start(function(maze) frame.text(0, 0, "size " .. maze.width) end)
+        tmaze.on_move(function(x, y, floor) moves = moves + 1 end)
+        tmaze.on_finish(function(elapsed, count)
+            frame.clear()
+            frame.text(-4, -1, moves .. "/" .. count)
+        end)
+    "#;
+
+    fn load(name: &str, source: &str) -> mlua::Result<Script> {
+        let path = std::env::temp_dir().join(format!("tmaze-{name}-{}.lua", std::process::id()));
+        fs::write(&path, source).unwrap();
+        let script = Script::load(&path);
+        fs::remove_file(&path).unwrap();
+        script
+    }
+
+    #[test]
+    fn endless_scripts_are_stopped() {
+        assert!(load("endless-load", "while true do end").is_err());
+
+        let script = load(
+            "endless-update",
+            "tmaze.on_update(function(elapsed) while elapsed >= 0 do end end)",
+        )
+        .unwrap();
+        assert!(script.call("on_update", 1.0).is_err());
+    }
+
+    #[test]
+    fn hooks_draw_overlay() {
+        let script = load("script", SCRIPT);
+
+        let mut scripts = Scripts {
+            scripts: vec![script.unwrap()],
+            ..Scripts::default()
+        };
+
+        let props = GameProperities {
+            game_mode: GameMode {
+                size: Dims3D(2, 1, 1),
+                is_tower: false,
+                goals: Default::default(),
+                objective: Default::default(),
+                braid: 0,
+                braid_floors: 0,
+                items: Default::default(),
+            },
+            generator: MazeGenAlgo::DepthFirstSearch.to_fn(),
+            seed: Some(1),
+        };
+        let mut game = RunningGame::new_threaded(props).unwrap().wait().unwrap();
+        game.start().unwrap();
+
+        let mut frame = Frame::new(Dims(10, 3));
+        scripts.update(&game);
+        scripts.draw(&mut frame, Style::default());
+        assert!(frame.to_string().starts_with("size 2"));
+
+        game.move_player(CellWall::Right, MoveMode::Slow, true)
+            .unwrap();
+        scripts.update(&game);
+        assert_eq!(game.get_state(), RunningGameState::Finished);
+
+        let mut frame = Frame::new(Dims(10, 3));
+        scripts.draw(&mut frame, Style::default());
+        assert_eq!(frame.to_string().lines().last().unwrap().trim(), "1/1");
+    }
+
+    #[test]
+    fn every_move_is_reported() {
+        let script = load(
+            "moves",
+            r#"
+            local cells = {}
+            tmaze.on_move(function(x, y, floor)
+                table.insert(cells, x .. "," .. y)
+                frame.clear()
+                frame.text(0, 0, table.concat(cells, " "))
+            end)
+            "#,
+        );
+        let mut scripts = Scripts {
+            scripts: vec![script.unwrap()],
+            ..Scripts::default()
+        };
+
+        // a corridor, the goal at the end
+        let props = GameProperities {
+            game_mode: GameMode {
+                size: Dims3D(4, 1, 1),
+                is_tower: false,
+                goals: Default::default(),
+                objective: Default::default(),
+                braid: 0,
+                braid_floors: 0,
+                items: Default::default(),
+            },
+            generator: MazeGenAlgo::DepthFirstSearch.to_fn(),
+            seed: Some(1),
+        };
+        let mut game = RunningGame::new_threaded(props).unwrap().wait().unwrap();
+        game.start().unwrap();
+        scripts.update(&game);
+
+        game.move_player(CellWall::Right, MoveMode::Normal, true)
+            .unwrap();
+        scripts.update(&game);
+
+        let mut frame = Frame::new(Dims(20, 1));
+        scripts.draw(&mut frame, Style::default());
+        assert_eq!(frame.to_string().trim(), "1,0 2,0 3,0");
+    }
+}
@@ -0,0 +1,235 @@
+//! Getters and setters of the simple settings, generated from a single declaration
+//!
+//! Each setting declared with [`settings_accessors`] gets a `get_*` method, which falls
+//! back to the default and clamps the value, and a `set_*` method, which clamps it too.
+//! Settings with a `ui` label are also described by [`SettingMeta`] and can be read and
+//! written by name, so menus can be built from them, like [`super::editor`].
+
+use super::ModeValue;
+
+/// Setting which can be edited in a menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SettingMeta {
+    /// Name of the field in the settings file
+    pub name: &'static str,
+    pub label: &'static str,
+    /// Doc comment of the setting, with the markdown left in
+    pub description: &'static str,
+    pub default: ModeValue,
+    /// Allowed range of numeric settings, `None` for toggles and unbounded numbers
+    pub range: Option<(f64, f64)>,
+    /// Smallest change of a numeric setting in a menu
+    pub step: f64,
+    /// Names of the variants of enums, which are stored as their index
+    pub choices: &'static [&'static str],
+}
+
+impl SettingMeta {
+    pub fn is_toggle(&self) -> bool {
+        matches!(self.default, ModeValue::Bool(_))
+    }
+
+    pub fn is_choice(&self) -> bool {
+        !self.choices.is_empty()
+    }
+}
+
+/// Type of a setting which can be edited by name
+pub trait SettingValue: Sized {
+    /// Names of the variants, if it's an enum
+    const CHOICES: &'static [&'static str] = &[];
+
+    fn to_value(self) -> ModeValue;
+    fn from_value(value: ModeValue) -> Option<Self>;
+
+    /// Step of the value in a menu, with the range of the setting
+    fn step(_range: Option<(f64, f64)>) -> f64 {
+        1.0
+    }
+}
+
+impl SettingValue for bool {
+    fn to_value(self) -> ModeValue {
+        ModeValue::Bool(self)
+    }
+
+    fn from_value(value: ModeValue) -> Option<Self> {
+        match value {
+            ModeValue::Bool(value) => Some(value),
+            ModeValue::Number(_) => None,
+        }
+    }
+}
+
+impl SettingValue for f32 {
+    fn to_value(self) -> ModeValue {
+        ModeValue::Number(self as f64)
+    }
+
+    fn from_value(value: ModeValue) -> Option<Self> {
+        match value {
+            ModeValue::Number(value) => Some(value as f32),
+            ModeValue::Bool(_) => None,
+        }
+    }
+
+    fn step(range: Option<(f64, f64)>) -> f64 {
+        match range {
+            Some((min, max)) if max - min <= 1.0 => 0.05,
+            _ => 0.1,
+        }
+    }
+}
+
+impl SettingValue for u64 {
+    fn to_value(self) -> ModeValue {
+        ModeValue::Number(self as f64)
+    }
+
+    fn from_value(value: ModeValue) -> Option<Self> {
+        match value {
+            ModeValue::Number(value) => Some(value.max(0.0).round() as u64),
+            ModeValue::Bool(_) => None,
+        }
+    }
+
+    /// At most 100 steps over the range
+    fn step(range: Option<(f64, f64)>) -> f64 {
+        range.map_or(1.0, |(min, max)| ((max - min) / 100.0).ceil().max(1.0))
+    }
+}
+
+/// Implements [`SettingValue`] for a fieldless enum, which is stored as the index of the variant
+///
+/// ```ignore
+/// choice_setting_value!(BumpFeedback { None => "None", Bell => "Bell" });
+/// ```
+macro_rules! choice_setting_value {
+    ($ty:ident { $($variant:ident => $name:literal),* $(,)? }) => {
+        impl accessors::SettingValue for $ty {
+            const CHOICES: &'static [&'static str] = &[$($name),*];
+
+            fn to_value(self) -> ModeValue {
+                let variants = [$($ty::$variant),*];
+                let index = variants.iter().position(|v| *v == self).unwrap_or(0);
+                ModeValue::Number(index as f64)
+            }
+
+            fn from_value(value: ModeValue) -> Option<Self> {
+                match value {
+                    ModeValue::Number(index) if index >= 0.0 => {
+                        [$($ty::$variant),*].get(index.round() as usize).copied()
+                    }
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+/// Generates accessors of settings, which are stored as `Option<T>` in [`super::SettingsInner`]
+///
+/// ```ignore
+/// settings_accessors! {
+///     /// Doc comment of the getter
+///     field(get_field, set_field): Type = default, clamp(min, max), ui("Label");
+/// }
+/// ```
+///
+/// Getter and setter are named after the field, they are written out to be searchable.
+/// Both `clamp` and `ui` are optional, `ui` can only be used with `bool`, `f32`, `u64` and enums
+/// with [`choice_setting_value`]. Doc comment is used as the description of the setting.
+macro_rules! settings_accessors {
+    (@range) => { None::<(f64, f64)> };
+    (@range $min:expr, $max:expr) => { Some(($min as f64, $max as f64)) };
+    (
+        $(
+            $(#[doc = $doc:literal])*
+            $field:ident($getter:ident, $setter:ident): $ty:ty = $default:expr
+            $(, clamp($min:expr, $max:expr))?
+            $(, ui($label:literal))?;
+        )*
+    ) => {
+        impl Settings {
+            $(
+                $(#[doc = $doc])*
+                pub fn $getter(&self) -> $ty {
+                    let value: $ty = self.read().$field.unwrap_or($default);
+                    $(let value = value.clamp($min, $max);)?
+                    value
+                }
+
+                pub fn $setter(&mut self, value: $ty) -> &mut Self {
+                    $(let value = value.clamp($min, $max);)?
+                    self.write().$field = Some(value);
+                    self
+                }
+            )*
+
+            /// Settings which can be edited in a menu, in the order of declaration
+            #[allow(clippy::unnecessary_cast)]
+            pub fn metadata() -> Vec<accessors::SettingMeta> {
+                use accessors::SettingValue as _;
+
+                let mut metadata = vec![];
+                $(
+                    #[allow(unused_variables)]
+                    let range = settings_accessors!(@range $($min, $max)?);
+                    #[allow(unused_variables)]
+                    let description = concat!($($doc),*);
+                    $(
+                        let default: $ty = $default;
+                        metadata.push(accessors::SettingMeta {
+                            name: stringify!($field),
+                            label: $label,
+                            description,
+                            default: default.to_value(),
+                            range,
+                            step: <$ty as accessors::SettingValue>::step(range),
+                            choices: <$ty as accessors::SettingValue>::CHOICES,
+                        });
+                    )?
+                )*
+                metadata
+            }
+
+            /// Value of a setting from [`Settings::metadata`] by its name
+            pub fn get_value(&self, name: &str) -> Option<ModeValue> {
+                use accessors::SettingValue as _;
+
+                match name {
+                    $($(
+                        stringify!($field) => {
+                            let _ = $label;
+                            Some(self.$getter().to_value())
+                        }
+                    )?)*
+                    _ => None,
+                }
+            }
+
+            /// Sets a setting from [`Settings::metadata`] by its name
+            ///
+            /// Returns `false` if there is no such setting or the value has a wrong type.
+            pub fn set_value(&mut self, name: &str, value: ModeValue) -> bool {
+                use accessors::SettingValue;
+
+                match name {
+                    $($(
+                        stringify!($field) => {
+                            let _ = $label;
+                            match <$ty as SettingValue>::from_value(value) {
+                                Some(value) => {
+                                    self.$setter(value);
+                                    true
+                                }
+                                None => false,
+                            }
+                        }
+                    )?)*
+                    _ => false,
+                }
+            }
+        }
+    };
+}
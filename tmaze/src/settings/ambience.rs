@@ -0,0 +1,62 @@
+use chrono::{Local, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// Part of the day, used to pick the ambience variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOfDay {
+    Morning,
+    Day,
+    Evening,
+    Night,
+}
+
+impl TimeOfDay {
+    pub fn from_hour(hour: u32) -> Self {
+        match hour {
+            5..=11 => TimeOfDay::Morning,
+            12..=17 => TimeOfDay::Day,
+            18..=21 => TimeOfDay::Evening,
+            _ => TimeOfDay::Night,
+        }
+    }
+
+    /// Time of day by the local clock
+    pub fn now() -> Self {
+        Self::from_hour(Local::now().hour())
+    }
+}
+
+/// What to change during some part of the day, unset fields are left as they are
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AmbienceVariant {
+    #[serde(default)]
+    pub theme: Option<String>,
+    #[serde(default)]
+    pub menu_playlist: Option<Vec<String>>,
+    #[serde(default)]
+    pub game_playlist: Option<Vec<String>>,
+}
+
+/// Mapping of the parts of the day to their variants
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ambience {
+    #[serde(default)]
+    pub morning: Option<AmbienceVariant>,
+    #[serde(default)]
+    pub day: Option<AmbienceVariant>,
+    #[serde(default)]
+    pub evening: Option<AmbienceVariant>,
+    #[serde(default)]
+    pub night: Option<AmbienceVariant>,
+}
+
+impl Ambience {
+    pub fn variant(&self, time: TimeOfDay) -> Option<&AmbienceVariant> {
+        match time {
+            TimeOfDay::Morning => self.morning.as_ref(),
+            TimeOfDay::Day => self.day.as_ref(),
+            TimeOfDay::Evening => self.evening.as_ref(),
+            TimeOfDay::Night => self.night.as_ref(),
+        }
+    }
+}
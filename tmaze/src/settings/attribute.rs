@@ -1,7 +1,7 @@
 use std::{fmt, str::FromStr};
 
 use crossterm::style::Attributes;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 macro_rules! Attribute {
     (
@@ -228,3 +228,15 @@ where
         attributes
     })
 }
+
+/// Attributes as a list of their names, the same as [`deserialize_attributes`] reads
+pub fn serialize_attributes<S>(attributes: &Attributes, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    Attribute::iterator()
+        .filter(|attr| *attr != Attribute::Reset && attributes.has((*attr).into()))
+        .map(|attr| attr.to_string())
+        .collect::<Vec<_>>()
+        .serialize(serializer)
+}
@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+use crate::{
+    app::{self, app::AppData, Activity, ActivityHandler, Change},
+    content::{self, ContentKind},
+    ui::{Hint, Menu, MenuConfig, MenuItem, OptionDef, Screen},
+};
+
+use super::Settings;
+
+enum ContentAction {
+    Nothing,
+    OpenFolder(PathBuf),
+    Rescan,
+    Back,
+}
+
+/// Lists installed themes and presets, each can be turned off
+pub struct ContentActivity {
+    menu: Menu,
+    actions: Vec<ContentAction>,
+}
+
+impl ContentActivity {
+    pub fn new(settings: &Settings) -> Self {
+        let items = content::discover_all();
+
+        let mut options = vec![];
+        let mut actions = vec![];
+        for kind in ContentKind::ALL {
+            for item in items.iter().filter(|item| item.kind == kind) {
+                let text = match &item.version {
+                    Some(version) => format!("{} v{}", item.name, version),
+                    None => item.name.clone(),
+                };
+
+                if item.builtin {
+                    options.push(MenuItem::Text(format!("{} (built-in)", text).into()));
+                } else {
+                    let id = item.id();
+                    options.push(MenuItem::Option(OptionDef {
+                        text: text.into(),
+                        val: settings.is_content_enabled(&id),
+                        fun: Box::new(move |enabled, data| {
+                            *enabled = !*enabled;
+                            data.settings.set_content_enabled(&id, *enabled);
+                            if kind == ContentKind::Theme {
+                                data.apply_settings();
+                            }
+                        }),
+                    }));
+                }
+                actions.push(ContentAction::Nothing);
+            }
+
+            let folder = format!("Open {} folder", kind.name().to_lowercase());
+            options.push(MenuItem::Text(folder.into()));
+            actions.push(ContentAction::OpenFolder(kind.dir()));
+            options.push(MenuItem::Separator);
+            actions.push(ContentAction::Nothing);
+        }
+
+        options.push(MenuItem::Text("Rescan".into()));
+        actions.push(ContentAction::Rescan);
+        options.push(MenuItem::Text("Back".into()));
+        actions.push(ContentAction::Back);
+
+        let disabled = items
+            .iter()
+            .filter(|item| !settings.is_content_enabled(&item.id()))
+            .count();
+        let menu_config = MenuConfig::new("Content", options).subtitle(format!(
+            "{} installed, {} disabled",
+            items.len(),
+            disabled
+        ));
+
+        Self {
+            menu: Menu::new(menu_config),
+            actions,
+        }
+    }
+
+    pub fn new_activity(settings: &Settings) -> Activity {
+        Activity::new_base_boxed("content", Self::new(settings))
+    }
+}
+
+impl ActivityHandler for ContentActivity {
+    fn update(&mut self, events: Vec<app::Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                match &self.actions[index] {
+                    ContentAction::Nothing => None,
+                    ContentAction::OpenFolder(path) => {
+                        match content::open_folder(path) {
+                            Ok(()) => log::info!("Opened {:?}", path),
+                            Err(err) => log::error!("Could not open {:?}: {}", path, err),
+                        }
+                        None
+                    }
+                    ContentAction::Rescan => {
+                        data.settings.reload_presets();
+                        Some(Change::replace(Self::new_activity(&data.settings)))
+                    }
+                    ContentAction::Back => Some(Change::pop_top()),
+                }
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
@@ -0,0 +1,942 @@
+//! Editor of every setting, a scrollable form with a row for each of them
+//!
+//! Rows of the simple settings are generated from [`Settings::metadata`], with their doc
+//! comments as descriptions. Settings stored as strings, lists or enums with data are
+//! described in [`leading_fields`] and [`trailing_fields`]. Structured settings, like profiles,
+//! presets or macros, have their own screens or can only be changed in the settings file.
+
+use std::io;
+
+use cmaze::dims::{Dims, Offset};
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent, MouseEventKind};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    app::{app::AppData, Activity, ActivityHandler, Change, Event},
+    helpers::is_release,
+    renderer::{compat::ColorSupport, Cell, Frame},
+    settings::theme::{Theme, ThemeDefinition, ThemeResolver},
+    ui::{center_box_in_screen, draw_box, wrap_words, Hint, Hit, HitMap, Rect, Screen, TextInput},
+};
+
+use super::{
+    accessors::{SettingMeta, SettingValue},
+    keymap::Action,
+    CameraMode, ModeValue, Settings, UpdateCheckInterval,
+};
+
+const LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+const AUTO_ON_OFF: &[&str] = &["Auto", "On", "Off"];
+const COLOR_SUPPORT: &[&str] = &["Auto", "16 colors", "256 colors", "True color"];
+const CAMERA_MODES: &[&str] = &["Close follow", "Edge follow"];
+const PAPERS: &[&str] = &["A4", "A5", "Letter"];
+
+/// Widest form, wider screens have empty space around it
+const MAX_WIDTH: i32 = 72;
+/// Cells of the slider of numeric settings
+const SLIDER_WIDTH: usize = 10;
+/// Lines of the form other than the rows, borders, path and description
+const CHROME_HEIGHT: i32 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Toggle,
+    Number {
+        min: f64,
+        max: f64,
+        step: f64,
+    },
+    /// Names of the variants, value is the index
+    Choice(&'static [&'static str]),
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Bool(bool),
+    Number(f64),
+    Choice(usize),
+    Text(String),
+}
+
+impl FieldValue {
+    fn into_bool(self) -> bool {
+        matches!(self, FieldValue::Bool(true))
+    }
+
+    fn into_choice(self) -> usize {
+        match self {
+            FieldValue::Choice(index) => index,
+            _ => 0,
+        }
+    }
+
+    fn into_number(self) -> f64 {
+        match self {
+            FieldValue::Number(value) => value,
+            _ => 0.0,
+        }
+    }
+
+    fn into_text(self) -> String {
+        match self {
+            FieldValue::Text(text) => text,
+            _ => String::new(),
+        }
+    }
+}
+
+type Getter = fn(&Settings) -> FieldValue;
+/// Returns a message for the player if the value is invalid
+type Setter = fn(&mut Settings, FieldValue) -> Result<(), String>;
+
+enum Access {
+    /// Read and written by name, see [`Settings::get_value`]
+    Named,
+    Custom(Getter, Setter),
+}
+
+/// Row of the editor
+pub struct Field {
+    /// Name of the field in the settings file
+    pub name: &'static str,
+    pub label: &'static str,
+    pub description: String,
+    pub kind: FieldKind,
+    access: Access,
+}
+
+impl Field {
+    fn from_meta(meta: &SettingMeta) -> Self {
+        let kind = if meta.is_toggle() {
+            FieldKind::Toggle
+        } else if meta.is_choice() {
+            FieldKind::Choice(meta.choices)
+        } else {
+            let (min, max) = meta.range.unwrap_or((0.0, f64::MAX));
+            FieldKind::Number {
+                min,
+                max,
+                step: meta.step,
+            }
+        };
+
+        Self {
+            name: meta.name,
+            label: meta.label,
+            description: plain_doc(meta.description),
+            kind,
+            access: Access::Named,
+        }
+    }
+
+    fn custom(
+        name: &'static str,
+        label: &'static str,
+        description: &str,
+        kind: FieldKind,
+        get: Getter,
+        set: Setter,
+    ) -> Self {
+        Self {
+            name,
+            label,
+            description: description.to_string(),
+            kind,
+            access: Access::Custom(get, set),
+        }
+    }
+
+    pub fn get(&self, settings: &Settings) -> FieldValue {
+        match self.access {
+            Access::Named => match (settings.get_value(self.name), self.kind) {
+                (Some(ModeValue::Bool(value)), _) => FieldValue::Bool(value),
+                (Some(ModeValue::Number(index)), FieldKind::Choice(_)) => {
+                    FieldValue::Choice(index as usize)
+                }
+                (Some(ModeValue::Number(value)), _) => FieldValue::Number(value),
+                (None, _) => unreachable!("named field should be in the metadata"),
+            },
+            Access::Custom(get, _) => get(settings),
+        }
+    }
+
+    pub fn set(&self, settings: &mut Settings, value: FieldValue) -> Result<(), String> {
+        match self.access {
+            Access::Named => {
+                let value = match value {
+                    FieldValue::Bool(value) => ModeValue::Bool(value),
+                    FieldValue::Number(value) => ModeValue::Number(value),
+                    FieldValue::Choice(index) => ModeValue::Number(index as f64),
+                    FieldValue::Text(_) => return Err("Expected a number".to_string()),
+                };
+                match settings.set_value(self.name, value) {
+                    true => Ok(()),
+                    false => Err(format!("Invalid value of {}", self.label)),
+                }
+            }
+            Access::Custom(_, set) => set(settings, value),
+        }
+    }
+
+    /// Value changed by one step, toggles are flipped and choices wrap around
+    pub fn step(&self, value: &FieldValue, up: bool) -> FieldValue {
+        match (self.kind, value) {
+            (FieldKind::Toggle, FieldValue::Bool(value)) => FieldValue::Bool(!value),
+            (FieldKind::Number { min, max, step }, FieldValue::Number(value)) => {
+                let value = if up { value + step } else { value - step };
+                // float steps shouldn't show up as 0.8500001
+                FieldValue::Number(((value * 1e6).round() / 1e6).clamp(min, max))
+            }
+            (FieldKind::Choice(choices), FieldValue::Choice(index)) => {
+                let count = choices.len();
+                FieldValue::Choice(match up {
+                    true => (index + 1) % count,
+                    false => (index + count - 1) % count,
+                })
+            }
+            (_, value) => value.clone(),
+        }
+    }
+
+    /// Value as it's typed into the text field
+    pub fn text(&self, value: &FieldValue) -> String {
+        match (self.kind, value) {
+            (FieldKind::Number { step, .. }, FieldValue::Number(value)) if step >= 1.0 => {
+                format!("{:.0}", value)
+            }
+            (_, FieldValue::Number(value)) => format!("{:.2}", value),
+            (FieldKind::Choice(choices), FieldValue::Choice(index)) => {
+                choices.get(*index).unwrap_or(&"?").to_string()
+            }
+            (_, FieldValue::Bool(value)) => value.to_string(),
+            (_, FieldValue::Text(text)) => text.clone(),
+            _ => String::new(),
+        }
+    }
+
+    /// Parses the value typed into the text field
+    pub fn parse(&self, text: &str) -> Result<FieldValue, String> {
+        match self.kind {
+            FieldKind::Number { min, max, .. } => text
+                .trim()
+                .parse::<f64>()
+                .map(|value| FieldValue::Number(value.clamp(min, max)))
+                .map_err(|_| format!("`{}` is not a number", text.trim())),
+            _ => Ok(FieldValue::Text(text.trim().to_string())),
+        }
+    }
+
+    /// Value as it's shown in the form
+    pub fn display(&self, value: &FieldValue) -> String {
+        match (self.kind, value) {
+            (_, FieldValue::Bool(value)) => if *value { "[▪]" } else { "[ ]" }.to_string(),
+            (FieldKind::Number { min, max, .. }, FieldValue::Number(value)) if max < f64::MAX => {
+                let filled = ((value - min) / (max - min) * SLIDER_WIDTH as f64).round() as usize;
+                let filled = filled.min(SLIDER_WIDTH);
+                format!(
+                    "{} [{}{}]",
+                    self.text(&FieldValue::Number(*value)),
+                    "#".repeat(filled),
+                    " ".repeat(SLIDER_WIDTH - filled)
+                )
+            }
+            (FieldKind::Choice(_), _) => format!("‹ {} ›", self.text(value)),
+            (FieldKind::Text, FieldValue::Text(text)) if text.is_empty() => "default".to_string(),
+            _ => self.text(value),
+        }
+    }
+
+    /// Whether Enter opens the text field
+    fn is_typed(&self) -> bool {
+        matches!(self.kind, FieldKind::Number { .. } | FieldKind::Text)
+    }
+}
+
+/// Markdown of a doc comment as plain text, links are replaced by the last part of their path
+pub fn plain_doc(doc: &str) -> String {
+    let mut text = String::new();
+    let mut rest = doc.trim();
+
+    while let Some(start) = rest.find("[`") {
+        text.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find("`]") else {
+            break;
+        };
+
+        let path = &rest[start + 2..start + len];
+        let name = path.rsplit("::").next().unwrap_or(path);
+        text.push_str(&name.replace('_', " "));
+        rest = &rest[start + len + 2..];
+    }
+    text.push_str(rest);
+
+    text.replace('`', "")
+}
+
+fn choice(value: impl SettingValue) -> FieldValue {
+    match value.to_value() {
+        ModeValue::Number(index) => FieldValue::Choice(index as usize),
+        ModeValue::Bool(_) => FieldValue::Choice(0),
+    }
+}
+
+fn from_choice<T: SettingValue>(value: FieldValue) -> Option<T> {
+    T::from_value(ModeValue::Number(value.into_choice() as f64))
+}
+
+fn tri_state(value: Option<bool>) -> FieldValue {
+    FieldValue::Choice(match value {
+        None => 0,
+        Some(true) => 1,
+        Some(false) => 2,
+    })
+}
+
+fn from_tri_state(value: FieldValue) -> Option<bool> {
+    match value.into_choice() {
+        1 => Some(true),
+        2 => Some(false),
+        _ => None,
+    }
+}
+
+fn log_level(level: log::Level) -> FieldValue {
+    let index = LOG_LEVELS
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(level.as_str()))
+        .unwrap_or(2);
+    FieldValue::Choice(index)
+}
+
+fn from_log_level(value: FieldValue) -> Option<String> {
+    LOG_LEVELS.get(value.into_choice()).map(|l| l.to_string())
+}
+
+fn list(items: Vec<String>) -> FieldValue {
+    FieldValue::Text(items.join(", "))
+}
+
+fn from_list(value: FieldValue) -> Vec<String> {
+    value
+        .into_text()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Fields shown before the generated ones, the look of the game
+fn leading_fields() -> Vec<Field> {
+    use FieldKind::*;
+
+    vec![
+        Field::custom(
+            "theme",
+            "Theme",
+            "Theme file from the themes directory, like theme.json5, empty for the default one",
+            Text,
+            |s| FieldValue::Text(s.read().theme.clone().unwrap_or_default()),
+            |s, value| {
+                let name = value.into_text();
+                if !name.is_empty() {
+                    ThemeDefinition::load_by_name(&name)
+                        .map_err(|err| format!("Could not load the theme: {}", err))?;
+                }
+                s.write().theme = Some(name).filter(|name| !name.is_empty());
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "mobile_mode",
+            "Mobile mode",
+            "Touch controls, with the dpad and a larger margin around it",
+            Toggle,
+            |s| FieldValue::Bool(s.get_mobile_mode()),
+            |s, value| {
+                s.set_mobile_mode(value.into_bool());
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "camera_mode",
+            "Camera",
+            "Close follow keeps the player centered, edge follow moves the camera only when the \
+             player gets near the edge of the screen",
+            Choice(CAMERA_MODES),
+            |s| {
+                FieldValue::Choice(match s.read().camera_mode.unwrap_or_default() {
+                    CameraMode::CloseFollow => 0,
+                    CameraMode::EdgeFollow(..) => 1,
+                })
+            },
+            |s, value| {
+                let current = s.read().camera_mode.unwrap_or_default();
+                s.write().camera_mode = Some(match (value.into_choice(), current) {
+                    (0, _) => CameraMode::CloseFollow,
+                    (_, edge @ CameraMode::EdgeFollow(..)) => edge,
+                    _ => CameraMode::EdgeFollow(Offset::Rel(0.25), Offset::Rel(0.25)),
+                });
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "viewport_margin",
+            "Viewport margin",
+            "Horizontal and vertical space around the maze, like 4, 2, \
+             empty to adapt it to the screen",
+            Text,
+            |s| {
+                FieldValue::Text(
+                    s.read()
+                        .viewport_margin
+                        .map(|(x, y)| format!("{}, {}", x, y))
+                        .unwrap_or_default(),
+                )
+            },
+            |s, value| {
+                let text = value.into_text();
+                if text.is_empty() {
+                    s.write().viewport_margin = None;
+                    return Ok(());
+                }
+
+                let margin: Option<Vec<i32>> =
+                    text.split(',').map(|n| n.trim().parse().ok()).collect();
+                match margin.as_deref() {
+                    Some(&[x, y]) if x >= 0 && y >= 0 => {
+                        s.set_viewport_margin(Dims(x, y));
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "`{}` is not a margin, use two numbers like 4, 2",
+                        text
+                    )),
+                }
+            },
+        ),
+        Field::custom(
+            "ascii_charset",
+            "ASCII characters",
+            "Walls and the UI are drawn only with ASCII characters, auto detects the terminal",
+            Choice(AUTO_ON_OFF),
+            |s| tri_state(s.read().ascii_charset),
+            |s, value| {
+                s.write().ascii_charset = from_tri_state(value);
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "color_support",
+            "Terminal colors",
+            "Colors of the theme are reduced to the ones the terminal has, auto detects them",
+            Choice(COLOR_SUPPORT),
+            |s| {
+                FieldValue::Choice(match s.get_color_support() {
+                    None => 0,
+                    Some(ColorSupport::Basic) => 1,
+                    Some(ColorSupport::Ansi256) => 2,
+                    Some(ColorSupport::TrueColor) => 3,
+                })
+            },
+            |s, value| {
+                let colors = match value.into_choice() {
+                    1 => Some(ColorSupport::Basic),
+                    2 => Some(ColorSupport::Ansi256),
+                    3 => Some(ColorSupport::TrueColor),
+                    _ => None,
+                };
+                s.set_color_support(colors);
+                s.write().limit_colors = None;
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "synchronized_updates",
+            "Synchronized updates",
+            "Frames are drawn at once, without tearing, auto detects the terminal",
+            Choice(AUTO_ON_OFF),
+            |s| tri_state(s.read().synchronized_updates),
+            |s, value| {
+                s.write().synchronized_updates = from_tri_state(value);
+                Ok(())
+            },
+        ),
+    ]
+}
+
+/// Fields shown after the generated ones, which are rarely changed
+fn trailing_fields() -> Vec<Field> {
+    use FieldKind::*;
+
+    vec![
+        Field::custom(
+            "update_check_interval",
+            "Update check",
+            "How often a newer version is looked for",
+            Choice(UpdateCheckInterval::CHOICES),
+            |s| choice(s.get_check_interval()),
+            |s, value| {
+                s.set_check_interval(from_choice(value).unwrap_or_default());
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "print_paper",
+            "Print paper",
+            "Paper size of the printed mazes",
+            Choice(PAPERS),
+            |s| {
+                let paper = s.read().print_paper.clone().unwrap_or_default();
+                let index = PAPERS.iter().position(|p| p.eq_ignore_ascii_case(&paper));
+                FieldValue::Choice(index.unwrap_or(0))
+            },
+            |s, value| {
+                s.write().print_paper = PAPERS.get(value.into_choice()).map(|p| p.to_string());
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "print_line_width",
+            "Print line width (mm)",
+            "Width of the walls of the printed mazes",
+            Number {
+                min: 0.1,
+                max: 5.0,
+                step: 0.1,
+            },
+            |s| FieldValue::Number(s.read().print_line_width.unwrap_or(0.8) as f64),
+            |s, value| {
+                s.write().print_line_width = Some(value.into_number() as f32);
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "menu_playlist",
+            "Menu playlist",
+            "Songs played in the menus, separated by commas",
+            Text,
+            |s| list(s.get_menu_playlist()),
+            |s, value| {
+                s.set_menu_playlist(from_list(value));
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "game_playlist",
+            "Game playlist",
+            "Songs played in the game, separated by commas",
+            Text,
+            |s| list(s.get_game_playlist()),
+            |s, value| {
+                s.set_game_playlist(from_list(value));
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "logging_level",
+            "Log level",
+            "Least important messages shown in the UI",
+            Choice(LOG_LEVELS),
+            |s| log_level(s.get_logging_level()),
+            |s, value| {
+                s.write().logging_level = from_log_level(value);
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "debug_logging_level",
+            "Debug log level",
+            "Least important messages shown in the UI in the debug mode",
+            Choice(LOG_LEVELS),
+            |s| log_level(s.get_debug_logging_level()),
+            |s, value| {
+                s.write().debug_logging_level = from_log_level(value);
+                Ok(())
+            },
+        ),
+        Field::custom(
+            "file_logging_level",
+            "File log level",
+            "Least important messages written to the log file",
+            Choice(LOG_LEVELS),
+            |s| log_level(s.get_file_logging_level()),
+            |s, value| {
+                s.write().file_logging_level = from_log_level(value);
+                Ok(())
+            },
+        ),
+    ]
+}
+
+/// Every field of the editor, in the order they are shown
+pub fn fields() -> Vec<Field> {
+    let generated = Settings::metadata();
+    let mut fields = leading_fields();
+    fields.extend(generated.iter().map(Field::from_meta));
+    fields.extend(trailing_fields());
+    fields
+}
+
+/// Position of the form and its rows on the screen
+struct Layout {
+    pos: Dims,
+    size: Dims,
+    /// Index of the first visible field
+    first: usize,
+    rows: usize,
+}
+
+pub struct SettingsEditor {
+    fields: Vec<Field>,
+    values: Vec<FieldValue>,
+    selected: usize,
+    /// Text field of the selected row, while it's being edited
+    input: Option<TextInput>,
+    /// Why the last change was rejected
+    error: Option<String>,
+    path: String,
+    hits: HitMap<usize>,
+}
+
+impl SettingsEditor {
+    pub fn new(settings: &Settings) -> Self {
+        let mut editor = Self {
+            fields: fields(),
+            values: vec![],
+            selected: 0,
+            input: None,
+            error: None,
+            path: settings.path().display().to_string(),
+            hits: HitMap::new(),
+        };
+        editor.refresh(settings);
+        editor
+    }
+
+    pub fn new_activity(settings: &Settings) -> Activity {
+        Activity::new_base_boxed("settings editor", Self::new(settings))
+    }
+
+    /// Selects the row of the setting with the name from the settings file
+    pub fn with_selected(mut self, name: &str) -> Self {
+        if let Some(index) = self.fields.iter().position(|f| f.name == name) {
+            self.select(index);
+        }
+        self
+    }
+
+    /// Reads the values again, a change can affect other settings, like the mobile mode
+    fn refresh(&mut self, settings: &Settings) {
+        self.values = self.fields.iter().map(|f| f.get(settings)).collect();
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.fields.len() - 1);
+        self.error = None;
+    }
+
+    fn apply(&mut self, value: FieldValue, data: &mut AppData) {
+        let field = &self.fields[self.selected];
+        match field.set(&mut data.settings, value) {
+            Ok(()) => {
+                self.error = None;
+                self.refresh(&data.settings);
+                data.apply_settings();
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn step(&mut self, up: bool, data: &mut AppData) {
+        let value = self.fields[self.selected].step(&self.values[self.selected], up);
+        if value != self.values[self.selected] {
+            self.apply(value, data);
+        }
+    }
+
+    /// Enter or a click, toggles and choices change, text fields are opened
+    fn activate(&mut self, data: &mut AppData) {
+        let field = &self.fields[self.selected];
+        if field.is_typed() {
+            let mut input = TextInput::new().with_placeholder("default");
+            input.set_value(field.text(&self.values[self.selected]));
+            self.input = Some(input);
+        } else {
+            self.step(true, data);
+        }
+    }
+
+    fn handle_input(&mut self, key: KeyEvent, data: &mut AppData) {
+        let Some(input) = &mut self.input else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Enter => {
+                let text = input.value().to_string();
+                self.input = None;
+                match self.fields[self.selected].parse(&text) {
+                    Ok(value) => self.apply(value, data),
+                    Err(err) => self.error = Some(err),
+                }
+            }
+            KeyCode::Esc => self.input = None,
+            _ => {
+                input.handle_key(key);
+            }
+        }
+    }
+
+    fn layout(&self, screen_size: Dims) -> Layout {
+        let width = (screen_size.0 - 2).clamp(20, MAX_WIDTH);
+        let rows = (screen_size.1 - 2 - CHROME_HEIGHT).clamp(1, self.fields.len() as i32) as usize;
+        let size = Dims(width, rows as i32 + CHROME_HEIGHT);
+
+        // selected row is kept in the middle, when it's possible
+        let first = self
+            .selected
+            .saturating_sub(rows / 2)
+            .min(self.fields.len() - rows);
+
+        Layout {
+            pos: center_box_in_screen(size, screen_size),
+            size,
+            first,
+            rows,
+        }
+    }
+
+    fn row_pos(layout: &Layout, row: usize) -> Dims {
+        layout.pos + Dims(2, 3 + row as i32)
+    }
+}
+
+impl ActivityHandler for SettingsEditor {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        let layout = self.layout(data.screen_size);
+        self.hits.clear();
+        for row in 0..layout.rows {
+            let rect = Rect::sized_at(Self::row_pos(&layout, row), Dims(layout.size.0 - 4, 1));
+            self.hits.register(rect, layout.first + row);
+        }
+
+        let keymap = data.settings.get_keymap();
+
+        for event in events {
+            match event {
+                Event::Term(TermEvent::Key(key)) if !is_release(key.kind) => {
+                    if self.input.is_some() {
+                        self.handle_input(key, data);
+                        continue;
+                    }
+
+                    let code = match keymap.action(&key) {
+                        Some(Action::MoveUp) => KeyCode::Up,
+                        Some(Action::MoveDown) => KeyCode::Down,
+                        Some(Action::MoveLeft) => KeyCode::Left,
+                        Some(Action::MoveRight) => KeyCode::Right,
+                        Some(Action::Pause) => KeyCode::Esc,
+                        _ => key.code,
+                    };
+
+                    let last = self.fields.len() - 1;
+                    match code {
+                        KeyCode::Up => self.select(self.selected.checked_sub(1).unwrap_or(last)),
+                        KeyCode::Down => self.select((self.selected + 1) % self.fields.len()),
+                        KeyCode::PageUp => self.select(self.selected.saturating_sub(layout.rows)),
+                        KeyCode::PageDown => self.select(self.selected + layout.rows),
+                        KeyCode::Home => self.select(0),
+                        KeyCode::End => self.select(last),
+                        KeyCode::Left => self.step(false, data),
+                        KeyCode::Right => self.step(true, data),
+                        KeyCode::Enter | KeyCode::Char(' ') => self.activate(data),
+                        KeyCode::Esc | KeyCode::Char('q') => return Some(Change::pop_top()),
+                        _ => {}
+                    }
+                }
+                Event::Term(TermEvent::Mouse(event)) if self.input.is_none() => {
+                    match self.hits.dispatch(&event) {
+                        Some(Hit::Hover(index)) if index != self.selected => self.select(index),
+                        Some(Hit::Click(index)) => {
+                            self.select(index);
+                            self.activate(data);
+                        }
+                        _ => {}
+                    }
+
+                    match event.kind {
+                        MouseEventKind::ScrollUp => {
+                            self.select(self.selected.saturating_sub(1));
+                        }
+                        MouseEventKind::ScrollDown => self.select(self.selected + 1),
+                        _ => {}
+                    }
+                }
+                Event::SettingsReloaded => self.refresh(&data.settings),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        match self.input {
+            Some(_) => vec![Hint::new("Enter", "save"), Hint::new("Esc", "cancel")],
+            None => vec![
+                Hint::new("↑↓", "select"),
+                Hint::new("←→", "change"),
+                Hint::new("Enter", "edit"),
+                Hint::new("Esc", "back"),
+            ],
+        }
+    }
+}
+
+impl Screen for SettingsEditor {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let [border, title, text, dim, error] = theme.extract([
+            "ui.editor.border",
+            "ui.editor.title",
+            "ui.editor.text",
+            "ui.editor.dim",
+            "ui.editor.error",
+        ]);
+
+        let layout = self.layout(frame.size);
+        let Layout { pos, size, .. } = layout;
+        let inner = (size.0 - 4).max(0) as usize;
+
+        frame.fill_rect(pos, size, Cell::styled(' ', text));
+        draw_box(frame, pos, size, border);
+        frame.draw(pos + Dims(3, 0), " All settings ", title);
+        frame.draw(pos + Dims(2, 1), fit(&self.path, inner), dim);
+
+        for row in 0..layout.rows {
+            let index = layout.first + row;
+            let (field, value) = (&self.fields[index], &self.values[index]);
+            let line = Self::row_pos(&layout, row);
+            let style = match index == self.selected {
+                true => text.invert(),
+                false => text,
+            };
+
+            let editing = self.input.as_ref().filter(|_| index == self.selected);
+            let value_width = match editing {
+                Some(_) => inner / 2,
+                None => field.display(value).width().min(inner / 2),
+            };
+            let label = fit(field.label, inner - value_width - 1);
+            frame.draw(line, format!("{:inner$}", label), style);
+
+            let value_pos = line + Dims((inner - value_width) as i32, 0);
+            match editing {
+                Some(input) => input.draw(frame, theme, value_pos, value_width as i32),
+                None => frame.draw(value_pos, fit(&field.display(value), value_width), style),
+            }
+        }
+
+        // description of the selected field, or why the change was rejected
+        let (message, style) = match &self.error {
+            Some(err) => (err.as_str(), error),
+            None => (self.fields[self.selected].description.as_str(), dim),
+        };
+        let bottom = pos + Dims(2, size.1 - 3);
+        for (i, line) in wrap_words(message, inner).iter().take(2).enumerate() {
+            frame.draw(bottom + Dims(0, i as i32), line.as_str(), style);
+        }
+
+        Ok(())
+    }
+}
+
+/// Text cut to the width, with an ellipsis if it doesn't fit
+pub(super) fn fit(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut fitted = String::new();
+    for ch in text.chars() {
+        if fitted.width() + 2 > width {
+            break;
+        }
+        fitted.push(ch);
+    }
+    fitted.push('…');
+    fitted
+}
+
+pub fn editor_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("ui.editor.border", "border")
+        .desc("Border of the editors")
+        .link("ui.editor.title", "text")
+        .desc("Titles in the editors")
+        .link("ui.editor.text", "text")
+        .desc("Text of the editors")
+        .link("ui.editor.dim", "dim")
+        .desc("Help and less important text of the editors")
+        .link("ui.editor.error", "ui.editor.text")
+        .desc("Invalid values in the editors");
+
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doc_links_are_plain() {
+        assert_eq!(
+            plain_doc(" Save a replay, see [`app::replay`]"),
+            "Save a replay, see replay"
+        );
+        assert_eq!(
+            plain_doc(" Like [`BumpFeedback::Flash`] with `first_person`"),
+            "Like Flash with first_person"
+        );
+    }
+
+    #[test]
+    fn selected_by_name() {
+        let settings = Settings::default();
+        let editor = SettingsEditor::new(&settings).with_selected("sticky_modifiers");
+        assert_eq!(editor.fields[editor.selected].name, "sticky_modifiers");
+
+        let editor = SettingsEditor::new(&settings).with_selected("missing");
+        assert_eq!(editor.selected, 0);
+    }
+
+    #[test]
+    fn every_field_round_trips() {
+        let mut settings = Settings::default();
+        let fields = fields();
+
+        let mut names: Vec<_> = fields.iter().map(|f| f.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), fields.len(), "fields should be unique");
+
+        for field in fields.iter().filter(|f| f.name != "theme") {
+            let value = field.get(&settings);
+            let changed = match field.kind {
+                FieldKind::Text => FieldValue::Text("1, 2".to_string()),
+                _ => field.step(&value, true),
+            };
+
+            field.set(&mut settings, changed.clone()).unwrap();
+            let value = field.get(&settings);
+            assert_eq!(field.text(&value), field.text(&changed), "{}", field.name);
+        }
+    }
+}
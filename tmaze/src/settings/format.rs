@@ -0,0 +1,496 @@
+//! File formats of the settings
+//!
+//! RON is the default one, TOML is used when the file has the `.toml` extension.
+//! Keys the game doesn't know are kept in TOML files, so they aren't lost when the file is saved
+//! by an older version or edited by other tools. RON can't represent them next to the known
+//! fields (see <https://github.com/ron-rs/ron/issues/115>), so they are dropped there.
+//!
+//! Saved files are patched, only changed settings are written into them, so comments
+//! and formatting of the rest of the file are kept.
+
+use std::{io, ops::Range, path::Path};
+
+use ron::extensions::Extensions;
+use serde::{
+    de::{self, Visitor},
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use thiserror::Error;
+
+use super::{SettingsInner, DEFAULT_SETTINGS};
+
+/// Top-level keys of a TOML file, which are not settings
+pub type UnknownFields = toml::Table;
+
+/// Values of the set settings by their names, used to find out which of them changed
+pub type FieldValues = serde_json::Map<String, serde_json::Value>;
+
+const TOML_HEADER: &str = "# TMaze settings, every setting is described in the settings editor\n\n";
+
+#[derive(Debug, Error)]
+pub enum FormatError {
+    #[error(transparent)]
+    Ron(#[from] ron::error::SpannedError),
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
+    #[error(transparent)]
+    Value(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsFormat {
+    Ron,
+    Toml,
+}
+
+impl SettingsFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => SettingsFormat::Toml,
+            _ => SettingsFormat::Ron,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SettingsFormat::Ron => "ron",
+            SettingsFormat::Toml => "toml",
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<(SettingsInner, UnknownFields), FormatError> {
+        match self {
+            SettingsFormat::Ron => Ok((ron_options().from_str(text)?, UnknownFields::new())),
+            SettingsFormat::Toml => {
+                let mut table: toml::Table = text.parse()?;
+                // TOML map keys are always strings, JSON values parse them back to numbers
+                let settings = SettingsInner::deserialize(serde_json::to_value(&table)?)?;
+
+                let known = field_names();
+                table.retain(|key, _| !known.contains(&key));
+                Ok((settings, table))
+            }
+        }
+    }
+
+    pub fn to_string(
+        self,
+        settings: &SettingsInner,
+        unknown: &UnknownFields,
+    ) -> io::Result<String> {
+        let invalid = |err: &dyn std::fmt::Display| {
+            io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+        };
+
+        match self {
+            SettingsFormat::Ron => {
+                let config =
+                    ron::ser::PrettyConfig::default().extensions(Extensions::IMPLICIT_SOME);
+                ron::ser::to_string_pretty(settings, config).map_err(|err| invalid(&err))
+            }
+            SettingsFormat::Toml => {
+                // through JSON text, so number keys become strings and floats are kept short
+                let json = serde_json::to_string(settings).map_err(|err| invalid(&err))?;
+                let mut json: serde_json::Value =
+                    serde_json::from_str(&json).map_err(|err| invalid(&err))?;
+                remove_nulls(&mut json);
+                let mut table: toml::Table =
+                    serde_json::from_value(json).map_err(|err| invalid(&err))?;
+                for (key, value) in unknown {
+                    table.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+
+                let body = toml::to_string_pretty(&table).map_err(|err| invalid(&err))?;
+                Ok(format!("{TOML_HEADER}{body}"))
+            }
+        }
+    }
+
+    /// Writes `changed` settings into `text` of the file, the rest of the file is kept as it is
+    ///
+    /// Settings which are not set anymore are removed, new ones are added at the end.
+    /// Returns `None` if `text` can't be patched, the whole file has to be written then.
+    pub fn patch(self, text: &str, settings: &SettingsInner, changed: &[&str]) -> Option<String> {
+        let new_text = self.to_string(settings, &UnknownFields::new()).ok()?;
+
+        match self {
+            SettingsFormat::Ron => {
+                let old = RonStruct::parse(text)?;
+                let new = RonStruct::parse(&new_text)?;
+
+                let changes = changed.iter().map(|name| {
+                    let value = new.field(name).map(|field| &new_text[field.value.clone()]);
+                    (*name, value)
+                });
+                Some(old.patch(text, changes))
+            }
+            SettingsFormat::Toml => {
+                let mut doc: toml_edit::DocumentMut = text.parse().ok()?;
+                let new: toml_edit::DocumentMut = new_text.parse().ok()?;
+
+                for name in changed {
+                    match (doc.get_mut(name), new.get(name)) {
+                        // assigned in place, so comments around the key are kept
+                        (Some(old), Some(item)) => {
+                            let mut item = item.clone();
+                            if let (Some(old), Some(new)) = (old.as_value(), item.as_value_mut()) {
+                                *new.decor_mut() = old.decor().clone();
+                            }
+                            *old = item;
+                        }
+                        (None, Some(item)) => {
+                            doc.insert(name, item.clone());
+                        }
+                        (_, None) => {
+                            doc.remove(name);
+                        }
+                    }
+                }
+                Some(doc.to_string())
+            }
+        }
+    }
+
+    /// Contents of a new settings file, RON one has documentation of every setting
+    pub fn default_file(self) -> String {
+        match self {
+            SettingsFormat::Ron => DEFAULT_SETTINGS.to_string(),
+            SettingsFormat::Toml => self
+                .to_string(&default_settings(), &UnknownFields::new())
+                .expect("default settings should be serializable"),
+        }
+    }
+}
+
+pub(super) fn ron_options() -> ron::Options {
+    ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME)
+}
+
+pub(super) fn default_settings() -> SettingsInner {
+    ron_options()
+        .from_str(DEFAULT_SETTINGS)
+        .expect("default settings should be valid")
+}
+
+pub(super) fn field_values(settings: &SettingsInner) -> FieldValues {
+    match serde_json::to_value(settings).expect("settings should be serializable") {
+        serde_json::Value::Object(map) => map,
+        _ => unreachable!("settings are a struct"),
+    }
+}
+
+/// Names of the settings which are different in `new`
+pub(super) fn changed_fields(old: &FieldValues, new: &FieldValues) -> Vec<&'static str> {
+    field_names()
+        .iter()
+        .copied()
+        .filter(|name| old.get(*name) != new.get(*name))
+        .collect()
+}
+
+/// TOML has no null, missing keys are read as `None` the same way
+fn remove_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+/// Names of the fields of [`SettingsInner`], as its `Deserialize` impl sees them
+fn field_names() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = SettingsInner::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Deserializer which only records the fields of the struct it's asked for
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("expected a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("fields recorded"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Field of a RON struct, with positions in the text it was parsed from
+#[derive(Debug)]
+struct RonField {
+    name: String,
+    /// Start of the name
+    start: usize,
+    value: Range<usize>,
+    /// End of the field, after the comma if there is one
+    end: usize,
+}
+
+/// Top-level struct of a RON file, only its fields are parsed, values are skipped
+#[derive(Debug)]
+struct RonStruct {
+    fields: Vec<RonField>,
+    /// Position of the closing parenthesis
+    close: usize,
+}
+
+impl RonStruct {
+    fn parse(text: &str) -> Option<Self> {
+        let mut scanner = RonScanner { text, pos: 0 };
+
+        // skips the struct name and attributes like `#![enable(implicit_some)]`
+        let mut depth = 0usize;
+        loop {
+            scanner.skip_trivia()?;
+            match scanner.peek()? {
+                b'(' if depth == 0 => break,
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth = depth.checked_sub(1)?,
+                _ => {}
+            }
+            scanner.skip_token()?;
+        }
+        scanner.pos += 1;
+
+        let mut fields = vec![];
+        loop {
+            scanner.skip_trivia()?;
+            if scanner.peek()? == b')' {
+                return Some(Self {
+                    fields,
+                    close: scanner.pos,
+                });
+            }
+
+            let start = scanner.pos;
+            let name = scanner.ident()?.to_string();
+            scanner.skip_trivia()?;
+            if scanner.peek()? != b':' {
+                return None;
+            }
+            scanner.pos += 1;
+            scanner.skip_trivia()?;
+
+            let value_start = scanner.pos;
+            let mut value_end = scanner.pos;
+            let mut depth = 0usize;
+            loop {
+                match scanner.peek()? {
+                    b',' | b')' if depth == 0 => break,
+                    b'(' | b'[' | b'{' => depth += 1,
+                    b')' | b']' | b'}' => depth = depth.checked_sub(1)?,
+                    _ => {}
+                }
+                scanner.skip_token()?;
+                value_end = scanner.pos;
+                scanner.skip_trivia()?;
+            }
+            if value_start == value_end {
+                return None;
+            }
+
+            let end = if scanner.peek()? == b',' {
+                scanner.pos += 1;
+                scanner.pos
+            } else {
+                value_end
+            };
+            fields.push(RonField {
+                name,
+                start,
+                value: value_start..value_end,
+                end,
+            });
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&RonField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Sets values of the fields, `None` removes the field
+    fn patch<'a>(
+        &self,
+        text: &str,
+        changes: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    ) -> String {
+        let mut edits: Vec<(Range<usize>, String)> = vec![];
+        let mut added = String::new();
+
+        for (name, value) in changes {
+            match (self.field(name), value) {
+                (Some(field), Some(value)) => edits.push((field.value.clone(), value.to_string())),
+                (Some(field), None) => {
+                    edits.push((whole_lines(text, field.start..field.end), "".into()))
+                }
+                (None, Some(value)) => added.push_str(&format!("    {}: {},\n", name, value)),
+                (None, None) => {}
+            }
+        }
+
+        if !added.is_empty() {
+            let line_start = text[..self.close].rfind('\n').map_or(0, |i| i + 1);
+            if text[line_start..self.close].trim().is_empty() {
+                edits.push((line_start..line_start, added));
+            } else {
+                edits.push((self.close..self.close, format!("\n{}", added)));
+            }
+
+            // last field needs a comma before the new ones
+            if let Some(last) = self.fields.last().filter(|last| last.end == last.value.end) {
+                edits.push((last.end..last.end, ",".into()));
+            }
+        }
+
+        edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        let mut text = text.to_string();
+        for (range, replacement) in edits {
+            text.replace_range(range, &replacement);
+        }
+        text
+    }
+}
+
+/// Extends the range to whole lines, if there is nothing else on them
+fn whole_lines(text: &str, range: Range<usize>) -> Range<usize> {
+    let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |i| range.end + i + 1);
+
+    let before = &text[line_start..range.start];
+    let after = &text[range.end..line_end];
+    if before.trim().is_empty() && after.trim().is_empty() {
+        line_start..line_end
+    } else {
+        range
+    }
+}
+
+/// Walks over RON text token by token, strings and comments are skipped as a whole
+struct RonScanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> RonScanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    /// Skips whitespace and comments, `None` if a comment is not closed
+    fn skip_trivia(&mut self) -> Option<()> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                // block comments can be nested in RON
+                let mut depth = 0usize;
+                loop {
+                    let rest = self.rest();
+                    if rest.starts_with("/*") {
+                        depth += 1;
+                        self.pos += 2;
+                    } else if rest.starts_with("*/") {
+                        depth -= 1;
+                        self.pos += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        self.pos += rest.chars().next()?.len_utf8();
+                    }
+                }
+            } else {
+                match rest.chars().next() {
+                    Some(c) if c.is_whitespace() => self.pos += c.len_utf8(),
+                    _ => return Some(()),
+                }
+            }
+        }
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        (len > 0).then(|| &rest[..len])
+    }
+
+    /// Skips a string, char, identifier, number or a single character
+    fn skip_token(&mut self) -> Option<()> {
+        let rest = self.rest();
+        let c = rest.chars().next()?;
+        match c {
+            '"' => self.skip_quoted(b'"'),
+            '\'' => self.skip_quoted(b'\''),
+            c if c.is_alphanumeric() || c == '_' => {
+                let ident = self.ident()?;
+                if matches!(ident, "r" | "br") && matches!(self.peek(), Some(b'"' | b'#')) {
+                    self.skip_raw_string()
+                } else if ident == "b" && matches!(self.peek(), Some(b'"' | b'\'')) {
+                    self.skip_token()
+                } else {
+                    Some(())
+                }
+            }
+            c => {
+                self.pos += c.len_utf8();
+                Some(())
+            }
+        }
+    }
+
+    fn skip_quoted(&mut self, quote: u8) -> Option<()> {
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                c if c == quote => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Raw string after its `r`, like `#"text with "quotes""#`
+    fn skip_raw_string(&mut self) -> Option<()> {
+        let hashes = self.rest().find(|c| c != '#')?;
+        self.pos += hashes;
+        if self.peek()? != b'"' {
+            return None;
+        }
+        self.pos += 1;
+
+        let end = format!("\"{}", "#".repeat(hashes));
+        self.pos += self.rest().find(&end)? + end.len();
+        Some(())
+    }
+}
@@ -0,0 +1,517 @@
+//! Key bindings of the game actions, set in the `keybindings` section of the settings
+//!
+//! Bindings are key chords like `"w"`, `"Up"` or `"Ctrl+z"`. Shift is never part of a chord,
+//! it's reserved for the fast moves, so `"W"` and `"w"` are the same key.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use cmaze::gameboard::CellWall;
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{self, app::AppData, Activity, ActivityHandler, Change},
+    helpers::is_release,
+    ui::{Hint, Menu, MenuConfig, MenuItem, Screen},
+};
+
+/// Action which can be bound to keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    FloorUp,
+    FloorDown,
+    Pause,
+    SwitchMode,
+    Hint,
+    Undo,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::FloorUp,
+        Action::FloorDown,
+        Action::Pause,
+        Action::SwitchMode,
+        Action::Hint,
+        Action::Undo,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::FloorUp => "Floor up",
+            Action::FloorDown => "Floor down",
+            Action::Pause => "Pause",
+            Action::SwitchMode => "Switch mode",
+            Action::Hint => "Hint",
+            Action::Undo => "Undo",
+        }
+    }
+
+    /// Text of the action on a button, like the ones of the dpad
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Action::MoveUp => "↑",
+            Action::MoveDown => "↓",
+            Action::MoveLeft => "←",
+            Action::MoveRight => "→",
+            Action::FloorUp => "Up",
+            Action::FloorDown => "Down",
+            Action::Pause => "Pause",
+            Action::SwitchMode => "Mode",
+            Action::Hint => "Hint",
+            Action::Undo => "Undo",
+        }
+    }
+
+    /// Direction of the move, `None` for the other actions
+    pub fn direction(self) -> Option<CellWall> {
+        match self {
+            Action::MoveUp => Some(CellWall::Top),
+            Action::MoveDown => Some(CellWall::Bottom),
+            Action::MoveLeft => Some(CellWall::Left),
+            Action::MoveRight => Some(CellWall::Right),
+            Action::FloorUp => Some(CellWall::Up),
+            Action::FloorDown => Some(CellWall::Down),
+            _ => None,
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::MoveUp => &["w", "Up"],
+            Action::MoveDown => &["s", "Down"],
+            Action::MoveLeft => &["a", "Left"],
+            Action::MoveRight => &["d", "Right"],
+            Action::FloorUp => &["e", "r", "p"],
+            Action::FloorDown => &["q", "f", "l"],
+            Action::Pause => &["Esc"],
+            Action::SwitchMode => &["Space"],
+            Action::Hint => &["h"],
+            Action::Undo => &["u", "Backspace"],
+        }
+    }
+}
+
+/// Key with modifiers, written like `Ctrl+Alt+x`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Chord of the pressed key, `None` for keys which can't be bound, like lone modifiers
+    pub fn from_event(event: &KeyEvent) -> Option<Self> {
+        let code = match event.code {
+            KeyCode::Char(ch) => KeyCode::Char(ch.to_ascii_lowercase()),
+            code @ (KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Esc
+            | KeyCode::Enter
+            | KeyCode::Tab
+            | KeyCode::Backspace
+            | KeyCode::Delete
+            | KeyCode::Insert
+            | KeyCode::Home
+            | KeyCode::End
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::F(_)) => code,
+            _ => return None,
+        };
+
+        Some(Self {
+            code,
+            modifiers: event.modifiers - KeyModifiers::SHIFT,
+        })
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 14] = [
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Esc", KeyCode::Esc),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+const MODIFIERS: [(&str, KeyModifiers); 3] = [
+    ("Ctrl", KeyModifiers::CONTROL),
+    ("Alt", KeyModifiers::ALT),
+    ("Super", KeyModifiers::SUPER),
+];
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `+` itself is a valid key, so it's split off only when followed by a key
+        let (mods, key) = match s.rsplit_once('+') {
+            Some((mods, "")) => (mods.strip_suffix('+').unwrap_or(""), "+"),
+            Some((mods, key)) => (mods, key),
+            None => ("", s),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in mods.split('+').filter(|m| !m.is_empty()) {
+            let (_, modifier) = MODIFIERS
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("unknown modifier '{}' in '{}'", name, s))?;
+            modifiers |= *modifier;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch.to_ascii_lowercase()),
+            _ if key.eq_ignore_ascii_case("Space") => KeyCode::Char(' '),
+            _ => match NAMED_KEYS.iter().find(|(n, _)| n.eq_ignore_ascii_case(key)) {
+                Some((_, code)) => *code,
+                None => match key.strip_prefix(['F', 'f']).map(str::parse) {
+                    Some(Ok(n @ 1..=24)) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{}'", s)),
+                },
+            },
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, modifier) in MODIFIERS {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(ch) => write!(f, "{}", ch.to_ascii_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => {
+                let (name, _) = NAMED_KEYS
+                    .iter()
+                    .find(|(_, c)| *c == code)
+                    .expect("only named keys are parsed");
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string()
+    }
+}
+
+/// Keys of all actions, actions missing in the settings keep their default keys
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMap {
+    bindings: BTreeMap<Action, Vec<KeyChord>>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = action.default_keys().iter();
+                let keys = keys.map(|key| key.parse().expect("default keys are valid"));
+                (action, keys.collect())
+            })
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl KeyMap {
+    pub fn new(overrides: Option<&BTreeMap<Action, Vec<KeyChord>>>) -> Self {
+        let mut keymap = Self::default();
+        if let Some(overrides) = overrides {
+            keymap.bindings.extend(overrides.clone());
+        }
+        keymap
+    }
+
+    /// Action bound to the pressed key, `None` if the key is not bound
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(event)?;
+        Action::ALL
+            .into_iter()
+            .find(|action| self.keys(*action).contains(&chord))
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyChord] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds the key to the action, replacing its keys, the key is removed from other actions
+    pub fn bind(&mut self, action: Action, chord: KeyChord) {
+        for (other, keys) in &mut self.bindings {
+            if *other != action && keys.contains(&chord) {
+                log::warn!("{} was bound to {}, unbinding", chord, other.label());
+                keys.retain(|key| *key != chord);
+            }
+        }
+        self.bindings.insert(action, vec![chord]);
+    }
+
+    pub fn bindings(&self) -> &BTreeMap<Action, Vec<KeyChord>> {
+        &self.bindings
+    }
+
+    /// All keys of the action, shown in the settings, e.g. `W, Up`
+    pub fn label(&self, action: Action) -> String {
+        match self.keys(action) {
+            [] => "unbound".to_string(),
+            keys => keys
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
+    /// First key of each action, shown in the key hints, e.g. `Q/E`
+    ///
+    /// Single characters are joined without a separator, so the moves are shown as `WASD`.
+    pub fn hint(&self, actions: &[Action]) -> String {
+        let keys: Vec<_> = actions
+            .iter()
+            .map(|action| match self.keys(*action).first() {
+                Some(key) => key.to_string(),
+                None => "-".to_string(),
+            })
+            .collect();
+
+        match actions.len() > 2 && keys.iter().all(|key| key.chars().count() == 1) {
+            true => keys.concat(),
+            false => keys.join("/"),
+        }
+    }
+}
+
+/// Lists the actions with their keys, selecting an action waits for a new key
+pub struct KeyBindingsActivity {
+    menu: Menu,
+    /// Action waiting for a new key
+    capturing: Option<Action>,
+}
+
+impl KeyBindingsActivity {
+    pub fn new(keymap: &KeyMap, selected: usize) -> Self {
+        let mut options: Vec<_> = Action::ALL
+            .iter()
+            .map(|action| {
+                let text = format!("{}: {}", action.label(), keymap.label(*action));
+                MenuItem::Text(text.into())
+            })
+            .collect();
+        options.extend([
+            MenuItem::Separator,
+            MenuItem::Text("Reset to defaults".into()),
+            MenuItem::Text("Back".into()),
+        ]);
+
+        let menu_config = MenuConfig::new("Key bindings", options)
+            .subtitles(Self::subtitles(None))
+            .default(selected)
+            .no_q();
+
+        Self {
+            menu: Menu::new(menu_config),
+            capturing: None,
+        }
+    }
+
+    pub fn new_activity(keymap: &KeyMap, selected: usize) -> Activity {
+        Activity::new_base_boxed("key bindings", Self::new(keymap, selected))
+    }
+
+    fn subtitles(capturing: Option<Action>) -> Vec<String> {
+        match capturing {
+            Some(action) => vec![
+                format!("Press a new key for {}", action.label().to_lowercase()),
+                "Esc to cancel".to_string(),
+            ],
+            None => vec![
+                "Select an action to rebind it".to_string(),
+                "Shift always makes moves fast".to_string(),
+            ],
+        }
+    }
+
+    fn capture(
+        &mut self,
+        action: Action,
+        events: Vec<app::Event>,
+        data: &mut AppData,
+    ) -> Option<Change> {
+        for event in events {
+            let app::Event::Term(TermEvent::Key(key)) = event else {
+                continue;
+            };
+            if is_release(key.kind) {
+                continue;
+            }
+
+            if key.code == KeyCode::Esc {
+                self.capturing = None;
+                self.menu.set_subtitles(Self::subtitles(None));
+                return None;
+            }
+
+            let Some(chord) = KeyChord::from_event(&key) else {
+                continue;
+            };
+
+            data.settings.set_keybinding(action, chord);
+            log::info!("{} bound to {}", action.label(), chord);
+
+            let keymap = data.settings.get_keymap();
+            return Some(Change::replace(Self::new_activity(
+                &keymap,
+                self.menu.selected(),
+            )));
+        }
+
+        None
+    }
+}
+
+impl ActivityHandler for KeyBindingsActivity {
+    fn update(&mut self, events: Vec<app::Event>, data: &mut AppData) -> Option<Change> {
+        if let Some(action) = self.capturing {
+            return self.capture(action, events, data);
+        }
+
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                match Action::ALL.get(index) {
+                    Some(action) => {
+                        self.capturing = Some(*action);
+                        self.menu.set_subtitles(Self::subtitles(self.capturing));
+                        None
+                    }
+                    // separator is skipped, so the reset is right after the actions
+                    None if index == Action::ALL.len() + 1 => {
+                        data.settings.reset_keybindings();
+                        log::info!("Key bindings reset to defaults");
+                        let keymap = data.settings.get_keymap();
+                        Some(Change::replace(Self::new_activity(&keymap, index)))
+                    }
+                    None => Some(Change::pop_top()),
+                }
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        match self.capturing {
+            Some(_) => vec![Hint::new("Any key", "bind"), Hint::new("Esc", "cancel")],
+            None => self.menu.hints(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_parse_and_print() {
+        for (text, printed) in [
+            ("w", "W"),
+            ("Ctrl+z", "Ctrl+Z"),
+            ("space", "Space"),
+            ("Alt++", "Alt++"),
+            ("f5", "F5"),
+            ("Up", "Up"),
+        ] {
+            let chord: KeyChord = text.parse().unwrap();
+            assert_eq!(chord.to_string(), printed);
+            assert_eq!(printed.parse::<KeyChord>(), Ok(chord));
+        }
+
+        assert!("Hyper+w".parse::<KeyChord>().is_err());
+        assert!("Shift+w".parse::<KeyChord>().is_err());
+        assert!("F99".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn rebinding_moves_the_key() {
+        let mut keymap = KeyMap::default();
+        let shifted = KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT);
+        assert_eq!(keymap.action(&shifted), Some(Action::FloorDown));
+
+        keymap.bind(Action::Undo, "q".parse().unwrap());
+        assert_eq!(keymap.action(&shifted), Some(Action::Undo));
+        assert_eq!(keymap.label(Action::FloorDown), "F, L");
+        assert_eq!(keymap.label(Action::Undo), "Q");
+        assert_eq!(
+            keymap.hint(&[Action::MoveUp, Action::MoveLeft, Action::MoveDown]),
+            "WAS"
+        );
+    }
+
+    #[test]
+    fn bindings_load_from_ron() {
+        let bindings: BTreeMap<Action, Vec<KeyChord>> =
+            ron::from_str(r#"{ undo: ["Ctrl+z"], hint: [] }"#).unwrap();
+        let keymap = KeyMap::new(Some(&bindings));
+
+        let ctrl_z = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(&ctrl_z), Some(Action::Undo));
+        assert_eq!(keymap.label(Action::Hint), "unbound");
+        assert_eq!(keymap.label(Action::Pause), "Esc");
+    }
+}
@@ -1,27 +1,57 @@
+#[macro_use]
+pub mod accessors;
+pub mod ambience;
 mod attribute;
+pub mod content;
+pub mod editor;
+pub mod format;
+pub mod keymap;
+pub mod search;
 pub mod theme;
+pub mod theme_editor;
 
+use ambience::{Ambience, AmbienceVariant, TimeOfDay};
+#[cfg(feature = "print")]
+use cmaze::print::PrintOptions;
 use cmaze::{
-    dims::{Dims, Offset},
-    game::GeneratorFn,
-    gameboard::algorithms::MazeAlgorithm,
+    dims::{Dims, Dims3D, GameMode, Offset},
+    game::{GeneratorFn, GoalObjective, GoalPlacement, ItemPlacement},
+    gameboard::{
+        algorithms::{MazeAlgorithm, SeedSource},
+        CellWall,
+    },
 };
 use derivative::Derivative;
-use ron::{self, extensions::Extensions};
+use format::{default_settings, FieldValues, SettingsFormat, UnknownFields};
+use keymap::{Action, KeyBindingsActivity, KeyChord, KeyMap};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fs, io,
-    path::PathBuf,
-    sync::{Arc, RwLock},
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::{Duration, SystemTime},
 };
 use theme::ThemeDefinition;
 
 use crate::{
     app::{self, app::AppData, Activity, ActivityHandler, Change},
-    helpers::constants::paths::settings_path,
+    content::{load_presets, ContentKind},
+    error::AppError,
+    helpers::{
+        constants::paths::{overlay_path, settings_path, theme_file_path},
+        on_off, Zoom,
+    },
     menu_actions,
-    renderer::MouseGuard,
-    ui::{split_menu_actions, Menu, MenuAction, MenuConfig, MenuItem, OptionDef, Popup, Screen},
+    renderer::compat::{ColorSupport, RenderCompat},
+    ui::{
+        split_menu_actions, usecase::dpad, Easing, Hint, Menu, MenuAction, MenuConfig, MenuItem,
+        OptionDef, Screen, SliderDef,
+    },
 };
 
 #[cfg(feature = "sound")]
@@ -29,6 +59,16 @@ use crate::sound::create_audio_settings;
 
 const DEFAULT_SETTINGS: &str = include_str!("./default_settings.ron");
 
+/// Longest input debounce, so the game can't be made unplayable by accident
+const MAX_INPUT_DEBOUNCE: u64 = 1000;
+/// Longest animation of a move by one cell, in milliseconds
+const MAX_ANIMATION: u64 = 500;
+
+/// Adaptive viewport margin relative to the shorter side of the screen
+const ADAPTIVE_MARGIN_RATIO: f32 = 0.08;
+/// Largest adaptive viewport margin, in widths of a character
+const MAX_ADAPTIVE_MARGIN: f32 = 8.0;
+
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum CameraMode {
     #[default]
@@ -36,6 +76,26 @@ pub enum CameraMode {
     EdgeFollow(Offset, Offset),
 }
 
+/// What happens when the player bumps into a wall, see [`Settings::get_bump_feedback`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BumpFeedback {
+    #[default]
+    None,
+    /// Terminal bell, some terminals flash the window instead
+    Bell,
+    /// Viewport flashes briefly, unless flashing or motion is reduced
+    Flash,
+}
+
+/// How the maze of a preset is played
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum PresetMode {
+    #[default]
+    Normal,
+    /// Maze has to be finished within this many seconds
+    Timed(f32),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MazePreset {
     pub title: String,
@@ -47,13 +107,97 @@ pub struct MazePreset {
     pub tower: bool,
     #[serde(default)]
     pub default: bool,
+    #[serde(default)]
+    pub goals: GoalPlacement,
+    #[serde(default)]
+    pub objective: GoalObjective,
+    /// Fraction of dead ends removed, from 0 (perfect maze) to 1 (no dead ends)
+    #[serde(default)]
+    pub braid: f32,
+    /// Floors which are braided, from 1, all of them if empty
+    #[serde(default)]
+    pub braid_floors: Vec<u16>,
+    /// Moves can't be taken back
+    #[serde(default)]
+    pub hardcore: bool,
+    #[serde(default)]
+    pub mode: PresetMode,
+    /// Coins on every floor
+    #[serde(default)]
+    pub coins: u16,
+    /// Locked door on the way to the goal, which needs a key
+    #[serde(default)]
+    pub door: bool,
+    /// Moves cost stamina, which comes back while the player stands still
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stamina: Option<app::stamina::Stamina>,
+    /// Size is searched for a maze with this solve time or difficulty, width and height only
+    /// give the ratio of the sides, see [`app::target`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<app::target::SolveTarget>,
+    /// Settings used while the maze is played, like its camera mode or theme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<SettingsInner>,
+}
+
+impl MazePreset {
+    /// Time limit of timed presets, at least a second
+    pub fn time_limit(&self) -> Option<Duration> {
+        match self.mode {
+            PresetMode::Normal => None,
+            PresetMode::Timed(seconds) => Some(Duration::from_secs_f32(seconds.max(1.0))),
+        }
+    }
+
+    pub fn game_mode(&self) -> GameMode {
+        let braid_floors = self
+            .braid_floors
+            .iter()
+            .filter(|floor| (1..=64).contains(*floor))
+            .fold(0, |mask, floor| mask | 1 << (floor - 1));
+
+        GameMode {
+            size: Dims3D(self.width as i32, self.height as i32, self.depth as i32),
+            is_tower: self.tower,
+            goals: self.goals,
+            objective: self.objective,
+            braid: (self.braid.clamp(0.0, 1.0) * 100.0).round() as u8,
+            braid_floors,
+            items: ItemPlacement {
+                coins: self.coins,
+                door: self.door,
+            },
+        }
+    }
+}
+
+/// Single move of a recorded macro
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MacroStep {
+    pub dir: CellWall,
+    #[serde(default)]
+    pub fast: bool,
+}
+
+/// Value of a game mode option, see [`crate::app::modes::ModeSetting`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModeValue {
+    Bool(bool),
+    Number(f64),
+}
+
+impl ModeValue {
+    pub fn same_kind(&self, other: &ModeValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
 }
 
 fn default_depth() -> u16 {
     1
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum MazeGenAlgo {
     #[default]
     RandomKruskals,
@@ -63,15 +207,17 @@ pub enum MazeGenAlgo {
 impl MazeGenAlgo {
     pub fn to_fn(&self) -> GeneratorFn {
         match self {
-            MazeGenAlgo::RandomKruskals => cmaze::gameboard::algorithms::RndKruskals::generate,
+            MazeGenAlgo::RandomKruskals => {
+                cmaze::gameboard::algorithms::RndKruskals::generate_seeded
+            }
             MazeGenAlgo::DepthFirstSearch => {
-                cmaze::gameboard::algorithms::DepthFirstSearch::generate
+                cmaze::gameboard::algorithms::DepthFirstSearch::generate_seeded
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum UpdateCheckInterval {
     Never,
     #[default]
@@ -82,82 +228,593 @@ pub enum UpdateCheckInterval {
     Always,
 }
 
-#[derive(Debug, Derivative, Serialize, Deserialize)]
+choice_setting_value!(Zoom {
+    Braille => "Braille",
+    Compact => "Compact",
+    HalfBlock => "Half blocks",
+    Normal => "Normal",
+    Large => "Large",
+});
+
+choice_setting_value!(Easing {
+    Linear => "Linear",
+    EaseIn => "Ease in",
+    EaseOut => "Ease out",
+    EaseInOut => "Ease in and out",
+});
+
+choice_setting_value!(BumpFeedback {
+    None => "None",
+    Bell => "Bell",
+    Flash => "Flash",
+});
+
+choice_setting_value!(UpdateCheckInterval {
+    Never => "Never",
+    Daily => "Daily",
+    Weekly => "Weekly",
+    Monthly => "Monthly",
+    Yearly => "Yearly",
+    Always => "Always",
+});
+
+choice_setting_value!(SeedSource {
+    Entropy => "Entropy",
+    Time => "Time",
+    Counter => "Counter",
+});
+
+choice_setting_value!(MazeGenAlgo {
+    RandomKruskals => "Random Kruskal's",
+    DepthFirstSearch => "Depth-first search",
+});
+
+/// Named set of overrides over the base settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsProfile {
+    pub name: String,
+    pub settings: SettingsInner,
+}
+
+#[derive(Debug, Clone, Derivative, Serialize, Deserialize)]
 #[derivative(Default)]
 #[serde(rename = "Settings")]
 pub struct SettingsInner {
     // general
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub theme: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub logging_level: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub debug_logging_level: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub file_logging_level: Option<String>,
 
     // viewport
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub slow: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub disable_tower_auto_up: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relative_controls: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_person_view: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub camera_mode: Option<CameraMode>,
-    #[serde(default)]
-    pub camera_smoothing: Option<f32>,
-    #[serde[default]]
-    pub player_smoothing: Option<f32>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation_easing: Option<Easing>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_animation: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_animation: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maze_zoom: Option<Zoom>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub viewport_margin: Option<(i32, i32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_aspect: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_path: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_delay: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reduce_motion: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attract_delay: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_screen_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_key_hints: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_breadcrumbs: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_flash: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessible: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bump_feedback: Option<BumpFeedback>,
+
+    // terminal compatibility, detected automatically if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ascii_charset: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_support: Option<ColorSupport>,
+    /// Older switch of the 16 colors, [`SettingsInner::color_support`] wins over it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_colors: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synchronized_updates: Option<bool>,
 
     // navigation
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enable_mouse: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enable_dpad: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub landscape_dpad_on_left: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub dpad_swap_up_down: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enable_margin_around_dpad: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enable_dpad_highlight: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dpad_size: Option<f32>,
+    /// Actions of the dpad buttons by their place, see [`Settings::get_dpad_actions`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dpad_actions: Option<Vec<Action>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause_on_focus_loss: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mobile_mode: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_debounce: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sticky_modifiers: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keybindings: Option<BTreeMap<Action, Vec<KeyChord>>>,
 
     // game config
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub default_maze_gen_algo: Option<MazeGenAlgo>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_source: Option<SeedSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub dont_ask_for_maze_algo: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm_quit: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hide_maze_when_paused: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_ghost: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undo_time_penalty: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undo_move_penalty: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_event_log: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlay_files: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlay_dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_replays: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub real_time_results: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gallery_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_budget: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surprise_max_width: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surprise_max_height: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub surprise_max_floors: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macros: Option<BTreeMap<u8, Vec<MacroStep>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode_options: Option<BTreeMap<String, BTreeMap<String, ModeValue>>>,
+
+    // printing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub print_paper: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub print_line_width: Option<f32>,
+
+    // http server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_server: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_port: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_token: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     // update check
     pub update_check_interval: Option<UpdateCheckInterval>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub display_update_check_errors: Option<bool>,
 
     // audio
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enable_audio: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub audio_volume: Option<f32>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enable_music: Option<bool>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub music_volume: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub menu_playlist: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub game_playlist: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shuffle_music: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gapless_music: Option<bool>,
+
+    // ambience
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enable_ambience: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ambience: Option<Ambience>,
+
+    // content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled_content: Option<Vec<String>>,
 
     // mazes
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mazes: Option<Vec<MazePreset>>,
-    // TODO: it's not possible in RON to have a HashMap with flattened keys,
-    // so we will support it in different way formats
-    // once we support them - this would mean dropping RON support
+
+    // profiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<Vec<SettingsProfile>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    // unknown fields can't be flattened here, RON doesn't support it,
     // https://github.com/ron-rs/ron/issues/115
-    // pub unknown_fields: HashMap<String, Value>,
+    // TOML files keep them outside, see `format::UnknownFields`
+}
+
+impl SettingsInner {
+    /// Overrides fields of `self` with fields set in `other`
+    ///
+    /// Profiles are not merged, they are only used from the base settings.
+    pub fn merge(&mut self, other: &SettingsInner) {
+        macro_rules! merge_fields {
+            ($($field:ident),* $(,)?; ignore $($ignored:ident),* $(,)?) => {
+                // exhaustive, so no field can be forgotten
+                let SettingsInner { $($field,)* $($ignored: _,)* } = other;
+                $(
+                    if $field.is_some() {
+                        self.$field = $field.clone();
+                    }
+                )*
+            };
+        }
+
+        merge_fields!(
+            theme,
+            logging_level,
+            debug_logging_level,
+            file_logging_level,
+            slow,
+            disable_tower_auto_up,
+            relative_controls,
+            first_person_view,
+            camera_mode,
+            animation_easing,
+            camera_animation,
+            player_animation,
+            maze_zoom,
+            viewport_margin,
+            font_aspect,
+            show_path,
+            frame_delay,
+            reduce_motion,
+            attract_delay,
+            end_screen_timeout,
+            show_key_hints,
+            show_breadcrumbs,
+            no_flash,
+            accessible,
+            bump_feedback,
+            ascii_charset,
+            color_support,
+            limit_colors,
+            synchronized_updates,
+            enable_mouse,
+            enable_dpad,
+            landscape_dpad_on_left,
+            dpad_swap_up_down,
+            enable_margin_around_dpad,
+            enable_dpad_highlight,
+            dpad_size,
+            dpad_actions,
+            pause_on_focus_loss,
+            mobile_mode,
+            input_debounce,
+            sticky_modifiers,
+            keybindings,
+            default_maze_gen_algo,
+            seed_source,
+            dont_ask_for_maze_algo,
+            confirm_quit,
+            hide_maze_when_paused,
+            show_ghost,
+            undo_time_penalty,
+            undo_move_penalty,
+            write_event_log,
+            overlay_files,
+            overlay_dir,
+            save_replays,
+            real_time_results,
+            gallery_size,
+            generation_timeout,
+            memory_budget,
+            surprise_max_width,
+            surprise_max_height,
+            surprise_max_floors,
+            macros,
+            mode_options,
+            print_paper,
+            print_line_width,
+            http_server,
+            http_port,
+            http_token,
+            update_check_interval,
+            display_update_check_errors,
+            enable_audio,
+            audio_volume,
+            enable_music,
+            music_volume,
+            menu_playlist,
+            game_playlist,
+            shuffle_music,
+            gapless_music,
+            enable_ambience,
+            ambience,
+            disabled_content,
+            mazes;
+            ignore profiles, active_profile,
+        );
+    }
+}
+
+/// Settings applied by the mobile mode, fields have to match [`Settings::set_mobile_mode`]
+fn mobile_bundle() -> SettingsInner {
+    SettingsInner {
+        enable_mouse: Some(true),
+        enable_dpad: Some(true),
+        enable_margin_around_dpad: Some(true),
+        dpad_size: Some(0.5),
+        viewport_margin: Some((6, 5)),
+        frame_delay: Some(90),
+        pause_on_focus_loss: Some(true),
+        ..Default::default()
+    }
+}
+
+/// Overlay pushed by [`Settings::with_overlay`], removed when dropped
+#[must_use = "overlay is removed right away when the guard is dropped"]
+pub struct OverlayGuard {
+    settings: Settings,
+    name: String,
+}
+
+impl Drop for OverlayGuard {
+    fn drop(&mut self) {
+        self.settings.remove_overlay(&self.name);
+    }
+}
+
+/// Name of the overlay used by the active profile
+const PROFILE_OVERLAY: &str = "profile";
+
+/// Name prefix of overlays pushed by [`Settings::with_overlay`]
+const SCOPED_PREFIX: &str = "scoped#";
+
+/// Maximum number of changes that can be undone
+const MAX_JOURNAL_LEN: usize = 64;
+
+/// State of all layers at some point, used by the change journal
+#[derive(Debug, Clone, Default)]
+struct LayersSnapshot {
+    base: SettingsInner,
+    overlays: Vec<(String, SettingsInner)>,
+}
+
+/// Base settings with named overlays stacked on top of them
+///
+/// Overlays only contain fields they override, `effective` is the result
+/// of merging all of them over the base and is what getters read.
+///
+/// Every change is recorded in `journal` so it can be undone, `session`
+/// holds the state the settings were loaded in.
+#[derive(Debug, Default)]
+struct SettingsLayers {
+    base: SettingsInner,
+    overlays: Vec<(String, SettingsInner)>,
+    effective: SettingsInner,
+
+    journal: Vec<LayersSnapshot>,
+    session: LayersSnapshot,
+    dirty: bool,
+    /// Modification time of the file when it was last loaded or saved
+    modified: Option<SystemTime>,
+    /// Settings as they are in the file, only the ones which differ are written
+    saved: FieldValues,
+    /// Keys of the file the game doesn't know, written back when saved
+    unknown: UnknownFields,
+    /// Incremented whenever an overlay is pushed or removed
+    overlays_revision: u64,
+    /// Presets from the presets directory with their content ids, loaded on first use
+    presets: Option<Vec<(String, MazePreset)>>,
+}
+
+impl SettingsLayers {
+    fn new(base: SettingsInner) -> Self {
+        let mut layers = Self {
+            base,
+            ..Default::default()
+        };
+        layers.recompute();
+        layers.start_session();
+        layers
+    }
+
+    /// Forget the journal and treat current state as the saved one
+    fn start_session(&mut self) {
+        self.journal.clear();
+        self.session = self.snapshot();
+        self.saved = format::field_values(&self.to_save());
+        self.dirty = false;
+    }
+
+    fn snapshot(&self) -> LayersSnapshot {
+        LayersSnapshot {
+            base: self.base.clone(),
+            overlays: self.overlays.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: LayersSnapshot) {
+        self.base = snapshot.base;
+        self.overlays = snapshot.overlays;
+        self.dirty = true;
+        self.recompute();
+    }
+
+    /// Record current state before it's changed
+    fn record(&mut self) {
+        if self.journal.len() == MAX_JOURNAL_LEN {
+            self.journal.remove(0);
+        }
+        let snapshot = self.snapshot();
+        self.journal.push(snapshot);
+        self.dirty = true;
+    }
+
+    /// Settings as they should be saved
+    ///
+    /// Changes made while a profile is active are stored in that profile.
+    fn to_save(&self) -> SettingsInner {
+        let mut base = self.base.clone();
+        let overlay = self.overlays.iter().find(|(n, _)| n == PROFILE_OVERLAY);
+
+        if let (Some((_, overlay)), Some(active)) = (overlay, &base.active_profile) {
+            if let Some(profile) = base
+                .profiles
+                .iter_mut()
+                .flatten()
+                .find(|p| p.name == *active)
+            {
+                profile.settings = overlay.clone();
+            }
+        }
+
+        base
+    }
+
+    fn recompute(&mut self) {
+        let mut effective = self.base.clone();
+        for (_, overlay) in &self.overlays {
+            effective.merge(overlay);
+        }
+        self.effective = effective;
+    }
+
+    /// Layer changes are written to, scoped overlays are skipped
+    fn top(&self) -> &SettingsInner {
+        match self.overlays.iter().rev().find(|(n, _)| !is_scoped(n)) {
+            Some((_, overlay)) => overlay,
+            None => &self.base,
+        }
+    }
+
+    fn top_mut(&mut self) -> &mut SettingsInner {
+        match self.overlays.iter_mut().rev().find(|(n, _)| !is_scoped(n)) {
+            Some((_, overlay)) => overlay,
+            None => &mut self.base,
+        }
+    }
+
+    fn has_scoped(&self) -> bool {
+        self.overlays.iter().any(|(n, _)| is_scoped(n))
+    }
+
+    /// Stop scoped overlays from hiding fields the user has just changed
+    fn unscope(&mut self, fields: &[&str]) {
+        for (_, overlay) in self.overlays.iter_mut().filter(|(n, _)| is_scoped(n)) {
+            let mut values = format::field_values(overlay);
+            if fields
+                .iter()
+                .all(|field| values.get(*field).is_none_or(|v| v.is_null()))
+            {
+                continue;
+            }
+            for field in fields {
+                values.remove(*field);
+            }
+            *overlay = serde_json::from_value(values.into())
+                .expect("settings without some fields should be valid");
+        }
+    }
+}
+
+fn is_scoped(name: &str) -> bool {
+    name.starts_with(SCOPED_PREFIX)
+}
+
+pub struct SettingsReadGuard<'a>(RwLockReadGuard<'a, SettingsLayers>);
+
+impl Deref for SettingsReadGuard<'_> {
+    type Target = SettingsInner;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0.effective
+    }
+}
+
+/// Write access to the topmost layer, effective settings are recomputed on drop
+///
+/// Scoped overlays are never written to. Fields changed through the guard are
+/// removed from them instead, so the change is visible right away and still kept
+/// after the overlay is gone.
+pub struct SettingsWriteGuard<'a>(RwLockWriteGuard<'a, SettingsLayers>, Option<FieldValues>);
+
+impl Deref for SettingsWriteGuard<'_> {
+    type Target = SettingsInner;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.top()
+    }
+}
+
+impl DerefMut for SettingsWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0.top_mut()
+    }
+}
+
+impl Drop for SettingsWriteGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(before) = self.1.take() {
+            let after = format::field_values(self.0.top());
+            let changed = format::changed_fields(&before, &after);
+            self.0.unscope(&changed);
+        }
+        self.0.recompute();
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Settings {
-    inner: Arc<RwLock<SettingsInner>>,
+    inner: Arc<RwLock<SettingsLayers>>,
     path: PathBuf,
     read_only: bool,
 }
@@ -166,7 +823,7 @@ impl Default for Settings {
     fn default() -> Self {
         let settings = SettingsInner::default();
         Self {
-            inner: Arc::new(RwLock::new(settings)),
+            inner: Arc::new(RwLock::new(SettingsLayers::new(settings))),
             path: settings_path(),
             read_only: false,
         }
@@ -187,23 +844,412 @@ impl Settings {
         self.read_only
     }
 
-    pub fn read(&self) -> std::sync::RwLockReadGuard<SettingsInner> {
-        self.inner.read().unwrap()
+    /// Effective settings, with all overlays applied
+    pub fn read(&self) -> SettingsReadGuard<'_> {
+        SettingsReadGuard(self.inner.read().unwrap())
+    }
+
+    /// Topmost layer, changes are applied to the active overlay if there is one
+    ///
+    /// Scoped overlays from [`Settings::with_overlay`] are skipped.
+    ///
+    /// State before the change is recorded, so it can be undone.
+    pub fn write(&mut self) -> SettingsWriteGuard<'_> {
+        let mut layers = self.inner.write().unwrap();
+        layers.record();
+        let before = layers
+            .has_scoped()
+            .then(|| format::field_values(layers.top()));
+        SettingsWriteGuard(layers, before)
+    }
+
+    /// Undo the last change, returns `false` if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let mut layers = self.inner.write().unwrap();
+        match layers.journal.pop() {
+            Some(snapshot) => {
+                layers.restore(snapshot);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Revert all changes made since the settings were loaded
+    ///
+    /// Revert itself is recorded, so it can be undone too.
+    pub fn revert_session(&mut self) -> bool {
+        let mut layers = self.inner.write().unwrap();
+        if layers.journal.is_empty() {
+            return false;
+        }
+
+        layers.record();
+        let session = layers.session.clone();
+        layers.restore(session);
+        true
+    }
+
+    /// Number of changes that can be undone
+    pub fn journal_len(&self) -> usize {
+        self.inner.read().unwrap().journal.len()
     }
 
-    pub fn write(&mut self) -> std::sync::RwLockWriteGuard<SettingsInner> {
-        self.inner.write().unwrap()
+    /// Whether there are changes which were not saved yet
+    pub fn is_dirty(&self) -> bool {
+        self.inner.read().unwrap().dirty
     }
+
+    /// Write settings to the file
+    ///
+    /// File is written atomically, so it's never left half-written.
+    /// Does nothing if settings are read-only.
+    pub fn save(&self) -> io::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
+        let format = SettingsFormat::from_path(&self.path);
+        let (string, saved) = {
+            let layers = self.inner.read().unwrap();
+            let settings = layers.to_save();
+            let saved = format::field_values(&settings);
+            let changed = format::changed_fields(&layers.saved, &saved);
+            if changed.is_empty() {
+                drop(layers);
+                self.inner.write().unwrap().dirty = false;
+                return Ok(());
+            }
+
+            // comments and formatting of the file are kept, unless it can't be patched
+            let patched = fs::read_to_string(&self.path)
+                .ok()
+                .and_then(|text| format.patch(&text, &settings, &changed));
+            let string = match patched {
+                Some(string) => string,
+                None => format.to_string(&settings, &layers.unknown)?,
+            };
+            (string, saved)
+        };
+
+        let tmp_path = self
+            .path
+            .with_extension(format!("{}.tmp", format.extension()));
+        fs::write(&tmp_path, string)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        let mut layers = self.inner.write().unwrap();
+        layers.dirty = false;
+        layers.saved = saved;
+        layers.modified = self.file_modified();
+        Ok(())
+    }
+
+    /// Make changes which are not written to the file, like the ones from command line flags
+    ///
+    /// Settings changed by `change` are written only if they are changed again later.
+    pub fn change_unsaved(&mut self, change: impl FnOnce(&mut Self)) {
+        let (before, dirty) = {
+            let layers = self.inner.read().unwrap();
+            (format::field_values(&layers.to_save()), layers.dirty)
+        };
+
+        change(self);
+
+        let mut layers = self.inner.write().unwrap();
+        let after = format::field_values(&layers.to_save());
+        for name in format::changed_fields(&before, &after) {
+            match after.get(name) {
+                Some(value) => layers.saved.insert(name.to_string(), value.clone()),
+                None => layers.saved.remove(name),
+            };
+        }
+        layers.dirty = dirty;
+    }
+
+    fn file_modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Reload the settings if the file was changed by something else since it was loaded or saved
+    ///
+    /// Returns `true` if they were reloaded. Overlays are kept and the active profile from the
+    /// file is applied again, the journal starts over. Invalid file is reported and ignored until
+    /// it changes again, unsaved changes should be saved before, or they are lost.
+    pub fn reload_if_changed(&mut self) -> bool {
+        let modified = self.file_modified();
+        {
+            let mut layers = self.inner.write().unwrap();
+            if modified.is_none() || modified == layers.modified {
+                return false;
+            }
+            layers.modified = modified;
+        }
+
+        let format = SettingsFormat::from_path(&self.path);
+        let (base, unknown) = match fs::read_to_string(&self.path)
+            .map_err(|err| err.to_string())
+            .and_then(|string| format.parse(&string).map_err(|err| err.to_string()))
+        {
+            Ok(base) => base,
+            Err(err) => {
+                log::error!("Settings file changed, but could not be loaded: {}", err);
+                return false;
+            }
+        };
+
+        let active_profile = base.active_profile.clone();
+        {
+            let mut layers = self.inner.write().unwrap();
+            layers.base = base;
+            layers.unknown = unknown;
+            layers.overlays.retain(|(n, _)| n != PROFILE_OVERLAY);
+            layers.recompute();
+        }
+
+        if let Some(profile) = active_profile {
+            if !self.set_active_profile(Some(&profile)) {
+                log::warn!("Unknown settings profile: '{}'", profile);
+            }
+        }
+
+        self.inner.write().unwrap().start_session();
+        true
+    }
+
+    /// Save settings if there are unsaved changes
+    pub fn save_if_dirty(&self) -> io::Result<()> {
+        if self.is_dirty() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Push an overlay on top of the current settings
+    ///
+    /// Overlay with the same name is replaced and moved to the top.
+    pub fn push_overlay(&mut self, name: impl Into<String>, overlay: SettingsInner) {
+        let name = name.into();
+        let mut layers = self.inner.write().unwrap();
+        layers.overlays.retain(|(n, _)| *n != name);
+        layers.overlays.push((name, overlay));
+        layers.overlays_revision += 1;
+        layers.recompute();
+    }
+
+    /// Push an overlay which is removed when the returned guard is dropped
+    ///
+    /// Every scoped overlay has its own name, so a guard never removes an overlay
+    /// pushed by another one. Changes made while it's active are written below it,
+    /// so they are kept after it's removed.
+    pub fn with_overlay(&self, overlay: SettingsInner) -> OverlayGuard {
+        static NEXT_SCOPE: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!(
+            "{SCOPED_PREFIX}{}",
+            NEXT_SCOPE.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut settings = self.clone();
+        settings.push_overlay(name.clone(), overlay);
+        OverlayGuard { settings, name }
+    }
+
+    /// Changes whenever an overlay is pushed or removed, so their effects can be applied
+    pub fn overlays_revision(&self) -> u64 {
+        self.inner.read().unwrap().overlays_revision
+    }
+
+    /// Remove an overlay by name, returns it if it was present
+    pub fn remove_overlay(&mut self, name: &str) -> Option<SettingsInner> {
+        let mut layers = self.inner.write().unwrap();
+        let index = layers.overlays.iter().position(|(n, _)| n == name)?;
+        let (_, overlay) = layers.overlays.remove(index);
+        layers.overlays_revision += 1;
+        layers.recompute();
+        Some(overlay)
+    }
+
+    pub fn has_overlay(&self, name: &str) -> bool {
+        self.inner
+            .read()
+            .unwrap()
+            .overlays
+            .iter()
+            .any(|(n, _)| n == name)
+    }
+}
+
+settings_accessors! {
+    // viewport
+    /// Player moves by one cell at a time, instead of until the next crossing
+    slow(get_slow, set_slow): bool = false, ui("Move one cell at a time");
+    /// Player doesn't move up the stairs of a tower by itself
+    disable_tower_auto_up(get_disable_tower_auto_up, set_disable_tower_auto_up): bool = false, ui("No auto climbing in towers");
+    /// Movement keys turn the player and move it forward, instead of moving it to the sides
+    relative_controls(get_relative_controls, set_relative_controls): bool = false, ui("Relative controls");
+    /// View of the corridor in front of the player next to the map, with the `first_person` feature
+    first_person_view(get_first_person_view, set_first_person_view): bool = false, ui("First-person view");
+    camera_mode(get_camera_mode, set_camera_mode): CameraMode = CameraMode::default();
+    /// Speed of the camera and the player over their animations
+    animation_easing(get_animation_easing, set_animation_easing): Easing = Easing::EaseOut, ui("Animation easing");
+    /// Time of the camera to move by one cell in milliseconds, 0 moves it at once
+    camera_animation(get_camera_animation, set_camera_animation): u64 = 150, clamp(0, MAX_ANIMATION), ui("Camera animation (ms)");
+    /// Time of the player to move by one cell in milliseconds, longer moves take a bit longer
+    player_animation(get_player_animation, set_player_animation): u64 = 60, clamp(0, MAX_ANIMATION), ui("Player animation (ms)");
+    /// Scale of the maze when a game starts, it's changed in game with `-` and `+`
+    maze_zoom(get_maze_zoom, set_maze_zoom): Zoom = Zoom::Normal, ui("Maze zoom");
+    /// Height of a character divided by its width, used to adapt the viewport margin
+    font_aspect(get_font_aspect, set_font_aspect): f32 = 2.0, clamp(1.0, 3.0), ui("Font aspect ratio");
+    /// Path of the player is drawn as lines colored by age, instead of dots
+    show_path(get_show_path, set_show_path): bool = true, ui("Show path");
+    /// Time between frames in milliseconds, higher values save battery
+    frame_delay(get_frame_delay, set_frame_delay): u64 = 45, clamp(1, 1000), ui("Frame delay (ms)");
+    /// Animations are skipped or slowed down
+    reduce_motion(get_reduce_motion, set_reduce_motion): bool = false, ui("Reduce motion");
+    /// Seconds of inactivity in the main menu before the demo starts, 0 disables it
+    attract_delay(get_attract_delay, set_attract_delay): u64 = 120, clamp(0, 3600), ui("Demo after idle (s)");
+    /// Seconds of inactivity on the end screen before it goes back to the main menu, or to the
+    /// next stage of the campaign, 0 disables it
+    end_screen_timeout(get_end_screen_timeout, set_end_screen_timeout): u64 = 0, clamp(0, 3600), ui("Leave end screen after (s)");
+    /// Line with the most useful keys of the screen at its bottom
+    show_key_hints(get_show_key_hints, set_show_key_hints): bool = false, ui("Show key hints");
+    /// Line with the menus the current one was opened from, at the top of menus
+    show_breadcrumbs(get_show_breadcrumbs, set_show_breadcrumbs): bool = true, ui("Show breadcrumbs");
+    /// Nothing flashes on the screen, like the visual bell of [`BumpFeedback::Flash`]
+    no_flash(get_no_flash, set_no_flash): bool = false, ui("No flashing");
+    /// Surroundings are described in words instead of drawn and menus say the selected item,
+    /// for screen readers, see [`crate::app::narration`]
+    accessible(get_accessible, set_accessible): bool = false, ui("Accessibility mode");
+    /// Feedback when the player bumps into a wall or tries to go to a missing floor
+    bump_feedback(get_bump_feedback, set_bump_feedback): BumpFeedback = BumpFeedback::None, ui("Bump feedback");
+
+    // navigation
+    enable_mouse(get_enable_mouse, set_enable_mouse): bool = true, ui("Enable mouse input");
+    /// On-screen buttons for moving the player, for touch screens
+    enable_dpad(get_enable_dpad, set_enable_dpad): bool = false, ui("Enable dpad");
+    /// Dpad is on the left side of the screen in landscape
+    landscape_dpad_on_left(get_landscape_dpad_on_left, set_landscape_dpad_on_left): bool = false, ui("Left-handed dpad");
+    dpad_swap_up_down(get_dpad_swap_up_down, set_dpad_swap_up_down): bool = false, ui("Swap Up and Down buttons");
+    enable_margin_around_dpad(get_enable_margin_around_dpad, set_enable_margin_around_dpad): bool = false, ui("Enable margin around dpad");
+    /// Button of the dpad under the finger or cursor is highlighted
+    enable_dpad_highlight(get_enable_dpad_highlight, set_enable_dpad_highlight): bool = true, ui("Enable dpad highlight");
+    /// Size of the dpad relative to the screen, 0.0 - 1.0, it's never wider than tall
+    dpad_size(get_dpad_size, set_dpad_size): f32 = 0.4, clamp(0.1, 1.0), ui("Dpad size");
+    /// Game is paused when the terminal loses focus
+    pause_on_focus_loss(get_pause_on_focus_loss, set_pause_on_focus_loss): bool = false, ui("Pause when unfocused");
+    /// Minimum time between two accepted moves, in milliseconds
+    input_debounce(get_input_debounce, set_input_debounce): u64 = 0, clamp(0, MAX_INPUT_DEBOUNCE), ui("Move delay (ms)");
+    /// Modifier key pressed alone is applied to the next key, needs keyboard enhancements
+    sticky_modifiers(get_sticky_modifiers, set_sticky_modifiers): bool = false, ui("Sticky modifiers");
+
+    // game config
+    /// Algorithm used for the generation of the mazes
+    default_maze_gen_algo(get_default_maze_gen_algo, set_default_maze_gen_algo): MazeGenAlgo = MazeGenAlgo::default(), ui("Maze algorithm");
+    /// Seeds of the mazes, entropy for random ones, time or a counter for the same mazes as others
+    seed_source(get_seed_source, set_seed_source): SeedSource = SeedSource::Entropy, ui("Seed source");
+    /// Default algorithm is used without asking for one before every game
+    dont_ask_for_maze_algo(get_dont_ask_for_maze_algo, set_dont_ask_for_maze_algo): bool = false, ui("Don't ask for algorithm");
+    /// Leaving a game in progress has to be confirmed
+    confirm_quit(get_confirm_quit, set_confirm_quit): bool = true, ui("Confirm quit");
+    /// Pause menu has a blank background, so the maze can't be studied while the time stops
+    hide_maze_when_paused(get_hide_maze_when_paused, set_hide_maze_when_paused): bool = false, ui("Hide maze when paused");
+    /// Ghost replays the best run of the same mode along with the player
+    show_ghost(get_show_ghost, set_show_ghost): bool = false, ui("Race the ghost");
+    /// Seconds added to the game time by every undo, see [`app::game_state::GameData::undo`]
+    undo_time_penalty(get_undo_time_penalty, set_undo_time_penalty): u64 = 5, clamp(0, 60), ui("Undo time penalty (s)");
+    /// Moves added to the move count by every undo
+    undo_move_penalty(get_undo_move_penalty, set_undo_move_penalty): u64 = 0, clamp(0, 100), ui("Undo move penalty");
+    /// Write the events of every run to the exports directory, see [`app::event_log`]
+    write_event_log(get_write_event_log, set_write_event_log): bool = false, ui("Write event log");
+    /// Write the time, moves and personal best delta of the run into text files for streaming,
+    /// see [`app::overlay`]
+    overlay_files(get_overlay_files, set_overlay_files): bool = false, ui("Overlay text files");
+    /// Save a replay of every won run, see [`app::replay`]
+    save_replays(get_save_replays, set_save_replays): bool = true, ui("Save replays");
+    /// Results show the real time, with pauses, instead of the game time
+    real_time_results(get_real_time_results, set_real_time_results): bool = false, ui("Real time in results");
+    /// Most mazes kept in the gallery, see [`app::gallery`]
+    gallery_size(get_gallery_size, set_gallery_size): u64 = 30, clamp(1, 200), ui("Gallery size");
+    /// Seconds the generation of a maze can take before it's cancelled, 0 waits forever
+    generation_timeout(get_generation_timeout, set_generation_timeout): u64 = 60, clamp(0, 3600), ui("Generation timeout (s)");
+    /// MiB a maze can take before its generation has to be confirmed, 0 only checks the free
+    /// memory, see [`app::memory`]
+    memory_budget(get_memory_budget, set_memory_budget): u64 = 1024, clamp(0, 1 << 20), ui("Memory budget (MiB)");
+    /// Widest maze of "Surprise me" in the maze size menu
+    surprise_max_width(get_surprise_max_width, set_surprise_max_width): u64 = 60, clamp(5, 1000), ui("Surprise max width");
+    /// Highest maze of "Surprise me"
+    surprise_max_height(get_surprise_max_height, set_surprise_max_height): u64 = 30, clamp(5, 1000), ui("Surprise max height");
+    /// Most floors of a maze of "Surprise me"
+    surprise_max_floors(get_surprise_max_floors, set_surprise_max_floors): u64 = 3, clamp(1, 16), ui("Surprise max floors");
+
+    // http server
+    /// Local HTTP server with the state of the game, with the `http` feature, see [`crate::http`]
+    http_server(get_http_server, set_http_server): bool = false, ui("HTTP server");
+    http_port(get_http_port, set_http_port): u64 = 7879, clamp(1024, 65535), ui("HTTP server port");
+
+    // update check
+    display_update_check_errors(get_display_update_check_errors, set_display_update_check_errors): bool = true, ui("Show update check errors");
+
+    // audio
+    /// Sounds and music, with the `sound` feature
+    enable_audio(get_enable_audio, set_enable_audio): bool = false, ui("Enable audio");
+    audio_volume(get_audio_volume, set_audio_volume): f32 = 0.0, clamp(0.0, 1.0), ui("Global volume");
+    enable_music(get_enable_music, set_enable_music): bool = false, ui("Enable music");
+    music_volume(get_music_volume, set_music_volume): f32 = 0.0, clamp(0.0, 1.0), ui("Music volume");
+    /// Songs of the playlists are played in a random order
+    shuffle_music(get_shuffle_music, set_shuffle_music): bool = true, ui("Shuffle music");
+    /// Songs follow each other without a gap of silence
+    gapless_music(get_gapless_music, set_gapless_music): bool = false, ui("Gapless playback");
+
+    // ambience
+    /// Theme and music change with the time of day, as set in the settings file
+    enable_ambience(get_enable_ambience, set_enable_ambience): bool = false, ui("Enable ambience");
 }
 
 impl Settings {
-    pub fn get_theme(&self) -> ThemeDefinition {
-        let theme_name = self.read().theme.clone();
-        if let Some(theme_name) = theme_name {
-            ThemeDefinition::load_by_name(&theme_name).expect("could not load the theme")
-        } else {
-            ThemeDefinition::load_default(self.read_only).expect("could not load the default theme")
+    /// Theme of the ambience or from the settings, the default one if neither is set
+    pub fn load_theme(&self) -> Result<ThemeDefinition, AppError> {
+        let theme_name = self.requested_theme().filter(|name| {
+            let enabled = self.is_content_enabled(&ContentKind::Theme.id(name));
+            if !enabled {
+                log::warn!("Theme `{}` is disabled, using the default one", name);
+            }
+            enabled
+        });
+        match theme_name {
+            Some(ref name) => ThemeDefinition::load_by_name(name),
+            None => ThemeDefinition::load_default(self.read_only),
         }
+        .map_err(|source| AppError::Theme {
+            name: theme_name.unwrap_or_else(|| "default".to_string()),
+            source,
+        })
+    }
+
+    /// Theme of the ambience or from the settings, even if it's disabled
+    fn requested_theme(&self) -> Option<String> {
+        self.get_ambience_variant()
+            .and_then(|v| v.theme)
+            .or_else(|| self.read().theme.clone())
+    }
+
+    /// File of the theme from [`Settings::load_theme`], `None` if it's the built-in one
+    pub fn theme_path(&self) -> Option<PathBuf> {
+        let name = self
+            .requested_theme()
+            .filter(|name| self.is_content_enabled(&ContentKind::Theme.id(name)));
+        match name {
+            Some(name) => Some(theme_file_path(&name)),
+            None => (!self.read_only).then(ThemeDefinition::default_path),
+        }
+    }
+
+    /// Theme from [`Settings::load_theme`], or the built-in one if it can't be loaded
+    pub fn get_theme(&self) -> ThemeDefinition {
+        self.load_theme().unwrap_or_else(|err| {
+            log::error!("{}", err);
+            ThemeDefinition::builtin()
+        })
     }
 
     pub fn get_logging_level(&self) -> log::Level {
@@ -230,133 +1276,206 @@ impl Settings {
             .unwrap_or(log::Level::Info)
     }
 
-    pub fn get_slow(&self) -> bool {
-        self.read().slow.unwrap_or_default()
+    /// Space between the edges of the screen and the maze, adapted to the screen if not set
+    pub fn get_viewport_margin(&self, screen_size: Dims) -> Dims {
+        match self.read().viewport_margin {
+            Some(margin) => Dims::from(margin),
+            None => Self::adaptive_viewport_margin(screen_size, self.get_font_aspect()),
+        }
     }
 
-    pub fn set_slow(&mut self, value: bool) -> &mut Self {
-        self.write().slow = Some(value);
-        self
-    }
+    /// Margin which looks the same horizontally and vertically, and grows with the screen
+    ///
+    /// Characters are taller than wide, so vertical margin is divided by `font_aspect`.
+    pub fn adaptive_viewport_margin(screen_size: Dims, font_aspect: f32) -> Dims {
+        // shorter side of the screen, in widths of a character
+        let shorter = (screen_size.0 as f32).min(screen_size.1 as f32 * font_aspect);
+        let margin = (shorter * ADAPTIVE_MARGIN_RATIO).clamp(1.0, MAX_ADAPTIVE_MARGIN);
 
-    pub fn get_disable_tower_auto_up(&self) -> bool {
-        self.read().disable_tower_auto_up.unwrap_or_default()
+        Dims(
+            margin.round() as i32,
+            (margin / font_aspect).round().max(1.0) as i32,
+        )
     }
 
-    pub fn set_disable_tower_auto_up(&mut self, value: bool) -> &mut Self {
-        self.write().disable_tower_auto_up = Some(value);
+    pub fn set_viewport_margin(&mut self, value: Dims) -> &mut Self {
+        self.write().viewport_margin = Some(value.into());
         self
     }
 
-    pub fn get_camera_mode(&self) -> CameraMode {
-        self.read().camera_mode.unwrap_or_default()
+    /// Terminal workarounds, unset ones are detected
+    pub fn get_render_compat(&self) -> RenderCompat {
+        let detected = RenderCompat::detect();
+        // screen readers read box drawing characters one by one
+        let accessible = self.get_accessible();
+        let settings = self.read();
+        RenderCompat {
+            ascii: accessible || settings.ascii_charset.unwrap_or(detected.ascii),
+            colors: self.get_color_support().unwrap_or(detected.colors),
+            sync_updates: settings
+                .synchronized_updates
+                .unwrap_or(detected.sync_updates),
+        }
     }
 
-    pub fn set_camera_mode(&mut self, value: CameraMode) -> &mut Self {
-        self.write().camera_mode = Some(value);
+    pub fn set_ascii_charset(&mut self, value: bool) -> &mut Self {
+        self.write().ascii_charset = Some(value);
         self
     }
 
-    pub fn get_camera_smoothing(&self) -> f32 {
-        self.read().camera_smoothing.unwrap_or(0.5).clamp(0.5, 1.0)
-    }
-
-    pub fn set_camera_smoothing(&mut self, value: f32) -> &mut Self {
-        self.write().camera_smoothing = Some(value.clamp(0.5, 1.0));
+    pub fn set_limit_colors(&mut self, value: bool) -> &mut Self {
+        self.write().limit_colors = Some(value);
         self
     }
 
-    pub fn get_player_smoothing(&self) -> f32 {
-        self.read().player_smoothing.unwrap_or(0.8).clamp(0.5, 1.0)
+    /// Colors of the terminal set in the settings, `None` if they are detected
+    pub fn get_color_support(&self) -> Option<ColorSupport> {
+        let settings = self.read();
+        settings
+            .color_support
+            .or(settings.limit_colors.map(|limit| match limit {
+                true => ColorSupport::Basic,
+                false => ColorSupport::TrueColor,
+            }))
     }
 
-    pub fn set_player_smoothing(&mut self, value: f32) -> &mut Self {
-        self.write().player_smoothing = Some(value.clamp(0.5, 1.0));
+    pub fn set_color_support(&mut self, value: Option<ColorSupport>) -> &mut Self {
+        self.write().color_support = value;
         self
     }
 
-    pub fn get_viewport_margin(&self) -> Dims {
-        self.read()
-            .viewport_margin
-            .map(Dims::from)
-            .unwrap_or(Dims(4, 3))
-    }
-
-    pub fn set_viewport_margin(&mut self, value: Dims) -> &mut Self {
-        self.write().viewport_margin = Some(value.into());
+    pub fn set_synchronized_updates(&mut self, value: bool) -> &mut Self {
+        self.write().synchronized_updates = Some(value);
         self
     }
 
-    pub fn get_enable_mouse(&self) -> bool {
-        self.read().enable_mouse.unwrap_or(true)
+    pub fn get_mobile_mode(&self) -> bool {
+        self.read().mobile_mode.unwrap_or_default()
     }
 
-    pub fn set_enable_mouse(&mut self, value: bool) -> &mut Self {
-        self.write().enable_mouse = Some(value);
+    /// Turn mobile mode on or off, see [`mobile_bundle`]
+    ///
+    /// Turning it off unsets the bundled settings, so they fall back
+    /// to the values from lower layers or to the defaults.
+    pub fn set_mobile_mode(&mut self, value: bool) -> &mut Self {
+        let mut settings = self.write();
+        if value {
+            settings.merge(&mobile_bundle());
+        } else {
+            settings.enable_mouse = None;
+            settings.enable_dpad = None;
+            settings.enable_margin_around_dpad = None;
+            settings.dpad_size = None;
+            settings.viewport_margin = None;
+            settings.frame_delay = None;
+            settings.pause_on_focus_loss = None;
+        }
+        settings.mobile_mode = Some(value);
+        drop(settings);
+
         self
     }
 
-    pub fn get_enable_dpad(&self) -> bool {
-        self.read().enable_dpad.unwrap_or(false)
+    /// Whether the game runs in a terminal app for phones, either Termux or iSH
+    ///
+    /// Terminal size isn't used, narrow windows on desktops have a keyboard too.
+    pub fn detect_touch_terminal() -> bool {
+        let termux = std::env::var_os("TERMUX_VERSION").is_some();
+        // iSH runs Alpine in an emulator on iOS and exposes its own proc entry
+        let ish = std::path::Path::new("/proc/ish").exists();
+        termux || ish
     }
 
-    pub fn set_enable_dpad(&mut self, value: bool) -> &mut Self {
-        self.write().enable_dpad = Some(value);
-        self
-    }
+    /// Paper size and line width for printed mazes, invalid paper falls back to the default
+    #[cfg(feature = "print")]
+    pub fn get_print_options(&self) -> PrintOptions {
+        let mut options = PrintOptions::default();
+        let settings = self.read();
 
-    pub fn get_landscape_dpad_on_left(&self) -> bool {
-        self.read().landscape_dpad_on_left.unwrap_or(false)
-    }
+        if let Some(paper) = &settings.print_paper {
+            match paper.parse() {
+                Ok(paper) => options.paper = paper,
+                Err(err) => log::warn!("Invalid print paper: {}", err),
+            }
+        }
 
-    pub fn set_landscape_dpad_on_left(&mut self, value: bool) -> &mut Self {
-        self.write().landscape_dpad_on_left = Some(value);
-        self
+        if let Some(width) = settings.print_line_width {
+            options.line_width = width.clamp(0.1, 5.0);
+        }
+
+        options
     }
 
-    pub fn get_dpad_swap_up_down(&self) -> bool {
-        self.read().dpad_swap_up_down.unwrap_or(false)
+    pub fn get_macro(&self, slot: u8) -> Option<Vec<MacroStep>> {
+        self.read().macros.as_ref()?.get(&slot).cloned()
     }
 
-    pub fn set_dpad_swap_up_down(&mut self, value: bool) -> &mut Self {
-        self.write().dpad_swap_up_down = Some(value);
+    /// Stores macro in the topmost layer, so it's saved to the active profile
+    pub fn set_macro(&mut self, slot: u8, steps: Vec<MacroStep>) -> &mut Self {
+        // start from the effective macros, so the other slots are not lost
+        let mut macros = self.read().macros.clone().unwrap_or_default();
+        macros.insert(slot, steps);
+        self.write().macros = Some(macros);
         self
     }
 
-    pub fn get_enable_margin_around_dpad(&self) -> bool {
-        self.read().enable_margin_around_dpad.unwrap_or(false)
+    /// Keys of the game actions, with the defaults for actions which are not set
+    pub fn get_keymap(&self) -> KeyMap {
+        KeyMap::new(self.read().keybindings.as_ref())
     }
 
-    pub fn set_enable_margin_around_dpad(&mut self, value: bool) -> &mut Self {
-        self.write().enable_margin_around_dpad = Some(value);
+    /// Binds the key to the action, see [`KeyMap::bind`]
+    pub fn set_keybinding(&mut self, action: Action, chord: KeyChord) -> &mut Self {
+        // whole map is stored, so the key is also unbound from the other actions
+        let mut keymap = self.get_keymap();
+        keymap.bind(action, chord);
+        self.write().keybindings = Some(keymap.bindings().clone());
         self
     }
 
-    pub fn get_enable_dpad_highlight(&self) -> bool {
-        self.read().enable_dpad_highlight.unwrap_or(true)
-    }
-
-    pub fn set_enable_dpad_highlight(&mut self, value: bool) -> &mut Self {
-        self.write().enable_dpad_highlight = Some(value);
+    pub fn reset_keybindings(&mut self) -> &mut Self {
+        self.write().keybindings = None;
         self
     }
 
-    pub fn set_default_maze_gen_algo(&mut self, value: MazeGenAlgo) -> &mut Self {
-        self.write().default_maze_gen_algo = Some(value);
-        self
+    /// Actions of the dpad buttons by their place, places missing in the settings keep their
+    /// default actions, see [`dpad::DEFAULT_ACTIONS`]
+    pub fn get_dpad_actions(&self) -> [Action; 6] {
+        let mut actions = dpad::DEFAULT_ACTIONS;
+        if let Some(custom) = &self.read().dpad_actions {
+            for (action, custom) in actions.iter_mut().zip(custom) {
+                *action = *custom;
+            }
+        }
+        actions
     }
 
-    pub fn get_default_maze_gen_algo(&self) -> MazeGenAlgo {
-        self.read().default_maze_gen_algo.unwrap_or_default()
+    /// User overrides of the options of a game mode
+    pub fn get_mode_options(&self, mode: &str) -> BTreeMap<String, ModeValue> {
+        self.read()
+            .mode_options
+            .as_ref()
+            .and_then(|modes| modes.get(mode))
+            .cloned()
+            .unwrap_or_default()
     }
 
-    pub fn set_dont_ask_for_maze_algo(&mut self, value: bool) -> &mut Self {
-        self.write().dont_ask_for_maze_algo = Some(value);
-        self
+    /// Directory of the overlay files, the `overlay` directory next to the settings by default
+    pub fn get_overlay_dir(&self) -> PathBuf {
+        self.read()
+            .overlay_dir
+            .as_ref()
+            .filter(|dir| !dir.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(overlay_path)
     }
 
-    pub fn get_dont_ask_for_maze_algo(&self) -> bool {
-        self.read().dont_ask_for_maze_algo.unwrap_or_default()
+    /// Token of the HTTP server, which doesn't start without one
+    pub fn get_http_token(&self) -> Option<String> {
+        self.read()
+            .http_token
+            .clone()
+            .filter(|token| !token.trim().is_empty())
     }
 
     pub fn set_check_interval(&mut self, value: UpdateCheckInterval) -> &mut Self {
@@ -368,129 +1487,257 @@ impl Settings {
         self.read().update_check_interval.unwrap_or_default()
     }
 
-    pub fn get_display_update_check_errors(&self) -> bool {
-        self.read().display_update_check_errors.unwrap_or(true)
+    pub fn get_menu_playlist(&self) -> Vec<String> {
+        self.get_ambience_variant()
+            .and_then(|v| v.menu_playlist)
+            .or_else(|| self.read().menu_playlist.clone())
+            .unwrap_or_default()
     }
 
-    pub fn set_display_update_check_errors(&mut self, value: bool) -> &mut Self {
-        self.write().display_update_check_errors = Some(value);
+    pub fn set_menu_playlist(&mut self, value: Vec<String>) -> &mut Self {
+        self.write().menu_playlist = Some(value);
         self
     }
 
-    pub fn get_enable_audio(&self) -> bool {
-        self.read().enable_audio.unwrap_or_default()
+    pub fn get_game_playlist(&self) -> Vec<String> {
+        self.get_ambience_variant()
+            .and_then(|v| v.game_playlist)
+            .or_else(|| self.read().game_playlist.clone())
+            .unwrap_or_default()
     }
 
-    pub fn set_enable_audio(&mut self, value: bool) -> &mut Self {
-        self.write().enable_audio = Some(value);
+    pub fn set_game_playlist(&mut self, value: Vec<String>) -> &mut Self {
+        self.write().game_playlist = Some(value);
         self
     }
 
-    pub fn get_audio_volume(&self) -> f32 {
-        self.read().audio_volume.unwrap_or_default().clamp(0., 1.)
+    /// Ambience variant for the current time of day, if ambience is enabled
+    ///
+    /// It takes precedence over the theme and playlists.
+    pub fn get_ambience_variant(&self) -> Option<AmbienceVariant> {
+        if !self.get_enable_ambience() {
+            return None;
+        }
+
+        self.read()
+            .ambience
+            .as_ref()?
+            .variant(TimeOfDay::now())
+            .cloned()
     }
 
-    pub fn set_audio_volume(&mut self, value: f32) -> &mut Self {
-        self.write().audio_volume = Some(value.clamp(0., 1.));
+    pub fn set_mazes(&mut self, value: Vec<MazePreset>) -> &mut Self {
+        self.write().mazes = Some(value);
         self
     }
 
-    pub fn get_enable_music(&self) -> bool {
-        self.read().enable_music.unwrap_or_default()
+    /// Maze presets from the settings, followed by the enabled external presets
+    ///
+    /// External presets are read only once, so indices into the list stay valid
+    /// until [`Settings::reload_presets`] is called.
+    pub fn get_mazes(&self) -> Vec<MazePreset> {
+        let mut layers = self.inner.write().unwrap();
+        let mut mazes = layers.effective.mazes.clone().unwrap_or_default();
+        let disabled = layers
+            .effective
+            .disabled_content
+            .clone()
+            .unwrap_or_default();
+        let presets = layers.presets.get_or_insert_with(load_presets);
+        mazes.extend(
+            presets
+                .iter()
+                .filter(|(id, _)| !disabled.contains(id))
+                .map(|(_, preset)| preset.clone()),
+        );
+        mazes
     }
 
-    pub fn set_enable_music(&mut self, value: bool) -> &mut Self {
-        self.write().enable_music = Some(value);
-        self
+    /// Read the presets directory again on next [`Settings::get_mazes`]
+    pub fn reload_presets(&mut self) {
+        self.inner.write().unwrap().presets = None;
     }
 
-    pub fn get_music_volume(&self) -> f32 {
-        self.read().music_volume.unwrap_or_default().clamp(0., 1.)
+    pub fn get_disabled_content(&self) -> Vec<String> {
+        self.read().disabled_content.clone().unwrap_or_default()
     }
 
-    pub fn set_music_volume(&mut self, value: f32) -> &mut Self {
-        self.write().music_volume = Some(value.clamp(0., 1.));
-        self
+    pub fn is_content_enabled(&self, id: &str) -> bool {
+        !self
+            .read()
+            .disabled_content
+            .as_ref()
+            .is_some_and(|disabled| disabled.iter().any(|d| d == id))
     }
 
-    pub fn set_mazes(&mut self, value: Vec<MazePreset>) -> &mut Self {
-        self.write().mazes = Some(value);
+    pub fn set_content_enabled(&mut self, id: &str, enabled: bool) -> &mut Self {
+        let mut disabled = self.get_disabled_content();
+        disabled.retain(|d| d != id);
+        if !enabled {
+            disabled.push(id.to_string());
+        }
+        self.write().disabled_content = Some(disabled);
         self
     }
 
-    pub fn get_mazes(&self) -> Vec<MazePreset> {
-        self.read().mazes.clone().unwrap_or_default()
+    pub fn get_profiles(&self) -> Vec<SettingsProfile> {
+        self.inner
+            .read()
+            .unwrap()
+            .base
+            .profiles
+            .clone()
+            .unwrap_or_default()
+    }
+
+    pub fn get_active_profile(&self) -> Option<String> {
+        self.inner.read().unwrap().base.active_profile.clone()
+    }
+
+    /// Switch to the profile with given name, `None` switches to the base settings
+    ///
+    /// Profile is applied as the lowest overlay, so other overlays still take
+    /// precedence. Returns `false` if there is no such profile.
+    pub fn set_active_profile(&mut self, name: Option<&str>) -> bool {
+        let mut layers = self.inner.write().unwrap();
+
+        let profile = match name {
+            Some(name) => {
+                let profile = layers
+                    .base
+                    .profiles
+                    .iter()
+                    .flatten()
+                    .find(|p| p.name == name)
+                    .map(|p| p.settings.clone());
+
+                match profile {
+                    Some(profile) => Some(profile),
+                    None => return false,
+                }
+            }
+            None => None,
+        };
+
+        layers.record();
+        layers.overlays.retain(|(n, _)| n != PROFILE_OVERLAY);
+        if let Some(profile) = profile {
+            layers
+                .overlays
+                .insert(0, (PROFILE_OVERLAY.to_string(), profile));
+        }
+        layers.base.active_profile = name.map(str::to_string);
+        layers.recompute();
+
+        true
     }
 }
 
 impl Settings {
-    pub fn load(path: PathBuf, read_only: bool) -> io::Result<Self> {
-        let default_settings_string = DEFAULT_SETTINGS;
-
-        let settings_string = fs::read_to_string(&path);
-        let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
-        let settings: SettingsInner = if let Ok(settings_string) = settings_string {
-            options
-                .from_str(&settings_string)
-                .expect("Could not parse settings file")
-        } else if !read_only {
-            fs::create_dir_all(path.parent().unwrap())?;
-            fs::write(&path, default_settings_string)?;
-            options.from_str(default_settings_string).unwrap()
-        } else {
-            options.from_str(default_settings_string).unwrap()
+    /// Load settings from the file, its format is picked by the extension
+    ///
+    /// File with default settings is created if it doesn't exist.
+    pub fn load(path: PathBuf, read_only: bool) -> Result<Self, AppError> {
+        let format = SettingsFormat::from_path(&path);
+
+        let (settings, unknown) = match fs::read_to_string(&path) {
+            Ok(text) => match format.parse(&text) {
+                Ok(parsed) => parsed,
+                Err(source) => return Err(AppError::Settings { path, source }),
+            },
+            Err(_) => {
+                if !read_only {
+                    fs::create_dir_all(path.parent().unwrap())
+                        .and_then(|_| fs::write(&path, format.default_file()))
+                        .map_err(|err| AppError::io("create the settings file", err))?;
+                }
+                (default_settings(), UnknownFields::new())
+            }
         };
 
-        Ok(Self {
-            inner: Arc::new(RwLock::new(settings)),
+        let active_profile = settings.active_profile.clone();
+
+        let mut settings = Self {
+            inner: Arc::new(RwLock::new(SettingsLayers::new(settings))),
             path,
             read_only,
-        })
+        };
+
+        if let Some(profile) = active_profile {
+            if !settings.set_active_profile(Some(&profile)) {
+                log::warn!("Unknown settings profile: '{}'", profile);
+            }
+        }
+
+        let modified = settings.file_modified();
+        let mut layers = settings.inner.write().unwrap();
+        layers.start_session();
+        layers.modified = modified;
+        layers.unknown = unknown;
+        drop(layers);
+
+        Ok(settings)
+    }
+
+    /// Default settings, used when the file can't be loaded
+    ///
+    /// They are read-only, so the file is not overwritten before it's fixed.
+    pub fn fallback(path: PathBuf) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(SettingsLayers::new(default_settings()))),
+            path,
+            read_only: true,
+        }
     }
 
     pub fn reset(&mut self) {
-        let default_settings_string = DEFAULT_SETTINGS;
-        let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
-        *self.write() = options.from_str(default_settings_string).unwrap();
+        *self.inner.write().unwrap() = SettingsLayers::new(default_settings());
 
         let path = settings_path();
-        fs::write(&path, default_settings_string).unwrap();
+        fs::write(&path, SettingsFormat::from_path(&path).default_file()).unwrap();
 
         self.path = path;
+        self.inner.write().unwrap().modified = self.file_modified();
     }
 
     pub fn reset_config(path: PathBuf) {
-        let default_settings_string = DEFAULT_SETTINGS;
-        fs::write(path, default_settings_string).unwrap();
+        let contents = SettingsFormat::from_path(&path).default_file();
+        fs::write(path, contents).unwrap();
     }
-}
-
-struct OtherSettingsPopup(Popup, MouseGuard);
 
-impl OtherSettingsPopup {
-    fn new(settings: &Settings) -> Self {
-        let popup = Popup::new(
-            "Other settings".to_string(),
-            vec![
-                "Path to the current settings:".to_string(),
-                format!(" {}", settings.path().to_string_lossy().to_string()),
-                "".to_string(),
-                "Other settings are not implemented in UI yet.".to_string(),
-                "Please edit the settings file directly.".to_string(),
-            ],
-        );
+    /// Convert RON settings file to TOML next to it, returns path of the new file
+    ///
+    /// Old file is renamed to `.ron.bak`, so the TOML one is used from now on.
+    pub fn migrate_to_toml(path: &Path) -> Result<PathBuf, AppError> {
+        let toml_path = path.with_extension(SettingsFormat::Toml.extension());
+        if SettingsFormat::from_path(path) == SettingsFormat::Toml {
+            return Ok(toml_path);
+        }
+        if toml_path.exists() {
+            let err = io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", toml_path.display()),
+            );
+            return Err(AppError::io("migrate the settings", err));
+        }
 
-        Self(popup, MouseGuard::new().unwrap())
-    }
-}
+        let text =
+            fs::read_to_string(path).map_err(|err| AppError::io("read the settings file", err))?;
+        let (settings, unknown) =
+            SettingsFormat::Ron
+                .parse(&text)
+                .map_err(|source| AppError::Settings {
+                    path: path.to_owned(),
+                    source,
+                })?;
 
-impl ActivityHandler for OtherSettingsPopup {
-    fn update(&mut self, events: Vec<app::Event>, data: &mut AppData) -> Option<Change> {
-        self.0.update(events, data)
-    }
+        SettingsFormat::Toml
+            .to_string(&settings, &unknown)
+            .and_then(|contents| fs::write(&toml_path, contents))
+            .and_then(|_| fs::rename(path, path.with_extension("ron.bak")))
+            .map_err(|err| AppError::io("migrate the settings", err))?;
 
-    fn screen(&self) -> &dyn Screen {
-        &self.0
+        Ok(toml_path)
     }
 }
 
@@ -500,24 +1747,35 @@ pub struct SettingsActivity {
 }
 
 impl SettingsActivity {
-    fn other_settings_popup(settings: &Settings) -> Activity {
-        Activity::new_base_boxed("settings".to_string(), OtherSettingsPopup::new(settings))
-    }
-}
-
-#[allow(clippy::new_without_default)]
-impl SettingsActivity {
-    pub fn new() -> Self {
+    pub fn new(settings: &Settings) -> Self {
         let options = menu_actions!(
+            "Search" -> _ => Change::push(search::SettingsSearch::new_activity()),
             "Audio" on "sound" -> data => Change::push(create_audio_settings(data)),
             "Controls" -> data => Change::push(create_controls_settings(data)),
-            "Other settings" -> data => Change::push(SettingsActivity::other_settings_popup(&data.settings)),
+            "Key bindings" -> data => Change::push(KeyBindingsActivity::new_activity(&data.settings.get_keymap(), 0)),
+            "Theme editor" -> data => Change::push(theme_editor::ThemeEditor::new_activity(&data.settings)),
+            "Content" -> data => Change::push(content::ContentActivity::new_activity(&data.settings)),
+            "Toggle mobile mode" -> data => SettingsActivity::toggle_mobile_mode(data),
+            "All settings" -> data => Change::push(editor::SettingsEditor::new_activity(&data.settings)),
+            "Undo last change" -> data => SettingsActivity::undo(data, Settings::undo),
+            "Revert session changes" -> data => SettingsActivity::undo(data, Settings::revert_session),
             "Back" -> _ => Change::pop_top(),
         );
 
         let (options, actions) = split_menu_actions(options);
 
-        let menu_config = MenuConfig::new("Settings", options).subtitle("Changes are not saved");
+        let saved = if settings.is_ro() {
+            "Changes are not saved"
+        } else {
+            "Changes are saved automatically"
+        };
+        let menu_config = MenuConfig::new("Settings", options)
+            .subtitle(saved)
+            .subtitle(format!("{} changes to undo", settings.journal_len()))
+            .subtitle(format!(
+                "Mobile mode: {}",
+                on_off(settings.get_mobile_mode(), false)
+            ));
 
         Self {
             actions,
@@ -525,8 +1783,27 @@ impl SettingsActivity {
         }
     }
 
-    pub fn new_activity() -> Activity {
-        Activity::new_base_boxed("settings".to_string(), Self::new())
+    pub fn new_activity(settings: &Settings) -> Activity {
+        Activity::new_base_boxed("settings".to_string(), Self::new(settings))
+    }
+
+    fn toggle_mobile_mode(data: &mut AppData) -> Change {
+        let enable = !data.settings.get_mobile_mode();
+        data.settings.set_mobile_mode(enable);
+        log::info!("Mobile mode {}", on_off(enable, false));
+
+        Change::replace(Self::new_activity(&data.settings))
+    }
+
+    /// Run an undo operation and reopen the menu, so the subtitle is up to date
+    fn undo(data: &mut AppData, undo: fn(&mut Settings) -> bool) -> Change {
+        if undo(&mut data.settings) {
+            data.apply_settings();
+        } else {
+            log::info!("No settings changes to undo");
+        }
+
+        Change::replace(Self::new_activity(&data.settings))
     }
 }
 
@@ -549,10 +1826,91 @@ impl ActivityHandler for SettingsActivity {
     fn screen(&self) -> &dyn Screen {
         &self.menu
     }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
+}
+
+/// Quick switch between settings profiles
+pub struct ProfileSwitchActivity {
+    profiles: Vec<Option<String>>,
+    menu: Menu,
+}
+
+impl ProfileSwitchActivity {
+    pub fn new(settings: &Settings) -> Self {
+        let profiles: Vec<_> = std::iter::once(None)
+            .chain(settings.get_profiles().into_iter().map(|p| Some(p.name)))
+            .collect();
+
+        let active = settings.get_active_profile();
+        let options: Vec<_> = profiles
+            .iter()
+            .map(|p| p.as_deref().unwrap_or("Base settings").to_string())
+            .collect();
+        let default = profiles.iter().position(|p| *p == active).unwrap_or(0);
+
+        let menu_config = MenuConfig::new_from_strings("Settings profile", options)
+            .counted()
+            .default(default);
+
+        Self {
+            profiles,
+            menu: Menu::new(menu_config),
+        }
+    }
+
+    pub fn new_activity(settings: &Settings) -> Activity {
+        Activity::new_base_boxed("profiles".to_string(), Self::new(settings))
+    }
+}
+
+impl ActivityHandler for ProfileSwitchActivity {
+    fn update(&mut self, events: Vec<app::Event>, data: &mut AppData) -> Option<Change> {
+        match self.menu.update(events, data)? {
+            Change::Pop {
+                res: Some(index), ..
+            } => {
+                let index = *index.downcast::<usize>().expect("menu should return index");
+                let profile = self.profiles[index].as_deref();
+
+                data.settings.set_active_profile(profile);
+                data.apply_settings();
+                log::info!(
+                    "Switched to profile: {}",
+                    profile.unwrap_or("base settings")
+                );
+
+                Some(Change::pop_top())
+            }
+            res => Some(res),
+        }
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.menu
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.menu.title()
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.menu.hints()
+    }
 }
 
 pub fn create_controls_settings(data: &mut AppData) -> Activity {
-    let menu_config = MenuConfig::new(
+    Activity::new_base_boxed("controls settings", Menu::new(controls_settings_menu(data)))
+}
+
+pub fn controls_settings_menu(data: &mut AppData) -> MenuConfig {
+    MenuConfig::new(
         "Controls settings",
         [
             MenuItem::Option(OptionDef {
@@ -603,10 +1961,444 @@ pub fn create_controls_settings(data: &mut AppData) -> Activity {
                     data.settings.set_enable_dpad_highlight(*enabled);
                 }),
             }),
+            MenuItem::Option(OptionDef {
+                text: "Pause when unfocused".into(),
+                val: data.settings.get_pause_on_focus_loss(),
+                fun: Box::new(|enabled, data| {
+                    *enabled = !*enabled;
+                    data.settings.set_pause_on_focus_loss(*enabled);
+                }),
+            }),
+            MenuItem::Slider(SliderDef {
+                text: "Move delay (x50ms)".into(),
+                val: (data.settings.get_input_debounce() / 50) as i32,
+                range: 0..=(MAX_INPUT_DEBOUNCE / 50) as i32,
+                as_num: true,
+                fun: Box::new(|up, val, data| {
+                    *val += if up { 1 } else { -1 };
+                    data.settings.set_input_debounce(*val as u64 * 50);
+                }),
+            }),
+            MenuItem::Option(OptionDef {
+                text: "Sticky modifiers".into(),
+                val: data.settings.get_sticky_modifiers(),
+                fun: Box::new(|enabled, data| {
+                    *enabled = !*enabled;
+                    data.settings.set_sticky_modifiers(*enabled);
+                }),
+            }),
             MenuItem::Separator,
             MenuItem::Text("Exit".into()),
         ],
-    );
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_overlay() {
+        let options = format::ron_options();
+        let base: SettingsInner = options.from_str(DEFAULT_SETTINGS).unwrap();
+        let mut settings = Settings {
+            inner: Arc::new(RwLock::new(SettingsLayers::new(base))),
+            path: settings_path(),
+            read_only: true,
+        };
+
+        assert!(!settings.get_slow());
+        assert!(settings.set_active_profile(Some("Casual")));
+        assert!(settings.get_slow());
+        assert_eq!(settings.get_audio_volume(), 0.5);
+
+        settings.push_overlay(
+            "test",
+            SettingsInner {
+                slow: Some(false),
+                ..Default::default()
+            },
+        );
+        assert!(!settings.get_slow());
+
+        // writes go to the topmost overlay
+        settings.set_audio_volume(0.2);
+        assert_eq!(settings.get_audio_volume(), 0.2);
+        assert!(settings.remove_overlay("test").is_some());
+        assert_eq!(settings.get_audio_volume(), 0.5);
+        assert!(settings.get_slow());
+
+        assert!(settings.set_active_profile(None));
+        assert!(!settings.get_slow());
+        assert!(!settings.set_active_profile(Some("missing")));
+    }
+
+    #[test]
+    fn scoped_overlay_is_removed_with_guard() {
+        let options = format::ron_options();
+        let preset: MazePreset = options
+            .from_str("(title: \"x\", width: 5, height: 5, settings: (show_path: false))")
+            .unwrap();
+        let mut settings = Settings::default();
+        settings.set_show_path(true);
+        let revision = settings.overlays_revision();
+
+        let first = settings.with_overlay(preset.settings.clone().unwrap());
+        let second = settings.with_overlay(SettingsInner {
+            camera_animation: Some(90),
+            ..Default::default()
+        });
+        assert!(!settings.get_show_path());
+        assert_eq!(settings.get_camera_animation(), 90);
+
+        // dropped out of order, each removes only its own overlay
+        drop(first);
+        assert!(settings.get_show_path());
+        assert_eq!(settings.get_camera_animation(), 90);
+        drop(second);
+        assert_ne!(settings.get_camera_animation(), 90);
+        assert_ne!(settings.overlays_revision(), revision);
+    }
+
+    #[test]
+    fn edits_outlive_scoped_overlay() {
+        let mut settings = Settings::default();
+        settings.push_overlay(PROFILE_OVERLAY, SettingsInner::default());
+        settings.set_show_path(true);
+
+        let guard = settings.with_overlay(SettingsInner {
+            show_path: Some(false),
+            camera_animation: Some(90),
+            ..Default::default()
+        });
+        settings.set_show_path(false);
+        settings.set_audio_volume(0.3);
+        settings.set_show_path(true);
+        assert!(settings.get_show_path());
+        assert_eq!(settings.get_camera_animation(), 90);
+
+        drop(guard);
+        assert!(settings.get_show_path());
+        assert_eq!(settings.get_audio_volume(), 0.3);
+        assert_ne!(settings.get_camera_animation(), 90);
+    }
+
+    #[test]
+    fn reload_changed_file() {
+        let path = std::env::temp_dir().join(format!("tmaze-settings-{}.ron", std::process::id()));
+        fs::write(&path, "(slow: false)").unwrap();
+        let mut settings = Settings::load(path.clone(), false).unwrap();
+        settings.push_overlay("test", SettingsInner::default());
+        assert!(!settings.reload_if_changed());
+
+        // own writes are not reloaded
+        settings.set_audio_volume(0.3);
+        settings.save().unwrap();
+        assert!(!settings.reload_if_changed());
+
+        let touch = |contents: &str, secs| {
+            fs::write(&path, contents).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(secs))
+                .unwrap();
+        };
+
+        touch("(slow: true)", 10);
+        assert!(settings.reload_if_changed());
+        assert!(settings.get_slow());
+        // overlays are kept
+        assert_eq!(settings.get_audio_volume(), 0.3);
+        assert!(settings.remove_overlay("test").is_some());
+
+        // invalid file is ignored until it changes again
+        touch("(slow: ", 20);
+        assert!(!settings.reload_if_changed());
+        assert!(settings.get_slow());
+        assert!(!settings.reload_if_changed());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn save_keeps_comments() {
+        let path = std::env::temp_dir().join(format!("tmaze-comments-{}.ron", std::process::id()));
+        fs::write(&path, DEFAULT_SETTINGS).unwrap();
+
+        let mut settings = Settings::load(path.clone(), false).unwrap();
+        settings.set_audio_volume(0.3);
+        settings.save().unwrap();
+
+        // only the changed line is different
+        let saved = fs::read_to_string(&path).unwrap();
+        let changed: Vec<_> = DEFAULT_SETTINGS
+            .lines()
+            .zip(saved.lines())
+            .filter(|(old, new)| old != new)
+            .collect();
+        assert_eq!(
+            changed,
+            [("    audio_volume: 0.5,", "    audio_volume: 0.3,")]
+        );
+        assert_eq!(saved.lines().count(), DEFAULT_SETTINGS.lines().count());
+
+        let (parsed, _) = SettingsFormat::Ron.parse(&saved).unwrap();
+        assert_eq!(parsed.audio_volume, Some(0.3));
+
+        // nothing changed, nothing is written
+        fs::write(&path, "(audio_volume: 0.3) // kept").unwrap();
+        settings.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "(audio_volume: 0.3) // kept"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ron_patch() {
+        let text = r#"#![enable(implicit_some)]
+Settings (
+    // theme: "old.json5",
+    logging_level: "a,b)", /* (not a field: 1) */
+    slow: false,
+
+    macros: {
+        1: [(dir: Left, fast: false)],
+    },
+    frame_delay: 10
+)
+"#;
+        let mut settings = SettingsFormat::Ron.parse(text).unwrap().0;
+        settings.theme = Some("new.json5".into());
+        settings.slow = Some(true);
+        settings.macros = None;
+        let changed = ["theme", "slow", "macros"];
+
+        let patched = SettingsFormat::Ron
+            .patch(text, &settings, &changed)
+            .unwrap();
+        assert_eq!(
+            patched,
+            r#"#![enable(implicit_some)]
+Settings (
+    // theme: "old.json5",
+    logging_level: "a,b)", /* (not a field: 1) */
+    slow: true,
+
+    frame_delay: 10,
+    theme: "new.json5",
+)
+"#
+        );
+        let (parsed, _) = SettingsFormat::Ron.parse(&patched).unwrap();
+        assert_eq!(
+            format::field_values(&parsed),
+            format::field_values(&settings)
+        );
+
+        assert!(SettingsFormat::Ron
+            .patch("(slow: ", &settings, &changed)
+            .is_none());
+        // unbalanced brackets of a hand-edited file
+        for text in [
+            "(slow: true])",
+            "(logging_level: \"info\"], slow: true)",
+            "](slow: true)",
+        ] {
+            assert!(SettingsFormat::Ron
+                .patch(text, &settings, &changed)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn toml_save_keeps_comments() {
+        let path = std::env::temp_dir().join(format!("tmaze-comments-{}.toml", std::process::id()));
+        let text = "# my settings\nslow = false # not too fast\n\n# sound\naudio_volume = 0.5\n";
+        fs::write(&path, text).unwrap();
+
+        let mut settings = Settings::load(path.clone(), false).unwrap();
+        settings.set_audio_volume(0.25);
+        settings.save().unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert_eq!(saved, text.replace("0.5", "0.25"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unsaved_changes_are_not_written() {
+        let path = std::env::temp_dir().join(format!("tmaze-unsaved-{}.ron", std::process::id()));
+        fs::write(&path, "(slow: false)").unwrap();
+
+        let mut settings = Settings::load(path.clone(), false).unwrap();
+        settings.change_unsaved(|settings| {
+            settings.set_accessible(true);
+        });
+        assert!(settings.get_accessible());
+        assert!(!settings.is_dirty());
+
+        settings.set_slow(true);
+        settings.save().unwrap();
+        let (saved, _) = SettingsFormat::Ron
+            .parse(&fs::read_to_string(&path).unwrap())
+            .unwrap();
+        assert_eq!(saved.slow, Some(true));
+        assert_eq!(saved.accessible, None);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn toml_keeps_unknown_keys() {
+        let path = std::env::temp_dir().join(format!("tmaze-settings-{}.toml", std::process::id()));
+        let mut defaults = default_settings();
+        defaults.macros = Some(BTreeMap::from([(
+            1,
+            vec![MacroStep {
+                dir: CellWall::Left,
+                fast: true,
+            }],
+        )]));
+        let text = SettingsFormat::Toml
+            .to_string(&defaults, &UnknownFields::new())
+            .unwrap();
+        fs::write(&path, format!("from_the_future = [1, 2]\n{text}")).unwrap();
+
+        let mut settings = Settings::load(path.clone(), false).unwrap();
+        let step = settings.read().macros.as_ref().unwrap()[&1][0];
+        assert!(step.fast && step.dir == CellWall::Left);
+        assert_eq!(settings.get_audio_volume(), defaults.audio_volume.unwrap());
+
+        settings.set_slow(true);
+        settings.save().unwrap();
+        let (saved, unknown) = SettingsFormat::Toml
+            .parse(&fs::read_to_string(&path).unwrap())
+            .unwrap();
+        assert_eq!(saved.slow, Some(true));
+        assert_eq!(unknown.keys().collect::<Vec<_>>(), ["from_the_future"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_file_falls_back() {
+        let path = std::env::temp_dir().join(format!("tmaze-invalid-{}.ron", std::process::id()));
+        fs::write(&path, "(slow: ").unwrap();
+        let err = Settings::load(path.clone(), false).unwrap_err();
+        assert!(matches!(err, AppError::Settings { .. }));
+
+        // fallback doesn't overwrite the broken file
+        let mut settings = Settings::fallback(path.clone());
+        settings.set_slow(true);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "(slow: ");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn journal_undo() {
+        let options = format::ron_options();
+        let base: SettingsInner = options.from_str(DEFAULT_SETTINGS).unwrap();
+        let mut settings = Settings {
+            inner: Arc::new(RwLock::new(SettingsLayers::new(base))),
+            path: settings_path(),
+            read_only: true,
+        };
+
+        assert!(!settings.undo());
+        assert!(!settings.is_dirty());
+
+        settings.set_slow(true);
+        settings.set_audio_volume(0.3);
+        assert!(settings.set_active_profile(Some("Casual")));
+        settings.set_audio_volume(0.1);
+        assert_eq!(settings.journal_len(), 4);
+
+        // edits made in a profile are saved into it
+        let saved = settings.inner.read().unwrap().to_save();
+        let casual = saved.profiles.iter().flatten().find(|p| p.name == "Casual");
+        assert_eq!(casual.unwrap().settings.audio_volume, Some(0.1));
+        let config = ron::ser::PrettyConfig::default()
+            .extensions(ron::extensions::Extensions::IMPLICIT_SOME);
+        let string = ron::ser::to_string_pretty(&saved, config).unwrap();
+        let loaded: SettingsInner = options.from_str(&string).unwrap();
+        assert_eq!(loaded.active_profile.as_deref(), Some("Casual"));
+        assert_eq!(loaded.slow, Some(true));
+
+        assert!(settings.undo());
+        assert_eq!(settings.get_audio_volume(), 0.3);
+        assert_eq!(settings.get_active_profile().as_deref(), Some("Casual"));
+        assert!(settings.undo());
+        assert_eq!(settings.get_active_profile(), None);
+        assert_eq!(settings.get_audio_volume(), 0.3);
+
+        assert!(settings.revert_session());
+        assert!(!settings.get_slow());
+        assert_eq!(settings.get_audio_volume(), 0.5);
+
+        // revert can be undone too
+        assert!(settings.undo());
+        assert!(settings.get_slow());
+    }
+
+    #[test]
+    fn settings_by_name() {
+        let mut settings = Settings {
+            inner: Arc::new(RwLock::new(SettingsLayers::new(SettingsInner::default()))),
+            path: settings_path(),
+            read_only: true,
+        };
+
+        let metadata = Settings::metadata();
+        for meta in &metadata {
+            assert_eq!(settings.get_value(meta.name), Some(meta.default));
+        }
 
-    Activity::new_base_boxed("controls settings", Menu::new(menu_config))
+        let dpad = metadata.iter().find(|m| m.name == "dpad_size").unwrap();
+        assert_eq!(dpad.range, Some((0.1, 1.0)));
+        assert!(settings.set_value("dpad_size", ModeValue::Number(5.0)));
+        assert_eq!(settings.get_dpad_size(), 1.0);
+
+        assert!(!settings.set_value("show_path", ModeValue::Number(1.0)));
+        assert!(!settings.set_value("camera_mode", ModeValue::Bool(true)));
+        assert!(settings.set_value("show_path", ModeValue::Bool(false)));
+        assert!(!settings.get_show_path());
+    }
+
+    #[test]
+    fn adaptive_margin() {
+        let margin = Settings::adaptive_viewport_margin;
+        assert_eq!(margin(Dims(80, 24), 2.0), Dims(4, 2));
+        assert_eq!(margin(Dims(40, 60), 2.0), Dims(3, 2));
+        assert_eq!(margin(Dims(300, 80), 2.0), Dims(8, 4));
+        assert_eq!(margin(Dims(10, 5), 2.0), Dims(1, 1));
+    }
+
+    #[test]
+    fn disabled_presets_are_hidden() {
+        let mut settings = Settings {
+            inner: Arc::new(RwLock::new(SettingsLayers::new(SettingsInner::default()))),
+            path: settings_path(),
+            read_only: true,
+        };
+        let preset: MazePreset =
+            ron::from_str(r#"(title: "Huge", width: 80, height: 40)"#).unwrap();
+        settings.inner.write().unwrap().presets = Some(vec![("preset/huge.ron".into(), preset)]);
+        settings.set_mazes(vec![]);
+
+        let titles = |settings: &Settings| -> Vec<_> {
+            settings.get_mazes().into_iter().map(|m| m.title).collect()
+        };
+        assert_eq!(titles(&settings), ["Huge"]);
+
+        settings.set_content_enabled("preset/huge.ron", false);
+        assert!(!settings.is_content_enabled("preset/huge.ron"));
+        assert!(titles(&settings).is_empty());
+
+        settings.set_content_enabled("preset/huge.ron", true);
+        assert_eq!(settings.get_disabled_content(), Vec::<String>::new());
+        assert_eq!(titles(&settings), ["Huge"]);
+    }
 }
@@ -0,0 +1,285 @@
+//! Search of the settings by their labels and descriptions
+//!
+//! Every row of the [`super::editor`] is indexed, so every setting can be found, the
+//! editor is opened with the found setting selected.
+
+use std::io;
+
+use cmaze::dims::Dims;
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    app::{self, app::AppData, Activity, ActivityHandler, Change},
+    helpers::is_release,
+    renderer::Frame,
+    settings::theme::Theme,
+    ui::{center_box_in_screen, draw_box, Screen, TextInput},
+};
+
+use super::editor::{self, SettingsEditor};
+
+struct SearchEntry {
+    /// Name of the setting in the settings file
+    name: &'static str,
+    label: &'static str,
+    description: String,
+}
+
+impl SearchEntry {
+    /// How well the entry matches, `None` if some of the words are missing
+    fn score(&self, words: &[String]) -> Option<u32> {
+        let label = self.label.to_lowercase();
+        let description = self.description.to_lowercase();
+        let name = self.name.replace('_', " ");
+
+        words.iter().try_fold(0, |score, word| {
+            if label.contains(word.as_str()) {
+                Some(score + 2)
+            } else if description.contains(word.as_str()) || name.contains(word.as_str()) {
+                Some(score + 1)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Finds settings by their label or description
+pub struct SettingsSearch {
+    entries: Vec<SearchEntry>,
+    input: TextInput,
+    /// Indices of the matching entries, best first
+    results: Vec<usize>,
+    selected: usize,
+}
+
+impl SettingsSearch {
+    /// Maximum number of results shown at once
+    const MAX_RESULTS: usize = 10;
+
+    pub fn new() -> Self {
+        let entries = editor::fields()
+            .into_iter()
+            .map(|field| SearchEntry {
+                name: field.name,
+                label: field.label,
+                description: field.description,
+            })
+            .collect();
+
+        let mut search = Self {
+            entries,
+            input: TextInput::new().with_placeholder("Type to search settings"),
+            results: vec![],
+            selected: 0,
+        };
+        search.filter();
+        search
+    }
+
+    pub fn new_activity() -> Activity {
+        Activity::new_base_boxed("settings search", Self::new())
+    }
+
+    fn filter(&mut self) {
+        let words: Vec<_> = self
+            .input
+            .value()
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+
+        let mut scored: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| entry.score(&words).map(|score| (i, score)))
+            .collect();
+        // stable, so entries with the same score keep their order
+        scored.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+        self.results = scored.into_iter().map(|(i, _)| i).collect();
+        self.selected = 0;
+    }
+
+    /// Opens the editor with the selected setting
+    fn jump(&self, data: &mut AppData) -> Option<Change> {
+        let entry = &self.entries[*self.results.get(self.selected)?];
+        log::debug!("Jumping to setting `{}`", entry.name);
+
+        let editor = SettingsEditor::new(&data.settings).with_selected(entry.name);
+        Some(Change::push(Activity::new_base_boxed(
+            "settings editor",
+            editor,
+        )))
+    }
+
+    fn size(screen: Dims) -> Dims {
+        let width = (screen.0 - 4).clamp(20, 70);
+        let height = Self::MAX_RESULTS as i32 + 5;
+        Dims(width, height)
+    }
+}
+
+impl Default for SettingsSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityHandler for SettingsSearch {
+    fn update(&mut self, events: Vec<app::Event>, data: &mut AppData) -> Option<Change> {
+        for event in events {
+            let app::Event::Term(TermEvent::Key(key @ KeyEvent { code, kind, .. })) = event else {
+                continue;
+            };
+
+            if is_release(kind) {
+                continue;
+            }
+
+            match code {
+                KeyCode::Esc => return Some(Change::pop_top()),
+                KeyCode::Enter => return self.jump(data),
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => {
+                    self.selected = (self.selected + 1).min(self.results.len().saturating_sub(1));
+                }
+                _ => {
+                    if self.input.handle_key(key) {
+                        self.filter();
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+}
+
+impl Screen for SettingsSearch {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let size = Self::size(frame.size);
+        let pos = center_box_in_screen(size, frame.size);
+        let inner = size.0 - 4;
+
+        let border = theme["ui_menu_border"];
+        let text = theme["ui_menu_text"];
+        let dim = theme["dim"];
+
+        draw_box(frame, pos, size, border);
+        frame.draw(
+            pos + Dims(3, 0),
+            " Search settings ",
+            theme["ui_menu_title"],
+        );
+
+        self.input.draw(frame, theme, pos + Dims(2, 1), inner);
+        frame.draw(pos + Dims(1, 2), "─".repeat(size.0 as usize - 2), border);
+
+        if self.results.is_empty() {
+            frame.draw(pos + Dims(2, 3), "No matching settings", dim);
+        }
+
+        // scroll, so the selected result is visible
+        let first = (self.selected + 1).saturating_sub(Self::MAX_RESULTS);
+        for (row, &index) in self
+            .results
+            .iter()
+            .skip(first)
+            .take(Self::MAX_RESULTS)
+            .enumerate()
+        {
+            let entry = &self.entries[index];
+            let line_pos = pos + Dims(2, 3 + row as i32);
+
+            let name: String = entry.label.chars().take(inner as usize).collect();
+            let selected = first + row == self.selected;
+
+            let (text, dim) = match selected {
+                true => (text.invert(), dim.invert()),
+                false => (text, dim),
+            };
+
+            let rest = inner as usize - name.width();
+            let description = match entry.description.as_str() {
+                "" => String::new(),
+                d if rest > 3 => format!(" - {}", d).chars().take(rest).collect(),
+                _ => String::new(),
+            };
+
+            frame.draw(line_pos, name.as_str(), text);
+            frame.draw(
+                line_pos + Dims(name.width() as i32, 0),
+                format!("{:<w$}", description, w = rest),
+                dim,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::Settings;
+
+    use super::*;
+
+    fn search(query: &str) -> Vec<&'static str> {
+        let mut search = SettingsSearch::new();
+        search.input.set_value(query.to_string());
+        search.filter();
+        search
+            .results
+            .iter()
+            .map(|&i| search.entries[i].name)
+            .collect()
+    }
+
+    #[test]
+    fn every_setting_is_indexed() {
+        let search = SettingsSearch::new();
+        assert_eq!(search.results.len(), search.entries.len());
+
+        for meta in Settings::metadata() {
+            let entry = search.entries.iter().find(|e| e.name == meta.name);
+            let entry = entry.unwrap_or_else(|| panic!("{} is not indexed", meta.name));
+            assert_eq!(entry.label, meta.label);
+            assert_eq!(entry.description, editor::plain_doc(meta.description));
+        }
+    }
+
+    #[test]
+    fn found_by_label_and_description() {
+        assert_eq!(
+            search("sticky modifiers").first(),
+            Some(&"sticky_modifiers")
+        );
+        // only in the description
+        assert!(search("accepted moves").contains(&"input_debounce"));
+        assert_eq!(search("save battery"), ["frame_delay"]);
+        // every word has to match
+        assert!(search("sticky battery").is_empty());
+    }
+
+    #[test]
+    fn labels_rank_above_descriptions() {
+        let search = SettingsSearch::new();
+        let results = self::search("dpad");
+        let label = |name: &str| {
+            let entry = search.entries.iter().find(|e| e.name == name).unwrap();
+            entry.label.to_lowercase().contains("dpad")
+        };
+
+        let ranks: Vec<_> = results.iter().map(|name| label(name)).collect();
+        assert!(ranks.len() > 1 && ranks[0]);
+        // labeled matches first, then the ones found by the description
+        assert!(ranks.windows(2).all(|w| w[0] || !w[1]));
+    }
+}
@@ -1,4 +1,8 @@
-use std::{fmt::Display, ops, path::PathBuf};
+use std::{
+    fmt::Display,
+    io, ops,
+    path::{Path, PathBuf},
+};
 
 use crossterm::style::{Attributes, ContentStyle};
 use hashbrown::HashMap;
@@ -6,12 +10,14 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::{
-    helpers::constants::paths::theme_file_path, settings::attribute::deserialize_attributes,
+    helpers::constants::paths::theme_file_path,
+    settings::attribute::{deserialize_attributes, serialize_attributes},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Theme {
     styles: HashMap<String, Style>,
+    texture_density: u8,
 }
 
 impl Theme {
@@ -26,6 +32,11 @@ impl Theme {
     pub fn extract<const N: usize>(&self, keys: [&str; N]) -> [Style; N] {
         keys.map(|key| self.get(key))
     }
+
+    /// Percentage of the walls decorated with moss and cracks, see [`crate::app::texture`]
+    pub fn texture_density(&self) -> u8 {
+        self.texture_density
+    }
 }
 
 impl ops::Index<&str> for Theme {
@@ -40,10 +51,37 @@ impl ops::Index<&str> for Theme {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ThemeDefinition {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "sorted_meta"
+    )]
     meta: Option<HashMap<String, String>>,
+    #[serde(serialize_with = "sorted")]
     styles: HashMap<String, StyleIdent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    texture_density: Option<u8>,
+}
+
+/// Keys are written sorted, so saved themes are easy to read and compare
+fn sorted<S: serde::Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter()
+        .collect::<std::collections::BTreeMap<_, _>>()
+        .serialize(serializer)
+}
+
+fn sorted_meta<S: serde::Serializer>(
+    meta: &Option<HashMap<String, String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match meta {
+        Some(meta) => sorted(meta, serializer),
+        None => serializer.serialize_none(),
+    }
 }
 
 // For some reason, Rust concat! doesn't allow const, so we have to use a macro
@@ -56,8 +94,13 @@ const DEFAULT_THEME_NAME: &str = default_theme_name!();
 const DEFAULT_THEME: &str = include_str!(concat!("./", default_theme_name!()));
 
 impl ThemeDefinition {
+    /// File of the default theme, it's created when the default theme is loaded
+    pub fn default_path() -> PathBuf {
+        theme_file_path(DEFAULT_THEME_NAME)
+    }
+
     pub fn load_default(read_only: bool) -> Result<Self, LoadError> {
-        let path = theme_file_path(DEFAULT_THEME_NAME);
+        let path = Self::default_path();
 
         if !read_only {
             std::fs::create_dir_all(path.parent().unwrap())?;
@@ -71,6 +114,11 @@ impl ThemeDefinition {
         }
     }
 
+    /// Default theme built into the game, when the theme files can't be loaded
+    pub fn builtin() -> Self {
+        json5::from_str(DEFAULT_THEME).expect("built-in theme should be valid")
+    }
+
     pub fn load_by_name(path: &str) -> Result<Self, LoadError> {
         Self::load_by_path(theme_file_path(path))
     }
@@ -78,18 +126,12 @@ impl ThemeDefinition {
     pub fn load_by_path(path: PathBuf) -> Result<Self, LoadError> {
         log::debug!("Loading theme from {:?}", path);
 
-        let ext = path
-            .extension()
-            .and_then(|s| s.to_str())
-            .expect("No extension");
+        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
 
         match ext {
             "toml" => Self::load_toml(path),
             "json" | "json5" => Self::load_json(path),
-            _ => Err(LoadError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Unknown file extension",
-            ))),
+            _ => Err(LoadError::UnknownFormat(path)),
         }
     }
 
@@ -105,6 +147,35 @@ impl ThemeDefinition {
         Ok(theme)
     }
 
+    /// Writes the theme to the file, in the format of its extension
+    ///
+    /// Comments and formatting of the original file are not kept.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let invalid =
+            |err: &dyn Display| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+        let content = match path.extension().and_then(|s| s.to_str()).unwrap_or("") {
+            "toml" => toml::to_string_pretty(self).map_err(|err| invalid(&err))?,
+            "json" | "json5" => serde_json::to_string_pretty(self).map_err(|err| invalid(&err))?,
+            _ => return Err(invalid(&LoadError::UnknownFormat(path.to_owned()))),
+        };
+        std::fs::write(path, content)
+    }
+
+    /// Style defined for the key, `None` removes it, so the key inherits its style again
+    pub fn set_style(&mut self, key: &str, style: Option<Style>) {
+        match style {
+            Some(style) => self
+                .styles
+                .insert(key.to_string(), StyleIdent::Style(style)),
+            None => self.styles.remove(key),
+        };
+    }
+
+    /// Value from the `meta` section, like `name` or `version`
+    pub fn meta(&self, key: &str) -> Option<&str> {
+        self.meta.as_ref()?.get(key).map(String::as_str)
+    }
+
     pub fn get(&self, key: &str) -> Option<StyleIdent> {
         if let Some(style) = self.styles.get(key) {
             Some(style.clone())
@@ -116,22 +187,33 @@ impl ThemeDefinition {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum StyleIdent {
     Style(Style),
     Ref(String),
 }
 
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
 pub struct Style {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub bg: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fg: Option<Color>,
-    #[serde(deserialize_with = "deserialize_attributes", default)]
+    #[serde(
+        deserialize_with = "deserialize_attributes",
+        serialize_with = "serialize_attributes",
+        skip_serializing_if = "no_attributes",
+        default
+    )]
     pub attr: Attributes,
 }
 
+fn no_attributes(attr: &Attributes) -> bool {
+    attr.is_empty()
+}
+
 impl Style {
     pub fn fg(color: Color) -> Self {
         Self {
@@ -275,7 +357,13 @@ pub enum NamedColor {
 }
 
 #[derive(Debug, Default)]
-pub struct ThemeResolver(HashMap<String, String>);
+pub struct ThemeResolver {
+    links: HashMap<String, String>,
+    /// What the keys are used for, shown to theme authors
+    descriptions: HashMap<String, String>,
+    /// Last linked key, [`ThemeResolver::desc`] describes it
+    last: Option<String>,
+}
 
 #[allow(dead_code)]
 impl ThemeResolver {
@@ -284,24 +372,48 @@ impl ThemeResolver {
     }
 
     pub fn link<S: Into<String>>(&mut self, key: S, based_on: S) -> &mut Self {
-        self.0.insert(key.into(), based_on.into());
+        let key = key.into();
+        self.links.insert(key.clone(), based_on.into());
+        self.last = Some(key);
+        self
+    }
+
+    /// Describes the key linked last, what it's used for
+    pub fn desc(&mut self, description: &str) -> &mut Self {
+        let key = self.last.clone().expect("describe a linked key");
+        self.descriptions.insert(key, description.to_string());
         self
     }
 
     pub fn get(&self, key: &str) -> &str {
-        self.0.get(key).map(|s| s.as_str()).unwrap_or("default")
+        self.links.get(key).map(|s| s.as_str()).unwrap_or("default")
+    }
+
+    pub fn description(&self, key: &str) -> Option<&str> {
+        self.descriptions.get(key).map(String::as_str)
+    }
+
+    /// All linked keys, sorted
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<_> = self.links.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        keys
     }
 
     pub fn resolve(&self, definition: &ThemeDefinition) -> Theme {
         let mut resolved = HashMap::new();
-        for (key, _) in &self.0 {
+        for key in self.links.keys() {
             let style = self.resolve_style(definition, key);
             resolved.insert(key.clone(), style);
         }
-        Theme { styles: resolved }
+        Theme {
+            styles: resolved,
+            texture_density: definition.texture_density.unwrap_or(0).min(100),
+        }
     }
 
-    fn resolve_style<'a>(&'a self, definition: &'a ThemeDefinition, key: &'a str) -> Style {
+    /// Style of the key, following the references of the theme and the links of the resolver
+    pub fn resolve_style(&self, definition: &ThemeDefinition, key: &str) -> Style {
         let mut key = key.to_string();
         let mut used = vec![key.clone()];
         loop {
@@ -331,7 +443,9 @@ impl ThemeResolver {
     ///
     /// It returns a mutable reference to `self` for chaining.
     pub fn extend(&mut self, other: Self) -> &mut Self {
-        self.0.extend(other.0);
+        self.links.extend(other.links);
+        self.descriptions.extend(other.descriptions);
+        self.last = None;
         self
     }
 }
@@ -341,6 +455,7 @@ pub enum LoadError {
     Io(#[from] std::io::Error),
     Toml(#[from] toml::de::Error),
     Json(#[from] json5::Error),
+    UnknownFormat(PathBuf),
 }
 
 impl Display for LoadError {
@@ -349,6 +464,11 @@ impl Display for LoadError {
             LoadError::Io(e) => write!(f, "IO error: {}", e),
             LoadError::Toml(e) => write!(f, "TOML parse error: {}", e),
             LoadError::Json(e) => write!(f, "JSON parse error: {}", e),
+            LoadError::UnknownFormat(path) => write!(
+                f,
+                "unknown format of {}, use .json, .json5 or .toml",
+                path.display()
+            ),
         }
     }
 }
@@ -357,6 +477,27 @@ impl Display for LoadError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn resolver_descriptions() {
+        let mut resolver = ThemeResolver::new();
+        resolver
+            .link("text", "")
+            .desc("Text")
+            .link("border", "text")
+            .link("item", "unknown")
+            .desc("Items");
+        assert_eq!(resolver.description("text"), Some("Text"));
+        assert_eq!(resolver.description("border"), None);
+        assert_eq!(resolver.description("item"), Some("Items"));
+        assert_eq!(resolver.description("unknown"), None);
+
+        let mut other = ThemeResolver::new();
+        other.link("button", "border").desc("Buttons");
+        resolver.extend(other);
+        assert_eq!(resolver.description("button"), Some("Buttons"));
+        assert_eq!(resolver.description("item"), Some("Items"));
+    }
+
     #[test]
     fn resolver() {
         let mut resolver = ThemeResolver::new();
@@ -385,6 +526,7 @@ mod tests {
                 .cloned()
                 .collect(),
             meta: None,
+            texture_density: None,
         };
 
         let theme = resolver.resolve(&definition);
@@ -405,6 +547,29 @@ mod tests {
         assert_eq!(theme.styles.get("item"), default_style);
     }
 
+    #[test]
+    fn saved_theme_loads_the_same() {
+        let mut definition = ThemeDefinition::builtin();
+        let mut style = Style::fg(Color::Hex(0x12, 0x34, 0x56));
+        style.attr.set(crossterm::style::Attribute::Bold);
+        definition.set_style("text", Some(style));
+        definition.set_style("border", None);
+
+        let json = serde_json::to_string_pretty(&definition).unwrap();
+        let loaded: ThemeDefinition = json5::from_str(&json).unwrap();
+        let toml = toml::to_string_pretty(&definition).unwrap();
+        assert_eq!(toml::from_str::<ThemeDefinition>(&toml).unwrap(), loaded);
+
+        // hex colors are written as RGB
+        let style = Style {
+            fg: Some(Color::RGB(0x12, 0x34, 0x56)),
+            ..style
+        };
+        assert_eq!(loaded.get("text"), Some(StyleIdent::Style(style)));
+        assert_eq!(loaded.get("border"), None);
+        assert_eq!(loaded.styles.len(), definition.styles.len());
+    }
+
     #[test]
     fn resolver_loop() {
         use std::panic;
@@ -416,6 +581,7 @@ mod tests {
         let definition = ThemeDefinition {
             styles: HashMap::new(),
             meta: None,
+            texture_density: None,
         };
 
         let result = panic::catch_unwind(|| resolver.resolve(&definition));
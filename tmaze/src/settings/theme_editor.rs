@@ -0,0 +1,370 @@
+//! Editor of the current theme, the style of a key is changed with the keyboard
+//!
+//! Every change is applied to the whole app right away, so the editor itself and the sample
+//! widgets next to the style show it. Changes are written to the theme file only when they are
+//! saved, leaving without saving loads the theme from the file again.
+
+use std::{io, path::PathBuf};
+
+use cmaze::dims::Dims;
+use crossterm::{
+    event::{Event as TermEvent, KeyCode},
+    style::Attribute,
+};
+
+use crate::{
+    app::{
+        app::{init_theme_resolver, AppData},
+        Activity, ActivityHandler, Change, Event,
+    },
+    error::AppError,
+    helpers::is_release,
+    renderer::{Cell, Frame},
+    ui::{center_box_in_screen, draw_box, wrap_words, Button, Hint, ProgressBar, Screen},
+};
+
+use super::{
+    editor::fit,
+    theme::{Color, NamedColor, Style, StyleIdent, Theme, ThemeDefinition, ThemeResolver},
+    Settings,
+};
+
+const COLORS: [NamedColor; 16] = [
+    NamedColor::Black,
+    NamedColor::DarkGrey,
+    NamedColor::Red,
+    NamedColor::DarkRed,
+    NamedColor::Green,
+    NamedColor::DarkGreen,
+    NamedColor::Yellow,
+    NamedColor::DarkYellow,
+    NamedColor::Blue,
+    NamedColor::DarkBlue,
+    NamedColor::Magenta,
+    NamedColor::DarkMagenta,
+    NamedColor::Cyan,
+    NamedColor::DarkCyan,
+    NamedColor::White,
+    NamedColor::Grey,
+];
+
+/// Attributes which can be toggled, others can still be set in the theme file
+const ATTRIBUTES: [(Attribute, &str); 5] = [
+    (Attribute::Bold, "bold"),
+    (Attribute::Dim, "dim"),
+    (Attribute::Italic, "italic"),
+    (Attribute::Underlined, "underlined"),
+    (Attribute::Reverse, "reverse"),
+];
+
+/// Foreground, background and the attributes
+const FIELDS: usize = 2 + ATTRIBUTES.len();
+
+const MAX_SIZE: Dims = Dims(80, 30);
+
+pub struct ThemeEditor {
+    definition: ThemeDefinition,
+    resolver: ThemeResolver,
+    /// Keys used by the game, sorted
+    keys: Vec<String>,
+    selected: usize,
+    field: usize,
+    /// Theme file, `None` for the built-in theme, which can't be saved
+    path: Option<PathBuf>,
+    /// Changes weren't saved yet
+    dirty: bool,
+}
+
+impl ThemeEditor {
+    pub fn new(settings: &Settings) -> Self {
+        let resolver = init_theme_resolver();
+        let keys = resolver.keys().into_iter().map(String::from).collect();
+
+        Self {
+            definition: settings.get_theme(),
+            resolver,
+            keys,
+            selected: 0,
+            field: 0,
+            path: settings.theme_path(),
+            dirty: false,
+        }
+    }
+
+    pub fn new_activity(settings: &Settings) -> Activity {
+        Activity::new_base_boxed("theme editor", Self::new(settings))
+    }
+
+    fn key(&self) -> &str {
+        &self.keys[self.selected]
+    }
+
+    /// Style of the selected key, as it's drawn
+    fn style(&self) -> Style {
+        self.resolver.resolve_style(&self.definition, self.key())
+    }
+
+    /// Where the style of the selected key comes from
+    fn origin(&self) -> String {
+        match self.definition.get(self.key()) {
+            Some(StyleIdent::Style(_)) => "Own style".to_string(),
+            Some(StyleIdent::Ref(key)) => format!("Same as {}", key),
+            None => match self.resolver.get(self.key()) {
+                "" | "default" => "Default style".to_string(),
+                key => format!("Inherited from {}", key),
+            },
+        }
+    }
+
+    /// Shows the edited theme in the whole app
+    fn preview(&self, data: &mut AppData) {
+        data.theme = self.resolver.resolve(&self.definition);
+    }
+
+    fn change(&mut self, data: &mut AppData, change: impl FnOnce(&mut Style)) {
+        let mut style = self.style();
+        change(&mut style);
+
+        let key = self.key().to_string();
+        self.definition.set_style(&key, Some(style));
+        self.dirty = true;
+        self.preview(data);
+    }
+
+    /// Changes the selected field, colors go through the named ones, attributes are toggled
+    fn step(&mut self, up: bool, data: &mut AppData) {
+        match self.field {
+            0 => self.change(data, |style| style.fg = next_color(style.fg, up)),
+            1 => self.change(data, |style| style.bg = next_color(style.bg, up)),
+            field => {
+                let (attr, _) = ATTRIBUTES[field - 2];
+                self.change(data, |style| style.attr.toggle(attr));
+            }
+        }
+    }
+
+    /// Removes the own style of the key, so it inherits it again
+    fn reset(&mut self, data: &mut AppData) {
+        let key = self.key().to_string();
+        self.definition.set_style(&key, None);
+        self.dirty = true;
+        self.preview(data);
+    }
+
+    fn save(&mut self, data: &mut AppData) {
+        let Some(path) = &self.path else {
+            log::warn!("Built-in theme can't be saved");
+            return;
+        };
+
+        match self.definition.save(path) {
+            Ok(()) => {
+                self.dirty = false;
+                log::info!("Theme saved to {}", path.display());
+            }
+            Err(err) => data.report(AppError::io("save the theme", err)),
+        }
+    }
+
+    fn leave(&mut self, data: &mut AppData) -> Change {
+        if self.dirty {
+            log::info!("Theme changes discarded");
+            data.apply_settings();
+        }
+        Change::pop_top()
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.keys.len() - 1);
+    }
+}
+
+/// Next named color, `None` is the first one, other colors continue from it
+fn next_color(color: Option<Color>, up: bool) -> Option<Color> {
+    let choices: Vec<_> = [None]
+        .into_iter()
+        .chain(COLORS.map(|named| Some(Color::Named(named))))
+        .collect();
+    let len = choices.len();
+
+    let index = choices.iter().position(|c| *c == color).unwrap_or(0);
+    let index = match up {
+        true => (index + 1) % len,
+        false => (index + len - 1) % len,
+    };
+    choices[index]
+}
+
+fn color_name(color: Option<Color>) -> String {
+    match color {
+        None => "none".to_string(),
+        Some(Color::Named(named)) => format!("{:?}", named),
+        Some(Color::RGB(r, g, b) | Color::Hex(r, g, b)) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+    }
+}
+
+impl ActivityHandler for ThemeEditor {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        for event in events {
+            match event {
+                Event::Term(TermEvent::Key(key)) if !is_release(key.kind) => {
+                    let last = self.keys.len() - 1;
+                    match key.code {
+                        KeyCode::Up => self.select(self.selected.checked_sub(1).unwrap_or(last)),
+                        KeyCode::Down => self.select((self.selected + 1) % self.keys.len()),
+                        KeyCode::PageUp => self.select(self.selected.saturating_sub(10)),
+                        KeyCode::PageDown => self.select(self.selected + 10),
+                        KeyCode::Home => self.select(0),
+                        KeyCode::End => self.select(last),
+                        KeyCode::Tab => self.field = (self.field + 1) % FIELDS,
+                        KeyCode::BackTab => self.field = (self.field + FIELDS - 1) % FIELDS,
+                        KeyCode::Left => self.step(false, data),
+                        KeyCode::Right | KeyCode::Enter | KeyCode::Char(' ') => {
+                            self.step(true, data)
+                        }
+                        KeyCode::Char('r') | KeyCode::Delete => self.reset(data),
+                        KeyCode::Char('s') => self.save(data),
+                        KeyCode::Esc | KeyCode::Char('q') => return Some(self.leave(data)),
+                        _ => {}
+                    }
+                }
+                // edits in the file win over the unchanged editor, unsaved changes are kept
+                Event::ThemeReloaded if !self.dirty => {
+                    self.definition = data.settings.get_theme();
+                }
+                Event::ThemeReloaded => {
+                    log::warn!("Theme file changed, saving overwrites it");
+                    self.preview(data);
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        self
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![
+            Hint::new("↑↓", "key"),
+            Hint::new("Tab", "field"),
+            Hint::new("←→", "change"),
+            Hint::new("R", "reset"),
+            Hint::new("S", "save"),
+            Hint::new("Esc", "back"),
+        ]
+    }
+}
+
+impl Screen for ThemeEditor {
+    fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
+        let [border, title, text, dim] = theme.extract([
+            "ui.editor.border",
+            "ui.editor.title",
+            "ui.editor.text",
+            "ui.editor.dim",
+        ]);
+
+        let size = Dims(
+            (frame.size.0 - 2).clamp(30, MAX_SIZE.0),
+            (frame.size.1 - 2).clamp(12, MAX_SIZE.1),
+        );
+        let pos = center_box_in_screen(size, frame.size);
+        let inner = (size.0 - 4).max(0) as usize;
+
+        frame.fill_rect(pos, size, Cell::styled(' ', text));
+        draw_box(frame, pos, size, border);
+        frame.draw(pos + Dims(3, 0), " Theme editor ", title);
+
+        let file = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "Built-in theme, changes can't be saved".to_string(),
+        };
+        let file = if self.dirty { file + " *" } else { file };
+        frame.draw(pos + Dims(2, 1), fit(&file, inner), dim);
+
+        // keys, each in its own style
+        let list_width = inner / 2;
+        let rows = (size.1 - 4).max(1) as usize;
+        let first = self
+            .selected
+            .saturating_sub(rows / 2)
+            .min(self.keys.len().saturating_sub(rows));
+        for (row, key) in self.keys.iter().enumerate().skip(first).take(rows) {
+            let line = pos + Dims(2, 3 + (row - first) as i32);
+            let marker = if row == self.selected { "> " } else { "  " };
+            frame.draw(line, marker, text);
+            frame.draw(line + Dims(2, 0), fit(key, list_width - 2), theme[key]);
+        }
+
+        // fields of the selected style
+        let col = pos + Dims(2 + list_width as i32 + 2, 3);
+        let col_width = inner.saturating_sub(list_width + 2);
+        let style = self.style();
+        frame.draw(col, fit(self.key(), col_width), title);
+        frame.draw(col + Dims(0, 1), fit(&self.origin(), col_width), dim);
+
+        // what the key is used for, at most two lines
+        let description = self.resolver.description(self.key()).unwrap_or_default();
+        let description: Vec<_> = wrap_words(description, col_width)
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .take(2)
+            .collect();
+        for (i, line) in description.iter().enumerate() {
+            frame.draw(col + Dims(0, 2 + i as i32), fit(line, col_width), text);
+        }
+        let col = col + Dims(0, description.len() as i32);
+
+        let mut fields = vec![
+            format!("Foreground: {}", color_name(style.fg)),
+            format!("Background: {}", color_name(style.bg)),
+        ];
+        fields.extend(ATTRIBUTES.iter().map(|(attr, name)| {
+            let check = if style.attr.has(*attr) { 'x' } else { ' ' };
+            format!("[{}] {}", check, name)
+        }));
+        for (i, field) in fields.iter().enumerate() {
+            let field_style = if i == self.field { text.invert() } else { text };
+            frame.draw(
+                col + Dims(0, 3 + i as i32),
+                fit(field, col_width),
+                field_style,
+            );
+        }
+
+        // sample widgets, drawn with the edited theme
+        let sample = col + Dims(0, 4 + FIELDS as i32);
+        if sample.1 + 5 < pos.1 + size.1 {
+            frame.draw(sample, fit("Sample text", col_width), theme[self.key()]);
+
+            let mut bar = ProgressBar::new("Progress".to_string());
+            bar.update_progress(0.6);
+            bar.draw_line(frame, sample + Dims(0, 1), col_width.min(20) as i32, theme);
+
+            Button::new("Button".to_string(), sample + Dims(0, 2), Dims(10, 3))
+                .set(true)
+                .draw_colored(frame, theme);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_cycle_through_none() {
+        let black = Some(Color::Named(NamedColor::Black));
+        let grey = Some(Color::Named(NamedColor::Grey));
+        assert_eq!(next_color(None, true), black);
+        assert_eq!(next_color(black, false), None);
+        assert_eq!(next_color(None, false), grey);
+        assert_eq!(next_color(Some(Color::RGB(1, 2, 3)), true), black);
+    }
+}
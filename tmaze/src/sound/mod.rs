@@ -9,7 +9,15 @@ use crate::{
     ui::{menu, MenuItem, SliderDef},
 };
 
-use self::track::Track;
+use std::time::Duration;
+
+use self::track::{MusicContext, Playlist, Track, TrackCache};
+
+/// How many music tracks are kept decoded in memory
+const TRACK_CACHE_CAPACITY: usize = 4;
+
+/// Silence between tracks when gapless playback is off
+const TRACK_GAP: Duration = Duration::from_secs(2);
 
 struct SoundHandles {
     _stream: OutputStream,
@@ -20,16 +28,14 @@ struct SoundHandles {
 pub struct SoundPlayer {
     handles: Option<SoundHandles>,
     settings: Settings,
+    cache: TrackCache,
 }
 
 impl SoundPlayer {
     pub fn new(settings: Settings) -> Self {
         let Ok((stream, handle)) = rodio::OutputStream::try_default() else {
             log::warn!("Failed to create audio stream, no sound will be played");
-            return Self {
-                handles: None,
-                settings,
-            };
+            return Self::silent(settings);
         };
 
         let sink = Sink::try_new(&handle).expect("Failed to create sink");
@@ -41,6 +47,16 @@ impl SoundPlayer {
                 sink,
             }),
             settings,
+            cache: TrackCache::new(TRACK_CACHE_CAPACITY),
+        }
+    }
+
+    /// Create a player without an audio stream, nothing will be played
+    pub fn silent(settings: Settings) -> Self {
+        Self {
+            handles: None,
+            settings,
+            cache: TrackCache::new(TRACK_CACHE_CAPACITY),
         }
     }
 
@@ -67,6 +83,27 @@ impl SoundPlayer {
         });
     }
 
+    /// Play the playlist of the context in a loop, decoded samples are cached between plays
+    pub fn play_playlist(&mut self, context: MusicContext) {
+        if self.handles.is_none() {
+            return;
+        }
+
+        let tracks = context
+            .playlist(&self.settings)
+            .into_iter()
+            .map(|track| self.cache.get(track))
+            .collect();
+        let gap = if self.settings.get_gapless_music() {
+            Duration::ZERO
+        } else {
+            TRACK_GAP
+        };
+
+        let playlist = Playlist::new(tracks, self.settings.get_shuffle_music(), gap);
+        self.play_track(Box::new(playlist));
+    }
+
     #[allow(dead_code)]
     pub fn play_sound(&self, track: Track) {
         let Some(handle) = self.handles.as_ref().map(|h| &h.handle) else {
@@ -90,6 +127,10 @@ impl SoundPlayer {
 }
 
 pub fn create_audio_settings(data: &mut AppData) -> Activity {
+    Activity::new_base_boxed("audio settings", menu::Menu::new(audio_settings_menu(data)))
+}
+
+pub fn audio_settings_menu(data: &mut AppData) -> menu::MenuConfig {
     fn update_vol(data: &mut AppData) {
         if data.settings.get_enable_audio() && data.settings.get_enable_music() {
             data.sound_player
@@ -99,7 +140,7 @@ pub fn create_audio_settings(data: &mut AppData) -> Activity {
         }
     }
 
-    let menu_config = menu::MenuConfig::new(
+    menu::MenuConfig::new(
         "Audio settings",
         [
             MenuItem::Option(OptionDef {
@@ -142,10 +183,24 @@ pub fn create_audio_settings(data: &mut AppData) -> Activity {
                     update_vol(data);
                 }),
             }),
+            MenuItem::Option(OptionDef {
+                text: "Shuffle music".into(),
+                val: data.settings.get_shuffle_music(),
+                fun: Box::new(|shuffle, data| {
+                    *shuffle = !*shuffle;
+                    data.settings.set_shuffle_music(*shuffle);
+                }),
+            }),
+            MenuItem::Option(OptionDef {
+                text: "Gapless playback".into(),
+                val: data.settings.get_gapless_music(),
+                fun: Box::new(|gapless, data| {
+                    *gapless = !*gapless;
+                    data.settings.set_gapless_music(*gapless);
+                }),
+            }),
             MenuItem::Separator,
             MenuItem::Text("Exit".into()),
         ],
-    );
-
-    Activity::new_base_boxed("audio settings", menu::Menu::new(menu_config))
+    )
 }
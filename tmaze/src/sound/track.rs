@@ -1,8 +1,9 @@
-use std::io;
+use std::{io, str::FromStr, time::Duration};
 
-use cmaze::gameboard::Maze;
+use crate::settings::Settings;
 
 use rand::{seq::SliceRandom, thread_rng};
+use rodio::{source::Buffered, Source};
 
 mod assets_sounds {
     pub const MUSIC_EASY: &[u8] = include_bytes!(concat!(
@@ -33,6 +34,19 @@ mod assets_sounds {
 
 pub type Track = Box<dyn rodio::Source<Item = i16> + Send>;
 
+/// Short sine tone, used as a cue
+pub fn tone(frequency: f32, duration: Duration) -> Track {
+    let tone = rodio::source::SineWave::new(frequency)
+        .take_duration(duration)
+        .amplify(0.2);
+    Box::new(tone.convert_samples())
+}
+
+type Decoded = rodio::Decoder<io::Cursor<&'static [u8]>>;
+
+/// Track which is decoded lazily while playing, clones share decoded samples
+pub type BufferedTrack = Buffered<Decoded>;
+
 // TODO: maan, i know this is not the best
 // BUT, for now it's gonna be enough
 // PS: once there are mods (copium),
@@ -55,25 +69,230 @@ impl MusicTrack {
         }
     }
 
+    fn decode(&self) -> Decoded {
+        let cursor = io::Cursor::new(self.get_data());
+        rodio::Decoder::new(cursor).unwrap()
+    }
+
     pub fn get_track(&self) -> Track {
-        let data = Self::get_data(self);
+        Box::new(self.decode())
+    }
+}
+
+impl FromStr for MusicTrack {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(MusicTrack::Easy),
+            "medium" => Ok(MusicTrack::Medium),
+            "hard" => Ok(MusicTrack::Hard),
+            "menu" => Ok(MusicTrack::Menu),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Least recently used cache of buffered tracks
+///
+/// Tracks are only decoded as far as they were played, and the number of
+/// cached tracks is bounded, so memory usage stays bounded too.
+pub struct TrackCache {
+    capacity: usize,
+    /// Most recently used track is last
+    entries: Vec<(MusicTrack, BufferedTrack)>,
+}
+
+impl TrackCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: vec![],
+        }
+    }
+
+    /// Get the track from the beginning, decoded samples are reused
+    pub fn get(&mut self, track: MusicTrack) -> BufferedTrack {
+        let entry = match self.entries.iter().position(|(t, _)| *t == track) {
+            Some(index) => self.entries.remove(index),
+            None => {
+                if self.entries.len() >= self.capacity {
+                    let (evicted, _) = self.entries.remove(0);
+                    log::trace!("Evicting track {:?} from the cache", evicted);
+                }
+                (track, track.decode().buffered())
+            }
+        };
+
+        let buffered = entry.1.clone();
+        self.entries.push(entry);
+        buffered
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Plays tracks one after another, over and over, without decoding them again
+///
+/// With `shuffle`, order is reshuffled every time the playlist wraps around.
+/// Between tracks there is `gap` of silence, zero gap makes the transition
+/// gapless.
+pub struct Playlist {
+    tracks: Vec<BufferedTrack>,
+    order: Vec<usize>,
+    pos: usize,
+    current: BufferedTrack,
+    shuffle: bool,
+    gap: Duration,
+
+    /// Samples of silence left to play and their format (channels, sample rate)
+    silence: usize,
+    silence_format: (u16, u32),
+}
+
+impl Playlist {
+    /// Create a new playlist, `tracks` must not be empty
+    pub fn new(tracks: Vec<BufferedTrack>, shuffle: bool, gap: Duration) -> Self {
+        assert!(!tracks.is_empty(), "playlist must not be empty");
+
+        let mut order: Vec<_> = (0..tracks.len()).collect();
+        if shuffle {
+            order.shuffle(&mut thread_rng());
+        }
+
+        Self {
+            current: tracks[order[0]].clone(),
+            tracks,
+            order,
+            pos: 0,
+            shuffle,
+            gap,
+            silence: 0,
+            silence_format: (1, 44100),
+        }
+    }
+
+    fn advance(&mut self, format: (u16, u32)) {
+        self.pos += 1;
+        if self.pos == self.order.len() {
+            self.pos = 0;
+
+            if self.shuffle {
+                let last = *self.order.last().unwrap();
+                self.order.shuffle(&mut thread_rng());
 
-        let cursor = io::Cursor::new(data);
-        let source = rodio::Decoder::new(cursor).unwrap();
+                // don't play the same track twice in a row
+                if self.order.len() > 1 && self.order[0] == last {
+                    let len = self.order.len();
+                    self.order.swap(0, len - 1);
+                }
+            }
+        }
+
+        self.current = self.tracks[self.order[self.pos]].clone();
 
-        Box::new(source)
+        let (channels, rate) = format;
+        self.silence = (self.gap.as_secs_f32() * rate as f32) as usize * channels as usize;
+        self.silence_format = format;
     }
+}
+
+impl Iterator for Playlist {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.silence > 0 {
+            self.silence -= 1;
+            return Some(0);
+        }
+
+        let format = (self.current.channels(), self.current.sample_rate());
+        let sample = self.current.next()?;
 
-    /// Choose a random track for the Maze
-    /// # Arguments
-    /// * `maze` - The maze to choose the track for
-    /// # Returns
-    /// * A random track for the maze
-    ///
-    /// # Notes
-    /// * We do *NOT* have yet a for determining the difficulty of the maze, so we just choose a random track
-    pub fn choose_for_maze(_maze: &Maze) -> Self {
+        // advance right away, so frame info always describes what's next
+        if self.current.current_frame_len() == Some(0) {
+            self.advance(format);
+        }
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+impl Source for Playlist {
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.silence {
+            0 => self.current.current_frame_len(),
+            silence => Some(silence),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self.silence {
+            0 => self.current.channels(),
+            _ => self.silence_format.0,
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self.silence {
+            0 => self.current.sample_rate(),
+            _ => self.silence_format.1,
+        }
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Where the music is played, each context has its own playlist
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicContext {
+    Menu,
+    Game,
+}
+
+impl MusicContext {
+    pub fn default_playlist(&self) -> Vec<MusicTrack> {
         use MusicTrack::*;
-        *[Easy, Medium, Hard].choose(&mut thread_rng()).unwrap()
+        match self {
+            MusicContext::Menu => vec![Menu],
+            MusicContext::Game => vec![Easy, Medium, Hard],
+        }
+    }
+
+    /// Playlist from settings, falls back to the default one if it's empty
+    pub fn playlist(&self, settings: &Settings) -> Vec<MusicTrack> {
+        let names = match self {
+            MusicContext::Menu => settings.get_menu_playlist(),
+            MusicContext::Game => settings.get_game_playlist(),
+        };
+
+        let tracks: Vec<_> = names
+            .iter()
+            .filter_map(|name| match name.parse() {
+                Ok(track) => Some(track),
+                Err(_) => {
+                    log::warn!("Unknown music track: '{}'", name);
+                    None
+                }
+            })
+            .collect();
+
+        if tracks.is_empty() {
+            self.default_playlist()
+        } else {
+            tracks
+        }
     }
 }
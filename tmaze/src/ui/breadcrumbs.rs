@@ -0,0 +1,175 @@
+//! Trail of the menus the active one was opened from, at the top of the screen
+//!
+//! Activities name themselves with [`crate::app::ActivityHandler::title`], the trail is made of
+//! the titled ones at the top of the stack and ends at the first one without a title, like a
+//! running game, so going back never skips it. Clicking one of the earlier menus goes back to it, all the
+//! activities above it are popped.
+
+use cmaze::dims::Dims;
+use crossterm::event::MouseEvent;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    app::activity::Activities,
+    renderer::Frame,
+    settings::theme::{Theme, ThemeResolver},
+};
+
+use super::{Hit, HitMap, Rect};
+
+/// Smallest screen the trail is shown on, menus take the whole height of smaller ones
+pub const BREADCRUMBS_MIN_SIZE: Dims = Dims(40, 16);
+
+const SEPARATOR: &str = " ▸ ";
+/// Shown instead of the oldest menus when the trail doesn't fit
+const ELLIPSIS: &str = "…";
+
+/// Crumbs of the activity stack, laid out when they're drawn
+#[derive(Debug, Default)]
+pub struct Breadcrumbs {
+    /// Index of the activity in the stack, its title and position
+    crumbs: Vec<(usize, String, i32)>,
+    /// Trail starts with [`ELLIPSIS`]
+    cut: bool,
+    hits: HitMap<usize>,
+}
+
+impl Breadcrumbs {
+    /// Lays out the titles of the activities above the topmost one without a title
+    pub fn update(&mut self, activities: &Activities, size: Dims) {
+        self.crumbs.clear();
+        self.hits.clear();
+
+        let titled = activities
+            .iter()
+            .enumerate()
+            .rev()
+            .map_while(|(i, activity)| {
+                let title = activity.title().filter(|title| !title.is_empty())?;
+                Some((i, title.to_string()))
+            });
+        let mut titled: Vec<_> = titled.collect();
+        titled.reverse();
+
+        if titled.len() < 2 || size.0 < BREADCRUMBS_MIN_SIZE.0 || size.1 < BREADCRUMBS_MIN_SIZE.1 {
+            return;
+        }
+
+        let (start, cut) = fit(&titled, size.0 - 2);
+        self.cut = cut;
+        let mut x = 1 + cut as i32 * (ELLIPSIS.width() + SEPARATOR.width()) as i32;
+        for (i, title) in titled.drain(start..) {
+            let width = title.width() as i32;
+            if i + 1 != activities.len() {
+                self.hits
+                    .register(Rect::sized_at(Dims(x, 0), Dims(width, 1)), i);
+            }
+            self.crumbs.push((i, title, x));
+            x += width + SEPARATOR.width() as i32;
+        }
+    }
+
+    /// Index of the activity to go back to, if one of the crumbs was clicked
+    pub fn dispatch(&mut self, event: &MouseEvent) -> Option<usize> {
+        match self.hits.dispatch(event) {
+            Some(Hit::Click(index)) => Some(index),
+            _ => None,
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, theme: &Theme) {
+        if self.crumbs.is_empty() {
+            return;
+        }
+
+        let [crumb_style, current_style, hover_style, separator_style] = theme.extract([
+            "ui.breadcrumbs",
+            "ui.breadcrumbs.current",
+            "ui.breadcrumbs.hover",
+            "ui.breadcrumbs.separator",
+        ]);
+
+        if self.cut {
+            frame.draw(Dims(1, 0), ELLIPSIS, separator_style);
+            frame.draw(
+                Dims(1 + ELLIPSIS.width() as i32, 0),
+                SEPARATOR,
+                separator_style,
+            );
+        }
+
+        let last = self.crumbs.len() - 1;
+        for (n, (i, title, x)) in self.crumbs.iter().enumerate() {
+            let style = match n == last {
+                true => current_style,
+                false if self.hits.is_hovered(*i) => hover_style,
+                false => crumb_style,
+            };
+            frame.draw(Dims(*x, 0), title.as_str(), style);
+            if n != last {
+                let sep_x = *x + title.width() as i32;
+                frame.draw(Dims(sep_x, 0), SEPARATOR, separator_style);
+            }
+        }
+    }
+}
+
+/// First crumb shown so the trail fits the width and whether the older ones were cut off
+///
+/// The active menu is always shown, even when it's wider than the screen.
+fn fit(titled: &[(usize, String)], width: i32) -> (usize, bool) {
+    let sep = SEPARATOR.width() as i32;
+    let widths: Vec<_> = titled.iter().map(|(_, t)| t.width() as i32).collect();
+
+    let total = widths.iter().sum::<i32>() + sep * (widths.len() as i32 - 1);
+    if total <= width {
+        return (0, false);
+    }
+
+    let ellipsis = ELLIPSIS.width() as i32 + sep;
+    let mut used = ellipsis + widths[widths.len() - 1];
+    let mut start = widths.len() - 1;
+    while start > 0 && used + widths[start - 1] + sep <= width {
+        start -= 1;
+        used += widths[start] + sep;
+    }
+    (start, true)
+}
+
+pub fn breadcrumbs_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("ui.breadcrumbs", "dim")
+        .desc("Earlier menus in the trail at the top of menus")
+        .link("ui.breadcrumbs.current", "text")
+        .desc("Current menu in the trail")
+        .link("ui.breadcrumbs.hover", "highlight")
+        .desc("Earlier menu under the cursor, clicking it goes back to it")
+        .link("ui.breadcrumbs.separator", "dim")
+        .desc("Arrows between the menus in the trail");
+
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_crumbs_are_cut_off() {
+        let titled: Vec<_> = ["Main menu", "Settings", "Controls"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| (i, t.to_string()))
+            .collect();
+
+        // "Main menu ▸ Settings ▸ Controls" is 31 wide
+        assert_eq!(fit(&titled, 31), (0, false));
+        // "… ▸ Settings ▸ Controls"
+        assert_eq!(fit(&titled, 30), (1, true));
+        assert_eq!(fit(&titled, 23), (1, true));
+        assert_eq!(fit(&titled, 22), (2, true));
+        assert_eq!(fit(&titled, 3), (2, true));
+    }
+}
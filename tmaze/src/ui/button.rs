@@ -7,7 +7,7 @@ use crate::{
     settings::theme::{Style, Theme, ThemeResolver},
 };
 
-use super::Rect;
+use super::{FocusEvent, Focusable, Rect};
 
 #[derive(Debug)]
 pub struct ButtonStyles {
@@ -34,12 +34,12 @@ impl ButtonStyles {
 impl Default for ButtonStyles {
     fn default() -> Self {
         Self {
-            border: "ui_button_border",
-            highlight: "ui_button_highlight",
-            text: "ui_button_text",
+            border: "ui.button.border",
+            highlight: "ui.button.highlight",
+            text: "ui.button.text",
 
-            disabled_border: "ui_button_disabled_border",
-            disabled_text: "ui_button_disabled_text",
+            disabled_border: "ui.button.disabled.border",
+            disabled_text: "ui.button.disabled.text",
         }
     }
 }
@@ -145,10 +145,12 @@ impl Button {
     }
 
     pub fn detect_over(&self, pos: Dims) -> bool {
-        pos.0 >= self.pos.0
-            && pos.0 < self.pos.0 + self.size.0
-            && pos.1 >= self.pos.1
-            && pos.1 < self.pos.1 + self.size.1
+        self.rect().contains(pos)
+    }
+
+    /// Area of the button, for [`super::HitMap`]
+    pub fn rect(&self) -> Rect {
+        Rect::sized_at(self.pos, self.size)
     }
 
     pub fn size(&self) -> Dims {
@@ -156,6 +158,16 @@ impl Button {
     }
 }
 
+impl Focusable for Button {
+    fn focus_event(&mut self, event: FocusEvent) {
+        self.set = event == FocusEvent::Gained;
+    }
+
+    fn can_focus(&self) -> bool {
+        !self.disabled
+    }
+}
+
 struct AppliedStyles {
     normal: Style,
     content: Style,
@@ -165,10 +177,15 @@ pub fn button_theme_resolver() -> ThemeResolver {
     let mut resolver = ThemeResolver::new();
     resolver
         .link("ui.button.border", "border")
+        .desc("Borders of buttons")
         .link("ui.button.highlight", "highlight")
+        .desc("Button which is selected or hovered")
         .link("ui.button.text", "text")
+        .desc("Labels of buttons")
         .link("ui.button.disabled.border", "disabled.border")
-        .link("ui.button.disabled.text", "disabled.text");
+        .desc("Borders of buttons which can't be pressed")
+        .link("ui.button.disabled.text", "disabled.text")
+        .desc("Labels of buttons which can't be pressed");
 
     resolver
 }
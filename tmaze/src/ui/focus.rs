@@ -0,0 +1,255 @@
+use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{
+    app::event::Event,
+    helpers::is_release,
+    settings::theme::{Style, Theme, ThemeResolver},
+};
+
+/// Focus change of a single widget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusEvent {
+    Gained,
+    Lost,
+}
+
+/// Widget which can be focused, it's notified when the focus moves to or from it
+pub trait Focusable {
+    fn focus_event(&mut self, event: FocusEvent);
+
+    /// Disabled widgets are skipped when cycling the focus
+    fn can_focus(&self) -> bool {
+        true
+    }
+}
+
+/// Focus moved from one widget to another, widgets are identified by index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FocusChange {
+    pub lost: Option<usize>,
+    pub gained: Option<usize>,
+}
+
+impl FocusChange {
+    /// Events for the affected widgets, in the order they should be delivered
+    pub fn events(&self) -> impl Iterator<Item = (usize, FocusEvent)> {
+        let lost = self.lost.map(|i| (i, FocusEvent::Lost));
+        let gained = self.gained.map(|i| (i, FocusEvent::Gained));
+        lost.into_iter().chain(gained)
+    }
+
+    /// Delivers the focus events, `widgets` are in the same order as in the manager
+    pub fn dispatch(&self, widgets: &mut [&mut dyn Focusable]) {
+        for (i, event) in self.events() {
+            if let Some(widget) = widgets.get_mut(i) {
+                widget.focus_event(event);
+            }
+        }
+    }
+}
+
+/// Keeps track of the focused widget of a screen composed of several widgets
+///
+/// Tab and Shift-Tab move the focus to the next and previous widget, other
+/// events are left for the focused widget. Widgets are identified by their
+/// index, so the screen keeps them in the same order.
+#[derive(Debug, Clone)]
+pub struct FocusManager {
+    enabled: Vec<bool>,
+    focused: Option<usize>,
+}
+
+impl FocusManager {
+    /// Manager of `count` widgets, the first one is focused
+    pub fn new(count: usize) -> Self {
+        Self {
+            enabled: vec![true; count],
+            focused: (count > 0).then_some(0),
+        }
+    }
+
+    /// Manager for the widgets, respecting their [`Focusable::can_focus`]
+    pub fn from_widgets(widgets: &[&dyn Focusable]) -> Self {
+        let mut manager = Self {
+            enabled: widgets.iter().map(|w| w.can_focus()).collect(),
+            focused: None,
+        };
+        manager.focused = manager.find(0, true);
+        manager
+    }
+
+    pub fn len(&self) -> usize {
+        self.enabled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_empty()
+    }
+
+    pub fn focused(&self) -> Option<usize> {
+        self.focused
+    }
+
+    pub fn is_focused(&self, index: usize) -> bool {
+        self.focused == Some(index)
+    }
+
+    /// Enables or disables a widget, disabled focused widget loses the focus
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> Option<FocusChange> {
+        self.enabled[index] = enabled;
+
+        if !enabled && self.is_focused(index) {
+            return self.focus_next();
+        }
+
+        if enabled && self.focused.is_none() {
+            return self.focus(index);
+        }
+
+        None
+    }
+
+    /// Moves the focus to the widget, if it's enabled
+    pub fn focus(&mut self, index: usize) -> Option<FocusChange> {
+        if !self.enabled.get(index).copied().unwrap_or(false) {
+            return None;
+        }
+
+        self.change(Some(index))
+    }
+
+    pub fn focus_next(&mut self) -> Option<FocusChange> {
+        let from = self.focused.map_or(0, |i| i + 1);
+        let target = self.find(from, true);
+        self.change(target)
+    }
+
+    pub fn focus_prev(&mut self) -> Option<FocusChange> {
+        let len = self.len();
+        let from = self.focused.map_or(len.saturating_sub(1), |i| i + len - 1);
+        let target = self.find(from, false);
+        self.change(target)
+    }
+
+    /// Handles the focus keys, returns the events which were not consumed
+    ///
+    /// Focus changes are returned as well, so they can be dispatched to the widgets.
+    pub fn handle_events(&mut self, events: Vec<Event>) -> (Vec<Event>, Vec<FocusChange>) {
+        let mut rest = Vec::with_capacity(events.len());
+        let mut changes = vec![];
+
+        for event in events {
+            let change = match event {
+                Event::Term(TermEvent::Key(KeyEvent {
+                    code,
+                    kind,
+                    modifiers,
+                    ..
+                })) if !is_release(kind) => match code {
+                    KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => {
+                        Some(self.focus_prev())
+                    }
+                    KeyCode::Tab => Some(self.focus_next()),
+                    KeyCode::BackTab => Some(self.focus_prev()),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match change {
+                Some(change) => changes.extend(change),
+                None => rest.push(event),
+            }
+        }
+
+        (rest, changes)
+    }
+
+    /// First enabled widget from the index, wrapping around
+    fn find(&self, from: usize, forward: bool) -> Option<usize> {
+        let len = self.len();
+        (0..len)
+            .map(|offset| match forward {
+                true => (from + offset) % len,
+                false => (from + len * 2 - offset) % len,
+            })
+            .find(|&i| self.enabled[i])
+    }
+
+    fn change(&mut self, target: Option<usize>) -> Option<FocusChange> {
+        if target == self.focused {
+            return None;
+        }
+
+        let lost = std::mem::replace(&mut self.focused, target);
+        Some(FocusChange {
+            lost,
+            gained: target,
+        })
+    }
+}
+
+/// Style of a widget, replaced by the focus highlight if it's focused
+pub fn focus_style(theme: &Theme, style: Style, focused: bool) -> Style {
+    if focused {
+        theme["ui.focus"]
+    } else {
+        style
+    }
+}
+
+pub fn focus_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+    resolver
+        .link("ui.focus", "highlight")
+        .desc("Outline of the focused widget");
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tab(shift: bool) -> Event {
+        let modifiers = match shift {
+            true => KeyModifiers::SHIFT,
+            false => KeyModifiers::NONE,
+        };
+        Event::Term(TermEvent::Key(KeyEvent::new(KeyCode::Tab, modifiers)))
+    }
+
+    #[test]
+    fn cycles_and_skips_disabled() {
+        let mut focus = FocusManager::new(3);
+        focus.set_enabled(1, false);
+
+        let (rest, changes) = focus.handle_events(vec![tab(false)]);
+        assert!(rest.is_empty());
+        assert_eq!(
+            changes,
+            [FocusChange {
+                lost: Some(0),
+                gained: Some(2)
+            }]
+        );
+
+        focus.handle_events(vec![tab(false)]);
+        assert_eq!(focus.focused(), Some(0));
+
+        focus.handle_events(vec![tab(true)]);
+        assert_eq!(focus.focused(), Some(2));
+    }
+
+    #[test]
+    fn disabling_focused_moves_focus() {
+        let mut focus = FocusManager::new(2);
+        focus.set_enabled(0, false);
+        assert_eq!(focus.focused(), Some(1));
+
+        focus.set_enabled(1, false);
+        assert_eq!(focus.focused(), None);
+
+        focus.set_enabled(0, true);
+        assert_eq!(focus.focused(), Some(0));
+    }
+}
@@ -0,0 +1,127 @@
+//! One line with the key hints of the active activity, at the bottom of the screen
+//!
+//! Activities declare their hints with [`crate::app::ActivityHandler::hints`], the most
+//! relevant first, the ones which don't fit are left out.
+
+use std::borrow::Cow;
+
+use cmaze::dims::Dims;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+};
+
+/// Smallest screen the footer is shown on, it would cover too much of smaller ones
+pub const FOOTER_MIN_SIZE: Dims = Dims(40, 16);
+
+/// Space between the hints
+const GAP: i32 = 3;
+
+/// Key and what it does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hint {
+    pub key: Cow<'static, str>,
+    pub action: &'static str,
+}
+
+impl Hint {
+    pub const fn new(key: &'static str, action: &'static str) -> Self {
+        Self {
+            key: Cow::Borrowed(key),
+            action,
+        }
+    }
+
+    /// Hint with a key which is not known in advance, like a rebindable one
+    pub fn owned(key: String, action: &'static str) -> Self {
+        Self {
+            key: Cow::Owned(key),
+            action,
+        }
+    }
+
+    fn width(&self) -> i32 {
+        (self.key.width() + 1 + self.action.width()) as i32
+    }
+}
+
+/// Hints of the [`super::Menu`] and activities built around it
+pub const MENU_HINTS: [Hint; 3] = [
+    Hint::new("↑↓", "select"),
+    Hint::new("Enter", "confirm"),
+    Hint::new("Esc", "back"),
+];
+
+/// Positions of the hints which fit on the line of the width
+fn layout(hints: &[Hint], width: i32) -> Vec<(i32, &Hint)> {
+    let mut x = 1;
+    let mut placed = vec![];
+    for hint in hints {
+        if x + hint.width() > width - 1 {
+            break;
+        }
+        placed.push((x, hint));
+        x += hint.width() + GAP;
+    }
+    placed
+}
+
+/// Draws the hints over the last line of the frame, nothing on tiny screens
+pub fn draw_footer(frame: &mut Frame, hints: &[Hint], theme: &Theme) {
+    if hints.is_empty() || frame.size.0 < FOOTER_MIN_SIZE.0 || frame.size.1 < FOOTER_MIN_SIZE.1 {
+        return;
+    }
+
+    let [key_style, text_style] = theme.extract(["ui.footer.key", "ui.footer.text"]);
+
+    let y = frame.size.1 - 1;
+    frame.fill_rect(
+        Dims(0, y),
+        Dims(frame.size.0, 1),
+        Cell::styled(' ', text_style),
+    );
+
+    for (x, hint) in layout(hints, frame.size.0) {
+        frame.draw(Dims(x, y), hint.key.as_ref(), key_style);
+        let action_x = x + hint.key.width() as i32 + 1;
+        frame.draw(Dims(action_x, y), hint.action, text_style);
+    }
+}
+
+pub fn footer_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("ui.footer.key", "highlight")
+        .desc("Keys in the footer with the controls")
+        .link("ui.footer.text", "dim")
+        .desc("Actions of the keys in the footer");
+
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hints_which_dont_fit_are_left_out() {
+        let hints = [
+            Hint::new("Esc", "pause"),
+            Hint::new("H", "hint"),
+            Hint::new("Space", "spectator mode"),
+        ];
+
+        let positions = |width| {
+            layout(&hints, width)
+                .into_iter()
+                .map(|(x, _)| x)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(40), [1, 13]);
+        assert_eq!(positions(43), [1, 13, 22]);
+        assert_eq!(positions(5), [] as [i32; 0]);
+    }
+}
@@ -2,19 +2,32 @@ use std::time::Duration;
 
 use cmaze::dims::Dims;
 use crossterm::style::{Attribute, Color, ContentStyle};
+use unicode_width::UnicodeWidthStr;
 
-use crate::{
-    helpers::{self, strings::multisize_string},
-    renderer::helpers::term_size,
-};
+use crate::helpers::{self, strings::multisize_string};
 
-pub fn center_box_in_screen(box_dims: Dims) -> Dims {
-    let size_u16 = term_size();
-    helpers::box_center(
-        Dims(0, 0),
-        Dims(size_u16.0 as i32, size_u16.1 as i32),
-        box_dims,
-    )
+/// Words of the text split into lines of the width, longer words get a line of their own
+pub fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let line = lines.last_mut().unwrap();
+        if !line.is_empty() && line.width() + word.width() + 1 > width {
+            lines.push(word.to_string());
+        } else {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+    }
+    lines
+}
+
+/// Position of the box in the middle of the screen, boxes bigger than the screen start at its
+/// edge, so their beginning is always visible
+pub fn center_box_in_screen(box_dims: Dims, screen_size: Dims) -> Dims {
+    let Dims(x, y) = helpers::box_center(Dims(0, 0), screen_size, box_dims);
+    Dims(x.max(0), y.max(0))
 }
 
 pub fn multisize_duration_format(dur: Duration, max_size: usize) -> String {
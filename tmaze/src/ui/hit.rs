@@ -0,0 +1,137 @@
+//! Hit-testing of the mouse events
+//!
+//! Widgets register their interactive regions with an id whenever their layout changes,
+//! [`HitMap::dispatch`] then resolves a mouse event to the id of the region under the cursor
+//! and keeps track of the hovered one, so every widget highlights on hover the same way.
+
+use cmaze::dims::Dims;
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+
+use super::Rect;
+
+/// Mouse event resolved to a region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit<Id> {
+    /// Cursor moved over the region
+    Hover(Id),
+    /// Region was clicked with the left button
+    Click(Id),
+}
+
+/// Interactive regions of a widget, in screen coordinates
+#[derive(Debug, Clone)]
+pub struct HitMap<Id> {
+    regions: Vec<(Rect, Id)>,
+    hovered: Option<Id>,
+}
+
+impl<Id> Default for HitMap<Id> {
+    fn default() -> Self {
+        Self {
+            regions: vec![],
+            hovered: None,
+        }
+    }
+}
+
+impl<Id: Copy + PartialEq> HitMap<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all regions, before they are registered again with the new layout
+    ///
+    /// Hovered region is kept, so the highlight doesn't blink while the layout is rebuilt.
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// Removes all regions and the hovered one, when the old layout can't be used anymore
+    pub fn reset(&mut self) {
+        self.regions.clear();
+        self.hovered = None;
+    }
+
+    /// Adds a region, regions registered later are on top of the earlier ones
+    pub fn register(&mut self, rect: Rect, id: Id) {
+        self.regions.push((rect, id));
+    }
+
+    /// Topmost region at the position
+    pub fn at(&self, pos: Dims) -> Option<Id> {
+        self.regions
+            .iter()
+            .rev()
+            .find(|(rect, _)| rect.contains(pos))
+            .map(|(_, id)| *id)
+    }
+
+    /// Region under the cursor, from the last mouse move or click
+    pub fn hovered(&self) -> Option<Id> {
+        self.hovered
+    }
+
+    pub fn is_hovered(&self, id: Id) -> bool {
+        self.hovered == Some(id)
+    }
+
+    /// Resolves moves and left clicks, other mouse events are ignored
+    pub fn dispatch(&mut self, event: &MouseEvent) -> Option<Hit<Id>> {
+        let id = self.at(Dims(event.column as i32, event.row as i32));
+
+        match event.kind {
+            MouseEventKind::Moved | MouseEventKind::Drag(MouseButton::Left) => {
+                self.hovered = id;
+                id.map(Hit::Hover)
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.hovered = id;
+                id.map(Hit::Click)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn topmost_region_wins_and_hover_is_tracked() {
+        let mut hits = HitMap::new();
+        hits.register(Rect::sized_at(Dims(0, 0), Dims(10, 5)), 'a');
+        hits.register(Rect::sized_at(Dims(4, 2), Dims(3, 1)), 'b');
+
+        assert_eq!(hits.at(Dims(4, 2)), Some('b'));
+        assert_eq!(hits.at(Dims(9, 4)), Some('a'));
+        assert_eq!(hits.at(Dims(10, 4)), None);
+
+        let moved = mouse(MouseEventKind::Moved, 5, 2);
+        assert_eq!(hits.dispatch(&moved), Some(Hit::Hover('b')));
+        assert!(hits.is_hovered('b'));
+
+        let scroll = mouse(MouseEventKind::ScrollDown, 1, 1);
+        assert_eq!(hits.dispatch(&scroll), None);
+        assert!(hits.is_hovered('b'));
+
+        let click = mouse(MouseEventKind::Up(MouseButton::Left), 1, 1);
+        assert_eq!(hits.dispatch(&click), Some(Hit::Click('a')));
+        assert_eq!(hits.hovered(), Some('a'));
+
+        let outside = mouse(MouseEventKind::Moved, 20, 20);
+        assert_eq!(hits.dispatch(&outside), None);
+        assert_eq!(hits.hovered(), None);
+    }
+}
@@ -0,0 +1,131 @@
+use cmaze::dims::Dims;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    helpers::is_release,
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+};
+
+use super::{FocusEvent, Focusable};
+
+/// Single line text field
+#[derive(Debug, Clone, Default)]
+pub struct TextInput {
+    value: String,
+    /// Cursor position, in chars
+    cursor: usize,
+    pub placeholder: String,
+    pub focused: bool,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self {
+            focused: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn set_value(&mut self, value: impl Into<String>) {
+        self.value = value.into();
+        self.cursor = self.value.chars().count();
+    }
+
+    /// Edits the text, returns whether the key was used by the input
+    pub fn handle_key(&mut self, event: KeyEvent) -> bool {
+        if is_release(event.kind) {
+            return false;
+        }
+
+        let len = self.value.chars().count();
+        match event.code {
+            KeyCode::Char('u') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.set_value("");
+            }
+            KeyCode::Char(ch) if !event.modifiers.contains(KeyModifiers::CONTROL) => {
+                let at = self.byte_index(self.cursor);
+                self.value.insert(at, ch);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let at = self.byte_index(self.cursor);
+                self.value.remove(at);
+            }
+            KeyCode::Delete if self.cursor < len => {
+                let at = self.byte_index(self.cursor);
+                self.value.remove(at);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Draws the field, text scrolls so the cursor is always visible
+    pub fn draw(&self, frame: &mut Frame, theme: &Theme, pos: Dims, width: i32) {
+        let width = width.max(1) as usize;
+        frame.fill_rect(
+            pos,
+            Dims(width as i32, 1),
+            Cell::styled(' ', theme["ui.input.text"]),
+        );
+
+        if self.value.is_empty() {
+            let placeholder: String = self.placeholder.chars().take(width - 1).collect();
+            frame.draw(pos + Dims(1, 0), placeholder, theme["ui.input.placeholder"]);
+        } else {
+            let skip = (self.cursor + 1).saturating_sub(width);
+            let visible: String = self.value.chars().skip(skip).take(width).collect();
+            frame.draw(pos, visible, theme["ui.input.text"]);
+        }
+
+        if self.focused {
+            let before: String = self.value.chars().take(self.cursor).collect();
+            let skip = (self.cursor + 1).saturating_sub(width);
+            let x = before.width().saturating_sub(skip) as i32;
+            let under = self.value.chars().nth(self.cursor).unwrap_or(' ');
+            frame.draw(pos + Dims(x, 0), under, theme["ui.input.cursor"].invert());
+        }
+    }
+
+    fn byte_index(&self, chars: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(chars)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+}
+
+impl Focusable for TextInput {
+    fn focus_event(&mut self, event: FocusEvent) {
+        self.focused = event == FocusEvent::Gained;
+    }
+}
+
+pub fn input_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+    resolver
+        .link("ui.input.text", "text")
+        .desc("Text typed into input fields")
+        .link("ui.input.placeholder", "dim")
+        .desc("Placeholder of an empty input field")
+        .link("ui.input.cursor", "ui.input.text")
+        .desc("Cursor of an input field");
+    resolver
+}
@@ -21,11 +21,19 @@ use crate::{
         event::Event,
     },
     helpers::{is_release, strings::MbyStaticStr, LineDir},
-    renderer::Frame,
-    settings::theme::{Style, Theme, ThemeResolver},
+    renderer::{Cell, Frame},
+    settings::{
+        keymap::Action,
+        theme::{Style, Theme, ThemeResolver},
+    },
 };
 
-use super::{center_box_in_screen, draw_box, Rect, Screen};
+use super::{center_box_in_screen, draw_box, Button, Hint, Hit, HitMap, Rect, Screen, MENU_HINTS};
+
+/// Size of the buttons under a menu, they get wider with the menu
+const BUTTON_SIZE: Dims = Dims(10, 3);
+/// Menus aren't made narrower than this on small screens
+const MIN_WIDTH: i32 = 12;
 
 pub fn panic_on_menu_push() -> ! {
     panic!("menu should only be popping itself or staying");
@@ -89,7 +97,7 @@ impl MenuItem {
 
     // TODO: same as Display, make it get a buffer and write to it,
     // so we don't allocate a new string every time
-    fn render(&self, width: usize) -> Cow<str> {
+    fn render(&self, width: usize) -> Cow<'_, str> {
         match self {
             MenuItem::Text(text) => text.as_ref_cow(),
             MenuItem::Option(OptionDef { text, val, .. }) => {
@@ -296,10 +304,26 @@ struct AppliedStyles {
     number: Style,
 }
 
+/// Button under a menu, they are shown with the dpad, so menus can be used only with a mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuButton {
+    Select,
+    Back,
+}
+
 pub struct Menu {
     config: MenuConfig,
     selected: usize, // isize for more readable code
-    items_pos: Option<Rect>,
+    /// Items by their index, separators can't be clicked
+    hits: HitMap<usize>,
+    buttons: Vec<(MenuButton, Button)>,
+    button_hits: HitMap<MenuButton>,
+    /// First shown item, when they don't fit the screen
+    scroll: usize,
+    /// Number of shown items, from the last layout
+    visible: usize,
+    /// Last description of the selected item, see [`AppData::narrate`]
+    narrated: Option<String>,
 }
 
 impl Menu {
@@ -307,14 +331,54 @@ impl Menu {
         let MenuConfig { options, .. } = &config;
 
         let default = config.default.unwrap_or(0).clamp(0, options.len() - 1);
+        let visible = options.len();
 
         Self {
             selected: default,
             config,
-            items_pos: None,
+            hits: HitMap::new(),
+            buttons: vec![],
+            button_hits: HitMap::new(),
+            scroll: 0,
+            visible,
+            narrated: None,
+        }
+    }
+
+    /// Says which item is selected when it or its value changed, in the accessibility mode
+    fn narrate(&mut self, app_data: &mut AppData) {
+        let item = match &self.config.options[self.selected] {
+            MenuItem::Text(text) => text.to_string(),
+            MenuItem::Option(OptionDef { text, val, .. }) => {
+                format!("{}, {}", text, if *val { "on" } else { "off" })
+            }
+            MenuItem::Slider(SliderDef { text, val, .. }) => format!("{}, {}", text, val),
+            MenuItem::Separator => return,
+        };
+        let is_item = |opt: &&MenuItem| !matches!(opt, MenuItem::Separator);
+        let position = self.config.options[..=self.selected]
+            .iter()
+            .filter(is_item)
+            .count();
+        let total = self.config.options.iter().filter(is_item).count();
+
+        let text = format!("{}: {}, {} of {}", self.config.title, item, position, total);
+        if self.narrated.as_ref() != Some(&text) {
+            app_data.narrate(text.clone());
+            self.narrated = Some(text);
         }
     }
 
+    /// Index of the selected item
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Replaces the lines under the title, like the ones which change while it's shown
+    pub fn set_subtitles(&mut self, subtitles: Vec<String>) {
+        self.config.subtitles = subtitles;
+    }
+
     pub fn into_activity(self) -> Activity {
         Activity::new("tmaze", "menu", Box::new(self))
     }
@@ -335,6 +399,21 @@ impl Menu {
                 break;
             }
         }
+
+        self.scroll_to_selected();
+    }
+
+    /// Scrolls the items so the selected one is shown
+    fn scroll_to_selected(&mut self) {
+        let visible = self.visible.max(1);
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible {
+            self.scroll = self.selected + 1 - visible;
+        }
+        self.scroll = self
+            .scroll
+            .min(self.config.options.len().saturating_sub(visible));
     }
 
     fn switch(&mut self, data: &mut AppData) -> Option<Change> {
@@ -349,6 +428,25 @@ impl Menu {
         None
     }
 
+    fn layout_buttons(&mut self, dims: &MenuDimenstions) {
+        self.buttons.clear();
+        self.button_hits.clear();
+        let Some(pos) = dims.buttons_pos else {
+            return;
+        };
+
+        let size = Dims((dims.size.0 / 2).max(BUTTON_SIZE.0), BUTTON_SIZE.1);
+        for (kind, text, x) in [
+            (MenuButton::Select, "Select", 0),
+            (MenuButton::Back, "Back", size.0),
+        ] {
+            let button = Button::new(text.to_string(), pos + Dims(x, 0), size)
+                .set(self.button_hits.is_hovered(kind));
+            self.button_hits.register(button.rect(), kind);
+            self.buttons.push((kind, button));
+        }
+    }
+
     fn update_slider(&mut self, right: bool, data: &mut AppData) {
         if let MenuItem::Slider(SliderDef {
             val, range, fun, ..
@@ -358,24 +456,6 @@ impl Menu {
             *val = (*val).clamp(*range.start(), *range.end());
         }
     }
-
-    fn get_opt_by_mouse_pos(&self, Dims(x, y): Dims) -> Option<usize> {
-        let Rect { start, end } = self.items_pos?;
-        let size = end - start;
-
-        // TODO: check using ranges instead
-        if y < start.1 || y > start.1 + size.1 || x < start.0 || x > start.0 + size.0 {
-            return None;
-        }
-
-        let selected = (y - start.1) as usize;
-
-        if matches!(self.config.options[selected], MenuItem::Separator) {
-            return None;
-        }
-
-        Some(selected)
-    }
 }
 
 impl ActivityHandler for Menu {
@@ -410,17 +490,44 @@ impl ActivityHandler for Menu {
             };
         }
 
-        let dims = MenuDimenstions::calc(&self.config);
-        self.items_pos = Some(Rect::sized_at(dims.items_pos, dims.items_size));
+        let settings = &app_data.settings;
+        let show_buttons = settings.get_enable_dpad() && settings.get_enable_mouse();
+        let dims = MenuDimenstions::calc(&self.config, app_data.screen_size, show_buttons);
+        self.visible = dims.visible;
+        self.scroll_to_selected();
+        self.layout_buttons(&dims);
+        self.hits.clear();
+        let shown = self.scroll..self.scroll + self.visible;
+        for (row, i) in shown.enumerate() {
+            if !matches!(self.config.options[i], MenuItem::Separator) {
+                let pos = dims.items_pos + Dims(0, row as i32);
+                self.hits
+                    .register(Rect::sized_at(pos, Dims(dims.items_size.0, 1)), i);
+            }
+        }
+
+        let keymap = app_data.settings.get_keymap();
 
         for event in events {
             match event {
-                Event::Term(TermEvent::Key(KeyEvent { code, kind, .. })) if !is_release(kind) => {
+                Event::Term(TermEvent::Key(key @ KeyEvent { code, kind, .. }))
+                    if !is_release(kind) =>
+                {
+                    // arrows, Enter and Esc always work, so a menu can't be made unusable
+                    let code = match keymap.action(&key) {
+                        Some(Action::MoveUp) => KeyCode::Up,
+                        Some(Action::MoveDown) => KeyCode::Down,
+                        Some(Action::MoveLeft) => KeyCode::Left,
+                        Some(Action::MoveRight) => KeyCode::Right,
+                        Some(Action::Pause) => KeyCode::Esc,
+                        _ => code,
+                    };
+
                     match code {
-                        KeyCode::Up | KeyCode::Char('w') => {
+                        KeyCode::Up => {
                             self.select(false);
                         }
-                        KeyCode::Down | KeyCode::Char('s') => {
+                        KeyCode::Down => {
                             self.select(true);
                         }
                         KeyCode::Enter | KeyCode::Char(' ') => {
@@ -436,6 +543,7 @@ impl ActivityHandler for Menu {
                             let old_sel = self.selected;
                             self.selected =
                                 (ch as isize - '1' as isize).clamp(0, opt_count - 1) as usize;
+                            self.scroll_to_selected();
 
                             if old_sel == self.selected {
                                 return_if_some!(self.switch(app_data));
@@ -451,19 +559,27 @@ impl ActivityHandler for Menu {
                         _ => {}
                     }
                 }
-                Event::Term(TermEvent::Mouse(MouseEvent {
-                    kind,
-                    column,
-                    row,
-                    modifiers,
-                })) => {
-                    let mouse_pos = (column, row).into();
-                    match kind {
-                        MouseEventKind::Moved => {
-                            if let Some(selected) = self.get_opt_by_mouse_pos(mouse_pos) {
-                                self.selected = selected;
+                Event::Term(TermEvent::Mouse(
+                    event @ MouseEvent {
+                        kind, modifiers, ..
+                    },
+                )) => {
+                    if let Some(hit) = self.button_hits.dispatch(&event) {
+                        match hit {
+                            Hit::Click(MenuButton::Select) => {
+                                return_if_some!(self.switch(app_data))
                             }
+                            Hit::Click(MenuButton::Back) => return Some(Change::pop_top()),
+                            Hit::Hover(_) => {}
                         }
+                        continue;
+                    }
+
+                    if let Some(Hit::Hover(i) | Hit::Click(i)) = self.hits.dispatch(&event) {
+                        self.selected = i;
+                    }
+
+                    match kind {
                         MouseEventKind::ScrollDown => {
                             if modifiers.contains(KeyModifiers::CONTROL) {
                                 self.update_slider(false, app_data);
@@ -479,9 +595,6 @@ impl ActivityHandler for Menu {
                             }
                         }
                         MouseEventKind::Up(MouseButton::Left) => {
-                            if let Some(selected) = self.get_opt_by_mouse_pos(mouse_pos) {
-                                self.selected = selected;
-                            }
                             return_if_some!(self.switch(app_data));
                         }
 
@@ -499,12 +612,29 @@ impl ActivityHandler for Menu {
             }
         }
 
+        self.narrate(app_data);
+
         None
     }
 
     fn screen(&self) -> &dyn Screen {
         self
     }
+
+    fn relayout(&mut self, _size: Dims) {
+        // items and buttons moved, the cursor isn't over the same ones anymore
+        self.hits.reset();
+        self.button_hits.reset();
+        self.buttons.clear();
+    }
+
+    fn title(&self) -> Option<&str> {
+        Some(&self.config.title)
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        MENU_HINTS.to_vec()
+    }
 }
 
 impl Screen for Menu {
@@ -522,6 +652,7 @@ impl Screen for Menu {
         } = self.config.styles.apply(theme);
 
         let MenuDimenstions {
+            pos,
             size,
             title_pos,
             items_pos,
@@ -530,44 +661,64 @@ impl Screen for Menu {
             count_pos,
             item_text_pos,
             item_text_len,
-        } = MenuDimenstions::calc(&self.config);
-
-        let max_item_width = size.0 as usize - 2 - self.config.special_width();
-
-        let options = self
-            .config
-            .options
-            .iter()
-            .map(|opt| opt.render(max_item_width))
-            .collect::<Vec<_>>();
-
-        let pos = center_box_in_screen(size);
-        let opt_count = options.len();
+            item_width,
+            visible,
+            buttons_pos: _,
+        } = MenuDimenstions::calc(&self.config, frame.size, !self.buttons.is_empty());
 
+        let opt_count = self.config.options.len();
         let max_count = opt_count.to_string().len();
+        let inner_width = (size.0 - 2).max(0) as usize;
+
+        // texts wider than a menu cut to the screen are cut too
+        let fit = |text: &str, width: usize| match text.width() > width {
+            true => text.with_exact_width(width),
+            false => text.to_string(),
+        };
 
+        // opaque, so it can be drawn over other content
+        frame.fill_rect(pos, size, Cell::styled(' ', theme["background"]));
         draw_box(frame, pos, size, border_style);
 
-        frame.draw(title_pos, title.as_str(), title_style);
+        frame.draw(
+            title_pos,
+            fit(title, inner_width.saturating_sub(2)),
+            title_style,
+        );
 
         for (i, subtitle) in self.config.subtitles.iter().enumerate() {
             frame.draw(
                 subtitles_pos + Dims(0, i as i32),
-                subtitle.as_str(),
+                fit(subtitle, inner_width.saturating_sub(2)),
                 subtitle_style,
             );
         }
 
         frame.draw(
             items_pos - Dims(0, 1),
-            LineDir::Horizontal
-                .round()
-                .to_string()
-                .repeat(size.0 as usize - 2),
+            LineDir::Horizontal.round().to_string().repeat(inner_width),
             separator_style,
         );
 
-        for (i, option) in options.iter().enumerate() {
+        // items are scrolled when the screen is too short for them
+        let scroll = self.scroll.min(opt_count - visible);
+        let arrow_x = pos.0 + size.0 - 3;
+        if scroll > 0 {
+            frame.draw(Dims(arrow_x, items_pos.1 - 1), '▲', separator_style);
+        }
+        if scroll + visible < opt_count {
+            frame.draw(
+                Dims(arrow_x, items_pos.1 + visible as i32),
+                '▼',
+                separator_style,
+            );
+        }
+
+        let shown = self.config.options.iter().enumerate();
+        for (row, (i, option)) in shown.skip(scroll).take(visible).enumerate() {
+            let option = option.render(item_width);
+            let row = row as i32;
+
             let prep_style = |style: Style| {
                 if i == self.selected {
                     style.invert()
@@ -578,39 +729,36 @@ impl Screen for Menu {
 
             // selector
             if i == self.selected {
-                frame.draw(
-                    items_pos + Dims(0, i as i32),
-                    "> ",
-                    prep_style(selector_style),
-                );
+                frame.draw(items_pos + Dims(0, row), "> ", prep_style(selector_style));
             } else {
-                frame.draw(
-                    items_pos + Dims(0, i as i32),
-                    "  ",
-                    prep_style(selector_style),
-                );
+                frame.draw(items_pos + Dims(0, row), "  ", prep_style(selector_style));
             }
 
             if *counted {
                 frame.draw(
-                    count_pos.unwrap() + Dims(0, i as i32),
+                    count_pos.unwrap() + Dims(0, row),
                     format!("{:width$}. ", i + 1, width = max_count),
                     prep_style(number_style),
                 );
             }
 
             frame.draw(
-                item_text_pos + Dims(0, i as i32),
-                option.as_ref().pad_to_width(item_text_len),
+                item_text_pos + Dims(0, row),
+                option.as_ref().with_exact_width(item_text_len),
                 prep_style(text_style),
             );
         }
 
+        for (_, button) in &self.buttons {
+            button.draw_colored(frame, theme);
+        }
+
         Ok(())
     }
 }
 
 struct MenuDimenstions {
+    pos: Dims,
     size: Dims,
     title_pos: Dims,
     items_pos: Dims,
@@ -619,10 +767,16 @@ struct MenuDimenstions {
     count_pos: Option<Dims>,
     item_text_pos: Dims,
     item_text_len: usize,
+    /// Width the items are rendered at, before they are cut to the menu
+    item_width: usize,
+    /// Number of items shown at once, the rest is scrolled to
+    visible: usize,
+    /// Position of the buttons under the menu, if they are shown
+    buttons_pos: Option<Dims>,
 }
 
 impl MenuDimenstions {
-    fn calc(config: &MenuConfig) -> Self {
+    fn calc(config: &MenuConfig, screen_size: Dims, buttons: bool) -> Self {
         let menu_size = {
             let special = config.special_width();
 
@@ -648,8 +802,21 @@ impl MenuDimenstions {
 
             Dims(width as i32, height as i32)
         };
+        let item_width = menu_size.0 as usize - 2 - config.special_width();
+
+        // menus bigger than the screen are cut to it and their items are scrolled
+        let buttons_height = if buttons { BUTTON_SIZE.1 } else { 0 };
+        let chrome = menu_size.1 - config.options.len() as i32;
+        let visible = (screen_size.1 - chrome - buttons_height)
+            .max(1)
+            .min(config.options.len() as i32);
+        let menu_size = Dims(
+            menu_size.0.min(screen_size.0.max(MIN_WIDTH)),
+            chrome + visible,
+        );
 
-        let pos = center_box_in_screen(menu_size);
+        let pos = center_box_in_screen(menu_size + Dims(0, buttons_height), screen_size);
+        let buttons_width = (menu_size.0 / 2).max(BUTTON_SIZE.0) * 2;
 
         let items_pos = Dims(pos.0 + 1, pos.1 + config.subtitles.len() as i32 + 3);
 
@@ -657,7 +824,7 @@ impl MenuDimenstions {
 
         let count_pos = if config.counted {
             let max_count = config.options.len().to_string().len();
-            item_text_len -= max_count + 2;
+            item_text_len = item_text_len.saturating_sub(max_count + 2);
             Some(Dims(items_pos.0 + 2, items_pos.1))
         } else {
             None
@@ -669,14 +836,19 @@ impl MenuDimenstions {
         });
 
         Self {
+            pos,
             size: menu_size,
             title_pos: pos + Dims(3, 1),
             items_pos,
-            items_size: Dims(menu_size.0 - 2, config.options.len() as i32),
+            items_size: Dims(menu_size.0 - 2, visible),
             subtitles_pos: pos + Dims(2, 2),
             count_pos,
             item_text_pos,
             item_text_len,
+            item_width,
+            visible: visible as usize,
+            buttons_pos: buttons
+                .then_some(pos + Dims((menu_size.0 - buttons_width) / 2, menu_size.1)),
         }
     }
 }
@@ -690,7 +862,7 @@ macro_rules! menu_actions {
             let opts: Vec<(_, MenuAction<_>)> = vec![
                 $(
                     $(#[cfg(feature = $feature)])?
-                    { ($crate::ui::menu::MenuItem::from($name), Box::new(|$data: &mut AppData| $action)) },
+                    { ($crate::ui::menu::MenuItem::from($name), Box::new(move |$data: &mut AppData| $action)) },
                 )*
             ];
 
@@ -710,12 +882,62 @@ pub fn menu_theme_resolver() -> ThemeResolver {
 
     resolver
         .link("ui_menu_border", "border")
+        .desc("Border of menus")
         .link("ui_menu_text", "text")
+        .desc("Options of menus")
         .link("ui_menu_title", "ui_menu_text")
+        .desc("Title of a menu")
         .link("ui_menu_subtitle", "ui_menu_text")
+        .desc("Subtitle under the title of a menu")
         .link("ui_menu_separator", "ui_menu_border")
+        .desc("Line between the title and the options of a menu")
         .link("ui_menu_selector", "ui_menu_text")
-        .link("ui_menu_number", "ui_menu_text");
+        .desc("Marker of the selected option of a menu")
+        .link("ui_menu_number", "ui_menu_text")
+        .desc("Numbers of the options of a menu");
 
     resolver
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::app::app::App;
+
+    use super::*;
+
+    fn resize(size: Dims) -> Event {
+        Event::Term(TermEvent::Resize(size.0 as u16, size.1 as u16))
+    }
+
+    fn key(code: KeyCode) -> Event {
+        Event::Term(TermEvent::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    #[test]
+    fn menu_fits_resized_screen() {
+        let options: Vec<_> = (1..=20).map(|i| format!("Option {}", i)).collect();
+        let menu = Menu::new(MenuConfig::new_from_strings("Long menu", options));
+        let mut app = App::new_headless_default(menu.into_activity(), Dims(40, 30));
+
+        app.run_headless([], 1);
+        let frame = app.last_frame().to_string();
+        assert!(frame.contains("Option 1 ") && frame.contains("Option 20"));
+
+        // items are scrolled, the title stays
+        app.run_headless([resize(Dims(40, 14))], 1);
+        let frame = app.last_frame().to_string();
+        assert!(frame.contains("Long menu") && frame.contains("Option 1 "));
+        assert!(!frame.contains("Option 20"), "{}", frame);
+
+        // selection wraps around to the last item, which is scrolled to
+        app.run_headless([key(KeyCode::Up)], 1);
+        let frame = app.last_frame().to_string();
+        assert!(frame.contains("Option 20") && !frame.contains("Option 1 "));
+
+        // narrower than the menu, so the box is cut
+        app.run_headless([resize(Dims(14, 30))], 1);
+        let frame = app.last_frame().to_string();
+        assert!(frame.contains("Option 1 ") && frame.contains("Option 20"));
+        assert!(frame.lines().all(|line| line.width() <= 14));
+    }
+}
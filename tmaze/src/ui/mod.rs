@@ -6,22 +6,36 @@ use crate::{
     settings::theme::{Theme, ThemeResolver},
 };
 
+pub mod breadcrumbs;
 pub mod button;
 pub mod draw_fn;
+pub mod focus;
+pub mod footer;
 pub mod helpers;
+pub mod hit;
+pub mod input;
 pub mod menu;
 pub mod popup;
 pub mod progressbar;
 pub mod rect;
+pub mod toast;
+pub mod tween;
 pub mod usecase;
 
+pub use breadcrumbs::Breadcrumbs;
 pub use button::*;
 pub use draw_fn::*;
+pub use focus::*;
+pub use footer::{draw_footer, Hint, MENU_HINTS};
 pub use helpers::*;
+pub use hit::{Hit, HitMap};
+pub use input::*;
 pub use menu::*;
 pub use popup::*;
 pub use progressbar::*;
 pub use rect::*;
+pub use toast::Toasts;
+pub use tween::{Easing, Tween};
 
 pub trait Screen {
     fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()>;
@@ -32,15 +46,25 @@ pub fn theme_resolver() -> ThemeResolver {
 
     resolver
         .link("text", "") // "" is same as "default"
+        .desc("Plain text of the whole interface")
         .link("border", "")
+        .desc("Borders of boxes and other frames")
         .link("highlight", "")
+        .desc("Highlighted text, like selected items")
         .link("background", "") // TODO: use
+        .desc("Background of the whole screen")
         .link("dim", "")
+        .desc("Less important text, like hints")
+        .extend(breadcrumbs::breadcrumbs_theme_resolver())
         .extend(button::button_theme_resolver())
+        .extend(focus::focus_theme_resolver())
+        .extend(footer::footer_theme_resolver())
+        .extend(input::input_theme_resolver())
         .extend(menu::menu_theme_resolver())
         .extend(popup::popup_theme_resolver())
         .extend(progressbar::progressbar_theme_resolver())
         .extend(rect::rect_theme_resolver())
+        .extend(toast::toast_theme_resolver())
         .extend(usecase::usedcase_ui_theme_resolver());
 
     resolver
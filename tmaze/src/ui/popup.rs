@@ -5,34 +5,67 @@ use cmaze::dims::Dims;
 
 use super::{draw_fn::*, *};
 use crate::{
-    app::{app::AppData, ActivityHandler, Change, Event},
+    app::{app::AppData, Activity, ActivityHandler, Change, Event},
+    error::AppError,
     helpers::is_release,
 };
 
+/// Width of the text of the error popup, longer messages are wrapped
+const ERROR_WIDTH: usize = 40;
+/// Narrowest the texts get wrapped to on small screens
+const MIN_TEXT_WIDTH: usize = 10;
+
 pub struct Popup {
     title: String,
     texts: Vec<String>,
+    /// Texts wrapped to fit the screen, see [`Popup::reflow`]
+    lines: Vec<String>,
+    /// Title and texts were said, in the accessibility mode
+    narrated: bool,
 }
 
 impl Popup {
     pub fn new(title: String, texts: impl Into<Vec<String>>) -> Self {
+        let texts = texts.into();
         Self {
             title,
-            texts: texts.into(),
+            lines: texts.clone(),
+            texts,
+            narrated: false,
         }
     }
+
+    /// Wraps the texts wider than the screen, the others are kept as they are
+    fn reflow(&mut self, screen_size: Dims) {
+        // border and padding on both sides
+        let width = (screen_size.0 - 4).max(MIN_TEXT_WIDTH as i32) as usize;
+
+        self.lines = self
+            .texts
+            .iter()
+            .flat_map(|text| match text.width() > width {
+                true => wrap_words(text, width),
+                false => vec![text.clone()],
+            })
+            .collect();
+    }
 }
 
 impl ActivityHandler for Popup {
-    fn update(&mut self, events: Vec<Event>, _: &mut AppData) -> Option<Change> {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        if !self.narrated {
+            let texts = self.texts.iter().filter(|text| !text.is_empty());
+            let text = [&self.title].into_iter().chain(texts).cloned();
+            data.narrate(text.collect::<Vec<_>>().join(". "));
+            self.narrated = true;
+        }
+
         for event in events {
             #[allow(clippy::single_match)] // for more events to come
             match event {
                 Event::Term(event) => match event {
-                    TermEvent::Key(KeyEvent { code, kind, .. }) => {
-                        if !is_release(kind) {
-                            return Some(Change::pop_top_with(code));
-                        }
+                    TermEvent::Key(KeyEvent { code, kind, .. }) if !is_release(kind) => {
+                        return Some(Change::pop_top_with(code));
                     }
                     TermEvent::Mouse(MouseEvent {
                         kind: MouseEventKind::Up(MouseButton::Left),
@@ -52,13 +85,21 @@ impl ActivityHandler for Popup {
     fn screen(&self) -> &dyn Screen {
         self
     }
+
+    fn relayout(&mut self, size: Dims) {
+        self.reflow(size);
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        vec![Hint::new("Any key", "close")]
+    }
 }
 
 impl Screen for Popup {
     fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
-        let box_size = popup_size(&self.title, &self.texts);
-        let title_pos = center_box_in_screen(Dims(self.title.width() as i32, 1)).0;
-        let pos = center_box_in_screen(box_size);
+        let box_size = popup_size(&self.title, &self.lines);
+        let title_pos = center_box_in_screen(Dims(self.title.width() as i32, 1), frame.size).0;
+        let pos = center_box_in_screen(box_size, frame.size);
 
         let box_style = theme["ui.popup.border"];
         let text_style = theme["ui.popup.text"];
@@ -67,14 +108,14 @@ impl Screen for Popup {
         draw_box(frame, pos, box_size, box_style);
         frame.draw(Dims(title_pos, pos.1 + 1), self.title.as_str(), title_style);
 
-        if !self.texts.is_empty() {
+        if !self.lines.is_empty() {
             frame.draw(
                 pos + Dims(1, 2),
                 "─".repeat(box_size.0 as usize - 2),
                 box_style,
             );
 
-            for (i, text) in self.texts.iter().enumerate() {
+            for (i, text) in self.lines.iter().enumerate() {
                 frame.draw(pos + Dims(2, i as i32 + 3), text.as_str(), text_style);
             }
         }
@@ -83,13 +124,50 @@ impl Screen for Popup {
     }
 }
 
+/// Popup with the message of an error and a hint how to recover from it
+pub struct ErrorPopup(Popup);
+
+impl ErrorPopup {
+    pub fn new(err: &AppError) -> Self {
+        let mut texts = wrap_words(&err.to_string(), ERROR_WIDTH);
+        texts.push(String::new());
+        texts.extend(wrap_words(err.hint(), ERROR_WIDTH));
+
+        Self(Popup::new(err.title().to_string(), texts))
+    }
+
+    /// Logs the error and returns the popup showing it
+    pub fn new_activity(err: &AppError) -> Activity {
+        log::error!("{}", err);
+        Activity::new_base_boxed("error", Self::new(err))
+    }
+}
+
+impl ActivityHandler for ErrorPopup {
+    fn update(&mut self, events: Vec<Event>, data: &mut AppData) -> Option<Change> {
+        self.0.update(events, data)
+    }
+
+    fn screen(&self) -> &dyn Screen {
+        &self.0
+    }
+
+    fn relayout(&mut self, size: Dims) {
+        self.0.relayout(size);
+    }
+
+    fn hints(&self) -> Vec<Hint> {
+        self.0.hints()
+    }
+}
+
 pub fn popup_size(title: &str, texts: &[String]) -> Dims {
-    match texts.iter().map(|text| text.len()).max() {
+    match texts.iter().map(|text| text.width()).max() {
         Some(l) => Dims(
-            2 + 2 + l.max(title.len()) as i32,
+            2 + 2 + l.max(title.width()) as i32,
             2 + 2 + texts.len() as i32,
         ),
-        None => Dims(4 + title.len() as i32, 3),
+        None => Dims(4 + title.width() as i32, 3),
     }
 }
 
@@ -98,8 +176,37 @@ pub fn popup_theme_resolver() -> ThemeResolver {
 
     resolver
         .link("ui.popup.border", "border")
+        .desc("Border of popups")
         .link("ui.popup.text", "text")
-        .link("ui.popup.title", "text");
+        .desc("Text of popups")
+        .link("ui.popup.title", "text")
+        .desc("Title of popups");
 
     resolver
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::app::app::App;
+
+    use super::*;
+
+    #[test]
+    fn popup_reflows_on_resize() {
+        let text = "words of a text which is too long for a narrow screen";
+        let popup = Popup::new("Reflow".to_string(), vec![text.to_string()]);
+        let mut app =
+            App::new_headless_default(Activity::new_base_boxed("popup", popup), Dims(80, 12));
+
+        app.run_headless([], 1);
+        assert!(app.last_frame().to_string().contains(text));
+
+        let resize = Event::Term(TermEvent::Resize(24, 16));
+        app.run_headless([resize], 1);
+        let frame = app.last_frame().to_string();
+        assert!(!frame.contains(text));
+        for word in text.split_whitespace() {
+            assert!(frame.contains(word), "'{}' is cut off:\n{}", word, frame);
+        }
+    }
+}
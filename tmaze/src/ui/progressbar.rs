@@ -24,12 +24,31 @@ impl ProgressBar {
     pub fn update_title(&mut self, title: String) {
         self.title = title;
     }
+
+    /// Draws the bar on a single line, `width` cells wide, with the title before it
+    pub fn draw_line(&self, frame: &mut Frame, pos: Dims, width: i32, theme: &Theme) {
+        let title_width = match self.title.is_empty() {
+            true => 0,
+            false => self.title.width() as i32 + 1,
+        };
+        let bar_width = (width - title_width).max(0) as usize;
+        let filled = (bar_width as f64 * self.progress.clamp(0., 1.)).round() as usize;
+
+        frame.draw(pos, self.title.as_str(), theme["ui.progressbar.text"]);
+        let pos = pos + Dims(title_width, 0);
+        frame.draw(pos, "█".repeat(filled), theme["ui.progressbar.progress"]);
+        frame.draw(
+            pos + Dims(filled as i32, 0),
+            "·".repeat(bar_width - filled),
+            theme["ui.progressbar.border"],
+        );
+    }
 }
 
 impl Screen for ProgressBar {
     fn draw(&self, frame: &mut Frame, theme: &Theme) -> io::Result<()> {
         let progress_size = Dims(self.title.width() as i32 + 2 + 2, 4);
-        let pos = center_box_in_screen(progress_size);
+        let pos = center_box_in_screen(progress_size, frame.size);
 
         let prg = "█".repeat((self.title.width() as f64 * self.progress) as usize);
 
@@ -50,8 +69,11 @@ pub fn progressbar_theme_resolver() -> ThemeResolver {
 
     resolver
         .link("ui.progressbar.border", "border")
+        .desc("Border of progress bars")
         .link("ui.progressbar.text", "text")
-        .link("ui.progressbar.progress", "border");
+        .desc("Title of progress bars")
+        .link("ui.progressbar.progress", "border")
+        .desc("Filled part of progress bars");
 
     resolver
 }
@@ -0,0 +1,119 @@
+//! Short notifications in the top right corner, drawn over any activity
+//!
+//! Toasts are owned by [`crate::app::app::AppData`], so they stay on the screen even when
+//! the activity which created them is replaced.
+
+use std::{collections::VecDeque, time::Duration};
+
+use cmaze::dims::Dims;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    renderer::{Cell, Frame},
+    settings::theme::{Theme, ThemeResolver},
+};
+
+use super::draw_box;
+
+/// How long a toast is shown
+const TOAST_TIME: Duration = Duration::from_secs(4);
+
+/// Toasts shown at once, the older ones wait in the queue
+const MAX_SHOWN: usize = 3;
+
+#[derive(Debug, Clone)]
+struct Toast {
+    title: String,
+    text: String,
+    left: Duration,
+}
+
+impl Toast {
+    fn size(&self) -> Dims {
+        let width = self.title.width().max(self.text.width()) as i32;
+        Dims(width + 4, 4)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, title: impl Into<String>, text: impl Into<String>) {
+        self.queue.push_back(Toast {
+            title: title.into(),
+            text: text.into(),
+            left: TOAST_TIME,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Advances the time of the shown toasts, removes the expired ones
+    pub fn tick(&mut self, delta: Duration) {
+        for toast in self.queue.iter_mut().take(MAX_SHOWN) {
+            toast.left = toast.left.saturating_sub(delta);
+        }
+        self.queue.retain(|toast| !toast.left.is_zero());
+    }
+
+    pub fn draw(&self, frame: &mut Frame, theme: &Theme) {
+        let [border, title, text] =
+            theme.extract(["ui.toast.border", "ui.toast.title", "ui.toast.text"]);
+
+        let mut y = 1;
+        for toast in self.queue.iter().take(MAX_SHOWN) {
+            let size = toast.size();
+            let pos = Dims(frame.size.0 - size.0 - 1, y);
+
+            frame.fill_rect(pos, size, Cell::styled(' ', text));
+            draw_box(frame, pos, size, border);
+            frame.draw(pos + Dims(2, 1), toast.title.as_str(), title);
+            frame.draw(pos + Dims(2, 2), toast.text.as_str(), text);
+
+            y += size.1;
+        }
+    }
+}
+
+pub fn toast_theme_resolver() -> ThemeResolver {
+    let mut resolver = ThemeResolver::new();
+
+    resolver
+        .link("ui.toast.border", "border")
+        .desc("Border of notifications")
+        .link("ui.toast.title", "highlight")
+        .desc("Title of notifications")
+        .link("ui.toast.text", "text")
+        .desc("Text of notifications");
+
+    resolver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toasts_expire_in_order() {
+        let mut toasts = Toasts::default();
+        for i in 0..MAX_SHOWN + 1 {
+            toasts.push("Title", format!("toast {}", i));
+        }
+
+        toasts.tick(TOAST_TIME - Duration::from_millis(1));
+        assert_eq!(toasts.queue.len(), MAX_SHOWN + 1);
+
+        // the waiting one starts its time only when shown
+        toasts.tick(Duration::from_millis(1));
+        assert_eq!(toasts.queue.len(), 1);
+        assert_eq!(toasts.queue[0].left, TOAST_TIME);
+
+        toasts.tick(TOAST_TIME);
+        assert!(toasts.is_empty());
+    }
+}
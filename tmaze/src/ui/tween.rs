@@ -0,0 +1,158 @@
+//! Animation of positions on the screen, with easing curves
+//!
+//! A [`Tween`] keeps its position as floats, so it moves through the characters between its
+//! start and target, even when the target is many cells away after a fast move. It's advanced
+//! by ticks and can be sampled in between them, so the animation doesn't depend on the frame
+//! rate.
+
+use std::time::Duration;
+
+use cmaze::dims::Dims3D;
+use serde::{Deserialize, Serialize};
+
+use crate::lerp;
+
+/// Characters of the screen between two neighboring cells of the maze
+const CELL_SIZE: f32 = 2.0;
+
+/// Speed of a [`Tween`] over the time of its animation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Starts slowly and speeds up
+    EaseIn,
+    /// Starts fast and slows down before the target
+    #[default]
+    EaseOut,
+    /// Speeds up and slows down again
+    EaseInOut,
+}
+
+impl Easing {
+    /// Progress of the animation at time `t`, both are between 0.0 and 1.0
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Position on the screen animated towards its target
+#[derive(Debug, Clone, Copy)]
+pub struct Tween {
+    from: (f32, f32),
+    to: Dims3D,
+    elapsed: Duration,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Tween {
+    /// Tween which is already at `pos`
+    pub fn new(pos: Dims3D) -> Self {
+        Self {
+            from: (pos.0 as f32, pos.1 as f32),
+            to: pos,
+            elapsed: Duration::ZERO,
+            duration: Duration::ZERO,
+            easing: Easing::default(),
+        }
+    }
+
+    /// Starts moving to `to` from the current position, if it's a new target
+    ///
+    /// Moving by one cell takes `per_cell`, longer moves take longer but their speed grows with
+    /// the square root of the distance, so fast moves don't drag. Position jumps to another
+    /// floor at once.
+    pub fn retarget(&mut self, to: Dims3D, per_cell: Duration, easing: Easing) {
+        if to == self.to {
+            return;
+        }
+
+        let from = match to.2 == self.to.2 {
+            true => self.value(Duration::ZERO),
+            false => (to.0 as f32, to.1 as f32),
+        };
+        let cells = (to.0 as f32 - from.0).hypot(to.1 as f32 - from.1) / CELL_SIZE;
+
+        *self = Self {
+            from,
+            to,
+            elapsed: Duration::ZERO,
+            duration: per_cell.mul_f32(cells.sqrt()),
+            easing,
+        };
+    }
+
+    pub fn advance(&mut self, dt: Duration) {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Exact position `ahead` of the last advance
+    pub fn value(&self, ahead: Duration) -> (f32, f32) {
+        let t = match self.duration.is_zero() {
+            true => 1.0,
+            false => (self.elapsed + ahead).as_secs_f32() / self.duration.as_secs_f32(),
+        };
+        let progress = self.easing.apply(t);
+
+        (
+            lerp!((self.from.0) -> (self.to.0 as f32) at progress),
+            lerp!((self.from.1) -> (self.to.1 as f32) at progress),
+        )
+    }
+
+    /// Position `ahead` of the last advance, rounded to characters, floor is always the target one
+    pub fn pos(&self, ahead: Duration) -> Dims3D {
+        let (x, y) = self.value(ahead);
+        Dims3D(x.round() as i32, y.round() as i32, self.to.2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_moves_pass_through_cells() {
+        let per_cell = Duration::from_millis(100);
+        let mut tween = Tween::new(Dims3D(1, 1, 0));
+        tween.retarget(Dims3D(9, 1, 0), per_cell, Easing::Linear);
+
+        // 4 cells take twice as long as one
+        tween.advance(Duration::from_millis(100));
+        assert_eq!(tween.pos(Duration::ZERO), Dims3D(5, 1, 0));
+        assert_eq!(tween.pos(Duration::from_millis(50)), Dims3D(7, 1, 0));
+        assert!(!tween.is_done());
+
+        tween.advance(Duration::from_secs(1));
+        assert!(tween.is_done());
+        assert_eq!(tween.pos(Duration::ZERO), Dims3D(9, 1, 0));
+
+        tween.retarget(Dims3D(3, 3, 1), per_cell, Easing::EaseOut);
+        assert_eq!(tween.pos(Duration::ZERO), Dims3D(3, 3, 1));
+    }
+
+    #[test]
+    fn easings_start_and_end_at_target() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert!(easing.apply(0.5) > 0.0 && easing.apply(0.5) < 1.0);
+        }
+    }
+}
@@ -2,17 +2,34 @@ use cmaze::{
     dims::*,
     gameboard::{CellWall, Maze},
 };
-use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use crossterm::event::MouseEvent;
 
 use crate::{
     app::app::AppData,
     helpers::line_center,
     make_even, make_odd,
     renderer::Frame,
-    settings::theme::{Theme, ThemeResolver},
-    ui::{Button, ButtonStyles, Rect},
+    settings::{
+        keymap::Action,
+        theme::{Theme, ThemeResolver},
+    },
+    ui::{Button, ButtonStyles, Hit, HitMap, Rect},
 };
 
+/// Actions of the buttons by their place: top, left, right, bottom and the top right and top
+/// left corners, same as the order of [`CellWall::get_in_order`]
+pub const DEFAULT_ACTIONS: [Action; 6] = [
+    Action::MoveUp,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::MoveDown,
+    Action::FloorUp,
+    Action::FloorDown,
+];
+
+/// Actions of the corners when their floor moves can't be used, in mazes with one floor
+const FLAT_CORNER_ACTIONS: [Action; 2] = [Action::Pause, Action::Undo];
+
 pub enum DPadType {
     _2D,
     _3D,
@@ -35,42 +52,54 @@ impl DPadType {
         matches!(self, Self::_3D)
     }
 
-    pub fn button_count(&self) -> usize {
-        match self {
-            Self::_2D => 4,
-            Self::_3D => 6,
+    /// Action of the button at the place, `None` if the button is hidden
+    ///
+    /// Floor moves can't be used with one floor, the corners get [`FLAT_CORNER_ACTIONS`]
+    /// instead and the other places are hidden.
+    pub fn slot_action(&self, slot: usize, action: Action) -> Option<Action> {
+        let floor_move = matches!(action, Action::FloorUp | Action::FloorDown);
+        match (self, slot) {
+            (Self::_2D, 4 | 5) if floor_move => Some(FLAT_CORNER_ACTIONS[slot - 4]),
+            (Self::_2D, _) if floor_move => None,
+            _ => Some(action),
         }
     }
 }
 
+struct DPadButton {
+    /// Place of the button, index of [`DEFAULT_ACTIONS`]
+    slot: usize,
+    action: Action,
+    button: Button,
+}
+
 pub struct DPad {
-    buttons: smallvec::SmallVec<[Button; 6]>,
+    buttons: smallvec::SmallVec<[DPadButton; 6]>,
     abs_pos: Dims,
+    /// Buttons by their index
+    hits: HitMap<usize>,
+    /// Actions from the settings, the buttons can differ, see [`DPadType::slot_action`]
+    actions: [Action; 6],
     pub swap_up_down: bool,
 }
 
 impl DPad {
-    pub fn new(expected_space: Option<Rect>, swap_up_down: bool, type_: DPadType) -> Self {
+    pub fn new(
+        expected_space: Option<Rect>,
+        swap_up_down: bool,
+        type_: DPadType,
+        actions: [Action; 6],
+    ) -> Self {
         let rect = expected_space.unwrap_or_else(|| Rect::sized(Dims(11, 3)));
         let space = rect.size();
 
-        let buttons = CellWall::get_in_order()
+        let buttons = actions
             .into_iter()
             .enumerate()
-            .take(type_.button_count())
-            .map(|(i, wall)| {
-                let pos = Self::calc_button_pos(space, i, swap_up_down);
-                let size = Self::calc_button_size(space, i);
-
-                use CellWall::*;
-                let chr = match wall {
-                    Top => "↑",
-                    Left => "←",
-                    Right => "→",
-                    Bottom => "↓",
-                    Up => "Up",
-                    Down => "Down",
-                };
+            .filter_map(|(slot, action)| Some((slot, type_.slot_action(slot, action)?)))
+            .map(|(slot, action)| {
+                let pos = Self::calc_button_pos(space, slot, swap_up_down);
+                let size = Self::calc_button_size(space, slot);
 
                 let styles = ButtonStyles {
                     border: "ui.dpad.border",
@@ -80,14 +109,34 @@ impl DPad {
                     disabled_text: "ui.dpad.disabled.text",
                 };
 
-                Button::new(chr.to_string(), pos, size).with_styles(styles)
+                let button = Button::new(action.symbol().to_string(), pos, size);
+                DPadButton {
+                    slot,
+                    action,
+                    button: button.with_styles(styles),
+                }
             })
             .collect();
 
-        Self {
+        let mut dpad = Self {
             buttons,
             abs_pos: rect.start,
+            hits: HitMap::new(),
+            actions,
             swap_up_down,
+        };
+        dpad.register_hits();
+        dpad
+    }
+
+    fn register_hits(&mut self) {
+        self.hits.clear();
+        for (i, DPadButton { button, .. }) in self.buttons.iter().enumerate() {
+            let rect = button.rect();
+            self.hits.register(
+                Rect::new(rect.start + self.abs_pos, rect.end + self.abs_pos),
+                i,
+            );
         }
     }
 
@@ -103,31 +152,39 @@ impl DPad {
     ///
     /// Returns the rects for the viewport and dpad, respectively.
     pub fn split_screen(data: &AppData) -> (Rect, Rect) {
-        let screen_size = data.screen_size;
-        let screen_ratio = (screen_size.0 as f32 / 2.0) / screen_size.1 as f32;
+        Self::split_rect(
+            data.screen_size,
+            data.settings.get_dpad_size(),
+            data.settings.get_landscape_dpad_on_left(),
+        )
+    }
+
+    /// Dpad goes below the viewport in portrait and to its side in landscape
+    ///
+    /// `size` is relative to the side the dpad takes, but the dpad is never wider
+    /// than it is tall (a cell is twice as tall as wide), so in wide terminals
+    /// the rest is left to the viewport.
+    fn split_rect(screen_size: Dims, size: f32, on_left: bool) -> (Rect, Rect) {
         let screen_rect = Rect::sized(screen_size);
+        let Dims(width, height) = screen_size;
+        let is_vertical = width / 2 < height;
 
-        let (side, is_vertical) = match screen_ratio {
-            1.0.. => (screen_size.0, false),
-            ..=1.0 => (screen_size.1, true),
-            _ => unreachable!(),
+        let (side, other) = if is_vertical {
+            (height, width / 2)
+        } else {
+            (width, height * 2)
         };
 
-        // TODO: load dpad ratio from settings
-        let dpad_size = Offset::Rel(2. / 5.).to_abs(side).max(10);
+        let dpad_size = Offset::Rel(size).to_abs(side).min(other).max(10).min(side);
+        let offset = Offset::Abs(dpad_size);
 
         if is_vertical {
-            screen_rect.split_y_end(Offset::Abs(dpad_size))
+            screen_rect.split_y_end(offset)
+        } else if on_left {
+            let (dpad, vp) = screen_rect.split_x(offset);
+            (vp, dpad)
         } else {
-            let on_right = !data.settings.get_landscape_dpad_on_left();
-            let offset = Offset::Abs(dpad_size);
-
-            if !on_right {
-                let (dpad, vp) = screen_rect.split_x(offset);
-                (vp, dpad)
-            } else {
-                screen_rect.split_x_end(offset)
-            }
+            screen_rect.split_x_end(offset)
         }
     }
 
@@ -136,48 +193,41 @@ impl DPad {
         let space = Dims(make_odd!(x), make_odd!(y));
         self.abs_pos = rect.start;
 
-        for (i, button) in self.buttons.iter_mut().enumerate() {
-            button.pos = Self::calc_button_pos(space, i, self.swap_up_down);
-            button.size = Self::calc_button_size(space, i);
+        for DPadButton { slot, button, .. } in self.buttons.iter_mut() {
+            button.pos = Self::calc_button_pos(space, *slot, self.swap_up_down);
+            button.size = Self::calc_button_size(space, *slot);
         }
+        self.register_hits();
+    }
+
+    /// Actions the dpad was made with, it has to be made again when they change
+    pub fn actions(&self) -> [Action; 6] {
+        self.actions
     }
 
+    /// Disables the buttons of the moves which are blocked, other actions are always enabled
     pub fn update_available_moves(&mut self, available_moves: [bool; 6]) {
-        for (button, &is_available) in self.buttons.iter_mut().zip(available_moves.iter()) {
-            button.disabled = !is_available;
+        let walls = CellWall::get_in_order();
+        for DPadButton { action, button, .. } in self.buttons.iter_mut() {
+            if let Some(dir) = action.direction() {
+                let index = walls.iter().position(|wall| *wall == dir).unwrap();
+                button.disabled = !available_moves[index];
+            }
         }
     }
 
-    pub fn apply_mouse_event(&mut self, event: MouseEvent) -> Option<CellWall> {
-        let mut touch_pos = (event.column, event.row).into();
-        touch_pos -= self.abs_pos;
-
-        let pressed = match event.kind {
-            MouseEventKind::Up(MouseButton::Left) => true,
-            MouseEventKind::Moved => false,
-            _ => {
-                return None;
-            }
-        };
+    /// Action of the clicked button, if it's enabled
+    pub fn apply_mouse_event(&mut self, event: MouseEvent) -> Option<Action> {
+        let hit = self.hits.dispatch(&event);
 
-        self.for_mut_buttons(|button| button.set = false);
-
-        for (button, dir) in self
-            .buttons
-            .iter_mut()
-            .zip(CellWall::get_in_order().into_iter())
-        {
-            if button.detect_over(touch_pos) {
-                button.set = true;
-                if pressed && !button.disabled {
-                    return Some(dir);
-                } else {
-                    return None;
-                }
-            }
+        for (i, DPadButton { button, .. }) in self.buttons.iter_mut().enumerate() {
+            button.set = self.hits.is_hovered(i);
         }
 
-        None
+        match hit {
+            Some(Hit::Click(i)) if !self.buttons[i].button.disabled => Some(self.buttons[i].action),
+            _ => None,
+        }
     }
 
     #[inline]
@@ -221,14 +271,14 @@ impl DPad {
 
     #[inline]
     fn for_buttons(&self, mut f: impl FnMut(&Button)) {
-        for button in self.buttons.iter() {
+        for DPadButton { button, .. } in self.buttons.iter() {
             f(button);
         }
     }
 
     #[inline]
     fn for_mut_buttons(&mut self, mut f: impl FnMut(&mut Button)) {
-        for button in self.buttons.iter_mut() {
+        for DPadButton { button, .. } in self.buttons.iter_mut() {
             f(button);
         }
     }
@@ -238,10 +288,63 @@ pub fn dpad_theme_resolver() -> ThemeResolver {
     let mut resolver = ThemeResolver::new();
     resolver
         .link("ui.dpad.border", "ui.button.border")
+        .desc("Borders of the touch controls")
         .link("ui.dpad.highlight", "ui.button.highlight")
+        .desc("Touch control button which is pressed or hovered")
         .link("ui.dpad.text", "ui.button.text")
+        .desc("Labels of the touch controls")
         .link("ui.dpad.disabled.border", "ui.button.disabled.border")
-        .link("ui.dpad.disabled.text", "ui.button.disabled.text");
+        .desc("Borders of touch controls which can't be used now")
+        .link("ui.dpad.disabled.text", "ui.button.disabled.text")
+        .desc("Labels of touch controls which can't be used now");
 
     resolver
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floor_moves_are_replaced_in_flat_mazes() {
+        let flat = DPadType::_2D;
+        assert_eq!(flat.slot_action(4, Action::FloorUp), Some(Action::Pause));
+        assert_eq!(flat.slot_action(5, Action::FloorDown), Some(Action::Undo));
+        assert_eq!(flat.slot_action(0, Action::FloorUp), None);
+        assert_eq!(flat.slot_action(4, Action::Hint), Some(Action::Hint));
+        assert_eq!(
+            DPadType::_3D.slot_action(4, Action::FloorUp),
+            Some(Action::FloorUp)
+        );
+    }
+
+    #[test]
+    fn dpad_follows_orientation() {
+        // portrait, dpad at the bottom
+        let (vp, dpad) = DPad::split_rect(Dims(80, 100), 0.4, false);
+        assert_eq!(dpad, Rect::new(Dims(0, 60), Dims(79, 99)));
+        assert_eq!(vp.size(), Dims(80, 60));
+
+        // landscape, dpad on the side picked by the player
+        let (vp, dpad) = DPad::split_rect(Dims(200, 60), 0.4, false);
+        assert_eq!(dpad.start, Dims(120, 0));
+        assert_eq!(vp.size(), Dims(120, 60));
+        let (vp, dpad) = DPad::split_rect(Dims(200, 60), 0.4, true);
+        assert_eq!(dpad.start, Dims(0, 0));
+        assert_eq!(vp.start, Dims(80, 0));
+    }
+
+    #[test]
+    fn dpad_is_not_wider_than_tall() {
+        let (vp, dpad) = DPad::split_rect(Dims(300, 30), 0.5, false);
+        assert_eq!(dpad.size(), Dims(60, 30));
+        assert_eq!(vp.size(), Dims(240, 30));
+
+        let (_, dpad) = DPad::split_rect(Dims(40, 200), 0.5, false);
+        assert_eq!(dpad.size(), Dims(40, 20));
+
+        // tiny screens still get a usable dpad, but never more than the screen
+        let (_, dpad) = DPad::split_rect(Dims(8, 6), 0.4, false);
+        assert_eq!(dpad.size(), Dims(8, 6));
+    }
+}